@@ -14,29 +14,44 @@ limitations under the License.
 //! Add a package to your dependencies for your project.
 
 use std::collections::HashMap;
-use std::io::Write;
+use std::io::{IsTerminal, Write};
 use std::sync::Arc;
 use std::{process::exit, sync::atomic::AtomicI16};
 
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Context, Result};
 use async_trait::async_trait;
 use colored::Colorize;
-use futures::{stream::FuturesUnordered, StreamExt};
-use indicatif::{ProgressBar, ProgressStyle};
 use tokio::sync::{mpsc, Mutex};
+use volt_check_engines::engines::{self, InstalledPackage, ENGINES};
 use volt_core::{
     command::Command,
+    help::Help,
     model::lock_file::{DependencyID, DependencyLock, LockFile},
-    VERSION,
+    prompt::prompts::{Confirm, Select},
 };
 use volt_utils::app::App;
 use volt_utils::{
-    self,
-    package::{Package, PackageJson, Version},
-    PROGRESS_CHARS,
+    self, bins,
+    git::GitSpec,
+    global,
+    journal::{self, Journal},
+    legacy_store, network,
+    package::{self, Package, PackageJson, Version},
+    peers::{self, PeerFix, PeerProblem},
+    pipeline,
+    platform::{self, Platform},
+    quota,
+    reporter::{self, Progress},
+    scrub,
+    volt_api::{self, VoltPackage, VoltResponse},
+    warnings::{Kind, Warnings},
+    workspace,
 };
 // use crate::commands::init;
 
+/// Passed by `volt install`, which installs the versions of the lock file when it has them.
+pub const FROM_LOCK_FILE_FLAG: &str = "--from-lock-file";
+
 /// Struct implementation for the `Add` command.
 #[derive(Clone)]
 pub struct Add {
@@ -50,31 +65,64 @@ pub struct Add {
 #[async_trait]
 impl Command for Add {
     /// Display a help menu for the `volt add` command.
-    fn help() -> String {
-        format!(
-            r#"volt {}
-    
-Add a package to your dependencies for your project.
-Usage: {} {} {} {}
-Options: 
-    
-  {} {} Output the version number.
-  {} {} Output verbose messages on internal operations.
-  {} {} Adds package as a dev dependency
-  {} {} Disable progress bar."#,
-            VERSION.bright_green().bold(),
-            "volt".bright_green().bold(),
-            "add".bright_purple(),
-            "[packages]".white(),
-            "[flags]".white(),
-            "--version".blue(),
-            "(-ver)".yellow(),
-            "--verbose".blue(),
-            "(-v)".yellow(),
-            "--dev".blue(),
-            "(-D)".yellow(),
-            "--no-progress".blue(),
-            "(-np)".yellow()
+    fn help() -> Help {
+        Help::new(
+            "add",
+            "Add a package to your dependencies for your project.",
+        )
+        .usage("[packages] [flags]")
+        .arg(
+            "[packages]",
+            "Packages to add, with an optional version (react@17).",
+        )
+        .flag(&["--version", "-ver"], "Output the version number.")
+        .verbose()
+        .flag(&["--dev", "-D"], "Adds package as a dev dependency.")
+        .flag(
+            &["--global", "-g"],
+            "Install for the user, with its binaries in PATH.",
+        )
+        .flag(
+            &["--strict-peer-deps"],
+            "Fail on unmet or conflicting peer dependencies.",
+        )
+        .flag(
+            &["--os", "--cpu", "--libc"],
+            "Install for another os, cpu or libc (--os=linux --cpu=x64).",
+        )
+        .flag(
+            &["--offline"],
+            "Install from the offline mirror without using the network.",
+        )
+        .flag(
+            &["--force"],
+            "Install even if the os, cpu or libc of package.json exclude this platform.",
+        )
+        .flag(&["--json"], "Print the warnings of the install as JSON.")
+        .flag(
+            &["--trust-all"],
+            "Run the install scripts of every new dependency which has them.",
+        )
+        .flag(
+            &["--trust-none"],
+            "Skip the install scripts of every new dependency which has them.",
+        )
+        .flag(
+            &["--workspace-root", "-w"],
+            "Add to the root of a workspace instead of a member.",
+        )
+        .no_progress()
+        .example(
+            "volt add react react-dom",
+            "Add react and react-dom to dependencies.",
+        )
+        .example(
+            "volt add -D typescript",
+            "Add typescript to devDependencies.",
+        )
+        .example(
+            "volt add foo@git+https://github.com/acme/tools.git#main&path:packages/foo",
+            "Add the package in packages/foo of a git repository.",
         )
     }
 
@@ -96,7 +144,7 @@ Options:
     async fn exec(app: Arc<App>) -> Result<()> {
         // Display help menu if `volt add` is run.
         if app.args.len() == 1 {
-            println!("{}", Self::help());
+            reporter::get().output(&Self::help().to_string());
             exit(1);
         }
 
@@ -109,9 +157,18 @@ Options:
             }
         }
 
+        let global = app.has_flag(&["-g", "--global"]);
+
+        // Global packages are the dependencies of a project in the global directory
+        let app = if global {
+            Arc::new(global::app(&app)?)
+        } else {
+            app
+        };
+
         // Check if package.json exists, otherwise, handle it.
-        if !std::env::current_dir()?.join("package.json").exists() {
-            println!("{} no package.json found.", "error".bright_red());
+        if !app.current_dir.join("package.json").exists() {
+            reporter::get().output(&format!("{} no package.json found.", "error".bright_red()));
             print!("Do you want to initialize package.json (Y/N): ");
             std::io::stdout().flush().expect("Could not flush stdout");
             let mut string: String = String::new();
@@ -123,346 +180,675 @@ Options:
             }
         }
 
-        // Load the existing package.json file
+        if !global {
+            check_workspace_root(&app)?;
+        }
+
+        // `react@catalog:` installs the range of the workspace catalog, and package.json keeps
+        // the reference, like it keeps the url of git dependencies
+        let mut references = HashMap::new();
+
+        let packages = packages
+            .into_iter()
+            .map(|spec| match volt_utils::parse_package_spec(&spec) {
+                (name, Some(range)) if range.starts_with(workspace::CATALOG_PREFIX) => {
+                    let resolved = workspace::resolve_range(&app.current_dir, &name, &range)?;
+                    let spec = format!("{}@{}", name, resolved);
 
-        let package_file = Arc::new(Mutex::new(PackageJson::from("package.json")));
+                    references.insert(name, range);
+                    Ok(spec)
+                }
+                (name, Some(range)) if GitSpec::is_git(&range) => {
+                    references.insert(name, range);
+                    Ok(spec)
+                }
+                // Installs keep the ranges of package.json
+                (name, Some(range)) if app.has_flag(&[FROM_LOCK_FILE_FLAG]) => {
+                    references.insert(name, range);
+                    Ok(spec)
+                }
+                _ => Ok(spec),
+            })
+            .collect::<Result<Vec<String>>>()?;
 
-        // Handles for multi-threaded operations
-        let mut handles = vec![];
+        // Shown once the install is done, or failed
+        let mut warnings = Warnings::new();
 
-        // Iterate through each package
-        for package in packages.clone() {
-            let app_instance = app.clone();
+        if !global {
+            check_platform(&app, &mut warnings)?;
+        }
 
-            let package_dir_loc;
+        // Entries of older stores are moved before anything looks for them
+        if let Err(error) = legacy_store::migrate(&app.volt_dir) {
+            warnings.add(
+                Kind::Other,
+                None,
+                format!("failed to migrate the store: {}", error),
+            );
+        }
 
-            if cfg!(target_os = "windows") {
-                // Check if %USERPROFILE%/.volt/packagename exists
-                package_dir_loc = format!(
-                    r"{}\.volt\{}",
-                    std::env::var("USERPROFILE").unwrap(),
-                    package
-                );
-            } else {
-                // Check if ~/.volt/packagename exists
-                package_dir_loc = format!(r"{}\.volt\{}", std::env::var("HOME").unwrap(), package);
+        if journal::is_pending(&app) {
+            reporter::get().info("Resuming an interrupted install", "");
+        }
+
+        let mut journal = Journal::begin(&app)?;
+
+        let result = tokio::select! {
+            result = install(
+                app.clone(),
+                packages,
+                &references,
+                &mut journal,
+                &mut warnings,
+            ) => result,
+            _ = tokio::signal::ctrl_c() => Err(anyhow!("install interrupted")),
+        };
+
+        let installed = match result {
+            Ok(installed) => installed,
+            Err(error) => {
+                // The error of the install is the one to report, a failed rollback comes on top
+                match journal.rollback() {
+                    Ok(()) => reporter::get().info("Rolled back the install", ""),
+                    Err(rollback) => reporter::get()
+                        .warn(&format!("failed to roll the install back: {:#}", rollback)),
+                }
+
+                print_summary(&app, &warnings);
+                return Err(error);
             }
+        };
 
-            let package_dir = std::path::Path::new(&package_dir_loc);
-            let package_file = package_file.clone();
-
-            if package_dir.exists() {
-                handles.push(tokio::spawn(async move {
-                    let verbose = app_instance.has_flag(&["-v", "--verbose"]);
-                    let pballowed = !app_instance.has_flag(&["--no-progress", "-np"]);
-
-                    let mut lock_file = LockFile::load(app_instance.lock_file_path.to_path_buf())
-                        .unwrap_or_else(|_| {
-                            LockFile::new(app_instance.lock_file_path.to_path_buf())
-                        });
-
-                    // TODO: Change this to handle multiple packages
-                    let progress_bar: ProgressBar = ProgressBar::new(1);
-
-                    progress_bar.set_style(
-                        ProgressStyle::default_bar()
-                            .progress_chars(PROGRESS_CHARS)
-                            .template(&format!(
-                                "{} [{{bar:40.magenta/blue}}] {{msg:.blue}}",
-                                "Fetching dependencies".bright_blue()
-                            )),
-                    );
-
-                    let response = volt_utils::get_volt_response(package.to_string()).await;
-
-                    let progress_bar = &progress_bar;
-
-                    progress_bar.finish_with_message("[OK]".bright_green().to_string());
-
-                    let length = &response
-                        .versions
-                        .get(&response.version)
-                        .unwrap()
-                        .packages
-                        .len();
-
-                    if *length == 1 {
-                        println!("Loaded 1 dependency");
-                    } else {
-                        println!("Loaded {} dependencies.", length);
-                    }
-
-                    let current_version = response.versions.get(&response.version).unwrap();
-
-                    let dependencies: Vec<_> = current_version
-                        .packages
-                        .iter()
-                        .map(|(_, object)| {
-                            let mut lock_dependencies: HashMap<String, String> = HashMap::new();
-
-                            for dep in object.clone().peer_dependencies {
-                                if !volt_utils::check_peer_dependency(&dep) {
-                                    progress_bar.println(format!(
-                                        "{}{} {} has unmet peer dependency {}",
-                                        " warn ".black().on_bright_yellow(),
-                                        ":",
-                                        object.clone().name.bright_cyan(),
-                                        &dep.bright_yellow()
-                                    ));
-                                }
-                            }
-
-                            if object.clone().dependencies.is_some() {
-                                for dep in object.clone().dependencies.unwrap().iter() {
-                                    // TODO: Change this to real version
-                                    lock_dependencies.insert(dep.clone(), String::new());
-                                }
-                            }
-
-                            lock_file.dependencies.insert(
-                                DependencyID(object.clone().name, object.clone().version),
-                                DependencyLock {
-                                    name: object.clone().name,
-                                    version: object.clone().version,
-                                    tarball: object.clone().tarball,
-                                    sha1: object.clone().sha1,
-                                    dependencies: lock_dependencies,
-                                },
-                            );
-
-                            object.clone()
-                        })
-                        .collect();
-
-                    progress_bar.finish_and_clear();
-                    let mut workers = FuturesUnordered::new();
-
-                    for dep in dependencies.clone() {
-                        let app_instance = app_instance.clone();
-                        workers.push(async move {
-                            volt_utils::install_extract_package(&app_instance, &dep)
-                                .await
-                                .unwrap();
-                        });
-                    }
-
-                    if pballowed {
-                        let progress_bar = ProgressBar::new(workers.len() as u64);
-
-                        progress_bar.set_style(
-                            ProgressStyle::default_bar()
-                                .progress_chars(PROGRESS_CHARS)
-                                .template(&format!(
-                                    "{} [{{bar:40.magenta/blue}}] {{msg:.blue}} {{pos}} / {{len}}",
-                                    "Installing packages".bright_blue()
-                                )),
-                        );
-
-                        while workers.next().await.is_some() {
-                            progress_bar.inc(1);
-                        }
-
-                        progress_bar.finish();
-                    } else {
-                        while workers.next().await.is_some() {}
-                    }
-
-                    volt_utils::create_dependency_links(
-                        app_instance.clone(),
-                        current_version.packages.clone(),
-                    )
-                    .await
-                    .unwrap();
-
-                    let mut package_json_file = package_file.lock().await;
-
-                    if app_instance.flags.contains(&"-D".to_string())
-                        || app_instance.flags.contains(&"--dev".to_string())
-                    {
-                        package_json_file
-                            .dev_dependencies
-                            .insert(package.to_string(), response.clone().version);
-                    } else {
-                        package_json_file
-                            .dependencies
-                            .insert(package.to_string(), response.clone().version);
-                    }
-
-                    // println!("pkg json file: {:?}", package_json_file);
-
-                    package_json_file.save();
-
-                    // Write to lock file
-                    if verbose {
-                        println!("info {}", "Writing to lock file".yellow());
-                    }
-
-                    lock_file
-                        .save()
-                        .context("Failed to save lock file")
-                        .unwrap();
-                }));
-            } else {
-                let verbose = app_instance.has_flag(&["-v", "--verbose"]);
-                let pballowed = !app_instance.has_flag(&["--no-progress", "-np"]);
-
-                let mut lock_file = LockFile::load(app_instance.lock_file_path.to_path_buf())
-                    .unwrap_or_else(|_| LockFile::new(app_instance.lock_file_path.to_path_buf()));
-
-                // TODO: Change this to handle multiple packages
-                let progress_bar: ProgressBar = ProgressBar::new(1);
-
-                progress_bar.set_style(
-                    ProgressStyle::default_bar()
-                        .progress_chars(PROGRESS_CHARS)
-                        .template(&format!(
-                            "{} [{{bar:40.magenta/blue}}] {{msg:.blue}}",
-                            "Fetching dependencies".bright_blue()
-                        )),
-                );
+        journal.commit()?;
 
-                let response = volt_utils::get_volt_response(package.to_string()).await;
-                let progress_bar = &progress_bar;
+        if global {
+            link_global_binaries(&app, &installed)?;
+        }
 
-                progress_bar.finish_with_message("[OK]".bright_green().to_string());
+        // Usage is only needed to stay under the quota, an install stands without it
+        if let Ok(lock_file) = LockFile::load(app.lock_file_path.clone()) {
+            let names = lock_file
+                .dependencies
+                .values()
+                .map(|dependency| {
+                    volt_api::store_name(&dependency.name, &dependency.version, &dependency.sha1)
+                })
+                .collect();
+
+            if let Err(error) = quota::after_install(&app, names) {
+                warnings.add(Kind::Other, None, error.to_string());
+            }
+        }
 
-                let length = &response
-                    .versions
-                    .get(&response.version)
-                    .unwrap()
-                    .packages
-                    .len();
+        scrub::spawn_after_install(&app);
 
-                // println!("{}", length);
-                if *length as u64 == 1 {
-                    println!("Loaded 1 dependency");
-                } else {
-                    println!("Loaded {} dependencies.", length);
-                }
+        print_summary(&app, &warnings);
 
-                let current_version = response.versions.get(&response.version).unwrap();
-
-                let dependencies: Vec<_> = current_version
-                    .packages
-                    .iter()
-                    .map(|(_, object)| {
-                        let mut lock_dependencies: HashMap<String, String> = HashMap::new();
-
-                        for dep in object.clone().peer_dependencies {
-                            if !volt_utils::check_peer_dependency(&dep) {
-                                progress_bar.println(format!(
-                                    "{}{} {} has unmet peer dependency {}",
-                                    " warn ".black().on_bright_yellow(),
-                                    ":",
-                                    object.clone().name.bright_cyan(),
-                                    &dep.bright_yellow()
-                                ));
-                            }
-                        }
-
-                        if object.clone().dependencies.is_some() {
-                            for dep in object.clone().dependencies.unwrap().iter() {
-                                // TODO: Change this to real version
-                                lock_dependencies.insert(dep.clone(), String::new());
-                            }
-                        }
-
-                        lock_file.dependencies.insert(
-                            DependencyID(object.clone().name, object.clone().version),
-                            DependencyLock {
-                                name: object.clone().name,
-                                version: object.clone().version,
-                                tarball: object.clone().tarball,
-                                sha1: object.clone().sha1,
-                                dependencies: lock_dependencies,
-                            },
-                        );
-
-                        object.clone()
-                    })
-                    .collect();
-
-                progress_bar.finish_and_clear();
-
-                let mut workers = FuturesUnordered::new();
-
-                for dep in dependencies.clone() {
-                    let app_instance = app_instance.clone();
-                    workers.push(async move {
-                        volt_utils::install_extract_package(&app_instance, &dep)
-                            .await
-                            .unwrap();
-                    });
-                }
+        Ok(())
+    }
+}
 
-                if pballowed {
-                    let progress_bar = ProgressBar::new(workers.len() as u64);
-
-                    progress_bar.set_style(
-                        ProgressStyle::default_bar()
-                            .progress_chars(PROGRESS_CHARS)
-                            .template(&format!(
-                                "{} [{{bar:40.magenta/blue}}] {{msg:.blue}} {{pos}} / {{len}}",
-                                "Installing packages".bright_blue()
-                            )),
-                    );
-
-                    while workers.next().await.is_some() {
-                        progress_bar.inc(1);
-                    }
-
-                    progress_bar.finish();
-                } else {
-                    while workers.next().await.is_some() {
-                        progress_bar.inc(1);
-                    }
-                }
+/// The resolutions of `packages` the lock file has, for installs. Their ranges in package.json
+/// are kept in `references`, the lock file doesn't change what the project asks for.
+///
+/// With `--frozen-lockfile`, every package must come from the lock file.
+fn locked_resolutions(
+    app: &App,
+    packages: &[String],
+    package_file: &PackageJson,
+    lock_file: &LockFile,
+    references: &mut HashMap<String, String>,
+) -> Result<HashMap<String, VoltResponse>> {
+    let frozen = app.has_flag(&["--frozen-lockfile"]);
+    let mut locked = HashMap::new();
+
+    if !frozen && !app.has_flag(&[FROM_LOCK_FILE_FLAG]) {
+        return Ok(locked);
+    }
 
-                volt_utils::create_dependency_links(
-                    app_instance.clone(),
-                    current_version.packages.clone(),
-                )
-                .await
-                .unwrap();
-
-                // Change package.json
-                // package_file.add_dependency(dep.name, dep.version);
-                let mut package_json_file = package_file.lock().await;
-
-                if app_instance.flags.contains(&"-D".to_string())
-                    || app_instance.flags.contains(&"--dev".to_string())
-                {
-                    package_json_file
-                        .dev_dependencies
-                        .insert(package.to_string(), response.clone().version);
-                } else {
-                    package_json_file
-                        .dependencies
-                        .insert(package.to_string(), response.clone().version);
-                }
+    for spec in packages.iter() {
+        let (name, range) = volt_utils::parse_package_spec(spec);
+
+        // Git dependencies are locked to their commit by their url already
+        if range.as_deref().is_some_and(GitSpec::is_git) {
+            continue;
+        }
+
+        let range = range.or_else(|| {
+            package_file
+                .dependencies
+                .get(&name)
+                .or_else(|| package_file.dev_dependencies.get(&name))
+                .or_else(|| package_file.optional_dependencies.get(&name))
+                .cloned()
+        });
+
+        match range.and_then(|range| Some((lock_file.resolution(&name, &range)?, range))) {
+            Some((response, range)) => {
+                references.entry(name).or_insert(range);
+                locked.insert(spec.clone(), response);
+            }
+            None if frozen => {
+                return Err(anyhow!(
+                    "{} is not in {}, run volt install without --frozen-lockfile",
+                    spec,
+                    app.lock_file_path.display()
+                ))
+            }
+            None => {}
+        }
+    }
 
-                // println!("pkg json file: {:?}", package_json_file);
+    Ok(locked)
+}
 
-                package_json_file.save();
+/// Resolve, fetch and link `packages`, recording the changes to the project in `journal`.
+///
+/// Packages in `references` are saved to package.json with their catalog reference or git url
+/// rather than their version. Returns the packages which were asked for, without their
+/// dependencies.
+async fn install(
+    app: Arc<App>,
+    packages: Vec<String>,
+    references: &HashMap<String, String>,
+    journal: &mut Journal,
+    warnings: &mut Warnings,
+) -> Result<Vec<VoltPackage>> {
+    // Load the existing package.json file
+    let mut package_file =
+        PackageJson::from(&app.current_dir.join("package.json").to_string_lossy());
 
-                // Write to lock file
-                if verbose {
-                    println!("info {}", "Writing to lock file".yellow());
-                }
+    let verbose = app.has_flag(&["-v", "--verbose"]);
+    let reporter = reporter::get();
+
+    let mut lock_file = LockFile::load(app.lock_file_path.to_path_buf())
+        .unwrap_or_else(|_| LockFile::new(app.lock_file_path.to_path_buf()));
+
+    let progress = Progress::start("Resolving and fetching packages", 0);
+
+    let mut references = references.clone();
+    let locked = locked_resolutions(&app, &packages, &package_file, &lock_file, &mut references)?;
 
-                lock_file
-                    .save()
-                    .context("Failed to save lock file")
-                    .unwrap();
+    // Downloads start as soon as each package is resolved
+    let mut pipeline =
+        pipeline::resolve_and_fetch_locked(app.clone(), packages, locked, &progress).await?;
+
+    progress.finish();
+
+    // Every requested package is installed as one tree, linked and locked once
+    let mut packages = pipeline.packages();
+
+    // Requested packages can be the peers of each other (`volt add react react-dom`)
+    loop {
+        let problems = peers::check(&packages, &package_file, &app.node_modules_dir);
+
+        if problems.is_empty() {
+            break;
+        }
+
+        if !peers::is_strict(&app) {
+            for problem in problems.iter() {
+                warnings.add(
+                    Kind::Peer,
+                    Some(problem.package.clone()),
+                    format!("has {}", problem.describe()),
+                );
             }
+
+            break;
+        }
+
+        let error = anyhow!(
+            "unmet peer dependencies\n{}",
+            peers::conflict_tree(&problems)
+        );
+
+        // Scripts and CI get the error, people can settle the conflicts instead
+        if !std::io::stdin().is_terminal() {
+            return Err(error);
+        }
+
+        let pins = match choose_peer_fixes(&problems, &mut package_file)? {
+            Some(pins) => pins,
+            None => return Err(error),
+        };
+
+        if pins.is_empty() {
+            continue;
+        }
+
+        let pinned = pipeline::resolve_and_fetch(app.clone(), pins, &Progress::hidden()).await?;
+
+        pipeline.resolutions.retain(|resolution| {
+            pinned
+                .resolutions
+                .iter()
+                .all(|pin| pin.name != resolution.name)
+        });
+        pipeline.resolutions.extend(pinned.resolutions);
+
+        packages = pipeline.packages();
+    }
+
+    if packages.len() == 1 {
+        reporter.info("Loaded", "1 dependency");
+    } else {
+        reporter.info("Loaded", &format!("{} dependencies", packages.len()));
+    }
+
+    let mut sorted: Vec<&VoltPackage> = packages.values().collect();
+    sorted.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let deprecated: Vec<(String, &String)> = sorted
+        .iter()
+        .filter_map(|package| {
+            let message = package.deprecated.as_ref()?;
+            Some((format!("{}@{}", package.name, package.version), message))
+        })
+        .collect();
+
+    // `strict-deprecations=true` in .voltrc fails the install instead of warning
+    if !deprecated.is_empty() && app.config.get_bool("strict-deprecations") == Some(true) {
+        let list: Vec<String> = deprecated
+            .iter()
+            .map(|(package, message)| format!("  {} {}", package, message))
+            .collect();
+
+        return Err(anyhow!(
+            "deprecated packages (strict-deprecations is set)\n{}",
+            list.join("\n")
+        ));
+    }
+
+    for (package, message) in deprecated {
+        warnings.add(Kind::Deprecated, Some(package), message.clone());
+    }
+
+    check_engines(&sorted, warnings);
+
+    let bin_plan = plan_bins(&app, &packages, &pipeline, &package_file, warnings)?;
+
+    if verbose {
+        let platform = Platform::from_app(&app);
+
+        for name in pipeline
+            .resolutions
+            .iter()
+            .flat_map(|resolution| resolution.skipped.iter())
+        {
+            reporter.info(
+                "Skipping optional dependency",
+                &format!(
+                    "{} (unsupported platform {}-{})",
+                    name.bright_cyan(),
+                    platform.os,
+                    platform.cpu
+                ),
+            );
+        }
+    }
+
+    for object in packages.values() {
+        lock_file.dependencies.insert(
+            DependencyID(object.name.clone(), object.version.clone()),
+            DependencyLock::from_package(object, &packages),
+        );
+    }
+
+    // Link everything once all downloads are done
+    for (bin, installed) in bin_plan.bins.iter() {
+        volt_utils::generate_script(&app, &packages[installed], bin);
+    }
+
+    volt_utils::create_dependency_links(app.clone(), packages.clone(), Some(&mut *journal)).await?;
+
+    crate::trust::run(&app, &packages)?;
+
+    for resolution in pipeline.resolutions.iter() {
+        let range = references
+            .get(&resolution.name)
+            .unwrap_or(&resolution.response.version)
+            .clone();
+
+        // Packages stay in the field they are in, unless `--dev` moves them
+        let field = if app.has_flag(&["-D", "--dev"]) {
+            &mut package_file.dev_dependencies
+        } else if package_file.dependencies.contains_key(&resolution.name) {
+            &mut package_file.dependencies
+        } else if package_file.dev_dependencies.contains_key(&resolution.name) {
+            &mut package_file.dev_dependencies
+        } else if package_file
+            .optional_dependencies
+            .contains_key(&resolution.name)
+        {
+            &mut package_file.optional_dependencies
+        } else {
+            &mut package_file.dependencies
+        };
+
+        field.insert(resolution.name.clone(), range);
+    }
+
+    package_file.save(&app.current_dir.join("package.json"));
+
+    lock_file.manifest_hash = package::manifest_hash(&app.current_dir).ok();
+
+    // Write to lock file
+    if verbose {
+        reporter.info("Writing to lock file", "");
+    }
+
+    lock_file.save().context("Failed to save lock file")?;
+
+    Ok(pipeline
+        .resolutions
+        .iter()
+        .filter_map(|resolution| packages.get(&resolution.name).cloned())
+        .collect())
+}
+
+/// Report the warnings of the install, along with the requests made to each registry with
+/// `--json` and `--network-report`.
+fn print_summary(app: &App, warnings: &Warnings) {
+    let reporter = reporter::get();
+
+    if reporter.name() != "json" || !app.has_flag(&["--network-report"]) {
+        reporter.warnings(warnings);
+        return;
+    }
+
+    let mut summary = warnings.to_json();
+
+    if app.has_flag(&["--network-report"]) {
+        summary["network"] = network::report_json();
+    }
+
+    reporter::get().document(&serde_json::to_string_pretty(&summary).unwrap_or_default());
+}
+
+/// Warn about `packages` whose `engines` don't match the installed Node and npm.
+fn check_engines(packages: &[&VoltPackage], warnings: &mut Warnings) {
+    let installed: Vec<InstalledPackage> = packages
+        .iter()
+        .filter(|package| !package.engines.is_empty())
+        .map(|package| InstalledPackage {
+            name: package.name.clone(),
+            version: package.version.clone(),
+            engines: package
+                .engines
+                .iter()
+                .map(|(engine, range)| (engine.clone(), range.clone()))
+                .collect(),
+        })
+        .collect();
+
+    // Only ask for the versions of engines some package constrains, starting them is slow
+    let current: HashMap<String, String> = ENGINES
+        .iter()
+        .filter(|engine| {
+            installed
+                .iter()
+                .any(|package| package.engines.contains_key(**engine))
+        })
+        .filter_map(|engine| Some((engine.to_string(), engines::engine_version(engine)?)))
+        .collect();
+
+    for incompatibility in engines::incompatibilities(&installed, &current) {
+        warnings.add(
+            Kind::Engine,
+            Some(format!(
+                "{}@{}",
+                incompatibility.package, incompatibility.version
+            )),
+            format!(
+                "needs {} {}, found {}",
+                incompatibility.engine, incompatibility.range, incompatibility.current
+            ),
+        );
+    }
+}
+
+/// Choose the package providing each binary, warning about the names several packages declare,
+/// or failing on them with `strict-bins=true`.
+fn plan_bins(
+    app: &App,
+    packages: &HashMap<String, VoltPackage>,
+    pipeline: &pipeline::Pipeline,
+    package_file: &PackageJson,
+    warnings: &mut Warnings,
+) -> Result<bins::Plan> {
+    let direct = pipeline
+        .resolutions
+        .iter()
+        .map(|resolution| &resolution.name)
+        .chain(package_file.dependencies.keys())
+        .chain(package_file.dev_dependencies.keys())
+        .chain(package_file.optional_dependencies.keys())
+        .cloned()
+        .collect();
+
+    let plan = bins::plan(packages, &direct);
+
+    if !plan.conflicts.is_empty() && bins::is_strict(app) {
+        let list: Vec<String> = plan
+            .conflicts
+            .iter()
+            .map(|conflict| format!("  {}", conflict.describe()))
+            .collect();
+
+        return Err(anyhow!(
+            "conflicting binaries (strict-bins is set)\n{}",
+            list.join("\n")
+        ));
+    }
+
+    for conflict in plan.conflicts.iter() {
+        warnings.add(Kind::Bin, None, conflict.describe());
+    }
+
+    Ok(plan)
+}
+
+/// Refuse to install a project whose `os`, `cpu` or `libc` fields exclude the platform, unless
+/// `--force` is passed.
+fn check_platform(app: &App, warnings: &mut Warnings) -> Result<()> {
+    let package_file = PackageJson::from(&app.current_dir.join("package.json").to_string_lossy());
+    let platform = Platform::from_app(app);
+
+    let mismatches = platform::mismatches(
+        &platform,
+        &package_file.os,
+        &package_file.cpu,
+        &package_file.libc,
+    );
+
+    if mismatches.is_empty() {
+        return Ok(());
+    }
+
+    let message = format!(
+        "the project doesn't support this platform: {}",
+        mismatches.join(", ")
+    );
+
+    if !app.has_flag(&["--force"]) {
+        return Err(anyhow!("{}, pass --force to install anyway", message));
+    }
+
+    warnings.add(Kind::Platform, None, message);
+
+    Ok(())
+}
+
+/// Refuse to add dependencies to the root of a workspace, which usually should go to a member,
+/// unless `-w` (`--workspace-root`) or `ignore-workspace-root-check` in `.voltrc` says so.
+fn check_workspace_root(app: &App) -> Result<()> {
+    let allowed = app.has_flag(&["-w", "--workspace-root", "--ignore-workspace-root-check"])
+        || app.config.get_bool("ignore-workspace-root-check") == Some(true);
+
+    if allowed || workspace::Workspace::load(&app.current_dir).is_none() {
+        return Ok(());
+    }
+
+    Err(anyhow!(
+        "{} is the root of a workspace, dependencies usually belong to a member: run volt add \
+         in the member (--cwd=<member>), or pass -w (--workspace-root) to add them to the root",
+        app.current_dir.display()
+    ))
+}
+
+/// Ask how to settle the problems of each peer, `None` when the install is aborted.
+///
+/// Skipped peers are recorded in `package_file`, the peers to pin are returned to be installed.
+fn choose_peer_fixes(
+    problems: &[PeerProblem],
+    package_file: &mut PackageJson,
+) -> Result<Option<Vec<String>>> {
+    reporter::get().output(&format!(
+        "{} unmet peer dependencies\n{}",
+        " warn ".black().on_bright_yellow(),
+        peers::conflict_tree(problems)
+    ));
+
+    let mut pins = vec![];
+
+    for (peer, fixes) in peers::fixes(problems) {
+        let mut items: Vec<String> = fixes.iter().map(PeerFix::describe).collect();
+        items.push(String::from("abort the install"));
+
+        let choice = Select {
+            message: format!("Settle the peer {}", peer),
+            paged: false,
+            selected: Some(1),
+            items,
         }
+        .run()?;
 
-        if !handles.is_empty() {
-            for handle in handles {
-                handle.await?;
+        match fixes.get(choice) {
+            Some(fix) => {
+                fix.apply(package_file);
+                pins.extend(fix.spec());
             }
+            None => return Ok(None),
         }
+    }
 
-        Ok(())
+    Ok(Some(pins))
+}
+
+/// Write shims for the binaries of global `packages`, and offer to put them in PATH.
+fn link_global_binaries(app: &App, packages: &[VoltPackage]) -> Result<()> {
+    let bin_dir = global::bin_dir(app);
+
+    for package in packages {
+        for name in global::write_shims(&bin_dir, &app.node_modules_dir, package)? {
+            reporter::get().output(&format!(
+                "{} Linked {} from {}",
+                "success".bright_green(),
+                name.bright_cyan(),
+                package.name
+            ));
+        }
+    }
+
+    if global::is_on_path(&bin_dir) {
+        return Ok(());
+    }
+
+    let location = global::path_location(app);
+
+    let accepted = std::io::stdin().is_terminal()
+        && Confirm {
+            message: format!(
+                "{} is not in PATH, add it to {}",
+                bin_dir.display(),
+                location
+            ),
+            default: true,
+        }
+        .run()?;
+
+    if accepted {
+        global::add_to_path(app, &bin_dir)?;
+        reporter::get().info("Open a new terminal", "to use global binaries");
+    } else {
+        reporter::get().output(&format!(
+            "{}: add {} to PATH to use global binaries",
+            " warn ".black().on_bright_yellow(),
+            bin_dir.display().to_string().bright_cyan()
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env::temp_dir;
+    use volt_utils::config::Config;
+
+    fn app(flags: &[&str]) -> App {
+        let root = temp_dir();
+
+        App {
+            current_dir: root.clone(),
+            home_dir: root.join("home"),
+            node_modules_dir: root.join("node_modules"),
+            volt_dir: root.join("home/.volt"),
+            cache_dir: root.join("home/.volt/.cache"),
+            lock_file_path: root.join("volt.lock"),
+            config_file: root.join(".voltrc"),
+            args: vec![],
+            flags: flags.iter().map(|flag| flag.to_string()).collect(),
+            config: Config::default(),
+        }
+    }
+
+    #[test]
+    fn resolves_packages_from_the_lock_file() {
+        let lock_file = LockFile::from_entries(&[
+            ("left", "1.1.0", &[("right", "2.3.0")]),
+            ("right", "2.3.0", &[]),
+        ]);
+
+        let package_file = PackageJson {
+            optional_dependencies: HashMap::from([(String::from("left"), String::from("^1.0.0"))]),
+            ..PackageJson::default()
+        };
+
+        let frozen = app(&["--frozen-lockfile"]);
+        let resolve = |app: &App, spec: &str, references: &mut HashMap<String, String>| {
+            locked_resolutions(
+                app,
+                &[spec.to_string()],
+                &package_file,
+                &lock_file,
+                references,
+            )
+        };
+
+        // The range comes from optionalDependencies
+        let mut references = HashMap::new();
+        let locked = resolve(&frozen, "left", &mut references).unwrap();
+        assert_eq!(locked["left"].version, "1.1.0");
+        assert_eq!(references["left"], "^1.0.0");
+
+        let error = resolve(&frozen, "left@^2.0.0", &mut HashMap::new()).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "left@^2.0.0 is not in {}, run volt install without --frozen-lockfile",
+                frozen.lock_file_path.display()
+            )
+        );
+
+        // Without --frozen-lockfile, it is resolved again
+        let locked = resolve(
+            &app(&[FROM_LOCK_FILE_FLAG]),
+            "left@^2.0.0",
+            &mut HashMap::new(),
+        );
+        assert!(locked.unwrap().is_empty());
     }
 }
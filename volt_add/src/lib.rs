@@ -12,3 +12,4 @@ limitations under the License.
 */
 
 pub mod command;
+pub mod trust;
@@ -0,0 +1,168 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Choose the dependencies whose install scripts run.
+//!
+//! The `preinstall`, `install` and `postinstall` scripts of dependencies are blocked unless the
+//! project trusts the package. The first install bringing in packages with install scripts lists
+//! them and asks which to trust, and the answers are kept in the `.voltrc` of the project as
+//! `trusted-dependencies` and `untrusted-dependencies`, so each package is only asked about once.
+//! Without a terminal, `--trust-all` and `--trust-none` answer instead, and without either
+//! nothing new is trusted and the question comes back on the next install.
+//!
+//! Trusted scripts run in the directory of the package in node_modules once everything is linked.
+
+use std::collections::{BTreeMap, HashMap};
+use std::io::IsTerminal;
+
+use anyhow::{anyhow, Context, Result};
+use colored::Colorize;
+use volt_core::prompt::prompts::MultiSelect;
+use volt_scripts::{pty, runner};
+use volt_utils::{app::App, config, package::install_scripts, reporter, volt_api::VoltPackage};
+
+pub const TRUSTED_KEY: &str = "trusted-dependencies";
+pub const UNTRUSTED_KEY: &str = "untrusted-dependencies";
+
+/// Ask which of `undecided` to trust, returning the trusted ones, or none when nobody can be
+/// asked.
+fn ask(app: &App, undecided: &[String]) -> Result<Option<Vec<String>>> {
+    if app.has_flag(&["--trust-all"]) {
+        return Ok(Some(undecided.to_vec()));
+    }
+
+    if app.has_flag(&["--trust-none"]) {
+        return Ok(Some(vec![]));
+    }
+
+    if !std::io::stdin().is_terminal() || pty::is_ci() {
+        return Ok(None);
+    }
+
+    let checked = MultiSelect {
+        message: String::from("These dependencies have install scripts, which should run?"),
+        items: undecided.to_vec(),
+    }
+    .run()?;
+
+    Ok(Some(
+        checked
+            .into_iter()
+            .map(|index| undecided[index].clone())
+            .collect(),
+    ))
+}
+
+/// Add `names` to the list setting `key` of the project `.voltrc`.
+fn remember(app: &App, key: &str, names: &[String]) -> Result<()> {
+    if names.is_empty() {
+        return Ok(());
+    }
+
+    let mut list = app.config.get_list(key).unwrap_or_default();
+    list.extend(names.iter().cloned());
+    list.sort();
+    list.dedup();
+
+    config::set_in_file(
+        &app.current_dir.join(config::CONFIG_FILE),
+        key,
+        &list.join(","),
+    )
+}
+
+/// Run the install scripts of the trusted packages among `packages`, asking about the packages
+/// the project hasn't decided about yet.
+pub fn run(app: &App, packages: &HashMap<String, VoltPackage>) -> Result<()> {
+    let with_scripts: BTreeMap<&String, Vec<(String, String)>> = packages
+        .keys()
+        .map(|name| (name, install_scripts(&app.node_modules_dir.join(name))))
+        .filter(|(_, scripts)| !scripts.is_empty())
+        .collect();
+
+    if with_scripts.is_empty() {
+        return Ok(());
+    }
+
+    let mut trusted = app.config.get_list(TRUSTED_KEY).unwrap_or_default();
+    let untrusted = app.config.get_list(UNTRUSTED_KEY).unwrap_or_default();
+
+    let undecided: Vec<String> = with_scripts
+        .keys()
+        .filter(|name| !trusted.contains(name) && !untrusted.contains(name))
+        .map(|name| name.to_string())
+        .collect();
+
+    if !undecided.is_empty() {
+        match ask(app, &undecided)? {
+            Some(chosen) => {
+                let refused: Vec<String> = undecided
+                    .iter()
+                    .filter(|name| !chosen.contains(name))
+                    .cloned()
+                    .collect();
+
+                remember(app, TRUSTED_KEY, &chosen)?;
+                remember(app, UNTRUSTED_KEY, &refused)?;
+
+                trusted.extend(chosen);
+            }
+            None => reporter::get().warn(&format!(
+                "skipped the install scripts of {}, pass --trust-all or --trust-none to decide",
+                undecided.join(", ")
+            )),
+        }
+    }
+
+    for (name, scripts) in with_scripts.iter() {
+        if !trusted.contains(name) {
+            continue;
+        }
+
+        let dir = app.node_modules_dir.join(name);
+        let version = packages[*name].version.as_str();
+
+        for (script, command) in scripts.iter() {
+            reporter::get().output(&format!(
+                "{} {}@{} {}",
+                ">".bright_magenta().bold(),
+                name,
+                version,
+                script
+            ));
+
+            let status = runner::shell_command(command)
+                .current_dir(&dir)
+                .env("npm_lifecycle_event", script)
+                .env("npm_package_name", name)
+                .env("npm_package_version", version)
+                .status()
+                .with_context(|| format!("failed to run the {} script of {}", script, name))?;
+
+            if !status.success() {
+                return Err(anyhow!(
+                    "the {} script of {}@{} failed with {}",
+                    script,
+                    name,
+                    version,
+                    status
+                ));
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,170 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! A self-hosted volt API.
+//!
+//! Serves the flattened trees the volt CDN serves for public packages (`GET /react.json`,
+//! `GET /@types%2fnode.json`, `GET /react@^17.json`), resolved from the registry of the
+//! `.voltrc` of the server with the registry resolver, so packages of a private registry get
+//! the same single-request installs. Point volt at it with `volt-api=<url>`.
+//!
+//! Resolutions are kept in memory for `max_age` and resolved again afterwards, so new versions
+//! show up. `GET /-/ping` answers `ok` for health checks.
+
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use colored::Colorize;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{header, Body, Method, Request, Response, Server, StatusCode};
+use volt_utils::{app::App, resolver};
+
+/// How long resolutions are kept when `--max-age` is not passed.
+pub const DEFAULT_MAX_AGE: u64 = 300;
+
+/// The package spec a request path asks for: `/@types%2fnode.json` is `@types/node`.
+pub fn package_spec(path: &str) -> Option<String> {
+    let spec = path
+        .strip_prefix('/')?
+        .strip_suffix(".json")?
+        .replace("%2f", "/")
+        .replace("%2F", "/")
+        .replace("%40", "@")
+        .replace("%5e", "^")
+        .replace("%5E", "^")
+        .replace("%7e", "~")
+        .replace("%7E", "~");
+
+    if spec.is_empty() || spec.starts_with('-') || spec.contains("..") {
+        return None;
+    }
+
+    Some(spec)
+}
+
+fn json(status: StatusCode, body: String) -> Response<Body> {
+    let mut response = Response::new(Body::from(body));
+    *response.status_mut() = status;
+    response.headers_mut().insert(
+        header::CONTENT_TYPE,
+        header::HeaderValue::from_static("application/json"),
+    );
+
+    response
+}
+
+async fn handle(app: Arc<App>, request: Request<Body>) -> Result<Response<Body>, Infallible> {
+    if request.method() != Method::GET {
+        let mut response = Response::new(Body::empty());
+        *response.status_mut() = StatusCode::METHOD_NOT_ALLOWED;
+        return Ok(response);
+    }
+
+    if request.uri().path() == "/-/ping" {
+        return Ok(Response::new(Body::from("ok")));
+    }
+
+    let spec = match package_spec(request.uri().path()) {
+        Some(spec) => spec,
+        None => {
+            return Ok(json(
+                StatusCode::NOT_FOUND,
+                serde_json::json!({ "error": "not found" }).to_string(),
+            ))
+        }
+    };
+
+    let response = match resolver::resolve(&app, &spec).await {
+        Ok(tree) => match serde_json::to_string(&tree) {
+            Ok(body) => json(StatusCode::OK, body),
+            Err(error) => json(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                serde_json::json!({ "error": error.to_string() }).to_string(),
+            ),
+        },
+        // volt resolves from the registry itself when the API has nothing
+        Err(error) => json(
+            StatusCode::NOT_FOUND,
+            serde_json::json!({ "error": format!("{:#}", error) }).to_string(),
+        ),
+    };
+
+    if app.has_flag(&["--verbose"]) {
+        println!(
+            "info {} {} {}",
+            "GET".yellow(),
+            spec,
+            response.status().as_u16()
+        );
+    }
+
+    Ok(response)
+}
+
+/// Serve resolutions on `address` until the process is stopped.
+pub async fn serve(app: Arc<App>, address: SocketAddr, max_age: Duration) -> Result<()> {
+    // Packuments are memoized for the life of the process otherwise
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(max_age);
+
+        loop {
+            interval.tick().await;
+            resolver::forget();
+        }
+    });
+
+    let service = make_service_fn(move |_| {
+        let app = app.clone();
+
+        async move { Ok::<_, Infallible>(service_fn(move |request| handle(app.clone(), request))) }
+    });
+
+    let server = Server::try_bind(&address)?.serve(service);
+
+    println!(
+        "{} serving volt metadata on http://{}",
+        "success".bright_green(),
+        server.local_addr()
+    );
+
+    server.await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_package_specs_from_paths() {
+        assert_eq!(package_spec("/react.json"), Some(String::from("react")));
+        assert_eq!(
+            package_spec("/@types%2fnode.json"),
+            Some(String::from("@types/node"))
+        );
+        assert_eq!(
+            package_spec("/@acme/widgets@%5e2.0.0.json"),
+            Some(String::from("@acme/widgets@^2.0.0"))
+        );
+        assert_eq!(package_spec("/react"), None);
+        assert_eq!(package_spec("/-/ping.json"), None);
+        assert_eq!(package_spec("/.json"), None);
+    }
+}
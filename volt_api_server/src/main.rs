@@ -0,0 +1,92 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+use std::net::SocketAddr;
+use std::process::exit;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use colored::Colorize;
+use volt_api_server::DEFAULT_MAX_AGE;
+use volt_utils::{app::App, ERROR_TAG};
+
+const DEFAULT_ADDRESS: &str = "127.0.0.1:8080";
+
+fn help() -> String {
+    format!(
+        r#"volt_api_server
+
+Serve flattened dependency trees of a registry to volt.
+
+Usage: {} {}
+
+Options:
+
+  {} {} Registry to resolve from, the one of .voltrc by default.
+  {} {} Address to listen on, {} by default.
+  {} {} Seconds resolutions are kept, {} by default.
+  {} {} Log every request."#,
+        "volt_api_server".green().bold(),
+        "[flags]".white(),
+        "--registry=<url>".blue(),
+        "".white(),
+        "--listen=<address>".blue(),
+        "".white(),
+        DEFAULT_ADDRESS,
+        "--max-age=<seconds>".blue(),
+        "".white(),
+        DEFAULT_MAX_AGE,
+        "--verbose".blue(),
+        "".white(),
+    )
+}
+
+#[tokio::main]
+async fn main() {
+    if let Err(error) = try_main().await {
+        eprintln!("{} {:#}", ERROR_TAG.clone(), error);
+        exit(1);
+    }
+}
+
+async fn try_main() -> Result<()> {
+    let mut app = App::initialize();
+
+    if app.has_flag(&["--help", "-h"]) {
+        println!("{}", help());
+        return Ok(());
+    }
+
+    if let Some(registry) = app.flag_value(&["--registry"]) {
+        app.config.set("registry", &registry);
+    }
+
+    let address: SocketAddr = app
+        .flag_value(&["--listen"])
+        .unwrap_or_else(|| DEFAULT_ADDRESS.to_string())
+        .parse()
+        .map_err(|_| anyhow!("invalid --listen address"))?;
+
+    let max_age = match app.flag_value(&["--max-age"]) {
+        Some(max_age) => max_age
+            .parse()
+            .map_err(|_| anyhow!("invalid --max-age: {}", max_age))?,
+        None => DEFAULT_MAX_AGE,
+    };
+
+    volt_api_server::serve(Arc::new(app), address, Duration::from_secs(max_age)).await
+}
@@ -11,38 +11,342 @@
     limitations under the License.
 */
 
-//! Handle an unknown command (can be listed in scripts).
+//! Check installed packages for known vulnerabilities.
 
+use std::collections::BTreeMap;
+use std::process::exit;
 use std::sync::Arc;
 
-use anyhow::Result;
+use anyhow::{anyhow, Context, Result};
 use async_trait::async_trait;
-// use regex::Regex;
-// use std::process;
-use volt_core::command::Command;
-use volt_utils::app::App;
+use colored::Colorize;
+use node_semver::{Range, Version};
+use serde_json::Value;
+use volt_core::{command::Command, help::Help, model::lock_file::LockFile};
+use volt_utils::{
+    app::App,
+    registry_mirrors,
+    report::{self, Format},
+    reporter,
+};
+
+use crate::signatures;
+
+/// Severities from the most to the least severe.
+pub const SEVERITIES: [&str; 5] = ["critical", "high", "moderate", "low", "info"];
+
+/// An advisory affecting installed versions of a package.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Vulnerability {
+    pub name: String,
+    pub installed: Vec<String>,
+    pub severity: String,
+    pub title: String,
+    pub url: String,
+    pub vulnerable_versions: String,
+}
+
+fn severity_rank(severity: &str) -> usize {
+    SEVERITIES
+        .iter()
+        .position(|known| *known == severity)
+        .unwrap_or(SEVERITIES.len())
+}
+
+/// Installed versions by package name, from the lock file.
+fn installed(lock_file: &LockFile) -> BTreeMap<String, Vec<String>> {
+    let mut installed: BTreeMap<String, Vec<String>> = BTreeMap::new();
+
+    for dependency in lock_file.dependencies.values() {
+        let versions = installed.entry(dependency.name.clone()).or_default();
+
+        if !versions.contains(&dependency.version) {
+            versions.push(dependency.version.clone());
+        }
+    }
+
+    installed
+}
+
+/// Read the vulnerabilities from a bulk advisory response, most severe first.
+pub fn vulnerabilities(
+    installed: &BTreeMap<String, Vec<String>>,
+    advisories: &Value,
+) -> Vec<Vulnerability> {
+    let mut vulnerabilities = vec![];
+
+    for (name, advisories) in advisories.as_object().into_iter().flatten() {
+        let versions = match installed.get(name) {
+            Some(versions) => versions,
+            None => continue,
+        };
+
+        for advisory in advisories.as_array().into_iter().flatten() {
+            let vulnerable_versions = advisory["vulnerable_versions"]
+                .as_str()
+                .unwrap_or("*")
+                .to_string();
+
+            // Unparsable ranges are reported rather than silently dropped
+            let affected: Vec<String> = match Range::parse(&vulnerable_versions) {
+                Ok(range) => versions
+                    .iter()
+                    .filter(|version| {
+                        Version::parse(version).map_or(true, |version| range.satisfies(&version))
+                    })
+                    .cloned()
+                    .collect(),
+                Err(_) => versions.clone(),
+            };
+
+            if affected.is_empty() {
+                continue;
+            }
+
+            vulnerabilities.push(Vulnerability {
+                name: name.clone(),
+                installed: affected,
+                severity: advisory["severity"].as_str().unwrap_or("info").to_string(),
+                title: advisory["title"].as_str().unwrap_or_default().to_string(),
+                url: advisory["url"].as_str().unwrap_or_default().to_string(),
+                vulnerable_versions,
+            });
+        }
+    }
+
+    vulnerabilities.sort_by(|a, b| {
+        severity_rank(&a.severity)
+            .cmp(&severity_rank(&b.severity))
+            .then(a.name.cmp(&b.name))
+    });
+
+    vulnerabilities
+}
+
+/// Ask the registry for the advisories of the packages in the lock file.
+pub async fn audit(app: &App) -> Result<Vec<Vulnerability>> {
+    let lock_file = LockFile::load(app.lock_file_path.clone()).map_err(|_| {
+        anyhow!(
+            "failed to read {}, run volt install first",
+            app.lock_file_path.display()
+        )
+    })?;
+
+    let installed = installed(&lock_file);
+    let registry = registry_mirrors::url(app).await;
+
+    let response = reqwest::Client::new()
+        .post(format!("{}/-/npm/v1/security/advisories/bulk", registry))
+        .header("Content-Type", "application/json")
+        .body(serde_json::to_string(&installed)?)
+        .send()
+        .await?
+        .error_for_status()
+        .with_context(|| format!("failed to get advisories from {}", registry))?
+        .text()
+        .await?;
+
+    Ok(vulnerabilities(
+        &installed,
+        &serde_json::from_str(&response)?,
+    ))
+}
+
+fn summary(vulnerabilities: &[Vulnerability]) -> String {
+    let counts: Vec<String> = SEVERITIES
+        .iter()
+        .filter_map(|severity| {
+            let count = vulnerabilities
+                .iter()
+                .filter(|vulnerability| vulnerability.severity == *severity)
+                .count();
+
+            (count > 0).then(|| format!("{} {}", count, severity))
+        })
+        .collect();
+
+    format!(
+        "{} vulnerabilities ({})",
+        vulnerabilities.len(),
+        counts.join(", ")
+    )
+}
+
+fn print_table(vulnerabilities: &[Vulnerability]) {
+    for vulnerability in vulnerabilities {
+        let severity = match vulnerability.severity.as_str() {
+            "critical" => vulnerability.severity.bright_red().bold(),
+            "high" => vulnerability.severity.bright_red(),
+            "moderate" => vulnerability.severity.yellow(),
+            _ => vulnerability.severity.normal(),
+        };
+
+        reporter::get().output(&format!(
+            "{} {} {}",
+            severity,
+            vulnerability.name.bold(),
+            vulnerability.title
+        ));
+        reporter::get().output(&format!(
+            "  installed {} (vulnerable {})",
+            vulnerability.installed.join(", "),
+            vulnerability.vulnerable_versions
+        ));
+
+        if !vulnerability.url.is_empty() {
+            reporter::get().output(&format!("  {}", vulnerability.url.bright_cyan()));
+        }
+
+        reporter::get().output("");
+    }
+
+    reporter::get().output(&summary(vulnerabilities).to_string());
+}
+
+/// The vulnerabilities as a markdown table linking to packages and advisories.
+pub fn markdown(vulnerabilities: &[Vulnerability]) -> String {
+    let rows: Vec<Vec<String>> = vulnerabilities
+        .iter()
+        .map(|vulnerability| {
+            vec![
+                vulnerability.severity.clone(),
+                report::link(
+                    &vulnerability.name,
+                    &report::package_url(&vulnerability.name),
+                ),
+                report::escape(&vulnerability.installed.join(", ")),
+                report::escape(&vulnerability.vulnerable_versions),
+                if vulnerability.url.is_empty() {
+                    report::escape(&vulnerability.title)
+                } else {
+                    report::link(&vulnerability.title, &vulnerability.url)
+                },
+            ]
+        })
+        .collect();
+
+    format!(
+        "### {}\n\n{}",
+        summary(vulnerabilities),
+        report::markdown_table(
+            &["Severity", "Package", "Installed", "Vulnerable", "Advisory"],
+            &rows
+        )
+    )
+}
+
 pub struct Audit {}
 
 #[async_trait]
 impl Command for Audit {
-    fn help() -> String {
-        todo!()
+    fn help() -> Help {
+        Help::new(
+            "audit",
+            "Check the installed packages for known vulnerabilities.",
+        )
+        .usage("[flags]")
+        .usage("signatures")
+        .command(
+            "signatures",
+            "Check registry signatures and provenance attestations.",
+        )
+        .flag(
+            &["--format=<format>"],
+            "Print a report for the terminal or markdown (table, markdown).",
+        )
+        .verbose()
     }
 
-    /// Execute the `volt watch` command
+    /// Execute the `volt audit` command
     ///
-    /// Execute a watch command
+    /// Lists the advisories affecting the packages in the lock file, and exits with an error
+    /// when there are any. `volt audit signatures` does the same for packages which can't be
+    /// verified.
     /// ## Arguments
-    /// * `error` - Instance of the command (`Arc<App>`)
+    /// * `app` - Instance of the command (`Arc<App>`)
     /// ## Examples
-    /// ```
-    /// //
+    /// ```ignore
     /// // .exec() is an async call so you need to await it
     /// Audit.exec(app).await;
     /// ```
     /// ## Returns
     /// * `Result<()>`
-    async fn exec(_app: Arc<App>) -> Result<()> {
-        Ok(())
+    async fn exec(app: Arc<App>) -> Result<()> {
+        if app.args.get(1).map(String::as_str) == Some("signatures") {
+            let checked = signatures::audit(&app).await?;
+            signatures::print(&checked);
+
+            if checked.iter().any(signatures::Checked::is_unverifiable) {
+                exit(1);
+            }
+
+            return Ok(());
+        }
+
+        let format = Format::from_app(&app)?;
+        let vulnerabilities = audit(&app).await?;
+
+        if vulnerabilities.is_empty() {
+            reporter::get().output("No known vulnerabilities found.");
+            return Ok(());
+        }
+
+        match format {
+            Format::Table => print_table(&vulnerabilities),
+            Format::Markdown => reporter::get().document(markdown(&vulnerabilities).trim_end()),
+        }
+
+        exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn reports_affected_versions() {
+        let installed: BTreeMap<String, Vec<String>> = vec![
+            (
+                String::from("lodash"),
+                vec![String::from("4.17.20"), String::from("4.17.21")],
+            ),
+            (String::from("minimist"), vec![String::from("1.2.0")]),
+        ]
+        .into_iter()
+        .collect();
+
+        let advisories = json!({
+            "lodash": [{
+                "id": 1,
+                "url": "https://github.com/advisories/GHSA-35jh-r3h4-6jhm",
+                "title": "Command Injection in lodash",
+                "severity": "high",
+                "vulnerable_versions": "<4.17.21"
+            }],
+            "minimist": [{
+                "id": 2,
+                "url": "https://github.com/advisories/GHSA-xvch-5gv4-984h",
+                "title": "Prototype Pollution in minimist",
+                "severity": "critical",
+                "vulnerable_versions": "<1.2.6"
+            }]
+        });
+
+        let vulnerabilities = vulnerabilities(&installed, &advisories);
+
+        assert_eq!(vulnerabilities.len(), 2);
+        assert_eq!(vulnerabilities[0].name, "minimist");
+        assert_eq!(vulnerabilities[1].installed, vec!["4.17.20"]);
+
+        assert_eq!(
+            markdown(&vulnerabilities[1..]),
+            "### 1 vulnerabilities (1 high)\n\n\
+             | Severity | Package | Installed | Vulnerable | Advisory |\n\
+             | --- | --- | --- | --- | --- |\n\
+             | high | [lodash](https://www.npmjs.com/package/lodash) | 4.17.20 | <4.17.21 | \
+             [Command Injection in lodash](https://github.com/advisories/GHSA-35jh-r3h4-6jhm) |\n"
+        );
     }
 }
@@ -0,0 +1,253 @@
+/*
+    Copyright 2021 Volt Contributors
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+        http://www.apache.org/licenses/LICENSE-2.0
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! `volt audit signatures`: check the registry signatures and provenance of installed packages.
+//!
+//! Packages from registries which publish signing keys must carry a valid signature, and
+//! packages linking a provenance attestation must link one about their tarball. Packages
+//! without provenance are only counted, most packages are published without.
+
+use std::collections::btree_map::Entry;
+use std::collections::{BTreeMap, BTreeSet};
+
+use anyhow::{anyhow, Result};
+use colored::Colorize;
+use futures::stream::{self, StreamExt};
+use volt_core::model::lock_file::LockFile;
+use volt_utils::{
+    app::App,
+    provenance::{self, Provenance, RegistryKey, Signature},
+    registry, reporter, resolver,
+};
+
+/// Packages checked at once.
+const CONCURRENCY: usize = 16;
+
+/// What was found out about an installed package.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Checked {
+    pub name: String,
+    pub version: String,
+    /// `None` when its registry doesn't sign packages
+    pub signature: Option<Signature>,
+    /// The error for attestations which can't be verified
+    pub provenance: Result<Option<Provenance>, String>,
+}
+
+impl Checked {
+    /// Check if the package can't be trusted to be what the registry published.
+    pub fn is_unverifiable(&self) -> bool {
+        matches!(
+            self.signature,
+            Some(Signature::Missing) | Some(Signature::Invalid)
+        ) || self.provenance.is_err()
+    }
+}
+
+async fn check(
+    app: &App,
+    keys: &BTreeMap<String, Vec<RegistryKey>>,
+    name: String,
+    version: String,
+) -> Checked {
+    let packument = match resolver::packument(app, name.clone()).await {
+        Ok(packument) => packument,
+        Err(error) => {
+            return Checked {
+                name,
+                version,
+                signature: Some(Signature::Missing),
+                provenance: Err(format!("{:#}", error)),
+            }
+        }
+    };
+
+    let dist = &packument["versions"][&version]["dist"];
+
+    let signature = keys
+        .get(&registry::url_for(app, &name))
+        .filter(|keys| !keys.is_empty())
+        .map(|keys| provenance::verify(keys, &name, &version, dist));
+
+    let provenance = provenance::fetch(app, &name, &version, dist)
+        .await
+        .map_err(|error| format!("{:#}", error));
+
+    Checked {
+        name,
+        version,
+        signature,
+        provenance,
+    }
+}
+
+/// Check every package of the lock file, by name.
+pub async fn audit(app: &App) -> Result<Vec<Checked>> {
+    let lock_file = LockFile::load(app.lock_file_path.clone()).map_err(|_| {
+        anyhow!(
+            "failed to read {}, run volt install first",
+            app.lock_file_path.display()
+        )
+    })?;
+
+    let installed: BTreeSet<(String, String)> = lock_file
+        .dependencies
+        .values()
+        .map(|dependency| (dependency.name.clone(), dependency.version.clone()))
+        .collect();
+
+    let mut keys = BTreeMap::new();
+
+    for (name, _) in installed.iter() {
+        if let Entry::Vacant(entry) = keys.entry(registry::url_for(app, name)) {
+            // Registries without keys don't sign, which is not the package's fault
+            let registry_keys = provenance::keys(entry.key()).await.unwrap_or_default();
+            entry.insert(registry_keys);
+        }
+    }
+
+    let keys = &keys;
+
+    let mut checked: Vec<Checked> = stream::iter(installed)
+        .map(|(name, version)| check(app, keys, name, version))
+        .buffer_unordered(CONCURRENCY)
+        .collect()
+        .await;
+
+    checked.sort_by(|a, b| (&a.name, &a.version).cmp(&(&b.name, &b.version)));
+
+    Ok(checked)
+}
+
+fn plural(count: usize) -> &'static str {
+    if count == 1 {
+        "package has"
+    } else {
+        "packages have"
+    }
+}
+
+/// Print what was checked, the unverifiable packages last.
+pub fn print(checked: &[Checked]) {
+    let signed = checked
+        .iter()
+        .filter(|checked| checked.signature == Some(Signature::Verified))
+        .count();
+    let attested = checked
+        .iter()
+        .filter(|checked| matches!(checked.provenance, Ok(Some(_))))
+        .count();
+    let unsigned = checked
+        .iter()
+        .filter(|checked| checked.signature.is_none())
+        .count();
+
+    reporter::get().output(&format!(
+        "audited {} packages\n",
+        checked.len().to_string().bright_cyan()
+    ));
+    reporter::get().output(&format!(
+        "{} {} verified registry signatures",
+        signed,
+        plural(signed)
+    ));
+    reporter::get().output(&format!(
+        "{} {} verified attestations",
+        attested,
+        plural(attested)
+    ));
+
+    if unsigned > 0 {
+        reporter::get().output(&format!(
+            "{} {} no signatures, their registry doesn't sign packages",
+            unsigned,
+            plural(unsigned)
+        ));
+    }
+
+    print_problem(
+        "missing registry signatures",
+        checked
+            .iter()
+            .filter(|checked| checked.signature == Some(Signature::Missing))
+            .map(|checked| (checked, String::new())),
+    );
+    print_problem(
+        "invalid registry signatures",
+        checked
+            .iter()
+            .filter(|checked| checked.signature == Some(Signature::Invalid))
+            .map(|checked| (checked, String::new())),
+    );
+    print_problem(
+        "unverifiable attestations",
+        checked.iter().filter_map(|checked| {
+            let error = checked.provenance.as_ref().err()?;
+            Some((checked, format!(": {}", error)))
+        }),
+    );
+}
+
+/// Print the packages with `problem`, with the details of each.
+fn print_problem<'a>(problem: &str, packages: impl Iterator<Item = (&'a Checked, String)>) {
+    let lines: Vec<String> = packages
+        .map(|(checked, detail)| format!("  {}@{}{}", checked.name.bold(), checked.version, detail))
+        .collect();
+
+    if lines.is_empty() {
+        return;
+    }
+
+    reporter::get().output(&format!(
+        "\n{} {} {}:",
+        lines.len(),
+        plural(lines.len()),
+        problem.bright_red()
+    ));
+
+    for line in lines.iter() {
+        reporter::get().output(&line.to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn checked(
+        signature: Option<Signature>,
+        provenance: Result<Option<Provenance>, String>,
+    ) -> Checked {
+        Checked {
+            name: String::from("left-pad"),
+            version: String::from("1.3.0"),
+            signature,
+            provenance,
+        }
+    }
+
+    #[test]
+    fn flags_unverifiable_packages() {
+        assert!(!checked(Some(Signature::Verified), Ok(None)).is_unverifiable());
+        // Registries which don't sign can't be held against their packages
+        assert!(!checked(None, Ok(Some(Provenance::default()))).is_unverifiable());
+
+        assert!(checked(Some(Signature::Missing), Ok(None)).is_unverifiable());
+        assert!(checked(Some(Signature::Invalid), Ok(None)).is_unverifiable());
+        assert!(checked(
+            Some(Signature::Verified),
+            Err(String::from("the attestation is not about the tarball"))
+        )
+        .is_unverifiable());
+    }
+}
@@ -0,0 +1,258 @@
+/*
+Copyright 2021 Volt Contributors
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+    http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+//! Open the documentation, repository or issue tracker of a package.
+
+use std::fs::read_to_string;
+use std::process::{Command as Process, Stdio};
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use colored::Colorize;
+use serde_json::Value;
+use volt_core::{command::Command, help::Help};
+use volt_utils::{app::App, mirror, parse_package_spec, registry_mirrors, reporter};
+
+use crate::urls;
+
+/// Get the manifest of `spec`: the installed one if there is one, the registry's otherwise.
+///
+/// Without a spec, the manifest of the current project.
+async fn manifest(app: &App, spec: Option<&str>) -> Result<Value> {
+    let spec = match spec {
+        Some(spec) => spec,
+        None => {
+            let path = app.current_dir.join("package.json");
+            let data = read_to_string(&path)
+                .with_context(|| format!("failed to read {}", path.display()))?;
+
+            return Ok(serde_json::from_str(&data)?);
+        }
+    };
+
+    let (name, version) = parse_package_spec(spec);
+
+    let installed = app.node_modules_dir.join(&name).join("package.json");
+
+    if version.is_none() {
+        if let Some(manifest) = read_to_string(&installed)
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+        {
+            return Ok(manifest);
+        }
+    }
+
+    if mirror::is_offline(app) {
+        return Err(anyhow!("{} is not installed", name));
+    }
+
+    let registry = registry_mirrors::url(app).await;
+
+    let response = reqwest::get(format!("{}/{}", registry, name.replace('/', "%2f")))
+        .await?
+        .error_for_status()
+        .with_context(|| format!("failed to fetch {}", name))?
+        .text()
+        .await?;
+
+    let packument: Value = serde_json::from_str(&response)?;
+
+    // A version, a dist-tag, or the latest version for ranges
+    let tag = version.unwrap_or_else(|| String::from("latest"));
+    let version = if packument["versions"][&tag].is_object() {
+        tag
+    } else {
+        packument["dist-tags"][&tag]
+            .as_str()
+            .or_else(|| packument["dist-tags"]["latest"].as_str())
+            .unwrap_or_default()
+            .to_string()
+    };
+
+    let mut manifest = packument["versions"][&version].clone();
+
+    if !manifest.is_object() {
+        return Ok(packument);
+    }
+
+    // Older versions don't always have the fields the packument has
+    for field in ["homepage", "repository", "bugs"] {
+        if manifest[field].is_null() && !packument[field].is_null() {
+            manifest[field] = packument[field].clone();
+        }
+    }
+
+    Ok(manifest)
+}
+
+/// Open `url` in the default browser.
+fn open(url: &str) -> Result<()> {
+    let mut command = if cfg!(target_os = "macos") {
+        Process::new("open")
+    } else if cfg!(windows) {
+        let mut command = Process::new("cmd");
+        command.args(["/C", "start", ""]);
+        command
+    } else {
+        Process::new("xdg-open")
+    };
+
+    let status = command
+        .arg(url)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()?;
+
+    if !status.success() {
+        return Err(anyhow!("failed to open a browser"));
+    }
+
+    Ok(())
+}
+
+/// Resolve a url of the package in the arguments with `find`, then open or print it.
+async fn browse(app: &App, what: &str, find: fn(&Value) -> Option<String>) -> Result<()> {
+    let spec = app.args.get(1).map(String::as_str);
+    let manifest = manifest(app, spec).await?;
+
+    let name = manifest["name"]
+        .as_str()
+        .unwrap_or("the package")
+        .to_string();
+
+    let url = find(&manifest).ok_or_else(|| anyhow!("{} has no {}", name, what))?;
+
+    if app.has_flag(&["--no-open"]) {
+        reporter::get().output(&url.to_string());
+        return Ok(());
+    }
+
+    reporter::get().info("Opening", &url.bright_cyan().to_string());
+
+    // Still useful over ssh or in containers without a browser
+    if let Err(e) = open(&url) {
+        reporter::get().output(&format!(
+            "{} {}, open {} instead",
+            " warn ".black().on_bright_yellow(),
+            e,
+            url
+        ));
+    }
+
+    Ok(())
+}
+
+fn help(command: &str, description: &str) -> Help {
+    Help::new(command, description)
+        .usage("[package] [flags]")
+        .arg("[package]", "The package, the current project by default.")
+        .flag(&["--no-open"], "Print the url instead of opening it.")
+        .flag(
+            &["--offline"],
+            "Use the installed manifest or fail, without the network.",
+        )
+        .verbose()
+}
+
+/// Struct implementation for the `docs` command.
+pub struct Docs;
+
+#[async_trait]
+impl Command for Docs {
+    /// Display a help menu for the `volt docs` command.
+    fn help() -> Help {
+        help(
+            "docs",
+            "Open the documentation of a package, or of the current project.",
+        )
+    }
+
+    /// Execute the `volt docs` command
+    ///
+    /// Opens the homepage of the package, its readme or its page on npmjs.com.
+    /// ## Arguments
+    /// * `app` - Instance of the command (`Arc<App>`)
+    /// ## Examples
+    /// ```ignore
+    /// // .exec() is an async call so you need to await it
+    /// Docs.exec(app).await;
+    /// ```
+    /// ## Returns
+    /// * `Result<()>`
+    async fn exec(app: Arc<App>) -> Result<()> {
+        browse(&app, "documentation", urls::docs_url).await
+    }
+}
+
+/// Struct implementation for the `repo` command.
+pub struct Repo;
+
+#[async_trait]
+impl Command for Repo {
+    /// Display a help menu for the `volt repo` command.
+    fn help() -> Help {
+        help(
+            "repo",
+            "Open the source repository of a package, or of the current project.",
+        )
+    }
+
+    /// Execute the `volt repo` command
+    ///
+    /// Opens the `repository` of the package, down to its directory in monorepos.
+    /// ## Arguments
+    /// * `app` - Instance of the command (`Arc<App>`)
+    /// ## Examples
+    /// ```ignore
+    /// // .exec() is an async call so you need to await it
+    /// Repo.exec(app).await;
+    /// ```
+    /// ## Returns
+    /// * `Result<()>`
+    async fn exec(app: Arc<App>) -> Result<()> {
+        browse(&app, "repository", urls::repository_url).await
+    }
+}
+
+/// Struct implementation for the `bugs` command.
+pub struct Bugs;
+
+#[async_trait]
+impl Command for Bugs {
+    /// Display a help menu for the `volt bugs` command.
+    fn help() -> Help {
+        help(
+            "bugs",
+            "Open the issue tracker of a package, or of the current project.",
+        )
+    }
+
+    /// Execute the `volt bugs` command
+    ///
+    /// Opens the `bugs` url of the package, or the issues of its repository.
+    /// ## Arguments
+    /// * `app` - Instance of the command (`Arc<App>`)
+    /// ## Examples
+    /// ```ignore
+    /// // .exec() is an async call so you need to await it
+    /// Bugs.exec(app).await;
+    /// ```
+    /// ## Returns
+    /// * `Result<()>`
+    async fn exec(app: Arc<App>) -> Result<()> {
+        browse(&app, "issue tracker", urls::bugs_url).await
+    }
+}
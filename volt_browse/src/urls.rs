@@ -0,0 +1,192 @@
+/*
+Copyright 2021 Volt Contributors
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+    http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+//! Web pages of a package, from the fields of its manifest, the way npm finds them.
+
+use serde_json::Value;
+
+/// Hosts which have issue trackers at `<repository>/issues`.
+const HOSTS_WITH_ISSUES: [&str; 3] = ["github.com", "gitlab.com", "bitbucket.org"];
+
+/// A browsable url for a `repository` value: `https://github.com/facebook/react`,
+/// `git+https://...git`, `git@github.com:facebook/react.git`, `github:facebook/react` or
+/// `facebook/react`.
+pub fn normalize_repository(repository: &str) -> Option<String> {
+    let repository = repository.trim();
+
+    let (host, path) = if let Some((prefix, path)) = repository
+        .split_once(':')
+        .filter(|(prefix, _)| ["github", "gitlab", "bitbucket", "gist"].contains(prefix))
+    {
+        let host = match prefix {
+            "github" => "github.com",
+            "gitlab" => "gitlab.com",
+            "bitbucket" => "bitbucket.org",
+            _ => "gist.github.com",
+        };
+
+        (host.to_string(), path.to_string())
+    } else if let Some(rest) = repository.strip_prefix("git@") {
+        // scp-like syntax: git@github.com:facebook/react.git
+        let (host, path) = rest.split_once(':')?;
+        (host.to_string(), path.to_string())
+    } else if let Some((_, rest)) = repository.split_once("://") {
+        // Drop credentials and the port of ssh urls
+        let rest = rest.rsplit_once('@').map_or(rest, |(_, rest)| rest);
+        let (host, path) = rest.split_once('/')?;
+        let host = host.split(':').next().unwrap_or(host);
+
+        (host.to_string(), path.to_string())
+    } else if repository.split('/').count() == 2 && !repository.contains(' ') {
+        (String::from("github.com"), repository.to_string())
+    } else {
+        return None;
+    };
+
+    let path = path
+        .split('#')
+        .next()
+        .unwrap_or_default()
+        .trim_end_matches('/')
+        .trim_end_matches(".git");
+
+    if host.is_empty() || path.is_empty() {
+        return None;
+    }
+
+    Some(format!("https://{}/{}", host, path))
+}
+
+/// The url of the `repository` of `manifest`, down to its `directory` in monorepos.
+pub fn repository_url(manifest: &Value) -> Option<String> {
+    let repository = &manifest["repository"];
+
+    let url = repository
+        .as_str()
+        .or_else(|| repository["url"].as_str())
+        .and_then(normalize_repository)?;
+
+    match repository["directory"].as_str() {
+        Some(directory) if url.starts_with("https://github.com/") => {
+            Some(format!("{}/tree/HEAD/{}", url, directory.trim_matches('/')))
+        }
+        _ => Some(url),
+    }
+}
+
+/// The `homepage` of `manifest`, its readme in the repository, or its page on npmjs.com.
+pub fn docs_url(manifest: &Value) -> Option<String> {
+    if let Some(homepage) = manifest["homepage"].as_str() {
+        return Some(homepage.to_string());
+    }
+
+    if let Some(url) = manifest["repository"]
+        .as_str()
+        .or_else(|| manifest["repository"]["url"].as_str())
+        .and_then(normalize_repository)
+    {
+        return Some(format!("{}#readme", url));
+    }
+
+    manifest["name"]
+        .as_str()
+        .map(|name| format!("https://www.npmjs.com/package/{}", name))
+}
+
+/// The issue tracker of `manifest`, from `bugs` or the issues of its repository.
+pub fn bugs_url(manifest: &Value) -> Option<String> {
+    let bugs = &manifest["bugs"];
+
+    if let Some(url) = bugs.as_str().or_else(|| bugs["url"].as_str()) {
+        return Some(url.to_string());
+    }
+
+    let url = manifest["repository"]
+        .as_str()
+        .or_else(|| manifest["repository"]["url"].as_str())
+        .and_then(normalize_repository)?;
+
+    HOSTS_WITH_ISSUES
+        .iter()
+        .any(|host| url.starts_with(&format!("https://{}/", host)))
+        .then(|| format!("{}/issues", url))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn normalizes_repositories() {
+        for repository in [
+            "https://github.com/facebook/react",
+            "git+https://github.com/facebook/react.git",
+            "git://github.com/facebook/react.git",
+            "git+ssh://git@github.com/facebook/react.git",
+            "git@github.com:facebook/react.git",
+            "github:facebook/react",
+            "facebook/react",
+        ] {
+            assert_eq!(
+                normalize_repository(repository).as_deref(),
+                Some("https://github.com/facebook/react"),
+                "{}",
+                repository
+            );
+        }
+
+        assert_eq!(
+            normalize_repository("gitlab:inkscape/inkscape").as_deref(),
+            Some("https://gitlab.com/inkscape/inkscape")
+        );
+        assert_eq!(normalize_repository("not a repository"), None);
+    }
+
+    #[test]
+    fn falls_back_to_the_repository() {
+        let manifest = json!({
+            "name": "react-dom",
+            "repository": {
+                "type": "git",
+                "url": "git+https://github.com/facebook/react.git",
+                "directory": "packages/react-dom"
+            }
+        });
+
+        assert_eq!(
+            docs_url(&manifest).as_deref(),
+            Some("https://github.com/facebook/react#readme")
+        );
+        assert_eq!(
+            repository_url(&manifest).as_deref(),
+            Some("https://github.com/facebook/react/tree/HEAD/packages/react-dom")
+        );
+        assert_eq!(
+            bugs_url(&manifest).as_deref(),
+            Some("https://github.com/facebook/react/issues")
+        );
+
+        let manifest = json!({ "name": "left-pad", "bugs": { "url": "https://example.com/bugs" } });
+
+        assert_eq!(
+            docs_url(&manifest).as_deref(),
+            Some("https://www.npmjs.com/package/left-pad")
+        );
+        assert_eq!(
+            bugs_url(&manifest).as_deref(),
+            Some("https://example.com/bugs")
+        );
+        assert_eq!(repository_url(&manifest), None);
+    }
+}
@@ -13,20 +13,99 @@ limitations under the License.
 
 //! Clean cached download files.
 
+use std::cmp::Reverse;
 use std::env::temp_dir;
 use std::fs;
 use std::fs::remove_file;
 use std::process::exit;
 use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use async_trait::async_trait;
 use colored::Colorize;
-use indicatif::{ProgressBar, ProgressStyle};
-use volt_core::command::Command;
-use volt_core::VERSION;
+use volt_core::{command::Command, help::Help};
+use volt_exec::command::prune_environments;
 use volt_utils::app::App;
-use volt_utils::PROGRESS_CHARS;
+use volt_utils::reporter::Progress;
+use volt_utils::{dedup, quota, reporter, scrub};
+
+/// Days a `volt x` environment is kept after its last use.
+const DEFAULT_ENVIRONMENT_TTL_DAYS: u64 = 7;
+
+/// Print the store entries, in the order of `--sort`.
+fn list(app: &App) -> Result<()> {
+    let mut entries = quota::entries(&app.volt_dir, &quota::Usage::load(&app.volt_dir));
+
+    match app.flag_value(&["--sort"]).as_deref() {
+        None | Some("name") => {}
+        Some("size") => entries.sort_by_key(|entry| Reverse(entry.size)),
+        // Least recently used first, the order they are evicted in
+        Some("used") => entries.sort_by_key(|entry| entry.last_used.unwrap_or(0)),
+        Some(order) => return Err(anyhow!("invalid value for --sort: {}", order)),
+    }
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs());
+
+    for entry in entries.iter() {
+        let used = match entry.last_used {
+            Some(last_used) => format!("{} days ago", now.saturating_sub(last_used) / 86400),
+            None => String::from("unknown"),
+        };
+
+        reporter::get().output(&format!(
+            "{:>10}  {:<14} {}",
+            quota::human_size(entry.size),
+            used.dimmed(),
+            entry.name.bright_cyan()
+        ));
+    }
+
+    reporter::get().output(&format!(
+        "{} {} in {} entries",
+        "Total".bright_green(),
+        quota::human_size(entries.iter().map(|entry| entry.size).sum()),
+        entries.len()
+    ));
+
+    Ok(())
+}
+
+/// Print how much space sharing identical files saves in the store.
+fn status(app: &App) {
+    let status = dedup::status(&app.volt_dir);
+
+    reporter::get().output(&format!("{:<12} {}", "Entries", status.entries));
+    reporter::get().output(&format!(
+        "{:<12} {} ({} stored)",
+        "Files", status.files, status.unique_files
+    ));
+    reporter::get().output(&format!(
+        "{:<12} {}",
+        "Size",
+        quota::human_size(status.logical_bytes)
+    ));
+    reporter::get().output(&format!(
+        "{:<12} {}",
+        "Stored",
+        quota::human_size(status.stored_bytes)
+    ));
+    reporter::get().output(&format!(
+        "{:<12} {} (saves {})",
+        "Dedup ratio",
+        format!("{:.2}x", status.ratio()).bright_green(),
+        quota::human_size(status.logical_bytes.saturating_sub(status.stored_bytes))
+    ));
+
+    if !dedup::is_enabled(app) {
+        reporter::get().info(
+            "Disabled",
+            "store-dedup is false, new entries keep their own copies",
+        );
+    }
+}
 
 /// Struct implementation for the `Add` command.
 #[derive(Clone)]
@@ -35,29 +114,52 @@ pub struct Cache {}
 #[async_trait]
 impl Command for Cache {
     /// Display a help menu for the `volt cahe` command.
-    fn help() -> String {
-        format!(
-            r#"volt {}
-    
-Handle the volt cache files.
-Usage: {} {} {}
-
-Commands:
-  clean - Clean downloaded cache files and metadata. 
-
-Options: 
-    
-  {} {} Output verbose messages on internal operations.
-  {} {} Disable progress bar."#,
-            VERSION.bright_green().bold(),
-            "volt".bright_green().bold(),
-            "cache".bright_purple(),
-            "[command]".bright_purple(),
-            "--verbose".blue(),
-            "(-v)".yellow(),
-            "--no-progress".blue(),
-            "(-np)".yellow()
-        )
+    fn help() -> Help {
+        Help::new("cache", "Handle the volt cache files.")
+            .usage("[command] [flags]")
+            .command("clean", "Clean downloaded cache files and metadata.")
+            .command(
+                "ls",
+                "List store entries with their size and when they were last installed.",
+            )
+            .command(
+                "prune",
+                "Remove `volt x` environments which haven't been used recently.",
+            )
+            .command(
+                "status",
+                "Show the size of the store and how much sharing identical files saves.",
+            )
+            .command(
+                "scrub",
+                "Verify store entries against their recorded hashes and repair corrupted ones.",
+            )
+            .verbose()
+            .no_progress()
+            .flag(
+                &["--ttl=<days>"],
+                "Days an unused `volt x` environment is kept for (default: 7).",
+            )
+            .flag(
+                &["--limit=<count>"],
+                "Scrub this many entries, continuing from the previous scrub.",
+            )
+            .flag(
+                &["--sort=<order>"],
+                "Order of `ls` entries (name, size, used).",
+            )
+            .example(
+                "volt cache ls --sort=size",
+                "List the largest store entries first.",
+            )
+            .example(
+                "volt store status",
+                "Show how much the store saves by sharing identical files.",
+            )
+            .example(
+                "volt store prune --ttl=30",
+                "Remove the `volt x` environments which weren't used in the last 30 days.",
+            )
     }
 
     /// Execute the `volt cache` command
@@ -75,7 +177,7 @@ Options:
     /// * `Result<()>`
     async fn exec(app: Arc<App>) -> Result<()> {
         if app.args.len() == 1 {
-            println!("{}", Self::help());
+            reporter::get().output(&Self::help().to_string());
             exit(1);
         }
         if app.args[1].as_str() == "clean" {
@@ -83,26 +185,72 @@ Options:
 
             let count = files.len();
 
-            let progress_bar = ProgressBar::new(count.to_owned() as u64);
-
-            progress_bar.set_style(
-                ProgressStyle::default_bar()
-                    .progress_chars(PROGRESS_CHARS)
-                    .template(&format!(
-                        "{} [{{bar:40.magenta/blue}}] {{msg:.blue}} {{len}} / {{pos}}",
-                        "Deleting Cache".bright_blue()
-                    )),
-            );
+            let progress = Progress::start("Deleting Cache", count as u64);
 
             for file in files {
                 let os_str = file.unwrap().file_name();
                 let f_name = format!(r"{}volt\{}", temp_dir().display(), os_str.to_str().unwrap());
 
                 remove_file(f_name).unwrap();
-                progress_bar.inc(1);
+                progress.inc(1);
+            }
+
+            progress.finish();
+        } else if app.args[1].as_str() == "prune" {
+            let days: u64 = match app.flag_value(&["--ttl"]) {
+                Some(days) => days
+                    .parse()
+                    .map_err(|_| anyhow!("invalid value for --ttl: {}", days))?,
+                None => DEFAULT_ENVIRONMENT_TTL_DAYS,
+            };
+
+            let removed = prune_environments(&app, Duration::from_secs(days * 24 * 60 * 60))?;
+
+            reporter::get().output(&format!(
+                "{} {} {}",
+                "Removed".bright_green(),
+                removed,
+                "unused environments".bright_green()
+            ));
+        } else if app.args[1].as_str() == "ls" {
+            list(&app)?;
+        } else if app.args[1].as_str() == "status" {
+            status(&app);
+        } else if app.args[1].as_str() == "scrub" {
+            let limit: Option<usize> = match app.flag_value(&["--limit"]) {
+                Some(limit) => Some(
+                    limit
+                        .parse()
+                        .map_err(|_| anyhow!("invalid value for --limit: {}", limit))?,
+                ),
+                None => None,
+            };
+
+            let report = scrub::scrub(&app, limit).await?;
+
+            for name in report.repaired.iter() {
+                reporter::get().info("Repaired", &name.bright_cyan().to_string());
+            }
+
+            for name in report.evicted.iter() {
+                reporter::get().output(&format!(
+                    "{}: {} was corrupted and could not be fetched again, it was evicted",
+                    " warn ".black().on_bright_yellow(),
+                    name.bright_cyan()
+                ));
             }
 
-            progress_bar.finish();
+            reporter::get().output(&format!(
+                "{} {} {}{}",
+                "Scrubbed".bright_green(),
+                report.checked,
+                "store entries".bright_green(),
+                if report.finished {
+                    ""
+                } else {
+                    ", run again to continue"
+                }
+            ));
         }
         Ok(())
     }
@@ -20,8 +20,7 @@ use std::sync::Arc;
 
 use anyhow::Result;
 use async_trait::async_trait;
-use colored::Colorize;
-use volt_core::{command::Command, VERSION};
+use volt_core::{command::Command, help::Help};
 use volt_utils::app::App;
 /// Struct implementation for the `Deploy` command.
 pub struct Fix;
@@ -29,24 +28,10 @@ pub struct Fix;
 #[async_trait]
 impl Command for Fix {
     /// Display a help menu for the `volt deploy` command.
-    fn help() -> String {
-        format!(
-            r#"volt {}
-    
-Checks for errors.
-
-Usage: {} {} {}
-
-Options: 
-
-  {} {} Output verbose messages on internal operations."#,
-            VERSION.bright_green().bold(),
-            "volt".bright_green().bold(),
-            "deploy".bright_purple(),
-            "[commit]".white(),
-            "--verbose".blue(),
-            "(-v)".yellow()
-        )
+    fn help() -> Help {
+        Help::new("check", "Checks for errors.")
+            .usage("[flags]")
+            .verbose()
     }
 
     /// Execute the `volt fix` command
@@ -0,0 +1,140 @@
+/*
+Copyright 2021 Volt Contributors
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+    http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+//! Check the `engines` of installed packages against the current Node and npm.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use colored::Colorize;
+use prettytable::{cell, row, Table};
+use volt_core::{command::Command, help::Help};
+use volt_utils::app::App;
+use volt_utils::reporter;
+
+use crate::engines::{self, ENGINES};
+
+/// Struct implementation for the `check-engines` command.
+pub struct CheckEngines;
+
+#[async_trait]
+impl Command for CheckEngines {
+    /// Display a help menu for the `volt check-engines` command.
+    fn help() -> Help {
+        Help::new(
+            "check-engines",
+            "Check the `engines` field of the project and every installed package against the
+current Node and npm versions. Pass a version to check before upgrading.",
+        )
+        .usage("[flags]")
+        .flag(
+            &["--node=<version>"],
+            "Check against this Node version instead of the installed one.",
+        )
+        .flag(
+            &["--npm=<version>"],
+            "Check against this npm version instead of the installed one.",
+        )
+        .verbose()
+        .example(
+            "volt check-engines --node=20.0.0",
+            "Check the packages support Node 20.",
+        )
+    }
+
+    /// Execute the `volt check-engines` command
+    ///
+    /// Reports the installed packages whose engines don't match as a table.
+    /// ## Arguments
+    /// * `app` - Instance of the command (`Arc<App>`)
+    /// ## Examples
+    /// ```ignore
+    /// // .exec() is an async call so you need to await it
+    /// CheckEngines.exec(app).await;
+    /// ```
+    /// ## Returns
+    /// * `Result<()>`
+    async fn exec(app: Arc<App>) -> Result<()> {
+        let mut current = HashMap::new();
+
+        for engine in ENGINES.iter() {
+            let flag = format!("--{}", engine);
+
+            let version = app
+                .flag_value(&[flag.as_str()])
+                .map(|version| version.trim_start_matches('v').to_string())
+                .or_else(|| engines::engine_version(engine));
+
+            match version {
+                Some(version) => {
+                    if app.has_flag(&["-v", "--verbose"]) {
+                        reporter::get().info("Checking", &format!("{} {}", engine, version));
+                    }
+
+                    current.insert(engine.to_string(), version);
+                }
+                None => reporter::get().output(&format!(
+                    "{}: {} was not found, its constraints are not checked",
+                    " warn ".black().on_bright_yellow(),
+                    engine.bright_cyan()
+                )),
+            }
+        }
+
+        let mut packages = engines::installed_packages(&app.node_modules_dir);
+
+        if let Some(project) = engines::read_package(&app.current_dir.join("package.json")) {
+            packages.insert(0, project);
+        }
+
+        let incompatibilities = engines::incompatibilities(&packages, &current);
+
+        if incompatibilities.is_empty() {
+            reporter::get().output(&format!(
+                "{}: {} packages are compatible",
+                "success".bright_green(),
+                packages.len()
+            ));
+
+            return Ok(());
+        }
+
+        let mut table = Table::new();
+        table.add_row(row![
+            "Package".green().bold(),
+            "Version".green().bold(),
+            "Engine".green().bold(),
+            "Required".green().bold(),
+            "Current".green().bold()
+        ]);
+
+        for incompatibility in incompatibilities.iter() {
+            table.add_row(row![
+                incompatibility.package,
+                incompatibility.version,
+                incompatibility.engine,
+                incompatibility.range.bright_yellow(),
+                incompatibility.current.bright_red()
+            ]);
+        }
+
+        table.printstd();
+
+        Err(anyhow!(
+            "{} incompatible engine constraints",
+            incompatibilities.len()
+        ))
+    }
+}
@@ -0,0 +1,201 @@
+/*
+Copyright 2021 Volt Contributors
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+    http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+//! Collect the `engines` constraints of installed packages and check them.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs::read_to_string;
+use std::path::Path;
+use std::process::Command;
+
+use node_semver::{Range, Version};
+use serde_json::Value;
+use walkdir::WalkDir;
+
+/// Engines which are checked, the others (`vscode`, `yarn`) are not installed by volt.
+pub const ENGINES: [&str; 2] = ["node", "npm"];
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct InstalledPackage {
+    pub name: String,
+    pub version: String,
+    pub engines: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Incompatibility {
+    pub package: String,
+    pub version: String,
+    pub engine: String,
+    pub range: String,
+    pub current: String,
+}
+
+/// Get the version of an engine from its `--version` output (`v16.4.0`).
+pub fn engine_version(engine: &str) -> Option<String> {
+    let program = if cfg!(windows) && engine == "npm" {
+        "npm.cmd"
+    } else {
+        engine
+    };
+
+    let output = Command::new(program).arg("--version").output().ok()?;
+    let version = String::from_utf8(output.stdout).ok()?;
+
+    Some(version.trim().trim_start_matches('v').to_string())
+}
+
+/// Find every package installed under `node_modules_dir`, in either layout, once per version.
+pub fn installed_packages(node_modules_dir: &Path) -> Vec<InstalledPackage> {
+    let mut packages = BTreeSet::new();
+
+    for entry in WalkDir::new(node_modules_dir)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_name() == "package.json")
+    {
+        if !is_package_root(entry.path()) {
+            continue;
+        }
+
+        if let Some(package) = read_package(entry.path()) {
+            packages.insert(package);
+        }
+    }
+
+    packages.into_iter().collect()
+}
+
+/// Check if `manifest` is `node_modules/<name>/package.json` or
+/// `node_modules/@scope/<name>/package.json`, rather than a fixture inside a package.
+fn is_package_root(manifest: &Path) -> bool {
+    let ancestors: Vec<String> = manifest
+        .ancestors()
+        .skip(1)
+        .take(3)
+        .map(|dir| {
+            dir.file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default()
+        })
+        .collect();
+
+    match ancestors.as_slice() {
+        [_, parent, ..] if parent == "node_modules" => true,
+        [_, scope, grandparent] => scope.starts_with('@') && grandparent == "node_modules",
+        _ => false,
+    }
+}
+
+pub fn read_package(manifest: &Path) -> Option<InstalledPackage> {
+    let data: Value = serde_json::from_str(&read_to_string(manifest).ok()?).ok()?;
+
+    Some(InstalledPackage {
+        name: data["name"].as_str()?.to_string(),
+        version: data["version"].as_str().unwrap_or_default().to_string(),
+        engines: data["engines"]
+            .as_object()
+            .map(|engines| {
+                engines
+                    .iter()
+                    .filter_map(|(engine, range)| {
+                        Some((engine.clone(), range.as_str()?.to_string()))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default(),
+    })
+}
+
+/// Check `packages` against the `current` engine versions.
+///
+/// Ranges which are not valid semver are skipped, npm doesn't enforce them either.
+pub fn incompatibilities(
+    packages: &[InstalledPackage],
+    current: &HashMap<String, String>,
+) -> Vec<Incompatibility> {
+    let mut incompatibilities = vec![];
+
+    for package in packages {
+        for (engine, range) in package.engines.iter() {
+            let current = match current.get(engine) {
+                Some(current) => current,
+                None => continue,
+            };
+
+            let satisfied = match (Range::parse(range), Version::parse(current)) {
+                (Ok(range), Ok(version)) => range.satisfies(&version),
+                _ => true,
+            };
+
+            if !satisfied {
+                incompatibilities.push(Incompatibility {
+                    package: package.name.clone(),
+                    version: package.version.clone(),
+                    engine: engine.clone(),
+                    range: range.clone(),
+                    current: current.clone(),
+                });
+            }
+        }
+    }
+
+    incompatibilities
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_incompatible_engines() {
+        let package = |name: &str, node: &str| InstalledPackage {
+            name: name.to_string(),
+            version: String::from("1.0.0"),
+            engines: vec![(String::from("node"), node.to_string())]
+                .into_iter()
+                .collect(),
+        };
+
+        let packages = vec![
+            package("old", ">=10 <16"),
+            package("modern", ">=14"),
+            package("custom", "any node"),
+        ];
+
+        let current = vec![(String::from("node"), String::from("16.4.0"))]
+            .into_iter()
+            .collect();
+
+        let incompatibilities = incompatibilities(&packages, &current);
+
+        assert_eq!(incompatibilities.len(), 1);
+        assert_eq!(incompatibilities[0].package, "old");
+    }
+
+    #[test]
+    fn package_roots() {
+        assert!(is_package_root(Path::new(
+            "node_modules/react/package.json"
+        )));
+        assert!(is_package_root(Path::new(
+            "node_modules/@babel/core/package.json"
+        )));
+        assert!(is_package_root(Path::new(
+            "node_modules/.volt/a@1.0.0/node_modules/a/package.json"
+        )));
+        assert!(!is_package_root(Path::new(
+            "node_modules/resolve/test/fixture/package.json"
+        )));
+    }
+}
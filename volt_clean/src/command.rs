@@ -0,0 +1,72 @@
+/*
+Copyright 2021 Volt Contributors
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+    http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+//! Remove what volt keeps for a project.
+
+use std::process::exit;
+use std::sync::Arc;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use volt_core::{command::Command, help::Help};
+use volt_utils::{app::App, project_state, reporter};
+
+/// Struct implementation for the `clean` command.
+pub struct Clean;
+
+#[async_trait]
+impl Command for Clean {
+    /// Display a help menu for the `volt clean` command.
+    fn help() -> Help {
+        Help::new("clean", "Remove what volt keeps for the project.")
+            .usage("[flags]")
+            .flag(
+                &["--state"],
+                "Remove .volt, with the install state, journals, script logs and timing traces.",
+            )
+            .verbose()
+            .example(
+                "volt clean --state",
+                "Start over from a clean project state.",
+            )
+    }
+
+    /// Execute the `volt clean` command
+    ///
+    /// Removes the `.volt` directory of the project.
+    /// ## Arguments
+    /// * `app` - Instance of the command (`Arc<App>`)
+    /// ## Examples
+    /// ```ignore
+    /// // .exec() is an async call so you need to await it
+    /// Clean.exec(app).await;
+    /// ```
+    /// ## Returns
+    /// * `Result<()>`
+    async fn exec(app: Arc<App>) -> Result<()> {
+        if !app.has_flag(&["--state"]) {
+            reporter::get().output(&Self::help().to_string());
+            exit(1);
+        }
+
+        let dir = project_state::dir(&app);
+
+        if project_state::clean(&app)? {
+            reporter::get().info("Removed", &dir.display().to_string());
+        } else {
+            reporter::get().info("Nothing to clean,", &format!("has no {}", dir.display()));
+        }
+
+        Ok(())
+    }
+}
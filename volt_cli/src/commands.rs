@@ -18,13 +18,14 @@ use std::str::FromStr;
 use std::sync::Arc;
 
 use anyhow::Result;
-use volt_core::command::Command;
+use volt_core::{command::Command, help};
 use volt_utils::app::App;
 
 #[derive(Debug)]
 pub enum AppCommand {
     Add,
     Cache,
+    Clean,
     Search,
     Clone,
     Compress,
@@ -43,6 +44,32 @@ pub enum AppCommand {
     Upgrade,
     Info,
     Stat,
+    Exec,
+    LintManifest,
+    Fetch,
+    CheckEngines,
+    Fund,
+    Ping,
+    Whoami,
+    Env,
+    Completions,
+    Complete,
+    Publish,
+    Docs,
+    Repo,
+    Bugs,
+    Lock,
+    Audit,
+    Outdated,
+    Why,
+    Explain,
+    Config,
+    Pin,
+    Unpin,
+    Dockerfile,
+    Inspect,
+    Diff,
+    Ui,
 }
 
 impl FromStr for AppCommand {
@@ -51,7 +78,8 @@ impl FromStr for AppCommand {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
             "add" => Ok(Self::Add),
-            "cache" => Ok(Self::Cache),
+            "cache" | "store" => Ok(Self::Cache),
+            "clean" => Ok(Self::Clean),
             "clone" => Ok(Self::Clone),
             "compress" => Ok(Self::Compress),
             "create" => Ok(Self::Create),
@@ -65,31 +93,56 @@ impl FromStr for AppCommand {
             "run" => Ok(Self::Run),
             "fix" => Ok(Self::Fix),
             "watch" => Ok(Self::Watch),
-            "upgrade" => Ok(Self::Upgrade),
+            "upgrade" | "update" => Ok(Self::Upgrade),
             "search" => Ok(Self::Search),
             "info" => Ok(Self::Info),
-            "stat" => Ok(Self::Stat),
+            "stat" | "stats" => Ok(Self::Stat),
+            "x" | "exec" => Ok(Self::Exec),
+            "lint-manifest" => Ok(Self::LintManifest),
+            "fetch" => Ok(Self::Fetch),
+            "check-engines" => Ok(Self::CheckEngines),
+            "fund" => Ok(Self::Fund),
+            "ping" => Ok(Self::Ping),
+            "whoami" => Ok(Self::Whoami),
+            "env" => Ok(Self::Env),
+            "completions" => Ok(Self::Completions),
+            "__complete" => Ok(Self::Complete),
+            "publish" => Ok(Self::Publish),
+            "docs" => Ok(Self::Docs),
+            "repo" => Ok(Self::Repo),
+            "bugs" => Ok(Self::Bugs),
+            "lock" => Ok(Self::Lock),
+            "audit" => Ok(Self::Audit),
+            "outdated" => Ok(Self::Outdated),
+            "why" => Ok(Self::Why),
+            "explain" => Ok(Self::Explain),
+            "config" => Ok(Self::Config),
+            "pin" => Ok(Self::Pin),
+            "unpin" => Ok(Self::Unpin),
+            "dockerfile" => Ok(Self::Dockerfile),
+            "inspect" | "view-tarball" => Ok(Self::Inspect),
+            "diff" => Ok(Self::Diff),
+            "ui" => Ok(Self::Ui),
             _ => Err(()),
         }
     }
 }
 
 impl AppCommand {
-    pub fn current() -> Option<Self> {
-        if std::env::args().len() == 1 {
-            return Some(Self::Help);
-        }
-
-        match std::env::args().nth(1) {
+    /// The command `app` runs, the first of its arguments. Flags can come before it,
+    /// `volt --cwd web install`.
+    pub fn current(app: &App) -> Option<Self> {
+        match app.args.first() {
             Some(cmd) => Self::from_str(cmd.as_str()).ok(),
-            None => None,
+            None => Some(Self::Help),
         }
     }
 
-    pub fn help(&self) -> String {
+    pub fn help(&self) -> help::Help {
         match self {
             Self::Add => volt_add::command::Add::help(),
             Self::Cache => volt_cache::command::Cache::help(),
+            Self::Clean => volt_clean::command::Clean::help(),
             Self::Compress => volt_compress::command::Compress::help(),
             Self::Clone => volt_clone::command::Clone::help(),
             Self::Create => volt_create::command::Create::help(),
@@ -108,14 +161,40 @@ impl AppCommand {
             Self::Search => volt_search::command::Search::help(),
             Self::Info => volt_info::command::Info::help(),
             Self::Stat => volt_stat::command::Stat::help(),
+            Self::Exec => volt_exec::command::Exec::help(),
+            Self::LintManifest => volt_lint_manifest::command::LintManifest::help(),
+            Self::Fetch => volt_fetch::command::Fetch::help(),
+            Self::CheckEngines => volt_check_engines::command::CheckEngines::help(),
+            Self::Fund => volt_fund::command::Fund::help(),
+            Self::Ping => volt_ping::command::Ping::help(),
+            Self::Whoami => volt_whoami::command::Whoami::help(),
+            Self::Env => volt_env::command::Env::help(),
+            Self::Completions => volt_completions::command::Completions::help(),
+            Self::Complete => volt_completions::complete::Complete::help(),
+            Self::Publish => volt_publish::command::Publish::help(),
+            Self::Docs => volt_browse::command::Docs::help(),
+            Self::Repo => volt_browse::command::Repo::help(),
+            Self::Bugs => volt_browse::command::Bugs::help(),
+            Self::Lock => volt_lock::command::Lock::help(),
+            Self::Audit => volt_audit::command::Audit::help(),
+            Self::Outdated => volt_outdated::command::Outdated::help(),
+            Self::Why => volt_why::command::Why::help(),
+            Self::Explain => volt_explain::command::Explain::help(),
+            Self::Config => volt_config::command::Config::help(),
+            Self::Pin => volt_pin::command::Pin::help(),
+            Self::Unpin => volt_pin::command::Unpin::help(),
+            Self::Dockerfile => volt_dockerfile::command::Dockerfile::help(),
+            Self::Inspect => volt_inspect::command::Inspect::help(),
+            Self::Diff => volt_inspect::command::Diff::help(),
+            Self::Ui => volt_ui::command::Ui::help(),
         }
     }
 
-    pub async fn run(&self, app: App) -> Result<()> {
-        let app = Arc::new(app);
+    pub async fn run(&self, app: Arc<App>) -> Result<()> {
         match self {
             Self::Add => volt_add::command::Add::exec(app).await,
             Self::Cache => volt_cache::command::Cache::exec(app).await,
+            Self::Clean => volt_clean::command::Clean::exec(app).await,
             Self::Clone => volt_clone::command::Clone::exec(app).await,
             Self::Compress => volt_compress::command::Compress::exec(app).await,
             Self::Create => volt_create::command::Create::exec(app).await,
@@ -134,6 +213,32 @@ impl AppCommand {
             Self::Search => volt_search::command::Search::exec(app).await,
             Self::Info => volt_info::command::Info::exec(app).await,
             Self::Stat => volt_stat::command::Stat::exec(app).await,
+            Self::Exec => volt_exec::command::Exec::exec(app).await,
+            Self::LintManifest => volt_lint_manifest::command::LintManifest::exec(app).await,
+            Self::Fetch => volt_fetch::command::Fetch::exec(app).await,
+            Self::CheckEngines => volt_check_engines::command::CheckEngines::exec(app).await,
+            Self::Fund => volt_fund::command::Fund::exec(app).await,
+            Self::Ping => volt_ping::command::Ping::exec(app).await,
+            Self::Whoami => volt_whoami::command::Whoami::exec(app).await,
+            Self::Env => volt_env::command::Env::exec(app).await,
+            Self::Completions => volt_completions::command::Completions::exec(app).await,
+            Self::Complete => volt_completions::complete::Complete::exec(app).await,
+            Self::Publish => volt_publish::command::Publish::exec(app).await,
+            Self::Docs => volt_browse::command::Docs::exec(app).await,
+            Self::Repo => volt_browse::command::Repo::exec(app).await,
+            Self::Bugs => volt_browse::command::Bugs::exec(app).await,
+            Self::Lock => volt_lock::command::Lock::exec(app).await,
+            Self::Audit => volt_audit::command::Audit::exec(app).await,
+            Self::Outdated => volt_outdated::command::Outdated::exec(app).await,
+            Self::Why => volt_why::command::Why::exec(app).await,
+            Self::Explain => volt_explain::command::Explain::exec(app).await,
+            Self::Config => volt_config::command::Config::exec(app).await,
+            Self::Pin => volt_pin::command::Pin::exec(app).await,
+            Self::Unpin => volt_pin::command::Unpin::exec(app).await,
+            Self::Dockerfile => volt_dockerfile::command::Dockerfile::exec(app).await,
+            Self::Inspect => volt_inspect::command::Inspect::exec(app).await,
+            Self::Diff => volt_inspect::command::Diff::exec(app).await,
+            Self::Ui => volt_ui::command::Ui::exec(app).await,
         }
     }
 }
@@ -17,18 +17,39 @@
 mod commands;
 
 use std::process::exit;
+use std::sync::Arc;
+use std::time::Duration;
 
 use crate::commands::AppCommand;
 
 use anyhow::Result;
 use colored::Colorize;
-use tokio::time::Instant;
+use tokio::time::{timeout, Instant};
 use volt_core::VERSION;
-use volt_utils::{app::App, ERROR_TAG};
+use volt_outdated::notify;
+use volt_utils::{
+    app::App,
+    diagnostics::{self, ErrorFormat},
+    metrics, network, policy, project_state, reporter, resolver, volt_version, ERROR_TAG,
+};
+
+/// How long a finished command waits for the check of new major versions.
+const UPDATE_CHECK_GRACE: Duration = Duration::from_millis(500);
 
 #[tokio::main]
 async fn main() {
-    if let Err(err) = try_main().await {
+    let app = App::initialize();
+    let error_format = ErrorFormat::from_app(&app);
+
+    if let Err(err) = try_main(app).await {
+        if error_format == ErrorFormat::Json {
+            match err.downcast_ref::<policy::Violations>() {
+                Some(violations) => eprintln!("{}", diagnostics::diagnostic_json(violations)),
+                None => eprintln!("{}", diagnostics::error_json(&err)),
+            }
+            std::process::exit(1);
+        }
+
         eprintln!("{} {}", ERROR_TAG.clone(), err);
         let err_chain = err.chain().skip(1);
         if err_chain.clone().next().is_some() {
@@ -45,27 +66,195 @@ async fn main() {
     }
 }
 
-async fn try_main() -> Result<()> {
-    let app = App::initialize();
-    let cmd = AppCommand::current().unwrap_or(AppCommand::Script); // Default command is help
+async fn try_main(app: App) -> Result<()> {
+    // A project pinning another version of volt runs that one
+    if let Some(code) = volt_version::switch(&app, VERSION).await? {
+        exit(code);
+    }
+
+    let cmd = AppCommand::current(&app).unwrap_or(AppCommand::Script); // Default command is help
 
     if app.has_flag(&["--help", "-h"]) {
-        println!("{}", cmd.help());
+        // `volt --help` has no command of its own
+        let cmd = AppCommand::current(&app).unwrap_or(AppCommand::Help);
+        reporter::get().output(&cmd.help().to_string());
         return Ok(());
     }
 
+    // `volt help <command>` is the same as `volt <command> --help`
+    if let AppCommand::Help = cmd {
+        if let Some(command) = app
+            .args
+            .get(1)
+            .and_then(|arg| arg.parse::<AppCommand>().ok())
+        {
+            reporter::get().output(&command.help().to_string());
+            return Ok(());
+        }
+    }
+
     if app.has_flag(&["--version"]) {
-        println!(
+        reporter::get().output(&format!(
             "volt v{}{}",
             "::".bright_magenta(),
             VERSION.bright_green().bold()
-        );
+        ));
         exit(0);
     }
 
     let time = Instant::now();
-    cmd.run(app).await?;
-    println!("Finished in {:.2}s", time.elapsed().as_secs_f32());
+    let reporter = reporter::from_app(&app)?;
+    reporter::set(reporter.clone());
+    // The output of completions is read by shells and Dockerfiles are saved
+    let raw_output = matches!(
+        cmd,
+        AppCommand::Complete | AppCommand::Completions | AppCommand::Dockerfile
+    );
+    let quiet = raw_output || reporter.name() != "human";
+    let timing = app.has_flag(&["--timing"]);
+    // JSON summaries include the report instead
+    let network_report = app.has_flag(&["--network-report"]) && !reporter::json();
+    let exporter = metrics::Exporter::from_app(&app);
+    // Traces are kept for projects, not for commands run anywhere
+    let timings = if timing && app.current_dir.join("package.json").exists() {
+        project_state::create(&app)
+            .ok()
+            .map(|dir| dir.join(project_state::TIMINGS_DIR))
+    } else {
+        None
+    };
+    // Scripts run as `volt <script>` are all counted as `run`
+    let command = match cmd {
+        AppCommand::Script => String::from("run"),
+        _ => app.args.first().cloned().unwrap_or_default(),
+    };
+    let app = Arc::new(app);
+    // Checked while the command runs, `volt outdated` already tells
+    let update_check = if !quiet && !matches!(cmd, AppCommand::Outdated) && notify::is_due(&app) {
+        let app = app.clone();
+        Some(tokio::spawn(async move { notify::check(&app).await }))
+    } else {
+        None
+    };
+    let result = cmd.run(app).await;
+
+    if let Some(exporter) = exporter {
+        exporter
+            .export(&command, time.elapsed(), result.as_ref().err())
+            .await;
+    }
+
+    // The requests of a failed command are the ones worth looking at
+    if network_report {
+        print_network_report();
+    }
+
+    result?;
+
+    // The json reporter tells other programs the command is done, the quiet one says nothing
+    if !raw_output {
+        reporter.finished(time.elapsed());
+    }
+
+    if timing {
+        print_timing(time);
+    }
+
+    if let Some(mut check) = update_check {
+        match timeout(UPDATE_CHECK_GRACE, &mut check).await {
+            Ok(Ok(packages)) => notify::print(&packages),
+            _ => check.abort(),
+        }
+    }
+
+    if let Some(dir) = timings {
+        if let Err(err) = save_timing(&dir, &command, time) {
+            eprintln!(
+                "{} failed to save the timing trace: {}",
+                "warn".yellow(),
+                err
+            );
+        }
+    }
 
     Ok(())
 }
+
+/// Save where the time of a `--timing` run went to `dir`, to compare runs of the project later.
+fn save_timing(dir: &std::path::Path, command: &str, time: Instant) -> Result<()> {
+    let packuments = resolver::packument_stats();
+    let started = std::time::SystemTime::now() - time.elapsed();
+    let millis = started
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|started| started.as_millis() as u64)
+        .unwrap_or_default();
+
+    let trace = serde_json::json!({
+        "command": command,
+        "started": millis,
+        "total": time.elapsed().as_secs_f64(),
+        "packuments": {
+            "lookups": packuments.lookups,
+            "fetches": packuments.fetches,
+            "hits": packuments.hits(),
+        },
+    });
+
+    std::fs::create_dir_all(dir)?;
+    std::fs::write(
+        dir.join(format!("{}-{}.json", millis, command)),
+        serde_json::to_string_pretty(&trace)? + "\n",
+    )?;
+
+    Ok(())
+}
+
+/// Print the requests made to each registry, on stderr to keep the output of commands.
+fn print_network_report() {
+    let report = network::report();
+
+    if report.is_empty() {
+        eprintln!("network {}", "no requests".yellow());
+    }
+
+    for (registry, stats) in report {
+        let statuses: Vec<String> = stats
+            .statuses
+            .iter()
+            .map(|(status, count)| format!("{}×{}", status, count))
+            .collect();
+
+        eprintln!(
+            "network {} {} requests ({} failed), {} received, {} from cache, {} retries, status {}",
+            registry.yellow(),
+            stats.requests,
+            stats.errors,
+            network::format_bytes(stats.bytes),
+            stats.cache_hits,
+            stats.retries,
+            if statuses.is_empty() {
+                String::from("-")
+            } else {
+                statuses.join(" ")
+            }
+        );
+    }
+}
+
+/// Print where the time of a `--timing` run went, on stderr to keep the output of commands.
+fn print_timing(time: Instant) {
+    let packuments = resolver::packument_stats();
+
+    eprintln!(
+        "timing {} {:.2}s",
+        "total".yellow(),
+        time.elapsed().as_secs_f32()
+    );
+    eprintln!(
+        "timing {} {} lookups, {} fetched, {} from memory",
+        "packuments".yellow(),
+        packuments.lookups,
+        packuments.fetches,
+        packuments.hits()
+    );
+}
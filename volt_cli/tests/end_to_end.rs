@@ -0,0 +1,275 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! `volt add`, `install` and `remove` against the mock registry.
+
+use std::fs::{create_dir_all, read_to_string, remove_dir_all, write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+use serde_json::Value;
+use volt_test_registry::{MockRegistry, Package, RunningRegistry};
+
+/// A project using `registry`, in a directory of its own with its own store.
+struct Project {
+    root: PathBuf,
+}
+
+impl Project {
+    fn new(name: &str, registry: &RunningRegistry, dependencies: &str) -> Self {
+        let root = std::env::temp_dir().join(format!("volt-e2e-{}-{}", name, std::process::id()));
+        let _ = remove_dir_all(&root);
+        create_dir_all(root.join("project")).unwrap();
+
+        write(
+            root.join("project/package.json"),
+            format!(
+                r#"{{ "name": "{}", "version": "1.0.0", "dependencies": {} }}"#,
+                name, dependencies
+            ),
+        )
+        .unwrap();
+        write(
+            root.join("project/.voltrc"),
+            format!("registry={}\nresolver=registry\n", registry.url()),
+        )
+        .unwrap();
+
+        Project { root }
+    }
+
+    fn dir(&self) -> PathBuf {
+        self.root.join("project")
+    }
+
+    fn run(&self, args: &[&str]) -> Output {
+        self.run_in(&self.dir(), args)
+    }
+
+    /// Run volt from `dir` rather than the project.
+    fn run_in(&self, dir: &Path, args: &[&str]) -> Output {
+        Command::new(env!("CARGO_BIN_EXE_volt_cli"))
+            .args(args)
+            .current_dir(dir)
+            .env("HOME", self.root.join("home"))
+            .env("XDG_DATA_HOME", self.root.join("data"))
+            .env("XDG_CACHE_HOME", self.root.join("cache"))
+            .output()
+            .unwrap()
+    }
+
+    fn volt(&self, args: &[&str]) -> Output {
+        let output = self.run(args);
+
+        assert!(
+            output.status.success(),
+            "volt {} failed:\n{}{}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        );
+
+        output
+    }
+
+    fn installed(&self, name: &str) -> Option<String> {
+        let path = self
+            .dir()
+            .join("node_modules")
+            .join(name)
+            .join("package.json");
+        let manifest: Value = serde_json::from_str(&read_to_string(path).ok()?).ok()?;
+
+        manifest["version"].as_str().map(String::from)
+    }
+
+    fn dependencies(&self) -> Value {
+        let path = self.dir().join("package.json");
+        let manifest: Value = serde_json::from_str(&read_to_string(path).unwrap()).unwrap();
+
+        manifest["dependencies"].clone()
+    }
+}
+
+impl Drop for Project {
+    fn drop(&mut self) {
+        let _ = remove_dir_all(&self.root);
+    }
+}
+
+#[test]
+fn installs_adds_and_removes_packages() {
+    let registry = MockRegistry::new()
+        .publish(Package::new("left", "1.0.0").dependency("right", "^2.0.0"))
+        .publish(Package::new("left", "1.1.0").dependency("right", "^2.0.0"))
+        .publish(Package::new("right", "2.3.0").file("index.js", "module.exports = 2"))
+        .publish(Package::new("extra", "0.4.0"))
+        .start();
+
+    let project = Project::new("install", &registry, r#"{ "left": "^1.0.0" }"#);
+
+    project.volt(&["install"]);
+    assert_eq!(project.installed("left").as_deref(), Some("1.1.0"));
+    assert_eq!(project.installed("right").as_deref(), Some("2.3.0"));
+
+    // --json prints one event per line, the summary among them
+    let output = project.volt(&["stats", "--json"]);
+    let stats = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|line| serde_json::from_str::<Value>(line).unwrap())
+        .find(|event| event["type"] == "document")
+        .unwrap()["document"]
+        .clone();
+    assert_eq!(stats["direct"], 1);
+    assert_eq!(stats["transitive"], 1);
+    assert_eq!(stats["heaviest"][0]["packages"], 2);
+    assert!(stats["size"].as_u64().unwrap() > 0);
+
+    project.volt(&["add", "extra"]);
+    assert_eq!(project.installed("extra").as_deref(), Some("0.4.0"));
+    assert!(project.dependencies()["extra"].is_string());
+
+    project.volt(&["remove", "extra"]);
+    assert!(project.dependencies()["extra"].is_null());
+    assert!(!project.dir().join("node_modules/extra").exists());
+
+    assert!(registry.requests().contains(&String::from("GET /left")));
+}
+
+#[test]
+fn installs_from_the_lock_file() {
+    let registry = MockRegistry::new()
+        .publish(Package::new("left", "1.1.0").dependency("right", "^2.0.0"))
+        .publish(Package::new("right", "2.3.0"))
+        .start();
+
+    let project = Project::new("locked", &registry, r#"{ "left": "^1.0.0" }"#);
+
+    project.volt(&["install"]);
+    let requests = registry.requests().len();
+
+    // Nothing is resolved again, the packuments aren't even cached anymore
+    remove_dir_all(project.dir().join("node_modules")).unwrap();
+    let _ = remove_dir_all(project.root.join("cache"));
+    project.volt(&["install", "--frozen-lockfile"]);
+
+    assert_eq!(project.installed("right").as_deref(), Some("2.3.0"));
+    assert_eq!(registry.requests().len(), requests);
+
+    write(
+        project.dir().join("package.json"),
+        r#"{ "name": "locked", "version": "1.0.0", "dependencies": { "left": "^2.0.0" } }"#,
+    )
+    .unwrap();
+
+    let output = project.run(&["install", "--frozen-lockfile"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("left@^2.0.0 is not locked"));
+
+    // Packages the lock file has and package.json no longer asks for don't match either
+    write(
+        project.dir().join("package.json"),
+        r#"{ "name": "locked", "version": "1.0.0", "dependencies": {} }"#,
+    )
+    .unwrap();
+
+    let output = project.run(&["install", "--frozen-lockfile"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("left@1.1.0, right@2.3.0 are locked but not depended on"));
+}
+
+#[test]
+fn installs_dev_dependencies_but_in_production() {
+    let registry = MockRegistry::new()
+        .publish(Package::new("left", "1.1.0"))
+        .publish(Package::new("left", "2.0.0"))
+        .publish(Package::new("tool", "3.0.0"))
+        .publish(Package::new("extra", "0.4.0"))
+        .start();
+
+    let project = Project::new("production", &registry, "{}");
+    write(
+        project.dir().join("package.json"),
+        r#"{
+            "name": "production",
+            "version": "1.0.0",
+            "dependencies": { "left": "^1.0.0" },
+            "devDependencies": { "tool": "^3.0.0", "left": "^2.0.0" },
+            "optionalDependencies": { "extra": "^0.4.0", "missing": "^1.0.0" }
+        }"#,
+    )
+    .unwrap();
+
+    // The optional dependency which isn't published doesn't fail the install
+    project.volt(&["install", "--production"]);
+    assert_eq!(project.installed("left").as_deref(), Some("1.1.0"));
+    assert_eq!(project.installed("extra").as_deref(), Some("0.4.0"));
+    assert_eq!(project.installed("missing"), None);
+    assert_eq!(project.installed("tool"), None);
+
+    // The range of dependencies wins over the one of devDependencies
+    project.volt(&["install"]);
+    assert_eq!(project.installed("tool").as_deref(), Some("3.0.0"));
+    assert_eq!(project.installed("left").as_deref(), Some("1.1.0"));
+
+    // Dev dependencies installed earlier are pruned
+    project.volt(&["install", "--production"]);
+    assert_eq!(project.installed("tool"), None);
+    assert!(!read_to_string(project.dir().join("volt.lock"))
+        .unwrap()
+        .contains("tool@"));
+
+    // Every package stays in its field
+    let manifest: Value =
+        serde_json::from_str(&read_to_string(project.dir().join("package.json")).unwrap()).unwrap();
+    assert!(manifest["dependencies"]["tool"].is_null());
+    assert!(manifest["dependencies"]["extra"].is_null());
+    assert_eq!(manifest["dependencies"]["left"], "^1.0.0");
+    assert_eq!(manifest["devDependencies"]["left"], "^2.0.0");
+    assert!(manifest["devDependencies"]["tool"].is_string());
+    assert!(manifest["optionalDependencies"]["extra"].is_string());
+}
+
+#[test]
+fn runs_commands_after_global_flags() {
+    let registry = MockRegistry::new()
+        .publish(Package::new("left", "1.1.0"))
+        .start();
+
+    let project = Project::new("cwd", &registry, r#"{ "left": "^1.0.0" }"#);
+
+    for args in [
+        &["--cwd", "project", "install"][..],
+        &["--cwd=project", "--verbose", "install"][..],
+    ] {
+        let output = project.run_in(&project.root, args);
+        assert!(
+            output.status.success(),
+            "volt {} failed:\n{}{}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        );
+        assert_eq!(project.installed("left").as_deref(), Some("1.1.0"));
+
+        remove_dir_all(project.dir().join("node_modules")).unwrap();
+    }
+
+    // Nothing was written next to the project
+    assert!(!project.root.join("node_modules").exists());
+    assert!(!project.root.join("volt.lock").exists());
+}
@@ -19,35 +19,28 @@ use std::sync::Arc;
 use anyhow::{anyhow, Result};
 use async_trait::async_trait;
 use colored::Colorize;
-use volt_core::command::Command;
-use volt_core::VERSION;
+use volt_core::{command::Command, help::Help};
 use volt_utils::app::App;
+use volt_utils::reporter;
 
 pub struct Clone {}
 
 #[async_trait]
 impl Command for Clone {
     /// Display a help menu for the `volt clone` command.
-    fn help() -> String {
-        format!(
-            r#"volt {}
-    
-Clone a project and setup a project from a repository.
-Usage: {} {} {} {}
-Options: 
-    
-  {} {} Output verbose messages on internal operations.
-  {} {} Disable progress bar."#,
-            VERSION.bright_green().bold(),
-            "volt".bright_green().bold(),
-            "clone".bright_purple(),
-            "[repository]".white(),
-            "[flags]".white(),
-            "--verbose".blue(),
-            "(-v)".yellow(),
-            "--no-progress".blue(),
-            "(-np)".yellow()
+    fn help() -> Help {
+        Help::new(
+            "clone",
+            "Clone a project and setup a project from a repository.",
         )
+        .usage("[repository] [flags]")
+        .arg(
+            "[repository]",
+            "A github repository (user/repo) or a git url.",
+        )
+        .verbose()
+        .no_progress()
+        .example("volt clone facebook/react", "")
     }
 
     /// Execute the `volt clone` command
@@ -66,7 +59,7 @@ Options:
     async fn exec(app: Arc<App>) -> Result<()> {
         let args: Vec<String> = app.args.clone();
         if args.is_empty() {
-            println!("{} expected repository url", "error".bright_red());
+            reporter::get().output(&format!("{} expected repository url", "error".bright_red()));
         }
         let exit_code = process::Command::new("cmd")
             .arg(format!("/C git clone {} --depth=1", args[0]).as_str())
@@ -0,0 +1,80 @@
+/*
+Copyright 2021 Volt Contributors
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+    http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+//! Print the completion script of a shell.
+
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use volt_core::{command::Command, help::Help};
+use volt_utils::app::App;
+use volt_utils::reporter;
+
+use crate::scripts::{self, SHELLS};
+
+/// Struct implementation for the `completions` command.
+pub struct Completions;
+
+#[async_trait]
+impl Command for Completions {
+    /// Display a help menu for the `volt completions` command.
+    fn help() -> Help {
+        Help::new(
+            "completions",
+            "Print the completion script of a shell (bash, zsh, fish or powershell).
+
+Package names are completed for volt add, and scripts for volt run.",
+        )
+        .usage("<shell>")
+        .arg("<shell>", "bash, zsh, fish or powershell.")
+        .example("volt completions bash > /etc/bash_completion.d/volt", "")
+        .example("volt completions zsh > \"${fpath[1]}/_volt\"", "")
+        .example(
+            "volt completions fish > ~/.config/fish/completions/volt.fish",
+            "",
+        )
+        .example("volt completions powershell >> $PROFILE", "")
+    }
+
+    /// Execute the `volt completions` command
+    ///
+    /// Prints the completion script of a shell.
+    /// ## Arguments
+    /// * `app` - Instance of the command (`Arc<App>`)
+    /// ## Examples
+    /// ```ignore
+    /// // .exec() is an async call so you need to await it
+    /// Completions.exec(app).await;
+    /// ```
+    /// ## Returns
+    /// * `Result<()>`
+    async fn exec(app: Arc<App>) -> Result<()> {
+        let shell = app
+            .args
+            .get(1)
+            .ok_or_else(|| anyhow!("missing shell, one of {}", SHELLS.join(", ")))?;
+
+        let script = scripts::script(shell).ok_or_else(|| {
+            anyhow!(
+                "no completions for {}, use one of {}",
+                shell,
+                SHELLS.join(", ")
+            )
+        })?;
+
+        reporter::get().document(script.trim_end());
+
+        Ok(())
+    }
+}
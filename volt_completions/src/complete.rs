@@ -0,0 +1,163 @@
+/*
+Copyright 2021 Volt Contributors
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+    http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+//! Suggest arguments for the completion scripts (`volt __complete <command> <word>`).
+//!
+//! Prints one candidate per line: commands without arguments, package names for `add` from
+//! the metadata cache and the registry search, and package.json scripts for `run`.
+
+use std::collections::BTreeSet;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use volt_core::{command::Command, help::Help};
+use volt_utils::{app::App, metadata_db::MetadataDb, package::PackageJson, registry, reporter};
+
+/// Commands completed as the first word, keep in sync with volt_cli.
+pub const COMMANDS: [&str; 49] = [
+    "add",
+    "audit",
+    "bugs",
+    "cache",
+    "check-engines",
+    "clean",
+    "clone",
+    "completions",
+    "compress",
+    "config",
+    "create",
+    "deploy",
+    "diff",
+    "dockerfile",
+    "docs",
+    "env",
+    "exec",
+    "explain",
+    "fetch",
+    "fix",
+    "fund",
+    "help",
+    "info",
+    "init",
+    "inspect",
+    "install",
+    "lint-manifest",
+    "list",
+    "lock",
+    "migrate",
+    "outdated",
+    "pin",
+    "ping",
+    "publish",
+    "remove",
+    "repo",
+    "run",
+    "search",
+    "stat",
+    "stats",
+    "store",
+    "ui",
+    "unpin",
+    "upgrade",
+    "view-tarball",
+    "watch",
+    "whoami",
+    "why",
+    "x",
+];
+
+/// Completions are typed interactively, a slow registry is skipped.
+const REGISTRY_TIMEOUT: Duration = Duration::from_millis(800);
+
+/// Struct implementation for the hidden `__complete` command.
+pub struct Complete;
+
+#[async_trait]
+impl Command for Complete {
+    fn help() -> Help {
+        Help::new(
+            "__complete",
+            "Complete the last word of a command line, used by the completion scripts.",
+        )
+        .usage("[command] [word]")
+    }
+
+    /// Execute the `volt __complete` command
+    ///
+    /// Prints the candidates for the word being completed.
+    /// ## Arguments
+    /// * `app` - Instance of the command (`Arc<App>`)
+    /// ## Examples
+    /// ```ignore
+    /// // .exec() is an async call so you need to await it
+    /// Complete.exec(app).await;
+    /// ```
+    /// ## Returns
+    /// * `Result<()>`
+    async fn exec(app: Arc<App>) -> Result<()> {
+        let word = app.args.get(2).map(String::as_str).unwrap_or_default();
+
+        let candidates = match app.args.get(1).map(String::as_str) {
+            None => COMMANDS.iter().map(|command| command.to_string()).collect(),
+            Some("add") => packages(&app, word).await,
+            Some("run") => scripts(&app, word),
+            Some(_) => vec![],
+        };
+
+        for candidate in candidates {
+            reporter::get().document(&candidate.to_string());
+        }
+
+        Ok(())
+    }
+}
+
+/// Package names starting with `prefix`, cached ones first.
+async fn packages(app: &App, prefix: &str) -> Vec<String> {
+    let mut names: BTreeSet<String> = MetadataDb::from_app(app)
+        .map(|db| db.names(prefix))
+        .unwrap_or_default()
+        .into_iter()
+        .collect();
+
+    // Every package matches an empty prefix, too many to be useful
+    if !prefix.is_empty() && !app.has_flag(&["--offline"]) {
+        if let Ok(suggestions) =
+            registry::suggest(&registry::url(app), prefix, REGISTRY_TIMEOUT).await
+        {
+            names.extend(suggestions);
+        }
+    }
+
+    names.into_iter().collect()
+}
+
+/// Scripts of the project starting with `prefix`.
+fn scripts(app: &App, prefix: &str) -> Vec<String> {
+    let path = app.current_dir.join("package.json");
+
+    if !path.exists() {
+        return vec![];
+    }
+
+    let mut names: Vec<String> = PackageJson::from(&path.to_string_lossy())
+        .scripts
+        .into_keys()
+        .filter(|name| name.starts_with(prefix))
+        .collect();
+
+    names.sort();
+    names
+}
@@ -0,0 +1,90 @@
+/*
+Copyright 2021 Volt Contributors
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+    http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+//! Completion scripts for the shells volt supports.
+//!
+//! Commands and flags are completed by the scripts, arguments are asked from the hidden
+//! `volt __complete <command> <word>` command.
+
+pub const SHELLS: [&str; 4] = ["bash", "zsh", "fish", "powershell"];
+
+const BASH: &str = r#"_volt() {
+    local cur="${COMP_WORDS[COMP_CWORD]}"
+
+    if [ "$COMP_CWORD" -eq 1 ]; then
+        COMPREPLY=($(compgen -W "$(volt __complete 2>/dev/null)" -- "$cur"))
+    elif [[ "$cur" != -* ]]; then
+        COMPREPLY=($(volt __complete "${COMP_WORDS[1]}" "$cur" 2>/dev/null))
+    fi
+}
+
+complete -o default -F _volt volt
+"#;
+
+const ZSH: &str = r#"#compdef volt
+
+_volt() {
+    local -a candidates
+
+    if (( CURRENT == 2 )); then
+        candidates=(${(f)"$(volt __complete 2>/dev/null)"})
+    elif [[ "${words[CURRENT]}" != -* ]]; then
+        candidates=(${(f)"$(volt __complete "${words[2]}" "${words[CURRENT]}" 2>/dev/null)"})
+    fi
+
+    compadd -a candidates
+}
+
+compdef _volt volt
+"#;
+
+const FISH: &str = r#"function __volt_complete
+    set -l words (commandline -opc)
+
+    if test (count $words) -eq 1
+        volt __complete 2>/dev/null
+    else
+        volt __complete $words[2] (commandline -ct) 2>/dev/null
+    end
+end
+
+complete -c volt -f -a '(__volt_complete)'
+"#;
+
+const POWERSHELL: &str = r#"Register-ArgumentCompleter -Native -CommandName volt -ScriptBlock {
+    param($wordToComplete, $commandAst, $cursorPosition)
+
+    $words = @($commandAst.CommandElements | ForEach-Object { $_.ToString() })
+
+    if ($words.Count -eq 1 -or ($words.Count -eq 2 -and $wordToComplete)) {
+        $candidates = volt __complete 2>$null
+    } elseif (-not $wordToComplete.StartsWith('-')) {
+        $candidates = volt __complete $words[1] "$wordToComplete" 2>$null
+    }
+
+    $candidates | Where-Object { $_ -like "$wordToComplete*" } | ForEach-Object {
+        [System.Management.Automation.CompletionResult]::new($_, $_, 'ParameterValue', $_)
+    }
+}
+"#;
+
+/// The completion script of `shell`.
+pub fn script(shell: &str) -> Option<&'static str> {
+    match shell {
+        "bash" => Some(BASH),
+        "zsh" => Some(ZSH),
+        "fish" => Some(FISH),
+        "powershell" | "pwsh" => Some(POWERSHELL),
+        _ => None,
+    }
+}
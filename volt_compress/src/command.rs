@@ -17,34 +17,18 @@ use std::sync::Arc;
 
 use anyhow::Result;
 use async_trait::async_trait;
-use colored::Colorize;
-use volt_core::{command::Command, VERSION};
+use volt_core::{command::Command, help::Help};
 use volt_utils::app::App;
 pub struct Compress {}
 
 #[async_trait]
 impl Command for Compress {
     /// Display a help menu for the `volt compress` command.
-    fn help() -> String {
-        format!(
-            r#"volt {}
-    
-Compress node_modules into node_modules.pack.
-Usage: {} {} {} {}
-Options: 
-    
-  {} {} Output verbose messages on internal operations.
-  {} {} Disable progress bar."#,
-            VERSION.bright_green().bold(),
-            "volt".bright_green().bold(),
-            "clone".bright_purple(),
-            "[repository]".white(),
-            "[flags]".white(),
-            "--verbose".blue(),
-            "(-v)".yellow(),
-            "--no-progress".blue(),
-            "(-np)".yellow()
-        )
+    fn help() -> Help {
+        Help::new("compress", "Compress node_modules into node_modules.pack.")
+            .usage("[flags]")
+            .verbose()
+            .no_progress()
     }
 
     /// Execute the `volt compress` command
@@ -0,0 +1,163 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Switch between the config profiles of the user `.voltrc`.
+//!
+//! Profiles are `profile.<name>.<key>=<value>` lines of the user config (see
+//! [config](volt_utils::config)). `volt config use <name>` records the choice as `profile=<name>`
+//! and `volt config use default` goes back to the settings outside of profiles.
+
+use std::process::exit;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use colored::Colorize;
+use volt_core::{command::Command, help::Help};
+use volt_utils::{
+    app::App,
+    config::{self, PROFILE_ENV},
+    reporter,
+};
+
+/// The name going back to the settings outside of profiles.
+const DEFAULT_PROFILE: &str = "default";
+
+/// Print the profiles, marking the one in use.
+fn list(app: &App) {
+    let profiles = app.config.profiles();
+
+    if profiles.is_empty() {
+        reporter::get().output(&format!(
+            "No profiles in {}, add profile.<name>.<key>=<value> lines to define them",
+            app.config_file.display()
+        ));
+        return;
+    }
+
+    for (name, values) in profiles.iter() {
+        let active = app.config.profile() == Some(name.as_str());
+
+        reporter::get().output(&format!(
+            "{} {}",
+            if active { "*" } else { " " },
+            if active {
+                name.bright_green().bold()
+            } else {
+                name.normal()
+            }
+        ));
+
+        for (key, value) in values.iter() {
+            reporter::get().output(&format!("    {}={}", key.dimmed(), value));
+        }
+    }
+}
+
+/// Make `profile` the one used from now on.
+fn use_profile(app: &App, profile: &str) -> Result<()> {
+    if profile == DEFAULT_PROFILE {
+        config::remove_from_file(&app.config_file, "profile")?;
+        reporter::get().output(&format!("{} the default settings", "Using".bright_green()));
+    } else {
+        if !app.config.profiles().contains_key(profile) {
+            return Err(anyhow!(
+                "the profile {} is not defined in {}",
+                profile,
+                app.config_file.display()
+            ));
+        }
+
+        if let Some(dir) = app.config_file.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+
+        config::set_in_file(&app.config_file, "profile", profile)?;
+        reporter::get().output(&format!(
+            "{} the {} profile",
+            "Using".bright_green(),
+            profile.bright_cyan()
+        ));
+    }
+
+    if let Ok(overridden) = std::env::var(PROFILE_ENV) {
+        if !overridden.is_empty() {
+            reporter::get().warn(&format!(
+                "{}={} is set and takes precedence",
+                PROFILE_ENV, overridden
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Struct implementation for the `config` command.
+pub struct Config;
+
+#[async_trait]
+impl Command for Config {
+    /// Display a help menu for the `volt config` command.
+    fn help() -> Help {
+        Help::new(
+            "config",
+            "Switch between the config profiles of your .voltrc.",
+        )
+        .usage("[command] [flags]")
+        .command("profiles", "List the profiles, marking the one in use.")
+        .command(
+            "use <profile>",
+            "Use a profile from now on, `default` for no profile.",
+        )
+        .verbose()
+        .example(
+            "volt config use work",
+            "Use the settings of profile.work.* lines.",
+        )
+        .example(
+            "VOLT_PROFILE=oss volt install",
+            "Use the oss profile for one command.",
+        )
+    }
+
+    /// Execute the `volt config` command
+    ///
+    /// Lists or switches the config profiles.
+    /// ## Arguments
+    /// * `app` - Instance of the command (`Arc<App>`)
+    /// ## Examples
+    /// ```ignore
+    /// // .exec() is an async call so you need to await it
+    /// Config.exec(app).await;
+    /// ```
+    /// ## Returns
+    /// * `Result<()>`
+    async fn exec(app: Arc<App>) -> Result<()> {
+        match (
+            app.args.get(1).map(String::as_str),
+            app.args.get(2).map(String::as_str),
+        ) {
+            (Some("profiles"), _) => list(&app),
+            (Some("use"), Some(profile)) => use_profile(&app, profile)?,
+            _ => {
+                reporter::get().output(&Self::help().to_string());
+                exit(1);
+            }
+        }
+
+        Ok(())
+    }
+}
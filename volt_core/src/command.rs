@@ -21,9 +21,11 @@ use async_trait::async_trait;
 
 use volt_utils::app::App;
 
+use crate::help::Help;
+
 #[async_trait]
 pub trait Command {
-    fn help() -> String;
+    fn help() -> Help;
 
     async fn exec(app: Arc<App>) -> Result<()>;
 }
@@ -0,0 +1,252 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! The help of a command, shown by `volt help <command>` and `volt <command> --help`.
+//!
+//! Commands describe their usage, arguments, subcommands, flags and examples, and every
+//! help is rendered with the same layout, columns aligned and `--help` listed last.
+
+use std::fmt;
+
+use colored::{ColoredString, Colorize};
+
+use crate::VERSION;
+
+/// A flag of a command, with its aliases (`["--verbose", "-v"]`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Flag {
+    pub names: Vec<String>,
+    pub description: String,
+}
+
+/// The help of a command.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Help {
+    pub command: String,
+    pub description: String,
+    /// What follows `volt <command>`, one line per form
+    pub usage: Vec<String>,
+    pub args: Vec<(String, String)>,
+    pub commands: Vec<(String, String)>,
+    pub flags: Vec<Flag>,
+    pub examples: Vec<(String, String)>,
+}
+
+impl Help {
+    pub fn new(command: &str, description: &str) -> Self {
+        Help {
+            command: command.to_string(),
+            description: description.to_string(),
+            ..Default::default()
+        }
+    }
+
+    pub fn usage(mut self, usage: &str) -> Self {
+        self.usage.push(usage.to_string());
+        self
+    }
+
+    pub fn arg(mut self, name: &str, description: &str) -> Self {
+        self.args.push((name.to_string(), description.to_string()));
+        self
+    }
+
+    pub fn command(mut self, name: &str, description: &str) -> Self {
+        self.commands
+            .push((name.to_string(), description.to_string()));
+        self
+    }
+
+    pub fn flag(mut self, names: &[&str], description: &str) -> Self {
+        self.flags.push(Flag {
+            names: names.iter().map(|name| name.to_string()).collect(),
+            description: description.to_string(),
+        });
+        self
+    }
+
+    /// The `--verbose` flag most commands take.
+    pub fn verbose(self) -> Self {
+        self.flag(
+            &["--verbose", "-v"],
+            "Output verbose messages on internal operations.",
+        )
+    }
+
+    /// The `--no-progress` flag of commands showing a progress bar.
+    pub fn no_progress(self) -> Self {
+        self.flag(&["--no-progress", "-np"], "Disable progress bar.")
+    }
+
+    pub fn example(mut self, command: &str, description: &str) -> Self {
+        self.examples
+            .push((command.to_string(), description.to_string()));
+        self
+    }
+}
+
+/// Write `rows` below `title`, the descriptions lined up after the widest name.
+fn section(
+    f: &mut fmt::Formatter<'_>,
+    title: &str,
+    rows: &[(String, ColoredString, &str)],
+) -> fmt::Result {
+    if rows.is_empty() {
+        return Ok(());
+    }
+
+    let width = rows
+        .iter()
+        .map(|(plain, _, _)| plain.chars().count())
+        .max()
+        .unwrap_or(0);
+
+    write!(f, "\n\n{}:\n", title)?;
+
+    for (plain, colored, description) in rows {
+        if description.is_empty() {
+            write!(f, "\n  {}", colored)?;
+        } else {
+            let padding = " ".repeat(width - plain.chars().count());
+            write!(f, "\n  {}{}  {}", colored, padding, description)?;
+        }
+    }
+
+    Ok(())
+}
+
+impl fmt::Display for Help {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "volt {}\n\n{}\n",
+            VERSION.bright_green().bold(),
+            self.description
+        )?;
+
+        let usage = if self.usage.is_empty() {
+            vec![String::new()]
+        } else {
+            self.usage.clone()
+        };
+
+        for (index, usage) in usage.iter().enumerate() {
+            let prefix = if index == 0 { "\nUsage:" } else { "\n      " };
+            write!(
+                f,
+                "{} {} {}",
+                prefix,
+                "volt".bright_green().bold(),
+                self.command.bright_purple()
+            )?;
+
+            if !usage.is_empty() {
+                write!(f, " {}", usage.white())?;
+            }
+        }
+
+        let args: Vec<_> = self
+            .args
+            .iter()
+            .map(|(name, description)| (name.clone(), name.white(), description.as_str()))
+            .collect();
+
+        let commands: Vec<_> = self
+            .commands
+            .iter()
+            .map(|(name, description)| (name.clone(), name.bright_blue(), description.as_str()))
+            .collect();
+
+        let help = Flag {
+            names: vec![String::from("--help"), String::from("-h")],
+            description: String::from("Show this help."),
+        };
+
+        let flags: Vec<_> = self
+            .flags
+            .iter()
+            .chain(Some(&help))
+            .map(|flag| {
+                let (name, aliases) = flag.names.split_first().expect("a flag without a name");
+
+                if aliases.is_empty() {
+                    (name.clone(), name.blue(), flag.description.as_str())
+                } else {
+                    let aliases = format!("({})", aliases.join(", "));
+                    (
+                        format!("{} {}", name, aliases),
+                        format!("{} {}", name.blue(), aliases.yellow()).normal(),
+                        flag.description.as_str(),
+                    )
+                }
+            })
+            .collect();
+
+        let examples: Vec<_> = self
+            .examples
+            .iter()
+            .map(|(command, description)| (command.clone(), command.normal(), description.as_str()))
+            .collect();
+
+        section(f, "Arguments", &args)?;
+        section(f, "Commands", &commands)?;
+        section(f, "Options", &flags)?;
+        section(f, "Examples", &examples)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_aligned_sections() {
+        colored::control::set_override(false);
+
+        let help = Help::new("why", "Show why a package is installed.")
+            .usage("[package] [flags]")
+            .arg("[package]", "The installed package.")
+            .flag(&["--reverse", "-r"], "List everything which depends on it.")
+            .flag(&["--json"], "Print the result as JSON.")
+            .example("volt why react", "");
+
+        assert_eq!(
+            help.to_string(),
+            format!(
+                r#"volt {}
+
+Show why a package is installed.
+
+Usage: volt why [package] [flags]
+
+Arguments:
+
+  [package]  The installed package.
+
+Options:
+
+  --reverse (-r)  List everything which depends on it.
+  --json          Print the result as JSON.
+  --help (-h)     Show this help.
+
+Examples:
+
+  volt why react"#,
+                VERSION
+            )
+        );
+    }
+}
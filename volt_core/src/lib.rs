@@ -18,6 +18,7 @@ extern crate lazy_static;
 
 pub mod classes;
 pub mod command;
+pub mod help;
 pub mod io;
 pub mod model;
 pub mod prompt;
@@ -16,3 +16,4 @@
 
 pub mod http_manager;
 pub mod lock_file;
+pub mod lock_graph;
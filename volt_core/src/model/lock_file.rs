@@ -24,13 +24,13 @@ use std::path::PathBuf;
 
 use serde::{de, ser, Deserialize, Deserializer, Serialize, Serializer};
 use thiserror::Error;
+use volt_utils::volt_api::{PackageSource, VersionData, VoltPackage, VoltResponse};
 
 #[derive(Error, Debug)]
 pub enum LockFileError {
     #[error("unable to read lock file")]
     IO(io::Error),
     #[error("unable to deserialize lock file")]
-    #[allow(dead_code)]
     Decode(serde_json::Error),
     #[error("unable to serialize lock file")]
     Encode(serde_json::Error),
@@ -63,10 +63,39 @@ pub enum LockFileError {
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct LockFile {
     pub path: PathBuf,
+    /// Hash of the package.json inputs the lock file was written for
+    pub manifest_hash: Option<String>,
+    /// The ranges `volt pin` replaced with exact versions, by dependency name
+    pub pins: BTreeMap<String, String>,
     #[serde(serialize_with = "sorted_dependencies")]
     pub dependencies: HashMap<DependencyID, DependencyLock>,
 }
 
+/// Contents of `volt.lock`, the dependencies with the manifest hash next to them.
+///
+/// Dependency keys always contain an `@`, so `manifestHash` can't clash with them.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct LockFileContents<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    manifest_hash: Option<&'a str>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pins: &'a BTreeMap<String, String>,
+    #[serde(flatten)]
+    dependencies: BTreeMap<&'a DependencyID, &'a DependencyLock>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LockFileData {
+    #[serde(default)]
+    manifest_hash: Option<String>,
+    #[serde(default)]
+    pins: BTreeMap<String, String>,
+    #[serde(flatten)]
+    dependencies: HashMap<DependencyID, DependencyLock>,
+}
+
 // #[derive(Clone, Serialize, Deserialize, Debug, Default)]
 // pub struct DependenciesMap(
 // );
@@ -96,7 +125,7 @@ impl<'de> de::Deserialize<'de> for DependencyID {
     where
         D: Deserializer<'de>,
     {
-        let s: &str = Deserialize::deserialize(deserializer)?;
+        let s: String = Deserialize::deserialize(deserializer)?;
         let mut parts = s.split('@');
         let name = parts
             .next()
@@ -135,30 +164,151 @@ impl PartialEq for DependencyID {
 
 impl Eq for DependencyID {}
 
-#[derive(Clone, Serialize, Deserialize, Debug)]
+#[derive(Clone, Serialize, Deserialize, Debug, Default)]
 pub struct DependencyLock {
     pub name: String,
     pub version: String,
     pub tarball: String,
     pub sha1: String,
+    /// The subresource integrity of the tarball (`sha512-<base64>`), kept from the lockfile of
+    /// another package manager
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub integrity: Option<String>,
+    /// The versions of its dependencies, or their ranges in lock files migrated from npm and
+    /// nothing in older ones
     pub dependencies: HashMap<String, String>,
+    /// Where the dependency was resolved from, the volt CDN for lock files which don't say
+    #[serde(default)]
+    pub source: PackageSource,
+    /// Its binaries, none in lock files written before they were recorded, which can't be
+    /// installed from without resolving again
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bin: Option<HashMap<String, String>>,
+    #[serde(
+        rename = "peerDependencies",
+        default,
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub peer_dependencies: Vec<String>,
+    #[serde(
+        rename = "optionalDependencies",
+        default,
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub optional_dependencies: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub os: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub cpu: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub libc: Vec<String>,
 }
 
+impl DependencyLock {
+    /// Lock `package`, with the versions its dependencies have among `packages`.
+    pub fn from_package(package: &VoltPackage, packages: &HashMap<String, VoltPackage>) -> Self {
+        let dependencies = package
+            .dependencies
+            .iter()
+            .flatten()
+            .map(|name| {
+                let version = packages
+                    .get(name)
+                    .map(|dependency| dependency.version.clone())
+                    .unwrap_or_default();
+
+                (name.clone(), version)
+            })
+            .collect();
+
+        Self {
+            name: package.name.clone(),
+            version: package.version.clone(),
+            tarball: package.tarball.clone(),
+            sha1: package.sha1.clone(),
+            integrity: package.integrity.clone(),
+            dependencies,
+            source: package.source,
+            bin: Some(package.bin.clone().unwrap_or_default()),
+            peer_dependencies: package.peer_dependencies.clone(),
+            optional_dependencies: package.optional_dependencies.clone(),
+            os: package.os.clone(),
+            cpu: package.cpu.clone(),
+            libc: package.libc.clone(),
+        }
+    }
+
+    /// The package to install, none if the lock file doesn't record enough to install it.
+    pub fn to_package(&self) -> Option<VoltPackage> {
+        let bin = self.bin.clone()?;
+
+        let mut dependencies: Vec<String> = self.dependencies.keys().cloned().collect();
+        dependencies.sort();
+
+        Some(VoltPackage {
+            name: self.name.clone(),
+            version: self.version.clone(),
+            tarball: self.tarball.clone(),
+            sha1: self.sha1.clone(),
+            integrity: self.integrity.clone(),
+            peer_dependencies: self.peer_dependencies.clone(),
+            dependencies: Some(dependencies).filter(|dependencies| !dependencies.is_empty()),
+            optional_dependencies: self.optional_dependencies.clone(),
+            bin: Some(bin).filter(|bin| !bin.is_empty()),
+            os: self.os.clone(),
+            cpu: self.cpu.clone(),
+            libc: self.libc.clone(),
+            source: self.source,
+            deprecated: None,
+            engines: HashMap::new(),
+        })
+    }
+}
+
+/// A name, version and the versions of its dependencies, see [`LockFile::from_entries()`].
+#[cfg(any(test, feature = "test-support"))]
+type Entry<'a> = (&'a str, &'a str, &'a [(&'a str, &'a str)]);
+
 impl LockFile {
     /// Creates a new instance of a lock file with a path it should be saved at.
     /// It can be saved to the file by calling [`Self::save()`].
     pub fn new(path: PathBuf) -> Self {
         Self {
             path,
+            manifest_hash: None,
+            pins: BTreeMap::new(),
             dependencies: HashMap::with_capacity(1), // We will be installing at least 1 dependency
         }
     }
 
+    /// A lock file at `volt.lock` with `(name, version, dependencies)` entries, for the tests of
+    /// the commands reading lock files, with the `test-support` feature.
+    #[cfg(any(test, feature = "test-support"))]
+    pub fn from_entries(entries: &[Entry]) -> Self {
+        let mut lock_file = Self::new(PathBuf::from("volt.lock"));
+
+        for (name, version, dependencies) in entries {
+            lock_file.dependencies.insert(
+                DependencyID(name.to_string(), version.to_string()),
+                DependencyLock {
+                    name: name.to_string(),
+                    version: version.to_string(),
+                    dependencies: dependencies
+                        .iter()
+                        .map(|(name, version)| (name.to_string(), version.to_string()))
+                        .collect(),
+                    bin: Some(HashMap::new()),
+                    ..DependencyLock::default()
+                },
+            );
+        }
+
+        lock_file
+    }
+
     /// Loads a lock file from the given path.
     pub fn load(path: PathBuf) -> Result<Self, LockFileError> {
         let lock_file = std::fs::read_to_string(path.clone()).map_err(LockFileError::IO)?;
-        let data =
-            serde_json::from_str::<HashMap<DependencyID, DependencyLock>>(&lock_file).unwrap();
         // let lock_file = File::open(&path).map_err(LockFileError::IO)?;
         // let reader = BufReader::new(lock_file);
 
@@ -166,9 +316,79 @@ impl LockFile {
 
         // println!("reader: {:?}", data);
 
+        Self::parse(path, &lock_file)
+    }
+
+    /// Parses the contents of a lock file which was read elsewhere, from a git revision for instance.
+    pub fn parse(path: PathBuf, contents: &str) -> Result<Self, LockFileError> {
+        let data = serde_json::from_str::<LockFileData>(contents).map_err(LockFileError::Decode)?;
+
         Ok(LockFile {
             path,
-            dependencies: data,
+            manifest_hash: data.manifest_hash,
+            pins: data.pins,
+            dependencies: data.dependencies,
+        })
+    }
+
+    /// The locked version of `name` which `range` accepts, the highest if there are several.
+    fn locked(&self, name: &str, range: &str) -> Option<&DependencyLock> {
+        let candidates: Vec<&DependencyLock> = self
+            .dependencies
+            .values()
+            .filter(|dependency| dependency.name == name)
+            .collect();
+
+        // Older lock files don't say which version a dependency needs
+        if range.is_empty() {
+            return match candidates.as_slice() {
+                [only] => Some(*only),
+                _ => None,
+            };
+        }
+
+        let version = volt_utils::max_satisfying(
+            candidates.iter().map(|dependency| &dependency.version),
+            range,
+        )?;
+
+        candidates
+            .into_iter()
+            .find(|dependency| dependency.version == version)
+    }
+
+    /// The resolution of `name` at a version `range` accepts, built from the lock file alone,
+    /// or none when the lock file doesn't have every package it needs.
+    ///
+    /// Optional dependencies which were skipped on the platform the lock file was written on
+    /// are left out.
+    pub fn resolution(&self, name: &str, range: &str) -> Option<VoltResponse> {
+        let root = self.locked(name, range)?;
+        let mut packages: HashMap<String, VoltPackage> = HashMap::new();
+        let mut queue = vec![root];
+
+        while let Some(dependency) = queue.pop() {
+            if packages.contains_key(&dependency.name) {
+                continue;
+            }
+
+            packages.insert(dependency.name.clone(), dependency.to_package()?);
+
+            for (child, range) in dependency.dependencies.iter() {
+                match self.locked(child, range) {
+                    Some(locked) => queue.push(locked),
+                    None if dependency.optional_dependencies.contains(child) => {}
+                    None => return None,
+                }
+            }
+        }
+
+        let mut versions = HashMap::new();
+        versions.insert(root.version.clone(), VersionData { packages });
+
+        Some(VoltResponse {
+            version: root.version.clone(),
+            versions,
         })
     }
 
@@ -176,6 +396,41 @@ impl LockFile {
     pub fn save(&self) -> Result<(), LockFileError> {
         let lock_file = File::create(&self.path).map_err(LockFileError::IO)?;
         let writer = BufWriter::new(lock_file);
-        serde_json::to_writer_pretty(writer, &self.dependencies).map_err(LockFileError::Encode)
+        let contents = LockFileContents {
+            manifest_hash: self.manifest_hash.as_deref(),
+            pins: &self.pins,
+            dependencies: self.dependencies.iter().collect(),
+        };
+
+        serde_json::to_writer_pretty(writer, &contents).map_err(LockFileError::Encode)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_from_the_lock_file_alone() {
+        let mut lock_file = LockFile::from_entries(&[
+            ("express", "4.18.2", &[("debug", "2.6.9")]),
+            ("debug", "2.6.9", &[("ms", "2.0.0")]),
+            ("ms", "2.0.0", &[]),
+            ("left-pad", "1.3.0", &[]),
+        ]);
+
+        // Written before binaries were locked
+        let left_pad = DependencyID(String::from("left-pad"), String::from("1.3.0"));
+        lock_file.dependencies.get_mut(&left_pad).unwrap().bin = None;
+
+        let response = lock_file.resolution("express", "^4.17.0").unwrap();
+        assert_eq!(response.version, "4.18.2");
+
+        let mut names: Vec<&String> = response.versions["4.18.2"].packages.keys().collect();
+        names.sort();
+        assert_eq!(names, vec!["debug", "express", "ms"]);
+
+        assert!(lock_file.resolution("express", "^5.0.0").is_none());
+        assert!(lock_file.resolution("left-pad", "^1.3.0").is_none());
     }
 }
@@ -0,0 +1,280 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+
+use serde::Serialize;
+
+use super::lock_file::LockFile;
+
+/// A resolved package of the lock file.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+pub struct Node {
+    pub name: String,
+    pub version: String,
+}
+
+/// A package which depends on another, directly or through other packages.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Dependent {
+    pub name: String,
+    pub version: String,
+    /// The package it asks for, `range` of which is recorded in the lock file
+    pub requires: Node,
+    pub range: String,
+    /// 1 for packages depending on the queried one directly
+    pub depth: usize,
+}
+
+/// An indexed view of the dependency graph recorded in a lock file.
+///
+/// Lock entries only record the range they ask for, each edge goes to the highest version of
+/// the package in the lock file which satisfies it.
+///
+/// ## Examples
+///
+/// ```ignore
+/// let graph = LockGraph::new(&lock_file);
+///
+/// // Everything which ends up requiring `ms`
+/// for dependent in graph.dependents_of("ms") {
+///     println!("{}@{} requires {}", dependent.name, dependent.version, dependent.range);
+/// }
+/// ```
+pub struct LockGraph {
+    nodes: Vec<Node>,
+    by_name: HashMap<String, Vec<usize>>,
+    /// Node → (dependency, range)
+    dependencies: Vec<Vec<(usize, String)>>,
+    /// Node → (dependent, range)
+    dependents: Vec<Vec<(usize, String)>>,
+}
+
+impl LockGraph {
+    pub fn new(lock_file: &LockFile) -> Self {
+        let ordered: BTreeMap<Node, &HashMap<String, String>> = lock_file
+            .dependencies
+            .values()
+            .map(|lock| {
+                (
+                    Node {
+                        name: lock.name.clone(),
+                        version: lock.version.clone(),
+                    },
+                    &lock.dependencies,
+                )
+            })
+            .collect();
+
+        let nodes: Vec<Node> = ordered.keys().cloned().collect();
+
+        let mut by_name: HashMap<String, Vec<usize>> = HashMap::new();
+
+        for (index, node) in nodes.iter().enumerate() {
+            by_name.entry(node.name.clone()).or_default().push(index);
+        }
+
+        let mut graph = LockGraph {
+            dependencies: vec![vec![]; nodes.len()],
+            dependents: vec![vec![]; nodes.len()],
+            nodes,
+            by_name,
+        };
+
+        for (from, requests) in ordered.values().enumerate() {
+            let mut requests: Vec<(&String, &String)> = requests.iter().collect();
+            requests.sort();
+
+            for (name, range) in requests {
+                if let Some(to) = graph.resolve(name, range) {
+                    graph.dependencies[from].push((to, range.clone()));
+                    graph.dependents[to].push((from, range.clone()));
+                }
+            }
+        }
+
+        graph
+    }
+
+    pub fn node(&self, index: usize) -> &Node {
+        &self.nodes[index]
+    }
+
+    /// Every package of the lock file, indexed like the nodes.
+    pub fn nodes(&self) -> &[Node] {
+        &self.nodes
+    }
+
+    /// Versions of `name` in the lock file.
+    pub fn find(&self, name: &str) -> &[usize] {
+        self.by_name.get(name).map_or(&[], Vec::as_slice)
+    }
+
+    /// The version of `name` which `range` resolves to.
+    pub fn resolve(&self, name: &str, range: &str) -> Option<usize> {
+        let candidates = self.find(name);
+
+        if candidates.len() <= 1 {
+            return candidates.first().copied();
+        }
+
+        let versions: Vec<String> = candidates
+            .iter()
+            .map(|index| self.nodes[*index].version.clone())
+            .collect();
+
+        // Unknown ranges (older lock files record none) resolve to the highest version
+        let range = if range.is_empty() { "*" } else { range };
+        let version = volt_utils::max_satisfying(versions.iter(), range)
+            .or_else(|| volt_utils::max_satisfying(versions.iter(), "*"))?;
+
+        candidates
+            .iter()
+            .copied()
+            .find(|index| self.nodes[*index].version == version)
+    }
+
+    /// Direct dependencies of `node`, with the ranges it asks for.
+    pub fn dependencies(&self, node: usize) -> &[(usize, String)] {
+        &self.dependencies[node]
+    }
+
+    /// Everything which depends on any version of `name`, directly or transitively, nearest
+    /// first.
+    pub fn dependents_of(&self, name: &str) -> Vec<Dependent> {
+        let mut queue: VecDeque<(usize, usize)> =
+            self.find(name).iter().map(|index| (*index, 0)).collect();
+        let mut seen: HashSet<usize> = self.find(name).iter().copied().collect();
+        let mut dependents = vec![];
+
+        while let Some((node, depth)) = queue.pop_front() {
+            for (dependent, range) in self.dependents[node].iter() {
+                dependents.push(Dependent {
+                    name: self.nodes[*dependent].name.clone(),
+                    version: self.nodes[*dependent].version.clone(),
+                    requires: self.nodes[node].clone(),
+                    range: range.clone(),
+                    depth: depth + 1,
+                });
+
+                if seen.insert(*dependent) {
+                    queue.push_back((*dependent, depth + 1));
+                }
+            }
+        }
+
+        dependents
+    }
+
+    /// Chains of packages from `roots` down to any version of `name`, shortest first.
+    ///
+    /// Each package appears once per chain, so cycles end the chain.
+    pub fn paths_to(&self, roots: &[usize], name: &str, limit: usize) -> Vec<Vec<usize>> {
+        let targets: HashSet<usize> = self.find(name).iter().copied().collect();
+        let mut queue: VecDeque<Vec<usize>> = roots.iter().map(|root| vec![*root]).collect();
+        let mut paths = vec![];
+
+        while let Some(path) = queue.pop_front() {
+            if paths.len() >= limit {
+                break;
+            }
+
+            let last = *path.last().unwrap_or(&0);
+
+            if targets.contains(&last) {
+                paths.push(path);
+                continue;
+            }
+
+            for (dependency, _) in self.dependencies[last].iter() {
+                if !path.contains(dependency) {
+                    let mut next = path.clone();
+                    next.push(*dependency);
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        paths
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lock_file() -> LockFile {
+        LockFile::from_entries(&[
+            (
+                "express",
+                "4.18.2",
+                &[("debug", "2.6.9"), ("body-parser", "1.20.1")],
+            ),
+            ("body-parser", "1.20.1", &[("debug", "2.6.9")]),
+            ("debug", "2.6.9", &[("ms", "2.0.0")]),
+            ("debug", "4.3.4", &[("ms", "2.1.2")]),
+            ("ms", "2.0.0", &[]),
+            ("ms", "2.1.2", &[]),
+        ])
+    }
+
+    #[test]
+    fn finds_dependents_and_paths() {
+        let graph = LockGraph::new(&lock_file());
+
+        let dependents: Vec<(String, usize)> = graph
+            .dependents_of("ms")
+            .into_iter()
+            .map(|dependent| {
+                (
+                    format!("{}@{}", dependent.name, dependent.version),
+                    dependent.depth,
+                )
+            })
+            .collect();
+
+        assert_eq!(
+            dependents,
+            vec![
+                (String::from("debug@2.6.9"), 1),
+                (String::from("debug@4.3.4"), 1),
+                (String::from("body-parser@1.20.1"), 2),
+                (String::from("express@4.18.2"), 2),
+                (String::from("express@4.18.2"), 3),
+            ]
+        );
+
+        let express = graph.find("express")[0];
+        let paths = graph.paths_to(&[express], "ms", 10);
+
+        let names: Vec<Vec<&str>> = paths
+            .iter()
+            .map(|path| {
+                path.iter()
+                    .map(|node| graph.node(*node).name.as_str())
+                    .collect()
+            })
+            .collect();
+
+        assert_eq!(
+            names,
+            vec![
+                vec!["express", "debug", "ms"],
+                vec!["express", "body-parser", "debug", "ms"]
+            ]
+        );
+    }
+}
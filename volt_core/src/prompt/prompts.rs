@@ -227,3 +227,30 @@ impl Select {
         input.interact()
     }
 }
+
+/// Prompt that allows the user to check any number of options
+#[derive(Debug, StructOpt)]
+pub struct MultiSelect {
+    /// Message for the prompt
+    #[structopt(short, long)]
+    pub message: String,
+
+    /// Items that can be checked
+    pub items: Vec<String>,
+}
+
+impl MultiSelect {
+    /// Returns the indices of the checked items.
+    pub fn run(&self) -> Result<Vec<usize>> {
+        if self.items.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let theme = ColorfulTheme::default();
+
+        dialoguer::MultiSelect::with_theme(&theme)
+            .with_prompt(&self.message)
+            .items(&self.items)
+            .interact()
+    }
+}
@@ -29,38 +29,23 @@ use dialoguer::Input;
 use flate2::read::GzDecoder;
 use tar::Archive;
 use volt_core::{
-    classes::create_templates::Template, command::Command, model::http_manager::get_package,
-    prompt::prompts::Select, VERSION,
+    classes::create_templates::Template, command::Command, help::Help,
+    model::http_manager::get_package, prompt::prompts::Select,
 };
 use volt_utils::app::App;
+use volt_utils::reporter;
 /// Struct implementation for the `Remove` command.
 pub struct Create;
 
 #[async_trait]
 impl Command for Create {
     /// Display a help menu for the `volt create` command.
-    fn help() -> String {
-        format!(
-            r#"volt {}
-    
-Creates a project from a template.
-
-Usage: {} {} {} {}
-
-Options: 
-
-  {} {} Output the version number.
-  {} {} Output verbose messages on internal operations."#,
-            VERSION.bright_green().bold(),
-            "volt".bright_green().bold(),
-            "create".bright_purple(),
-            "[template]".white(),
-            "[flags]".white(),
-            "--version".blue(),
-            "(-ver)".yellow(),
-            "--verbose".blue(),
-            "(-v)".yellow()
-        )
+    fn help() -> Help {
+        Help::new("create", "Creates a project from a template.")
+            .usage("[template] [flags]")
+            .arg("[template]", "The template to create the project from.")
+            .flag(&["--version", "-ver"], "Output the version number.")
+            .verbose()
     }
 
     /// Execute the `volt create` command
@@ -110,11 +95,11 @@ Options:
             if templates.contains(_template) {
                 template = _template.to_string();
             } else {
-                println!(
+                reporter::get().output(&format!(
                     "{} Template {} doesn't exist!",
                     "error".bright_red(),
                     _template.bright_blue()
-                );
+                ));
                 process::exit(1);
             }
         }
@@ -127,7 +112,7 @@ Options:
                 .interact_text()?;
 
             if app_name.is_empty() {
-                println!("{} Invalid app name!", "error".bright_red());
+                reporter::get().output(&format!("{} Invalid app name!", "error".bright_red()));
                 process::exit(1);
             }
         } else {
@@ -138,11 +123,11 @@ Options:
         let template_name = template.split('-').collect::<Vec<&str>>()[0];
         let version = "create-".to_owned() + template_name;
         let package_json = get_package(&version).await?.unwrap_or_else(|| {
-            println!(
+            reporter::get().output(&format!(
                 "{} Could not find template for {}",
                 "error".red().bold(),
                 template_name
-            );
+            ));
             exit(1)
         });
         // For dev checking
@@ -150,15 +135,15 @@ Options:
             .versions
             .get(&package_json.dist_tags.latest)
             .unwrap_or_else(|| {
-                println!(
+                reporter::get().output(&format!(
                     "{} Could not find template version for {}",
                     "error".red().bold(),
                     template_name
-                );
+                ));
                 exit(1)
             });
 
-        println!("HANDLE THIS");
+        reporter::get().output("HANDLE THIS");
         let tarball_file = volt_utils::download_tarball_create(&app, &package_json, &version)
             .await
             .unwrap();
@@ -168,10 +153,8 @@ Options:
                 .unwrap(),
         );
         let mut archive = Archive::new(gz_decoder);
-        let mut dir = std::env::current_dir().unwrap();
-
         archive
-            .unpack(&dir.join(app_name))
+            .unpack(&app.current_dir.join(app_name))
             .context("Unable to unpack dependency")?;
         Ok(())
     }
@@ -1,5 +1,7 @@
 #![allow(dead_code)]
 
+use volt_utils::reporter;
+
 pub fn create_next_app() {
-    println!("creating next app")
+    reporter::get().output("creating next app")
 }
@@ -1,5 +1,7 @@
 #![allow(dead_code)]
 
+use volt_utils::reporter;
+
 pub fn create_next_app_ts() {
-    println!("creating next typescript app")
+    reporter::get().output("creating next typescript app")
 }
@@ -2,29 +2,30 @@ use colored::Colorize;
 use tokio::fs;
 use volt_core::command::Command;
 use volt_utils::app::App;
+use volt_utils::reporter;
 
 #[allow(dead_code)]
 pub async fn create_react_app(app_name: String) {
-    println!("creating react app: {}", app_name.bright_green());
-    fs::create_dir(app_name.clone())
+    reporter::get().output(&format!("creating react app: {}", app_name.bright_green()));
+    let mut app = App::initialize();
+    let dir = app.current_dir.join(&app_name);
+    fs::create_dir(&dir)
         .await
-        .unwrap_or_else(|e| println!("{} {}", "error".bright_red(), e));
-    fs::create_dir(format!("{}/src", app_name))
+        .unwrap_or_else(|e| reporter::get().output(&format!("{} {}", "error".bright_red(), e)));
+    fs::create_dir(dir.join("src"))
         .await
-        .unwrap_or_else(|e| println!("{} {}", "error".bright_red(), e));
-    fs::create_dir(format!("{}/public", app_name))
+        .unwrap_or_else(|e| reporter::get().output(&format!("{} {}", "error".bright_red(), e)));
+    fs::create_dir(dir.join("public"))
         .await
-        .unwrap_or_else(|e| println!("{} {}", "error".bright_red(), e));
-    println!("{}", "$ volt init -y".truecolor(147, 148, 148));
-    let dir = std::env::current_dir().unwrap().join(&app_name);
-    let _ = std::env::set_current_dir(&dir); // Set current directory to $dir as init depends on it
-    let mut app = App::initialize();
+        .unwrap_or_else(|e| reporter::get().output(&format!("{} {}", "error".bright_red(), e)));
+    reporter::get().output(&"$ volt init -y".truecolor(147, 148, 148).to_string());
+    // Init runs in $dir
+    app.node_modules_dir = dir.join("node_modules");
+    app.lock_file_path = dir.join("volt.lock");
+    app.current_dir = dir.clone();
     app.flags = vec![String::from("-y")];
     volt_init::command::Init::exec(std::sync::Arc::new(app))
         .await
         .unwrap();
-    std::env::set_current_dir(std::env::current_dir().unwrap()).unwrap(); // reset current dir
-    fs::File::create(format!("{}/README.md", dir.to_str().unwrap()))
-        .await
-        .unwrap();
+    fs::File::create(dir.join("README.md")).await.unwrap();
 }
@@ -1,5 +1,7 @@
 #![allow(dead_code)]
 
+use volt_utils::reporter;
+
 pub fn create_react_app_ts() {
-    println!("creating react typescript app")
+    reporter::get().output("creating react typescript app")
 }
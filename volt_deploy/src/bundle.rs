@@ -0,0 +1,308 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Copy one workspace package and its production dependencies into a standalone directory.
+//!
+//! Dependencies are taken from the installed node_modules of the monorepo, following node's
+//! resolution from the package that requires them, so the bundle contains exactly the versions
+//! the package runs with in the monorepo. Workspace dependencies are copied in like registry
+//! packages and their `workspace:` ranges are replaced by real versions.
+
+use std::collections::HashMap;
+use std::fs::{self, create_dir_all, read_to_string};
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context, Result};
+use serde_json::Value;
+use volt_utils::workspace::Workspace;
+use walkdir::WalkDir;
+
+/// Dependency fields installed in production.
+const PRODUCTION_FIELDS: [&str; 2] = ["dependencies", "optionalDependencies"];
+
+/// Entries never copied out of a package directory.
+const SKIPPED_ENTRIES: [&str; 2] = ["node_modules", ".git"];
+
+pub struct Bundle {
+    /// Workspace members by package name
+    members: HashMap<String, PathBuf>,
+    /// Version of every package copied to the root node_modules of the bundle
+    placed: HashMap<String, String>,
+    copied: usize,
+    output: PathBuf,
+}
+
+impl Bundle {
+    pub fn new(workspace: &Workspace, output: &Path) -> Self {
+        let members = workspace
+            .members()
+            .into_iter()
+            .filter_map(|dir| Some((read_manifest(&dir).ok()?["name"].as_str()?.to_string(), dir)))
+            .collect();
+
+        Bundle {
+            members,
+            placed: HashMap::new(),
+            copied: 0,
+            output: output.to_path_buf(),
+        }
+    }
+
+    /// Find a member by package name or by its directory relative to the workspace root.
+    pub fn member(&self, workspace: &Workspace, selector: &str) -> Option<PathBuf> {
+        self.members.get(selector).cloned().or_else(|| {
+            let dir = workspace.root.join(selector);
+            self.members
+                .values()
+                .find(|member| **member == dir)
+                .cloned()
+        })
+    }
+
+    /// Copy the package in `source` and everything it needs in production into the output.
+    ///
+    /// Returns the number of copied dependencies.
+    pub fn deploy(&mut self, source: &Path) -> Result<usize> {
+        if self.output.exists() && fs::read_dir(&self.output)?.next().is_some() {
+            return Err(anyhow!(
+                "{} already exists and is not empty",
+                self.output.display()
+            ));
+        }
+
+        copy_package(source, &self.output)?;
+
+        let output = self.output.clone();
+        self.copy_dependencies(source, &output)?;
+
+        Ok(self.copied)
+    }
+
+    /// Copy the production dependencies of the package in `source`, which was copied to `target`.
+    fn copy_dependencies(&mut self, source: &Path, target: &Path) -> Result<()> {
+        let manifest = read_manifest(source)?;
+
+        for field in PRODUCTION_FIELDS.iter() {
+            let dependencies = match manifest[*field].as_object() {
+                Some(dependencies) => dependencies,
+                None => continue,
+            };
+
+            for name in dependencies.keys() {
+                let dependency_source = match self.resolve(source, name) {
+                    Some(dir) => dir,
+                    // Optional dependencies for other platforms are never installed
+                    None if *field == "optionalDependencies" => continue,
+                    None => {
+                        return Err(anyhow!(
+                            "{} depends on {}, which is not installed. Run `volt install` first.",
+                            source.display(),
+                            name
+                        ))
+                    }
+                };
+
+                let version = read_manifest(&dependency_source)?["version"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_string();
+
+                // Hoist to the bundle root unless another version is there already
+                let dependency_target = match self.placed.get(name) {
+                    Some(placed_version) if *placed_version == version => continue,
+                    Some(_) => target.join("node_modules").join(name),
+                    None => self.output.join("node_modules").join(name),
+                };
+
+                if dependency_target.exists() {
+                    continue;
+                }
+
+                copy_package(&dependency_source, &dependency_target)?;
+                self.copied += 1;
+
+                self.placed.entry(name.clone()).or_insert(version);
+
+                self.copy_dependencies(&dependency_source, &dependency_target)?;
+            }
+        }
+
+        self.pin_workspace_ranges(&target.join("package.json"))
+    }
+
+    /// Resolve `name` the way node does from the package in `from`, preferring workspace members.
+    fn resolve(&self, from: &Path, name: &str) -> Option<PathBuf> {
+        if let Some(member) = self.members.get(name) {
+            return Some(member.clone());
+        }
+
+        from.ancestors()
+            .map(|dir| dir.join("node_modules").join(name))
+            .find(|dir| dir.join("package.json").exists())
+            // Isolated installs link into the virtual store
+            .and_then(|dir| fs::canonicalize(dir).ok())
+    }
+
+    /// Replace `workspace:` ranges with the versions of the members they point at and drop
+    /// development-only fields.
+    fn pin_workspace_ranges(&self, path: &Path) -> Result<()> {
+        let mut manifest: Value = serde_json::from_str(&read_to_string(path)?)?;
+
+        if let Value::Object(object) = &mut manifest {
+            *object = std::mem::take(object)
+                .into_iter()
+                .filter(|(key, _)| key != "devDependencies")
+                .collect();
+        }
+
+        for field in PRODUCTION_FIELDS.iter() {
+            if let Some(dependencies) = manifest[*field].as_object_mut() {
+                for (name, range) in dependencies.iter_mut() {
+                    let pinned = match range.as_str() {
+                        Some(range) if range.starts_with("workspace:") => self
+                            .members
+                            .get(name)
+                            .and_then(|member| read_manifest(member).ok())
+                            .and_then(|member| member["version"].as_str().map(String::from)),
+                        _ => None,
+                    };
+
+                    if let Some(version) = pinned {
+                        *range = Value::String(version);
+                    }
+                }
+            }
+        }
+
+        fs::write(path, serde_json::to_string_pretty(&manifest)? + "\n")?;
+
+        Ok(())
+    }
+}
+
+fn read_manifest(dir: &Path) -> Result<Value> {
+    let path = dir.join("package.json");
+    let source =
+        read_to_string(&path).with_context(|| format!("failed to read {}", path.display()))?;
+
+    Ok(serde_json::from_str(&source)?)
+}
+
+/// Copy a package directory, following links and leaving out nested node_modules.
+fn copy_package(source: &Path, target: &Path) -> Result<()> {
+    let walker = WalkDir::new(source)
+        .follow_links(true)
+        .into_iter()
+        .filter_entry(|entry| {
+            entry.depth() == 0
+                || !SKIPPED_ENTRIES
+                    .iter()
+                    .any(|skipped| entry.file_name() == *skipped)
+        });
+
+    for entry in walker {
+        let entry = entry?;
+        let destination = target.join(entry.path().strip_prefix(source)?);
+
+        if entry.file_type().is_dir() {
+            create_dir_all(&destination)?;
+        } else {
+            fs::copy(entry.path(), &destination)
+                .with_context(|| format!("failed to copy {}", entry.path().display()))?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_package(dir: &Path, manifest: &str) {
+        create_dir_all(dir).unwrap();
+        fs::write(dir.join("package.json"), manifest).unwrap();
+        fs::write(dir.join("index.js"), "").unwrap();
+    }
+
+    #[test]
+    fn bundles_production_dependencies() {
+        let root = std::env::temp_dir().join(format!("volt-deploy-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+
+        write_package(
+            &root,
+            r#"{ "name": "monorepo", "workspaces": ["packages/*"] }"#,
+        );
+        write_package(
+            &root.join("packages/api"),
+            r#"{ "name": "api", "version": "1.0.0", "dependencies": { "shared": "workspace:*", "express": "^4.0.0" }, "devDependencies": { "jest": "^27.0.0" } }"#,
+        );
+        write_package(
+            &root.join("packages/shared"),
+            r#"{ "name": "shared", "version": "2.1.0", "dependencies": { "ms": "^2.0.0" } }"#,
+        );
+        write_package(
+            &root.join("node_modules/express"),
+            r#"{ "name": "express", "version": "4.17.1", "dependencies": { "ms": "2.0.0" } }"#,
+        );
+        write_package(
+            &root.join("node_modules/express/node_modules/ms"),
+            r#"{ "name": "ms", "version": "2.0.0" }"#,
+        );
+        write_package(
+            &root.join("node_modules/ms"),
+            r#"{ "name": "ms", "version": "2.1.3" }"#,
+        );
+        write_package(
+            &root.join("node_modules/jest"),
+            r#"{ "name": "jest", "version": "27.0.0" }"#,
+        );
+
+        let workspace = Workspace::load(&root).unwrap();
+        let output = root.join("out");
+        let mut bundle = Bundle::new(&workspace, &output);
+        let member = bundle.member(&workspace, "api").unwrap();
+
+        bundle.deploy(&member).unwrap();
+
+        let manifest = read_manifest(&output).unwrap();
+        assert_eq!(manifest["dependencies"]["shared"], "2.1.0");
+        assert!(manifest.get("devDependencies").is_none());
+
+        assert!(output.join("node_modules/shared/index.js").exists());
+        assert!(output.join("node_modules/express/index.js").exists());
+        assert!(!output.join("node_modules/jest").exists());
+
+        // Both versions of `ms` are kept, each where its dependents resolve it
+        let versions: Vec<String> = [
+            output.join("node_modules/ms"),
+            output.join("node_modules/express/node_modules/ms"),
+        ]
+        .iter()
+        .map(|dir| {
+            read_manifest(dir).unwrap()["version"]
+                .as_str()
+                .unwrap()
+                .to_string()
+        })
+        .collect();
+        assert!(versions.contains(&String::from("2.0.0")));
+        assert!(versions.contains(&String::from("2.1.3")));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
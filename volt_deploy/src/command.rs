@@ -14,39 +14,37 @@
     limitations under the License.
 */
 
-//! Remove a package from your direct dependencies.
+//! Push your changes, or bundle a workspace package for production.
 
-use std::{env, process, sync::Arc};
+use std::{process, sync::Arc};
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use async_trait::async_trait;
 use colored::Colorize;
-use volt_core::{command::Command, VERSION};
-use volt_utils::app::App;
+use volt_core::{command::Command, help::Help};
+use volt_utils::{app::App, reporter, workspace::Workspace};
+
+use crate::bundle::Bundle;
 /// Struct implementation for the `Deploy` command.
 pub struct Deploy;
 
 #[async_trait]
 impl Command for Deploy {
     /// Display a help menu for the `volt deploy` command.
-    fn help() -> String {
-        format!(
-            r#"volt {}
-    
-Deploys your commit to Github.
-
-Usage: {} {} {}
-
-Options: 
-
-  {} {} Output verbose messages on internal operations."#,
-            VERSION.bright_green().bold(),
-            "volt".bright_green().bold(),
-            "deploy".bright_purple(),
-            "[commit]".white(),
-            "--verbose".blue(),
-            "(-v)".yellow()
+    fn help() -> Help {
+        Help::new(
+            "deploy",
+            "Deploys your commit to Github, or copies a workspace package with only its production
+dependencies into a directory ready for a Docker image.",
+        )
+        .usage("[commit]")
+        .usage("[workspace] [dir]")
+        .arg("[commit]", "The commit message.")
+        .arg(
+            "[workspace] [dir]",
+            "The workspace package to copy, and where to.",
         )
+        .verbose()
     }
 
     /// Execute the `volt deploy` command
@@ -63,26 +61,64 @@ Options:
     /// * `Result<()>`
     async fn exec(app: Arc<App>) -> Result<()> {
         let args: Vec<String> = app.args.clone();
+
+        if let [_, workspace, dir] = args.as_slice() {
+            return deploy_workspace(&app, workspace, dir);
+        }
+
         if args.is_empty() {
-            println!("{} expected commit name", "error".bright_red());
+            reporter::get().output(&format!("{} expected commit name", "error".bright_red()));
             process::exit(1);
         } else {
             let commit_msg = &args[0];
-            env::set_current_dir(env::current_dir()?)?;
-            // println!("current dir: {:?}", env::current_dir()?);
             process::Command::new("git")
                 .args(&["add", "."])
+                .current_dir(&app.current_dir)
                 .output()
                 .expect("Failed to add");
             process::Command::new("git")
                 .args(&["commit", "-m", commit_msg.as_str()])
+                .current_dir(&app.current_dir)
                 .output()
                 .expect("Failed to commit");
             process::Command::new("git")
                 .args(&["push"])
+                .current_dir(&app.current_dir)
                 .output()
                 .expect("Failed to push");
         }
         Ok(())
     }
 }
+
+/// Bundle the workspace member `selector` into `dir`.
+fn deploy_workspace(app: &App, selector: &str, dir: &str) -> Result<()> {
+    let workspace = Workspace::find(&app.current_dir)
+        .ok_or_else(|| anyhow!("no workspace found at or above the current directory"))?;
+
+    let output = app.current_dir.join(dir);
+    let mut bundle = Bundle::new(&workspace, &output);
+
+    let member = bundle
+        .member(&workspace, selector)
+        .ok_or_else(|| anyhow!("{} is not a member of the workspace", selector))?;
+
+    if app.has_flag(&["-v", "--verbose"]) {
+        reporter::get().info(
+            "Deploying",
+            &member.display().to_string().bright_cyan().to_string(),
+        );
+    }
+
+    let count = bundle.deploy(&member)?;
+
+    reporter::get().output(&format!(
+        "{}: deployed {} with {} production dependencies to {}",
+        "success".bright_green(),
+        selector.bright_cyan(),
+        count,
+        output.display()
+    ));
+
+    Ok(())
+}
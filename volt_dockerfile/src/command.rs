@@ -0,0 +1,88 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Print a Dockerfile which caches the install of the project in its own layers.
+
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use colored::Colorize;
+use volt_core::{command::Command, help::Help};
+use volt_utils::app::App;
+use volt_utils::reporter;
+
+use crate::dockerfile::Project;
+
+/// Struct implementation for the `dockerfile` command.
+pub struct Dockerfile;
+
+#[async_trait]
+impl Command for Dockerfile {
+    /// Display a help menu for the `volt dockerfile` command.
+    fn help() -> Help {
+        Help::new(
+            "dockerfile",
+            "Print a multi-stage Dockerfile installing from the lock file.",
+        )
+        .usage("[flags]")
+        .flag(
+            &["--package=<member>"],
+            "Deploy a workspace package with only its production dependencies.",
+        )
+        .flag(
+            &["--output=<file>"],
+            "Write the Dockerfile instead of printing it.",
+        )
+        .verbose()
+        .example(
+            "volt dockerfile --package=api --output=Dockerfile",
+            "Write a Dockerfile for the api package of the workspace.",
+        )
+    }
+
+    /// Execute the `volt dockerfile` command
+    ///
+    /// Prints a Dockerfile for the project.
+    /// ## Arguments
+    /// * `app` - Instance of the command (`Arc<App>`)
+    /// ## Examples
+    /// ```ignore
+    /// // .exec() is an async call so you need to await it
+    /// Dockerfile.exec(app).await;
+    /// ```
+    /// ## Returns
+    /// * `Result<()>`
+    async fn exec(app: Arc<App>) -> Result<()> {
+        let package = app.flag_value(&["--package"]);
+        let project = Project::detect(&app, package.as_deref())?;
+        let dockerfile = project.render();
+
+        match app.flag_value(&["--output"]) {
+            Some(output) => {
+                let path = app.current_dir.join(output);
+
+                std::fs::write(&path, dockerfile)
+                    .with_context(|| format!("failed to write {}", path.display()))?;
+
+                reporter::get().output(&format!("{} {}", "Wrote".bright_green(), path.display()));
+            }
+            None => reporter::get().document(dockerfile.trim_end()),
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,284 @@
+/*
+Copyright 2021 Volt Contributors
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+    http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+//! A multi-stage Dockerfile installing from the lock file.
+//!
+//! The store is filled by `volt fetch` from `volt.lock` alone, so its layer is only rebuilt when
+//! the lock file changes. Dependencies are installed from the package.json files of the project
+//! and its workspace members before the sources are copied, so editing code only rebuilds the
+//! layers after the install, which fails if the lock file doesn't match them. The last stage holds
+//! the production dependencies of the deployed workspace package (see `volt deploy`), or the
+//! project once its dev dependencies are pruned by a `--production` install.
+
+use std::fmt::Write;
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+use volt_utils::{
+    app::App,
+    config::CONFIG_FILE,
+    package::PackageJson,
+    workspace::{self, Workspace},
+};
+
+/// Where volt is built from in the first stage.
+const REPOSITORY: &str = "https://github.com/voltpkg/volt";
+
+/// Where the stages keep the volt store, `$XDG_DATA_HOME/volt/store`.
+const DATA_HOME: &str = "/volt";
+
+/// What the Dockerfile is generated for.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Project {
+    /// The tag of the node image, `20-slim`
+    pub node: String,
+    pub voltrc: bool,
+    /// The directories of the workspace members, relative to the root
+    pub members: Vec<String>,
+    /// The build script, `volt run build` or `volt run build --recursive`
+    pub build: Option<String>,
+    /// The workspace member deployed with its production dependencies
+    pub deploy: Option<String>,
+    /// The command of the image
+    pub start: Vec<String>,
+}
+
+/// The node image tag for an `engines.node` range: its first major version, `lts` otherwise.
+pub fn node_tag(range: Option<&str>) -> String {
+    let major: String = range
+        .unwrap_or_default()
+        .chars()
+        .skip_while(|c| !c.is_ascii_digit())
+        .take_while(|c| c.is_ascii_digit())
+        .collect();
+
+    if major.is_empty() {
+        String::from("lts-slim")
+    } else {
+        format!("{}-slim", major)
+    }
+}
+
+/// The command starting the package in `dir`: its start script, or node on its main file.
+fn start_command(dir: &Path) -> Vec<String> {
+    let package_json = PackageJson::from(&dir.join("package.json").to_string_lossy());
+
+    if package_json.scripts.contains_key("start") {
+        vec![String::from("npm"), String::from("start")]
+    } else {
+        vec![
+            String::from("node"),
+            package_json
+                .main
+                .unwrap_or_else(|| String::from("index.js")),
+        ]
+    }
+}
+
+impl Project {
+    /// Read the project in the current directory, deploying the workspace member `deploy`.
+    pub fn detect(app: &App, deploy: Option<&str>) -> Result<Self> {
+        if !app.lock_file_path.exists() {
+            return Err(anyhow!(
+                "{} is missing, run volt install first",
+                app.lock_file_path.display()
+            ));
+        }
+
+        let root = &app.current_dir;
+        let package_json = PackageJson::from(&root.join("package.json").to_string_lossy());
+        let workspace = Workspace::load(root);
+
+        let members: Vec<_> = workspace
+            .as_ref()
+            .map(Workspace::members)
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|dir| Some((workspace::relative_path(root, &dir)?, dir)))
+            .collect();
+
+        let deploy = match deploy {
+            Some(selector) => {
+                if workspace.is_none() {
+                    return Err(anyhow!(
+                        "--package needs a package.json declaring workspaces"
+                    ));
+                }
+
+                // By name, or by directory like volt deploy
+                let (relative, _) = members
+                    .iter()
+                    .find(|(relative, dir)| {
+                        relative == selector.trim_end_matches('/')
+                            || PackageJson::from(&dir.join("package.json").to_string_lossy()).name
+                                == selector
+                    })
+                    .ok_or_else(|| anyhow!("{} is not a member of the workspace", selector))?;
+
+                Some(relative.clone())
+            }
+            None => None,
+        };
+
+        let member_builds = members.iter().any(|(_, dir)| {
+            PackageJson::from(&dir.join("package.json").to_string_lossy())
+                .scripts
+                .contains_key("build")
+        });
+
+        let build = if member_builds {
+            Some(String::from("volt run build --recursive"))
+        } else if package_json.scripts.contains_key("build") {
+            Some(String::from("volt run build"))
+        } else {
+            None
+        };
+
+        let start = match &deploy {
+            Some(relative) => start_command(&root.join(relative)),
+            None => start_command(root),
+        };
+
+        Ok(Project {
+            node: node_tag(package_json.engines.get("node").map(String::as_str)),
+            voltrc: root.join(CONFIG_FILE).exists(),
+            members: members.into_iter().map(|(relative, _)| relative).collect(),
+            build,
+            deploy,
+            start,
+        })
+    }
+
+    /// The Dockerfile, to be saved at the root of the project.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+
+        // Writing to a String can't fail
+        let mut line = |text: &str| {
+            let _ = writeln!(out, "{}", text);
+        };
+
+        line("# syntax=docker/dockerfile:1");
+        line("# Generated by `volt dockerfile`, add node_modules to .dockerignore");
+        line("");
+        line("FROM rust:1-slim AS volt");
+        line(&format!(
+            "RUN cargo install --locked --git {} volt_cli && mv /usr/local/cargo/bin/volt_cli /usr/local/bin/volt",
+            REPOSITORY
+        ));
+        line("");
+        line(&format!("FROM node:{} AS deps", self.node));
+        line("COPY --from=volt /usr/local/bin/volt /usr/local/bin/volt");
+        line(&format!("ENV XDG_DATA_HOME={}", DATA_HOME));
+        line("WORKDIR /app");
+        line("# Rebuilt only when the lock file or the settings change");
+
+        let mut settings = vec![String::from("volt.lock")];
+
+        if self.voltrc {
+            settings.push(CONFIG_FILE.to_string());
+        }
+
+        line(&format!("COPY {} ./", settings.join(" ")));
+        line("RUN volt fetch --no-progress");
+        line("COPY package.json ./");
+
+        for member in self.members.iter() {
+            line(&format!("COPY {0}/package.json {0}/", member));
+        }
+
+        line("RUN volt install --frozen-lockfile --check-lock-hash");
+        line("");
+        line("FROM deps AS build");
+        line("COPY . .");
+
+        if let Some(build) = &self.build {
+            line(&format!("RUN {}", build));
+        }
+
+        match &self.deploy {
+            Some(deploy) => line(&format!("RUN volt deploy {} /deploy", deploy)),
+            // The dev dependencies were only needed to build
+            None => line("RUN volt install --production --frozen-lockfile"),
+        }
+
+        line("");
+        line(&format!("FROM node:{}", self.node));
+        line("ENV NODE_ENV=production");
+        line("WORKDIR /app");
+
+        match &self.deploy {
+            Some(_) => line("COPY --from=build /deploy ./"),
+            None => line("COPY --from=build /app ./"),
+        }
+
+        line("USER node");
+
+        let start: Vec<String> = self.start.iter().map(|arg| format!("{:?}", arg)).collect();
+        line(&format!("CMD [{}]", start.join(", ")));
+
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn copies_manifests_before_sources() {
+        assert_eq!(node_tag(Some(">=18.12")), "18-slim");
+        assert_eq!(node_tag(None), "lts-slim");
+
+        let project = Project {
+            node: String::from("20-slim"),
+            voltrc: true,
+            members: vec![String::from("packages/api"), String::from("packages/web")],
+            build: Some(String::from("volt run build --recursive")),
+            deploy: Some(String::from("packages/api")),
+            start: vec![String::from("node"), String::from("dist/server.js")],
+        };
+
+        let dockerfile = project.render();
+        let position = |text: &str| dockerfile.find(text).unwrap();
+
+        assert!(position("COPY volt.lock .voltrc ./") < position("RUN volt fetch"));
+        assert!(position("RUN volt fetch") < position("COPY package.json ./"));
+        assert!(position("COPY packages/web/package.json packages/web/") < position("COPY . ."));
+        assert!(
+            position("RUN volt run build --recursive")
+                < position("RUN volt deploy packages/api /deploy")
+        );
+        assert!(dockerfile.contains("RUN volt install --frozen-lockfile --check-lock-hash"));
+        assert!(dockerfile.contains("COPY --from=build /deploy ./"));
+        assert!(!dockerfile.contains("--production"));
+        assert!(dockerfile.ends_with("CMD [\"node\", \"dist/server.js\"]\n"));
+
+        // Without a deploy target, the build stage drops the dev dependencies before the copy
+        let dockerfile = Project {
+            deploy: None,
+            ..project
+        }
+        .render();
+        let position = |text: &str| dockerfile.find(text).unwrap();
+
+        assert!(
+            position("RUN volt run build --recursive")
+                < position("RUN volt install --production --frozen-lockfile")
+        );
+        assert!(
+            position("RUN volt install --production --frozen-lockfile")
+                < position("COPY --from=build /app ./")
+        );
+    }
+}
@@ -0,0 +1,102 @@
+/*
+Copyright 2021 Volt Contributors
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+    http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+//! Show the directories volt uses.
+
+use std::sync::Arc;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use colored::Colorize;
+use serde_json::json;
+use volt_core::{command::Command, help::Help};
+use volt_utils::{app::App, config::CONFIG_FILE, global, reporter};
+
+/// Struct implementation for the `env` command.
+pub struct Env;
+
+#[async_trait]
+impl Command for Env {
+    /// Display a help menu for the `volt env` command.
+    fn help() -> Help {
+        Help::new(
+            "env",
+            "Show where volt keeps the store, the cache, the config files and global packages.",
+        )
+        .usage("[flags]")
+        .flag(&["--json"], "Output the directories as JSON.")
+        .verbose()
+    }
+
+    /// Execute the `volt env` command
+    ///
+    /// Prints the directories of the platform volt uses.
+    /// ## Arguments
+    /// * `app` - Instance of the command (`Arc<App>`)
+    /// ## Examples
+    /// ```ignore
+    /// // .exec() is an async call so you need to await it
+    /// Env.exec(app).await;
+    /// ```
+    /// ## Returns
+    /// * `Result<()>`
+    async fn exec(app: Arc<App>) -> Result<()> {
+        let project_config = app.current_dir.join(CONFIG_FILE);
+        let global_dir = global::dir(&app);
+        let global_bin_dir = global::bin_dir(&app);
+
+        let entries = [
+            ("store", &app.volt_dir),
+            ("cache", &app.cache_dir),
+            ("config", &app.config_file),
+            ("global", &global_dir),
+            ("globalBin", &global_bin_dir),
+            ("projectConfig", &project_config),
+            ("nodeModules", &app.node_modules_dir),
+        ];
+
+        if reporter::json() {
+            let object: serde_json::Map<String, serde_json::Value> = entries
+                .iter()
+                .map(|(name, path)| (name.to_string(), json!(path.to_string_lossy())))
+                .collect();
+
+            reporter::get().document(&serde_json::to_string_pretty(&object)?);
+
+            return Ok(());
+        }
+
+        let width = entries
+            .iter()
+            .map(|(name, _)| name.len())
+            .max()
+            .unwrap_or(0);
+
+        for (name, path) in entries.iter() {
+            let missing = if path.exists() {
+                String::new()
+            } else {
+                format!(" {}", "(missing)".bright_black())
+            };
+
+            reporter::get().output(&format!(
+                "{}  {}{}",
+                format!("{:width$}", name, width = width).bright_cyan(),
+                path.display(),
+                missing
+            ));
+        }
+
+        Ok(())
+    }
+}
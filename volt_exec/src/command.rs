@@ -0,0 +1,481 @@
+/*
+Copyright 2021 Volt Contributors
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+    http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+//! Run a binary from a package without adding it to your project.
+
+use std::collections::HashMap;
+use std::env::temp_dir;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{self, exit};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use volt_core::{command::Command, help::Help};
+use volt_utils::{app::App, fs::remove_dir_all, reporter, volt_api::VoltPackage};
+
+/// File written into an environment once it has been fully installed.
+/// Its modification time is refreshed on every run and used by `volt store prune --ttl`.
+pub const ENVIRONMENT_MARKER: &str = ".volt-x";
+
+/// What `volt x` was asked to run.
+#[derive(Debug, PartialEq)]
+struct Invocation {
+    /// Packages to install with `-p`/`--package`, the command's own package without them
+    packages: Vec<String>,
+    /// The binary to run, or the package it comes from without `--package`
+    command: String,
+    args: Vec<String>,
+}
+
+impl Invocation {
+    /// Read what `app` runs. Flags before the command belong to volt, everything after it is
+    /// passed to the binary, which the app keeps in order after `x` and the command.
+    fn from_app(app: &App) -> Option<Self> {
+        let (command, args) = app.args.get(1..)?.split_first()?;
+
+        Some(Invocation {
+            packages: app.flag_values(&["--package", "-p"]),
+            command: command.clone(),
+            args: args.to_vec(),
+        })
+    }
+}
+
+/// Struct implementation for the `x` command.
+pub struct Exec;
+
+#[async_trait]
+impl Command for Exec {
+    /// Display a help menu for the `volt x` command.
+    fn help() -> Help {
+        Help::new(
+            "x",
+            "Run a binary from a package without adding it to your project.",
+        )
+        .usage("[flags] [package[@version]] [args]")
+        .arg(
+            "[package[@version]]",
+            "The package the binary comes from, or the binary to run with --package.",
+        )
+        .arg("[args]", "Arguments passed to the binary.")
+        .flag(
+            &["--package=<package>", "-p"],
+            "Install this package into the environment, can be repeated.",
+        )
+        .flag(
+            &["--no-cache"],
+            "Install into a throwaway environment instead of the cache.",
+        )
+        .verbose()
+        .example("volt x cowsay hello", "")
+        .example(
+            "volt x -p typescript -p ts-node ts-node script.ts",
+            "Run ts-node with typescript installed next to it.",
+        )
+    }
+
+    /// Execute the `volt x` command
+    ///
+    /// Run a binary from a package without adding it to your project.
+    /// ## Arguments
+    /// * `app` - Instance of the command (`Arc<App>`)
+    /// ## Examples
+    /// ```ignore
+    /// // Run cowsay 2.x from a cached environment
+    /// // .exec() is an async call so you need to await it
+    /// Exec.exec(app).await;
+    /// ```
+    /// ## Returns
+    /// * `Result<()>`
+    async fn exec(app: Arc<App>) -> Result<()> {
+        let invocation = match Invocation::from_app(&app) {
+            Some(invocation) => invocation,
+            None => {
+                reporter::get().output(&Self::help().to_string());
+                exit(1);
+            }
+        };
+
+        let verbose = app.has_flag(&["-v", "--verbose"]);
+        let no_cache = app.has_flag(&["--no-cache"]);
+
+        // Without --package, the command is the package to run the binary of
+        let specs = if invocation.packages.is_empty() {
+            vec![invocation.command.clone()]
+        } else {
+            invocation.packages.clone()
+        };
+
+        let mut requested = vec![];
+        let mut packages: HashMap<String, VoltPackage> = HashMap::new();
+
+        for spec in specs.iter() {
+            let (name, tree) = resolve_package(&app, spec).await?;
+            let package = tree[&name].clone();
+
+            packages.insert(name, package.clone());
+            requested.push(package);
+
+            for (name, package) in tree {
+                packages.entry(name).or_insert(package);
+            }
+        }
+
+        // The packages asked for win over the dependencies of the others
+        for package in requested.iter() {
+            packages.insert(package.name.clone(), package.clone());
+        }
+
+        let binary = if invocation.packages.is_empty() {
+            binary_path(&requested[0])?
+        } else {
+            requested
+                .iter()
+                .find_map(|package| {
+                    let path = package.bin.as_ref()?.get(&invocation.command)?;
+                    Some(Path::new(&package.name).join(path))
+                })
+                .ok_or_else(|| {
+                    anyhow!(
+                        "none of {} provides {}",
+                        invocation.packages.join(", "),
+                        invocation.command
+                    )
+                })?
+        };
+
+        let key = environment_key(
+            requested
+                .iter()
+                .map(|package| (package.name.as_str(), package.version.as_str())),
+        );
+
+        let environment = if no_cache {
+            temp_dir().join("volt").join(format!("x-{}", process::id()))
+        } else {
+            environment_path(&app, &key, &node_abi())
+        };
+
+        if !environment.join(ENVIRONMENT_MARKER).exists() {
+            if verbose {
+                reporter::get().info("Creating environment", &environment.display().to_string());
+            }
+
+            install_environment(&app, &environment, packages.into_values().collect()).await?;
+        } else if verbose {
+            reporter::get().info(
+                "Using cached environment",
+                &environment.display().to_string(),
+            );
+        }
+
+        // Refresh the last-used time of this environment
+        fs::write(environment.join(ENVIRONMENT_MARKER), key.join("\n"))?;
+
+        let status = process::Command::new("node")
+            .arg(environment.join("node_modules").join(binary))
+            .args(&invocation.args)
+            .current_dir(&app.current_dir)
+            .status()
+            .context("failed to execute node")?;
+
+        if no_cache {
+            remove_dir_all(&environment).ok();
+        }
+
+        exit(status.code().unwrap_or(1));
+    }
+}
+
+/// Directory holding the cached `volt x` environments (`<volt dir>/x`).
+pub fn environments_dir(app: &App) -> PathBuf {
+    app.volt_dir.join("x")
+}
+
+/// The `name@version` of each package of an environment, sorted so that the order they were
+/// asked for in doesn't matter.
+fn environment_key<'a>(packages: impl IntoIterator<Item = (&'a str, &'a str)>) -> Vec<String> {
+    let mut key: Vec<String> = packages
+        .into_iter()
+        .map(|(name, version)| format!("{}@{}", name.replace('/', "+"), version))
+        .collect();
+    key.sort();
+
+    key
+}
+
+/// The cached environment of `key`, native addons are only valid for the node ABI `abi`.
+fn environment_path(app: &App, key: &[String], abi: &str) -> PathBuf {
+    environments_dir(app).join(format!("{}-node{}", key.join("_"), abi))
+}
+
+/// Remove cached environments which haven't been used for longer than `ttl`.
+///
+/// Returns the number of environments removed.
+pub fn prune_environments(app: &App, ttl: Duration) -> Result<usize> {
+    let dir = environments_dir(app);
+
+    if !dir.exists() {
+        return Ok(0);
+    }
+
+    let mut removed = 0;
+
+    for entry in fs::read_dir(&dir)? {
+        let path = entry?.path();
+
+        // Without its marker, the environment is being installed by another volt x, or was left
+        // half done, and goes once the directory itself is older than the ttl
+        let last_used = fs::metadata(path.join(ENVIRONMENT_MARKER))
+            .or_else(|_| fs::metadata(&path))
+            .and_then(|metadata| metadata.modified())
+            .unwrap_or(SystemTime::UNIX_EPOCH);
+
+        let expired = SystemTime::now()
+            .duration_since(last_used)
+            .map(|age| age > ttl)
+            .unwrap_or(false);
+
+        if expired {
+            remove_dir_all(&path)?;
+            removed += 1;
+        }
+    }
+
+    Ok(removed)
+}
+
+/// Get the ABI version of the installed node binary, native addons are only valid for one ABI.
+fn node_abi() -> String {
+    process::Command::new("node")
+        .args(["-p", "process.versions.modules"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        .unwrap_or_else(|| String::from("unknown"))
+}
+
+async fn install_environment(
+    app: &App,
+    environment: &Path,
+    packages: Vec<VoltPackage>,
+) -> Result<()> {
+    fs::create_dir_all(environment)?;
+
+    let environment_app = Arc::new(App {
+        current_dir: environment.to_path_buf(),
+        home_dir: app.home_dir.clone(),
+        node_modules_dir: environment.join("node_modules"),
+        volt_dir: app.volt_dir.clone(),
+        cache_dir: app.cache_dir.clone(),
+        config_file: app.config_file.clone(),
+        lock_file_path: environment.join("volt.lock"),
+        args: app.args.clone(),
+        flags: app.flags.clone(),
+        config: app.config.clone(),
+    });
+
+    for package in packages.iter() {
+        volt_utils::install_extract_package(&environment_app, package).await?;
+    }
+
+    volt_utils::create_dependency_links(
+        environment_app.clone(),
+        packages
+            .into_iter()
+            .map(|package| (package.name.clone(), package))
+            .collect(),
+        None,
+    )
+    .await
+}
+
+/// Resolve `spec` (`cowsay` or `cowsay@1`) to its name and the packages of its tree.
+async fn resolve_package(app: &App, spec: &str) -> Result<(String, HashMap<String, VoltPackage>)> {
+    let (name, range) = volt_utils::parse_package_spec(spec);
+
+    let mut response = volt_utils::mirror::get_volt_response(app, &name).await?;
+
+    let version = match range {
+        Some(range) => volt_utils::max_satisfying(response.versions.keys(), &range)
+            .ok_or_else(|| anyhow!("no version of {} satisfies {}", name, range))?,
+        None => response.version.clone(),
+    };
+
+    let packages = response
+        .versions
+        .remove(&version)
+        .ok_or_else(|| anyhow!("{}@{} is not available", name, version))?
+        .packages;
+
+    if !packages.contains_key(&name) {
+        return Err(anyhow!("{}@{} is not available", name, version));
+    }
+
+    Ok((name, packages))
+}
+
+/// The binary of `package` to run, relative to node_modules.
+fn binary_path(package: &VoltPackage) -> Result<PathBuf> {
+    let bin = package
+        .bin
+        .as_ref()
+        .filter(|bin| !bin.is_empty())
+        .ok_or_else(|| anyhow!("{} does not provide any binaries", package.name))?;
+
+    // Prefer the binary named after the package (`cowsay`), otherwise use the first one
+    let unscoped_name = package.name.rsplit('/').next().unwrap();
+    let path = bin
+        .get(unscoped_name)
+        .unwrap_or_else(|| bin.values().next().unwrap());
+
+    Ok(Path::new(&package.name).join(path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use volt_utils::app::parse_args;
+    use volt_utils::config::Config;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    fn app(root: &Path, command_line: &[&str]) -> App {
+        let (args, flags) = parse_args(command_line.iter().map(|arg| arg.to_string()));
+
+        App {
+            current_dir: root.to_path_buf(),
+            home_dir: root.join("home"),
+            node_modules_dir: root.join("node_modules"),
+            volt_dir: root.join("home/.volt"),
+            cache_dir: root.join("home/.volt/.cache"),
+            lock_file_path: root.join("volt.lock"),
+            config_file: root.join(".voltrc"),
+            args,
+            flags,
+            config: Config::default(),
+        }
+    }
+
+    fn invocation(command_line: &[&str]) -> Option<Invocation> {
+        Invocation::from_app(&app(&temp_dir(), command_line))
+    }
+
+    #[test]
+    fn parses_packages_and_command() {
+        assert_eq!(
+            invocation(&[
+                "x",
+                "-p",
+                "typescript",
+                "--package=ts-node@10",
+                "ts-node",
+                "-T",
+                "script.ts"
+            ]),
+            Some(Invocation {
+                packages: args(&["typescript", "ts-node@10"]),
+                command: String::from("ts-node"),
+                args: args(&["-T", "script.ts"]),
+            })
+        );
+
+        // Flags of volt can come before `x`
+        assert_eq!(
+            invocation(&["--cwd", "web", "x", "--no-cache", "cowsay", "hello", "-p"]),
+            Some(Invocation {
+                packages: vec![],
+                command: String::from("cowsay"),
+                args: args(&["hello", "-p"]),
+            })
+        );
+
+        assert_eq!(invocation(&["x", "-p", "typescript"]), None);
+    }
+
+    #[test]
+    fn caches_an_environment_per_version_and_abi() {
+        let app = app(&temp_dir(), &["x", "cowsay"]);
+
+        let key = environment_key([("typescript", "4.5.2"), ("@types/node", "16.11.7")]);
+        assert_eq!(key, args(&["@types+node@16.11.7", "typescript@4.5.2"]));
+
+        // The order of --package doesn't matter, the versions and the ABI do
+        let path = environment_path(&app, &key, "93");
+        assert_eq!(
+            path,
+            environment_path(
+                &app,
+                &environment_key([("@types/node", "16.11.7"), ("typescript", "4.5.2")]),
+                "93"
+            )
+        );
+        assert_ne!(
+            path,
+            environment_path(
+                &app,
+                &environment_key([("typescript", "4.5.3"), ("@types/node", "16.11.7")]),
+                "93"
+            )
+        );
+        assert_ne!(path, environment_path(&app, &key, "102"));
+        assert!(path.starts_with(environments_dir(&app)));
+    }
+
+    #[test]
+    fn prunes_environments_unused_for_longer_than_the_ttl() {
+        let root = temp_dir().join(format!("volt-x-prune-{}", process::id()));
+        let app = app(&root, &["store", "prune"]);
+        let day = Duration::from_secs(24 * 60 * 60);
+
+        for (name, age, marker) in [
+            ("recent", day, true),
+            ("old", day * 10, true),
+            ("installing", Duration::ZERO, false),
+            ("broken", day * 10, false),
+        ] {
+            let environment = environments_dir(&app).join(name);
+            fs::create_dir_all(&environment).unwrap();
+
+            // An environment without its marker was never fully installed
+            let dated = if marker {
+                environment.join(ENVIRONMENT_MARKER)
+            } else {
+                environment.clone()
+            };
+
+            if marker {
+                fs::File::create(&dated).unwrap();
+            }
+
+            fs::File::open(&dated)
+                .unwrap()
+                .set_modified(SystemTime::now() - age)
+                .unwrap();
+        }
+
+        assert_eq!(prune_environments(&app, day * 7).unwrap(), 2);
+        assert!(environments_dir(&app).join("recent").exists());
+        assert!(!environments_dir(&app).join("old").exists());
+        assert!(environments_dir(&app).join("installing").exists());
+        assert!(!environments_dir(&app).join("broken").exists());
+
+        let _ = remove_dir_all(&root);
+    }
+}
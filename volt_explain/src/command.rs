@@ -0,0 +1,278 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Explain why the resolver chose a version of a package.
+//!
+//! The tree of each dependency of package.json is walked with the registry resolver, which
+//! records every range asking for the package, and the rule which picked one version among
+//! them. The volt CDN resolves trees without saying why, so it can't be explained.
+
+use std::process::exit;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use colored::Colorize;
+use serde::Serialize;
+use serde_json::Value;
+use volt_core::{command::Command, help::Help, model::lock_file::LockFile};
+use volt_utils::{
+    app::App,
+    explain::{Candidate, Decision},
+    package::PackageJson,
+    reporter, resolver,
+};
+
+/// The decision for the package in the tree of one dependency of package.json.
+#[derive(Serialize)]
+struct Tree {
+    /// `name@range` of the dependency
+    root: String,
+    field: &'static str,
+    decision: Decision,
+    strategy: String,
+    conflicts: Vec<Candidate>,
+}
+
+/// An entry of `overrides` or `resolutions` in package.json for the package.
+#[derive(Serialize)]
+struct Override {
+    field: &'static str,
+    value: Value,
+}
+
+#[derive(Serialize)]
+struct Explanation {
+    name: String,
+    version: Option<String>,
+    /// The versions in the lock file
+    locked: Vec<String>,
+    overrides: Vec<Override>,
+    trees: Vec<Tree>,
+}
+
+fn print_explanation(explanation: &Explanation) {
+    for tree in explanation.trees.iter() {
+        reporter::get().output(&format!(
+            "{}@{} in the tree of {} ({})",
+            explanation.name.bright_cyan().bold(),
+            tree.decision.version,
+            tree.root.bright_cyan(),
+            tree.field
+        ));
+        reporter::get().output(&format!("  chosen as {}", tree.strategy.green()));
+
+        let width = tree
+            .decision
+            .candidates
+            .iter()
+            .map(|candidate| candidate.range.len())
+            .max()
+            .unwrap_or(0);
+
+        for candidate in tree.decision.candidates.iter() {
+            let required_by = if candidate.required_by.is_empty() {
+                String::from("package.json")
+            } else {
+                candidate.required_by.join(", ")
+            };
+
+            let conflict = if tree.conflicts.contains(candidate) {
+                format!("  {}", "conflict".bright_red())
+            } else {
+                String::new()
+            };
+
+            reporter::get().output(&format!(
+                "  {:width$}  {}  depth {}  {}{}",
+                candidate.range.yellow(),
+                candidate.version,
+                candidate.depth,
+                required_by.dimmed(),
+                conflict,
+                width = width
+            ));
+        }
+    }
+
+    if !explanation.locked.is_empty() {
+        reporter::get().output(&format!("locked: {}", explanation.locked.join(", ")));
+    }
+
+    for entry in explanation.overrides.iter() {
+        reporter::get().output(&format!(
+            "{}: {} {} (volt doesn't apply them yet)",
+            entry.field, explanation.name, entry.value
+        ));
+    }
+}
+
+/// Struct implementation for the `explain` command.
+pub struct Explain;
+
+#[async_trait]
+impl Command for Explain {
+    /// Display a help menu for the `volt explain` command.
+    fn help() -> Help {
+        Help::new(
+            "explain",
+            "Explain why the resolver chose a version of a package: the ranges asking for it, \
+             the rule which picked it and the overrides of package.json.",
+        )
+        .usage("<package>[@version] [flags]")
+        .arg(
+            "<package>[@version]",
+            "The package, and the version to explain.",
+        )
+        .flag(&["--json"], "Print the result as JSON.")
+        .verbose()
+        .example("volt explain react-is@16.13.1", "")
+    }
+
+    /// Execute the `volt explain` command
+    ///
+    /// Walks the tree of each dependency of package.json and lists the decisions for a package.
+    /// ## Arguments
+    /// * `app` - Instance of the command (`Arc<App>`)
+    /// ## Examples
+    /// ```ignore
+    /// // .exec() is an async call so you need to await it
+    /// Explain.exec(app).await;
+    /// ```
+    /// ## Returns
+    /// * `Result<()>`
+    async fn exec(app: Arc<App>) -> Result<()> {
+        let (name, version) = match app.args.get(1) {
+            Some(spec) => volt_utils::parse_package_spec(spec),
+            None => {
+                reporter::get().output(&Self::help().to_string());
+                exit(1);
+            }
+        };
+
+        let package_json =
+            PackageJson::from(&app.current_dir.join("package.json").to_string_lossy());
+
+        let fields = [
+            ("dependencies", &package_json.dependencies),
+            ("devDependencies", &package_json.dev_dependencies),
+            ("optionalDependencies", &package_json.optional_dependencies),
+        ];
+
+        let mut roots: Vec<(&'static str, String)> = fields
+            .iter()
+            .flat_map(|(field, dependencies)| {
+                dependencies
+                    .iter()
+                    .map(move |(name, range)| (*field, format!("{}@{}", name, range)))
+            })
+            .collect();
+
+        roots.sort_by(|a, b| a.1.cmp(&b.1));
+
+        let mut trees = vec![];
+
+        for (field, root) in roots {
+            let decisions = match resolver::resolve_explained(&app, &root).await {
+                Ok((_, decisions)) => decisions,
+                Err(error) => {
+                    reporter::get().warn(&format!("skipped {}: {:#}", root, error));
+                    continue;
+                }
+            };
+
+            let decision = match decisions.get(&name) {
+                Some(decision) => decision.clone(),
+                None => continue,
+            };
+
+            let asked = match &version {
+                Some(version) => {
+                    decision.version == *version
+                        || decision
+                            .candidates
+                            .iter()
+                            .any(|candidate| candidate.version == *version)
+                }
+                None => true,
+            };
+
+            if asked {
+                trees.push(Tree {
+                    root,
+                    field,
+                    strategy: decision.strategy(),
+                    conflicts: decision.conflicts().into_iter().cloned().collect(),
+                    decision,
+                });
+            }
+        }
+
+        if trees.is_empty() {
+            let spec = match &version {
+                Some(version) => format!("{}@{}", name, version),
+                None => name,
+            };
+
+            return Err(anyhow!(
+                "{} is not in the tree of any dependency of package.json",
+                spec
+            ));
+        }
+
+        let locked = LockFile::load(app.lock_file_path.clone())
+            .map(|lock_file| {
+                let mut versions: Vec<String> = lock_file
+                    .dependencies
+                    .values()
+                    .filter(|dependency| dependency.name == name)
+                    .map(|dependency| dependency.version.clone())
+                    .collect();
+
+                versions.sort();
+                versions
+            })
+            .unwrap_or_default();
+
+        let overrides = ["overrides", "resolutions"]
+            .iter()
+            .filter_map(|field| {
+                let value = package_json.other.get(*field)?.get(&name)?;
+
+                Some(Override {
+                    field,
+                    value: value.clone(),
+                })
+            })
+            .collect();
+
+        let explanation = Explanation {
+            name,
+            version,
+            locked,
+            overrides,
+            trees,
+        };
+
+        if reporter::json() {
+            reporter::get().document(&serde_json::to_string_pretty(&explanation)?);
+        } else {
+            print_explanation(&explanation);
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,260 @@
+/*
+Copyright 2021 Volt Contributors
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+    http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+//! Download every package in the lock file into the volt store.
+//!
+//! A locked version whose tarball was unpublished (404 or 410) can be replaced by the nearest
+//! version matching its range in package.json, or its caret range for transitive dependencies.
+//! The substitution is offered, made right away with `--auto`, and recorded in the lock file.
+
+use std::collections::HashMap;
+use std::io::IsTerminal;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use colored::Colorize;
+use futures::{stream::FuturesUnordered, StreamExt};
+use volt_core::{
+    command::Command,
+    help::Help,
+    model::lock_file::{DependencyID, DependencyLock, LockFile},
+    prompt::prompts::Confirm,
+};
+use volt_utils::{
+    app::App,
+    network,
+    package::PackageJson,
+    reporter::{self, Progress},
+    resolver, resume,
+    volt_api::{store_name, VoltPackage},
+};
+
+/// The range a substitute for `package` has to match: its range in package.json, or the caret
+/// range of the locked version.
+fn substitute_range(app: &App, package: &VoltPackage) -> String {
+    let package_file = PackageJson::from(&app.current_dir.join("package.json").to_string_lossy());
+
+    [
+        &package_file.dependencies,
+        &package_file.dev_dependencies,
+        &package_file.optional_dependencies,
+    ]
+    .iter()
+    .filter_map(|dependencies| dependencies.get(&package.name))
+    .find(|range| node_semver::Range::parse(range).is_ok())
+    .cloned()
+    .unwrap_or_else(|| format!("^{}", package.version))
+}
+
+/// Replace `package`, whose tarball is gone, with the nearest version matching its range if the
+/// user agrees, or right away with `--auto`. Returns the substitute, once fetched.
+async fn substitute(app: &App, package: &VoltPackage) -> Result<VoltPackage> {
+    let range = substitute_range(app, package);
+    let substitute = resolver::substitute(app, &package.name, &package.version, &range)
+        .await
+        .with_context(|| {
+            format!(
+                "the tarball of {}@{} is gone and no substitute was found",
+                package.name, package.version
+            )
+        })?;
+
+    let message = format!(
+        "The tarball of {}@{} is gone from the registry, use {}@{} ({}) instead",
+        package.name, package.version, substitute.name, substitute.version, range
+    );
+
+    let accepted = if app.has_flag(&["--auto"]) {
+        true
+    } else if std::io::stdin().is_terminal() {
+        Confirm {
+            message,
+            default: true,
+        }
+        .run()?
+    } else {
+        return Err(anyhow!(
+            "the tarball of {}@{} is gone from the registry, pass --auto to use {} instead",
+            package.name,
+            package.version,
+            substitute.version
+        ));
+    };
+
+    if !accepted {
+        return Err(anyhow!(
+            "the tarball of {}@{} is gone from the registry",
+            package.name,
+            package.version
+        ));
+    }
+
+    volt_utils::fetch_package(app, &substitute)
+        .await
+        .with_context(|| format!("failed to fetch {}@{}", substitute.name, substitute.version))?;
+
+    reporter::get().output(&format!(
+        "{} {}@{} with {}@{}, its tarball is gone from the registry",
+        "Substituted".yellow(),
+        package.name.bright_cyan(),
+        package.version,
+        substitute.name.bright_cyan(),
+        substitute.version
+    ));
+
+    Ok(substitute)
+}
+
+/// Struct implementation for the `fetch` command.
+pub struct Fetch;
+
+#[async_trait]
+impl Command for Fetch {
+    /// Display a help menu for the `volt fetch` command.
+    fn help() -> Help {
+        Help::new(
+            "fetch",
+            "Download every package in volt.lock into the volt store without touching node_modules.
+
+Only the lock file is read, so copying it alone into a Docker layer before running
+fetch keeps the layer cached until the dependencies change. A later `volt install`
+links from the store without downloading anything.",
+        )
+        .usage("[flags]")
+        .flag(
+            &["--offline"],
+            "Install from the offline mirror without using the network.",
+        )
+        .flag(
+            &["--auto"],
+            "Replace versions whose tarball was unpublished without asking.",
+        )
+        .verbose()
+        .no_progress()
+    }
+
+    /// Execute the `volt fetch` command
+    ///
+    /// Downloads every package in the lock file into the volt store.
+    /// ## Arguments
+    /// * `app` - Instance of the command (`Arc<App>`)
+    /// ## Examples
+    /// ```ignore
+    /// // .exec() is an async call so you need to await it
+    /// Fetch.exec(app).await;
+    /// ```
+    /// ## Returns
+    /// * `Result<()>`
+    async fn exec(app: Arc<App>) -> Result<()> {
+        let mut lock_file = LockFile::load(app.lock_file_path.clone()).with_context(|| {
+            format!(
+                "failed to read {}, run `volt install` to create it",
+                app.lock_file_path.display()
+            )
+        })?;
+
+        let verbose = app.has_flag(&["-v", "--verbose"]);
+
+        let packages: Vec<VoltPackage> = lock_file
+            .dependencies
+            .values()
+            .filter(|lock| {
+                !resume::is_extracted(&app, &store_name(&lock.name, &lock.version, &lock.sha1))
+            })
+            .map(|lock| VoltPackage {
+                name: lock.name.clone(),
+                version: lock.version.clone(),
+                tarball: lock.tarball.clone(),
+                sha1: lock.sha1.clone(),
+                integrity: lock.integrity.clone(),
+                peer_dependencies: vec![],
+                dependencies: Some(lock.dependencies.keys().cloned().collect()),
+                optional_dependencies: vec![],
+                bin: None,
+                os: vec![],
+                cpu: vec![],
+                libc: vec![],
+                source: lock.source,
+                deprecated: None,
+                engines: Default::default(),
+            })
+            .collect();
+
+        let cached = lock_file.dependencies.len() - packages.len();
+
+        let mut workers = FuturesUnordered::new();
+
+        for package in packages.iter() {
+            let app = app.clone();
+
+            workers.push(async move {
+                if verbose {
+                    reporter::get().info(
+                        "Fetching",
+                        &format!("{}@{}", package.name.bright_cyan(), package.version),
+                    );
+                }
+
+                let result = volt_utils::fetch_package(&app, package)
+                    .await
+                    .with_context(|| {
+                        format!("failed to fetch {}@{}", package.name, package.version)
+                    });
+
+                (package, result)
+            });
+        }
+
+        let progress = Progress::start("Fetching packages", workers.len() as u64);
+
+        // Unpublished tarballs are dealt with once the others are fetched
+        let mut missing = vec![];
+
+        while let Some((package, result)) = workers.next().await {
+            match result {
+                Err(error) if network::is_missing(&error) => missing.push(package),
+                result => result.map(|_| ())?,
+            }
+
+            progress.inc(1);
+        }
+
+        progress.finish();
+
+        for package in missing {
+            let substitute = substitute(&app, package).await?;
+
+            lock_file
+                .dependencies
+                .remove(&DependencyID(package.name.clone(), package.version.clone()));
+
+            // The versions of its dependencies aren't known without resolving it again
+            lock_file.dependencies.insert(
+                DependencyID(substitute.name.clone(), substitute.version.clone()),
+                DependencyLock::from_package(&substitute, &HashMap::new()),
+            );
+
+            lock_file.save().context("Failed to save lock file")?;
+        }
+
+        reporter::get().output(&format!(
+            "{}: fetched {} packages, {} already in the store",
+            "success".bright_green(),
+            packages.len(),
+            cached
+        ));
+
+        Ok(())
+    }
+}
@@ -21,32 +21,19 @@ use std::sync::Arc;
 use anyhow::Result;
 use async_trait::async_trait;
 use colored::Colorize;
-use volt_core::{command::Command, VERSION};
+use volt_core::{command::Command, help::Help};
 use volt_utils::app::App;
+use volt_utils::reporter;
 /// Struct implementation for the `Deploy` command.
 pub struct Fix;
 
 #[async_trait]
 impl Command for Fix {
     /// Display a help menu for the `volt deploy` command.
-    fn help() -> String {
-        format!(
-            r#"volt {}
-    
-Deploys your commit to Github.
-
-Usage: {} {} {}
-
-Options: 
-
-  {} {} Output verbose messages on internal operations."#,
-            VERSION.bright_green().bold(),
-            "volt".bright_green().bold(),
-            "deploy".bright_purple(),
-            "[commit]".white(),
-            "--verbose".blue(),
-            "(-v)".yellow()
-        )
+    fn help() -> Help {
+        Help::new("fix", "Fix common errors in the package.json file.")
+            .usage("[flags]")
+            .verbose()
     }
 
     /// Execute the `volt fix` command
@@ -60,7 +47,7 @@ Options:
     /// ## Returns
     /// * `Result<()>`
     async fn exec(_app: Arc<App>) -> Result<()> {
-        println!("{}", "Scanning for errors".bright_cyan());
+        reporter::get().output(&"Scanning for errors".bright_cyan().to_string());
 
         Ok(())
     }
@@ -0,0 +1,104 @@
+/*
+Copyright 2021 Volt Contributors
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+    http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+//! Show the funding links of installed dependencies.
+
+use std::sync::Arc;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use colored::Colorize;
+use serde_json::Value;
+use volt_core::{command::Command, help::Help};
+use volt_utils::app::App;
+use volt_utils::reporter;
+
+use crate::funding;
+
+/// Struct implementation for the `fund` command.
+pub struct Fund;
+
+#[async_trait]
+impl Command for Fund {
+    /// Display a help menu for the `volt fund` command.
+    fn help() -> Help {
+        Help::new(
+            "fund",
+            "Show the funding links of installed dependencies, grouped by link.",
+        )
+        .usage("[flags]")
+        .flag(&["--json"], "Output the funding information as JSON.")
+        .verbose()
+    }
+
+    /// Execute the `volt fund` command
+    ///
+    /// Lists every funding link of the installed packages with the packages asking for it.
+    /// ## Arguments
+    /// * `app` - Instance of the command (`Arc<App>`)
+    /// ## Examples
+    /// ```ignore
+    /// // .exec() is an async call so you need to await it
+    /// Fund.exec(app).await;
+    /// ```
+    /// ## Returns
+    /// * `Result<()>`
+    async fn exec(app: Arc<App>) -> Result<()> {
+        let packages = funding::funded_packages(&app.node_modules_dir);
+
+        if reporter::json() {
+            reporter::get().document(&serde_json::to_string_pretty(&Value::Array(
+                packages.iter().map(|package| package.to_json()).collect(),
+            ))?);
+
+            return Ok(());
+        }
+
+        if packages.is_empty() {
+            reporter::get().info("Fund", "no installed packages ask for funding");
+            return Ok(());
+        }
+
+        let urls = funding::by_url(&packages);
+
+        for (index, (url, dependents)) in urls.iter().enumerate() {
+            let last = index + 1 == urls.len();
+
+            reporter::get().output(&format!(
+                "{} {}",
+                if last { "└─┬" } else { "├─┬" },
+                url.bright_cyan()
+            ));
+
+            for (position, dependent) in dependents.iter().enumerate() {
+                reporter::get().output(&format!(
+                    "{} {} {}",
+                    if last { " " } else { "│" },
+                    if position + 1 == dependents.len() {
+                        "└──"
+                    } else {
+                        "├──"
+                    },
+                    dependent
+                ));
+            }
+        }
+
+        reporter::get().output(&format!(
+            "\n{} packages are looking for funding",
+            packages.len().to_string().bright_green()
+        ));
+
+        Ok(())
+    }
+}
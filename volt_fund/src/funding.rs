@@ -0,0 +1,154 @@
+/*
+Copyright 2021 Volt Contributors
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+    http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+//! Read the `funding` field of installed packages.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::read_to_string;
+use std::path::Path;
+
+use serde_json::{json, Value};
+use walkdir::WalkDir;
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Funding {
+    /// `github`, `opencollective`, `patreon`... when the package says so
+    pub kind: Option<String>,
+    pub url: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct FundedPackage {
+    pub name: String,
+    pub version: String,
+    pub funding: Vec<Funding>,
+}
+
+impl FundedPackage {
+    pub fn to_json(&self) -> Value {
+        json!({
+            "name": self.name,
+            "version": self.version,
+            "funding": self.funding.iter().map(|funding| json!({
+                "type": funding.kind,
+                "url": funding.url,
+            })).collect::<Vec<_>>(),
+        })
+    }
+}
+
+/// Parse a `funding` field, which is a url, a `{ type, url }` object or a list of both.
+pub fn parse_funding(value: &Value) -> Vec<Funding> {
+    match value {
+        Value::String(url) => vec![Funding {
+            kind: None,
+            url: url.clone(),
+        }],
+        Value::Object(object) => object
+            .get("url")
+            .and_then(Value::as_str)
+            .map(|url| Funding {
+                kind: object.get("type").and_then(Value::as_str).map(String::from),
+                url: url.to_string(),
+            })
+            .into_iter()
+            .collect(),
+        Value::Array(values) => values.iter().flat_map(parse_funding).collect(),
+        _ => vec![],
+    }
+}
+
+/// Find the installed packages which ask for funding, once per version.
+pub fn funded_packages(node_modules_dir: &Path) -> Vec<FundedPackage> {
+    let mut packages = BTreeSet::new();
+
+    for entry in WalkDir::new(node_modules_dir)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_name() == "package.json")
+    {
+        let in_node_modules = entry
+            .path()
+            .ancestors()
+            .skip(2)
+            .take(2)
+            .any(|dir| dir.file_name().is_some_and(|name| name == "node_modules"));
+
+        if !in_node_modules {
+            continue;
+        }
+
+        let data: Value = match read_to_string(entry.path())
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+        {
+            Some(data) => data,
+            None => continue,
+        };
+
+        let funding = parse_funding(&data["funding"]);
+
+        if let (Some(name), false) = (data["name"].as_str(), funding.is_empty()) {
+            packages.insert(FundedPackage {
+                name: name.to_string(),
+                version: data["version"].as_str().unwrap_or_default().to_string(),
+                funding,
+            });
+        }
+    }
+
+    packages.into_iter().collect()
+}
+
+/// Group packages by funding url, so maintainers of several packages are listed once.
+pub fn by_url(packages: &[FundedPackage]) -> BTreeMap<String, Vec<String>> {
+    let mut urls: BTreeMap<String, Vec<String>> = BTreeMap::new();
+
+    for package in packages {
+        for funding in package.funding.iter() {
+            urls.entry(funding.url.clone())
+                .or_default()
+                .push(format!("{}@{}", package.name, package.version));
+        }
+    }
+
+    urls
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_every_funding_format() {
+        let funding = parse_funding(&json!([
+            "https://github.com/sponsors/ljharb",
+            { "type": "opencollective", "url": "https://opencollective.com/babel" },
+            { "type": "patreon" },
+        ]));
+
+        assert_eq!(
+            funding,
+            vec![
+                Funding {
+                    kind: None,
+                    url: String::from("https://github.com/sponsors/ljharb"),
+                },
+                Funding {
+                    kind: Some(String::from("opencollective")),
+                    url: String::from("https://opencollective.com/babel"),
+                },
+            ]
+        );
+    }
+}
@@ -20,58 +20,89 @@ use std::sync::Arc;
 
 use anyhow::Result;
 use async_trait::async_trait;
-use colored::Colorize;
-use volt_core::{command::Command, VERSION};
+use volt_core::{command::Command, help};
 use volt_utils::app::App;
+use volt_utils::reporter;
 /// Struct implementation for the `Help` command.
 pub struct Help;
 
 #[async_trait]
 impl Command for Help {
     /// Display a help menu for the `volt help` command.
-    fn help() -> String {
-        format!(
-            r#"volt {}
-    
-Displays help information.
-
-Usage: {} {} {}
-
-Commands:
-
-  {} {} - Install all dependencies for a project.
-  {} {} - Interactively create or update a package.json file for a project.
-  {} {} - Add a dependency to a project.
-  {} {} - Lists the dependency tree of a project.
-  {} {} - Remove a dependency from the package.json file for a project.
-  {} {} - Push changes to a github repository the easy way.
-  {} {} - Clean the volt cache files and metadata.
-  {} {} - Clone a github repository and get setup with all required dependencies.
-  {} {} - Run a defined script.
-  "#,
-            VERSION.bright_green().bold(),
-            "volt".bright_green().bold(),
-            "[commands]".bright_purple(),
-            "[flags]".bright_purple(),
-            "*".bright_magenta().bold(),
-            "install".bright_blue(),
-            "*".bright_magenta().bold(),
-            "init".bright_blue(),
-            "*".bright_magenta().bold(),
-            "add".bright_blue(),
-            "*".bright_magenta().bold(),
-            "list".bright_blue(),
-            "*".bright_magenta().bold(),
-            "remove".bright_blue(),
-            "*".bright_magenta().bold(),
-            "cache".bright_blue(),
-            "*".bright_magenta().bold(),
-            "deploy".bright_blue(),
-            "*".bright_magenta().bold(),
-            "clone".bright_blue(),
-            "*".bright_magenta().bold(),
-            "run".bright_blue(),
-        )
+    fn help() -> help::Help {
+        help::Help::new("help", "Displays help information.")
+            .usage("[command]")
+            .command("install", "Install all dependencies for a project.")
+            .command("init", "Interactively create or update a package.json file for a project.")
+            .command("add", "Add a dependency to a project.")
+            .command("list", "Lists the dependency tree of a project.")
+            .command("remove", "Remove a dependency from the package.json file for a project.")
+            .command(
+                "deploy",
+                "Push changes to a github repository, or bundle a workspace package for production.",
+            )
+            .command("cache", "Clean the volt cache files and metadata.")
+            .command(
+                "store",
+                "List, verify and prune the volt store and volt x environments, like cache.",
+            )
+            .command("clean", "Remove the .volt state of a project.")
+            .command(
+                "clone",
+                "Clone a github repository and get setup with all required dependencies.",
+            )
+            .command("run", "Run a defined script.")
+            .command("x", "Run a binary from a package without adding it to a project.")
+            .command("lint-manifest", "Validate the package.json file of a project.")
+            .command(
+                "fetch",
+                "Download the packages in the lock file without touching node_modules.",
+            )
+            .command(
+                "check-engines",
+                "Check the engines of installed packages against the current Node and npm.",
+            )
+            .command("fund", "Show the funding links of installed dependencies.")
+            .command("ping", "Check the registry is reachable and the auth token is valid.")
+            .command("whoami", "Show the username of the registry auth token.")
+            .command("env", "Show where volt keeps the store, cache and config.")
+            .command("completions", "Print the completion script of a shell.")
+            .command("publish", "Publish a package to the registry.")
+            .command("docs", "Open the documentation of a package.")
+            .command("repo", "Open the source repository of a package.")
+            .command("bugs", "Open the issue tracker of a package.")
+            .command("lock", "Compare the lock file against a git revision or another lock file.")
+            .command("audit", "Check installed packages for known vulnerabilities.")
+            .command("outdated", "List dependencies which have newer versions.")
+            .command("why", "Show why a package is installed.")
+            .command("stat", "Show the downloads of a package, or summarize the project without one.")
+            .command("stats", "Summarize the dependencies of the project, like stat without a package.")
+            .command("explain", "Explain why the resolver chose a version of a package.")
+            .command("config", "Switch between the config profiles of your .voltrc.")
+            .command("pin", "Replace caret and tilde ranges with the locked versions.")
+            .command("unpin", "Put back the ranges of dependencies pinned with volt pin.")
+            .command("dockerfile", "Print a multi-stage Dockerfile installing from the lock file.")
+            .command("inspect", "List the files and install scripts of a package before installing it.")
+            .command("diff", "Show what changed between two published versions of a package.")
+            .command("ui", "Browse and manage dependencies in a terminal dashboard.")
+            .flag(&["--cwd=<dir>"], "Run as if volt was started in <dir>, for any command.")
+            .flag(
+                &["--prefix=<dir>"],
+                "Use <dir> as the project, or as the global directory with --global.",
+            )
+            .flag(
+                &["--reporter=<name>"],
+                "Show progress and messages as human (default), json or quiet.",
+            )
+            .flag(
+                &["--timing"],
+                "Print how long the command took and how many packuments were fetched, and keep a trace in .volt/timings.",
+            )
+            .flag(
+                &["--network-report"],
+                "Print the requests, bytes, cache hits, retries and status codes of each registry.",
+            )
+            .example("volt help add", "Show the help of volt add.")
     }
 
     /// Execute the `volt help` command
@@ -90,7 +121,7 @@ Commands:
     /// ## Returns
     /// * `Result<()>`
     async fn exec(_app: Arc<App>) -> Result<()> {
-        println!("{}", Self::help());
+        reporter::get().output(&Self::help().to_string());
         Ok(())
     }
 }
@@ -18,34 +18,22 @@ use std::sync::Arc;
 use anyhow::Result;
 use async_trait::async_trait;
 use colored::Colorize;
-use volt_core::{command::Command, model::http_manager::get_package, VERSION};
+use volt_core::{command::Command, help::Help, model::http_manager::get_package};
 use volt_utils::{
     app::App,
     package::{Package, PackageJson, Version},
+    provenance, reporter, resolver,
 };
 
 pub struct Info {}
 
 #[async_trait]
 impl Command for Info {
-    fn help() -> String {
-        format!(
-            r#"volt {}
-    
-Shows the information of a package 
-
-Usage: {} {} {}
-
-Options: 
-
-  {} {} Output verbose messages on internal operations."#,
-            VERSION.bright_green().bold(),
-            "volt".bright_green().bold(),
-            "deploy".bright_purple(),
-            "[commit]".white(),
-            "--verbose".blue(),
-            "(-v)".yellow()
-        )
+    fn help() -> Help {
+        Help::new("info", "Shows the information of a package.")
+            .usage("[package] [flags]")
+            .arg("[package]", "The package, the current project by default.")
+            .verbose()
     }
 
     /// Execute the `volt info` command
@@ -63,64 +51,120 @@ Options:
     /// * `Result<()>`
     async fn exec(app: Arc<App>) -> Result<()> {
         let mut name = String::new();
-        if !std::env::current_dir()?.join("package.json").exists() {
-            println!(
+        if !app.current_dir.join("package.json").exists() {
+            reporter::get().output(&format!(
                 "{}: {}\n",
                 "Warning:".yellow().bold(),
                 "Could not find a package.json file in the current directory"
-            );
+            ));
             name = volt_utils::get_basename(app.current_dir.to_str().unwrap()).to_string()
         } else {
-            let package_file = PackageJson::from("package.json");
+            let package_file =
+                PackageJson::from(&app.current_dir.join("package.json").to_string_lossy());
             name = package_file.name;
         }
         let package: Package = get_package(&name).await?.unwrap();
         if package.description == None {
-            println!("{}", "<No description provided>".yellow().bold());
+            reporter::get().output(&"<No description provided>".yellow().bold().to_string());
         } else {
-            println!("{}\n", package.description.unwrap());
+            reporter::get().output(&format!("{}\n", package.description.unwrap()));
         }
         if package.keywords == None {
-            println!("{}", "<No Keyword provided>".yellow().bold());
+            reporter::get().output(&"<No Keyword provided>".yellow().bold().to_string());
         } else {
-            print!("{}: ", "Keywords".blue().bold());
-            for keyword in package.keywords.unwrap().iter() {
-                print!("{} ", keyword.green())
-            }
-            print!("\n")
+            let keywords: Vec<String> = package
+                .keywords
+                .unwrap()
+                .iter()
+                .map(|keyword| keyword.green().to_string())
+                .collect();
+            reporter::get().output(&format!(
+                "{}: {}",
+                "Keywords".blue().bold(),
+                keywords.join(" ")
+            ));
         }
-        print!("\n");
+        reporter::get().output("");
         let latest_version = package.dist_tags.latest;
-        println!("Latest Version: v{}\n", latest_version.blue());
+        reporter::get().output(&format!("Latest Version: v{}\n", latest_version.blue()));
         let latestpackage: &Version = &package.versions[&latest_version];
-        println!("dist:");
-        println!("  tarball: {}", latestpackage.dist.tarball.blue().bold());
-        println!("  shasum: {}", latestpackage.dist.shasum.blue().bold());
+        reporter::get().output("dist:");
+        reporter::get().output(&format!(
+            "  tarball: {}",
+            latestpackage.dist.tarball.blue().bold()
+        ));
+        reporter::get().output(&format!(
+            "  shasum: {}",
+            latestpackage.dist.shasum.blue().bold()
+        ));
         if latestpackage.dist.integrity != "" {
-            println!(
+            reporter::get().output(&format!(
                 "  integrity: {}",
                 latestpackage.dist.integrity.blue().bold()
-            );
+            ));
         }
         if latestpackage.dist.unpacked_size != 0 {
-            println!(
+            reporter::get().output(&format!(
                 "  unpackedSize: {}{}",
                 (latestpackage.dist.unpacked_size / 1024)
                     .to_string()
                     .blue()
                     .bold(),
                 "kb".blue().bold()
-            );
+            ));
+        }
+
+        // The packument has the attestations, the typed model doesn't
+        let packument = resolver::packument(&app, name.clone()).await?;
+        let dist = &packument["versions"][&latest_version]["dist"];
+
+        if let Some(message) = resolver::deprecation(&packument, &latest_version) {
+            reporter::get().output(&format!(
+                "\n{}: {}",
+                "deprecated".bright_red().bold(),
+                message
+            ));
+        }
+
+        match provenance::fetch(&app, &name, &latest_version, dist).await {
+            Ok(Some(provenance)) => {
+                reporter::get().output(&format!(
+                    "\nprovenance: {}",
+                    "attested".bright_green().bold()
+                ));
+
+                let lines = [
+                    ("built by", provenance.builder),
+                    ("repository", provenance.repository),
+                    ("workflow", provenance.workflow),
+                    ("ref", provenance.git_ref),
+                    ("commit", provenance.commit),
+                ];
+
+                for (label, value) in lines.iter() {
+                    if let Some(value) = value {
+                        reporter::get().output(&format!("  {}: {}", label, value.blue().bold()));
+                    }
+                }
+            }
+            Ok(None) => {
+                reporter::get().output(&format!("\nprovenance: {}", "none".yellow().bold()))
+            }
+            Err(error) => reporter::get().output(&format!(
+                "\nprovenance: {} ({:#})",
+                "unverifiable".bright_red().bold(),
+                error
+            )),
         }
 
         // println!("{:#?}", latestpackage);
-        println!("{}", "\nmaintainers:");
+        reporter::get().output(&"\nmaintainers:".to_string());
         for maintainer in latestpackage.maintainers.iter() {
-            println!(
+            reporter::get().output(&format!(
                 "  - {}<{}>",
                 maintainer.email,
                 maintainer.name.yellow().bold()
-            )
+            ))
         }
         Ok(())
     }
@@ -14,25 +14,31 @@
     limitations under the License.
 */
 
-use std::fs::File;
+use std::fs::{self, File};
 use std::io::Write;
 use std::sync::Arc;
 use std::{env, process};
 
-use anyhow::Result;
+use anyhow::{anyhow, Context, Result};
 use async_trait::async_trait;
 use colored::Colorize;
 use regex::Regex;
+use serde_json::json;
 use volt_core::classes::init_data::InitData;
 use volt_core::classes::init_data::License;
-use volt_core::command::Command;
+use volt_core::model::lock_file::LockFile;
 use volt_core::prompt::prompts::Confirm;
 use volt_core::prompt::prompts::Input;
 use volt_core::prompt::prompts::Select;
+use volt_core::{command::Command, help::Help};
 use volt_utils::app::App;
 // use volt_core::utils;
-use volt_core::VERSION;
 use volt_utils::get_git_config;
+use volt_utils::package::JsonFormat;
+use volt_utils::reporter;
+use volt_utils::workspace::Workspace;
+
+use crate::template::{self, TemplateSource};
 
 /// Struct implementation for the `Init` command.
 pub struct Init;
@@ -40,27 +46,26 @@ pub struct Init;
 #[async_trait]
 impl Command for Init {
     /// Display a help menu for the `volt init` command.
-    fn help() -> String {
-        format!(
-            r#"volt {}
-    
-Interactively create or update a package.json file for a project
-
-Usage: {} {} {}
-    
-Options:
-    
-  {} {} Initialize a package.json file without any prompts.  
-  {} {} Output verbose messages on internal operations."#,
-            VERSION.bright_green().bold(),
-            "volt".bright_green().bold(),
-            "init".bright_purple(),
-            "[flags]".white(),
-            "--yes".blue(),
-            "(-y)".yellow(),
-            "--verbose".blue(),
-            "(-v)".yellow()
+    fn help() -> Help {
+        Help::new(
+            "init",
+            "Interactively create or update a package.json file for a project.",
+        )
+        .usage("[flags]")
+        .flag(
+            &["--yes", "-y"],
+            "Initialize a package.json file without any prompts.",
+        )
+        .flag(
+            &["--template <source>"],
+            "Scaffold the project from github:user/repo or a registry package.",
         )
+        .flag(
+            &["--workspace"],
+            "Create a monorepo with a packages/ directory.",
+        )
+        .flag(&["--tsconfig"], "Add a shared tsconfig to a new workspace.")
+        .verbose()
     }
 
     /// Execute the `volt init` command
@@ -79,21 +84,21 @@ Options:
     /// ## Returns
     /// * `Result<()>`
     async fn exec(app: Arc<App>) -> Result<()> {
-        let temp =
-            volt_utils::get_basename(&env::current_dir().unwrap().to_string_lossy()).to_string();
+        if app.has_flag(&["--template"]) || app.flag_value(&["--template"]).is_some() {
+            return init_from_template(&app).await;
+        }
+
+        if app.has_flag(&["--workspace"]) {
+            return init_workspace(&app);
+        }
+
+        let temp = volt_utils::get_basename(&app.current_dir.to_string_lossy()).to_string();
         let split: Vec<&str> = temp.split('\\').collect::<Vec<&str>>();
         let cwd: String = split[split.len() - 1].to_string();
 
         let data = if app.has_flag(&["-y", "--yes"]) {
             // Set name to current directory name
-            let name = env::current_dir()
-                .map(|dir| {
-                    dir.file_name()
-                        .map(|file_name| file_name.to_string_lossy().to_string())
-                })
-                .ok()
-                .flatten()
-                .unwrap_or_else(|| "app".to_string());
+            let name = directory_name(&app);
 
             let version = "0.1.0".to_string();
 
@@ -101,24 +106,7 @@ Options:
 
             let main = "index.js".to_string();
 
-            let author = {
-                let git_user_name = get_git_config("user.name")
-                    .ok()
-                    .flatten()
-                    .unwrap_or_else(String::new);
-
-                let git_email = get_git_config("user.email")
-                    .ok()
-                    .flatten()
-                    .map(|email| format!("<{}>", email))
-                    .unwrap_or_else(String::new);
-
-                if git_user_name.is_empty() && git_email.is_empty() {
-                    None
-                } else {
-                    Some([git_user_name, git_email].join(" "))
-                }
-            };
+            let author = git_author();
 
             let repository = get_git_config("remote.origin.url").ok().flatten();
 
@@ -153,7 +141,7 @@ Options:
                  // It passes and everyone is happy
                  // it continues with the other code
             } else {
-                println!("{}", "Name cannot contain special characters".red());
+                reporter::get().output(&"Name cannot contain special characters".red().to_string());
                 loop {
                     let input: Input = Input {
                         message: String::from("name"),
@@ -167,7 +155,8 @@ Options:
                     if re_name.is_match(&name) {
                         break;
                     } else {
-                        println!("{}", "Name cannot contain special characters".red());
+                        reporter::get()
+                            .output(&"Name cannot contain special characters".red().to_string());
                     }
                 }
             }
@@ -325,7 +314,7 @@ Options:
             }
         };
 
-        let mut file = File::create(r"package.json").unwrap();
+        let mut file = File::create(app.current_dir.join("package.json")).unwrap();
         if let Err(error) = file.write(data.dump().as_bytes()) {
             eprintln!(
                 "{} : {} {}",
@@ -336,7 +325,242 @@ Options:
             process::exit(1);
         }
 
-        println!("{}", "Successfully Initialized package.json".bright_green());
+        reporter::get().output(
+            &"Successfully Initialized package.json"
+                .bright_green()
+                .to_string(),
+        );
+
+        // Register the new package with the enclosing workspace
+        if let Some(mut workspace) = app.current_dir.parent().and_then(Workspace::find) {
+            if !workspace.includes(&app.current_dir) {
+                workspace.add_member(&app.current_dir)?;
+
+                reporter::get().output(&format!(
+                    "{} {}",
+                    "Registered package in workspace".bright_green(),
+                    workspace.root.display()
+                ));
+            }
+        }
+
         Ok(())
     }
 }
+
+/// Name of the project directory, used as the default package name.
+fn directory_name(app: &App) -> String {
+    app.current_dir
+        .file_name()
+        .map(|file_name| file_name.to_string_lossy().to_string())
+        .unwrap_or_else(|| "app".to_string())
+}
+
+/// Author (`name <email>`) taken from the git config.
+fn git_author() -> Option<String> {
+    let git_user_name = get_git_config("user.name")
+        .ok()
+        .flatten()
+        .unwrap_or_default();
+
+    let git_email = get_git_config("user.email")
+        .ok()
+        .flatten()
+        .map(|email| format!("<{}>", email))
+        .unwrap_or_default();
+
+    if git_user_name.is_empty() && git_email.is_empty() {
+        None
+    } else {
+        Some([git_user_name, git_email].join(" "))
+    }
+}
+
+/// `volt init --template github:user/repo`
+async fn init_from_template(app: &App) -> Result<()> {
+    // --template=<source> or --template <source>
+    let source = match app
+        .flag_value(&["--template"])
+        .or_else(|| app.args.get(1).cloned())
+    {
+        Some(source) => source,
+        None => {
+            eprintln!(
+                "{}: expected a template source",
+                "error".bright_red().bold()
+            );
+            process::exit(1);
+        }
+    };
+
+    let name = directory_name(app);
+    let author = git_author().unwrap_or_default();
+
+    reporter::get().output(&format!(
+        "{} {}",
+        "Downloading template".bright_purple(),
+        source
+    ));
+
+    let template = TemplateSource::parse(&source).fetch().await?;
+    let skipped = template::apply(&template, &app.current_dir, &name, &author);
+
+    std::fs::remove_dir_all(template::download_dir()).ok();
+
+    for path in skipped? {
+        reporter::get().output(&format!(
+            "{}: {} already exists, skipping",
+            " warn ".black().on_bright_yellow(),
+            path.display().to_string().bright_yellow()
+        ));
+    }
+
+    if app.current_dir.join("package.json").exists() {
+        reporter::get().output(&"$ volt install".truecolor(147, 148, 148).to_string());
+
+        let status = process::Command::new(env::current_exe()?)
+            .arg("install")
+            .current_dir(&app.current_dir)
+            .status()?;
+
+        if !status.success() {
+            return Err(anyhow!("failed to install template dependencies"));
+        }
+    }
+
+    reporter::get().output(&format!(
+        "{} {}",
+        "Successfully Initialized".bright_green(),
+        name
+    ));
+    Ok(())
+}
+
+/// `volt init --workspace`
+fn init_workspace(app: &App) -> Result<()> {
+    let name = directory_name(app);
+
+    let tsconfig = if app.has_flag(&["--tsconfig"]) {
+        true
+    } else if app.has_flag(&["-y", "--yes"]) {
+        false
+    } else {
+        Confirm {
+            message: String::from("shared tsconfig"),
+            default: false,
+        }
+        .run()?
+    };
+
+    let path = app.current_dir.join("package.json");
+
+    // An existing package.json becomes the root of the workspace, keeping what it has
+    let source = fs::read_to_string(&path).unwrap_or_default();
+    let mut data = if source.trim().is_empty() {
+        json!({ "name": name, "version": "1.0.0" })
+    } else {
+        serde_json::from_str(&source)
+            .with_context(|| format!("failed to parse {}", path.display()))?
+    };
+
+    let object = data
+        .as_object_mut()
+        .ok_or_else(|| anyhow!("{} is not a JSON object", path.display()))?;
+
+    if object.contains_key("workspaces") {
+        return Err(anyhow!("{} already declares workspaces", path.display()));
+    }
+
+    object.insert(String::from("private"), json!(true));
+    object.insert(String::from("workspaces"), json!(["packages/*"]));
+
+    if !object.contains_key("author") {
+        if let Some(author) = git_author() {
+            object.insert(String::from("author"), json!(author));
+        }
+    }
+
+    fs::write(&path, JsonFormat::detect(&source).write(&data))?;
+
+    fs::create_dir_all(app.current_dir.join("packages"))?;
+
+    if tsconfig {
+        let config = json!({
+            "compilerOptions": {
+                "target": "es2019",
+                "module": "commonjs",
+                "strict": true,
+                "declaration": true,
+                "composite": true,
+                "esModuleInterop": true,
+                "skipLibCheck": true,
+            },
+        });
+
+        let path = app.current_dir.join("tsconfig.base.json");
+
+        if path.exists() {
+            reporter::get().warn("tsconfig.base.json already exists, skipping");
+        } else {
+            fs::write(path, serde_json::to_string_pretty(&config)? + "\n")?;
+        }
+    }
+
+    if !app.lock_file_path.exists() {
+        LockFile::new(app.lock_file_path.clone())
+            .save()
+            .context("failed to create lock file")?;
+    }
+
+    reporter::get().output(
+        &"Successfully Initialized workspace"
+            .bright_green()
+            .to_string(),
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use volt_utils::config::Config;
+
+    #[test]
+    fn turns_an_existing_package_into_a_workspace() {
+        let root = env::temp_dir().join(format!("volt-init-workspace-{}", process::id()));
+        fs::create_dir_all(&root).unwrap();
+        fs::write(
+            root.join("package.json"),
+            "{\n    \"name\": \"monorepo\",\n    \"scripts\": { \"test\": \"jest\" }\n}\n",
+        )
+        .unwrap();
+
+        let app = App {
+            current_dir: root.clone(),
+            home_dir: root.join("home"),
+            node_modules_dir: root.join("node_modules"),
+            volt_dir: root.join("home/.volt"),
+            cache_dir: root.join("home/.volt/.cache"),
+            lock_file_path: root.join("volt.lock"),
+            config_file: root.join(".voltrc"),
+            args: vec![String::from("init")],
+            flags: vec![String::from("--workspace"), String::from("--yes")],
+            config: Config::default(),
+        };
+
+        init_workspace(&app).unwrap();
+
+        let source = fs::read_to_string(root.join("package.json")).unwrap();
+        let data: serde_json::Value = serde_json::from_str(&source).unwrap();
+
+        assert!(source.starts_with("{\n    \"name\": \"monorepo\",\n    \"scripts\""));
+        assert_eq!(data["scripts"]["test"], "jest");
+        assert_eq!(data["workspaces"], json!(["packages/*"]));
+        assert!(root.join("packages").is_dir());
+
+        // Running it again doesn't touch the workspace
+        assert!(init_workspace(&app).is_err());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
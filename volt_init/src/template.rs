@@ -0,0 +1,305 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Scaffold a project from a remote template (`github:user/repo` or a registry package).
+
+use std::env::temp_dir;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use anyhow::{anyhow, Context, Result};
+use flate2::read::GzDecoder;
+use serde_json::Value;
+use tar::Archive;
+use volt_core::model::http_manager::get_package;
+use volt_utils::package::JsonFormat;
+use walkdir::WalkDir;
+
+/// Where a template is downloaded from.
+#[derive(Debug, PartialEq)]
+pub enum TemplateSource {
+    /// `github:user/repo` with an optional `#ref`
+    GitHub {
+        repository: String,
+        reference: Option<String>,
+    },
+    /// Any package on the registry
+    Registry(String),
+}
+
+impl TemplateSource {
+    pub fn parse(source: &str) -> Self {
+        match source.strip_prefix("github:") {
+            Some(repository) => {
+                let mut parts = repository.splitn(2, '#');
+
+                TemplateSource::GitHub {
+                    repository: parts.next().unwrap().to_string(),
+                    reference: parts.next().map(String::from),
+                }
+            }
+            None => TemplateSource::Registry(source.to_string()),
+        }
+    }
+
+    /// Download the template into [`download_dir()`] and return the template root.
+    pub async fn fetch(&self) -> Result<PathBuf> {
+        let destination = download_dir();
+
+        if destination.exists() {
+            fs::remove_dir_all(&destination)?;
+        }
+
+        match self {
+            TemplateSource::GitHub {
+                repository,
+                reference,
+            } => {
+                let mut command = process::Command::new("git");
+
+                command
+                    .arg("clone")
+                    .arg("--depth=1")
+                    .arg(format!("https://github.com/{}.git", repository));
+
+                if let Some(reference) = reference {
+                    command.arg("--branch").arg(reference);
+                }
+
+                let status = command
+                    .arg(&destination)
+                    .status()
+                    .context("failed to run git")?;
+
+                if !status.success() {
+                    return Err(anyhow!("failed to clone github:{}", repository));
+                }
+
+                fs::remove_dir_all(destination.join(".git")).ok();
+
+                Ok(destination)
+            }
+            TemplateSource::Registry(name) => {
+                let package = get_package(name)
+                    .await?
+                    .ok_or_else(|| anyhow!("template {} does not exist", name))?;
+
+                let version = package
+                    .versions
+                    .get(&package.dist_tags.latest)
+                    .ok_or_else(|| anyhow!("template {} has no published versions", name))?;
+
+                let bytes = reqwest::get(version.dist.tarball.as_str())
+                    .await?
+                    .bytes()
+                    .await?;
+
+                Archive::new(GzDecoder::new(&*bytes))
+                    .unpack(&destination)
+                    .context("unable to unpack template")?;
+
+                // Tarballs from the registry are wrapped in a `package/` directory,
+                // templates may keep the project files in a `template/` directory
+                let root = destination.join("package");
+                let template = root.join("template");
+
+                if template.exists() {
+                    Ok(template)
+                } else {
+                    Ok(root)
+                }
+            }
+        }
+    }
+}
+
+/// Temporary directory templates are downloaded to.
+pub fn download_dir() -> PathBuf {
+    temp_dir()
+        .join("volt")
+        .join(format!("template-{}", process::id()))
+}
+
+/// Replace `{{name}}` and `{{author}}` in `contents`, escaped in JSON files where they are inside
+/// strings.
+fn substitute(contents: &str, path: &Path, name: &str, author: &str) -> String {
+    let json = path
+        .extension()
+        .is_some_and(|extension| extension == "json");
+
+    let escape = |value: &str| {
+        if !json {
+            return value.to_string();
+        }
+
+        let quoted = serde_json::to_string(value).unwrap_or_default();
+        quoted[1..quoted.len() - 1].to_string()
+    };
+
+    contents
+        .replace("{{name}}", &escape(name))
+        .replace("{{author}}", &escape(author))
+}
+
+/// Copy a downloaded template into `target`, replacing `{{name}}` and `{{author}}` in text files.
+///
+/// Existing files are never overwritten, the paths which were skipped are returned.
+pub fn apply(template: &Path, target: &Path, name: &str, author: &str) -> Result<Vec<PathBuf>> {
+    let mut skipped = vec![];
+
+    for entry in WalkDir::new(template).min_depth(1) {
+        let entry = entry?;
+        let relative = entry.path().strip_prefix(template)?;
+        let destination = target.join(relative);
+
+        if entry.file_type().is_dir() {
+            fs::create_dir_all(&destination)?;
+            continue;
+        }
+
+        if destination.exists() {
+            skipped.push(relative.to_path_buf());
+            continue;
+        }
+
+        match fs::read_to_string(entry.path()) {
+            Ok(contents) => fs::write(&destination, substitute(&contents, relative, name, author))?,
+            // Binary files are copied as they are
+            Err(_) => {
+                fs::copy(entry.path(), &destination)?;
+            }
+        }
+    }
+
+    let package_json = target.join("package.json");
+
+    if package_json.exists() && !skipped.iter().any(|path| path == Path::new("package.json")) {
+        let source = fs::read_to_string(&package_json)?;
+        let mut data: Value = serde_json::from_str(&source)
+            .context("the package.json of the template is not valid JSON")?;
+
+        let object = data
+            .as_object_mut()
+            .ok_or_else(|| anyhow!("the package.json of the template is not a JSON object"))?;
+
+        object.insert(String::from("name"), Value::String(name.to_string()));
+
+        if !author.is_empty() {
+            object.insert(String::from("author"), Value::String(author.to_string()));
+        }
+
+        fs::write(&package_json, JsonFormat::detect(&source).write(&data))?;
+    }
+
+    Ok(skipped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn template(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = temp_dir().join(format!("volt-template-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        for (path, contents) in files {
+            let path = dir.join("template").join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+
+        fs::create_dir_all(dir.join("project")).unwrap();
+        dir
+    }
+
+    #[test]
+    fn applies_a_template() {
+        let dir = template(
+            "apply",
+            &[
+                (
+                    "package.json",
+                    "{\n  \"name\": \"template\",\n  \"main\": \"index.js\"\n}\n",
+                ),
+                ("src/index.js", "console.log('{{name}} by {{author}}')"),
+                ("README.md", "# {{name}}"),
+            ],
+        );
+        fs::write(dir.join("project").join("README.md"), "# mine").unwrap();
+
+        let skipped = apply(
+            &dir.join("template"),
+            &dir.join("project"),
+            "app",
+            "Jane <jane@example.com>",
+        )
+        .unwrap();
+
+        assert_eq!(skipped, vec![PathBuf::from("README.md")]);
+        assert_eq!(
+            fs::read_to_string(dir.join("project/src/index.js")).unwrap(),
+            "console.log('app by Jane <jane@example.com>')"
+        );
+        assert_eq!(
+            fs::read_to_string(dir.join("project/README.md")).unwrap(),
+            "# mine"
+        );
+        assert_eq!(
+            fs::read_to_string(dir.join("project/package.json")).unwrap(),
+            "{\n  \"name\": \"app\",\n  \"main\": \"index.js\",\n  \"author\": \"Jane <jane@example.com>\"\n}\n"
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn escapes_values_in_json_files() {
+        let dir = template(
+            "escape",
+            &[(
+                "package.json",
+                "{\n  \"name\": \"template\",\n  \"description\": \"{{name}} by {{author}}\"\n}\n",
+            )],
+        );
+
+        apply(
+            &dir.join("template"),
+            &dir.join("project"),
+            "app",
+            "Jane \"JJ\" Doe \\o/",
+        )
+        .unwrap();
+
+        let data: Value =
+            serde_json::from_str(&fs::read_to_string(dir.join("project/package.json")).unwrap())
+                .unwrap();
+        assert_eq!(data["description"], "app by Jane \"JJ\" Doe \\o/");
+        assert_eq!(data["author"], "Jane \"JJ\" Doe \\o/");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn rejects_a_package_json_which_is_not_an_object() {
+        let dir = template("array", &[("package.json", "[]")]);
+
+        let error = apply(&dir.join("template"), &dir.join("project"), "app", "").unwrap_err();
+        assert!(error.to_string().contains("not a JSON object"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
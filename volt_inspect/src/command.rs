@@ -0,0 +1,288 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Show what the published tarballs of a package hold before installing them.
+
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use colored::Colorize;
+use serde_json::json;
+use volt_core::{command::Command, help::Help};
+use volt_utils::{
+    app::App, fetcher, network::format_bytes, parse_package_spec, reporter, resolver,
+    volt_api::VoltPackage,
+};
+
+use crate::contents::{self, Contents};
+use crate::diff::{self, Status};
+
+/// The package `spec` (`react@18`, the latest version without one) stands for, and its tarball.
+pub async fn download(app: &App, spec: &str) -> Result<(VoltPackage, bytes::Bytes)> {
+    let (name, range) = parse_package_spec(spec);
+    let package = resolver::select(app, &name, range.as_deref().unwrap_or("latest")).await?;
+
+    let bytes = fetcher::fetch(app, &package).await?;
+
+    if !package.sha1.is_empty() && App::calc_hash(&bytes)? != package.sha1 {
+        return Err(anyhow!(
+            "the tarball of {}@{} doesn't match its checksum",
+            package.name,
+            package.version
+        ));
+    }
+
+    Ok((package, bytes))
+}
+
+/// Struct implementation for the `inspect` command.
+pub struct Inspect;
+
+#[async_trait]
+impl Command for Inspect {
+    /// Display a help menu for the `volt inspect` command.
+    fn help() -> Help {
+        Help::new(
+            "inspect",
+            "List the files of a published package without installing it.",
+        )
+        .usage("[package] [flags]")
+        .arg(
+            "[package]",
+            "The package, with an optional version or tag (react@18).",
+        )
+        .flag(&["--json"], "Print the files and findings as JSON.")
+        .verbose()
+        .example(
+            "volt inspect sharp",
+            "Check the install scripts and native files of sharp.",
+        )
+    }
+
+    /// Execute the `volt inspect` command
+    ///
+    /// Downloads the tarball of a package and lists what it holds.
+    /// ## Arguments
+    /// * `app` - Instance of the command (`Arc<App>`)
+    /// ## Examples
+    /// ```ignore
+    /// // .exec() is an async call so you need to await it
+    /// Inspect.exec(app).await;
+    /// ```
+    /// ## Returns
+    /// * `Result<()>`
+    async fn exec(app: Arc<App>) -> Result<()> {
+        let spec = app
+            .args
+            .get(1)
+            .ok_or_else(|| anyhow!("missing package, run volt inspect <package>[@version]"))?;
+
+        let (package, bytes) = download(&app, spec).await?;
+
+        let contents = Contents::read(&bytes)?;
+
+        if reporter::json() {
+            let mut report = contents.to_json();
+            report["name"] = json!(package.name);
+            report["version"] = json!(package.version);
+            report["tarballSize"] = json!(bytes.len());
+
+            reporter::get().document(&serde_json::to_string_pretty(&report)?);
+            return Ok(());
+        }
+
+        reporter::get().output(&format!(
+            "{}@{} tarball {}, unpacked {} in {} files\n",
+            package.name.bright_cyan().bold(),
+            package.version,
+            format_bytes(bytes.len()),
+            format_bytes(contents.unpacked_size() as usize),
+            contents.files.len()
+        ));
+
+        for file in contents.files.iter() {
+            reporter::get().output(&format!(
+                "{:>9}  {}",
+                format_bytes(file.size as usize),
+                file.path
+            ));
+        }
+
+        reporter::get().output("");
+
+        if contents.install_scripts.is_empty() {
+            reporter::get().output(&format!("install scripts: {}", "none".bright_green()));
+        } else {
+            reporter::get().output("install scripts:");
+
+            for (name, command) in contents.install_scripts.iter() {
+                reporter::get().output(&format!("  {} {}", format!("{}:", name).yellow(), command));
+            }
+        }
+
+        if contents.native.is_empty() {
+            reporter::get().output(&format!("native build files: {}", "none".bright_green()));
+        } else {
+            reporter::get().output(&format!(
+                "native build files: {}",
+                contents.native.join(", ").yellow()
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Struct implementation for the `diff` command.
+pub struct Diff;
+
+#[async_trait]
+impl Command for Diff {
+    /// Display a help menu for the `volt diff` command.
+    fn help() -> Help {
+        Help::new(
+            "diff",
+            "Show what changed between two published versions of a package.",
+        )
+        .usage("[package@version] [package@version] [flags]")
+        .arg("[package@version]", "The old and the new version.")
+        .flag(&["--name-only"], "Only list the files which changed.")
+        .flag(
+            &["--max-size=<bytes>"],
+            "Skip the text diff of larger files (100000).",
+        )
+        .verbose()
+        .example(
+            "volt diff left-pad@1.2.0 left-pad@1.3.0",
+            "Review the changes of left-pad 1.3.0 before upgrading.",
+        )
+    }
+
+    /// Execute the `volt diff` command
+    ///
+    /// Downloads the tarballs of two versions and prints the files which differ.
+    /// ## Arguments
+    /// * `app` - Instance of the command (`Arc<App>`)
+    /// ## Examples
+    /// ```ignore
+    /// // .exec() is an async call so you need to await it
+    /// Diff.exec(app).await;
+    /// ```
+    /// ## Returns
+    /// * `Result<()>`
+    async fn exec(app: Arc<App>) -> Result<()> {
+        let (old_spec, new_spec) = match (app.args.get(1), app.args.get(2)) {
+            (Some(old), Some(new)) => (old, new),
+            _ => {
+                return Err(anyhow!(
+                    "missing versions, run volt diff <package>@<version> <package>@<version>"
+                ))
+            }
+        };
+
+        let max_size = match app.flag_value(&["--max-size"]) {
+            Some(value) => value
+                .parse()
+                .map_err(|_| anyhow!("--max-size should be a number of bytes, not {}", value))?,
+            None => diff::DEFAULT_MAX_SIZE,
+        };
+
+        let (old_package, old_bytes) = download(&app, old_spec).await?;
+        let (new_package, new_bytes) = download(&app, new_spec).await?;
+
+        let old = contents::files(&old_bytes)?;
+        let new = contents::files(&new_bytes)?;
+        let changes = diff::changes(&old, &new);
+
+        reporter::get().output(&format!(
+            "{}@{} → {}@{}: {} files changed\n",
+            old_package.name.bright_cyan().bold(),
+            old_package.version,
+            new_package.name.bright_cyan().bold(),
+            new_package.version,
+            changes.len()
+        ));
+
+        let width = changes
+            .iter()
+            .map(|change| change.path.len())
+            .max()
+            .unwrap_or(0);
+
+        for change in changes.iter() {
+            let letter = match change.status {
+                Status::Added => change.status.letter().bright_green(),
+                Status::Removed => change.status.letter().bright_red(),
+                Status::Modified => change.status.letter().yellow(),
+            };
+
+            let lines = match change.lines {
+                Some((added, removed)) => format!(
+                    "{} {}",
+                    format!("+{}", added).bright_green(),
+                    format!("-{}", removed).bright_red()
+                ),
+                None => String::from("binary"),
+            };
+
+            reporter::get().output(&format!(
+                "{}  {:width$}  {}",
+                letter,
+                change.path,
+                lines,
+                width = width
+            ));
+        }
+
+        if app.has_flag(&["--name-only"]) {
+            return Ok(());
+        }
+
+        for change in changes.iter().filter(|change| change.lines.is_some()) {
+            let path = change.path.as_str();
+            let (a, b) = (old.get(path), new.get(path));
+
+            match diff::unified(path, a.map(Vec::as_slice), b.map(Vec::as_slice), max_size) {
+                Some(unified) => {
+                    reporter::get().output("");
+
+                    for line in unified.lines() {
+                        if line.starts_with("+++") || line.starts_with("---") {
+                            reporter::get().output(&line.bold().to_string());
+                        } else if line.starts_with('+') {
+                            reporter::get().output(&line.bright_green().to_string());
+                        } else if line.starts_with('-') {
+                            reporter::get().output(&line.bright_red().to_string());
+                        } else if line.starts_with("@@") {
+                            reporter::get().output(&line.cyan().to_string());
+                        } else {
+                            reporter::get().output(&line.to_string());
+                        }
+                    }
+                }
+                None => reporter::get().output(&format!(
+                    "\n{} {} is larger than {}, pass --max-size=<bytes> to see its diff",
+                    "info".yellow(),
+                    path,
+                    format_bytes(max_size)
+                )),
+            }
+        }
+
+        Ok(())
+    }
+}
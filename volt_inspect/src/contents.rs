@@ -0,0 +1,218 @@
+/*
+Copyright 2021 Volt Contributors
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+    http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+//! What a package tarball holds, read without extracting it.
+//!
+//! Besides the files, the install scripts of its package.json and the files of native builds
+//! are picked out, since those run code or compilers on the machine installing the package.
+//! npm runs `node-gyp rebuild` for a `binding.gyp` when the package has no install script of
+//! its own, which is reported as an install script too.
+
+use std::collections::BTreeMap;
+use std::io::Read;
+
+use anyhow::{Context, Result};
+use flate2::read::GzDecoder;
+use serde_json::{json, Value};
+use tar::Archive;
+
+/// The scripts npm runs when the package is installed.
+const INSTALL_SCRIPTS: [&str; 3] = ["preinstall", "install", "postinstall"];
+
+/// A file of the tarball.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    /// Relative to the package, without the `package/` directory of the tarball
+    pub path: String,
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Contents {
+    /// Sorted by path
+    pub files: Vec<Entry>,
+    /// Script name → command
+    pub install_scripts: Vec<(String, String)>,
+    /// Paths of the files building or shipping native code
+    pub native: Vec<String>,
+}
+
+/// Whether the file at `path` builds or ships native code.
+pub fn is_native(path: &str) -> bool {
+    let name = path.rsplit('/').next().unwrap_or(path);
+
+    matches!(name, "binding.gyp" | "CMakeLists.txt" | "Makefile")
+        || name.ends_with(".node")
+        || name.ends_with(".gypi")
+}
+
+/// The path of a tarball entry within the package, without the directory tarballs keep the
+/// package in (`package/` for most of them).
+fn package_path(path: String) -> String {
+    match path.split_once('/') {
+        Some((_, path)) => path.to_string(),
+        None => path,
+    }
+}
+
+/// Every file of the gzipped tarball `bytes` with its data, by path.
+pub fn files(bytes: &[u8]) -> Result<BTreeMap<String, Vec<u8>>> {
+    let mut archive = Archive::new(GzDecoder::new(bytes));
+    let mut files = BTreeMap::new();
+
+    for entry in archive.entries().context("failed to read the tarball")? {
+        let mut entry = entry.context("failed to read the tarball")?;
+
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+
+        let path = package_path(entry.path()?.to_string_lossy().to_string());
+        let mut data = vec![];
+        entry.read_to_end(&mut data)?;
+
+        files.insert(path, data);
+    }
+
+    Ok(files)
+}
+
+impl Contents {
+    /// Read the gzipped tarball `bytes`.
+    pub fn read(bytes: &[u8]) -> Result<Self> {
+        let mut archive = Archive::new(GzDecoder::new(bytes));
+        let mut contents = Contents::default();
+        let mut package_json = None;
+
+        for entry in archive.entries().context("failed to read the tarball")? {
+            let mut entry = entry.context("failed to read the tarball")?;
+
+            if !entry.header().entry_type().is_file() {
+                continue;
+            }
+
+            let path = package_path(entry.path()?.to_string_lossy().to_string());
+
+            if path == "package.json" {
+                let mut data = String::new();
+                entry.read_to_string(&mut data)?;
+                package_json = serde_json::from_str::<Value>(&data).ok();
+            }
+
+            if is_native(&path) {
+                contents.native.push(path.clone());
+            }
+
+            contents.files.push(Entry {
+                size: entry.header().size()?,
+                path,
+            });
+        }
+
+        contents.files.sort_by(|a, b| a.path.cmp(&b.path));
+        contents.native.sort();
+
+        let scripts = package_json
+            .as_ref()
+            .and_then(|package_json| package_json["scripts"].as_object());
+
+        for name in INSTALL_SCRIPTS.iter() {
+            if let Some(command) = scripts.and_then(|scripts| scripts.get(*name)?.as_str()) {
+                contents
+                    .install_scripts
+                    .push((name.to_string(), command.to_string()));
+            }
+        }
+
+        let has_gyp = contents.files.iter().any(|file| file.path == "binding.gyp");
+        let has_install = contents
+            .install_scripts
+            .iter()
+            .any(|(name, _)| name == "install" || name == "preinstall");
+
+        if has_gyp && !has_install {
+            contents
+                .install_scripts
+                .push((String::from("install"), String::from("node-gyp rebuild")));
+        }
+
+        Ok(contents)
+    }
+
+    /// The size of the files once extracted.
+    pub fn unpacked_size(&self) -> u64 {
+        self.files.iter().map(|file| file.size).sum()
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "files": self.files.iter().map(|file| json!({
+                "path": file.path,
+                "size": file.size,
+            })).collect::<Vec<_>>(),
+            "unpackedSize": self.unpacked_size(),
+            "installScripts": self
+                .install_scripts
+                .iter()
+                .map(|(name, command)| (name.clone(), Value::String(command.clone())))
+                .collect::<serde_json::Map<_, _>>(),
+            "native": self.native,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::{write::GzEncoder, Compression};
+
+    fn tarball(files: &[(&str, &str)]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(GzEncoder::new(vec![], Compression::default()));
+
+        for (path, data) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, path, data.as_bytes())
+                .unwrap();
+        }
+
+        builder.into_inner().unwrap().finish().unwrap()
+    }
+
+    #[test]
+    fn finds_install_scripts_and_native_files() {
+        let bytes = tarball(&[
+            (
+                "package/package.json",
+                r#"{ "scripts": { "test": "jest" } }"#,
+            ),
+            ("package/src/addon.cc", "int main() {}"),
+            ("package/binding.gyp", "{}"),
+            ("package/index.js", "module.exports = 1"),
+        ]);
+
+        let contents = Contents::read(&bytes).unwrap();
+
+        assert_eq!(contents.files.len(), 4);
+        assert_eq!(contents.files[0].path, "binding.gyp");
+        assert_eq!(contents.unpacked_size(), 33 + 13 + 2 + 18);
+        assert_eq!(contents.native, vec!["binding.gyp"]);
+        assert_eq!(
+            contents.install_scripts,
+            vec![(String::from("install"), String::from("node-gyp rebuild"))]
+        );
+    }
+}
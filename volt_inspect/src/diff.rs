@@ -0,0 +1,183 @@
+/*
+Copyright 2021 Volt Contributors
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+    http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+//! Compare the files of two tarballs.
+//!
+//! Files are matched by path. Text diffs are only made for files up to a size, bundles and
+//! minified builds give diffs nobody reads, and binary files are only reported as changed.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use similar::TextDiff;
+
+/// Files larger than this get no text diff, when `--max-size` is not set.
+pub const DEFAULT_MAX_SIZE: usize = 100_000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Status {
+    Added,
+    Removed,
+    Modified,
+}
+
+impl Status {
+    pub fn letter(&self) -> &'static str {
+        match self {
+            Status::Added => "A",
+            Status::Removed => "D",
+            Status::Modified => "M",
+        }
+    }
+}
+
+/// A file which differs between the tarballs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change {
+    pub path: String,
+    pub status: Status,
+    /// Lines added and removed, none for binary files
+    pub lines: Option<(usize, usize)>,
+}
+
+/// Files with a NUL byte or which aren't UTF-8 are binary.
+fn text(data: &[u8]) -> Option<&str> {
+    if data.contains(&0) {
+        return None;
+    }
+
+    std::str::from_utf8(data).ok()
+}
+
+/// The files which differ between `old` and `new`, sorted by path.
+pub fn changes(old: &BTreeMap<String, Vec<u8>>, new: &BTreeMap<String, Vec<u8>>) -> Vec<Change> {
+    let paths: BTreeSet<&String> = old.keys().chain(new.keys()).collect();
+    let empty = vec![];
+
+    paths
+        .into_iter()
+        .filter_map(|path| {
+            let status = match (old.get(path), new.get(path)) {
+                (Some(a), Some(b)) if a == b => return None,
+                (Some(_), Some(_)) => Status::Modified,
+                (None, Some(_)) => Status::Added,
+                _ => Status::Removed,
+            };
+
+            let a = old.get(path).unwrap_or(&empty);
+            let b = new.get(path).unwrap_or(&empty);
+
+            let lines = match (text(a), text(b)) {
+                (Some(a), Some(b)) => {
+                    let diff = TextDiff::from_lines(a, b);
+                    let mut counts = (0, 0);
+
+                    for change in diff.iter_all_changes() {
+                        match change.tag() {
+                            similar::ChangeTag::Insert => counts.0 += 1,
+                            similar::ChangeTag::Delete => counts.1 += 1,
+                            similar::ChangeTag::Equal => {}
+                        }
+                    }
+
+                    Some(counts)
+                }
+                _ => None,
+            };
+
+            Some(Change {
+                path: path.clone(),
+                status,
+                lines,
+            })
+        })
+        .collect()
+}
+
+/// The unified diff of the file at `path`, none when it is binary or larger than `max_size`.
+pub fn unified(
+    path: &str,
+    old: Option<&[u8]>,
+    new: Option<&[u8]>,
+    max_size: usize,
+) -> Option<String> {
+    let (a, b) = (old.unwrap_or_default(), new.unwrap_or_default());
+
+    if a.len().max(b.len()) > max_size {
+        return None;
+    }
+
+    let (a, b) = (text(a)?, text(b)?);
+
+    let old_header = match old {
+        Some(_) => format!("a/{}", path),
+        None => String::from("/dev/null"),
+    };
+    let new_header = match new {
+        Some(_) => format!("b/{}", path),
+        None => String::from("/dev/null"),
+    };
+
+    Some(
+        TextDiff::from_lines(a, b)
+            .unified_diff()
+            .context_radius(3)
+            .header(&old_header, &new_header)
+            .to_string(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn files(entries: &[(&str, &str)]) -> BTreeMap<String, Vec<u8>> {
+        entries
+            .iter()
+            .map(|(path, data)| (path.to_string(), data.as_bytes().to_vec()))
+            .collect()
+    }
+
+    #[test]
+    fn lists_changed_files_and_their_lines() {
+        let old = files(&[
+            ("index.js", "a\nb\nc\n"),
+            ("README.md", "docs\n"),
+            ("old.js", "x\n"),
+        ]);
+        let new = files(&[
+            ("index.js", "a\nB\nc\nd\n"),
+            ("README.md", "docs\n"),
+            ("install.js", "fetch()\n"),
+        ]);
+
+        let changes = changes(&old, &new);
+        let summary: Vec<_> = changes
+            .iter()
+            .map(|change| (change.path.as_str(), change.status, change.lines))
+            .collect();
+
+        assert_eq!(
+            summary,
+            vec![
+                ("index.js", Status::Modified, Some((2, 1))),
+                ("install.js", Status::Added, Some((1, 0))),
+                ("old.js", Status::Removed, Some((0, 1))),
+            ]
+        );
+
+        let diff = unified("index.js", Some(b"a\nb\n"), Some(b"a\nc\n"), 100).unwrap();
+        assert!(diff.starts_with("--- a/index.js\n+++ b/index.js\n"));
+        assert!(diff.contains("-b\n+c\n"));
+        assert_eq!(unified("index.js", Some(b"a\nb\n"), None, 2), None);
+    }
+}
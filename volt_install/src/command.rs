@@ -16,41 +16,74 @@
 
 //! Installs dependencies for a project.
 
+use std::collections::HashMap;
 use std::sync::Arc;
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use async_trait::async_trait;
-use colored::Colorize;
-use volt_core::{command::Command, VERSION};
+use volt_core::{
+    command::Command,
+    help::Help,
+    model::{lock_file::LockFile, lock_graph::LockGraph},
+};
 use volt_utils::app::App;
-use volt_utils::package::PackageJson;
+use volt_utils::git::GitSpec;
+use volt_utils::package::{self, PackageJson};
+use volt_utils::{reporter, snapshot, workspace};
+
+use crate::{members, prune};
+
 /// Struct implementation for the `Install` command.
 pub struct Install;
 
 #[async_trait]
 impl Command for Install {
     /// Display a help menu for the `volt install` command.
-    fn help() -> String {
-        format!(
-            r#"volt {}
-        
-Install dependencies for a project.
-
-Usage: {} {} {}
-    
-Options: 
-    
-  {} {} Accept all prompts while installing dependencies.  
-  {} {} Output verbose messages on internal operations."#,
-            VERSION.bright_green().bold(),
-            "volt".bright_green().bold(),
-            "install".bright_purple(),
-            "[flags]".white(),
-            "--yes".blue(),
-            "(-y)".yellow(),
-            "--verbose".blue(),
-            "(-v)".yellow()
-        )
+    fn help() -> Help {
+        Help::new("install", "Install dependencies for a project.")
+            .usage("[flags]")
+            .flag(
+                &["--yes", "-y"],
+                "Accept all prompts while installing dependencies.",
+            )
+            .flag(
+                &["--check-lock-hash"],
+                "Fail if package.json changed since the lock file was written.",
+            )
+            .flag(
+                &["--frozen-lockfile"],
+                "Fail if volt.lock doesn't have every dependency of package.json.",
+            )
+            .flag(
+                &["--production"],
+                "Install everything but the dev dependencies.",
+            )
+            .flag(
+                &["--minimize-install"],
+                "Skip tests, docs and source maps of dependencies.",
+            )
+            .flag(
+                &["--force"],
+                "Install even if the os, cpu or libc of package.json exclude this platform.",
+            )
+            .flag(&["--json"], "Print the warnings of the install as JSON.")
+            .flag(
+                &["--trust-all"],
+                "Run the install scripts of every new dependency which has them.",
+            )
+            .flag(
+                &["--trust-none"],
+                "Skip the install scripts of every new dependency which has them.",
+            )
+            .flag(
+                &["--ignore-snapshot"],
+                "Install even if nothing changed since the last install.",
+            )
+            .flag(
+                &["--workspace-concurrency=<n>"],
+                "Install up to n workspace members at once (4).",
+            )
+            .verbose()
     }
 
     /// Execute the `volt install` command
@@ -69,22 +102,240 @@ Options:
     /// ## Returns
     /// * `Result<()>`
     async fn exec(_app: Arc<App>) -> Result<()> {
-        let package_file = PackageJson::from("package.json");
-        let dependencies = package_file.dependencies;
+        if _app.has_flag(&["--check-lock-hash"]) {
+            check_lock_hash(&_app)?;
+        }
+
+        if _app.has_flag(&["--frozen-lockfile"]) {
+            check_frozen_lock_file(&_app)?;
+        }
+
+        // Nothing changed since the last install, node_modules is not even looked at
+        if !_app.has_flag(&["--ignore-snapshot"]) && snapshot::is_current(&_app) {
+            reporter::get().info("node_modules is up to date", "");
+        } else {
+            install_root(&_app).await?;
+        }
+
+        members::install(&_app).await
+    }
+}
+
+/// The dependencies `volt install` installs, by name with their range.
+struct Dependencies {
+    /// `dependencies`, and `devDependencies` unless `--production` is set
+    required: HashMap<String, String>,
+    /// `optionalDependencies`, which may fail to install without failing the install
+    optional: HashMap<String, String>,
+    /// `devDependencies` left out by `--production`
+    skipped: HashMap<String, String>,
+}
+
+/// Sort the dependencies of `package_file`, the range in `dependencies` winning over the others.
+fn installed_dependencies(app: &App, package_file: PackageJson) -> Dependencies {
+    let mut required = package_file.dependencies;
+    let mut skipped = HashMap::new();
+
+    for (name, range) in package_file.dev_dependencies {
+        if required.contains_key(&name) {
+            continue;
+        }
+
+        if app.has_flag(&["--production"]) {
+            skipped.insert(name, range);
+        } else {
+            required.insert(name, range);
+        }
+    }
+
+    let optional = package_file
+        .optional_dependencies
+        .into_iter()
+        .filter(|(name, _)| !required.contains_key(name))
+        .collect();
+
+    Dependencies {
+        required,
+        optional,
+        skipped,
+    }
+}
+
+/// Install the dependencies of the package.json in the current directory.
+async fn install_root(app: &App) -> Result<()> {
+    let dependencies = installed_dependencies(
+        app,
+        PackageJson::from(&app.current_dir.join("package.json").to_string_lossy()),
+    );
+
+    // An earlier install may have put the dev dependencies there
+    if app.has_flag(&["--production"]) {
+        let mut installed = dependencies.required.clone();
+        installed.extend(dependencies.optional.clone());
+
+        let pruned = prune::prune(app, &installed, &dependencies.skipped)?;
+
+        if !pruned.is_empty() {
+            reporter::get().info("Pruned", &pruned.join(", "));
+        }
+    }
+
+    // The root of a workspace often has nothing but its members
+    if dependencies.required.is_empty() && dependencies.optional.is_empty() {
+        return Ok(());
+    }
+
+    if !dependencies.required.is_empty() {
+        add(dependencies.required).await?;
+    }
+
+    let mut complete = true;
 
-        let mut app = App::initialize();
+    // Each optional dependency is installed on its own, so that one failing leaves the others
+    for (name, range) in dependencies.optional {
+        let spec = format!("{}@{}", name, range);
 
-        let mut deps = dependencies
-            .into_iter()
-            .map(|value| value.0)
-            .collect::<Vec<String>>();
+        if let Err(error) = add(HashMap::from([(name, range)])).await {
+            reporter::get().warn(&format!(
+                "optional dependency {} was not installed: {:#}",
+                spec, error
+            ));
+            complete = false;
+        }
+    }
+
+    // The next install tries the failed optional dependencies again
+    if complete {
+        snapshot::record(app)?;
+    }
+
+    Ok(())
+}
+
+/// Add `dependencies` to the project, at the versions of the lock file.
+async fn add(dependencies: HashMap<String, String>) -> Result<()> {
+    let mut add = App::initialize();
+
+    // Add resolves catalog references and git urls, and keeps the ranges in package.json
+    let mut deps = dependencies
+        .into_iter()
+        .map(|(name, range)| format!("{}@{}", name, range))
+        .collect::<Vec<String>>();
+
+    deps.push(String::from("add"));
+
+    add.args = deps;
+    // Installing the dependencies a workspace root declares is no accident
+    add.flags
+        .push(String::from("--ignore-workspace-root-check"));
+    // The versions of the lock file are installed, without resolving them again
+    add.flags
+        .push(String::from(volt_add::command::FROM_LOCK_FILE_FLAG));
+
+    volt_add::command::Add::exec(Arc::new(add)).await
+}
+
+/// Check the lock file was written for the current package.json files, without resolving.
+fn check_lock_hash(app: &App) -> Result<()> {
+    let lock_file = LockFile::load(app.lock_file_path.clone()).map_err(|_| {
+        anyhow!(
+            "{} is missing, run volt install",
+            app.lock_file_path.display()
+        )
+    })?;
+
+    let hash = package::manifest_hash(&app.current_dir)?;
 
-        deps.push(String::from("add"));
+    match lock_file.manifest_hash {
+        Some(locked) if locked == hash => Ok(()),
+        Some(_) => Err(anyhow!(
+            "package.json changed since the lock file was written, run volt install and commit volt.lock"
+        )),
+        None => Err(anyhow!(
+            "the lock file has no manifest hash, run volt install and commit volt.lock"
+        )),
+    }
+}
+
+/// Check the lock file and package.json match, before installing anything: every dependency of
+/// package.json is locked, and every package of the lock file is needed by one of them.
+fn check_frozen_lock_file(app: &App) -> Result<()> {
+    let lock_file = LockFile::load(app.lock_file_path.clone()).map_err(|_| {
+        anyhow!(
+            "{} is missing, run volt install without --frozen-lockfile",
+            app.lock_file_path.display()
+        )
+    })?;
+
+    let dependencies = installed_dependencies(
+        app,
+        PackageJson::from(&app.current_dir.join("package.json").to_string_lossy()),
+    );
+
+    let mut missing: Vec<String> = dependencies
+        .required
+        .iter()
+        .chain(dependencies.optional.iter())
+        .filter(|(name, range)| {
+            if GitSpec::is_git(range) {
+                return !lock_file
+                    .dependencies
+                    .values()
+                    .any(|dependency| &dependency.name == *name);
+            }
+
+            match workspace::resolve_range(&app.current_dir, name, range) {
+                Ok(range) => lock_file.resolution(name, &range).is_none(),
+                Err(_) => true,
+            }
+        })
+        .map(|(name, range)| format!("{}@{}", name, range))
+        .collect();
+    missing.sort();
 
-        app.args = deps;
+    // Dev dependencies left out by --production are still asked for, and pruned by the install
+    let mut declared = dependencies.required;
+    declared.extend(dependencies.optional);
+    declared.extend(dependencies.skipped);
 
-        volt_add::command::Add::exec(Arc::new(app)).await.unwrap();
+    let graph = LockGraph::new(&lock_file);
+    let needed = prune::reachable(&graph, &declared);
 
-        Ok(())
+    let mut extraneous: Vec<String> = graph
+        .nodes()
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| !needed.contains(index))
+        .map(|(_, node)| format!("{}@{}", node.name, node.version))
+        .collect();
+    extraneous.sort();
+
+    let verb = |packages: &[String]| if packages.len() == 1 { "is" } else { "are" };
+    let mut mismatches = vec![];
+
+    if !missing.is_empty() {
+        mismatches.push(format!(
+            "{} {} not locked",
+            missing.join(", "),
+            verb(&missing)
+        ));
+    }
+
+    if !extraneous.is_empty() {
+        mismatches.push(format!(
+            "{} {} locked but not depended on",
+            extraneous.join(", "),
+            verb(&extraneous)
+        ));
     }
+
+    if mismatches.is_empty() {
+        return Ok(());
+    }
+
+    Err(anyhow!(
+        "{} doesn't match package.json, {}, run volt install without --frozen-lockfile",
+        app.lock_file_path.display(),
+        mismatches.join(" and ")
+    ))
 }
@@ -0,0 +1,292 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Share the peer singletons of a workspace between its members.
+//!
+//! Packages which others name as peers, like react or eslint, are meant to be loaded once, but
+//! members installed on their own each get a copy, and a component of one member ends up with
+//! another React than the app of another. Once every member is installed, each peer installed in
+//! more than one place is kept once: the highest installed version satisfying the ranges of every
+//! member holding it (its own dependencies and the peer ranges of its packages) stays where it
+//! is, the root first, and the other copies are replaced with links to it. Peers whose ranges
+//! can't agree on an installed version are left alone and reported.
+//!
+//! `dedupe-peers=false` in `.voltrc` turns this off.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{read_dir, read_to_string};
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use node_semver::{Range, Version};
+use serde_json::Value;
+use volt_utils::app::App;
+use volt_utils::reporter;
+
+/// A package of the workspace with its own node_modules, the root or a member.
+struct Holder {
+    /// Relative to the root of the workspace
+    relative: String,
+    node_modules: PathBuf,
+    /// Name → version of the packages at the top of node_modules
+    installed: BTreeMap<String, String>,
+    /// Package → the ranges asking for it, from package.json and the installed packages
+    ranges: BTreeMap<String, Vec<String>>,
+    /// The names the installed packages ask for as peers
+    peers: BTreeSet<String>,
+}
+
+fn read_manifest(dir: &Path) -> Value {
+    read_to_string(dir.join("package.json"))
+        .ok()
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or_default()
+}
+
+/// The directories at the top of `node_modules`, inside scopes too.
+fn top_level(node_modules: &Path) -> Vec<(String, PathBuf)> {
+    let entries = |dir: &Path| -> Vec<(String, PathBuf)> {
+        read_dir(dir)
+            .map(|entries| {
+                entries
+                    .filter_map(|entry| entry.ok())
+                    .map(|entry| {
+                        (
+                            entry.file_name().to_string_lossy().to_string(),
+                            entry.path(),
+                        )
+                    })
+                    .filter(|(name, _)| !name.starts_with('.'))
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+
+    entries(node_modules)
+        .into_iter()
+        .flat_map(|(name, path)| {
+            if name.starts_with('@') {
+                entries(&path)
+                    .into_iter()
+                    .map(|(package, path)| (format!("{}/{}", name, package), path))
+                    .collect()
+            } else {
+                vec![(name, path)]
+            }
+        })
+        .collect()
+}
+
+impl Holder {
+    fn load(relative: String, dir: &Path) -> Self {
+        let node_modules = dir.join("node_modules");
+        let manifest = read_manifest(dir);
+
+        let mut installed = BTreeMap::new();
+        let mut ranges: BTreeMap<String, Vec<String>> = BTreeMap::new();
+
+        for field in ["dependencies", "devDependencies", "peerDependencies"] {
+            for (name, range) in manifest[field].as_object().into_iter().flatten() {
+                if let Some(range) = range.as_str() {
+                    ranges
+                        .entry(name.clone())
+                        .or_default()
+                        .push(range.to_string());
+                }
+            }
+        }
+
+        let mut peers = BTreeSet::new();
+
+        for (name, path) in top_level(&node_modules) {
+            let manifest = read_manifest(&path);
+
+            if let Some(version) = manifest["version"].as_str() {
+                installed.insert(name, version.to_string());
+            }
+
+            for (peer, range) in manifest["peerDependencies"]
+                .as_object()
+                .into_iter()
+                .flatten()
+            {
+                peers.insert(peer.clone());
+
+                if let Some(range) = range.as_str() {
+                    ranges
+                        .entry(peer.clone())
+                        .or_default()
+                        .push(range.to_string());
+                }
+            }
+        }
+
+        Holder {
+            relative,
+            node_modules,
+            installed,
+            ranges,
+            peers,
+        }
+    }
+}
+
+fn satisfies(version: &str, range: &str) -> bool {
+    match (Range::parse(range), Version::parse(version)) {
+        (Ok(range), Ok(version)) => range.satisfies(&version),
+        // Tags and urls can't be checked
+        _ => true,
+    }
+}
+
+/// The highest of `installed` satisfying every one of `ranges`.
+fn shared_version<'a>(installed: &[&'a str], ranges: &[&str]) -> Option<&'a str> {
+    installed
+        .iter()
+        .copied()
+        .filter(|version| ranges.iter().all(|range| satisfies(version, range)))
+        .max_by(|a, b| match (Version::parse(a), Version::parse(b)) {
+            (Ok(a), Ok(b)) => a.cmp(&b),
+            _ => a.cmp(b),
+        })
+}
+
+/// Share the peers installed more than once between `members` (relative path, directory) and
+/// the root of the workspace in `root`.
+pub fn dedupe(app: &App, root: &Path, members: &[(String, PathBuf)]) -> Result<()> {
+    if app.config.get_bool("dedupe-peers") == Some(false) {
+        return Ok(());
+    }
+
+    let holders: Vec<Holder> = std::iter::once((String::from("."), root.to_path_buf()))
+        .chain(members.iter().cloned())
+        .map(|(relative, dir)| Holder::load(relative, &dir))
+        .collect();
+
+    let peers: BTreeSet<&String> = holders.iter().flat_map(|holder| &holder.peers).collect();
+
+    for peer in peers {
+        let holding: Vec<&Holder> = holders
+            .iter()
+            .filter(|holder| holder.installed.contains_key(peer))
+            .collect();
+
+        let copies: BTreeSet<PathBuf> = holding
+            .iter()
+            .filter_map(|holder| holder.node_modules.join(peer).canonicalize().ok())
+            .collect();
+
+        if copies.len() < 2 {
+            continue;
+        }
+
+        let versions: Vec<&str> = holding
+            .iter()
+            .map(|holder| holder.installed[peer].as_str())
+            .collect();
+        let ranges: Vec<&str> = holding
+            .iter()
+            .flat_map(|holder| holder.ranges.get(peer).into_iter().flatten())
+            .map(String::as_str)
+            .collect();
+
+        let version = match shared_version(&versions, &ranges) {
+            Some(version) => version,
+            None => {
+                let wanted: Vec<String> = holding
+                    .iter()
+                    .map(|holder| {
+                        format!(
+                            "{} has {} and needs {}",
+                            holder.relative,
+                            holder.installed[peer],
+                            holder
+                                .ranges
+                                .get(peer)
+                                .map(|ranges| ranges.join(" and "))
+                                .unwrap_or_else(|| String::from("any version"))
+                        )
+                    })
+                    .collect();
+
+                reporter::get().warn(&format!(
+                    "{} can't be shared between members, {}",
+                    peer,
+                    wanted.join(", ")
+                ));
+                continue;
+            }
+        };
+
+        let owner = holding
+            .iter()
+            .find(|holder| holder.installed[peer] == version)
+            .unwrap();
+        let target = owner.node_modules.join(peer).canonicalize()?;
+
+        let mut linked = vec![];
+
+        for holder in holding.iter() {
+            let link = holder.node_modules.join(peer);
+
+            if link.canonicalize().ok().as_ref() == Some(&target) {
+                continue;
+            }
+
+            volt_utils::fs::remove_dir_all(&link)?;
+            volt_utils::create_symlink(
+                target.to_string_lossy().to_string(),
+                link.to_string_lossy().to_string(),
+            )?;
+
+            linked.push(holder.relative.clone());
+        }
+
+        reporter::get().info(
+            "Shared",
+            &format!(
+                "{}@{} of {} with {}",
+                peer,
+                version,
+                owner.relative,
+                linked.join(", ")
+            ),
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shares_the_highest_version_every_range_accepts() {
+        assert_eq!(
+            shared_version(&["17.0.1", "17.0.2"], &["^17.0.0", ">=17.0.1"]),
+            Some("17.0.2")
+        );
+        assert_eq!(
+            shared_version(&["17.0.2", "18.2.0"], &["^17.0.0 || ^18.0.0", "latest"]),
+            Some("18.2.0")
+        );
+        assert_eq!(
+            shared_version(&["17.0.2", "18.2.0"], &["^17.0.0", "^18.0.0"]),
+            None
+        );
+    }
+}
@@ -1 +1,4 @@
 pub mod command;
+pub mod dedupe;
+pub mod members;
+pub mod prune;
@@ -0,0 +1,150 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Install the members of a workspace at the same time.
+//!
+//! Each member is installed by a `volt install` of its own in the member directory, at most
+//! `workspace-concurrency` (`--workspace-concurrency=<n>`, 4 by default) at once. They share the
+//! store, where [store locks](volt_utils::store_lock) keep two of them from extracting the same
+//! package. The output of each member is shown once it is done, and once they are all installed
+//! the peers they have in common are [shared](crate::dedupe).
+
+use std::path::PathBuf;
+use std::process::Stdio;
+
+use anyhow::{anyhow, Result};
+use colored::Colorize;
+use futures::{stream, StreamExt};
+use tokio::process::Command;
+use volt_utils::app::App;
+use volt_utils::reporter;
+use volt_utils::workspace::{self, Workspace};
+
+use crate::dedupe;
+
+/// Members installed at once when `workspace-concurrency` is not set.
+const DEFAULT_CONCURRENCY: usize = 4;
+
+/// Flags which would send the install of a member somewhere else.
+const LOCATION_FLAGS: [&str; 3] = ["--cwd", "--prefix", "--workspace-concurrency"];
+
+/// The number of members installed at once.
+pub fn concurrency(app: &App) -> usize {
+    app.flag_value(&["--workspace-concurrency"])
+        .or_else(|| app.config.get("workspace-concurrency").map(String::from))
+        .and_then(|value| value.parse().ok())
+        .filter(|concurrency| *concurrency > 0)
+        .unwrap_or(DEFAULT_CONCURRENCY)
+}
+
+/// The flags of the root install passed on to members.
+fn forwarded_flags(flags: &[String]) -> Vec<String> {
+    flags
+        .iter()
+        .filter(|flag| {
+            let name = flag.split('=').next().unwrap_or_default();
+            !LOCATION_FLAGS.contains(&name)
+        })
+        .cloned()
+        .collect()
+}
+
+/// Install the member in `dir` with `flags`, returning its output and whether it succeeded.
+async fn install_member(dir: PathBuf, flags: Vec<String>) -> Result<(Vec<u8>, bool)> {
+    let output = Command::new(std::env::current_exe()?)
+        .arg("install")
+        .arg(format!("--cwd={}", dir.display()))
+        .args(flags)
+        .stdin(Stdio::null())
+        .output()
+        .await?;
+
+    let mut text = output.stdout;
+    text.extend(output.stderr);
+
+    Ok((text, output.status.success()))
+}
+
+/// Install every member of the workspace declared in the current directory, if there is one.
+pub async fn install(app: &App) -> Result<()> {
+    let workspace = match Workspace::load(&app.current_dir) {
+        Some(workspace) => workspace,
+        None => return Ok(()),
+    };
+
+    let members: Vec<(String, PathBuf)> = workspace
+        .members()
+        .into_iter()
+        .filter_map(|dir| Some((workspace::relative_path(&workspace.root, &dir)?, dir)))
+        .collect();
+
+    let flags = forwarded_flags(&app.flags);
+
+    let mut installs = stream::iter(members.clone())
+        .map(|(relative, dir)| {
+            let install = install_member(dir, flags.clone());
+            async move { (relative, install.await) }
+        })
+        .buffer_unordered(concurrency(app));
+
+    let mut failed = vec![];
+
+    while let Some((relative, result)) = installs.next().await {
+        reporter::get().output(&format!("{} {}", ">".bright_magenta().bold(), relative));
+
+        match result {
+            Ok((output, success)) => {
+                reporter::get().output(String::from_utf8_lossy(&output).trim_end());
+
+                if !success {
+                    failed.push(relative);
+                }
+            }
+            Err(error) => {
+                reporter::get().output(&format!(
+                    "{} failed to start volt: {}",
+                    "error".bright_red(),
+                    error
+                ));
+                failed.push(relative);
+            }
+        }
+    }
+
+    if !failed.is_empty() {
+        return Err(anyhow!(
+            "failed to install workspace members: {}",
+            failed.join(", ")
+        ));
+    }
+
+    dedupe::dedupe(app, &workspace.root, &members)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_members_in_their_directory() {
+        let flags: Vec<String> = ["--yes", "--cwd=..", "--workspace-concurrency=2", "--force"]
+            .iter()
+            .map(|flag| flag.to_string())
+            .collect();
+
+        assert_eq!(forwarded_flags(&flags), vec!["--yes", "--force"]);
+    }
+}
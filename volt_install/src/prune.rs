@@ -0,0 +1,114 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Drop what a `--production` install doesn't need.
+//!
+//! An earlier install may have put the dev dependencies in node_modules and the lock file.
+//! Packages the dev dependencies lead to, directly or through other packages, and the installed
+//! dependencies don't, are removed from both, along with their scripts.
+
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+
+use anyhow::{Context, Result};
+use volt_core::model::{
+    lock_file::{DependencyID, LockFile},
+    lock_graph::LockGraph,
+};
+use volt_utils::app::App;
+
+/// The nodes `roots` (name → range) lead to, directly or through other packages.
+pub(crate) fn reachable(graph: &LockGraph, roots: &HashMap<String, String>) -> HashSet<usize> {
+    let mut queue: VecDeque<usize> = roots
+        .iter()
+        .filter_map(|(name, range)| graph.resolve(name, range))
+        .collect();
+    let mut reached: HashSet<usize> = queue.iter().copied().collect();
+
+    while let Some(node) = queue.pop_front() {
+        for (dependency, _) in graph.dependencies(node) {
+            if reached.insert(*dependency) {
+                queue.push_back(*dependency);
+            }
+        }
+    }
+
+    reached
+}
+
+/// Remove the packages only `dev` leads to, and none of `installed`, from the lock file and
+/// node_modules, returning their names.
+///
+/// Lock files written before the versions of dependencies were recorded don't lead anywhere, so
+/// only what is known to belong to the dev dependencies goes.
+pub fn prune(
+    app: &App,
+    installed: &HashMap<String, String>,
+    dev: &HashMap<String, String>,
+) -> Result<Vec<String>> {
+    let mut lock_file = match LockFile::load(app.lock_file_path.clone()) {
+        Ok(lock_file) => lock_file,
+        // Nothing was installed yet
+        Err(_) => return Ok(vec![]),
+    };
+
+    let graph = LockGraph::new(&lock_file);
+    let needed = reachable(&graph, installed);
+
+    let removed: Vec<_> = reachable(&graph, dev)
+        .difference(&needed)
+        .map(|index| graph.node(*index))
+        .filter_map(|node| {
+            let id = DependencyID(node.name.clone(), node.version.clone());
+            lock_file.dependencies.remove(&id)
+        })
+        .collect();
+
+    if removed.is_empty() {
+        return Ok(vec![]);
+    }
+
+    // node_modules has one copy of each name, kept if another version of it is still needed
+    let kept: HashSet<&String> = lock_file
+        .dependencies
+        .values()
+        .map(|dependency| &dependency.name)
+        .collect();
+
+    let scripts = app.node_modules_dir.join("scripts");
+    let mut names = BTreeSet::new();
+
+    for dependency in removed
+        .iter()
+        .filter(|dependency| !kept.contains(&dependency.name))
+    {
+        let dir = app.node_modules_dir.join(&dependency.name);
+
+        volt_utils::fs::remove_dir_all(&dir)
+            .with_context(|| format!("failed to remove {}", dir.display()))?;
+
+        for bin in dependency.bin.iter().flat_map(|bins| bins.keys()) {
+            for script in [format!("{}.sh", bin), format!("{}.cmd", bin)] {
+                let _ = std::fs::remove_file(scripts.join(script));
+            }
+        }
+
+        names.insert(dependency.name.clone());
+    }
+
+    lock_file.save().context("Failed to save lock file")?;
+
+    Ok(names.into_iter().collect())
+}
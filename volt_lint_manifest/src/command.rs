@@ -0,0 +1,95 @@
+/*
+Copyright 2021 Volt Contributors
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+    http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+//! Validate package.json against the npm schema and common mistakes.
+
+use std::fs::read_to_string;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use colored::Colorize;
+use volt_core::{command::Command, help::Help};
+use volt_utils::{
+    app::App,
+    diagnostics::{self, ErrorFormat},
+    reporter,
+};
+
+use crate::rules::lint;
+
+/// Struct implementation for the `lint-manifest` command.
+pub struct LintManifest;
+
+#[async_trait]
+impl Command for LintManifest {
+    /// Display a help menu for the `volt lint-manifest` command.
+    fn help() -> Help {
+        Help::new(
+            "lint-manifest",
+            "Validate package.json against the npm schema and common mistakes.
+
+Checks field types, the package name and version, dependency ranges,
+bin paths and entries in files.",
+        )
+        .usage("[flags]")
+        .flag(
+            &["--error-format json"],
+            "Print problems as JSON lines on stderr, for editors.",
+        )
+        .verbose()
+    }
+
+    /// Execute the `volt lint-manifest` command
+    ///
+    /// Validate package.json against the npm schema and common mistakes.
+    /// ## Arguments
+    /// * `app` - Instance of the command (`Arc<App>`)
+    /// ## Examples
+    /// ```ignore
+    /// // Lint the package.json in the current directory
+    /// // .exec() is an async call so you need to await it
+    /// LintManifest.exec(app).await;
+    /// ```
+    /// ## Returns
+    /// * `Result<()>`
+    async fn exec(app: Arc<App>) -> Result<()> {
+        let path = app.current_dir.join("package.json");
+
+        let source =
+            read_to_string(&path).with_context(|| format!("failed to read {}", path.display()))?;
+
+        let diagnostics = lint(&app.current_dir, &source);
+
+        if diagnostics.is_empty() {
+            reporter::get().output(&format!(
+                "{} package.json is valid",
+                "success".bright_green()
+            ));
+            return Ok(());
+        }
+
+        let count = diagnostics.len();
+        let format = ErrorFormat::from_app(&app);
+
+        for diagnostic in diagnostics {
+            diagnostics::emit(format, diagnostic);
+        }
+
+        Err(anyhow!(
+            "found {} {} in package.json",
+            count,
+            if count == 1 { "problem" } else { "problems" }
+        ))
+    }
+}
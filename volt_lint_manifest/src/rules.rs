@@ -0,0 +1,531 @@
+/*
+Copyright 2021 Volt Contributors
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+    http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+//! Schema and semantic checks for package.json.
+
+use std::path::Path;
+
+use miette::{Diagnostic, NamedSource, SourceSpan};
+use node_semver::{Range, Version};
+use serde_json::{Map, Value};
+use thiserror::Error;
+use volt_utils::workspace::glob_match;
+use walkdir::WalkDir;
+
+use crate::spans::{pointer, Spans};
+
+const DEPENDENCY_FIELDS: [&str; 4] = [
+    "dependencies",
+    "devDependencies",
+    "optionalDependencies",
+    "peerDependencies",
+];
+
+/// Expected JSON type of a known top-level field.
+#[derive(Debug, Clone, Copy)]
+enum Kind {
+    String,
+    Bool,
+    StringArray,
+    StringMap,
+    StringOrStringMap,
+    StringOrObject,
+    ArrayOrObject,
+}
+
+impl Kind {
+    fn matches(self, value: &Value) -> bool {
+        let is_string_map = |value: &Value| {
+            value
+                .as_object()
+                .is_some_and(|map| map.values().all(Value::is_string))
+        };
+
+        match self {
+            Kind::String => value.is_string(),
+            Kind::Bool => value.is_boolean(),
+            Kind::StringArray => value
+                .as_array()
+                .is_some_and(|values| values.iter().all(Value::is_string)),
+            Kind::StringMap => is_string_map(value),
+            Kind::StringOrStringMap => value.is_string() || is_string_map(value),
+            Kind::StringOrObject => value.is_string() || value.is_object(),
+            Kind::ArrayOrObject => value.is_array() || value.is_object(),
+        }
+    }
+
+    fn describe(self) -> &'static str {
+        match self {
+            Kind::String => "a string",
+            Kind::Bool => "a boolean",
+            Kind::StringArray => "an array of strings",
+            Kind::StringMap => "an object of strings",
+            Kind::StringOrStringMap => "a string or an object of strings",
+            Kind::StringOrObject => "a string or an object",
+            Kind::ArrayOrObject => "an array or an object",
+        }
+    }
+}
+
+const SCHEMA: [(&str, Kind); 20] = [
+    ("name", Kind::String),
+    ("version", Kind::String),
+    ("description", Kind::String),
+    ("main", Kind::String),
+    ("module", Kind::String),
+    ("types", Kind::String),
+    ("license", Kind::String),
+    ("homepage", Kind::String),
+    ("packageManager", Kind::String),
+    ("private", Kind::Bool),
+    ("keywords", Kind::StringArray),
+    ("files", Kind::StringArray),
+    ("os", Kind::StringArray),
+    ("cpu", Kind::StringArray),
+    ("scripts", Kind::StringMap),
+    ("engines", Kind::StringMap),
+    ("bin", Kind::StringOrStringMap),
+    ("author", Kind::StringOrObject),
+    ("repository", Kind::StringOrObject),
+    ("workspaces", Kind::ArrayOrObject),
+];
+
+/// A problem found in package.json, pointing into its source.
+#[derive(Debug, Error, Diagnostic)]
+#[error("{message}")]
+#[diagnostic(code(volt::lint_manifest))]
+pub struct ManifestDiagnostic {
+    pub message: String,
+    #[source_code]
+    pub source_code: NamedSource,
+    #[label("{label}")]
+    pub span: SourceSpan,
+    pub label: String,
+    #[help]
+    pub help: Option<String>,
+}
+
+/// Collects diagnostics for one manifest.
+struct Linter<'a> {
+    dir: &'a Path,
+    source: &'a str,
+    spans: Spans,
+    diagnostics: Vec<ManifestDiagnostic>,
+}
+
+impl Linter<'_> {
+    fn report(&mut self, span: SourceSpan, message: String, label: &str, help: Option<String>) {
+        self.diagnostics.push(ManifestDiagnostic {
+            message,
+            source_code: NamedSource::new("package.json", self.source.to_string()),
+            span,
+            label: label.to_string(),
+            help,
+        });
+    }
+
+    fn schema(&mut self, manifest: &Map<String, Value>) {
+        for (field, kind) in SCHEMA.iter() {
+            if let Some(value) = manifest.get(*field) {
+                if !kind.matches(value) {
+                    let span = self.spans.value(&pointer("", field));
+                    self.report(
+                        span,
+                        format!("`{}` must be {}", field, kind.describe()),
+                        "wrong type",
+                        None,
+                    );
+                }
+            }
+        }
+
+        for field in DEPENDENCY_FIELDS.iter() {
+            if let Some(value) = manifest.get(*field) {
+                if !Kind::StringMap.matches(value) {
+                    let span = self.spans.value(&pointer("", field));
+                    self.report(
+                        span,
+                        format!("`{}` must be {}", field, Kind::StringMap.describe()),
+                        "wrong type",
+                        None,
+                    );
+                }
+            }
+        }
+    }
+
+    fn name(&mut self, manifest: &Map<String, Value>, private: bool) {
+        let name = match manifest.get("name") {
+            Some(Value::String(name)) => name,
+            Some(_) => return,
+            None => {
+                if !private {
+                    self.report(
+                        (0, 1).into(),
+                        String::from("missing `name`"),
+                        "package.json starts here",
+                        Some(String::from(
+                            "add a name, or set \"private\": true if the package is never published",
+                        )),
+                    );
+                }
+
+                return;
+            }
+        };
+
+        if let Some(problem) = name_problem(name) {
+            let span = self.spans.value("/name");
+            self.report(
+                span,
+                format!("invalid package name `{}`", name),
+                problem,
+                None,
+            );
+        }
+    }
+
+    fn version(&mut self, manifest: &Map<String, Value>, private: bool) {
+        match manifest.get("version") {
+            Some(Value::String(version)) => {
+                if Version::parse(version).is_err() {
+                    let span = self.spans.value("/version");
+                    self.report(
+                        span,
+                        format!("invalid version `{}`", version),
+                        "not a semver version",
+                        Some(String::from("versions look like 1.0.0 or 2.1.0-beta.1")),
+                    );
+                }
+            }
+            Some(_) => {}
+            None => {
+                if !private {
+                    self.report(
+                        (0, 1).into(),
+                        String::from("missing `version`"),
+                        "package.json starts here",
+                        Some(String::from(
+                            "add a version, or set \"private\": true if the package is never published",
+                        )),
+                    );
+                }
+            }
+        }
+    }
+
+    fn ranges(&mut self, manifest: &Map<String, Value>) {
+        for field in DEPENDENCY_FIELDS.iter() {
+            let dependencies = match manifest.get(*field).and_then(Value::as_object) {
+                Some(dependencies) => dependencies,
+                None => continue,
+            };
+
+            for (name, range) in dependencies {
+                let range = match range.as_str() {
+                    Some(range) => range,
+                    None => continue,
+                };
+
+                if is_valid_specifier(range) {
+                    continue;
+                }
+
+                let span = self.spans.value(&pointer(&pointer("", field), name));
+                self.report(
+                    span,
+                    format!("invalid version range for `{}` in `{}`", name, field),
+                    "not a semver range",
+                    Some(String::from(
+                        "use a range like ^1.2.0, a dist-tag, or a file:/git/https specifier",
+                    )),
+                );
+            }
+        }
+    }
+
+    fn bin(&mut self, manifest: &Map<String, Value>) {
+        let entries: Vec<(String, String, String)> = match manifest.get("bin") {
+            Some(Value::String(path)) => {
+                vec![(String::new(), path.clone(), String::from("/bin"))]
+            }
+            Some(Value::Object(bin)) => bin
+                .iter()
+                .filter_map(|(name, path)| {
+                    path.as_str()
+                        .map(|path| (name.clone(), path.to_string(), pointer("/bin", name)))
+                })
+                .collect(),
+            _ => return,
+        };
+
+        for (name, path, location) in entries {
+            if name.contains('/') || name.contains(char::is_whitespace) {
+                let span = self.spans.key(&location);
+                self.report(
+                    span,
+                    format!("invalid binary name `{}`", name),
+                    "binary names can't contain slashes or whitespace",
+                    None,
+                );
+            }
+
+            if !self.dir.join(&path).is_file() {
+                let span = self.spans.value(&location);
+                self.report(
+                    span,
+                    format!("binary `{}` does not exist", path),
+                    "no such file",
+                    Some(String::from(
+                        "bin paths are relative to the directory containing package.json",
+                    )),
+                );
+            }
+        }
+    }
+
+    fn files(&mut self, manifest: &Map<String, Value>) {
+        let files = match manifest.get("files").and_then(Value::as_array) {
+            Some(files) => files,
+            None => return,
+        };
+
+        let paths = package_paths(self.dir);
+
+        for (index, entry) in files.iter().enumerate() {
+            let entry = match entry.as_str() {
+                Some(entry) => entry,
+                None => continue,
+            };
+
+            // Negated entries only exclude files
+            if entry.starts_with('!') {
+                continue;
+            }
+
+            let exists = if entry.contains('*') {
+                paths.iter().any(|path| glob_match(entry, path))
+            } else {
+                self.dir.join(entry).exists()
+            };
+
+            if !exists {
+                let span = self.spans.value(&format!("/files/{}", index));
+                self.report(
+                    span,
+                    format!("`{}` in `files` does not match anything", entry),
+                    "no such file",
+                    Some(String::from(
+                        "paths in files are relative to the directory containing package.json",
+                    )),
+                );
+            }
+        }
+    }
+}
+
+/// Lint the manifest `source` of the package in `dir`.
+pub fn lint(dir: &Path, source: &str) -> Vec<ManifestDiagnostic> {
+    let mut linter = Linter {
+        dir,
+        source,
+        spans: Spans::parse(source),
+        diagnostics: vec![],
+    };
+
+    let manifest = match serde_json::from_str::<Value>(source) {
+        Ok(Value::Object(manifest)) => manifest,
+        Ok(_) => {
+            linter.report(
+                (0, source.len()).into(),
+                String::from("package.json must contain an object"),
+                "not an object",
+                None,
+            );
+
+            return linter.diagnostics;
+        }
+        Err(error) => {
+            let offset = line_offset(source, error.line(), error.column());
+            linter.report(
+                (offset, 0).into(),
+                String::from("package.json is not valid JSON"),
+                &error.to_string(),
+                None,
+            );
+
+            return linter.diagnostics;
+        }
+    };
+
+    let private = manifest.get("private") == Some(&Value::Bool(true));
+
+    linter.schema(&manifest);
+    linter.name(&manifest, private);
+    linter.version(&manifest, private);
+    linter.ranges(&manifest);
+    linter.bin(&manifest);
+    linter.files(&manifest);
+
+    linter.diagnostics
+}
+
+/// Check a package name against the registry rules, returning what is wrong with it.
+pub fn name_problem(name: &str) -> Option<&'static str> {
+    if name.is_empty() {
+        return Some("name can't be empty");
+    }
+
+    if name.len() > 214 {
+        return Some("name can't be longer than 214 characters");
+    }
+
+    if name.to_lowercase() != name {
+        return Some("name can't contain uppercase letters");
+    }
+
+    if name.trim() != name {
+        return Some("name can't have leading or trailing whitespace");
+    }
+
+    let (scope, unscoped) = match name.strip_prefix('@') {
+        Some(scoped) => match scoped.split_once('/') {
+            Some((scope, package)) if !scope.is_empty() && !package.is_empty() => (scope, package),
+            _ => return Some("scoped names look like @scope/name"),
+        },
+        None => ("", name),
+    };
+
+    if unscoped.starts_with('.') || unscoped.starts_with('_') {
+        return Some("name can't start with a period or an underscore");
+    }
+
+    let url_safe = |c: char| c.is_ascii_alphanumeric() || "-._~".contains(c);
+
+    if !scope.chars().chain(unscoped.chars()).all(url_safe) {
+        return Some("name can only contain URL-safe characters");
+    }
+
+    None
+}
+
+/// Check if a dependency specifier is a semver range, a dist-tag or a non-registry source.
+fn is_valid_specifier(specifier: &str) -> bool {
+    const PROTOCOLS: [&str; 11] = [
+        "file:",
+        "link:",
+        "workspace:",
+        "npm:",
+        "git:",
+        "git+",
+        "http:",
+        "https:",
+        "github:",
+        "gitlab:",
+        "bitbucket:",
+    ];
+
+    if PROTOCOLS
+        .iter()
+        .any(|protocol| specifier.starts_with(protocol))
+    {
+        return true;
+    }
+
+    // `user/repo` GitHub shorthand
+    if specifier.contains('/') && !specifier.contains(' ') {
+        return true;
+    }
+
+    // `latest`, `next`, `beta`
+    let is_tag = specifier
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic())
+        && specifier
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-._".contains(c));
+
+    is_tag || Range::parse(specifier).is_ok()
+}
+
+/// Get the paths of all files and directories in the package, relative to `dir`.
+fn package_paths(dir: &Path) -> Vec<String> {
+    WalkDir::new(dir)
+        .min_depth(1)
+        .into_iter()
+        .filter_entry(|entry| entry.file_name() != "node_modules" && entry.file_name() != ".git")
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let relative = entry.path().strip_prefix(dir).ok()?;
+
+            Some(
+                relative
+                    .components()
+                    .map(|component| component.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/"),
+            )
+        })
+        .collect()
+}
+
+/// Convert a 1-based line and column from serde_json into a byte offset.
+fn line_offset(source: &str, line: usize, column: usize) -> usize {
+    let start: usize = source
+        .split_inclusive('\n')
+        .take(line.saturating_sub(1))
+        .map(str::len)
+        .sum();
+
+    (start + column.saturating_sub(1)).min(source.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn package_names() {
+        assert_eq!(name_problem("volt"), None);
+        assert_eq!(name_problem("@volt/core"), None);
+        assert!(name_problem("Volt").is_some());
+        assert!(name_problem("_private").is_some());
+        assert!(name_problem("@volt").is_some());
+        assert!(name_problem("my package").is_some());
+    }
+
+    #[test]
+    fn dependency_specifiers() {
+        assert!(is_valid_specifier("^1.2.0"));
+        assert!(is_valid_specifier(">=1 <2 || 3.x"));
+        assert!(is_valid_specifier("latest"));
+        assert!(is_valid_specifier("file:../lib"));
+        assert!(is_valid_specifier("user/repo#main"));
+        assert!(!is_valid_specifier("^^1"));
+        assert!(!is_valid_specifier("1.2.3.4"));
+    }
+
+    #[test]
+    fn reports_spans_into_source() {
+        let source = r#"{ "name": "app", "version": "one", "dependencies": { "a": "^^1" } }"#;
+        let diagnostics = lint(Path::new("."), source);
+
+        let spans: Vec<&str> = diagnostics
+            .iter()
+            .map(|d| &source[d.span.offset()..d.span.offset() + d.span.len()])
+            .collect();
+
+        assert_eq!(spans, vec!["\"one\"", "\"^^1\""]);
+    }
+}
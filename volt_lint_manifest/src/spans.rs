@@ -0,0 +1,232 @@
+/*
+Copyright 2021 Volt Contributors
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+    http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+//! Byte ranges of the keys and values in a JSON document, addressed by JSON pointer.
+
+use std::collections::HashMap;
+use std::ops::Range;
+
+use miette::SourceSpan;
+
+#[derive(Debug, Default)]
+pub struct Spans {
+    keys: HashMap<String, Range<usize>>,
+    values: HashMap<String, Range<usize>>,
+}
+
+impl Spans {
+    /// Collect the spans of `source`, stopping at the first syntax error.
+    pub fn parse(source: &str) -> Self {
+        let mut parser = Parser {
+            source: source.as_bytes(),
+            position: 0,
+            spans: Spans::default(),
+        };
+
+        parser.value(String::new());
+        parser.spans
+    }
+
+    /// Span of the value at `pointer` (`/dependencies/react`).
+    pub fn value(&self, pointer: &str) -> SourceSpan {
+        to_source_span(self.values.get(pointer))
+    }
+
+    /// Span of the key at `pointer`, or of its value for array items and the root.
+    pub fn key(&self, pointer: &str) -> SourceSpan {
+        match self.keys.get(pointer) {
+            Some(range) => to_source_span(Some(range)),
+            None => self.value(pointer),
+        }
+    }
+}
+
+/// Append a key to a JSON pointer, escaping it as described in RFC 6901.
+pub fn pointer(parent: &str, key: &str) -> String {
+    format!("{}/{}", parent, key.replace('~', "~0").replace('/', "~1"))
+}
+
+fn to_source_span(range: Option<&Range<usize>>) -> SourceSpan {
+    match range {
+        Some(range) => (range.start, range.end - range.start).into(),
+        None => (0, 0).into(),
+    }
+}
+
+struct Parser<'a> {
+    source: &'a [u8],
+    position: usize,
+    spans: Spans,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.source.get(self.position).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.peek() {
+            self.position += 1;
+        }
+    }
+
+    fn expect(&mut self, byte: u8) -> Option<()> {
+        self.skip_whitespace();
+
+        if self.peek()? == byte {
+            self.position += 1;
+            Some(())
+        } else {
+            None
+        }
+    }
+
+    fn value(&mut self, pointer: String) -> Option<()> {
+        self.skip_whitespace();
+        let start = self.position;
+
+        match self.peek()? {
+            b'{' => self.object(&pointer)?,
+            b'[' => self.array(&pointer)?,
+            b'"' => {
+                self.string()?;
+            }
+            _ => {
+                while let Some(byte) = self.peek() {
+                    if matches!(byte, b',' | b'}' | b']' | b' ' | b'\t' | b'\n' | b'\r') {
+                        break;
+                    }
+
+                    self.position += 1;
+                }
+            }
+        }
+
+        self.spans.values.insert(pointer, start..self.position);
+
+        Some(())
+    }
+
+    fn object(&mut self, pointer: &str) -> Option<()> {
+        self.expect(b'{')?;
+        self.skip_whitespace();
+
+        if self.peek()? == b'}' {
+            self.position += 1;
+            return Some(());
+        }
+
+        loop {
+            self.skip_whitespace();
+
+            let start = self.position;
+            let key = self.string()?;
+            let child = self::pointer(pointer, &key);
+
+            self.spans.keys.insert(child.clone(), start..self.position);
+
+            self.expect(b':')?;
+            self.value(child)?;
+            self.skip_whitespace();
+
+            match self.peek()? {
+                b',' => self.position += 1,
+                b'}' => {
+                    self.position += 1;
+                    return Some(());
+                }
+                _ => return None,
+            }
+        }
+    }
+
+    fn array(&mut self, pointer: &str) -> Option<()> {
+        self.expect(b'[')?;
+        self.skip_whitespace();
+
+        if self.peek()? == b']' {
+            self.position += 1;
+            return Some(());
+        }
+
+        for index in 0.. {
+            self.value(format!("{}/{}", pointer, index))?;
+            self.skip_whitespace();
+
+            match self.peek()? {
+                b',' => self.position += 1,
+                b']' => {
+                    self.position += 1;
+                    break;
+                }
+                _ => return None,
+            }
+        }
+
+        Some(())
+    }
+
+    /// Parse a string literal and return its unescaped contents.
+    fn string(&mut self) -> Option<String> {
+        let start = self.position;
+
+        if self.peek()? != b'"' {
+            return None;
+        }
+
+        self.position += 1;
+
+        loop {
+            match self.peek()? {
+                b'\\' => self.position += 2,
+                b'"' => {
+                    self.position += 1;
+                    break;
+                }
+                _ => self.position += 1,
+            }
+        }
+
+        let literal = std::str::from_utf8(&self.source[start..self.position]).ok()?;
+        serde_json::from_str(literal).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn locates_nested_keys_and_values() {
+        let source = r#"{ "name": "app", "dependencies": { "a/b": "^1.0.0" }, "files": ["dist"] }"#;
+        let spans = Spans::parse(source);
+
+        let span = spans.value("/dependencies/a~1b");
+        assert_eq!(
+            &source[span.offset()..span.offset() + span.len()],
+            "\"^1.0.0\""
+        );
+
+        let span = spans.key("/name");
+        assert_eq!(
+            &source[span.offset()..span.offset() + span.len()],
+            "\"name\""
+        );
+
+        let span = spans.value("/files/0");
+        assert_eq!(
+            &source[span.offset()..span.offset() + span.len()],
+            "\"dist\""
+        );
+    }
+}
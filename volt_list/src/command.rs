@@ -19,8 +19,9 @@ use anyhow::Result;
 use async_trait::async_trait;
 use colored::Colorize;
 use std::fs::read_dir;
-use volt_core::{command::Command, VERSION};
+use volt_core::{command::Command, help::Help};
 use volt_utils::app::App;
+use volt_utils::reporter;
 use walkdir::WalkDir;
 
 use std::path::PathBuf;
@@ -30,23 +31,10 @@ pub struct List;
 #[async_trait]
 impl Command for List {
     /// Display a help menu for the `volt list` command.
-    fn help() -> String {
-        format!(
-            r#"volt {}
-    
-List dependency tree from node_modules.
-Usage: {} {} {} {}
-Options: 
-    
-  {} {} Output verbose messages on internal operations."#,
-            VERSION.bright_green().bold(),
-            "volt".bright_green().bold(),
-            "clone".bright_purple(),
-            "[repository]".white(),
-            "[flags]".white(),
-            "--verbose".blue(),
-            "(-v)".yellow(),
-        )
+    fn help() -> Help {
+        Help::new("list", "List dependency tree from node_modules.")
+            .usage("[flags]")
+            .verbose()
     }
 
     /// Execute the `volt list` command
@@ -71,7 +59,7 @@ Options:
             depth = app.args.iter().find_map(|s| s.parse().ok()).unwrap_or(2);
         }
 
-        let dirs = WalkDir::new("node_modules");
+        let dirs = WalkDir::new(&app.node_modules_dir);
 
         let dependency_paths: Vec<_> = dirs
             .into_iter()
@@ -80,28 +68,37 @@ Options:
             .collect();
 
         if dependency_paths.len() == 1 {
-            println!("{}", "No Dependencies Found!".bright_cyan());
+            reporter::get().output(&"No Dependencies Found!".bright_cyan().to_string());
             return Ok(());
         } else if dependency_paths.is_empty() {
-            println!(
+            reporter::get().output(&format!(
                 "{} {} {}",
                 "Failed to find".bright_cyan(),
                 "node_modules".bright_yellow().bold(),
-                "folder".bright_cyan(),
-            );
+                "folder".bright_cyan()
+            ));
             return Ok(());
         }
 
         for dep in dependency_paths {
-            let dep_path = dep.path().to_str().unwrap();
+            let dep_path = dep
+                .path()
+                .strip_prefix(&app.current_dir)
+                .unwrap_or_else(|_| dep.path())
+                .to_str()
+                .unwrap();
             let dep_path_split: Vec<&str> = dep_path.split('\\').collect();
             let dep_name: &str = dep_path_split[dep_path_split.len() - 1];
             if dep_name != "node_modules"
                 && dep_name != "scripts"
                 && !dep_name.starts_with("node_modules")
             {
-                println!("{} {}", "-".bright_cyan(), dep_name.bright_blue().bold());
-                let dirs = WalkDir::new(format!("node_modules/{}/node_modules", dep_name))
+                reporter::get().output(&format!(
+                    "{} {}",
+                    "-".bright_cyan(),
+                    dep_name.bright_blue().bold()
+                ));
+                let dirs = WalkDir::new(app.node_modules_dir.join(dep_name).join("node_modules"))
                     .follow_links(true)
                     .max_depth((depth - 1) as usize);
                 let dependency_paths: Vec<_> = dirs
@@ -111,7 +108,12 @@ Options:
                     .collect();
 
                 for dep in dependency_paths {
-                    let dep_path = dep.path().to_str().unwrap();
+                    let dep_path = dep
+                        .path()
+                        .strip_prefix(&app.current_dir)
+                        .unwrap_or_else(|_| dep.path())
+                        .to_str()
+                        .unwrap();
                     let dep_path_split: Vec<&str> = dep_path.split('\\').collect();
                     let dep_name: &str = dep_path_split[dep_path_split.len() - 1];
                     if dep_name != "node_modules"
@@ -122,9 +124,7 @@ Options:
                         && !dep_path.contains("test")
                         && !dep_name.starts_with("node_modules")
                     {
-                        for _ in 0..dep_path_split.len() {
-                            print!("  ");
-                        }
+                        let indent = "  ".repeat(dep_path_split.len());
                         let mut version = "".to_owned();
                         for file in read_dir(std::env::temp_dir().join("volt"))? {
                             let file_path: PathBuf = file?.path();
@@ -139,13 +139,14 @@ Options:
                             }
                         }
                         let padding = 50 - (dep_path_split.len() * 2);
-                        print!(
-                            "{} {:<width$}",
+                        reporter::get().output(&format!(
+                            "{}{} {:<width$}{}",
+                            indent,
                             "-".bright_purple(),
                             dep_name,
+                            version.truecolor(190, 190, 190),
                             width = padding
-                        );
-                        println!("{}", version.clone().truecolor(190, 190, 190));
+                        ));
                     }
                 }
             }
@@ -0,0 +1,234 @@
+/*
+Copyright 2021 Volt Contributors
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+    http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+//! Inspect the lock file.
+
+use std::fs::read_to_string;
+use std::process::{exit, Command as Process};
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use colored::Colorize;
+use volt_core::{command::Command, help::Help, model::lock_file::LockFile};
+use volt_utils::app::App;
+use volt_utils::reporter;
+
+use crate::diff::{diff, Change, Jump};
+
+/// Read the lock file to compare against: `target` if it is a file, the lock file at the git
+/// revision `target` otherwise.
+///
+/// A revision without a lock file compares as an empty one, so every package shows as added.
+fn base_lock_file(app: &App, target: &str) -> Result<LockFile> {
+    let path = app.current_dir.join(target);
+
+    if path.is_file() {
+        let contents =
+            read_to_string(&path).with_context(|| format!("failed to read {}", path.display()))?;
+
+        return LockFile::parse(path, &contents)
+            .with_context(|| format!("{} is not a volt lock file", target));
+    }
+
+    let file_name = app
+        .lock_file_path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| String::from("volt.lock"));
+
+    let show = Process::new("git")
+        .args(["show", &format!("{}:./{}", target, file_name)])
+        .current_dir(&app.current_dir)
+        .output()
+        .context("failed to run git")?;
+
+    if show.status.success() {
+        return LockFile::parse(
+            app.lock_file_path.clone(),
+            &String::from_utf8_lossy(&show.stdout),
+        )
+        .with_context(|| format!("the {} of {} is not valid", file_name, target));
+    }
+
+    let is_revision = Process::new("git")
+        .args([
+            "rev-parse",
+            "--verify",
+            "--quiet",
+            &format!("{}^{{commit}}", target),
+        ])
+        .current_dir(&app.current_dir)
+        .output()
+        .map(|output| output.status.success())
+        .unwrap_or(false);
+
+    if is_revision {
+        Ok(LockFile::new(app.lock_file_path.clone()))
+    } else {
+        Err(anyhow!(
+            "{} is neither a lock file nor a git revision",
+            target
+        ))
+    }
+}
+
+fn jump_label(jump: Option<Jump>) -> String {
+    match jump {
+        Some(Jump::Major) => "major".bright_red().bold().to_string(),
+        Some(Jump::Minor) => "minor".yellow().to_string(),
+        Some(Jump::Patch) => "patch".green().to_string(),
+        Some(Jump::Prerelease) => "prerelease".cyan().to_string(),
+        Some(Jump::Downgrade) => "downgrade".bright_red().to_string(),
+        None => "unknown".dimmed().to_string(),
+    }
+}
+
+fn print_changes(changes: &[Change], target: &str) {
+    if changes.is_empty() {
+        reporter::get().output(&format!(
+            "No lock file changes from {}.",
+            target.bright_cyan()
+        ));
+        return;
+    }
+
+    reporter::get().output(&format!(
+        "Lock file changes from {}:\n",
+        target.bright_cyan()
+    ));
+
+    let (mut added, mut removed, mut changed, mut integrity) = (0, 0, 0, 0);
+
+    for change in changes {
+        match change {
+            Change::Added { name, versions } => {
+                added += 1;
+                reporter::get().output(&format!(
+                    "  {} {} {}",
+                    "+".green(),
+                    name,
+                    versions.join(", ").green()
+                ));
+            }
+            Change::Removed { name, versions } => {
+                removed += 1;
+                reporter::get().output(&format!(
+                    "  {} {} {}",
+                    "-".red(),
+                    name,
+                    versions.join(", ").red()
+                ));
+            }
+            Change::Changed {
+                name,
+                from,
+                to,
+                jump,
+            } => {
+                changed += 1;
+                reporter::get().output(&format!(
+                    "  {} {} {} → {} ({})",
+                    "~".yellow(),
+                    name,
+                    from.join(", ").red(),
+                    to.join(", ").green(),
+                    jump_label(*jump)
+                ));
+            }
+            Change::Integrity {
+                name,
+                version,
+                from_sha1,
+                to_sha1,
+                from_tarball,
+                to_tarball,
+            } => {
+                integrity += 1;
+                reporter::get().output(&format!(
+                    "  {} {} {} {} (sha1 {} → {})",
+                    "!".bright_red().bold(),
+                    name,
+                    version,
+                    "integrity changed".bright_red().bold(),
+                    from_sha1,
+                    to_sha1
+                ));
+
+                if from_tarball != to_tarball {
+                    reporter::get()
+                        .output(&format!("      tarball {} → {}", from_tarball, to_tarball));
+                }
+            }
+        }
+    }
+
+    reporter::get().output(&format!(
+        "\n{} added, {} removed, {} changed, {} with changed integrity",
+        added, removed, changed, integrity
+    ));
+}
+
+/// Struct implementation for the `lock` command.
+pub struct Lock;
+
+#[async_trait]
+impl Command for Lock {
+    /// Display a help menu for the `volt lock` command.
+    fn help() -> Help {
+        Help::new("lock", "Inspect the lock file.")
+            .usage("[command] [flags]")
+            .command(
+                "diff [<ref>|<file>]",
+                "Compare the lock file against a git revision (HEAD by default) or another lock file.",
+            )
+            .flag(&["--json"], "Print the changes as JSON.")
+            .verbose()
+            .example("volt lock diff main", "Show what changed in the lock file since main.")
+    }
+
+    /// Execute the `volt lock` command
+    ///
+    /// Lists the packages added, removed and changed since a git revision or another lock file.
+    /// ## Arguments
+    /// * `app` - Instance of the command (`Arc<App>`)
+    /// ## Examples
+    /// ```ignore
+    /// // .exec() is an async call so you need to await it
+    /// Lock.exec(app).await;
+    /// ```
+    /// ## Returns
+    /// * `Result<()>`
+    async fn exec(app: Arc<App>) -> Result<()> {
+        if app.args.get(1).map(String::as_str) != Some("diff") {
+            reporter::get().output(&Self::help().to_string());
+            exit(1);
+        }
+
+        let target = app.args.get(2).map_or("HEAD", String::as_str);
+
+        let current = LockFile::load(app.lock_file_path.clone())
+            .with_context(|| format!("failed to read {}", app.lock_file_path.display()))?;
+
+        let base = base_lock_file(&app, target)?;
+        let changes = diff(&base, &current);
+
+        if reporter::json() {
+            reporter::get().document(&serde_json::to_string_pretty(&changes)?);
+        } else {
+            print_changes(&changes, target);
+        }
+
+        Ok(())
+    }
+}
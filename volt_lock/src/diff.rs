@@ -0,0 +1,251 @@
+/*
+Copyright 2021 Volt Contributors
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+    http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+//! Compare the packages resolved by two lock files.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use node_semver::Version;
+use serde::Serialize;
+use volt_core::model::lock_file::LockFile;
+
+/// How far apart two versions of a package are.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Jump {
+    Major,
+    Minor,
+    Patch,
+    Prerelease,
+    Downgrade,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase", tag = "type")]
+pub enum Change {
+    Added {
+        name: String,
+        versions: Vec<String>,
+    },
+    Removed {
+        name: String,
+        versions: Vec<String>,
+    },
+    Changed {
+        name: String,
+        from: Vec<String>,
+        to: Vec<String>,
+        jump: Option<Jump>,
+    },
+    /// The same version now resolves to different contents
+    Integrity {
+        name: String,
+        version: String,
+        #[serde(rename = "fromSha1")]
+        from_sha1: String,
+        #[serde(rename = "toSha1")]
+        to_sha1: String,
+        #[serde(rename = "fromTarball")]
+        from_tarball: String,
+        #[serde(rename = "toTarball")]
+        to_tarball: String,
+    },
+}
+
+impl Change {
+    pub fn name(&self) -> &str {
+        match self {
+            Change::Added { name, .. }
+            | Change::Removed { name, .. }
+            | Change::Changed { name, .. }
+            | Change::Integrity { name, .. } => name,
+        }
+    }
+}
+
+/// Version → (sha1, tarball), by package name.
+type Resolutions = BTreeMap<String, BTreeMap<String, (String, String)>>;
+
+fn resolutions(lock_file: &LockFile) -> Resolutions {
+    let mut resolutions: Resolutions = BTreeMap::new();
+
+    for dependency in lock_file.dependencies.values() {
+        resolutions
+            .entry(dependency.name.clone())
+            .or_default()
+            .insert(
+                dependency.version.clone(),
+                (dependency.sha1.clone(), dependency.tarball.clone()),
+            );
+    }
+
+    resolutions
+}
+
+/// The jump from the highest version of `from` to the highest of `to`.
+pub fn jump(from: &[String], to: &[String]) -> Option<Jump> {
+    let highest = |versions: &[String]| {
+        versions
+            .iter()
+            .filter_map(|version| Version::parse(version).ok())
+            .max()
+    };
+
+    let (from, to) = (highest(from)?, highest(to)?);
+
+    Some(if to < from {
+        Jump::Downgrade
+    } else if to.major != from.major {
+        Jump::Major
+    } else if to.minor != from.minor {
+        Jump::Minor
+    } else if to.patch != from.patch {
+        Jump::Patch
+    } else {
+        Jump::Prerelease
+    })
+}
+
+/// Changes from `old` to `new`, by package name.
+pub fn diff(old: &LockFile, new: &LockFile) -> Vec<Change> {
+    let old = resolutions(old);
+    let new = resolutions(new);
+
+    let names: BTreeSet<&String> = old.keys().chain(new.keys()).collect();
+    let mut changes = vec![];
+
+    for name in names {
+        let versions = |resolutions: &BTreeMap<String, (String, String)>| -> Vec<String> {
+            resolutions.keys().cloned().collect()
+        };
+
+        match (old.get(name), new.get(name)) {
+            (None, Some(to)) => changes.push(Change::Added {
+                name: name.clone(),
+                versions: versions(to),
+            }),
+            (Some(from), None) => changes.push(Change::Removed {
+                name: name.clone(),
+                versions: versions(from),
+            }),
+            (Some(from), Some(to)) => {
+                if from.keys().ne(to.keys()) {
+                    changes.push(Change::Changed {
+                        name: name.clone(),
+                        from: versions(from),
+                        to: versions(to),
+                        jump: jump(&versions(from), &versions(to)),
+                    });
+                }
+
+                for (version, (from_sha1, from_tarball)) in from.iter() {
+                    match to.get(version) {
+                        Some((to_sha1, to_tarball)) if to_sha1 != from_sha1 => {
+                            changes.push(Change::Integrity {
+                                name: name.clone(),
+                                version: version.clone(),
+                                from_sha1: from_sha1.clone(),
+                                to_sha1: to_sha1.clone(),
+                                from_tarball: from_tarball.clone(),
+                                to_tarball: to_tarball.clone(),
+                            })
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            (None, None) => {}
+        }
+    }
+
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn lock_file(packages: &[(&str, &str, &str)]) -> LockFile {
+        let contents: serde_json::Map<String, serde_json::Value> = packages
+            .iter()
+            .map(|(name, version, sha1)| {
+                (
+                    format!("{}@^{}", name, version),
+                    serde_json::json!({
+                        "name": name,
+                        "version": version,
+                        "tarball": format!("https://registry.npmjs.org/{0}/-/{0}-{1}.tgz", name, version),
+                        "sha1": sha1,
+                        "dependencies": {}
+                    }),
+                )
+            })
+            .collect();
+
+        LockFile::parse(
+            PathBuf::from("volt.lock"),
+            &serde_json::to_string(&contents).unwrap(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn finds_added_removed_and_changed_packages() {
+        let old = lock_file(&[
+            ("react", "17.0.2", "a"),
+            ("lodash", "4.17.20", "b"),
+            ("left-pad", "1.3.0", "c"),
+            ("chalk", "4.1.2", "d"),
+        ]);
+
+        let new = lock_file(&[
+            ("react", "18.2.0", "e"),
+            ("lodash", "4.17.21", "f"),
+            ("chalk", "4.1.2", "tampered"),
+            ("zod", "3.22.4", "g"),
+        ]);
+
+        let changes = diff(&old, &new);
+        let names: Vec<&str> = changes.iter().map(Change::name).collect();
+
+        assert_eq!(names, vec!["chalk", "left-pad", "lodash", "react", "zod"]);
+
+        assert!(matches!(
+            &changes[0],
+            Change::Integrity { to_sha1, .. } if to_sha1 == "tampered"
+        ));
+        assert!(matches!(&changes[1], Change::Removed { .. }));
+        assert!(matches!(
+            &changes[2],
+            Change::Changed {
+                jump: Some(Jump::Patch),
+                ..
+            }
+        ));
+        assert!(matches!(
+            &changes[3],
+            Change::Changed {
+                jump: Some(Jump::Major),
+                ..
+            }
+        ));
+        assert!(matches!(&changes[4], Change::Added { .. }));
+
+        assert!(diff(&new, &new).is_empty());
+        assert_eq!(
+            jump(&[String::from("2.0.0")], &[String::from("1.9.0")]),
+            Some(Jump::Downgrade)
+        );
+    }
+}
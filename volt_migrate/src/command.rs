@@ -14,108 +14,81 @@
     limitations under the License.
 */
 
-//!  Migrates a package from your direct dependencies.
+//! Migrates a project from npm, yarn or pnpm to volt, or from volt to another package manager.
 
-use std::{env, fs, process, sync::Arc};
+use std::fs::{self, read_to_string};
+use std::path::Path;
+use std::sync::Arc;
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use async_trait::async_trait;
 use colored::Colorize;
-use volt_core::{
-    classes::package_manager::PackageManager, command::Command, prompt::prompts::Select, VERSION,
+use volt_core::{classes::package_manager::PackageManager, command::Command, help::Help};
+use volt_utils::{
+    app::App,
+    config::{self, CONFIG_FILE},
+    package::{PackageJson, Workspaces},
+    reporter,
 };
-use volt_utils::app::App;
+
+use crate::{import, settings};
+
 /// Struct implementation for the `Migrate` command.
 pub struct Migrate;
 
 #[async_trait]
 impl Command for Migrate {
     /// Display a help menu for the `volt migrate` command.
-    fn help() -> String {
-        format!(
-            r#"volt {}
-    
-Migrates a project to either yarn,volt,npm or pnpm from your current proj.
-
-Usage: {} {} {} {}
-
-Options: 
-
-  {} {} Output the version number.
-  {} {} Output verbose messages on internal operations."#,
-            VERSION.bright_green().bold(),
-            "volt".bright_green().bold(),
-            "migrate".bright_purple(),
-            "[package_manager_name]".white(),
-            "[flags]".white(),
-            "--version".blue(),
-            "(-ver)".yellow(),
-            "--verbose".blue(),
-            "(-v)".yellow()
+    fn help() -> Help {
+        Help::new(
+            "migrate",
+            "Migrates a project from npm, yarn or pnpm to volt.
+
+Detects the current package manager, imports its lockfile into volt.lock,
+translates .npmrc/.yarnrc settings into .voltrc and verifies the result
+with a fresh install. Pass yarn, pnpm or npm to migrate away from volt instead.",
+        )
+        .usage("[package_manager_name] [flags]")
+        .arg(
+            "[package_manager_name]",
+            "yarn, pnpm or npm, to migrate away from volt.",
+        )
+        .flag(
+            &["--remove-old"],
+            "Remove the old lockfile and package manager configuration.",
         )
+        .flag(&["--no-install"], "Skip the verification install.")
+        .verbose()
     }
 
     /// Execute the `volt migrate` command
     ///
-    /// Migrates a project to either yarn,volt,npm or pnpm from your current proj.
+    /// Migrates a project from npm, yarn or pnpm to volt, or from volt to another package manager.
     /// ## Arguments
     /// * `app` - Instance of the command (`Arc<App>`)
-    /// * `package_manager_name` - The  (`String`)
-    /// * `flags` - List of flags passed in through the CLI (`Vec<String>`)
     /// ## Examples
-    /// ```
-    /// // Migrate a package from your direct dependencies with logging level verbose
+    /// ```ignore
+    /// // Migrate the current project to volt
     /// // .exec() is an async call so you need to await it
-    /// Migrate.exec(app, vec![], vec!["--verbose"]).await;
+    /// Migrate.exec(app).await;
     /// ```
     /// ## Returns
     /// * `Result<()>`
     async fn exec(app: Arc<App>) -> Result<()> {
-        let packagemanagers: Vec<String> = PackageManager::options();
-        let mut packagemanager: String = String::new();
-        if app.args.len() == 1 {
-            packagemanager = app.args[0].to_string();
-        } else if app.args.len() == 1 {
-            let select = Select {
-                message: String::from("Package Manager"),
-                paged: true,
-                selected: Some(1),
-                items: packagemanagers.clone(),
-            };
-            let selected = select.run().unwrap_or_else(|err| {
-                eprintln!(
-                    "{}: {}",
-                    "error".bright_red().bold(),
-                    err.to_string().bright_yellow()
-                );
-                process::exit(1);
-            });
-
-            packagemanager = PackageManager::from_index(selected).unwrap().to_string();
-        } else {
-            println!("{}", "volt migrate only takes 1 argument".bright_red());
-        }
+        let packagemanager = app
+            .args
+            .get(1)
+            .cloned()
+            .unwrap_or_else(|| String::from("volt"));
 
         if packagemanager.eq_ignore_ascii_case("volt") {
-            std::fs::remove_dir_all("node_modules")?;
+            return migrate_to_volt(app).await;
+        }
 
-            let files = fs::read_dir(env::current_dir().unwrap()).unwrap();
-            files
-                .filter_map(Result::ok)
-                .filter(|d| {
-                    if let Some(e) = d.path().extension() {
-                        String::from(e.to_str().unwrap()).contains("lock")
-                    } else {
-                        false
-                    }
-                })
-                .for_each(|f| std::fs::remove_file(f.file_name()).unwrap());
-            println!("{}", "$ volt install".truecolor(147, 148, 148));
-            volt_install::command::Install::exec(app).await?; // NOTE WILL ONLY WORK IF THE VAR DEPENDENCIES is populated
-        } else if packagemanager.eq_ignore_ascii_case("yarn") {
-            std::fs::remove_dir_all("node_modules")?;
+        if packagemanager.eq_ignore_ascii_case("yarn") {
+            volt_utils::fs::remove_dir_all(&app.node_modules_dir)?;
 
-            let files = fs::read_dir(env::current_dir().unwrap()).unwrap();
+            let files = fs::read_dir(&app.current_dir).unwrap();
             files
                 .filter_map(Result::ok)
                 .filter(|d| {
@@ -125,18 +98,19 @@ Options:
                         false
                     }
                 })
-                .for_each(|f| std::fs::remove_file(f.file_name()).unwrap());
+                .for_each(|f| std::fs::remove_file(f.path()).unwrap());
 
-            println!("{}", "$ yarn".truecolor(147, 148, 148));
+            reporter::get().output(&"$ yarn".truecolor(147, 148, 148).to_string());
             std::process::Command::new("yarn")
+                .current_dir(&app.current_dir)
                 .spawn()
                 .expect("failed to execute")
                 .wait()
                 .unwrap();
         } else if packagemanager.eq_ignore_ascii_case("pnpm") {
-            std::fs::remove_dir_all("node_modules")?;
+            volt_utils::fs::remove_dir_all(&app.node_modules_dir)?;
 
-            let files = fs::read_dir(env::current_dir().unwrap()).unwrap();
+            let files = fs::read_dir(&app.current_dir).unwrap();
             files
                 .filter_map(Result::ok)
                 .filter(|d| {
@@ -146,19 +120,20 @@ Options:
                         false
                     }
                 })
-                .for_each(|f| std::fs::remove_file(f.file_name()).unwrap());
+                .for_each(|f| std::fs::remove_file(f.path()).unwrap());
 
-            println!("{}", "$ pnpm install".truecolor(147, 148, 148));
+            reporter::get().output(&"$ pnpm install".truecolor(147, 148, 148).to_string());
             std::process::Command::new("pnpm")
                 .arg("install")
+                .current_dir(&app.current_dir)
                 .spawn()
                 .expect("failed to execute")
                 .wait()
                 .unwrap();
         } else if packagemanager.eq_ignore_ascii_case("npm") {
-            std::fs::remove_dir_all("node_modules")?;
+            volt_utils::fs::remove_dir_all(&app.node_modules_dir)?;
 
-            let files = fs::read_dir(env::current_dir().unwrap()).unwrap();
+            let files = fs::read_dir(&app.current_dir).unwrap();
             files
                 .filter_map(Result::ok)
                 .filter(|d| {
@@ -168,18 +143,241 @@ Options:
                         false
                     }
                 })
-                .for_each(|f| std::fs::remove_file(f.file_name()).unwrap());
+                .for_each(|f| std::fs::remove_file(f.path()).unwrap());
 
-            println!("{}", "$ npm install".truecolor(147, 148, 148));
+            reporter::get().output(&"$ npm install".truecolor(147, 148, 148).to_string());
             std::process::Command::new("npm")
                 .arg("install")
+                .current_dir(&app.current_dir)
                 .spawn()
                 .expect("failed to execute")
                 .wait()
                 .unwrap();
         } else {
-            println!("Volt accepts only volt, yarn, pnpm or npm for volt migrate's args it does not support {}" ,app.args[0].to_string().red());
+            reporter::get().output(&format!("Volt accepts only volt, yarn, pnpm or npm for volt migrate's args it does not support {}" ,packagemanager.red()));
         }
         Ok(())
     }
 }
+
+/// Detect the package manager a project uses from `packageManager`, its lockfile or its config files.
+pub fn detect(dir: &Path, package_json: &PackageJson) -> Option<PackageManager> {
+    if let Some(package_manager) = &package_json.package_manager {
+        let name = package_manager.split('@').next().unwrap_or_default();
+
+        match name {
+            "npm" => return Some(PackageManager::Npm),
+            "yarn" => return Some(PackageManager::Yarn),
+            "pnpm" => return Some(PackageManager::Pnpm),
+            _ => {}
+        }
+    }
+
+    let managers = [
+        PackageManager::Pnpm,
+        PackageManager::Yarn,
+        PackageManager::Npm,
+    ];
+
+    if let Some(manager) = managers
+        .iter()
+        .find(|manager| import::lockfile_name(dir, **manager).is_some())
+    {
+        return Some(*manager);
+    }
+
+    let config_files = [
+        ("pnpm-workspace.yaml", PackageManager::Pnpm),
+        (".pnpmfile.cjs", PackageManager::Pnpm),
+        (".yarnrc.yml", PackageManager::Yarn),
+        (".yarnrc", PackageManager::Yarn),
+        (".npmrc", PackageManager::Npm),
+    ];
+
+    config_files
+        .iter()
+        .find(|(file, _)| dir.join(file).exists())
+        .map(|(_, manager)| *manager)
+}
+
+/// Files and directories which only the old package manager uses.
+fn old_artifacts(manager: PackageManager) -> &'static [&'static str] {
+    match manager {
+        PackageManager::Npm => &["package-lock.json", "npm-shrinkwrap.json"],
+        PackageManager::Yarn => &[
+            "yarn.lock",
+            ".yarnrc",
+            ".yarnrc.yml",
+            ".yarn",
+            ".pnp.cjs",
+            ".pnp.loader.mjs",
+        ],
+        PackageManager::Pnpm => &["pnpm-lock.yaml", "pnpm-workspace.yaml"],
+        PackageManager::Volt => &[],
+    }
+}
+
+/// Read the `packages` globs of pnpm-workspace.yaml.
+fn pnpm_workspace_packages(source: &str) -> Vec<String> {
+    source
+        .lines()
+        .skip_while(|line| !line.starts_with("packages:"))
+        .skip(1)
+        .take_while(|line| line.starts_with(' ') || line.trim().is_empty())
+        .filter_map(|line| line.trim().strip_prefix('-'))
+        .map(|pattern| {
+            pattern
+                .trim()
+                .trim_matches('"')
+                .trim_matches('\'')
+                .to_string()
+        })
+        .collect()
+}
+
+async fn migrate_to_volt(app: Arc<App>) -> Result<()> {
+    let dir = app.current_dir.clone();
+    let verbose = app.has_flag(&["-v", "--verbose"]);
+    let mut package_json =
+        PackageJson::from(&app.current_dir.join("package.json").to_string_lossy());
+
+    let manager = detect(&dir, &package_json).ok_or_else(|| {
+        anyhow!("unable to detect the package manager of this project, there is no lockfile or package manager config")
+    })?;
+
+    reporter::get().info("Detected", &manager.to_string());
+
+    // Lockfile
+    match import::lockfile_name(&dir, manager) {
+        Some(name) => {
+            let packages = import::read_lockfile(&dir, manager)?;
+            let lock_file = import::to_lock_file(&app.lock_file_path, &package_json, &packages);
+
+            lock_file.save()?;
+
+            reporter::get().output(&format!(
+                "{} Imported {} packages from {}",
+                "success".bright_green(),
+                packages.len(),
+                name
+            ));
+        }
+        None => reporter::get().output(&format!(
+            "{}: no lockfile found, dependencies will be resolved from scratch",
+            " warn ".black().on_bright_yellow()
+        )),
+    }
+
+    // Settings
+    let settings = settings::translate(&dir);
+
+    for (key, value) in settings.iter() {
+        config::set_in_file(&dir.join(CONFIG_FILE), key, value)?;
+
+        if verbose {
+            reporter::get().info("Translated", &format!("{}={}", key, value));
+        }
+    }
+
+    if !settings.is_empty() {
+        reporter::get().output(&format!(
+            "{} Wrote {} settings to {}",
+            "success".bright_green(),
+            settings.len(),
+            CONFIG_FILE
+        ));
+    }
+
+    if dir.join(".pnpmfile.cjs").exists() {
+        reporter::get().output(&format!(
+            "{}: .pnpmfile.cjs hooks can't be migrated and will be ignored",
+            " warn ".black().on_bright_yellow()
+        ));
+    }
+
+    // package.json
+    let mut changed = false;
+
+    if package_json.workspaces.is_none() {
+        if let Ok(source) = read_to_string(dir.join("pnpm-workspace.yaml")) {
+            package_json.workspaces = Some(Workspaces::Packages(pnpm_workspace_packages(&source)));
+            changed = true;
+        }
+    }
+
+    // `packageManager` would make corepack refuse to run anything but the old manager
+    if package_json.package_manager.take().is_some() {
+        changed = true;
+    }
+
+    if changed {
+        package_json.save(&app.current_dir.join("package.json"));
+        reporter::get().output(&format!(
+            "{} Updated package.json",
+            "success".bright_green()
+        ));
+    }
+
+    if app.has_flag(&["--remove-old"]) {
+        for artifact in old_artifacts(manager) {
+            let path = dir.join(artifact);
+
+            let removed = if path.is_dir() {
+                volt_utils::fs::remove_dir_all(&path).is_ok()
+            } else {
+                fs::remove_file(&path).is_ok()
+            };
+
+            if removed && verbose {
+                reporter::get().info("Removed", artifact);
+            }
+        }
+    }
+
+    if app.has_flag(&["--no-install"]) {
+        return Ok(());
+    }
+
+    // The old node_modules layout can't be reused
+    if app.node_modules_dir.exists() {
+        volt_utils::fs::remove_dir_all(&app.node_modules_dir)?;
+    }
+
+    reporter::get().output(&"$ volt install".truecolor(147, 148, 148).to_string());
+    volt_install::command::Install::exec(app.clone()).await?;
+
+    verify_install(&app, &package_json, manager)
+}
+
+/// Check that every direct dependency was installed.
+fn verify_install(app: &App, package_json: &PackageJson, manager: PackageManager) -> Result<()> {
+    let missing: Vec<&String> = package_json
+        .dependencies
+        .keys()
+        .filter(|name| {
+            !app.node_modules_dir
+                .join(name)
+                .join("package.json")
+                .exists()
+        })
+        .collect();
+
+    if !missing.is_empty() {
+        return Err(anyhow!(
+            "migration finished but these dependencies are missing from node_modules: {}",
+            missing
+                .iter()
+                .map(|name| name.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+    }
+
+    reporter::get().output(&format!(
+        "{} Migrated from {} to volt",
+        "success".bright_green(),
+        manager
+    ));
+
+    Ok(())
+}
@@ -0,0 +1,640 @@
+/*
+Copyright 2021 Volt Contributors
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+    http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+//! Import the lockfiles of npm, yarn and pnpm into a volt.lock.
+
+use std::collections::HashMap;
+use std::fs::read_to_string;
+use std::path::Path;
+
+use anyhow::{anyhow, Context, Result};
+use serde_json::Value;
+use volt_core::{
+    classes::package_manager::PackageManager,
+    model::lock_file::{DependencyID, DependencyLock, LockFile},
+};
+use volt_utils::package::PackageJson;
+use volt_utils::sri;
+use volt_utils::volt_api::PackageSource;
+
+/// A package pinned by the old lockfile.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportedPackage {
+    pub name: String,
+    pub version: String,
+    pub tarball: String,
+    pub sha1: String,
+    /// The subresource integrity of the tarball (`sha512-<base64>`)
+    pub integrity: Option<String>,
+    pub dependencies: HashMap<String, String>,
+}
+
+impl ImportedPackage {
+    /// An entry of a lockfile which checks its tarball with `integrity`, and its sha1 when it
+    /// has one.
+    fn new(
+        name: String,
+        version: String,
+        tarball: String,
+        integrity: Option<&str>,
+        dependencies: HashMap<String, String>,
+    ) -> Self {
+        ImportedPackage {
+            name,
+            version,
+            tarball,
+            sha1: integrity.and_then(sri::sha1_hex).unwrap_or_default(),
+            integrity: integrity.map(String::from),
+            dependencies,
+        }
+    }
+}
+
+/// The lockfile used by `manager`, relative to the project root.
+pub fn lockfile_name(dir: &Path, manager: PackageManager) -> Option<&'static str> {
+    let candidates: &[&'static str] = match manager {
+        PackageManager::Npm => &["package-lock.json", "npm-shrinkwrap.json"],
+        PackageManager::Yarn => &["yarn.lock"],
+        PackageManager::Pnpm => &["pnpm-lock.yaml"],
+        PackageManager::Volt => &["volt.lock"],
+    };
+
+    candidates
+        .iter()
+        .copied()
+        .find(|name| dir.join(name).exists())
+}
+
+/// Read the packages pinned by the lockfile of `manager` in `dir`.
+pub fn read_lockfile(dir: &Path, manager: PackageManager) -> Result<Vec<ImportedPackage>> {
+    let name =
+        lockfile_name(dir, manager).ok_or_else(|| anyhow!("no lockfile found for {}", manager))?;
+
+    let source =
+        read_to_string(dir.join(name)).with_context(|| format!("failed to read {}", name))?;
+
+    match manager {
+        PackageManager::Npm => parse_npm(&source),
+        PackageManager::Yarn if source.contains("__metadata:") => Ok(parse_yarn_berry(&source)),
+        PackageManager::Yarn => Ok(parse_yarn_classic(&source)),
+        PackageManager::Pnpm => Ok(parse_pnpm(&source)),
+        PackageManager::Volt => Err(anyhow!("the project already uses volt")),
+    }
+}
+
+/// Build a volt.lock from imported packages.
+///
+/// Every range requested by package.json or by another package is pinned to the locked
+/// version of that package which satisfies it.
+pub fn to_lock_file(
+    path: &Path,
+    package_json: &PackageJson,
+    packages: &[ImportedPackage],
+) -> LockFile {
+    let mut versions: HashMap<&str, Vec<&ImportedPackage>> = HashMap::new();
+
+    for package in packages {
+        versions.entry(&package.name).or_default().push(package);
+    }
+
+    let mut requests: Vec<(&String, &String)> = package_json
+        .dependencies
+        .iter()
+        .chain(package_json.dev_dependencies.iter())
+        .chain(package_json.optional_dependencies.iter())
+        .collect();
+
+    for package in packages {
+        requests.extend(package.dependencies.iter());
+    }
+
+    let mut lock_file = LockFile::new(path.to_path_buf());
+
+    for (name, range) in requests {
+        let candidates = match versions.get(name.as_str()) {
+            Some(candidates) => candidates,
+            None => continue,
+        };
+
+        let package = candidates
+            .iter()
+            .find(|package| &package.version == range)
+            .or_else(|| {
+                let version = volt_utils::max_satisfying(
+                    candidates.iter().map(|package| &package.version),
+                    range,
+                )?;
+
+                candidates.iter().find(|package| package.version == version)
+            })
+            // Tags and urls can't be matched against versions
+            .or_else(|| candidates.first());
+
+        if let Some(package) = package {
+            lock_file.dependencies.insert(
+                DependencyID(name.clone(), range.clone()),
+                DependencyLock {
+                    name: package.name.clone(),
+                    version: package.version.clone(),
+                    tarball: package.tarball.clone(),
+                    sha1: package.sha1.clone(),
+                    integrity: package.integrity.clone(),
+                    dependencies: package.dependencies.clone(),
+                    source: PackageSource::Registry,
+                    ..Default::default()
+                },
+            );
+        }
+    }
+
+    lock_file
+}
+
+/// Default registry tarball for lockfiles which don't record one.
+fn registry_tarball(name: &str, version: &str) -> String {
+    let unscoped = name.rsplit('/').next().unwrap_or(name);
+    format!(
+        "https://registry.npmjs.org/{}/-/{}-{}.tgz",
+        name, unscoped, version
+    )
+}
+
+/// Get the name from a `name@range` specifier, which may be scoped (`@scope/name@range`).
+fn split_specifier(specifier: &str) -> Option<(&str, &str)> {
+    let scope = usize::from(specifier.starts_with('@'));
+    let at = specifier[scope..].find('@')? + scope;
+    Some((&specifier[..at], &specifier[at + 1..]))
+}
+
+fn unquote(value: &str) -> &str {
+    value.trim().trim_matches('"').trim_matches('\'')
+}
+
+fn string_map(value: &Value) -> HashMap<String, String> {
+    value
+        .as_object()
+        .map(|map| {
+            map.iter()
+                .filter_map(|(name, range)| Some((name.clone(), range.as_str()?.to_string())))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// package-lock.json, both the nested v1 format and the flat `packages` format of v2 and v3.
+fn parse_npm(source: &str) -> Result<Vec<ImportedPackage>> {
+    let data: Value =
+        serde_json::from_str(source).context("package-lock.json is not valid JSON")?;
+    let mut packages = vec![];
+
+    if let Some(entries) = data["packages"].as_object() {
+        for (path, entry) in entries {
+            let name = match path.rsplit_once("node_modules/") {
+                Some((_, name)) => name,
+                // The root project
+                None => continue,
+            };
+
+            if entry["link"].as_bool() == Some(true) {
+                continue;
+            }
+
+            let version = match entry["version"].as_str() {
+                Some(version) => version,
+                None => continue,
+            };
+
+            let mut dependencies = string_map(&entry["dependencies"]);
+            dependencies.extend(string_map(&entry["optionalDependencies"]));
+
+            packages.push(ImportedPackage::new(
+                entry["name"].as_str().unwrap_or(name).to_string(),
+                version.to_string(),
+                entry["resolved"]
+                    .as_str()
+                    .map(String::from)
+                    .unwrap_or_else(|| registry_tarball(name, version)),
+                entry["integrity"].as_str(),
+                dependencies,
+            ));
+        }
+    } else {
+        collect_npm_v1(&data["dependencies"], &mut packages);
+    }
+
+    Ok(packages)
+}
+
+fn collect_npm_v1(dependencies: &Value, packages: &mut Vec<ImportedPackage>) {
+    let dependencies = match dependencies.as_object() {
+        Some(dependencies) => dependencies,
+        None => return,
+    };
+
+    for (name, entry) in dependencies {
+        if let Some(version) = entry["version"].as_str() {
+            packages.push(ImportedPackage::new(
+                name.clone(),
+                version.to_string(),
+                entry["resolved"]
+                    .as_str()
+                    .map(String::from)
+                    .unwrap_or_else(|| registry_tarball(name, version)),
+                entry["integrity"].as_str(),
+                string_map(&entry["requires"]),
+            ));
+        }
+
+        collect_npm_v1(&entry["dependencies"], packages);
+    }
+}
+
+/// A block of an indentation based lockfile: the header line and its `key value` fields.
+#[derive(Default)]
+struct Block<'a> {
+    header: &'a str,
+    fields: HashMap<&'a str, &'a str>,
+    dependencies: HashMap<String, String>,
+}
+
+/// Split yarn.lock and pnpm-lock.yaml style sources into blocks of entries indented by `indent`.
+///
+/// `separator` splits fields into a key and a value (` ` for yarn classic, `:` for yaml).
+fn blocks<'a>(
+    lines: impl Iterator<Item = &'a str>,
+    indent: usize,
+    separator: char,
+) -> Vec<Block<'a>> {
+    let mut blocks: Vec<Block> = vec![];
+    let mut in_dependencies = false;
+
+    for line in lines {
+        let trimmed = line.trim_start();
+
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+
+        let depth = line.len() - trimmed.len();
+
+        if depth < indent {
+            continue;
+        }
+
+        if depth == indent {
+            blocks.push(Block {
+                header: trimmed.trim_end_matches(':'),
+                ..Block::default()
+            });
+            in_dependencies = false;
+            continue;
+        }
+
+        let block = match blocks.last_mut() {
+            Some(block) => block,
+            None => continue,
+        };
+
+        let (key, value) = match trimmed.split_once(separator) {
+            Some((key, value)) => (unquote(key), unquote(value)),
+            None => (unquote(trimmed.trim_end_matches(':')), ""),
+        };
+
+        if depth == indent + 2 {
+            in_dependencies =
+                value.is_empty() && (key == "dependencies" || key == "optionalDependencies");
+
+            if !value.is_empty() {
+                block.fields.insert(key, value);
+            }
+        } else if depth == indent + 4 && in_dependencies {
+            block
+                .dependencies
+                .insert(key.to_string(), value.to_string());
+        }
+    }
+
+    blocks
+}
+
+/// Classic (v1) yarn.lock.
+fn parse_yarn_classic(source: &str) -> Vec<ImportedPackage> {
+    blocks(source.lines(), 0, ' ')
+        .into_iter()
+        .filter_map(|block| {
+            let specifier = unquote(block.header.split(", ").next()?);
+            let (name, _) = split_specifier(specifier)?;
+            let version = *block.fields.get("version")?;
+            let resolved = block.fields.get("resolved").copied().unwrap_or_default();
+
+            let mut package = ImportedPackage::new(
+                name.to_string(),
+                version.to_string(),
+                resolved.to_string(),
+                block.fields.get("integrity").copied(),
+                block.dependencies,
+            );
+
+            // The sha1 of the tarball is kept in the url fragment
+            match resolved.split_once('#') {
+                Some((tarball, sha1)) => {
+                    package.tarball = tarball.to_string();
+                    package.sha1 = sha1.to_string();
+                }
+                None if resolved.is_empty() => package.tarball = registry_tarball(name, version),
+                None => {}
+            }
+
+            Some(package)
+        })
+        .collect()
+}
+
+/// yarn.lock written by yarn 2 and later, a yaml file with `name@npm:range` keys.
+fn parse_yarn_berry(source: &str) -> Vec<ImportedPackage> {
+    blocks(source.lines(), 0, ':')
+        .into_iter()
+        .filter(|block| block.header != "__metadata")
+        .filter_map(|block| {
+            let specifier = unquote(block.header.split(", ").next()?);
+            let (name, range) = split_specifier(specifier)?;
+
+            // Workspaces, patches and links are not registry packages
+            if !range.starts_with("npm:") {
+                return None;
+            }
+
+            let version = *block.fields.get("version")?;
+
+            // The checksum yarn records is the one of its own zip archive, not of the tarball
+            Some(ImportedPackage {
+                name: name.to_string(),
+                version: version.to_string(),
+                tarball: registry_tarball(name, version),
+                sha1: String::new(),
+                integrity: None,
+                dependencies: block
+                    .dependencies
+                    .into_iter()
+                    .map(|(name, range)| {
+                        let range = range.trim_start_matches("npm:").to_string();
+                        (name, range)
+                    })
+                    .collect(),
+            })
+        })
+        .collect()
+}
+
+/// pnpm-lock.yaml, with `/name/1.0.0` (v5), `/name@1.0.0` (v6) or `name@1.0.0` (v9) keys.
+fn parse_pnpm(source: &str) -> Vec<ImportedPackage> {
+    // Only the `packages:` section pins versions, v9 keeps dependencies in `snapshots:`
+    let mut sections: HashMap<&str, Vec<&str>> = HashMap::new();
+    let mut current = "";
+
+    for line in source.lines() {
+        if !line.starts_with(' ') && line.ends_with(':') {
+            current = line.trim_end_matches(':');
+        } else {
+            sections.entry(current).or_default().push(line);
+        }
+    }
+
+    let snapshots: HashMap<String, HashMap<String, String>> = blocks(
+        sections.remove("snapshots").unwrap_or_default().into_iter(),
+        2,
+        ':',
+    )
+    .into_iter()
+    .filter_map(|block| {
+        let (name, version) = pnpm_package_key(unquote(block.header))?;
+        Some((format!("{}@{}", name, version), block.dependencies))
+    })
+    .collect();
+
+    blocks(
+        sections.remove("packages").unwrap_or_default().into_iter(),
+        2,
+        ':',
+    )
+    .into_iter()
+    .filter_map(|block| {
+        let (name, version) = pnpm_package_key(unquote(block.header))?;
+        let key = format!("{}@{}", name, version);
+
+        let dependencies = if block.dependencies.is_empty() {
+            snapshots.get(&key).cloned().unwrap_or_default()
+        } else {
+            block.dependencies
+        };
+
+        let fields = block.fields;
+        let resolution = fields.get("resolution").copied().unwrap_or_default();
+        let tarball = pnpm_resolution_field(resolution, "tarball")
+            .or_else(|| fields.get("tarball").copied())
+            .map(String::from)
+            .unwrap_or_else(|| registry_tarball(&name, &version));
+
+        Some(ImportedPackage::new(
+            name,
+            version,
+            tarball,
+            pnpm_resolution_field(resolution, "integrity"),
+            // Dependencies are pinned to exact versions, drop the peer suffixes
+            dependencies
+                .into_iter()
+                .map(|(name, version)| (name, strip_peer_suffix(&version).to_string()))
+                .collect(),
+        ))
+    })
+    .collect()
+}
+
+/// A field of the inline `resolution: {integrity: sha512-..., tarball: ...}` of pnpm.
+fn pnpm_resolution_field<'a>(resolution: &'a str, key: &str) -> Option<&'a str> {
+    resolution
+        .trim()
+        .trim_start_matches('{')
+        .trim_end_matches('}')
+        .split(", ")
+        .find_map(|field| {
+            let (name, value) = field.split_once(':')?;
+            (name.trim() == key).then(|| unquote(value))
+        })
+}
+
+/// Split a pnpm package key into the name and version.
+fn pnpm_package_key(key: &str) -> Option<(String, String)> {
+    let key = key.trim_start_matches('/');
+    let key = &key[..key.find('(').unwrap_or(key.len())];
+
+    // v5 separates the version with a slash, later versions with an at sign
+    let (name, version) = match key.rsplit_once('/') {
+        Some((name, version)) if version.starts_with(|c: char| c.is_ascii_digit()) => {
+            (name, version)
+        }
+        _ => split_specifier(key)?,
+    };
+
+    Some((name.to_string(), strip_peer_suffix(version).to_string()))
+}
+
+/// Remove the peer dependency suffix pnpm appends to versions (`1.0.0(react@17.0.2)`, `1.0.0_react@17.0.2`).
+fn strip_peer_suffix(version: &str) -> &str {
+    let end = version.find(['(', '_']).unwrap_or(version.len());
+    &version[..end]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn yarn_classic_entries() {
+        let source = r#"# THIS IS AN AUTOGENERATED FILE. DO NOT EDIT THIS FILE DIRECTLY.
+# yarn lockfile v1
+
+
+"@babel/code-frame@^7.0.0", "@babel/code-frame@^7.10.4":
+  version "7.12.13"
+  resolved "https://registry.yarnpkg.com/@babel/code-frame/-/code-frame-7.12.13.tgz#dcfc826beef65e75c50e21d3837d7d95798dd658"
+  integrity sha512-abc
+  dependencies:
+    "@babel/highlight" "^7.12.13"
+
+js-tokens@^4.0.0:
+  version "4.0.0"
+  resolved "https://registry.yarnpkg.com/js-tokens/-/js-tokens-4.0.0.tgz#19203fb59991df98e3a287050d4647cdeaf32499"
+"#;
+
+        let packages = parse_yarn_classic(source);
+
+        assert_eq!(packages.len(), 2);
+        assert_eq!(packages[0].name, "@babel/code-frame");
+        assert_eq!(packages[0].version, "7.12.13");
+        assert_eq!(packages[0].sha1, "dcfc826beef65e75c50e21d3837d7d95798dd658");
+        assert_eq!(packages[0].integrity.as_deref(), Some("sha512-abc"));
+        assert_eq!(packages[0].dependencies["@babel/highlight"], "^7.12.13");
+    }
+
+    #[test]
+    fn npm_entries_keep_their_integrity() {
+        let source = r#"{
+            "lockfileVersion": 3,
+            "packages": {
+                "": { "name": "app" },
+                "node_modules/left-pad": {
+                    "version": "1.3.0",
+                    "resolved": "https://registry.npmjs.org/left-pad/-/left-pad-1.3.0.tgz",
+                    "integrity": "sha512-XI5MPzVNApjAyhQzphX8BkmKsKUxD4LdyK24iZeQEA== sha1-W4o6d2Xf4AEmHd6RVYnngvjJTR4="
+                }
+            }
+        }"#;
+
+        let packages = parse_npm(source).unwrap();
+
+        assert_eq!(packages.len(), 1);
+        assert!(packages[0]
+            .integrity
+            .as_deref()
+            .unwrap()
+            .starts_with("sha512-"));
+        assert_eq!(packages[0].sha1, "5b8a3a7765dfe001261dde915589e782f8c94d1e");
+    }
+
+    #[test]
+    fn splits_specifiers() {
+        assert_eq!(split_specifier("react@^17"), Some(("react", "^17")));
+        assert_eq!(
+            split_specifier("@babel/core@npm:7.0.0"),
+            Some(("@babel/core", "npm:7.0.0"))
+        );
+        assert_eq!(split_specifier("react"), None);
+        assert_eq!(split_specifier(""), None);
+        assert_eq!(split_specifier("é@1"), Some(("é", "1")));
+    }
+
+    #[test]
+    fn pnpm_entries() {
+        let source = r#"lockfileVersion: '6.0'
+
+dependencies:
+  react:
+    specifier: ^17.0.2
+    version: 17.0.2
+
+packages:
+
+  /loose-envify@1.4.0:
+    resolution: {integrity: sha512-abc}
+    hasBin: true
+    dependencies:
+      js-tokens: 4.0.0
+    dev: false
+
+  /react@17.0.2(react-dom@17.0.2):
+    resolution: {integrity: sha512-def}
+    dependencies:
+      loose-envify: 1.4.0
+"#;
+
+        let packages = parse_pnpm(source);
+
+        assert_eq!(packages.len(), 2);
+        assert_eq!(packages[1].name, "react");
+        assert_eq!(packages[1].version, "17.0.2");
+        assert_eq!(packages[0].dependencies["js-tokens"], "4.0.0");
+        assert_eq!(packages[1].integrity.as_deref(), Some("sha512-def"));
+
+        let key = |name: &str, version: &str| Some((name.to_string(), version.to_string()));
+        assert_eq!(
+            pnpm_package_key("/@types/node/16.0.0"),
+            key("@types/node", "16.0.0")
+        );
+        assert_eq!(
+            pnpm_package_key("/string_decoder/1.3.0_react@17.0.2"),
+            key("string_decoder", "1.3.0")
+        );
+        assert_eq!(
+            pnpm_package_key("@types/node@16.0.0"),
+            key("@types/node", "16.0.0")
+        );
+    }
+
+    #[test]
+    fn pins_ranges_to_locked_versions() {
+        let package = |name: &str, version: &str| ImportedPackage {
+            name: name.to_string(),
+            version: version.to_string(),
+            tarball: String::new(),
+            sha1: String::new(),
+            integrity: None,
+            dependencies: HashMap::new(),
+        };
+
+        let mut package_json = PackageJson::default();
+        package_json
+            .dependencies
+            .insert("lodash".to_string(), "^4.0.0".to_string());
+
+        let lock_file = to_lock_file(
+            Path::new("volt.lock"),
+            &package_json,
+            &[package("lodash", "3.10.1"), package("lodash", "4.17.21")],
+        );
+
+        let lock =
+            &lock_file.dependencies[&DependencyID("lodash".to_string(), "^4.0.0".to_string())];
+        assert_eq!(lock.version, "4.17.21");
+    }
+}
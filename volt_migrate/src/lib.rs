@@ -1 +1,3 @@
 pub mod command;
+pub mod import;
+pub mod settings;
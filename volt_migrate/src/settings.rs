@@ -0,0 +1,176 @@
+/*
+Copyright 2021 Volt Contributors
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+    http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+//! Translate `.npmrc`, `.yarnrc` and `.yarnrc.yml` settings into `.voltrc` settings.
+
+use std::fs::read_to_string;
+use std::path::Path;
+
+use volt_utils::config;
+
+/// `.npmrc` keys which mean the same thing for volt.
+const NPMRC_KEYS: [&str; 12] = [
+    "registry",
+    "strict-ssl",
+    "save-exact",
+    "save-prefix",
+    "engine-strict",
+    "auto-install-peers",
+    "strict-peer-dependencies",
+    "shamefully-hoist",
+    "hoist-pattern",
+    "public-hoist-pattern",
+    "virtual-store-dir",
+    "node-linker",
+];
+
+/// Read the settings of the old package manager in `dir` as volt settings.
+pub fn translate(dir: &Path) -> Vec<(String, String)> {
+    let mut settings = vec![];
+
+    if let Ok(source) = read_to_string(dir.join(".npmrc")) {
+        settings.extend(translate_npmrc(&source));
+    }
+
+    if let Ok(source) = read_to_string(dir.join(".yarnrc")) {
+        settings.extend(translate_yarnrc(&source));
+    }
+
+    if let Ok(source) = read_to_string(dir.join(".yarnrc.yml")) {
+        settings.extend(translate_yarnrc_yml(&source));
+    }
+
+    settings
+}
+
+fn is_relevant(key: &str) -> bool {
+    NPMRC_KEYS.contains(&key)
+        // Scoped registries (`@acme:registry`) and registry credentials (`//host/:_authToken`)
+        || (key.starts_with('@') && key.ends_with(":registry"))
+        || key.starts_with("//")
+}
+
+fn translate_npmrc(source: &str) -> Vec<(String, String)> {
+    config::parse(source)
+        .into_iter()
+        .filter(|(key, _)| is_relevant(key))
+        .collect()
+}
+
+/// Classic yarn settings are `key value` lines with optional quotes.
+fn translate_yarnrc(source: &str) -> Vec<(String, String)> {
+    source
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let (key, value) = line.split_once(char::is_whitespace)?;
+            let key = key.trim_matches('"');
+            let value = value.trim().trim_matches('"');
+
+            if is_relevant(key) {
+                Some((key.to_string(), value.to_string()))
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// Yarn 2+ settings are yaml with their own key names.
+fn translate_yarnrc_yml(source: &str) -> Vec<(String, String)> {
+    let mut settings = vec![];
+    let mut section = "";
+    let mut scope: Option<String> = None;
+
+    for line in source.lines() {
+        let trimmed = line.trim();
+
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+
+        let depth = line.len() - line.trim_start().len();
+        let (key, value) = match trimmed.split_once(':') {
+            Some((key, value)) => (
+                key.trim().trim_matches('"'),
+                value.trim().trim_matches('"').trim_matches('\''),
+            ),
+            None => continue,
+        };
+
+        match (depth, key) {
+            (0, _) => {
+                section = key;
+                scope = None;
+
+                let key = match key {
+                    "npmRegistryServer" => "registry",
+                    "defaultSemverRangePrefix" => "save-prefix",
+                    "enableStrictSsl" => "strict-ssl",
+                    "nodeLinker" => "node-linker",
+                    _ => continue,
+                };
+
+                if !value.is_empty() {
+                    settings.push((key.to_string(), value.to_string()));
+                }
+            }
+            // npmScopes:
+            //   acme:
+            //     npmRegistryServer: "https://npm.acme.dev"
+            (2, _) if section == "npmScopes" && value.is_empty() => scope = Some(key.to_string()),
+            (4, "npmRegistryServer") => {
+                if let Some(scope) = &scope {
+                    settings.push((format!("@{}:registry", scope), value.to_string()));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    settings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn yarn_settings() {
+        assert_eq!(
+            translate_yarnrc(
+                "registry \"https://registry.example.com\"\n--install.frozen-lockfile true\n"
+            ),
+            vec![(
+                "registry".to_string(),
+                "https://registry.example.com".to_string()
+            )]
+        );
+
+        let settings = translate_yarnrc_yml(
+            "nodeLinker: node-modules\nnpmScopes:\n  acme:\n    npmRegistryServer: \"https://npm.acme.dev\"\nyarnPath: .yarn/releases/yarn.cjs\n",
+        );
+
+        assert_eq!(
+            settings,
+            vec![
+                ("node-linker".to_string(), "node-modules".to_string()),
+                (
+                    "@acme:registry".to_string(),
+                    "https://npm.acme.dev".to_string()
+                ),
+            ]
+        );
+    }
+}
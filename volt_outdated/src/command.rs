@@ -13,33 +13,245 @@
 
 //! Check for outdated packages.
 
+use std::fs::read_to_string;
 use std::sync::Arc;
 
 use anyhow::Result;
 use async_trait::async_trait;
-use volt_core::command::Command;
-use volt_utils::app::App;
+use colored::Colorize;
+use futures::future::join_all;
+use serde_json::Value;
+use volt_core::{command::Command, help::Help};
+use volt_utils::{
+    app::App,
+    max_satisfying,
+    package::PackageJson,
+    report::{self, Format},
+    reporter, resolver,
+};
+
+/// A direct dependency which is not at the version it could be.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutdatedPackage {
+    pub name: String,
+    /// Installed version, if it is installed
+    pub current: Option<String>,
+    /// Highest version satisfying the range in package.json
+    pub wanted: Option<String>,
+    pub latest: String,
+    /// `dependencies` or `devDependencies`
+    pub kind: &'static str,
+}
+
+impl OutdatedPackage {
+    pub fn is_outdated(&self) -> bool {
+        let current = self.current.as_deref();
+        current != Some(self.latest.as_str()) || current != self.wanted.as_deref()
+    }
+}
+
+fn installed_version(app: &App, name: &str) -> Option<String> {
+    let data: Value = serde_json::from_str(
+        &read_to_string(app.node_modules_dir.join(name).join("package.json")).ok()?,
+    )
+    .ok()?;
+
+    data["version"].as_str().map(String::from)
+}
+
+/// Compare the direct dependencies of the project with the registry.
+pub async fn outdated(app: &App) -> Result<Vec<OutdatedPackage>> {
+    let package_json = PackageJson::from(&app.current_dir.join("package.json").to_string_lossy());
+    let dependencies = package_json
+        .dependencies
+        .iter()
+        .map(|dependency| (dependency, "dependencies"))
+        .chain(
+            package_json
+                .dev_dependencies
+                .iter()
+                .map(|dependency| (dependency, "devDependencies")),
+        );
+
+    let checks = dependencies.map(|((name, range), kind)| async move {
+        let packument = resolver::packument(app, name.clone()).await?;
+
+        let versions: Vec<String> = packument["versions"]
+            .as_object()
+            .map(|versions| versions.keys().cloned().collect())
+            .unwrap_or_default();
+
+        Ok(OutdatedPackage {
+            name: name.clone(),
+            current: installed_version(app, name),
+            wanted: max_satisfying(versions.iter(), range),
+            latest: packument["dist-tags"]["latest"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string(),
+            kind,
+        })
+    });
+
+    let mut packages = join_all(checks)
+        .await
+        .into_iter()
+        .collect::<Result<Vec<OutdatedPackage>>>()?;
+
+    packages.retain(OutdatedPackage::is_outdated);
+    packages.sort_by(|a, b| a.name.cmp(&b.name));
+
+    Ok(packages)
+}
+
+fn print_table(packages: &[OutdatedPackage]) {
+    let headers = ["Package", "Current", "Wanted", "Latest", "Type"];
+
+    let rows: Vec<[String; 5]> = packages
+        .iter()
+        .map(|package| {
+            [
+                package.name.clone(),
+                package
+                    .current
+                    .clone()
+                    .unwrap_or_else(|| String::from("missing")),
+                package
+                    .wanted
+                    .clone()
+                    .unwrap_or_else(|| String::from("none")),
+                package.latest.clone(),
+                package.kind.to_string(),
+            ]
+        })
+        .collect();
+
+    let widths: Vec<usize> = (0..headers.len())
+        .map(|column| {
+            rows.iter()
+                .map(|row| row[column].len())
+                .chain(std::iter::once(headers[column].len()))
+                .max()
+                .unwrap_or_default()
+        })
+        .collect();
+
+    let pad = |text: &str, column: usize| format!("{:width$}", text, width = widths[column]);
+
+    reporter::get().output(
+        &headers
+            .iter()
+            .enumerate()
+            .map(|(column, header)| pad(header, column).underline().to_string())
+            .collect::<Vec<String>>()
+            .join("  ")
+            .to_string(),
+    );
+
+    for row in rows.iter() {
+        reporter::get().output(&format!(
+            "{}  {}  {}  {}  {}",
+            pad(&row[0], 0).yellow(),
+            pad(&row[1], 1),
+            pad(&row[2], 2).green(),
+            pad(&row[3], 3).bright_magenta(),
+            pad(&row[4], 4)
+        ));
+    }
+}
+
+/// The outdated packages as a markdown table linking to their pages.
+pub fn markdown(packages: &[OutdatedPackage]) -> String {
+    let rows: Vec<Vec<String>> = packages
+        .iter()
+        .map(|package| {
+            vec![
+                report::link(&package.name, &report::package_url(&package.name)),
+                report::escape(package.current.as_deref().unwrap_or("missing")),
+                report::escape(package.wanted.as_deref().unwrap_or("none")),
+                report::escape(&package.latest),
+                package.kind.to_string(),
+            ]
+        })
+        .collect();
+
+    format!(
+        "### Outdated packages\n\n{}",
+        report::markdown_table(&["Package", "Current", "Wanted", "Latest", "Type"], &rows)
+    )
+}
+
 pub struct Outdated {}
 #[async_trait]
 impl Command for Outdated {
-    fn help() -> String {
-        todo!()
+    fn help() -> Help {
+        Help::new(
+            "outdated",
+            "List the direct dependencies which have newer versions.",
+        )
+        .usage("[flags]")
+        .flag(
+            &["--format=<format>"],
+            "Print a table for the terminal or markdown (table, markdown).",
+        )
+        .verbose()
     }
 
     /// Execute the `volt outdated` command
     ///
     /// Check for outdated packages
     /// ## Arguments
-    /// * `error` - Instance of the command (`Arc<App>`)
+    /// * `app` - Instance of the command (`Arc<App>`)
     /// ## Examples
-    /// ```
+    /// ```ignore
     /// // Check for outdated packages
     /// // .exec() is an async call so you need to await it
     /// Outdated.exec(app).await;
     /// ```
     /// ## Returns
     /// * `Result<()>`
-    async fn exec(_app: Arc<App>) -> Result<()> {
+    async fn exec(app: Arc<App>) -> Result<()> {
+        let format = Format::from_app(&app)?;
+        let packages = outdated(&app).await?;
+
+        match format {
+            _ if packages.is_empty() => reporter::get().output("All dependencies are up to date."),
+            Format::Table => print_table(&packages),
+            Format::Markdown => reporter::get().document(markdown(&packages).trim_end()),
+        }
+
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lists_outdated_packages_in_markdown() {
+        let package = OutdatedPackage {
+            name: String::from("react"),
+            current: Some(String::from("17.0.1")),
+            wanted: Some(String::from("17.0.2")),
+            latest: String::from("18.2.0"),
+            kind: "dependencies",
+        };
+
+        assert!(package.is_outdated());
+        assert!(!OutdatedPackage {
+            current: Some(String::from("18.2.0")),
+            wanted: Some(String::from("18.2.0")),
+            ..package.clone()
+        }
+        .is_outdated());
+
+        assert_eq!(
+            markdown(&[package]),
+            "### Outdated packages\n\n\
+             | Package | Current | Wanted | Latest | Type |\n\
+             | --- | --- | --- | --- | --- |\n\
+             | [react](https://www.npmjs.com/package/react) | 17.0.1 | 17.0.2 | 18.2.0 | dependencies |\n"
+        );
+    }
+}
@@ -0,0 +1,162 @@
+/*
+    Copyright 2021 Volt Contributors
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+        http://www.apache.org/licenses/LICENSE-2.0
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Tell about new major versions of the direct dependencies.
+//!
+//! Commands run in a project check in the background, at most once every
+//! `update-check-interval` hours (a day by default), whether the installed direct dependencies
+//! have a new major version, and warn about them after finishing. The check never
+//! delays a command by more than a moment and its failures are ignored. The time of the last
+//! check is kept in `.volt/update-check.json`.
+//!
+//! `update-notifier=false` in `.voltrc` turns this off, and it never runs in CI or when the
+//! output isn't a terminal.
+
+use std::fs::{read_to_string, write};
+use std::io::IsTerminal;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use colored::Colorize;
+use node_semver::Version;
+use serde_json::{json, Value};
+use volt_scripts::pty;
+use volt_utils::{app::App, project_state, reporter};
+
+use crate::command::{outdated, OutdatedPackage};
+
+/// Hours between two checks of a project.
+const DEFAULT_INTERVAL: u64 = 24;
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
+}
+
+/// Whether the project in `app` is due for a check.
+pub fn is_due(app: &App) -> bool {
+    if app.config.get_bool("update-notifier") == Some(false)
+        || pty::is_ci()
+        || !std::io::stdout().is_terminal()
+        || !app.current_dir.join("package.json").exists()
+        || !app.node_modules_dir.exists()
+    {
+        return false;
+    }
+
+    let interval = app
+        .config
+        .get("update-check-interval")
+        .and_then(|hours| hours.parse::<u64>().ok())
+        .unwrap_or(DEFAULT_INTERVAL);
+
+    let checked = read_to_string(project_state::path(app, project_state::UPDATE_CHECK_FILE))
+        .ok()
+        .and_then(|text| serde_json::from_str::<Value>(&text).ok())
+        .and_then(|state| state["checked"].as_u64())
+        .unwrap_or_default();
+
+    now().saturating_sub(checked) >= interval * 3600
+}
+
+fn major(version: &str) -> Option<u64> {
+    Version::parse(version).ok().map(|version| version.major)
+}
+
+/// Whether the latest version of `package` is a major version ahead of the installed one.
+fn has_new_major(package: &OutdatedPackage) -> bool {
+    match (
+        package.current.as_deref().and_then(major),
+        major(&package.latest),
+    ) {
+        (Some(current), Some(latest)) => latest > current,
+        _ => false,
+    }
+}
+
+/// The direct dependencies with a new major version, none when the registry can't tell.
+///
+/// The time of the check is saved once the registry answered, or failed to, so a failing registry
+/// isn't asked on every command, and a check cut short by a command finishing first is done again
+/// on the next one.
+pub async fn check(app: &App) -> Vec<OutdatedPackage> {
+    let packages = outdated(app).await;
+
+    let saved = project_state::create(app).and_then(|dir| {
+        Ok(write(
+            dir.join(project_state::UPDATE_CHECK_FILE),
+            json!({ "checked": now() }).to_string() + "\n",
+        )?)
+    });
+
+    if saved.is_err() {
+        return vec![];
+    }
+
+    let mut packages = packages.unwrap_or_default();
+    packages.retain(has_new_major);
+
+    packages
+}
+
+/// Tell about `packages` through the reporter, after the output of the command.
+pub fn print(packages: &[OutdatedPackage]) {
+    if packages.is_empty() {
+        return;
+    }
+
+    let updates: Vec<String> = packages
+        .iter()
+        .map(|package| {
+            format!(
+                "{} {} → {}",
+                package.name,
+                package.current.as_deref().unwrap_or_default(),
+                package.latest.bright_magenta()
+            )
+        })
+        .collect();
+
+    reporter::get().warn(&format!(
+        "{} {}, see volt outdated",
+        "New major versions".yellow(),
+        updates.join(", ")
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_tells_about_major_versions() {
+        let package = OutdatedPackage {
+            name: String::from("react"),
+            current: Some(String::from("17.0.1")),
+            wanted: Some(String::from("17.0.2")),
+            latest: String::from("18.2.0"),
+            kind: "dependencies",
+        };
+
+        assert!(has_new_major(&package));
+        assert!(!has_new_major(&OutdatedPackage {
+            latest: String::from("17.1.0"),
+            ..package.clone()
+        }));
+        assert!(!has_new_major(&OutdatedPackage {
+            current: None,
+            ..package
+        }));
+    }
+}
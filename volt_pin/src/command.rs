@@ -0,0 +1,355 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Pin the caret and tilde ranges of package.json to the versions in the lock file.
+//!
+//! `volt pin` replaces `^1.2.0` with the locked `1.2.3` and remembers `^1.2.0` in the `pins` of
+//! the lock file, which `volt unpin` puts back. Pins the lock file doesn't know about, from a
+//! manifest pinned by hand, become caret ranges of the pinned version.
+
+use std::collections::{BTreeMap, HashMap};
+use std::process::exit;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use colored::Colorize;
+use node_semver::{Range, Version};
+use volt_core::{command::Command, help::Help, model::lock_file::LockFile};
+use volt_utils::{
+    app::App,
+    package::{self, PackageJson},
+    parse_package_spec, reporter,
+};
+
+/// A dependency range rewritten by `volt pin` or `volt unpin`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change {
+    pub name: String,
+    pub from: String,
+    pub to: String,
+}
+
+/// Whether `range` is a caret or tilde range, the ranges `volt pin` replaces.
+fn is_pinnable(range: &str) -> bool {
+    (range.starts_with('^') || range.starts_with('~')) && Range::parse(range).is_ok()
+}
+
+/// The highest version of `name` in the lock file matching `range`.
+fn locked_version(lock_file: &LockFile, name: &str, range: &str) -> Option<Version> {
+    let range = Range::parse(range).ok()?;
+
+    lock_file
+        .dependencies
+        .values()
+        .filter(|lock| lock.name == name)
+        .filter_map(|lock| Version::parse(&lock.version).ok())
+        .filter(|version| version.satisfies(&range))
+        .max()
+}
+
+/// Pin the ranges of `dependencies` which `selected` accepts, recording them in `pins`.
+pub fn pin(
+    dependencies: &mut HashMap<String, String>,
+    selected: &dyn Fn(&str) -> bool,
+    lock_file: &LockFile,
+    pins: &mut BTreeMap<String, String>,
+) -> Vec<Change> {
+    let mut changes = vec![];
+
+    for (name, range) in dependencies.iter_mut() {
+        if !selected(name) || !is_pinnable(range) {
+            continue;
+        }
+
+        if let Some(version) = locked_version(lock_file, name, range) {
+            pins.insert(name.clone(), range.clone());
+
+            changes.push(Change {
+                name: name.clone(),
+                from: std::mem::replace(range, version.to_string()),
+                to: version.to_string(),
+            });
+        }
+    }
+
+    changes
+}
+
+/// Put back the ranges recorded in `pins` for the exact versions of `dependencies` which
+/// `selected` accepts, or caret ranges if they weren't pinned by `volt pin`.
+pub fn unpin(
+    dependencies: &mut HashMap<String, String>,
+    selected: &dyn Fn(&str) -> bool,
+    pins: &mut BTreeMap<String, String>,
+) -> Vec<Change> {
+    let mut changes = vec![];
+
+    for (name, range) in dependencies.iter_mut() {
+        let version = match Version::parse(range.as_str()) {
+            Ok(version) if selected(name) => version,
+            _ => continue,
+        };
+
+        // A pin changed by hand since no longer matches the range it replaced
+        let restored = pins
+            .remove(name)
+            .filter(|pinned| {
+                Range::parse(pinned)
+                    .map(|pinned| version.satisfies(&pinned))
+                    .unwrap_or(false)
+            })
+            .unwrap_or_else(|| format!("^{}", version));
+
+        changes.push(Change {
+            name: name.clone(),
+            from: std::mem::replace(range, restored.clone()),
+            to: restored,
+        });
+    }
+
+    changes
+}
+
+/// Apply `rewrite` to the dependencies of package.json picked on the command line, then save
+/// package.json and the lock file.
+fn apply(
+    app: &App,
+    verb: &str,
+    done: &str,
+    help: fn() -> Help,
+    rewrite: impl Fn(
+        &mut HashMap<String, String>,
+        &dyn Fn(&str) -> bool,
+        &LockFile,
+        &mut BTreeMap<String, String>,
+    ) -> Vec<Change>,
+) -> Result<()> {
+    let names: Vec<String> = app.args[1..]
+        .iter()
+        .map(|spec| parse_package_spec(spec).0)
+        .collect();
+    let all = app.has_flag(&["--all"]);
+
+    if names.is_empty() && !all {
+        reporter::get().output(&help().to_string());
+        exit(1);
+    }
+
+    let mut package_json =
+        PackageJson::from(&app.current_dir.join("package.json").to_string_lossy());
+
+    for name in names.iter() {
+        if ![
+            &package_json.dependencies,
+            &package_json.dev_dependencies,
+            &package_json.optional_dependencies,
+        ]
+        .iter()
+        .any(|dependencies| dependencies.contains_key(name))
+        {
+            return Err(anyhow!("{} is not a dependency in package.json", name));
+        }
+    }
+
+    let mut lock_file = LockFile::load(app.lock_file_path.clone()).map_err(|_| {
+        anyhow!(
+            "failed to read {}, run volt install first",
+            app.lock_file_path.display()
+        )
+    })?;
+
+    let selected = |name: &str| all || names.iter().any(|selected| selected == name);
+    let up_to_date = lock_file.manifest_hash.is_some()
+        && lock_file.manifest_hash == package::manifest_hash(&app.current_dir).ok();
+    let mut pins = std::mem::take(&mut lock_file.pins);
+    let mut changes = vec![];
+
+    for dependencies in [
+        &mut package_json.dependencies,
+        &mut package_json.dev_dependencies,
+        &mut package_json.optional_dependencies,
+    ] {
+        changes.extend(rewrite(dependencies, &selected, &lock_file, &mut pins));
+    }
+
+    lock_file.pins = pins;
+
+    if changes.is_empty() {
+        reporter::get().output(&format!("{} nothing to {}", "info".yellow(), verb));
+        return Ok(());
+    }
+
+    changes.sort_by(|a, b| a.name.cmp(&b.name));
+
+    for change in changes.iter() {
+        reporter::get().output(&format!(
+            "{} {} {} → {}",
+            done.bright_green(),
+            change.name.bright_cyan(),
+            change.from.dimmed(),
+            change.to
+        ));
+    }
+
+    package_json.save(&app.current_dir.join("package.json"));
+
+    // The locked versions still match the rewritten ranges
+    if up_to_date {
+        lock_file.manifest_hash = package::manifest_hash(&app.current_dir).ok();
+    }
+
+    lock_file.save().context("Failed to save lock file")?;
+
+    Ok(())
+}
+
+/// Struct implementation for the `pin` command.
+pub struct Pin;
+
+#[async_trait]
+impl Command for Pin {
+    /// Display a help menu for the `volt pin` command.
+    fn help() -> Help {
+        Help::new(
+            "pin",
+            "Replace caret and tilde ranges with the locked versions.",
+        )
+        .usage("[packages] [flags]")
+        .arg("[packages]", "The dependencies to pin.")
+        .flag(&["--all"], "Pin every dependency of package.json.")
+        .verbose()
+        .example(
+            "volt pin --all",
+            "Pin every dependency to its locked version.",
+        )
+        .example("volt pin react", "Replace ^18.2.0 with the locked 18.2.1.")
+    }
+
+    /// Execute the `volt pin` command
+    ///
+    /// Pins dependency ranges of package.json to the versions in the lock file.
+    /// ## Arguments
+    /// * `app` - Instance of the command (`Arc<App>`)
+    /// ## Examples
+    /// ```ignore
+    /// // .exec() is an async call so you need to await it
+    /// Pin.exec(app).await;
+    /// ```
+    /// ## Returns
+    /// * `Result<()>`
+    async fn exec(app: Arc<App>) -> Result<()> {
+        apply(&app, "pin", "Pinned", Self::help, pin)
+    }
+}
+
+/// Struct implementation for the `unpin` command.
+pub struct Unpin;
+
+#[async_trait]
+impl Command for Unpin {
+    /// Display a help menu for the `volt unpin` command.
+    fn help() -> Help {
+        Help::new(
+            "unpin",
+            "Put back the ranges of dependencies pinned with volt pin.",
+        )
+        .usage("[packages] [flags]")
+        .arg("[packages]", "The dependencies to unpin.")
+        .flag(&["--all"], "Unpin every dependency of package.json.")
+        .verbose()
+        .example(
+            "volt unpin --all",
+            "Put back the range of every pinned dependency.",
+        )
+    }
+
+    /// Execute the `volt unpin` command
+    ///
+    /// Puts back the ranges `volt pin` replaced.
+    /// ## Arguments
+    /// * `app` - Instance of the command (`Arc<App>`)
+    /// ## Examples
+    /// ```ignore
+    /// // .exec() is an async call so you need to await it
+    /// Unpin.exec(app).await;
+    /// ```
+    /// ## Returns
+    /// * `Result<()>`
+    async fn exec(app: Arc<App>) -> Result<()> {
+        apply(
+            &app,
+            "unpin",
+            "Unpinned",
+            Self::help,
+            |dependencies, selected, _, pins| unpin(dependencies, selected, pins),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn pins_and_unpins_locked_versions() {
+        let lock_file = LockFile::parse(
+            PathBuf::from("volt.lock"),
+            &serde_json::json!({
+                "react@18.2.1": {
+                    "name": "react", "version": "18.2.1", "tarball": "", "sha1": "", "dependencies": {}
+                },
+                "lodash@4.17.21": {
+                    "name": "lodash", "version": "4.17.21", "tarball": "", "sha1": "", "dependencies": {}
+                }
+            })
+            .to_string(),
+        )
+        .unwrap();
+
+        let original: HashMap<String, String> = [
+            ("react", "^18.2.0"),
+            ("lodash", "~4.17.0"),
+            ("left-pad", "1.3.0"),
+            ("local", "file:../local"),
+        ]
+        .iter()
+        .map(|(name, range)| (name.to_string(), range.to_string()))
+        .collect();
+
+        let mut dependencies = original.clone();
+        let mut pins = BTreeMap::new();
+
+        let mut changes = pin(&mut dependencies, &|_| true, &lock_file, &mut pins);
+        changes.sort_by(|a, b| a.name.cmp(&b.name));
+
+        assert_eq!(
+            changes.iter().map(|change| &change.to).collect::<Vec<_>>(),
+            ["4.17.21", "18.2.1"]
+        );
+        assert_eq!(dependencies["react"], "18.2.1");
+        assert_eq!(pins["react"], "^18.2.0");
+
+        unpin(&mut dependencies, &|_| true, &mut pins);
+
+        // Pinned by hand, left-pad gets a caret range
+        assert_eq!(dependencies["react"], original["react"]);
+        assert_eq!(dependencies["lodash"], original["lodash"]);
+        assert_eq!(dependencies["left-pad"], "^1.3.0");
+        assert!(pins.is_empty());
+    }
+}
@@ -0,0 +1,108 @@
+/*
+Copyright 2021 Volt Contributors
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+    http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+//! Check the registry is reachable and the auth token is valid.
+
+use std::sync::Arc;
+use std::time::Instant;
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use colored::Colorize;
+use volt_core::{command::Command, help::Help};
+use volt_utils::{app::App, registry, reporter};
+
+/// Struct implementation for the `ping` command.
+pub struct Ping;
+
+#[async_trait]
+impl Command for Ping {
+    /// Display a help menu for the `volt ping` command.
+    fn help() -> Help {
+        Help::new(
+            "ping",
+            "Check the registry is reachable, how long it takes to answer and whether the
+configured auth token is valid.",
+        )
+        .usage("[registry] [flags]")
+        .arg(
+            "[registry]",
+            "The registry to ping, the configured one by default.",
+        )
+        .verbose()
+    }
+
+    /// Execute the `volt ping` command
+    ///
+    /// Pings the registry and checks the auth token with a whoami round trip.
+    /// ## Arguments
+    /// * `app` - Instance of the command (`Arc<App>`)
+    /// ## Examples
+    /// ```ignore
+    /// // .exec() is an async call so you need to await it
+    /// Ping.exec(app).await;
+    /// ```
+    /// ## Returns
+    /// * `Result<()>`
+    async fn exec(app: Arc<App>) -> Result<()> {
+        let (registry, source) = match app.args.get(1) {
+            Some(registry) => (
+                registry.trim_end_matches('/').to_string(),
+                String::from("command line"),
+            ),
+            None => (
+                registry::url(&app),
+                app.config.source("registry").map_or_else(
+                    || String::from("default"),
+                    |path| path.display().to_string(),
+                ),
+            ),
+        };
+
+        reporter::get().info("Registry", &registry.bright_cyan().to_string());
+        reporter::get().info("Configured by", &source.to_string());
+
+        let start = Instant::now();
+
+        reqwest::Client::new()
+            .get(format!("{}/-/ping", registry))
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .with_context(|| format!("{} is not reachable", registry))?;
+
+        reporter::get().info("Latency", &format!("{}ms", start.elapsed().as_millis()));
+
+        match registry::auth_token(&app, &registry) {
+            Some(token) => {
+                let start = Instant::now();
+
+                let username = registry::whoami(&registry, &token).await?;
+
+                reporter::get().info(
+                    "Auth",
+                    &format!(
+                        "logged in as {} ({}ms)",
+                        username.bright_green(),
+                        start.elapsed().as_millis()
+                    ),
+                );
+            }
+            None => reporter::get().info("Auth", "no auth token configured"),
+        }
+
+        reporter::get().output(&format!("{}: {} is up", "success".bright_green(), registry));
+
+        Ok(())
+    }
+}
@@ -11,35 +11,238 @@
     limitations under the License.
 */
 
-//! Check for outdated packages.
+//! Publish a package to the registry.
 
 use std::sync::Arc;
 
-use anyhow::Result;
+use anyhow::{anyhow, Context, Result};
 use async_trait::async_trait;
-use volt_core::command::Command;
-use volt_utils::app::App;
-pub struct Publish {}
+use colored::Colorize;
+use serde_json::{json, Value};
+use volt_core::{command::Command, help::Help};
+use volt_utils::pack::{self, Tarball};
+use volt_utils::{app::App, registry, reporter};
+
+use crate::oidc;
+
+/// Struct implementation for the `publish` command.
+pub struct Publish;
+
 #[async_trait]
 impl Command for Publish {
-    fn help() -> String {
-        todo!()
+    /// Display a help menu for the `volt publish` command.
+    fn help() -> Help {
+        Help::new(
+            "publish",
+            "Publish the package in the current directory to the registry.
+
+With --provenance in GitHub Actions (with id-token: write) or with NPM_ID_TOKEN set, the
+identity token of the job is exchanged for a short-lived publish token, no stored token
+is needed once the workflow is a trusted publisher of the package.",
+        )
+        .usage("[flags]")
+        .flag(
+            &["--provenance"],
+            "Publish with a short-lived token from the CI identity (trusted publishing).",
+        )
+        .flag(
+            &["--tag=<tag>"],
+            "Dist-tag of the published version (default: latest).",
+        )
+        .flag(
+            &["--access=<access>"],
+            "public or restricted, for scoped packages.",
+        )
+        .flag(
+            &["--dry-run"],
+            "Pack and show what would be published, without publishing.",
+        )
+        .flag(
+            &["--registry=<url>"],
+            "Publish to this registry instead of the configured one.",
+        )
+        .verbose()
     }
 
-    /// Execute the `volt outdated` command
+    /// Execute the `volt publish` command
     ///
-    /// Check for outdated packages
+    /// Packs the package and uploads it to the registry.
     /// ## Arguments
-    /// * `error` - Instance of the command (`Arc<App>`)
+    /// * `app` - Instance of the command (`Arc<App>`)
     /// ## Examples
-    /// ```
-    /// // Check for outdated packages
+    /// ```ignore
     /// // .exec() is an async call so you need to await it
     /// Publish.exec(app).await;
     /// ```
     /// ## Returns
     /// * `Result<()>`
-    async fn exec(_app: Arc<App>) -> Result<()> {
+    async fn exec(app: Arc<App>) -> Result<()> {
+        let verbose = app.has_flag(&["-v", "--verbose"]);
+
+        let path = app.current_dir.join("package.json");
+        let manifest: Value = serde_json::from_str(
+            &std::fs::read_to_string(&path)
+                .with_context(|| format!("failed to read {}", path.display()))?,
+        )?;
+
+        let name = manifest["name"]
+            .as_str()
+            .ok_or_else(|| anyhow!("package.json has no name"))?
+            .to_string();
+        let version = manifest["version"]
+            .as_str()
+            .ok_or_else(|| anyhow!("package.json has no version"))?
+            .to_string();
+
+        if manifest["private"].as_bool() == Some(true) {
+            return Err(anyhow!("{} is private and can't be published", name));
+        }
+
+        let registry = app
+            .flag_value(&["--registry"])
+            .or_else(|| {
+                manifest["publishConfig"]["registry"]
+                    .as_str()
+                    .map(String::from)
+            })
+            .map(|registry| registry.trim_end_matches('/').to_string())
+            .unwrap_or_else(|| registry::url_for(&app, &name));
+
+        let tag = app
+            .flag_value(&["--tag"])
+            .unwrap_or_else(|| String::from("latest"));
+
+        let tarball = pack::pack(&app.current_dir, &manifest)?;
+
+        for file in tarball.files.iter() {
+            if verbose {
+                reporter::get().info("Packed", &file.to_string());
+            }
+        }
+
+        reporter::get().info(
+            "Packed",
+            &format!(
+                "{}@{} ({} files, {} bytes)",
+                name.bright_cyan(),
+                version,
+                tarball.files.len(),
+                tarball.data.len()
+            ),
+        );
+
+        if app.has_flag(&["--dry-run"]) {
+            reporter::get().info(
+                "Would publish",
+                &format!("to {} with tag {}", registry, tag),
+            );
+            return Ok(());
+        }
+
+        let token = if app.has_flag(&["--provenance"]) {
+            let id_token = oidc::id_token(&oidc::audience(&registry))
+                .await?
+                .ok_or_else(|| {
+                    anyhow!(
+                        "--provenance needs a CI identity token, run in GitHub Actions with `id-token: write` or set NPM_ID_TOKEN"
+                    )
+                })?;
+
+            if verbose {
+                reporter::get().info(
+                    "Exchanging the identity token",
+                    &format!("from {}", id_token.provider),
+                );
+            }
+
+            oidc::exchange(&registry, &name, &id_token.token).await?
+        } else {
+            registry::auth_token(&app, &registry).ok_or_else(|| {
+                anyhow!(
+                    "not logged in to {}, add {} to .voltrc or publish from CI with --provenance",
+                    registry,
+                    registry::scoped_key(&registry, "_authToken")
+                )
+            })?
+        };
+
+        let access = app.flag_value(&["--access"]).or_else(|| {
+            manifest["publishConfig"]["access"]
+                .as_str()
+                .map(String::from)
+        });
+
+        let document = document(&manifest, &registry, &tag, access, &tarball);
+
+        let response = reqwest::Client::new()
+            .put(format!("{}/{}", registry, oidc::escape_name(&name)))
+            .bearer_auth(token)
+            .header("content-type", "application/json")
+            .body(serde_json::to_vec(&document)?)
+            .send()
+            .await
+            .with_context(|| format!("failed to reach {}", registry))?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body: Value = serde_json::from_str(&response.text().await.unwrap_or_default())
+                .unwrap_or(Value::Null);
+
+            return Err(anyhow!(
+                "{} refused {}@{}: {}",
+                registry,
+                name,
+                version,
+                body["error"]
+                    .as_str()
+                    .or_else(|| body["message"].as_str())
+                    .unwrap_or_else(|| status.as_str())
+            ));
+        }
+
+        reporter::get().output(&format!(
+            "{} + {}@{}",
+            "success".bright_green(),
+            name,
+            version
+        ));
+
         Ok(())
     }
 }
+
+/// The document the registry expects for a new version, with the tarball attached.
+fn document(
+    manifest: &Value,
+    registry: &str,
+    tag: &str,
+    access: Option<String>,
+    tarball: &Tarball,
+) -> Value {
+    let name = manifest["name"].as_str().unwrap_or_default();
+    let version = manifest["version"].as_str().unwrap_or_default();
+
+    let mut published = manifest.clone();
+    published["_id"] = json!(format!("{}@{}", name, version));
+    published["dist"] = json!({
+        "shasum": tarball.shasum,
+        "integrity": tarball.integrity,
+        "tarball": format!("{}/{}/-/{}", registry, name, tarball.file_name),
+    });
+
+    json!({
+        "_id": name,
+        "name": name,
+        "description": manifest["description"],
+        "dist-tags": { tag: version },
+        "versions": { version: published },
+        "access": access,
+        "_attachments": {
+            tarball.file_name.clone(): {
+                "content_type": "application/octet-stream",
+                "data": base64::encode(&tarball.data),
+                "length": tarball.data.len(),
+            }
+        }
+    })
+}
@@ -0,0 +1,133 @@
+/*
+    Copyright 2021 Volt Contributors
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+        http://www.apache.org/licenses/LICENSE-2.0
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Trusted publishing: trade the OIDC identity token of a CI job for a short-lived token
+//! allowed to publish one package, so no long-lived token has to be stored in the CI secrets.
+//!
+//! The package must have the repository and workflow declared as a trusted publisher on the
+//! registry. Identity tokens come from GitHub Actions (the job needs `id-token: write`) or
+//! from `NPM_ID_TOKEN`, set through `id_tokens` on GitLab CI or by any other provider.
+
+use std::env;
+
+use anyhow::{anyhow, Context, Result};
+use serde_json::Value;
+
+pub struct IdToken {
+    /// The CI provider, for logs
+    pub provider: &'static str,
+    pub token: String,
+}
+
+/// The audience registries expect in identity tokens (`npm:registry.npmjs.org`).
+pub fn audience(registry: &str) -> String {
+    let host = registry
+        .split_once("://")
+        .map_or(registry, |(_, host)| host)
+        .trim_end_matches('/');
+
+    format!("npm:{}", host)
+}
+
+/// Get an identity token for `audience` from the CI provider, `None` outside of a supported CI.
+pub async fn id_token(audience: &str) -> Result<Option<IdToken>> {
+    if let Ok(token) = env::var("NPM_ID_TOKEN") {
+        return Ok(Some(IdToken {
+            provider: "NPM_ID_TOKEN",
+            token,
+        }));
+    }
+
+    let (url, request_token) = match (
+        env::var("ACTIONS_ID_TOKEN_REQUEST_URL"),
+        env::var("ACTIONS_ID_TOKEN_REQUEST_TOKEN"),
+    ) {
+        (Ok(url), Ok(request_token)) => (url, request_token),
+        _ => return Ok(None),
+    };
+
+    let response = reqwest::Client::new()
+        .get(url)
+        .query(&[("audience", audience)])
+        .bearer_auth(request_token)
+        .send()
+        .await?
+        .error_for_status()
+        .context("failed to get an identity token from GitHub Actions")?;
+
+    let body: Value = serde_json::from_str(&response.text().await?)?;
+
+    let token = body["value"]
+        .as_str()
+        .ok_or_else(|| anyhow!("GitHub Actions returned no identity token"))?;
+
+    Ok(Some(IdToken {
+        provider: "GitHub Actions",
+        token: token.to_string(),
+    }))
+}
+
+/// `@scope/name` → `@scope%2fname`, the way the registry expects names in paths.
+pub fn escape_name(name: &str) -> String {
+    name.replace('/', "%2f")
+}
+
+/// Exchange `id_token` for a short-lived token allowed to publish `package` to `registry`.
+pub async fn exchange(registry: &str, package: &str, id_token: &str) -> Result<String> {
+    let response = reqwest::Client::new()
+        .post(format!(
+            "{}/-/npm/v1/oidc/token/exchange/package/{}",
+            registry.trim_end_matches('/'),
+            escape_name(package)
+        ))
+        .bearer_auth(id_token)
+        .send()
+        .await?;
+
+    let status = response.status();
+    let body: Value =
+        serde_json::from_str(&response.text().await.unwrap_or_default()).unwrap_or(Value::Null);
+
+    if !status.is_success() {
+        let message = body["message"]
+            .as_str()
+            .or_else(|| body["error"].as_str())
+            .unwrap_or_else(|| status.canonical_reason().unwrap_or_default());
+
+        return Err(anyhow!(
+            "{} refused the identity token for {} ({}), check that this workflow is a trusted publisher of the package",
+            registry,
+            package,
+            message
+        ));
+    }
+
+    body["token"]
+        .as_str()
+        .map(String::from)
+        .ok_or_else(|| anyhow!("{} returned no token", registry))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn audiences_and_names() {
+        assert_eq!(
+            audience("https://registry.npmjs.org/"),
+            "npm:registry.npmjs.org"
+        );
+        assert_eq!(escape_name("@volt/cli"), "@volt%2fcli");
+    }
+}
@@ -16,49 +16,36 @@
 
 //! Remove a package from your direct dependencies.
 
-use std::{io::Write, path::Path, process, sync::Arc};
+use std::{io::Write, process, sync::Arc};
 
 use anyhow::Result;
 use async_trait::async_trait;
 use colored::Colorize;
-use tokio::{
-    fs::{remove_dir_all, remove_file},
-    sync::Mutex,
-};
+use tokio::{fs::remove_file, sync::Mutex};
 use volt_core::{
     command::Command,
+    help::Help,
     model::lock_file::{DependencyID, LockFile},
-    VERSION,
 };
-use volt_utils::{app::App, get_volt_response, package::PackageJson};
+use volt_utils::{
+    app::App,
+    fs::remove_dir_all,
+    mirror,
+    package::{self, PackageJson},
+    reporter,
+};
 /// Struct implementation for the `Remove` command.
 pub struct Remove;
 
 #[async_trait]
 impl Command for Remove {
     /// Display a help menu for the `volt remove` command.
-    fn help() -> String {
-        format!(
-            r#"volt {}
-    
-Removes a package from your direct dependencies.
-
-Usage: {} {} {} {}
-
-Options: 
-
-  {} {} Output the version number.
-  {} {} Output verbose messages on internal operations."#,
-            VERSION.bright_green().bold(),
-            "volt".bright_green().bold(),
-            "remove".bright_purple(),
-            "[packages]".white(),
-            "[flags]".white(),
-            "--version".blue(),
-            "(-ver)".yellow(),
-            "--verbose".blue(),
-            "(-v)".yellow()
-        )
+    fn help() -> Help {
+        Help::new("remove", "Removes a package from your direct dependencies.")
+            .usage("[packages] [flags]")
+            .arg("[packages]", "Packages to remove.")
+            .flag(&["--version", "-ver"], "Output the version number.")
+            .verbose()
     }
 
     /// Execute the `volt remove` command
@@ -78,7 +65,7 @@ Options:
     /// * `Result<()>`
     async fn exec(app: Arc<App>) -> Result<()> {
         if app.args.len() == 1 {
-            println!("{}", Self::help());
+            reporter::get().output(&Self::help().to_string());
             process::exit(1);
         }
 
@@ -89,10 +76,10 @@ Options:
             }
         }
 
-        let package_json_dir = std::env::current_dir()?.join("package.json");
+        let package_json_dir = app.current_dir.join("package.json");
 
         if !package_json_dir.exists() {
-            println!("{} no package.json found", "error".bright_red());
+            reporter::get().output(&format!("{} no package.json found", "error".bright_red()));
             print!("Do you want to initialize package.json (Y/N): ");
             std::io::stdout().flush().expect("Could not flush stdout");
             let mut string: String = String::new();
@@ -104,11 +91,13 @@ Options:
             }
         }
 
-        let package_file = Arc::new(Mutex::new(PackageJson::from("package.json")));
+        let package_file = Arc::new(Mutex::new(PackageJson::from(
+            &app.current_dir.join("package.json").to_string_lossy(),
+        )));
 
         // let mut handles = vec![];
 
-        println!("{}", "Removing dependencies".bright_purple());
+        reporter::get().output(&"Removing dependencies".bright_purple().to_string());
 
         for package in packages {
             let package_file = package_file.clone();
@@ -119,12 +108,12 @@ Options:
 
             package_json_file.dependencies.remove(&package);
 
-            package_json_file.save();
+            package_json_file.save(&app.current_dir.join("package.json"));
 
             let mut lock_file = LockFile::load(app_new.lock_file_path.to_path_buf())
                 .unwrap_or_else(|_| LockFile::new(app_new.lock_file_path.to_path_buf()));
 
-            let response = get_volt_response(package.to_string()).await;
+            let response = mirror::get_volt_response(&app_new, &package).await?;
 
             let current_version = response.versions.get(&response.version).unwrap();
 
@@ -143,31 +132,29 @@ Options:
                     .dependencies
                     .remove(&DependencyID(object.clone().name, object.clone().version));
 
-                let scripts = Path::new("node_modules/scripts")
+                let scripts = app_new
+                    .node_modules_dir
+                    .join("scripts")
                     .join(format!("{}.cmd", object.clone().name).as_str());
 
                 if scripts.exists() {
-                    remove_file(format!(
-                        "node_modules/scripts/{}",
-                        scripts.file_name().unwrap().to_str().unwrap()
-                    ))
-                    .await
-                    .unwrap_or_else(|err| {
-                        println!(
+                    remove_file(&scripts).await.unwrap_or_else(|err| {
+                        reporter::get().output(&format!(
                             "Failed to delete scripts file in node_modules/scripts: {}",
                             err
-                        );
+                        ));
                     });
                 }
             }
 
+            lock_file.manifest_hash = package::manifest_hash(&app_new.current_dir).ok();
+
             lock_file.save().unwrap();
 
-            let node_modules_dir = std::env::current_dir().unwrap().join("node_modules");
-            let dep_dir = node_modules_dir.join(&package);
+            let dep_dir = app_new.node_modules_dir.join(&package);
             if dep_dir.exists() {
-                remove_dir_all(dep_dir).await.unwrap_or_else(|_| {
-                    println!("Failed to delete dependency dir in node_modules")
+                remove_dir_all(&dep_dir).unwrap_or_else(|_| {
+                    reporter::get().output("Failed to delete dependency dir in node_modules")
                 });
             }
         }
@@ -178,7 +165,7 @@ Options:
         //     }
         // }
 
-        println!("{}", "Successfully Removed Packages".bright_blue());
+        reporter::get().output(&"Successfully Removed Packages".bright_blue().to_string());
 
         Ok(())
     }
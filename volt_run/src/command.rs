@@ -15,17 +15,25 @@
 */
 
 use std::fs::read_dir;
-use std::path::Path;
 use std::sync::Arc;
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use async_trait::async_trait;
 use colored::Colorize;
-use volt_core::command::Command;
-use volt_core::VERSION;
-use volt_scripts::command::Script;
+use volt_core::{command::Command, help::Help};
+use volt_scripts::{
+    env_files::EnvFiles,
+    output::OutputMode,
+    pty::Terminal,
+    report,
+    runner::{self, Job},
+    supervise::Timeouts,
+};
 use volt_utils::app::App;
 use volt_utils::package::PackageJson;
+use volt_utils::project_state;
+use volt_utils::reporter;
+use volt_utils::workspace::Workspace;
 
 /// Struct implementation for the `Run` command.
 pub struct Run;
@@ -33,24 +41,34 @@ pub struct Run;
 #[async_trait]
 impl Command for Run {
     /// Display a help menu for the `volt run` command.
-    fn help() -> String {
-        format!(
-            r#"volt {}
-    
-Run a pre-defined package script
-
-Usage: {} {} {}
-    
-Options:
-    
-  {} {} Output verbose messages on internal operations."#,
-            VERSION.bright_green().bold(),
-            "volt".bright_green().bold(),
-            "run".bright_purple(),
-            "file-name".white(),
-            "--verbose".blue(),
-            "(-v)".yellow()
-        )
+    fn help() -> Help {
+        Help::new("run", "Run a pre-defined package script.")
+            .usage("[script] [flags]")
+            .arg("[script]", "The script, or a pattern of scripts (test:*) with --parallel.")
+            .verbose()
+            .flag(&["--parallel"], "Run scripts matching a pattern (test:*) at the same time.")
+            .flag(&["--recursive", "-r"], "Run the script in every workspace package which has it.")
+            .flag(
+                &["--stream"],
+                "Prefix each line with the script it comes from (default with --parallel/--recursive).",
+            )
+            .flag(
+                &["--aggregate-output"],
+                "Show the output of each script at once when it is done.",
+            )
+            .flag(&["--raw"], "Show the output as the scripts write it, without prefixes.")
+            .flag(&["--no-tty"], "Don't run scripts under a pseudo-terminal (the default in CI).")
+            .flag(
+                &["--timeout=<seconds>"],
+                "Stop scripts running for longer (script-timeout in .voltrc).",
+            )
+            .flag(
+                &["--env-file=<path>"],
+                "Load variables from another file, after the env-files of .voltrc (.env).",
+            )
+            .example("volt run test", "Run pretest, test and posttest, with node_modules/.bin in PATH.")
+            .example("volt run --parallel \"test:*\"", "Run every test: script at the same time.")
+            .example("volt run start --env-file=.env.test", "Run start with the variables of .env.test.")
     }
 
     /// Execute the `volt run` command
@@ -69,22 +87,79 @@ Options:
     /// ## Returns
     /// * `Result<()>`
     async fn exec(app: Arc<App>) -> Result<()> {
-        if app.clone().args.len() == 1_usize {
-            let package_json = PackageJson::from("package.json");
+        if app.args.len() >= 2 && app.current_dir.join("package.json").exists() {
+            let package_json =
+                PackageJson::from(&app.current_dir.join("package.json").to_string_lossy());
+            let name = &app.args[1];
+            let terminal = Terminal::detect(app.has_flag(&["--no-tty"]));
+            let mode = OutputMode::from_flags(
+                app.has_flag(&["--aggregate-output"]),
+                app.has_flag(&["--raw"]),
+            );
+            let timeouts = Timeouts::from_app(&app)?;
+            let env_files = EnvFiles::from_app(&app);
 
-            let args = app.args.clone();
-            let command: &str = args[0].as_str();
+            report::log_to(match project_state::create(&app) {
+                Ok(dir) => dir.join(project_state::LOGS_DIR),
+                Err(_) => app.cache_dir.join("logs"),
+            });
 
-            if package_json.scripts.contains_key(command) {
-                Script::exec(app.clone()).await.unwrap();
-                std::process::exit(0);
+            // volt run build --recursive
+            if app.has_flag(&["-r", "--recursive"]) {
+                return runner::run_jobs(
+                    &recursive_jobs(&app, name, &timeouts, &env_files)?,
+                    app.has_flag(&["--parallel"]),
+                    terminal,
+                    mode,
+                );
+            }
+
+            let env = env_files.load(&app.current_dir)?;
+
+            // volt run "test:*"
+            if runner::is_pattern(name) {
+                let matches = runner::matching_scripts(name, &package_json.scripts);
+
+                if matches.is_empty() {
+                    reporter::get().output(&format!(
+                        "{}: no scripts match {}",
+                        "error".bright_red().bold(),
+                        name.bright_yellow().bold()
+                    ));
+                    std::process::exit(1);
+                }
+
+                return runner::run_scripts(
+                    &app.current_dir,
+                    &matches,
+                    &package_json.scripts,
+                    app.has_flag(&["--parallel"]),
+                    terminal,
+                    mode,
+                    &timeouts,
+                    &env,
+                );
+            }
+
+            // `pretest` and `posttest` run around `test`
+            if package_json.scripts.contains_key(name) {
+                return runner::run_scripts(
+                    &app.current_dir,
+                    &runner::lifecycle(name, &package_json.scripts),
+                    &package_json.scripts,
+                    false,
+                    terminal,
+                    mode,
+                    &timeouts,
+                    &env,
+                );
             }
         }
 
-        let path = Path::new("node_modules/scripts");
+        let path = app.node_modules_dir.join("scripts");
 
         if path.exists() {
-            let files = read_dir("node_modules/scripts").unwrap();
+            let files = read_dir(&path).unwrap();
 
             let mut files_vec: Vec<String> = vec![];
 
@@ -98,35 +173,96 @@ Options:
 
             if app.clone().args.len() == 1_usize {
                 let print_string = files_vec.join(", ");
-                println!(
+                reporter::get().output(&format!(
                     "{}{} {}",
                     "scripts".bright_cyan().bold(),
                     ":".bright_magenta().bold(),
                     print_string
-                );
+                ));
                 std::process::exit(1);
             }
 
             if files_vec.contains(&app.args[1]) {
-                let location = format!("node_modules/scripts/{}", &app.args[1]);
+                let location = path.join(&app.args[1]);
 
                 let command = format!("scripts/{}", &app.args[1]);
-                println!("{} {}", ">".bright_magenta().bold(), command);
+                reporter::get().output(&format!("{} {}", ">".bright_magenta().bold(), command));
 
                 std::process::Command::new("cmd.exe")
                     .arg("/C")
-                    .arg(location.replace("/", r"\"))
+                    .arg(location)
                     .spawn()
                     .unwrap();
             } else {
-                println!(
+                reporter::get().output(&format!(
                     "{}: {} 'is not a valid script.'",
                     "error".bright_red().bold(),
-                    &app.args[1].bright_yellow().bold(),
-                );
+                    &app.args[1].bright_yellow().bold()
+                ));
             }
         }
 
         Ok(())
     }
 }
+
+/// The scripts matching `name` in every member of the workspace around the project.
+fn recursive_jobs(
+    app: &App,
+    name: &str,
+    timeouts: &Timeouts,
+    env_files: &EnvFiles,
+) -> Result<Vec<Job>> {
+    let workspace = Workspace::find(&app.current_dir)
+        .ok_or_else(|| anyhow!("--recursive needs a package.json declaring workspaces"))?;
+
+    let mut jobs = vec![];
+
+    for member in workspace.members() {
+        let package_json = PackageJson::from(&member.join("package.json").to_string_lossy());
+
+        let names = if runner::is_pattern(name) {
+            runner::matching_scripts(name, &package_json.scripts)
+        } else if package_json.scripts.contains_key(name) {
+            vec![name.to_string()]
+        } else {
+            vec![]
+        };
+
+        let package = if package_json.name.is_empty() {
+            member.file_name().unwrap().to_string_lossy().to_string()
+        } else {
+            package_json.name.clone()
+        };
+
+        let env = if names.is_empty() {
+            vec![]
+        } else {
+            env_files.load(&member)?
+        };
+
+        for script in names.iter() {
+            jobs.push(Job {
+                // `api` or `api test:unit` when several scripts of a package match
+                label: if names.len() > 1 {
+                    format!("{} {}", package, script)
+                } else {
+                    package.clone()
+                },
+                dir: member.clone(),
+                script: package_json.scripts[script].clone(),
+                timeout: timeouts.get(script),
+                env: env.clone(),
+            });
+        }
+    }
+
+    if jobs.is_empty() {
+        return Err(anyhow!(
+            "no workspace package has a script matching `{}`",
+            name
+        ));
+    }
+
+    Ok(jobs)
+}
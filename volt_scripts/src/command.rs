@@ -18,15 +18,20 @@ use std::sync::Arc;
 use anyhow::Result;
 use async_trait::async_trait;
 use colored::Colorize;
-use volt_core::command::Command;
+use volt_core::{command::Command, help::Help};
 use volt_utils::app::App;
 use volt_utils::package::PackageJson;
+use volt_utils::reporter;
 pub struct Script {}
 
 #[async_trait]
 impl Command for Script {
-    fn help() -> String {
-        todo!()
+    fn help() -> Help {
+        Help::new(
+            "[script]",
+            "Run a script of package.json, for commands volt doesn't have.",
+        )
+        .usage("[args]")
     }
 
     /// Execute the `volt {script}` command
@@ -43,7 +48,8 @@ impl Command for Script {
     /// ## Returns
     /// * `Result<()>`
     async fn exec(app: Arc<App>) -> Result<()> {
-        let package_json = PackageJson::from("package.json");
+        let package_json =
+            PackageJson::from(&app.current_dir.join("package.json").to_string_lossy());
 
         let args = app.args.clone();
         let command: &str = args[0].as_str();
@@ -64,17 +70,22 @@ impl Command for Script {
                 std::process::Command::new("cmd.exe")
                     .arg("/C")
                     .arg(exec)
+                    .current_dir(&app.current_dir)
                     .spawn()
                     .unwrap();
             } else {
-                std::process::Command::new("sh").arg(exec).spawn().unwrap();
+                std::process::Command::new("sh")
+                    .arg(exec)
+                    .current_dir(&app.current_dir)
+                    .spawn()
+                    .unwrap();
             }
         } else {
-            println!(
+            reporter::get().output(&format!(
                 "{}: {} is not a valid command.",
                 "error".bright_red().bold(),
                 command.bright_yellow().bold()
-            );
+            ));
         }
 
         Ok(())
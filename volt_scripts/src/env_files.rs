@@ -0,0 +1,219 @@
+/*
+    Copyright 2021 Volt Contributors
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+        http://www.apache.org/licenses/LICENSE-2.0
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Environment files loaded into the scripts of `volt run`.
+//!
+//! `env-files=.env,.env.local` in `.voltrc` lists the files read from the directory of each
+//! script, `.env` by default; missing ones are skipped. `--env-file=<path>` adds files which must
+//! exist, read after the configured ones. A variable of a later file overrides the same variable
+//! of an earlier one, and the environment volt runs in overrides them all.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context, Result};
+use volt_utils::app::App;
+
+/// The files read when `.voltrc` has no `env-files`.
+pub const DEFAULT_FILES: &[&str] = &[".env"];
+
+/// The environment files of a run, in the order they are read.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EnvFiles {
+    /// Relative to the directory of each script, skipped when missing
+    configured: Vec<PathBuf>,
+    /// From `--env-file`, relative to the current directory
+    required: Vec<PathBuf>,
+}
+
+impl EnvFiles {
+    pub fn from_app(app: &App) -> Self {
+        let configured = app
+            .config
+            .get_list("env-files")
+            .unwrap_or_else(|| DEFAULT_FILES.iter().map(|file| file.to_string()).collect());
+
+        // --env-file=.env.test --env-file=.env.ci
+        let required = app
+            .flags
+            .iter()
+            .filter_map(|flag| flag.strip_prefix("--env-file="))
+            .flat_map(|files| files.split(','))
+            .filter(|file| !file.is_empty())
+            .map(|file| app.current_dir.join(file))
+            .collect();
+
+        EnvFiles {
+            configured: configured.into_iter().map(PathBuf::from).collect(),
+            required,
+        }
+    }
+
+    /// The variables to set for a script running in `dir`, without those already set.
+    pub fn load(&self, dir: &Path) -> Result<Vec<(String, String)>> {
+        let mut variables = BTreeMap::new();
+
+        for file in self.configured.iter().map(|file| dir.join(file)) {
+            if file.is_file() {
+                variables.extend(read(&file)?);
+            }
+        }
+
+        for file in self.required.iter() {
+            variables.extend(read(file)?);
+        }
+
+        Ok(variables
+            .into_iter()
+            .filter(|(name, _)| std::env::var_os(name).is_none())
+            .collect())
+    }
+}
+
+fn read(file: &Path) -> Result<Vec<(String, String)>> {
+    let source = std::fs::read_to_string(file)
+        .with_context(|| format!("failed to read the environment file {}", file.display()))?;
+
+    parse(&source).with_context(|| format!("invalid environment file {}", file.display()))
+}
+
+/// Parse the `NAME=value` lines of an environment file.
+///
+/// Lines may start with `export`, `#` starts a comment outside of quotes, `"` values understand
+/// `\n`, `\"` and `\\`, and `'` values are taken as they are.
+pub fn parse(source: &str) -> Result<Vec<(String, String)>> {
+    let mut variables = vec![];
+
+    for (index, line) in source.lines().enumerate() {
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let line = line.strip_prefix("export ").unwrap_or(line);
+
+        let (name, value) = line
+            .split_once('=')
+            .ok_or_else(|| anyhow!("line {} is not NAME=value", index + 1))?;
+        let name = name.trim();
+
+        if name.is_empty()
+            || name.starts_with(|c: char| c.is_ascii_digit())
+            || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        {
+            return Err(anyhow!(
+                "line {}: `{}` is not a valid name",
+                index + 1,
+                name
+            ));
+        }
+
+        let value = value.trim();
+
+        let value = if let Some(quoted) = value.strip_prefix('"') {
+            let mut unescaped = String::new();
+            let mut chars = quoted.chars();
+
+            loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some('\\') => match chars.next() {
+                        Some('n') => unescaped.push('\n'),
+                        Some(c) => unescaped.push(c),
+                        None => break,
+                    },
+                    Some(c) => unescaped.push(c),
+                    None => return Err(anyhow!("line {}: unterminated quote", index + 1)),
+                }
+            }
+
+            unescaped
+        } else if let Some(quoted) = value.strip_prefix('\'') {
+            quoted
+                .split_once('\'')
+                .map(|(value, _)| value.to_string())
+                .ok_or_else(|| anyhow!("line {}: unterminated quote", index + 1))?
+        } else {
+            // FOO=bar # comment
+            value
+                .split_once(" #")
+                .map_or(value, |(value, _)| value)
+                .trim_end()
+                .to_string()
+        };
+
+        variables.push((name.to_string(), value));
+    }
+
+    Ok(variables)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_environment_files() {
+        let source = r#"
+# database
+export DB_HOST=localhost # local only
+DB_PASSWORD="p#ss \"word\"\nline"
+GREETING='hello $USER'
+EMPTY=
+"#;
+
+        assert_eq!(
+            parse(source).unwrap(),
+            vec![
+                (String::from("DB_HOST"), String::from("localhost")),
+                (
+                    String::from("DB_PASSWORD"),
+                    String::from("p#ss \"word\"\nline")
+                ),
+                (String::from("GREETING"), String::from("hello $USER")),
+                (String::from("EMPTY"), String::new()),
+            ]
+        );
+
+        assert!(parse("1ST=value").is_err());
+        assert!(parse("NAME=\"open").is_err());
+    }
+
+    #[test]
+    fn later_files_override_earlier_ones() {
+        let dir = std::env::temp_dir().join(format!("volt-env-files-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join(".env"),
+            "VOLT_ENV_A=env\nVOLT_ENV_B=env\nPATH=/nowhere",
+        )
+        .unwrap();
+        std::fs::write(dir.join(".env.test"), "VOLT_ENV_B=test").unwrap();
+
+        let env_files = EnvFiles {
+            configured: vec![PathBuf::from(".env"), PathBuf::from(".env.local")],
+            required: vec![dir.join(".env.test")],
+        };
+
+        assert_eq!(
+            env_files.load(&dir).unwrap(),
+            vec![
+                (String::from("VOLT_ENV_A"), String::from("env")),
+                (String::from("VOLT_ENV_B"), String::from("test")),
+            ]
+        );
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -1 +1,7 @@
 pub mod command;
+pub mod env_files;
+pub mod output;
+pub mod pty;
+pub mod report;
+pub mod runner;
+pub mod supervise;
@@ -0,0 +1,152 @@
+/*
+    Copyright 2021 Volt Contributors
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+        http://www.apache.org/licenses/LICENSE-2.0
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Show the output of scripts running together (`--parallel`, `--recursive`).
+
+use std::io::{Read, Write};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+
+use crate::report::Tail;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutputMode {
+    /// Lines of every script as they come, prefixed with the script they come from
+    Stream,
+    /// The whole output of each script at once, when it is done
+    Aggregate,
+    /// Output as the scripts write it, without prefixes
+    Raw,
+}
+
+impl OutputMode {
+    /// `--aggregate-output` or `--raw`, streaming otherwise.
+    pub fn from_flags(aggregate: bool, raw: bool) -> Self {
+        if aggregate {
+            OutputMode::Aggregate
+        } else if raw {
+            OutputMode::Raw
+        } else {
+            OutputMode::Stream
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Channel {
+    Stdout,
+    Stderr,
+}
+
+/// Where the output of one script goes.
+#[derive(Debug, Clone)]
+pub enum Sink {
+    Direct,
+    /// Whole lines, after the prefix
+    Prefixed(String),
+    /// Kept until the script is done, stdout and stderr together
+    Buffered(Arc<Mutex<Vec<u8>>>),
+}
+
+impl Sink {
+    /// Copy everything from `reader` until the script closes its side, keeping the end in `tail`.
+    pub fn copy(
+        &self,
+        mut reader: impl Read + Send + 'static,
+        channel: Channel,
+        tail: Tail,
+    ) -> JoinHandle<()> {
+        let sink = self.clone();
+
+        std::thread::spawn(move || {
+            let mut buffer = [0u8; 8192];
+            let mut pending = vec![];
+
+            // Reads of a pseudo-terminal fail with EIO instead of returning 0 once it is closed
+            while let Ok(read) = reader.read(&mut buffer) {
+                if read == 0 {
+                    break;
+                }
+
+                tail.push(&buffer[..read]);
+                sink.write(channel, &buffer[..read], &mut pending);
+            }
+
+            // The last line, if it has no line break
+            if let Sink::Prefixed(prefix) = &sink {
+                if !pending.is_empty() {
+                    write(channel, &[prefix.as_bytes(), &pending, b"\n"]);
+                }
+            }
+        })
+    }
+
+    fn write(&self, channel: Channel, data: &[u8], pending: &mut Vec<u8>) {
+        match self {
+            Sink::Direct => write(channel, &[data]),
+            Sink::Prefixed(prefix) => {
+                pending.extend_from_slice(data);
+
+                while let Some(end) = pending.iter().position(|byte| *byte == b'\n') {
+                    let line: Vec<u8> = pending.drain(..=end).collect();
+                    write(channel, &[prefix.as_bytes(), &line]);
+                }
+            }
+            Sink::Buffered(buffer) => buffer.lock().unwrap().extend_from_slice(data),
+        }
+    }
+
+    /// Take what a buffered sink kept.
+    pub fn take(&self) -> Vec<u8> {
+        match self {
+            Sink::Buffered(buffer) => std::mem::take(&mut *buffer.lock().unwrap()),
+            _ => vec![],
+        }
+    }
+}
+
+/// Write `parts` without output of other scripts in between.
+pub fn write(channel: Channel, parts: &[&[u8]]) {
+    fn write_all(mut output: impl Write, parts: &[&[u8]]) {
+        for part in parts {
+            let _ = output.write_all(part);
+        }
+
+        let _ = output.flush();
+    }
+
+    match channel {
+        Channel::Stdout => write_all(std::io::stdout().lock(), parts),
+        Channel::Stderr => write_all(std::io::stderr().lock(), parts),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn buffers_until_taken() {
+        let sink = Sink::Buffered(Arc::default());
+
+        sink.copy(&b"one\ntwo"[..], Channel::Stdout, Tail::default())
+            .join()
+            .unwrap();
+        sink.copy(&b"\nthree\n"[..], Channel::Stderr, Tail::default())
+            .join()
+            .unwrap();
+
+        assert_eq!(sink.take(), b"one\ntwo\nthree\n".to_vec());
+        assert!(sink.take().is_empty());
+    }
+}
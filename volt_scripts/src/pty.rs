@@ -0,0 +1,227 @@
+/*
+    Copyright 2021 Volt Contributors
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+        http://www.apache.org/licenses/LICENSE-2.0
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Run scripts under a pseudo-terminal, so tools which check for a terminal (jest, webpack,
+//! mocha) keep their colors, progress bars and interactive prompts.
+//!
+//! Only stdout and stderr go through the pseudo-terminal, which is copied to the real one.
+//! stdin stays the real terminal, so keystrokes reach the script directly.
+
+use std::io::IsTerminal;
+use std::process::{Child, Command, Stdio};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use anyhow::Result;
+
+use crate::output::{Channel, Sink};
+use crate::report::Output;
+use crate::supervise::{self, Outcome};
+
+/// How the output of scripts reaches the terminal.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Terminal {
+    /// Through a pseudo-terminal
+    Pty,
+    /// Through pipes, so scripts see no terminal
+    Pipe,
+}
+
+impl Terminal {
+    /// Use a pseudo-terminal when volt writes to one and it is supported, unless `no_tty`
+    /// (`--no-tty`) is set or volt runs in CI.
+    pub fn detect(no_tty: bool) -> Self {
+        if cfg!(unix) && !no_tty && !is_ci() && std::io::stdout().is_terminal() {
+            Terminal::Pty
+        } else {
+            Terminal::Pipe
+        }
+    }
+}
+
+/// Check the variables CI services set.
+pub fn is_ci() -> bool {
+    let set = |name: &str| {
+        std::env::var(name)
+            .map(|value| !value.is_empty() && value != "false" && value != "0")
+            .unwrap_or(false)
+    };
+
+    [
+        "CI",
+        "CONTINUOUS_INTEGRATION",
+        "BUILD_NUMBER",
+        "RUN_ID",
+        "TF_BUILD",
+    ]
+    .iter()
+    .any(|name| set(name))
+}
+
+/// A running script, with the threads copying its output.
+pub struct RunningScript {
+    child: Child,
+    copiers: Vec<JoinHandle<()>>,
+    output: Output,
+}
+
+impl RunningScript {
+    /// The id of the script, and of its process group on unix.
+    pub fn id(&self) -> u32 {
+        self.child.id()
+    }
+
+    /// The end of what the script wrote, complete once it exited.
+    pub fn output(&self) -> Output {
+        self.output.clone()
+    }
+
+    /// Wait for the script to exit, stopping it once `timeout` passed or Ctrl-C is pressed.
+    pub fn wait(mut self, timeout: Option<Duration>) -> Result<Outcome> {
+        let outcome = supervise::wait(&mut self.child, timeout)?;
+
+        for copier in self.copiers.drain(..) {
+            let _ = copier.join();
+        }
+
+        Ok(outcome)
+    }
+}
+
+/// Start `command` in a process group of its own with its output going to `sink`, through the
+/// terminal `terminal` says.
+pub fn spawn(mut command: Command, terminal: Terminal, sink: &Sink) -> Result<RunningScript> {
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(&mut command, 0);
+
+    if terminal == Terminal::Pty {
+        if let Ok(script) = unix::spawn(&mut command, sink) {
+            return Ok(script);
+        }
+    }
+
+    let mut child = command
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    let output = Output::default();
+    let copiers = vec![
+        sink.copy(
+            child.stdout.take().unwrap(),
+            Channel::Stdout,
+            output.stdout.clone(),
+        ),
+        sink.copy(
+            child.stderr.take().unwrap(),
+            Channel::Stderr,
+            output.stderr.clone(),
+        ),
+    ];
+
+    Ok(RunningScript {
+        child,
+        copiers,
+        output,
+    })
+}
+
+#[cfg(unix)]
+mod unix {
+    use std::fs::File;
+    use std::io;
+    use std::os::unix::io::{FromRawFd, RawFd};
+    use std::process::{Command, Stdio};
+
+    use super::RunningScript;
+    use crate::output::{Channel, Sink};
+    use crate::report::Output;
+
+    pub fn spawn(command: &mut Command, sink: &Sink) -> io::Result<RunningScript> {
+        let (master, slave) = open()?;
+
+        // Each Stdio owns its descriptor, dropped once the child has them
+        let stdout = unsafe { Stdio::from_raw_fd(dup(slave)?) };
+        let stderr = unsafe { Stdio::from_raw_fd(slave) };
+
+        let spawned = command.stdout(stdout).stderr(stderr).spawn();
+
+        // Drop the descriptors held by the command, so reads end with the script
+        command.stdout(Stdio::inherit()).stderr(Stdio::inherit());
+
+        let master = unsafe { File::from_raw_fd(master) };
+        let output = Output::default();
+
+        Ok(RunningScript {
+            child: spawned?,
+            copiers: vec![sink.copy(master, Channel::Stdout, output.stdout.clone())],
+            output,
+        })
+    }
+
+    /// Open a pseudo-terminal the size of the real one.
+    fn open() -> io::Result<(RawFd, RawFd)> {
+        let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+        let sized = unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) } == 0;
+
+        let (mut master, mut slave) = (0, 0);
+
+        let result = unsafe {
+            libc::openpty(
+                &mut master,
+                &mut slave,
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+                if sized {
+                    &mut size as *mut libc::winsize
+                } else {
+                    std::ptr::null_mut()
+                },
+            )
+        };
+
+        if result != 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        // Keep the descriptors out of other scripts started in parallel
+        for fd in [master, slave] {
+            unsafe { libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC) };
+        }
+
+        Ok((master, slave))
+    }
+
+    fn dup(fd: RawFd) -> io::Result<RawFd> {
+        match unsafe { libc::fcntl(fd, libc::F_DUPFD_CLOEXEC, 0) } {
+            -1 => Err(io::Error::last_os_error()),
+            fd => Ok(fd),
+        }
+    }
+}
+
+#[cfg(not(unix))]
+mod unix {
+    use std::io;
+    use std::process::Command;
+
+    use super::RunningScript;
+    use crate::output::Sink;
+
+    pub fn spawn(_command: &mut Command, _sink: &Sink) -> io::Result<RunningScript> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "pseudo-terminals are not supported",
+        ))
+    }
+}
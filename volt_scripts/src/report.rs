@@ -0,0 +1,290 @@
+/*
+    Copyright 2021 Volt Contributors
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+        http://www.apache.org/licenses/LICENSE-2.0
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Reports of failed scripts.
+//!
+//! The last output of every script is kept while it runs. When a script fails or times out, its
+//! command, directory, outcome, the variables volt set on top of its own environment and that
+//! output are written to a log file in `.volt/logs` of the project, and the path is printed after the
+//! last lines of the output, so the error is still there once the output scrolled away.
+//! Scripts cancelled with Ctrl-C aren't reported.
+
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use colored::Colorize;
+use volt_utils::reporter;
+
+use crate::supervise::Outcome;
+
+/// The output kept of each channel, in bytes.
+const TAIL_BYTES: usize = 64 * 1024;
+
+/// The lines of output printed with the path of the log.
+const EXCERPT_LINES: usize = 10;
+
+static LOG_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// Write the logs of failed scripts to `dir`, instead of `volt-logs` in the temporary directory.
+pub fn log_to(dir: PathBuf) {
+    let _ = LOG_DIR.set(dir);
+}
+
+fn log_dir() -> PathBuf {
+    LOG_DIR
+        .get()
+        .cloned()
+        .unwrap_or_else(|| std::env::temp_dir().join("volt-logs"))
+}
+
+/// The last [`TAIL_BYTES`] a script wrote to one channel.
+#[derive(Debug, Clone, Default)]
+pub struct Tail(Arc<Mutex<VecDeque<u8>>>);
+
+impl Tail {
+    pub fn push(&self, data: &[u8]) {
+        let mut tail = self.0.lock().unwrap();
+
+        tail.extend(data);
+
+        let excess = tail.len().saturating_sub(TAIL_BYTES);
+        tail.drain(..excess);
+    }
+
+    /// What was kept, without terminal escape sequences.
+    pub fn text(&self) -> String {
+        let tail: Vec<u8> = self.0.lock().unwrap().iter().copied().collect();
+        strip_escapes(&String::from_utf8_lossy(&tail))
+    }
+}
+
+/// What a script wrote. Under a pseudo-terminal, stderr is part of stdout.
+#[derive(Debug, Clone, Default)]
+pub struct Output {
+    pub stdout: Tail,
+    pub stderr: Tail,
+}
+
+/// A script which didn't succeed.
+#[derive(Debug)]
+pub struct Failure<'a> {
+    pub label: &'a str,
+    pub script: &'a str,
+    pub dir: &'a Path,
+    pub outcome: Outcome,
+    pub output: &'a Output,
+    /// The variables set on top of the environment of volt
+    pub env: &'a [(String, String)],
+}
+
+/// Remove the colors and cursor movements of terminal output.
+pub fn strip_escapes(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '\u{1b}' {
+            if c != '\r' {
+                out.push(c);
+            }
+            continue;
+        }
+
+        // CSI sequences end with a byte from @ to ~, others are a single character
+        if chars.next_if_eq(&'[').is_some() {
+            for c in chars.by_ref() {
+                if ('@'..='~').contains(&c) {
+                    break;
+                }
+            }
+        } else {
+            chars.next();
+        }
+    }
+
+    out
+}
+
+/// Whether the value of the variable `name` could be a credential, hidden in logs.
+fn is_secret(name: &str) -> bool {
+    let name = name.to_ascii_uppercase();
+
+    [
+        "TOKEN",
+        "SECRET",
+        "PASSWORD",
+        "PASSWD",
+        "KEY",
+        "AUTH",
+        "CREDENTIAL",
+    ]
+    .iter()
+    .any(|word| name.contains(word))
+}
+
+fn last_lines(text: &str, count: usize) -> Vec<&str> {
+    let lines: Vec<&str> = text
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .collect();
+    lines[lines.len().saturating_sub(count)..].to_vec()
+}
+
+impl Failure<'_> {
+    /// The contents of the log file.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        let dir = self
+            .dir
+            .canonicalize()
+            .unwrap_or_else(|_| self.dir.to_path_buf());
+
+        // Writing to a String can't fail
+        let _ = writeln!(out, "script: {}", self.label);
+        let _ = writeln!(out, "command: {}", self.script);
+        let _ = writeln!(out, "cwd: {}", dir.display());
+        let _ = writeln!(out, "outcome: {}", outcome(&self.outcome));
+        let _ = writeln!(out, "\nenvironment set by volt:");
+
+        if self.env.is_empty() {
+            let _ = writeln!(out, "  (none)");
+        }
+
+        for (name, value) in self.env {
+            let value = if is_secret(name) { "***" } else { value };
+            let _ = writeln!(out, "  {}={}", name, value);
+        }
+
+        for (channel, tail) in [
+            ("stdout", &self.output.stdout),
+            ("stderr", &self.output.stderr),
+        ] {
+            let _ = writeln!(out, "\n--- {} ---", channel);
+            let _ = write!(out, "{}", tail.text());
+        }
+
+        out
+    }
+
+    /// Write the log file and print its path after the last lines of the output.
+    pub fn report(&self) -> Result<PathBuf> {
+        let dir = log_dir();
+        let millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|time| time.as_millis())
+            .unwrap_or_default();
+        let name: String = self
+            .label
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+            .collect();
+        let path = dir.join(format!("{}-{}.log", millis, name));
+
+        std::fs::create_dir_all(&dir)
+            .and_then(|_| std::fs::write(&path, self.render()))
+            .with_context(|| format!("failed to write {}", path.display()))?;
+
+        // Under a pseudo-terminal errors are on stdout
+        let stderr = self.output.stderr.text();
+        let excerpt = if stderr.trim().is_empty() {
+            self.output.stdout.text()
+        } else {
+            stderr
+        };
+
+        let lines = last_lines(&excerpt, EXCERPT_LINES);
+
+        if !lines.is_empty() {
+            eprintln!(
+                "\n{} {} {}, last lines of its output:",
+                ">".bright_magenta().bold(),
+                self.label,
+                outcome(&self.outcome)
+            );
+
+            for line in lines {
+                eprintln!("  {} {}", "|".bright_black(), line);
+            }
+        }
+
+        eprintln!(
+            "{} report written to {}",
+            ">".bright_magenta().bold(),
+            path.display()
+        );
+
+        Ok(path)
+    }
+}
+
+fn outcome(outcome: &Outcome) -> String {
+    match outcome {
+        Outcome::Exited(status) => match status.code() {
+            Some(code) => format!("exited with code {}", code),
+            None => format!("exited with {}", status),
+        },
+        Outcome::TimedOut(timeout) => format!("timed out after {}s", timeout.as_secs_f64()),
+        Outcome::Cancelled => String::from("was cancelled"),
+    }
+}
+
+/// Report `failure` unless the script succeeded or was cancelled, warning if the log can't be
+/// written.
+pub fn report_failure(failure: &Failure) {
+    if failure.outcome.success() || failure.outcome == Outcome::Cancelled {
+        return;
+    }
+
+    if let Err(error) = failure.report() {
+        reporter::get().warn(&format!("{:#}", error));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn logs_output_without_escapes_or_secrets() {
+        let output = Output::default();
+        output.stdout.push(b"\x1b[32mcompiling\x1b[0m\r\n");
+        output.stderr.push(b"error: missing semicolon\n");
+
+        let env = vec![
+            (String::from("NODE_ENV"), String::from("test")),
+            (String::from("NPM_TOKEN"), String::from("npm_abc")),
+        ];
+
+        let failure = Failure {
+            label: "build",
+            script: "tsc -p .",
+            dir: Path::new("/nonexistent"),
+            outcome: Outcome::TimedOut(std::time::Duration::from_secs(5)),
+            output: &output,
+            env: &env,
+        };
+
+        let log = failure.render();
+
+        assert!(log.contains("outcome: timed out after 5s"));
+        assert!(log.contains("--- stdout ---\ncompiling\n"));
+        assert!(log.contains("error: missing semicolon"));
+        assert!(log.contains("NODE_ENV=test"));
+        assert!(log.contains("NPM_TOKEN=***"));
+        assert!(!log.contains("npm_abc"));
+    }
+}
@@ -0,0 +1,416 @@
+/*
+    Copyright 2021 Volt Contributors
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+        http://www.apache.org/licenses/LICENSE-2.0
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Run scripts defined in package.json through the platform shell.
+
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::mpsc;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use colored::{Color, Colorize};
+
+use crate::output::{self, Channel, OutputMode, Sink};
+use crate::pty::{self, RunningScript, Terminal};
+use crate::report::{self, Failure, Output};
+use crate::supervise::{self, Foreground, Interrupts, Outcome, Timeouts};
+use volt_utils::reporter;
+
+/// Check if a script name contains a wildcard (`test:*`).
+pub fn is_pattern(name: &str) -> bool {
+    name.contains('*')
+}
+
+/// Get the names of all scripts matching `pattern`, sorted by name.
+///
+/// `*` matches anything except the `:` separator, `**` matches across separators,
+/// so `test:*` matches `test:unit` but not `test:unit:watch` (`test:**` matches both).
+pub fn matching_scripts(pattern: &str, scripts: &HashMap<String, String>) -> Vec<String> {
+    let mut matches: Vec<String> = scripts
+        .keys()
+        .filter(|name| wildcard_match(pattern.as_bytes(), name.as_bytes()))
+        .cloned()
+        .collect();
+
+    matches.sort();
+    matches
+}
+
+/// `name` with the `pre` and `post` scripts of `scripts` around it, like npm runs `pretest`,
+/// `test` and `posttest` for `test`.
+pub fn lifecycle(name: &str, scripts: &HashMap<String, String>) -> Vec<String> {
+    let pre = format!("pre{}", name);
+    let post = format!("post{}", name);
+
+    let mut names = vec![];
+
+    if scripts.contains_key(&pre) {
+        names.push(pre);
+    }
+
+    names.push(name.to_string());
+
+    if scripts.contains_key(&post) {
+        names.push(post);
+    }
+
+    names
+}
+
+fn wildcard_match(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern {
+        [] => text.is_empty(),
+        [b'*', b'*', rest @ ..] => (0..=text.len()).any(|i| wildcard_match(rest, &text[i..])),
+        [b'*', rest @ ..] => {
+            for i in 0..=text.len() {
+                if wildcard_match(rest, &text[i..]) {
+                    return true;
+                }
+
+                if i < text.len() && text[i] == b':' {
+                    break;
+                }
+            }
+
+            false
+        }
+        [c, rest @ ..] => match text {
+            [t, text_rest @ ..] if t == c => wildcard_match(rest, text_rest),
+            _ => false,
+        },
+    }
+}
+
+/// Build a command which executes `script` through the platform shell.
+pub fn shell_command(script: &str) -> Command {
+    if cfg!(target_os = "windows") {
+        let mut command = Command::new("cmd.exe");
+        command.arg("/C").arg(script);
+        command
+    } else {
+        let mut command = Command::new("sh");
+        command.arg("-c").arg(script);
+        command
+    }
+}
+
+/// PATH with the binaries of the packages installed in `dir` first, so scripts call `jest`
+/// rather than `node_modules/.bin/jest`.
+pub fn bin_path(dir: &Path) -> Option<OsString> {
+    let dir = std::path::absolute(dir).ok()?;
+    let paths = std::env::var_os("PATH").unwrap_or_default();
+
+    std::env::join_paths(
+        std::iter::once(dir.join("node_modules").join(".bin")).chain(std::env::split_paths(&paths)),
+    )
+    .ok()
+}
+
+/// A script to run in a package.
+#[derive(Debug, Clone)]
+pub struct Job {
+    /// Shown before its output
+    pub label: String,
+    pub dir: PathBuf,
+    pub script: String,
+    /// Stopped once it runs for longer
+    pub timeout: Option<Duration>,
+    /// Set on top of the environment of volt, from [env_files](crate::env_files)
+    pub env: Vec<(String, String)>,
+}
+
+/// Run a single script in `dir` with the terminal to itself and wait for it to exit.
+pub fn run_script(
+    dir: &Path,
+    name: &str,
+    script: &str,
+    terminal: Terminal,
+    timeout: Option<Duration>,
+    env: &[(String, String)],
+) -> Result<Outcome> {
+    reporter::get().output(&format!("{} {}", ">".bright_magenta().bold(), name));
+    reporter::get().output(&format!("{} {}\n", ">".bright_magenta().bold(), script));
+
+    let mut command = shell_command(script);
+    command.current_dir(dir);
+    command.env("npm_lifecycle_event", name);
+
+    if let Some(path) = bin_path(dir) {
+        command.env("PATH", path);
+    }
+
+    command.envs(env.iter().cloned());
+
+    let running = pty::spawn(command, terminal, &Sink::Direct)
+        .with_context(|| format!("failed to execute script `{}`", name))?;
+
+    let output = running.output();
+
+    let outcome = {
+        let _foreground = Foreground::take(running.id());
+        running.wait(timeout)?
+    };
+
+    report::report_failure(&Failure {
+        label: name,
+        script,
+        dir,
+        outcome,
+        output: &output,
+        env,
+    });
+
+    Ok(outcome)
+}
+
+/// Run the given scripts of the package in `dir` one after another, or all at once if `parallel`
+/// is set.
+///
+/// Sequential runs stop at the first script that fails, times out or is cancelled.
+#[allow(clippy::too_many_arguments)]
+pub fn run_scripts(
+    dir: &Path,
+    names: &[String],
+    scripts: &HashMap<String, String>,
+    parallel: bool,
+    terminal: Terminal,
+    mode: OutputMode,
+    timeouts: &Timeouts,
+    env: &[(String, String)],
+) -> Result<()> {
+    if !parallel {
+        let _interrupts = Interrupts::catch();
+
+        for name in names {
+            if supervise::is_cancelled() {
+                return Err(anyhow!("cancelled before script `{}`", name));
+            }
+
+            run_script(dir, name, &scripts[name], terminal, timeouts.get(name), env)?
+                .into_result(name)?;
+        }
+
+        return Ok(());
+    }
+
+    let jobs: Vec<Job> = names
+        .iter()
+        .map(|name| Job {
+            label: name.clone(),
+            dir: dir.to_path_buf(),
+            script: scripts[name].clone(),
+            timeout: timeouts.get(name),
+            env: env.to_vec(),
+        })
+        .collect();
+
+    run_jobs(&jobs, true, terminal, mode)
+}
+
+/// Run `jobs` one after another, or all at once if `parallel` is set, showing their output as
+/// `mode` says.
+///
+/// Sequential runs stop at the first job that fails, times out or is cancelled. Parallel jobs
+/// can't read the terminal, and Ctrl-C stops all of them.
+pub fn run_jobs(jobs: &[Job], parallel: bool, terminal: Terminal, mode: OutputMode) -> Result<()> {
+    let _interrupts = Interrupts::catch();
+    let width = jobs.iter().map(|job| job.label.len()).max().unwrap_or(0);
+
+    let start = |index: usize, job: &Job| -> Result<(RunningScript, Sink)> {
+        let sink = match mode {
+            OutputMode::Stream => Sink::Prefixed(prefix(index, &job.label, width)),
+            OutputMode::Aggregate => Sink::Buffered(Default::default()),
+            OutputMode::Raw => Sink::Direct,
+        };
+
+        if mode != OutputMode::Aggregate {
+            reporter::get().output(&format!("{} {}", ">".bright_magenta().bold(), job.label));
+        }
+
+        let mut command = shell_command(&job.script);
+        command.current_dir(&job.dir);
+
+        if let Some(path) = bin_path(&job.dir) {
+            command.env("PATH", path);
+        }
+
+        command.envs(job.env.iter().cloned());
+
+        if parallel {
+            command.stdin(Stdio::null());
+        }
+
+        let running = pty::spawn(command, terminal, &sink)
+            .with_context(|| format!("failed to execute script `{}`", job.label))?;
+
+        Ok((running, sink))
+    };
+
+    // Failures are reported after the output, so the excerpt is the last thing shown
+    let finish = |job: &Job, sink: &Sink, outcome: &Result<Outcome>, output: &Output| {
+        if mode == OutputMode::Aggregate {
+            reporter::get().output(&format!("{} {}", ">".bright_magenta().bold(), job.label));
+            output::write(Channel::Stdout, &[&sink.take()]);
+        }
+
+        if let Ok(outcome) = outcome {
+            report::report_failure(&Failure {
+                label: &job.label,
+                script: &job.script,
+                dir: &job.dir,
+                outcome: *outcome,
+                output,
+                env: &job.env,
+            });
+        }
+    };
+
+    if !parallel {
+        for (index, job) in jobs.iter().enumerate() {
+            if supervise::is_cancelled() {
+                return Err(anyhow!("cancelled before script `{}`", job.label));
+            }
+
+            let (running, sink) = start(index, job)?;
+            let output = running.output();
+
+            let outcome = {
+                let _foreground = Foreground::take(running.id());
+                running.wait(job.timeout)
+            };
+
+            finish(job, &sink, &outcome, &output);
+            outcome?.into_result(&job.label)?;
+        }
+
+        return Ok(());
+    }
+
+    let (sender, receiver) = mpsc::channel();
+
+    for (index, job) in jobs.iter().enumerate() {
+        let (running, sink) = start(index, job)?;
+        let output = running.output();
+        let sender = sender.clone();
+        let timeout = job.timeout;
+
+        // Wait on a thread of its own, so each script is reported as soon as it is done
+        std::thread::spawn(move || {
+            let _ = sender.send((index, sink, output, running.wait(timeout)));
+        });
+    }
+
+    drop(sender);
+
+    let mut failed = vec![];
+
+    for (index, sink, output, outcome) in receiver {
+        let job = &jobs[index];
+
+        finish(job, &sink, &outcome, &output);
+
+        match outcome {
+            Ok(outcome) if outcome.success() => {}
+            Ok(outcome) => failed.push(format!("{}{}", job.label, outcome.suffix())),
+            Err(_) => failed.push(job.label.clone()),
+        }
+    }
+
+    if !failed.is_empty() {
+        return Err(anyhow!("scripts failed: {}", failed.join(", ")));
+    }
+
+    Ok(())
+}
+
+/// `label | `, padded to `width` and colored after the position of the job.
+fn prefix(index: usize, label: &str, width: usize) -> String {
+    let colors = [
+        Color::Cyan,
+        Color::Magenta,
+        Color::Yellow,
+        Color::Green,
+        Color::Blue,
+        Color::Red,
+    ];
+
+    format!(
+        "{} | ",
+        format!("{:width$}", label, width = width).color(colors[index % colors.len()])
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scripts(names: &[&str]) -> HashMap<String, String> {
+        names
+            .iter()
+            .map(|name| (name.to_string(), String::new()))
+            .collect()
+    }
+
+    #[test]
+    fn single_star_stops_at_separator() {
+        let scripts = scripts(&["test", "test:unit", "test:e2e", "test:unit:watch", "build"]);
+
+        assert_eq!(
+            matching_scripts("test:*", &scripts),
+            vec!["test:e2e", "test:unit"]
+        );
+    }
+
+    #[test]
+    fn double_star_crosses_separators() {
+        let scripts = scripts(&["test", "test:unit", "test:unit:watch", "build"]);
+
+        assert_eq!(
+            matching_scripts("test:**", &scripts),
+            vec!["test:unit", "test:unit:watch"]
+        );
+    }
+
+    #[test]
+    fn star_matches_within_segment() {
+        let scripts = scripts(&["lint:js", "lint:css", "build:js"]);
+
+        assert_eq!(
+            matching_scripts("*:js", &scripts),
+            vec!["build:js", "lint:js"]
+        );
+    }
+
+    #[test]
+    fn runs_pre_and_post_scripts_around_a_script() {
+        let scripts = scripts(&["pretest", "test", "posttest", "build", "postinstall"]);
+
+        assert_eq!(
+            lifecycle("test", &scripts),
+            vec!["pretest", "test", "posttest"]
+        );
+        assert_eq!(lifecycle("build", &scripts), vec!["build"]);
+    }
+
+    #[test]
+    fn puts_the_binaries_of_the_project_first_in_path() {
+        let path = bin_path(Path::new("/project")).unwrap();
+        assert_eq!(
+            std::env::split_paths(&path).next(),
+            Some(PathBuf::from("/project/node_modules/.bin"))
+        );
+    }
+}
@@ -0,0 +1,356 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Timeouts and cancellation of running scripts.
+//!
+//! On unix every script runs in a process group of its own, so a timeout or Ctrl-C stops
+//! everything the script started, not only its shell. A script running alone gets the
+//! terminal, and Ctrl-C reaches it like it would in a shell; otherwise volt catches Ctrl-C
+//! and stops the running scripts itself.
+
+use std::collections::HashMap;
+use std::process::{Child, ExitStatus};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Result};
+use volt_utils::app::App;
+
+/// How long a stopped script has to exit before it is killed.
+const GRACE: Duration = Duration::from_secs(3);
+
+/// How often running scripts are checked for timeouts and Ctrl-C.
+pub const POLL: Duration = Duration::from_millis(50);
+
+static CANCELLED: AtomicBool = AtomicBool::new(false);
+
+/// The time scripts may run for, in seconds: `--timeout=<seconds>` for every script of a run,
+/// else `script-timeout.<name>` or `script-timeout` in `.voltrc`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Timeouts {
+    default: Option<Duration>,
+    scripts: HashMap<String, Duration>,
+    flag: Option<Duration>,
+}
+
+impl Timeouts {
+    pub fn from_app(app: &App) -> Result<Self> {
+        let seconds = |key: &str, value: &str| -> Result<Duration> {
+            value
+                .parse::<f64>()
+                .ok()
+                .filter(|seconds| seconds.is_finite() && *seconds > 0.0)
+                .map(Duration::from_secs_f64)
+                .ok_or_else(|| anyhow!("{} should be a number of seconds, not `{}`", key, value))
+        };
+
+        let mut timeouts = Timeouts::default();
+
+        for (key, value) in app.config.iter() {
+            if key == "script-timeout" {
+                timeouts.default = Some(seconds(key, value)?);
+            } else if let Some(script) = key.strip_prefix("script-timeout.") {
+                timeouts
+                    .scripts
+                    .insert(script.to_string(), seconds(key, value)?);
+            }
+        }
+
+        if let Some(value) = app.flag_value(&["--timeout"]) {
+            timeouts.flag = Some(seconds("--timeout", &value)?);
+        }
+
+        Ok(timeouts)
+    }
+
+    /// The timeout of the script `name`, if it has one.
+    pub fn get(&self, name: &str) -> Option<Duration> {
+        self.flag
+            .or_else(|| self.scripts.get(name).copied())
+            .or(self.default)
+    }
+}
+
+/// How a script ended.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Outcome {
+    Exited(ExitStatus),
+    TimedOut(Duration),
+    /// By Ctrl-C
+    Cancelled,
+}
+
+impl Outcome {
+    pub fn success(&self) -> bool {
+        matches!(self, Outcome::Exited(status) if status.success())
+    }
+
+    /// Why the script `name` failed, if it did.
+    pub fn into_result(self, name: &str) -> Result<()> {
+        match self {
+            Outcome::Exited(status) if status.success() => Ok(()),
+            Outcome::Exited(status) => Err(anyhow!("script `{}` exited with {}", name, status)),
+            Outcome::TimedOut(timeout) => Err(anyhow!(
+                "script `{}` timed out after {}s",
+                name,
+                timeout.as_secs_f64()
+            )),
+            Outcome::Cancelled => Err(anyhow!("script `{}` was cancelled", name)),
+        }
+    }
+
+    /// ` (timed out)` or ` (cancelled)`, after the name of a failed script.
+    pub fn suffix(&self) -> &'static str {
+        match self {
+            Outcome::Exited(_) => "",
+            Outcome::TimedOut(_) => " (timed out)",
+            Outcome::Cancelled => " (cancelled)",
+        }
+    }
+}
+
+/// Check if Ctrl-C was pressed since scripts started running.
+pub fn is_cancelled() -> bool {
+    CANCELLED.load(Ordering::SeqCst)
+}
+
+/// Wait for `child`, stopping it once `timeout` passed or Ctrl-C is pressed.
+pub fn wait(child: &mut Child, timeout: Option<Duration>) -> Result<Outcome> {
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(if interrupted(&status) {
+                Outcome::Cancelled
+            } else {
+                Outcome::Exited(status)
+            });
+        }
+
+        if is_cancelled() {
+            stop(child)?;
+            return Ok(Outcome::Cancelled);
+        }
+
+        if let (Some(deadline), Some(timeout)) = (deadline, timeout) {
+            if Instant::now() >= deadline {
+                stop(child)?;
+                return Ok(Outcome::TimedOut(timeout));
+            }
+        }
+
+        std::thread::sleep(POLL);
+    }
+}
+
+/// Ask the process group of `child` to terminate, and kill it if it is still there after
+/// [`GRACE`].
+fn stop(child: &mut Child) -> Result<()> {
+    #[cfg(unix)]
+    {
+        let group = child.id() as libc::pid_t;
+        unsafe { libc::killpg(group, libc::SIGTERM) };
+
+        let deadline = Instant::now() + GRACE;
+
+        while Instant::now() < deadline {
+            if child.try_wait()?.is_some() {
+                // Whatever the script started and left behind
+                unsafe { libc::killpg(group, libc::SIGKILL) };
+                return Ok(());
+            }
+
+            std::thread::sleep(POLL);
+        }
+
+        unsafe { libc::killpg(group, libc::SIGKILL) };
+    }
+
+    #[cfg(not(unix))]
+    let _ = child.kill();
+
+    child.wait()?;
+    Ok(())
+}
+
+/// Check if a script exited because of Ctrl-C, by the signal or the `128 + SIGINT` status
+/// shells exit with.
+fn interrupted(status: &ExitStatus) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+
+        status.signal() == Some(libc::SIGINT) || status.code() == Some(128 + libc::SIGINT)
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = status;
+        false
+    }
+}
+
+pub use self::imp::{Foreground, Interrupts};
+
+#[cfg(unix)]
+mod imp {
+    use std::io::IsTerminal;
+    use std::sync::atomic::Ordering;
+
+    use super::CANCELLED;
+
+    extern "C" fn cancel(_: libc::c_int) {
+        CANCELLED.store(true, Ordering::SeqCst);
+    }
+
+    /// Ctrl-C (and termination) caught while scripts run, until dropped.
+    pub struct Interrupts {
+        previous: Vec<(libc::c_int, libc::sigaction)>,
+    }
+
+    impl Interrupts {
+        pub fn catch() -> Self {
+            CANCELLED.store(false, Ordering::SeqCst);
+
+            let previous = [libc::SIGINT, libc::SIGTERM]
+                .iter()
+                .map(|&signal| unsafe {
+                    let mut action: libc::sigaction = std::mem::zeroed();
+                    action.sa_sigaction = cancel as *const () as libc::sighandler_t;
+                    libc::sigemptyset(&mut action.sa_mask);
+
+                    let mut previous: libc::sigaction = std::mem::zeroed();
+                    libc::sigaction(signal, &action, &mut previous);
+
+                    (signal, previous)
+                })
+                .collect();
+
+            Interrupts { previous }
+        }
+    }
+
+    impl Drop for Interrupts {
+        fn drop(&mut self) {
+            for (signal, previous) in self.previous.iter() {
+                unsafe { libc::sigaction(*signal, previous, std::ptr::null_mut()) };
+            }
+        }
+    }
+
+    /// The terminal handed to the process group of a script, until dropped.
+    pub struct Foreground {
+        owner: Option<libc::pid_t>,
+    }
+
+    impl Foreground {
+        /// Hand the terminal to the group `group` if volt has it.
+        pub fn take(group: u32) -> Self {
+            let owner = unsafe { libc::getpgrp() };
+
+            let ours = std::io::stdin().is_terminal()
+                && unsafe { libc::tcgetpgrp(libc::STDIN_FILENO) } == owner;
+
+            if !ours || unsafe { libc::tcsetpgrp(libc::STDIN_FILENO, group as libc::pid_t) } != 0 {
+                return Foreground { owner: None };
+            }
+
+            // The script may have read the terminal before it had it, and been stopped for it
+            unsafe { libc::killpg(group as libc::pid_t, libc::SIGCONT) };
+
+            Foreground { owner: Some(owner) }
+        }
+    }
+
+    impl Drop for Foreground {
+        fn drop(&mut self) {
+            if let Some(owner) = self.owner {
+                // Taking the terminal back from the background stops volt unless ignored
+                unsafe {
+                    let previous = libc::signal(libc::SIGTTOU, libc::SIG_IGN);
+                    libc::tcsetpgrp(libc::STDIN_FILENO, owner);
+                    libc::signal(libc::SIGTTOU, previous);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(not(unix))]
+mod imp {
+    pub struct Interrupts;
+
+    impl Interrupts {
+        pub fn catch() -> Self {
+            Interrupts
+        }
+    }
+
+    pub struct Foreground;
+
+    impl Foreground {
+        pub fn take(_group: u32) -> Self {
+            Foreground
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flag_overrides_script_and_default_timeouts() {
+        let mut timeouts = Timeouts {
+            default: Some(Duration::from_secs(60)),
+            ..Default::default()
+        };
+        timeouts
+            .scripts
+            .insert(String::from("postinstall"), Duration::from_secs(5));
+
+        assert_eq!(timeouts.get("postinstall"), Some(Duration::from_secs(5)));
+        assert_eq!(timeouts.get("build"), Some(Duration::from_secs(60)));
+
+        timeouts.flag = Some(Duration::from_secs(1));
+
+        assert_eq!(timeouts.get("postinstall"), Some(Duration::from_secs(1)));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn stops_the_whole_group_on_timeout() {
+        use std::os::unix::process::CommandExt;
+
+        let marker = std::env::temp_dir().join(format!("volt-timeout-{}", std::process::id()));
+        let _ = std::fs::remove_file(&marker);
+
+        // The subshell would outlive the shell if only the shell was stopped
+        let mut child = std::process::Command::new("sh")
+            .arg("-c")
+            .arg(format!("(sleep 1; touch {}) & wait", marker.display()))
+            .process_group(0)
+            .spawn()
+            .unwrap();
+
+        let outcome = wait(&mut child, Some(Duration::from_millis(200))).unwrap();
+
+        assert_eq!(outcome, Outcome::TimedOut(Duration::from_millis(200)));
+
+        std::thread::sleep(Duration::from_millis(1500));
+        assert!(!marker.exists());
+    }
+}
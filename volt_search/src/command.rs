@@ -12,17 +12,22 @@
 */
 
 //! Search for a package.
+use crate::index::{self, Index};
 use crate::search::SearchData;
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use async_trait::async_trait;
 use chttp::ResponseExt;
 use colored::Colorize;
 use prettytable::row;
 use std::sync::Arc;
 // use search::SearchResp;
-use prettytable::{cell, Table};
-use volt_core::{command::Command, VERSION};
-use volt_utils::app::App;
+use prettytable::Table;
+use volt_core::{command::Command, help::Help};
+use volt_utils::{app::App, mirror, reporter};
+
+/// Results listed from the local index.
+const LOCAL_LIMIT: usize = 20;
+
 fn truncate(s: &str, max_chars: usize) -> String {
     match s.char_indices().nth(max_chars) {
         None => s.to_string(),
@@ -30,31 +35,86 @@ fn truncate(s: &str, max_chars: usize) -> String {
     }
 }
 
+async fn remote_search(query: &str) -> Result<Vec<SearchData>> {
+    let response = chttp::get_async(format!(
+        "https://www.npmjs.com/search/suggestions?q={}",
+        query
+    ))
+    .await?
+    .text_async()
+    .await?;
+
+    serde_json::from_str(&response).map_err(|e| {
+        anyhow!(
+            "failed to parse response from server {}",
+            e.to_string().bright_red()
+        )
+    })
+}
+
+/// The local index, synced first when it is missing or stale and the network may be used.
+async fn local_index(app: &App) -> Result<Index> {
+    let path = index::path(app);
+    let existing = Index::read(&path).ok();
+
+    let interval = app
+        .config
+        .get("search-index-interval")
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(index::DEFAULT_INTERVAL);
+
+    let stale = existing
+        .as_ref()
+        .is_none_or(|index| index.is_stale(index::now(), interval));
+
+    if !stale || mirror::is_offline(app) {
+        return existing.ok_or_else(|| {
+            anyhow!("there is no search index yet, sync it with `volt search --sync` when online")
+        });
+    }
+
+    match (index::sync(app).await, existing) {
+        (Ok(index), _) => Ok(index),
+        (Err(e), Some(existing)) => {
+            reporter::get().output(&format!(
+                "{} failed to sync the search index, using the one from {} days ago: {}",
+                " warn ".black().on_bright_yellow(),
+                index::now().saturating_sub(existing.synced_at) / (24 * 60 * 60),
+                e
+            ));
+
+            Ok(existing)
+        }
+        (Err(e), None) => Err(e),
+    }
+}
+
+fn local_results(index: &Index, query: &str) -> Vec<SearchData> {
+    index
+        .search(query, LOCAL_LIMIT)
+        .into_iter()
+        .map(|entry| SearchData {
+            name: entry.name.clone(),
+            version: entry.version.clone(),
+            description: entry.description.clone(),
+        })
+        .collect()
+}
+
 pub struct Search {}
 #[async_trait]
 impl Command for Search {
-    fn help() -> String {
-        format!(
-            r#"volt {}
-
-Searches for a package 
-
-Usage: {} {} {} {}
-
-Options: 
-
-  {} {} Output the version number.
-  {} {} Output verbose messages on internal operations."#,
-            VERSION.bright_green().bold(),
-            "volt".bright_green().bold(),
-            "remove".bright_purple(),
-            "[packages]".white(),
-            "[flags]".white(),
-            "--version".blue(),
-            "(-ver)".yellow(),
-            "--verbose".blue(),
-            "(-v)".yellow()
-        )
+    fn help() -> Help {
+        Help::new("search", "Searches for a package.")
+            .usage("[query] [flags]")
+            .arg("[query]", "Words of the package name or description.")
+            .flag(
+                &["--local", "-l"],
+                "Search the local index instead of the registry.",
+            )
+            .flag(&["--sync"], "Sync the local index from the registry.")
+            .flag(&["--version", "-ver"], "Output the version number.")
+            .verbose()
     }
 
     /// Execute the `volt search` command
@@ -71,48 +131,55 @@ Options:
     /// ## Returns
     /// * `Result<()>`
     async fn exec(app: Arc<App>) -> Result<()> {
-        if app.args.len() >= 2 {
-            let package_name = &app.args[1];
-
-            let response = chttp::get_async(format!(
-                "https://www.npmjs.com/search/suggestions?q={}",
-                package_name
-            ))
-            .await
-            .unwrap_or_else(|_| {
-                println!("{}: package does not exist", "error".bright_red(),);
-                std::process::exit(1);
-            })
-            .text_async()
-            .await
-            .unwrap_or_else(|_| {
-                println!("{}: package does not exist", "error".bright_red());
-                std::process::exit(1);
-            });
-            let s: Vec<SearchData> = serde_json::from_str(&response).unwrap_or_else(|e| {
-                println!(
-                    "{}: failed to parse response from server {}",
-                    "error".bright_red(),
-                    e.to_string().bright_red(),
-                );
-
-                std::process::exit(1);
-            });
-
-            let mut table = Table::new();
-            table.add_row(row![
-                "Name".green().bold(),
-                "Version".green().bold(),
-                "Description".green().bold()
-            ]);
-            for i in s.iter() {
-                table.add_row(row![i.name, i.version, truncate(&i.description, 35)]);
+        if app.has_flag(&["--sync"]) {
+            let index = index::sync(&app).await?;
+
+            reporter::get().output(&format!(
+                "{} synced {} packages from {}",
+                "success".bright_green(),
+                index.entries.len(),
+                index.source
+            ));
+        }
+
+        if app.args.len() < 2 {
+            return Ok(());
+        }
+
+        let query = app.args[1..].join(" ");
+
+        let results = if app.has_flag(&["--local", "-l"]) || mirror::is_offline(&app) {
+            local_results(&local_index(&app).await?, &query)
+        } else {
+            match remote_search(&query).await {
+                Ok(results) => results,
+                // Basic discovery still works on a flaky network
+                Err(e) => match Index::read(&index::path(&app)) {
+                    Ok(index) => {
+                        reporter::get().output(&format!(
+                            "{} failed to search the registry, using the local index: {}",
+                            " warn ".black().on_bright_yellow(),
+                            e
+                        ));
+
+                        local_results(&index, &query)
+                    }
+                    Err(_) => return Err(e),
+                },
             }
-            table.printstd();
+        };
 
-            // let u: SearchResp = s;
-            // panic!("{:#?}", s);
+        let mut table = Table::new();
+        table.add_row(row![
+            "Name".green().bold(),
+            "Version".green().bold(),
+            "Description".green().bold()
+        ]);
+        for i in results.iter() {
+            table.add_row(row![i.name, i.version, truncate(&i.description, 35)]);
         }
+        table.printstd();
+
         Ok(())
     }
 }
@@ -0,0 +1,447 @@
+/*
+    Copyright 2021 Volt Contributors
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+        http://www.apache.org/licenses/LICENSE-2.0
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Local index of package names and descriptions, for searches without the network.
+//!
+//! The index is a gzipped list of JSON lines in `<cache dir>/search-index.jsonl.gz`. It is
+//! synced from the search API of the registry (`/-/v1/search`), paging through the results of
+//! the `search-index-queries` keywords, or with `search-index-source=replicate` from the
+//! `_all_docs` view of a CouchDB replica (`search-index-replica`), which has every name but no
+//! descriptions. `volt search --local` syncs it again once it is older than
+//! `search-index-interval` seconds, and keeps using the old one when the sync fails.
+
+use std::collections::BTreeMap;
+use std::fs::{create_dir_all, rename, File};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, Context, Result};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use volt_utils::app::App;
+
+pub const INDEX_FILE: &str = "search-index.jsonl.gz";
+
+/// Seconds before the index is synced again when `search-index-interval` is not set.
+pub const DEFAULT_INTERVAL: u64 = 7 * 24 * 60 * 60;
+
+/// Keywords paged through when `search-index-queries` is not set.
+pub const DEFAULT_QUERIES: [&str; 12] = [
+    "javascript",
+    "typescript",
+    "node",
+    "react",
+    "vue",
+    "cli",
+    "css",
+    "test",
+    "webpack",
+    "babel",
+    "eslint",
+    "http",
+];
+
+/// Results per query when `search-index-size` is not set, the most the search API pages to.
+pub const DEFAULT_SIZE: usize = 5000;
+
+/// Results per page of the search API.
+const PAGE_SIZE: usize = 250;
+
+/// Rows per page of `_all_docs`.
+const REPLICA_PAGE_SIZE: usize = 10000;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Entry {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub version: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub description: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub keywords: Vec<String>,
+}
+
+/// First line of the index file.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Header {
+    /// Seconds since the epoch
+    synced_at: u64,
+    source: String,
+}
+
+pub struct Index {
+    pub synced_at: u64,
+    pub source: String,
+    pub entries: Vec<Entry>,
+}
+
+pub fn path(app: &App) -> PathBuf {
+    app.cache_dir.join(INDEX_FILE)
+}
+
+impl Index {
+    pub fn read(path: &Path) -> Result<Self> {
+        let mut lines = BufReader::new(GzDecoder::new(File::open(path)?)).lines();
+
+        let header: Header = serde_json::from_str(
+            &lines
+                .next()
+                .ok_or_else(|| anyhow!("the search index is empty"))??,
+        )?;
+
+        let mut entries = vec![];
+
+        for line in lines {
+            entries.push(serde_json::from_str(&line?)?);
+        }
+
+        Ok(Index {
+            synced_at: header.synced_at,
+            source: header.source,
+            entries,
+        })
+    }
+
+    /// Write the index next to `path` and move it in place, so a reader never sees half of it.
+    pub fn write(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            create_dir_all(parent)?;
+        }
+
+        let partial = path.with_extension("partial");
+        let mut encoder = GzEncoder::new(File::create(&partial)?, Compression::best());
+
+        let header = Header {
+            synced_at: self.synced_at,
+            source: self.source.clone(),
+        };
+
+        writeln!(encoder, "{}", serde_json::to_string(&header)?)?;
+
+        for entry in self.entries.iter() {
+            writeln!(encoder, "{}", serde_json::to_string(entry)?)?;
+        }
+
+        encoder.finish()?;
+        rename(&partial, path)?;
+
+        Ok(())
+    }
+
+    pub fn is_stale(&self, now: u64, interval: u64) -> bool {
+        now.saturating_sub(self.synced_at) >= interval
+    }
+
+    /// Entries matching every word of `query`, best matches first.
+    ///
+    /// Names equal to the query come first, then names starting with it, names containing it,
+    /// and last entries which only mention it in their keywords or description.
+    pub fn search(&self, query: &str, limit: usize) -> Vec<&Entry> {
+        let query = query.to_lowercase();
+        let words: Vec<&str> = query.split_whitespace().collect();
+
+        let mut matches: Vec<(u8, &Entry)> = self
+            .entries
+            .iter()
+            .filter_map(|entry| {
+                let name = entry.name.to_lowercase();
+
+                let rank = if name == query {
+                    0
+                } else if name.starts_with(&query) {
+                    1
+                } else if name.contains(&query) {
+                    2
+                } else {
+                    let text = format!(
+                        "{} {} {}",
+                        name,
+                        entry.keywords.join(" ").to_lowercase(),
+                        entry.description.to_lowercase()
+                    );
+
+                    if words.is_empty() || !words.iter().all(|word| text.contains(word)) {
+                        return None;
+                    }
+
+                    3
+                };
+
+                Some((rank, entry))
+            })
+            .collect();
+
+        matches.sort_by(|(a_rank, a), (b_rank, b)| {
+            a_rank
+                .cmp(b_rank)
+                .then(a.name.len().cmp(&b.name.len()))
+                .then(a.name.cmp(&b.name))
+        });
+
+        matches
+            .into_iter()
+            .take(limit)
+            .map(|(_, entry)| entry)
+            .collect()
+    }
+}
+
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+/// Sync the index from the configured source and write it to the cache directory.
+pub async fn sync(app: &App) -> Result<Index> {
+    let (source, entries) = match app.config.get("search-index-source") {
+        Some("replicate") => {
+            let replica = app
+                .config
+                .get("search-index-replica")
+                .ok_or_else(|| anyhow!("search-index-replica is not set"))?;
+
+            (String::from(replica), replicate(replica).await?)
+        }
+        _ => {
+            let registry = volt_utils::registry_mirrors::url(app).await;
+
+            let queries = app
+                .config
+                .get_list("search-index-queries")
+                .unwrap_or_else(|| {
+                    DEFAULT_QUERIES
+                        .iter()
+                        .map(|query| query.to_string())
+                        .collect()
+                });
+
+            let size = app
+                .config
+                .get("search-index-size")
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(DEFAULT_SIZE);
+
+            (registry.clone(), search(&registry, &queries, size).await?)
+        }
+    };
+
+    let index = Index {
+        synced_at: now(),
+        source,
+        entries,
+    };
+
+    index.write(&path(app))?;
+
+    Ok(index)
+}
+
+/// Page through the results of every query, keeping each package once.
+async fn search(registry: &str, queries: &[String], size: usize) -> Result<Vec<Entry>> {
+    let client = reqwest::Client::new();
+    let mut entries = BTreeMap::new();
+
+    for query in queries {
+        let mut from = 0;
+
+        while from < size {
+            let response = client
+                .get(format!("{}/-/v1/search", registry))
+                .query(&[
+                    ("text", query.as_str()),
+                    ("size", &PAGE_SIZE.min(size - from).to_string()),
+                    ("from", &from.to_string()),
+                ])
+                .send()
+                .await?
+                .error_for_status()
+                .with_context(|| format!("failed to search {} for {}", registry, query))?
+                .text()
+                .await?;
+
+            let page = parse_search_page(&serde_json::from_str(&response)?);
+
+            if page.is_empty() {
+                break;
+            }
+
+            from += page.len();
+
+            for entry in page {
+                entries.entry(entry.name.clone()).or_insert(entry);
+            }
+        }
+    }
+
+    Ok(entries.into_values().collect())
+}
+
+/// Page through the `_all_docs` view of a CouchDB replica of the registry.
+async fn replicate(replica: &str) -> Result<Vec<Entry>> {
+    let client = reqwest::Client::new();
+    let replica = replica.trim_end_matches('/');
+    let mut entries = vec![];
+    let mut start_key: Option<String> = None;
+
+    loop {
+        let mut request = client
+            .get(format!("{}/_all_docs", replica))
+            .query(&[("limit", REPLICA_PAGE_SIZE.to_string())]);
+
+        if let Some(key) = start_key.as_ref() {
+            request = request.query(&[
+                ("startkey", serde_json::to_string(key)?),
+                ("skip", String::from("1")),
+            ]);
+        }
+
+        let response = request
+            .send()
+            .await?
+            .error_for_status()
+            .with_context(|| format!("failed to replicate {}", replica))?
+            .text()
+            .await?;
+
+        let names = parse_all_docs_page(&serde_json::from_str(&response)?);
+
+        match names.last() {
+            Some(last) => start_key = Some(last.clone()),
+            None => break,
+        }
+
+        entries.extend(names.into_iter().map(|name| Entry {
+            name,
+            version: String::new(),
+            description: String::new(),
+            keywords: vec![],
+        }));
+    }
+
+    Ok(entries)
+}
+
+fn parse_search_page(page: &Value) -> Vec<Entry> {
+    page["objects"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|object| {
+            let package = &object["package"];
+
+            Some(Entry {
+                name: package["name"].as_str()?.to_string(),
+                version: package["version"].as_str().unwrap_or_default().to_string(),
+                description: package["description"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_string(),
+                keywords: package["keywords"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|keyword| keyword.as_str().map(String::from))
+                    .collect(),
+            })
+        })
+        .collect()
+}
+
+fn parse_all_docs_page(page: &Value) -> Vec<String> {
+    page["rows"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|row| row["id"].as_str())
+        // Design documents are not packages
+        .filter(|id| !id.starts_with("_design/"))
+        .map(String::from)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(name: &str, description: &str) -> Entry {
+        Entry {
+            name: name.to_string(),
+            version: String::from("1.0.0"),
+            description: description.to_string(),
+            keywords: vec![],
+        }
+    }
+
+    #[test]
+    fn ranks_and_round_trips_entries() {
+        let index = Index {
+            synced_at: 1000,
+            source: String::from("https://registry.npmjs.org"),
+            entries: vec![
+                entry("preact", "Fast 3kb React alternative"),
+                entry("react-dom", "React package for working with the DOM."),
+                entry(
+                    "react",
+                    "React is a JavaScript library for building user interfaces.",
+                ),
+                entry("lodash", "Lodash modular utilities."),
+                entry(
+                    "inferno",
+                    "An extremely fast, React-like JavaScript library",
+                ),
+            ],
+        };
+
+        let names: Vec<&str> = index
+            .search("React", 10)
+            .iter()
+            .map(|entry| entry.name.as_str())
+            .collect();
+
+        assert_eq!(names, vec!["react", "react-dom", "preact", "inferno"]);
+        assert!(index.search("fast library", 10)[0].name == "inferno");
+        assert!(index.search("vue", 10).is_empty());
+
+        let path = std::env::temp_dir()
+            .join(format!("volt-search-{}", std::process::id()))
+            .join(INDEX_FILE);
+
+        index.write(&path).unwrap();
+        let read = Index::read(&path).unwrap();
+
+        assert_eq!(read.synced_at, 1000);
+        assert_eq!(read.entries, index.entries);
+        assert!(read.is_stale(1000 + DEFAULT_INTERVAL, DEFAULT_INTERVAL));
+
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn parses_replica_pages() {
+        let page = serde_json::json!({
+            "total_rows": 3,
+            "offset": 0,
+            "rows": [
+                { "id": "_design/app", "key": "_design/app", "value": {} },
+                { "id": "@types/node", "key": "@types/node", "value": {} },
+                { "id": "react", "key": "react", "value": {} }
+            ]
+        });
+
+        assert_eq!(parse_all_docs_page(&page), vec!["@types/node", "react"]);
+    }
+}
@@ -17,7 +17,7 @@ use std::sync::Arc;
 
 use anyhow::Result;
 use async_trait::async_trait;
-use volt_core::command::Command;
+use volt_core::{command::Command, help::Help};
 
 use volt_utils::app::App;
 
@@ -25,7 +25,7 @@ pub struct Set {}
 
 #[async_trait]
 impl Command for Set {
-    fn help() -> String {
+    fn help() -> Help {
         todo!()
     }
 
@@ -14,7 +14,7 @@
     limitations under the License.
 */
 
-//! Display stats on a specific package
+//! Display stats on a specific package, or on the dependencies of the project without one
 
 use std::sync::Arc;
 
@@ -23,8 +23,11 @@ use async_trait::async_trait;
 use colored::Colorize;
 use reqwest::get;
 use serde_json::Value;
-use volt_core::{command::Command, VERSION};
+use volt_core::{command::Command, help::Help};
 use volt_utils::app::App;
+use volt_utils::reporter;
+
+use crate::summary;
 
 /// Struct implementation for the `stat` command.
 pub struct Stat;
@@ -32,23 +35,24 @@ pub struct Stat;
 #[async_trait]
 impl Command for Stat {
     /// Display a help menu for the `volt stat` command.
-    fn help() -> String {
-        format!(
-            r#"volt {}
-    
-Displays statistics on a specific package.
-
-Usage: {} {} {}"#,
-            VERSION.bright_green().bold(),
-            "volt".bright_green().bold(),
-            "stat".bright_purple(),
-            "[package]".white(),
+    fn help() -> Help {
+        Help::new(
+            "stat",
+            "Displays statistics on a specific package, or summarizes the dependencies of the project.",
+        )
+        .usage("[package] [flags]")
+        .arg(
+            "[package]",
+            "The package to show the statistics of, the project if omitted.",
         )
+        .flag(&["--json"], "Print the summary of the project as JSON.")
+        .verbose()
     }
 
     /// Execute the `volt stat` command
     ///
-    /// Displays stats on a specific package
+    /// Displays stats on a specific package, or counts the dependencies of the project, lists the
+    /// heaviest and duplicated ones, those with install scripts, and the licenses
     /// ## Examples
     /// ```
     /// // .exec() is an async call so you need to await it
@@ -58,9 +62,13 @@ Usage: {} {} {}"#,
     /// * `Result<()>`
     async fn exec(app: Arc<App>) -> Result<()> {
         let args = &app.args;
-        let package = &args[1];
 
-        println!("{}\n", package.bright_cyan().bold());
+        let package = match args.get(1) {
+            Some(package) => package,
+            None => return summary::print(&app),
+        };
+
+        reporter::get().output(&format!("{}\n", package.bright_cyan().bold()));
 
         // Get downloads for the past week
         let url = format!(
@@ -81,11 +89,11 @@ Usage: {} {} {}"#,
         let data: Value = serde_json::from_str(&file_contents)?;
 
         let downloads = &data["downloads"];
-        println!(
+        reporter::get().output(&format!(
             "{} {}",
             downloads.to_string().bright_green(),
             "downloads in the past week!".bright_green()
-        );
+        ));
         Ok(())
     }
 }
@@ -0,0 +1,392 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Summarize the dependencies of a project, `volt stat` without a package.
+//!
+//! The graph comes from the lock file, and what the packages contain (their size, license and
+//! install scripts) from their entries in the store, or node_modules for packages which aren't in
+//! the store anymore.
+
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::fs::read_to_string;
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Result};
+use colored::Colorize;
+use serde::Serialize;
+use serde_json::Value;
+use volt_core::model::{
+    lock_file::LockFile,
+    lock_graph::{LockGraph, Node},
+};
+use volt_utils::{
+    app::App,
+    package::{self, PackageJson},
+    quota::{human_size, package_size},
+    reporter, volt_api,
+};
+
+/// Direct dependencies listed as the heaviest.
+const HEAVIEST_LIMIT: usize = 5;
+
+/// A direct dependency with everything it brings in.
+#[derive(Debug, Serialize)]
+struct Subtree {
+    name: String,
+    version: String,
+    packages: usize,
+    size: u64,
+}
+
+#[derive(Debug, Serialize)]
+struct Duplicate {
+    name: String,
+    versions: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Summary {
+    direct: usize,
+    transitive: usize,
+    /// The longest of the shortest chains from package.json to a package
+    depth: usize,
+    size: u64,
+    heaviest: Vec<Subtree>,
+    duplicated: Vec<Duplicate>,
+    /// Packages with install scripts
+    scripts: Vec<String>,
+    /// License → number of packages
+    licenses: BTreeMap<String, usize>,
+}
+
+/// What a package contains, from its files.
+#[derive(Default)]
+struct Contents {
+    size: u64,
+    license: Option<String>,
+    has_scripts: bool,
+}
+
+/// Every package reachable from `roots` with its distance to them, 1 for the roots.
+fn depths(graph: &LockGraph, roots: &[usize]) -> HashMap<usize, usize> {
+    let mut depths: HashMap<usize, usize> = roots.iter().map(|root| (*root, 1)).collect();
+    let mut queue: VecDeque<usize> = roots.iter().copied().collect();
+
+    while let Some(node) = queue.pop_front() {
+        let depth = depths[&node];
+
+        for (dependency, _) in graph.dependencies(node) {
+            if !depths.contains_key(dependency) {
+                depths.insert(*dependency, depth + 1);
+                queue.push_back(*dependency);
+            }
+        }
+    }
+
+    depths
+}
+
+/// `root` and everything below it.
+fn subtree(graph: &LockGraph, root: usize) -> HashSet<usize> {
+    depths(graph, &[root]).into_keys().collect()
+}
+
+/// Names the lock file has several versions of.
+fn duplicated(graph: &LockGraph) -> Vec<Duplicate> {
+    let mut versions: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+
+    for node in graph.nodes() {
+        versions
+            .entry(&node.name)
+            .or_default()
+            .push(node.version.clone());
+    }
+
+    versions
+        .into_iter()
+        .filter(|(_, versions)| versions.len() > 1)
+        .map(|(name, versions)| Duplicate {
+            name: name.to_string(),
+            versions,
+        })
+        .collect()
+}
+
+/// The license of a package.json, `MIT` or `{ "type": "MIT" }` in older packages.
+fn license(manifest: &Value) -> Option<String> {
+    manifest["license"]
+        .as_str()
+        .or_else(|| manifest["license"]["type"].as_str())
+        .map(String::from)
+}
+
+/// The checksums of the lock file, by name and version.
+fn checksums(lock_file: &LockFile) -> HashMap<(&str, &str), &str> {
+    lock_file
+        .dependencies
+        .values()
+        .map(|lock| {
+            (
+                (lock.name.as_str(), lock.version.as_str()),
+                lock.sha1.as_str(),
+            )
+        })
+        .collect()
+}
+
+/// Where the files of `node` are, its entry in the store or its copy in node_modules.
+fn package_dir(app: &App, sha1: &str, node: &Node) -> Option<PathBuf> {
+    let entry = app
+        .volt_dir
+        .join(volt_api::store_name(&node.name, &node.version, sha1));
+
+    if entry.exists() {
+        return Some(entry);
+    }
+
+    let installed = app.node_modules_dir.join(&node.name);
+    let manifest: Value =
+        serde_json::from_str(&read_to_string(installed.join("package.json")).ok()?).ok()?;
+
+    if manifest["version"] == node.version.as_str() {
+        Some(installed)
+    } else {
+        None
+    }
+}
+
+fn contents(app: &App, sha1: &str, node: &Node) -> Contents {
+    let dir = match package_dir(app, sha1, node) {
+        Some(dir) => dir,
+        None => return Contents::default(),
+    };
+
+    let manifest: Value = read_to_string(dir.join("package.json"))
+        .ok()
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or_default();
+
+    Contents {
+        size: package_size(&dir),
+        license: license(&manifest),
+        has_scripts: !package::install_scripts(&dir).is_empty(),
+    }
+}
+
+fn summarize(app: &App, lock_file: &LockFile, package_json: &PackageJson) -> Summary {
+    let graph = LockGraph::new(lock_file);
+
+    let fields = [
+        &package_json.dependencies,
+        &package_json.dev_dependencies,
+        &package_json.optional_dependencies,
+    ];
+
+    let mut roots: Vec<usize> = fields
+        .iter()
+        .flat_map(|dependencies| dependencies.iter())
+        .filter_map(|(name, range)| graph.resolve(name, range))
+        .collect();
+    roots.sort_unstable();
+    roots.dedup();
+
+    let depths = depths(&graph, &roots);
+    let checksums = checksums(lock_file);
+
+    let contents: HashMap<usize, Contents> = depths
+        .keys()
+        .map(|index| {
+            let node = graph.node(*index);
+            let sha1 = checksums
+                .get(&(node.name.as_str(), node.version.as_str()))
+                .copied()
+                .unwrap_or_default();
+
+            (*index, contents(app, sha1, node))
+        })
+        .collect();
+
+    let mut heaviest: Vec<Subtree> = roots
+        .iter()
+        .map(|root| {
+            let nodes = subtree(&graph, *root);
+
+            Subtree {
+                name: graph.node(*root).name.clone(),
+                version: graph.node(*root).version.clone(),
+                packages: nodes.len(),
+                size: nodes.iter().map(|node| contents[node].size).sum(),
+            }
+        })
+        .collect();
+    heaviest.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.name.cmp(&b.name)));
+    heaviest.truncate(HEAVIEST_LIMIT);
+
+    let mut scripts: Vec<String> = contents
+        .iter()
+        .filter(|(_, contents)| contents.has_scripts)
+        .map(|(node, _)| {
+            let node = graph.node(*node);
+            format!("{}@{}", node.name, node.version)
+        })
+        .collect();
+    scripts.sort();
+
+    let mut licenses = BTreeMap::new();
+
+    for contents in contents.values() {
+        let license = contents
+            .license
+            .clone()
+            .unwrap_or_else(|| String::from("unknown"));
+
+        *licenses.entry(license).or_insert(0) += 1;
+    }
+
+    Summary {
+        direct: roots.len(),
+        transitive: depths.len() - roots.len(),
+        depth: depths.values().copied().max().unwrap_or_default(),
+        size: contents.values().map(|contents| contents.size).sum(),
+        heaviest,
+        duplicated: duplicated(&graph),
+        scripts,
+        licenses,
+    }
+}
+
+fn print_summary(summary: &Summary) {
+    reporter::get().output(&format!(
+        "{} {} direct, {} transitive, {} deep, {}",
+        "Dependencies".bright_cyan().bold(),
+        summary.direct,
+        summary.transitive,
+        summary.depth,
+        human_size(summary.size)
+    ));
+
+    if !summary.heaviest.is_empty() {
+        reporter::get().output(&format!("\n{}", "Heaviest".bright_cyan().bold()));
+
+        for subtree in summary.heaviest.iter() {
+            reporter::get().output(&format!(
+                "  {}@{} {} in {} packages",
+                subtree.name,
+                subtree.version,
+                human_size(subtree.size).yellow(),
+                subtree.packages
+            ));
+        }
+    }
+
+    if !summary.duplicated.is_empty() {
+        reporter::get().output(&format!("\n{}", "Duplicated".bright_cyan().bold()));
+
+        for duplicate in summary.duplicated.iter() {
+            reporter::get().output(&format!(
+                "  {} {}",
+                duplicate.name,
+                duplicate.versions.join(", ").dimmed()
+            ));
+        }
+    }
+
+    if !summary.scripts.is_empty() {
+        reporter::get().output(&format!("\n{}", "Install scripts".bright_cyan().bold()));
+        reporter::get().output(&format!("  {}", summary.scripts.join(", ")));
+    }
+
+    if !summary.licenses.is_empty() {
+        let mut licenses: Vec<(&String, &usize)> = summary.licenses.iter().collect();
+        licenses.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+
+        reporter::get().output(&format!("\n{}", "Licenses".bright_cyan().bold()));
+
+        for (license, count) in licenses {
+            reporter::get().output(&format!("  {} {}", license, count.to_string().dimmed()));
+        }
+    }
+}
+
+/// Print the summary of the dependencies of the project `app` is in.
+pub fn print(app: &App) -> Result<()> {
+    let lock_file = LockFile::load(app.lock_file_path.clone()).map_err(|_| {
+        anyhow!(
+            "failed to read {}, run volt install first",
+            app.lock_file_path.display()
+        )
+    })?;
+
+    let package_json = PackageJson::from(&app.current_dir.join("package.json").to_string_lossy());
+
+    let summary = summarize(app, &lock_file, &package_json);
+
+    if reporter::json() {
+        reporter::get().document(&serde_json::to_string_pretty(&summary)?);
+    } else {
+        print_summary(&summary);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn measures_depth_and_duplicates() {
+        let lock_file = LockFile::from_entries(&[
+            ("express", "4.18.2", &[("debug", "2.6.9")]),
+            ("debug", "2.6.9", &[("ms", "2.0.0")]),
+            ("debug", "4.3.4", &[("ms", "2.1.2")]),
+            ("ms", "2.0.0", &[]),
+            ("ms", "2.1.2", &[]),
+        ]);
+        let graph = LockGraph::new(&lock_file);
+
+        let express = graph.resolve("express", "^4.0.0").unwrap();
+        let depths = depths(&graph, &[express]);
+
+        assert_eq!(depths.len(), 3);
+        assert_eq!(depths.values().max(), Some(&3));
+        assert_eq!(subtree(&graph, express).len(), 3);
+
+        let duplicated: Vec<(String, Vec<String>)> = duplicated(&graph)
+            .into_iter()
+            .map(|duplicate| (duplicate.name, duplicate.versions))
+            .collect();
+        assert_eq!(
+            duplicated,
+            vec![
+                (
+                    String::from("debug"),
+                    vec![String::from("2.6.9"), String::from("4.3.4")]
+                ),
+                (
+                    String::from("ms"),
+                    vec![String::from("2.0.0"), String::from("2.1.2")]
+                ),
+            ]
+        );
+
+        assert_eq!(
+            license(&serde_json::json!({ "license": { "type": "MIT" } })).as_deref(),
+            Some("MIT")
+        );
+    }
+}
@@ -17,12 +17,12 @@ use std::sync::Arc;
 
 use anyhow::Result;
 use async_trait::async_trait;
-use volt_core::command::Command;
+use volt_core::{command::Command, help::Help};
 use volt_utils::app::App;
 pub struct Team {}
 #[async_trait]
 impl Command for Team {
-    fn help() -> String {
+    fn help() -> Help {
         todo!()
     }
 
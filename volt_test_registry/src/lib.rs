@@ -0,0 +1,439 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! An npm registry in the test process, serving packages made up by the test.
+//!
+//! ```ignore
+//! let registry = MockRegistry::new()
+//!     .publish(Package::new("left", "1.0.0").dependency("right", "^2.0.0"))
+//!     .publish(Package::new("right", "2.1.0").file("index.js", "module.exports = 2"))
+//!     .start();
+//!
+//! // registry=<url> in the .voltrc of the test project
+//! println!("{}", registry.url());
+//! ```
+//!
+//! Packuments are served at `/<name>` and tarballs at `/<name>/-/<name>-<version>.tgz`, like the
+//! npm registry, with checksums and publish times. The server runs on a thread of its own until
+//! the [`RunningRegistry`] is dropped, so it works from sync and async tests alike, and it keeps
+//! the requests it answered for tests to check.
+
+use std::collections::{BTreeMap, HashMap};
+use std::convert::Infallible;
+use std::net::{SocketAddr, TcpListener};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+
+use flate2::{write::GzEncoder, Compression};
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{header, Body, Method, Request, Response, Server, StatusCode};
+use serde_json::{json, Map, Value};
+use sha1::{Digest, Sha1};
+use tokio::sync::oneshot;
+
+/// When packages are published, unless [`Package::published`] says otherwise.
+pub const DEFAULT_PUBLISHED: &str = "2021-01-01T00:00:00.000Z";
+
+/// A version of a package, with the files of its tarball.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Package {
+    pub name: String,
+    pub version: String,
+    pub dependencies: BTreeMap<String, String>,
+    pub scripts: BTreeMap<String, String>,
+    pub bin: BTreeMap<String, String>,
+    /// Besides the package.json, which is generated
+    pub files: BTreeMap<String, Vec<u8>>,
+    pub published: String,
+}
+
+impl Package {
+    pub fn new(name: &str, version: &str) -> Self {
+        Package {
+            name: name.to_string(),
+            version: version.to_string(),
+            dependencies: BTreeMap::new(),
+            scripts: BTreeMap::new(),
+            bin: BTreeMap::new(),
+            files: BTreeMap::new(),
+            published: DEFAULT_PUBLISHED.to_string(),
+        }
+    }
+
+    pub fn dependency(mut self, name: &str, range: &str) -> Self {
+        self.dependencies
+            .insert(name.to_string(), range.to_string());
+        self
+    }
+
+    pub fn script(mut self, name: &str, command: &str) -> Self {
+        self.scripts.insert(name.to_string(), command.to_string());
+        self
+    }
+
+    pub fn bin(mut self, name: &str, path: &str) -> Self {
+        self.bin.insert(name.to_string(), path.to_string());
+        self
+    }
+
+    pub fn file(mut self, path: &str, contents: impl Into<Vec<u8>>) -> Self {
+        self.files.insert(path.to_string(), contents.into());
+        self
+    }
+
+    /// Set the publish time, an RFC 3339 date.
+    pub fn published(mut self, time: &str) -> Self {
+        self.published = time.to_string();
+        self
+    }
+
+    /// The package.json of the package.
+    pub fn manifest(&self) -> Value {
+        let mut manifest = json!({
+            "name": self.name,
+            "version": self.version,
+        });
+
+        if !self.dependencies.is_empty() {
+            manifest["dependencies"] = json!(self.dependencies);
+        }
+
+        if !self.scripts.is_empty() {
+            manifest["scripts"] = json!(self.scripts);
+        }
+
+        if !self.bin.is_empty() {
+            manifest["bin"] = json!(self.bin);
+        }
+
+        manifest
+    }
+
+    /// The gzipped tarball of the package, with the files under `package/`.
+    pub fn tarball(&self) -> Vec<u8> {
+        let mut builder = tar::Builder::new(GzEncoder::new(vec![], Compression::default()));
+        let manifest = serde_json::to_vec_pretty(&self.manifest()).unwrap_or_default();
+
+        let files = std::iter::once(("package.json", manifest.as_slice())).chain(
+            self.files
+                .iter()
+                .map(|(path, data)| (path.as_str(), data.as_slice())),
+        );
+
+        for (path, data) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+
+            // Writing to memory doesn't fail
+            let _ = builder.append_data(&mut header, format!("package/{}", path), data);
+        }
+
+        builder
+            .into_inner()
+            .and_then(|encoder| encoder.finish())
+            .unwrap_or_default()
+    }
+
+    /// The path of the tarball on the registry.
+    pub fn tarball_path(&self) -> String {
+        let basename = self.name.rsplit('/').next().unwrap_or(&self.name);
+
+        format!("/{}/-/{}-{}.tgz", self.name, basename, self.version)
+    }
+}
+
+/// The packages to serve, before the server starts.
+#[derive(Debug, Clone, Default)]
+pub struct MockRegistry {
+    packages: Vec<Package>,
+    /// Name → tag → version, `latest` is the highest version when it isn't set
+    tags: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+/// What the server answers with.
+struct State {
+    packuments: HashMap<String, Value>,
+    tarballs: HashMap<String, Vec<u8>>,
+    requests: Arc<Mutex<Vec<String>>>,
+}
+
+fn sha1(data: &[u8]) -> String {
+    format!("{:x}", Sha1::digest(data))
+}
+
+/// The numbers of a version, to find the highest one.
+fn version_key(version: &str) -> Vec<u64> {
+    version
+        .split(|c: char| !c.is_ascii_digit())
+        .filter_map(|part| part.parse().ok())
+        .collect()
+}
+
+impl MockRegistry {
+    pub fn new() -> Self {
+        MockRegistry::default()
+    }
+
+    pub fn publish(mut self, package: Package) -> Self {
+        self.packages.push(package);
+        self
+    }
+
+    pub fn tag(mut self, name: &str, tag: &str, version: &str) -> Self {
+        self.tags
+            .entry(name.to_string())
+            .or_default()
+            .insert(tag.to_string(), version.to_string());
+        self
+    }
+
+    /// The packument of `name`, with tarballs served from `url`.
+    pub fn packument(&self, name: &str, url: &str) -> Option<Value> {
+        let versions: Vec<&Package> = self
+            .packages
+            .iter()
+            .filter(|package| package.name == name)
+            .collect();
+
+        let latest = versions
+            .iter()
+            .max_by_key(|package| version_key(&package.version))?;
+
+        let mut tags = self.tags.get(name).cloned().unwrap_or_default();
+        tags.entry(String::from("latest"))
+            .or_insert_with(|| latest.version.clone());
+
+        let mut manifests = Map::new();
+        let mut times = Map::new();
+
+        for package in versions.iter() {
+            let mut manifest = package.manifest();
+            manifest["dist"] = json!({
+                "shasum": sha1(&package.tarball()),
+                "tarball": format!("{}{}", url, package.tarball_path()),
+            });
+
+            manifests.insert(package.version.clone(), manifest);
+            times.insert(package.version.clone(), json!(package.published));
+        }
+
+        Some(json!({
+            "name": name,
+            "dist-tags": tags,
+            "versions": manifests,
+            "time": times,
+        }))
+    }
+
+    fn state(&self, url: &str, requests: Arc<Mutex<Vec<String>>>) -> State {
+        let mut packuments = HashMap::new();
+        let mut tarballs = HashMap::new();
+
+        for package in self.packages.iter() {
+            if let Some(packument) = self.packument(&package.name, url) {
+                packuments.insert(package.name.clone(), packument);
+            }
+
+            tarballs.insert(package.tarball_path(), package.tarball());
+        }
+
+        State {
+            packuments,
+            tarballs,
+            requests,
+        }
+    }
+
+    /// Serve the packages on a free port of localhost.
+    pub fn start(self) -> RunningRegistry {
+        let requests = Arc::new(Mutex::new(vec![]));
+        let (address_sender, address_receiver) = mpsc::channel();
+        let (shutdown, shutdown_receiver) = oneshot::channel::<()>();
+        let log = requests.clone();
+
+        thread::spawn(move || {
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .expect("failed to start the runtime of the mock registry");
+
+            runtime.block_on(async move {
+                // Bound first, the packuments hold the url
+                let listener = TcpListener::bind(SocketAddr::from(([127, 0, 0, 1], 0)))
+                    .expect("failed to bind the mock registry");
+                let address = listener
+                    .local_addr()
+                    .expect("failed to bind the mock registry");
+                let builder = Server::from_tcp(listener).expect("failed to bind the mock registry");
+                let state = Arc::new(self.state(&format!("http://{}", address), log));
+
+                let service = make_service_fn(move |_| {
+                    let state = state.clone();
+
+                    async move {
+                        Ok::<_, Infallible>(service_fn(move |request| {
+                            let response = handle(&state, &request);
+                            async move { Ok::<_, Infallible>(response) }
+                        }))
+                    }
+                });
+
+                let server = builder.serve(service).with_graceful_shutdown(async {
+                    let _ = shutdown_receiver.await;
+                });
+
+                let _ = address_sender.send(address);
+                let _ = server.await;
+            });
+        });
+
+        let address = address_receiver
+            .recv()
+            .expect("the mock registry failed to start");
+
+        RunningRegistry {
+            url: format!("http://{}", address),
+            requests,
+            shutdown: Some(shutdown),
+        }
+    }
+}
+
+fn respond(status: StatusCode, content_type: &'static str, body: Vec<u8>) -> Response<Body> {
+    let mut response = Response::new(Body::from(body));
+    *response.status_mut() = status;
+    response.headers_mut().insert(
+        header::CONTENT_TYPE,
+        header::HeaderValue::from_static(content_type),
+    );
+
+    response
+}
+
+fn handle(state: &State, request: &Request<Body>) -> Response<Body> {
+    let path = request
+        .uri()
+        .path()
+        .replace("%2f", "/")
+        .replace("%2F", "/")
+        .replace("%40", "@");
+
+    if let Ok(mut requests) = state.requests.lock() {
+        requests.push(format!("{} {}", request.method(), path));
+    }
+
+    if request.method() != Method::GET {
+        return respond(StatusCode::METHOD_NOT_ALLOWED, "text/plain", vec![]);
+    }
+
+    if let Some(tarball) = state.tarballs.get(&path) {
+        return respond(StatusCode::OK, "application/octet-stream", tarball.clone());
+    }
+
+    match state.packuments.get(path.trim_start_matches('/')) {
+        Some(packument) => respond(
+            StatusCode::OK,
+            "application/json",
+            packument.to_string().into_bytes(),
+        ),
+        None => respond(
+            StatusCode::NOT_FOUND,
+            "application/json",
+            json!({ "error": "Not found" }).to_string().into_bytes(),
+        ),
+    }
+}
+
+/// A registry being served, stopped when dropped.
+pub struct RunningRegistry {
+    url: String,
+    requests: Arc<Mutex<Vec<String>>>,
+    shutdown: Option<oneshot::Sender<()>>,
+}
+
+impl RunningRegistry {
+    /// `http://127.0.0.1:<port>`, without a trailing slash.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// The requests answered so far, `GET /left` or `GET /left/-/left-1.0.0.tgz`.
+    pub fn requests(&self) -> Vec<String> {
+        self.requests
+            .lock()
+            .map(|requests| requests.clone())
+            .unwrap_or_default()
+    }
+}
+
+impl Drop for RunningRegistry {
+    fn drop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn serves_packuments_and_tarballs() {
+        let registry = MockRegistry::new()
+            .publish(Package::new("@acme/left", "1.0.0"))
+            .publish(Package::new("@acme/left", "1.10.0").dependency("right", "^2.0.0"))
+            .publish(Package::new("@acme/left", "1.9.0"))
+            .start();
+
+        let packument = reqwest::get(format!("{}/@acme%2fleft", registry.url()))
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        let packument: Value = serde_json::from_str(&packument).unwrap();
+
+        assert_eq!(packument["dist-tags"]["latest"], "1.10.0");
+
+        let dist = &packument["versions"]["1.10.0"]["dist"];
+        let tarball = reqwest::get(dist["tarball"].as_str().unwrap())
+            .await
+            .unwrap()
+            .bytes()
+            .await
+            .unwrap();
+
+        assert_eq!(sha1(&tarball), dist["shasum"]);
+        assert_eq!(
+            reqwest::get(format!("{}/missing", registry.url()))
+                .await
+                .unwrap()
+                .status(),
+            404
+        );
+        assert_eq!(
+            registry.requests(),
+            vec![
+                "GET /@acme/left",
+                "GET /@acme/left/-/left-1.10.0.tgz",
+                "GET /missing"
+            ]
+        );
+    }
+}
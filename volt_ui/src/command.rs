@@ -0,0 +1,313 @@
+/*
+Copyright 2021 Volt Contributors
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+    http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+//! Browse and manage the dependencies of the project in the terminal.
+
+use std::fs::read_dir;
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use colored::Colorize;
+use console::{Key, Term};
+use volt_core::{
+    command::Command,
+    help::Help,
+    model::{lock_file::LockFile, lock_graph::LockGraph},
+    prompt::prompts::Input,
+};
+use volt_utils::{app::App, package::PackageJson, quota, reporter};
+
+use crate::dashboard::{self, Dashboard, Section};
+
+/// An action on the dependencies, run by the commands doing it from the cli.
+enum Action {
+    Add(String),
+    Remove(String),
+    Update(String),
+}
+
+impl Action {
+    fn args(&self) -> Vec<String> {
+        match self {
+            Action::Add(package) => vec![String::from("add"), package.clone()],
+            Action::Remove(package) => vec![String::from("remove"), package.clone()],
+            Action::Update(package) => vec![String::from("add"), format!("{}@latest", package)],
+        }
+    }
+}
+
+fn tree_section(app: &App, package_json: &PackageJson) -> Section {
+    let lock_file = match LockFile::load(app.lock_file_path.clone()) {
+        Ok(lock_file) => lock_file,
+        Err(_) => {
+            return Section::failed(
+                "Tree",
+                format!(
+                    "failed to read {}, run volt install first",
+                    app.lock_file_path.display()
+                ),
+            )
+        }
+    };
+
+    let mut roots: Vec<(String, String)> = package_json
+        .dependencies
+        .iter()
+        .chain(package_json.dev_dependencies.iter())
+        .chain(package_json.optional_dependencies.iter())
+        .map(|(name, range)| (name.clone(), range.clone()))
+        .collect();
+
+    roots.sort();
+
+    Section::new(
+        "Tree",
+        dashboard::tree_items(&LockGraph::new(&lock_file), &roots),
+    )
+}
+
+/// The size of each package at the top of node_modules, scoped packages included.
+fn package_sizes(node_modules_dir: &Path) -> Result<Vec<(String, u64)>> {
+    let mut sizes = vec![];
+
+    for entry in read_dir(node_modules_dir)?.filter_map(|entry| entry.ok()) {
+        let name = entry.file_name().to_string_lossy().to_string();
+
+        if name.starts_with('.') {
+            continue;
+        }
+
+        if name.starts_with('@') {
+            for scoped in read_dir(entry.path())?.filter_map(|entry| entry.ok()) {
+                sizes.push((
+                    format!("{}/{}", name, scoped.file_name().to_string_lossy()),
+                    quota::dir_size(&scoped.path()),
+                ));
+            }
+        } else {
+            sizes.push((name, quota::dir_size(&entry.path())));
+        }
+    }
+
+    Ok(sizes)
+}
+
+/// Load every section, the ones asking the registry at the same time.
+async fn load(app: &App) -> Dashboard {
+    let package_json = PackageJson::from(&app.current_dir.join("package.json").to_string_lossy());
+
+    let (outdated, audit) = tokio::join!(
+        volt_outdated::command::outdated(app),
+        volt_audit::command::audit(app)
+    );
+
+    let outdated = match outdated {
+        Ok(packages) => Section::new("Outdated", dashboard::outdated_items(&packages)),
+        Err(error) => Section::failed("Outdated", error.to_string()),
+    };
+
+    let vulnerable = match audit {
+        Ok(vulnerabilities) => {
+            Section::new("Vulnerable", dashboard::vulnerable_items(&vulnerabilities))
+        }
+        Err(error) => Section::failed("Vulnerable", error.to_string()),
+    };
+
+    let disk = match package_sizes(&app.node_modules_dir) {
+        Ok(sizes) => Section::new("Disk", dashboard::disk_items(sizes)),
+        Err(_) => Section::failed(
+            "Disk",
+            String::from("no node_modules, run volt install first"),
+        ),
+    };
+
+    Dashboard {
+        project: package_json.name.clone(),
+        sections: vec![tree_section(app, &package_json), outdated, vulnerable, disk],
+        current: 0,
+        status: None,
+    }
+}
+
+fn draw(term: &Term, dashboard: &Dashboard) -> Result<()> {
+    let (height, width) = term.size();
+
+    term.move_cursor_to(0, 0)?;
+    term.write_str(
+        &dashboard
+            .render(width as usize, height as usize)
+            .into_iter()
+            .map(|line| format!("{}\x1b[K", line))
+            .collect::<Vec<String>>()
+            .join("\r\n"),
+    )?;
+    term.flush()?;
+
+    Ok(())
+}
+
+/// Leave the dashboard to run an action, its output stays until a key is pressed.
+async fn run(app: &App, term: &Term, action: Action) -> Result<()> {
+    term.clear_screen()?;
+    term.show_cursor()?;
+
+    let args = action.args();
+    reporter::get().output(&format!(
+        "{} {}\n",
+        "$ volt".dimmed(),
+        args.join(" ").bright_cyan()
+    ));
+
+    let app = Arc::new(app.with_args(args));
+
+    let result = match action {
+        Action::Add(_) | Action::Update(_) => volt_add::command::Add::exec(app).await,
+        Action::Remove(_) => volt_remove::command::Remove::exec(app).await,
+    };
+
+    if let Err(error) = result.as_ref() {
+        reporter::get().output(&format!("{} {}", "error".bright_red(), error));
+    }
+
+    reporter::get().output(&format!("\n{}", "Press any key to go back.".dimmed()));
+    term.read_key()?;
+    term.hide_cursor()?;
+    term.clear_screen()?;
+
+    result
+}
+
+/// Struct implementation for the `ui` command.
+pub struct Ui;
+
+#[async_trait]
+impl Command for Ui {
+    /// Display a help menu for the `volt ui` command.
+    fn help() -> Help {
+        Help::new(
+            "ui",
+            "Browse the dependency tree, outdated and vulnerable packages and disk usage,
+and add, remove or update packages from the terminal.",
+        )
+        .usage("[flags]")
+        .verbose()
+    }
+
+    /// Execute the `volt ui` command
+    ///
+    /// Opens the dependency dashboard until `q` is pressed.
+    /// ## Arguments
+    /// * `app` - Instance of the command (`Arc<App>`)
+    /// ## Examples
+    /// ```ignore
+    /// // .exec() is an async call so you need to await it
+    /// Ui.exec(app).await;
+    /// ```
+    /// ## Returns
+    /// * `Result<()>`
+    async fn exec(app: Arc<App>) -> Result<()> {
+        let term = Term::stdout();
+
+        if !term.features().is_attended() {
+            return Err(anyhow!("volt ui needs an interactive terminal"));
+        }
+
+        reporter::get().output(&"Loading dependencies…".dimmed().to_string());
+        let mut dashboard = load(&app).await;
+
+        term.hide_cursor()?;
+        term.clear_screen()?;
+
+        loop {
+            draw(&term, &dashboard)?;
+
+            let visible = Dashboard::visible(term.size().0 as usize);
+            let selected = dashboard.section().selected().map(String::from);
+
+            let key = term.read_key()?;
+            dashboard.status = None;
+
+            let action = match key {
+                Key::Char('q') | Key::Escape => break,
+                Key::ArrowLeft | Key::BackTab => {
+                    dashboard.switch(-1);
+                    None
+                }
+                Key::ArrowRight | Key::Tab => {
+                    dashboard.switch(1);
+                    None
+                }
+                Key::ArrowUp | Key::Char('k') => {
+                    dashboard.section().select(-1, visible);
+                    None
+                }
+                Key::ArrowDown | Key::Char('j') => {
+                    dashboard.section().select(1, visible);
+                    None
+                }
+                Key::PageUp => {
+                    dashboard.section().select(-(visible as isize), visible);
+                    None
+                }
+                Key::PageDown => {
+                    dashboard.section().select(visible as isize, visible);
+                    None
+                }
+                Key::Char('a') => {
+                    term.clear_screen()?;
+                    term.show_cursor()?;
+
+                    let package = Input {
+                        message: String::from("Package to add"),
+                        default: None,
+                        allow_empty: true,
+                    }
+                    .run()?;
+
+                    term.hide_cursor()?;
+                    term.clear_screen()?;
+
+                    Some(package.trim().to_string())
+                        .filter(|package| !package.is_empty())
+                        .map(Action::Add)
+                }
+                Key::Char('r') => selected.map(Action::Remove),
+                Key::Char('u') => selected.map(Action::Update),
+                Key::Char('R') => {
+                    dashboard = load(&app).await;
+                    None
+                }
+                _ => None,
+            };
+
+            if let Some(action) = action {
+                let current = dashboard.current;
+                let status = match run(&app, &term, action).await {
+                    Ok(()) => "done".bright_green().to_string(),
+                    Err(error) => format!("{} {}", "error".bright_red(), error),
+                };
+
+                dashboard = load(&app).await;
+                dashboard.current = current;
+                dashboard.status = Some(status);
+            }
+        }
+
+        term.clear_screen()?;
+        term.show_cursor()?;
+
+        Ok(())
+    }
+}
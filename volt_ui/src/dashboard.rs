@@ -0,0 +1,359 @@
+/*
+Copyright 2021 Volt Contributors
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+    http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+//! State and rendering of the `volt ui` dashboard, kept apart from the terminal.
+
+use std::collections::HashSet;
+
+use colored::Colorize;
+use volt_audit::command::Vulnerability;
+use volt_core::model::lock_graph::LockGraph;
+use volt_outdated::command::OutdatedPackage;
+use volt_utils::quota::human_size;
+
+/// Lines above and below the list of a section.
+const CHROME_LINES: usize = 5;
+
+/// A line of a section, `package` is what actions apply to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Item {
+    pub package: Option<String>,
+    pub line: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Section {
+    pub title: &'static str,
+    pub items: Vec<Item>,
+    /// Why the section could not be loaded
+    pub error: Option<String>,
+    pub cursor: usize,
+    pub offset: usize,
+}
+
+impl Section {
+    pub fn new(title: &'static str, items: Vec<Item>) -> Self {
+        Section {
+            title,
+            items,
+            error: None,
+            cursor: 0,
+            offset: 0,
+        }
+    }
+
+    pub fn failed(title: &'static str, error: String) -> Self {
+        Section {
+            error: Some(error),
+            ..Section::new(title, vec![])
+        }
+    }
+
+    /// Move the cursor by `delta` lines, scrolling to keep it within `visible` lines.
+    pub fn select(&mut self, delta: isize, visible: usize) {
+        if self.items.is_empty() {
+            return;
+        }
+
+        let last = self.items.len() as isize - 1;
+        self.cursor = (self.cursor as isize + delta).clamp(0, last) as usize;
+
+        if self.cursor < self.offset {
+            self.offset = self.cursor;
+        } else if self.cursor >= self.offset + visible.max(1) {
+            self.offset = self.cursor + 1 - visible.max(1);
+        }
+    }
+
+    pub fn selected(&self) -> Option<&str> {
+        self.items.get(self.cursor)?.package.as_deref()
+    }
+}
+
+pub struct Dashboard {
+    pub project: String,
+    pub sections: Vec<Section>,
+    pub current: usize,
+    /// Result of the last action
+    pub status: Option<String>,
+}
+
+impl Dashboard {
+    pub fn section(&mut self) -> &mut Section {
+        &mut self.sections[self.current]
+    }
+
+    pub fn switch(&mut self, delta: isize) {
+        let count = self.sections.len() as isize;
+        self.current = (self.current as isize + delta).rem_euclid(count) as usize;
+    }
+
+    /// Lines of the list for a terminal `height` lines tall.
+    pub fn visible(height: usize) -> usize {
+        height.saturating_sub(CHROME_LINES).max(1)
+    }
+
+    /// The screen, as `height` lines cut at `width` columns.
+    pub fn render(&self, width: usize, height: usize) -> Vec<String> {
+        let mut lines = vec![format!(
+            "{} {}  {}",
+            "volt".bright_green().bold(),
+            "ui".bright_purple(),
+            self.project.bright_cyan()
+        )];
+
+        lines.push(
+            self.sections
+                .iter()
+                .enumerate()
+                .map(|(index, section)| {
+                    let title = format!(" {} ({}) ", section.title, section.items.len());
+
+                    if index == self.current {
+                        title.black().on_bright_cyan().to_string()
+                    } else {
+                        title
+                    }
+                })
+                .collect::<Vec<String>>()
+                .join(" "),
+        );
+
+        lines.push("─".repeat(width).dimmed().to_string());
+
+        let section = &self.sections[self.current];
+        let visible = Self::visible(height);
+
+        if let Some(error) = section.error.as_ref() {
+            lines.push(format!("  {} {}", "error".bright_red(), error));
+        } else if section.items.is_empty() {
+            lines.push(format!("  {}", "Nothing to show.".dimmed()));
+        }
+
+        for (index, item) in section
+            .items
+            .iter()
+            .enumerate()
+            .skip(section.offset)
+            .take(visible)
+        {
+            if index == section.cursor {
+                lines.push(format!("{} {}", "❯".bright_cyan(), item.line));
+            } else {
+                lines.push(format!("  {}", item.line));
+            }
+        }
+
+        while lines.len() < height.saturating_sub(2) {
+            lines.push(String::new());
+        }
+
+        lines.push("─".repeat(width).dimmed().to_string());
+        lines.push(self.status.clone().unwrap_or_else(|| {
+            "←/→ section  ↑/↓ move  a add  r remove  u update  R reload  q quit"
+                .dimmed()
+                .to_string()
+        }));
+
+        lines
+            .into_iter()
+            .map(|line| console::truncate_str(&line, width, "…").to_string())
+            .collect()
+    }
+}
+
+/// The dependency tree from the dependencies of package.json, packages shown already are
+/// marked as deduped instead of being expanded again.
+pub fn tree_items(graph: &LockGraph, roots: &[(String, String)]) -> Vec<Item> {
+    fn visit(
+        graph: &LockGraph,
+        node: usize,
+        depth: usize,
+        root: &str,
+        seen: &mut HashSet<usize>,
+        items: &mut Vec<Item>,
+    ) {
+        let package = graph.node(node);
+        let expanded = seen.insert(node);
+
+        items.push(Item {
+            package: Some(root.to_string()),
+            line: format!(
+                "{}{} {}{}",
+                "  ".repeat(depth),
+                if depth == 0 {
+                    package.name.bold().to_string()
+                } else {
+                    package.name.clone()
+                },
+                package.version.dimmed(),
+                if expanded {
+                    String::new()
+                } else {
+                    " (deduped)".dimmed().to_string()
+                }
+            ),
+        });
+
+        if expanded {
+            for (dependency, _) in graph.dependencies(node) {
+                visit(graph, *dependency, depth + 1, root, seen, items);
+            }
+        }
+    }
+
+    let mut items = vec![];
+    let mut seen = HashSet::new();
+
+    for (name, range) in roots {
+        match graph.resolve(name, range) {
+            Some(node) => visit(graph, node, 0, name, &mut seen, &mut items),
+            None => items.push(Item {
+                package: Some(name.clone()),
+                line: format!("{} {}", name.bold(), "not installed".yellow()),
+            }),
+        }
+    }
+
+    items
+}
+
+pub fn outdated_items(packages: &[OutdatedPackage]) -> Vec<Item> {
+    packages
+        .iter()
+        .map(|package| Item {
+            package: Some(package.name.clone()),
+            line: format!(
+                "{:<32} {:>12} → {:<12} {}",
+                package.name,
+                package.current.as_deref().unwrap_or("missing").red(),
+                package.latest.green(),
+                package.kind.dimmed()
+            ),
+        })
+        .collect()
+}
+
+pub fn vulnerable_items(vulnerabilities: &[Vulnerability]) -> Vec<Item> {
+    vulnerabilities
+        .iter()
+        .map(|vulnerability| {
+            let severity = match vulnerability.severity.as_str() {
+                "critical" => vulnerability.severity.bright_red().bold(),
+                "high" => vulnerability.severity.bright_red(),
+                "moderate" => vulnerability.severity.yellow(),
+                _ => vulnerability.severity.normal(),
+            };
+
+            Item {
+                package: Some(vulnerability.name.clone()),
+                line: format!(
+                    "{:<8} {} {} {}",
+                    severity,
+                    vulnerability.name.bold(),
+                    vulnerability.installed.join(", ").dimmed(),
+                    vulnerability.title
+                ),
+            }
+        })
+        .collect()
+}
+
+/// Packages of node_modules by size, largest first.
+pub fn disk_items(mut sizes: Vec<(String, u64)>) -> Vec<Item> {
+    sizes.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    let total: u64 = sizes.iter().map(|(_, size)| size).sum();
+
+    sizes
+        .into_iter()
+        .map(|(name, size)| Item {
+            line: format!(
+                "{:>10} {:>5.1}%  {}",
+                human_size(size),
+                if total == 0 {
+                    0.0
+                } else {
+                    size as f64 * 100.0 / total as f64
+                },
+                name
+            ),
+            package: Some(name),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use volt_core::model::lock_file::LockFile;
+
+    fn item(package: &str) -> Item {
+        Item {
+            package: Some(package.to_string()),
+            line: package.to_string(),
+        }
+    }
+
+    #[test]
+    fn scrolls_with_the_cursor() {
+        let mut section = Section::new("Tree", (0..10).map(|i| item(&i.to_string())).collect());
+
+        section.select(5, 3);
+        assert_eq!((section.cursor, section.offset), (5, 3));
+
+        section.select(100, 3);
+        assert_eq!((section.cursor, section.offset), (9, 7));
+
+        section.select(-8, 3);
+        assert_eq!((section.cursor, section.offset), (1, 1));
+        assert_eq!(section.selected(), Some("1"));
+    }
+
+    #[test]
+    fn builds_the_tree_with_deduped_packages() {
+        colored::control::set_override(false);
+
+        let contents = serde_json::json!({
+            "a@^1.0.0": { "name": "a", "version": "1.0.0", "tarball": "", "sha1": "", "dependencies": { "c": "^1.0.0" } },
+            "b@^1.0.0": { "name": "b", "version": "1.0.0", "tarball": "", "sha1": "", "dependencies": { "c": "^1.0.0" } },
+            "c@^1.0.0": { "name": "c", "version": "1.2.0", "tarball": "", "sha1": "", "dependencies": {} }
+        });
+
+        let lock_file = LockFile::parse(PathBuf::from("volt.lock"), &contents.to_string()).unwrap();
+        let graph = LockGraph::new(&lock_file);
+
+        let roots = vec![
+            (String::from("a"), String::from("^1.0.0")),
+            (String::from("b"), String::from("^1.0.0")),
+            (String::from("d"), String::from("^1.0.0")),
+        ];
+
+        let lines: Vec<String> = tree_items(&graph, &roots)
+            .into_iter()
+            .map(|item| item.line)
+            .collect();
+
+        assert_eq!(
+            lines,
+            vec![
+                "a 1.0.0",
+                "  c 1.2.0",
+                "b 1.0.0",
+                "  c 1.2.0 (deduped)",
+                "d not installed"
+            ]
+        );
+    }
+}
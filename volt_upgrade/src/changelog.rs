@@ -0,0 +1,321 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Release notes between two versions of a package.
+//!
+//! The GitHub releases of the repository of the package are used when there are some, the
+//! `CHANGELOG.md` (or `HISTORY.md`, `CHANGES.md`) inside the tarball of the target version
+//! otherwise. Set `GITHUB_TOKEN` to avoid the rate limit of anonymous GitHub requests.
+
+use std::io::Read;
+
+use anyhow::{anyhow, Context, Result};
+use flate2::read::GzDecoder;
+use node_semver::Version;
+use serde_json::Value;
+use tar::Archive;
+
+/// Files looked for in tarballs, compared without case.
+const CHANGELOG_FILES: [&str; 4] = [
+    "package/changelog.md",
+    "package/history.md",
+    "package/changes.md",
+    "package/changelog",
+];
+
+/// Notes of one version.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Release {
+    pub version: String,
+    pub notes: String,
+}
+
+/// Where the notes were found.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Changelog {
+    pub source: String,
+    /// Newest first
+    pub releases: Vec<Release>,
+}
+
+/// The first `x.y.z` version in `text`, with its prerelease (`v2.0.0-rc.1` → `2.0.0-rc.1`).
+pub fn find_version(text: &str) -> Option<Version> {
+    let bytes = text.as_bytes();
+
+    for (start, _) in text.match_indices(|c: char| c.is_ascii_digit()) {
+        // Only at the start of a number
+        if start > 0 && (bytes[start - 1].is_ascii_digit() || bytes[start - 1] == b'.') {
+            continue;
+        }
+
+        let end = text[start..]
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '.' || c == '-' || c == '+'))
+            .map_or(text.len(), |end| start + end);
+
+        let candidate = text[start..end].trim_end_matches(['.', '-', '+']);
+
+        if let Ok(version) = Version::parse(candidate) {
+            return Some(version);
+        }
+    }
+
+    None
+}
+
+/// Split a markdown changelog into the sections under headings which name a version.
+pub fn parse_sections(changelog: &str) -> Vec<Release> {
+    let mut releases: Vec<Release> = vec![];
+    let mut current: Option<(Version, Vec<&str>)> = None;
+
+    for line in changelog.lines() {
+        let heading = line.trim_start().starts_with('#');
+
+        if let Some(version) = heading.then(|| find_version(line)).flatten() {
+            if let Some((version, lines)) = current.take() {
+                releases.push(Release {
+                    version: version.to_string(),
+                    notes: lines.join("\n").trim().to_string(),
+                });
+            }
+
+            current = Some((version, vec![]));
+        } else if let Some((_, lines)) = current.as_mut() {
+            lines.push(line);
+        }
+    }
+
+    if let Some((version, lines)) = current {
+        releases.push(Release {
+            version: version.to_string(),
+            notes: lines.join("\n").trim().to_string(),
+        });
+    }
+
+    releases
+}
+
+/// Keep the releases after `current` up to `target` included, newest first.
+pub fn between(releases: Vec<Release>, current: &str, target: &str) -> Vec<Release> {
+    let (current, target) = match (Version::parse(current), Version::parse(target)) {
+        (Ok(current), Ok(target)) => (current, target),
+        _ => return vec![],
+    };
+
+    let mut releases: Vec<(Version, Release)> = releases
+        .into_iter()
+        .filter_map(|release| Some((Version::parse(&release.version).ok()?, release)))
+        .filter(|(version, _)| *version > current && *version <= target)
+        .collect();
+
+    releases.sort_by(|(a, _), (b, _)| b.cmp(a));
+    releases.dedup_by(|(a, _), (b, _)| a == b);
+
+    releases.into_iter().map(|(_, release)| release).collect()
+}
+
+/// `owner/repo` of a GitHub repository url.
+pub fn github_repository(url: &str) -> Option<String> {
+    let (_, path) = url.split_once("github.com")?;
+    let mut parts = path
+        .trim_start_matches(['/', ':'])
+        .split('/')
+        .filter(|part| !part.is_empty());
+
+    let owner = parts.next()?;
+    let repo = parts.next()?.trim_end_matches(".git");
+
+    Some(format!("{}/{}", owner, repo))
+}
+
+async fn github_releases(repository: &str, name: &str) -> Result<Vec<Release>> {
+    let mut request = reqwest::Client::new()
+        .get(format!(
+            "https://api.github.com/repos/{}/releases?per_page=100",
+            repository
+        ))
+        .header("User-Agent", "volt")
+        .header("Accept", "application/vnd.github+json");
+
+    if let Ok(token) = std::env::var("GITHUB_TOKEN") {
+        request = request.bearer_auth(token);
+    }
+
+    let body: Value = serde_json::from_str(
+        &request
+            .send()
+            .await?
+            .error_for_status()
+            .with_context(|| format!("failed to get the releases of {}", repository))?
+            .text()
+            .await?,
+    )?;
+
+    Ok(body
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|release| release["draft"].as_bool() != Some(true))
+        .filter_map(|release| {
+            // Tags are `v1.2.3`, `1.2.3` or `name@1.2.3` in monorepos
+            let tag = release["tag_name"].as_str()?;
+
+            match tag.rsplit_once('@') {
+                Some((package, _)) if !package.is_empty() && package != name => return None,
+                _ => {}
+            }
+
+            let version = find_version(tag)?;
+
+            Some(Release {
+                version: version.to_string(),
+                notes: release["body"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .trim()
+                    .to_string(),
+            })
+        })
+        .collect())
+}
+
+async fn tarball_changelog(tarball: &str) -> Result<String> {
+    let bytes = reqwest::get(tarball)
+        .await?
+        .error_for_status()?
+        .bytes()
+        .await?;
+
+    let mut archive = Archive::new(GzDecoder::new(&*bytes));
+
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.to_string_lossy().to_lowercase();
+
+        if CHANGELOG_FILES.contains(&path.as_str()) {
+            let mut changelog = String::new();
+            entry.read_to_string(&mut changelog)?;
+            return Ok(changelog);
+        }
+    }
+
+    Err(anyhow!("no changelog in {}", tarball))
+}
+
+/// Get the release notes of `name` after `current` up to `target` from `packument`.
+///
+/// GitHub releases of packages published from monorepos are filtered by tag, so those of
+/// sibling packages don't show up.
+pub async fn changelog(
+    name: &str,
+    packument: &Value,
+    current: &str,
+    target: &str,
+) -> Result<Changelog> {
+    let manifest = &packument["versions"][target];
+
+    let repository = manifest["repository"]
+        .as_str()
+        .or_else(|| manifest["repository"]["url"].as_str())
+        .or_else(|| packument["repository"]["url"].as_str())
+        .or_else(|| packument["repository"].as_str())
+        .and_then(github_repository);
+
+    if let Some(repository) = repository {
+        if let Ok(releases) = github_releases(&repository, name).await {
+            let releases = between(releases, current, target);
+
+            if !releases.is_empty() {
+                return Ok(Changelog {
+                    source: format!("https://github.com/{}/releases", repository),
+                    releases,
+                });
+            }
+        }
+    }
+
+    let tarball = manifest["dist"]["tarball"]
+        .as_str()
+        .ok_or_else(|| anyhow!("{}@{} has no tarball", name, target))?;
+
+    let releases = between(
+        parse_sections(&tarball_changelog(tarball).await?),
+        current,
+        target,
+    );
+
+    Ok(Changelog {
+        source: format!("CHANGELOG.md of {}@{}", name, target),
+        releases,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHANGELOG: &str = r#"# Changelog
+
+## [2.1.0](https://github.com/acme/lib/compare/v2.0.0...v2.1.0) (2023-03-01)
+
+### Features
+
+* add `retry` option
+
+## 2.0.0 - 2023-01-10
+
+- **Breaking:** drop node 12
+
+## v1.9.3
+
+- fix a crash
+
+## 1.9.2
+"#;
+
+    #[test]
+    fn keeps_sections_between_versions() {
+        let releases = between(parse_sections(CHANGELOG), "1.9.2", "2.1.0");
+        let versions: Vec<&str> = releases
+            .iter()
+            .map(|release| release.version.as_str())
+            .collect();
+
+        assert_eq!(versions, vec!["2.1.0", "2.0.0", "1.9.3"]);
+        assert_eq!(releases[1].notes, "- **Breaking:** drop node 12");
+        assert!(releases[0].notes.contains("add `retry` option"));
+    }
+
+    #[test]
+    fn finds_versions_in_tags_and_repositories() {
+        assert_eq!(
+            find_version("@babel/core@7.22.5").map(|version| version.to_string()),
+            Some(String::from("7.22.5"))
+        );
+        assert_eq!(
+            find_version("v3.0.0-rc.1").map(|version| version.to_string()),
+            Some(String::from("3.0.0-rc.1"))
+        );
+        assert_eq!(find_version("Unreleased"), None);
+        assert_eq!(
+            github_repository("git+https://github.com/facebook/react.git").as_deref(),
+            Some("facebook/react")
+        );
+        assert_eq!(
+            github_repository("git@github.com:facebook/react.git").as_deref(),
+            Some("facebook/react")
+        );
+    }
+}
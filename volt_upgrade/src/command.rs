@@ -14,14 +14,119 @@
     limitations under the License.
 */
 
+//! Upgrade dependencies to their latest versions, showing what changed in between.
+
+use std::io::IsTerminal;
 use std::sync::Arc;
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use async_trait::async_trait;
 use colored::Colorize;
-use volt_core::command::Command;
-use volt_core::VERSION;
+use serde_json::Value;
+use volt_core::prompt::prompts::Confirm;
+use volt_core::{command::Command, help::Help};
+use volt_outdated::command::{outdated, OutdatedPackage};
 use volt_utils::app::App;
+use volt_utils::registry_mirrors;
+use volt_utils::reporter;
+
+use crate::changelog::{changelog, Changelog};
+
+/// Lines of release notes shown per package without `--full-changelog`.
+const CHANGELOG_LINES: usize = 40;
+
+/// Get the full packument of `name`, the abbreviated one has no repository.
+async fn packument(registry: &str, name: &str) -> Result<Value> {
+    let response = reqwest::get(format!("{}/{}", registry, name.replace('/', "%2f")))
+        .await?
+        .error_for_status()
+        .with_context(|| format!("failed to fetch {}", name))?
+        .text()
+        .await?;
+
+    Ok(serde_json::from_str(&response)?)
+}
+
+fn print_changelog(changelog: &Changelog, full: bool) {
+    if changelog.releases.is_empty() {
+        reporter::get().output(&format!(
+            "  No release notes found in {}\n",
+            changelog.source
+        ));
+        return;
+    }
+
+    let lines: Vec<String> = changelog
+        .releases
+        .iter()
+        .flat_map(|release| {
+            std::iter::once(format!("{}", release.version.bright_cyan().bold()))
+                .chain(release.notes.lines().map(String::from))
+                .chain(std::iter::once(String::new()))
+        })
+        .collect();
+
+    let shown = if full {
+        lines.len()
+    } else {
+        lines.len().min(CHANGELOG_LINES)
+    };
+
+    for line in lines[..shown].iter() {
+        reporter::get().output(&format!("  {}", line));
+    }
+
+    if shown < lines.len() {
+        reporter::get().output(&format!(
+            "  {} more lines in {}, or use --full-changelog\n",
+            lines.len() - shown,
+            changelog.source
+        ));
+    } else {
+        reporter::get().output(&format!("  From {}\n", changelog.source.dimmed()));
+    }
+}
+
+/// Show what changed in `package` up to its latest version, and ask whether to upgrade it.
+async fn propose(app: &App, registry: &str, package: &OutdatedPackage) -> Result<bool> {
+    let current = package.current.as_deref().unwrap_or("missing");
+
+    reporter::get().output(&format!(
+        "{} {} → {}",
+        package.name.bright_cyan().bold(),
+        current.red(),
+        package.latest.green()
+    ));
+
+    if !app.has_flag(&["--no-changelog"]) && package.current.is_some() {
+        match packument(registry, &package.name).await {
+            Ok(packument) => {
+                match changelog(&package.name, &packument, current, &package.latest).await {
+                    Ok(changelog) => {
+                        print_changelog(&changelog, app.has_flag(&["--full-changelog"]))
+                    }
+                    Err(e) => reporter::get().output(&format!("  No release notes: {}\n", e)),
+                }
+            }
+            Err(e) => reporter::get().output(&format!("  No release notes: {}\n", e)),
+        }
+    }
+
+    if app.has_flag(&["--yes", "-y"]) {
+        return Ok(true);
+    }
+
+    // Only listed when nobody can answer
+    if !std::io::stdin().is_terminal() {
+        return Ok(false);
+    }
+
+    Ok(Confirm {
+        message: format!("Upgrade {} to {}", package.name, package.latest),
+        default: true,
+    }
+    .run()?)
+}
 
 /// Struct implementation for the `Upgrade` command.
 pub struct Upgrade;
@@ -29,24 +134,17 @@ pub struct Upgrade;
 #[async_trait]
 impl Command for Upgrade {
     /// Display a help menu for the `volt upgrade` command.
-    fn help() -> String {
-        format!(
-            r#"volt {}
-    
-Upgrade project dependencies
-
-Usage: {} {} {}
-    
-Options:
-    
-  {} {} Output verbose messages on internal operations."#,
-            VERSION.bright_green().bold(),
-            "volt".bright_green().bold(),
-            "upgrade".bright_purple(),
-            "file-name".white(),
-            "--verbose".blue(),
-            "(-v)".yellow()
+    fn help() -> Help {
+        Help::new(
+            "upgrade",
+            "Upgrade project dependencies to their latest versions, showing the release notes in between.",
         )
+        .usage("[packages] [flags]")
+        .arg("[packages]", "Packages to upgrade, all dependencies by default.")
+        .flag(&["--yes", "-y"], "Upgrade without asking.")
+        .flag(&["--no-changelog"], "Don't fetch release notes.")
+        .flag(&["--full-changelog"], "Show the release notes without truncating them.")
+        .verbose()
     }
 
     /// Execute the `volt upgrade` command
@@ -54,17 +152,48 @@ Options:
     /// Upgrade project dependencies
     /// ## Arguments
     /// * `app` - Instance of the command (`Arc<App>`)
-    /// * `packages` - List of packages to add (`Vec<String>`)
-    /// * `flags` - List of flags passed in through the CLI (`Vec<String>`)
     /// ## Examples
-    /// ```
+    /// ```ignore
     /// // Upgrade project dependencies
     /// // .exec() is an async call so you need to await it
-    /// Upgrade.exec(app, vec![], vec!["--yes"]).await;
+    /// Upgrade.exec(app).await;
     /// ```
     /// ## Returns
     /// * `Result<()>`
-    async fn exec(_app: Arc<App>) -> Result<()> {
-        Ok(())
+    async fn exec(app: Arc<App>) -> Result<()> {
+        let names = &app.args[1..];
+        let registry = registry_mirrors::url(&app).await;
+
+        let candidates: Vec<OutdatedPackage> = outdated(&app)
+            .await?
+            .into_iter()
+            .filter(|package| package.current.as_deref() != Some(package.latest.as_str()))
+            .filter(|package| names.is_empty() || names.contains(&package.name))
+            .collect();
+
+        if candidates.is_empty() {
+            reporter::get().output("All dependencies are at their latest versions.");
+            return Ok(());
+        }
+
+        let mut accepted = vec![];
+
+        for package in candidates.iter() {
+            if propose(&app, &registry, package).await? {
+                accepted.push(format!("{}@{}", package.name, package.latest));
+            }
+        }
+
+        if accepted.is_empty() {
+            return Ok(());
+        }
+
+        let add = app.with_args(
+            std::iter::once(String::from("add"))
+                .chain(accepted)
+                .collect(),
+        );
+
+        volt_add::command::Add::exec(Arc::new(add)).await
     }
 }
@@ -1 +1,2 @@
+pub mod changelog;
 pub mod command;
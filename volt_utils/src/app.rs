@@ -1,26 +1,58 @@
-use crate::enable_ansi_support;
-use anyhow::Result;
+use crate::{config::Config, enable_ansi_support, paths::Paths, ERROR_TAG};
+use anyhow::{anyhow, Context, Result};
+use colored::Colorize;
 use dirs::home_dir;
 use sha1::{Digest, Sha1};
-use std::{env, io, path::PathBuf};
+use std::{
+    env, io,
+    path::{Path, PathBuf},
+};
+
+/// Flags whose value can also be passed as the next argument.
+const VALUE_FLAGS: [&str; 8] = [
+    "--error-format",
+    "--format",
+    "--cwd",
+    "--prefix",
+    "--registry",
+    "--timeout",
+    "--package",
+    "-p",
+];
+
+/// Commands running a program, every argument after the program is its own, `volt x tsc -v`.
+const PROGRAM_COMMANDS: [&str; 2] = ["x", "exec"];
 
 #[derive(Debug)]
 pub struct App {
     pub current_dir: PathBuf,
     pub home_dir: PathBuf,
     pub node_modules_dir: PathBuf,
+    /// The store of extracted packages
     pub volt_dir: PathBuf,
+    /// Metadata and downloads which can be thrown away
+    pub cache_dir: PathBuf,
+    /// The user `.voltrc`
+    pub config_file: PathBuf,
     pub lock_file_path: PathBuf,
     pub args: Vec<String>,
     pub flags: Vec<String>,
+    /// Settings from the user and project `.voltrc`
+    pub config: Config,
 }
 
 impl App {
     pub fn initialize() -> Self {
         enable_ansi_support().unwrap();
 
-        // Current Directory
-        let current_directory = env::current_dir().unwrap();
+        let (refined_args, flags) = parse_args(std::env::args().skip(1));
+
+        // Current Directory, the process keeps the one it was started from
+        let current_directory =
+            project_dir(&env::current_dir().unwrap(), &flags).unwrap_or_else(|error| {
+                eprintln!("{} {}", ERROR_TAG.clone(), error);
+                std::process::exit(1);
+            });
 
         // Home Directory: /username or C:\Users\username
         let home_directory = home_dir().unwrap_or_else(|| current_directory.clone());
@@ -28,37 +60,46 @@ impl App {
         // node_modules/
         let node_modules_directory = current_directory.join("node_modules");
 
-        // Volt Global Directory: /username/.volt or C:\Users\username\.volt
-        let volt_dir = home_directory.join(".volt");
+        // Platform directories, see `paths` for where they are
+        let paths = Paths::resolve(&home_directory);
 
         // Create volt directory if it doesn't exist
-        std::fs::create_dir_all(&volt_dir).ok();
+        std::fs::create_dir_all(&paths.store).ok();
 
         // ./volt.lock
         let lock_file_path = current_directory.join("volt.lock");
 
-        let cli_args: Vec<_> = std::env::args().collect();
-
-        let mut refined_args: Vec<String> = Vec::new();
+        let mut config = Config::load(&paths.config_file, &current_directory);
 
-        let mut flags: Vec<String> = Vec::new();
+        if let Some(profile) = config
+            .profile()
+            .filter(|profile| !config.profiles().contains_key(*profile))
+        {
+            eprintln!(
+                "{} the profile {} is not defined in {}",
+                "warn".yellow(),
+                profile,
+                paths.config_file.display()
+            );
+        }
 
-        for arg in cli_args.into_iter().skip(1) {
-            if arg.starts_with("--") || arg.starts_with('-') {
-                flags.push(arg);
-            } else {
-                refined_args.push(arg);
-            }
+        let global = flags.iter().any(|flag| flag == "--global" || flag == "-g");
+        if let Some(prefix) = value_of(&flags, &["--prefix"]).filter(|_| global) {
+            let prefix = current_directory.join(prefix);
+            config.set("global-dir", &prefix.to_string_lossy());
         }
 
         App {
             current_dir: current_directory,
             home_dir: home_directory,
             node_modules_dir: node_modules_directory,
-            volt_dir,
+            volt_dir: paths.store,
+            cache_dir: paths.cache,
+            config_file: paths.config_file,
             lock_file_path,
             args: refined_args,
             flags,
+            config,
         }
     }
 
@@ -69,6 +110,39 @@ impl App {
             .any(|flag| flags.iter().any(|search_flag| flag == search_flag))
     }
 
+    /// Get the value of a flag passed as `--flag=value`
+    pub fn flag_value(&self, flags: &[&str]) -> Option<String> {
+        value_of(&self.flags, flags)
+    }
+
+    /// Get every value of a flag which can be repeated, `-p a -p b`
+    pub fn flag_values(&self, flags: &[&str]) -> Vec<String> {
+        self.flags
+            .iter()
+            .filter_map(|flag| {
+                let (name, value) = flag.split_once('=')?;
+
+                flags.contains(&name).then(|| value.to_string())
+            })
+            .collect()
+    }
+
+    /// A copy of the app running other arguments, to run a command from another one.
+    pub fn with_args(&self, args: Vec<String>) -> App {
+        App {
+            current_dir: self.current_dir.clone(),
+            home_dir: self.home_dir.clone(),
+            node_modules_dir: self.node_modules_dir.clone(),
+            volt_dir: self.volt_dir.clone(),
+            cache_dir: self.cache_dir.clone(),
+            config_file: self.config_file.clone(),
+            lock_file_path: self.lock_file_path.clone(),
+            args,
+            flags: self.flags.clone(),
+            config: self.config.clone(),
+        }
+    }
+
     pub fn calc_hash(data: &bytes::Bytes) -> Result<String> {
         let mut hasher = Sha1::new();
         io::copy(&mut &**data, &mut hasher)?;
@@ -76,3 +150,165 @@ impl App {
         Ok(format!("{:x}", hasher.finalize()))
     }
 }
+
+/// Split command line arguments into arguments and flags.
+pub fn parse_args(mut cli_args: impl Iterator<Item = String>) -> (Vec<String>, Vec<String>) {
+    let mut refined_args: Vec<String> = Vec::new();
+
+    let mut flags: Vec<String> = Vec::new();
+
+    while let Some(arg) = cli_args.next() {
+        let program =
+            refined_args.len() >= 2 && PROGRAM_COMMANDS.contains(&refined_args[0].as_str());
+
+        if program {
+            refined_args.push(arg);
+        } else if VALUE_FLAGS.contains(&arg.as_str()) {
+            // `--error-format json` is read as `--error-format=json`
+            match cli_args.next() {
+                Some(value) => flags.push(format!("{}={}", arg, value)),
+                None => flags.push(arg),
+            }
+        } else if arg.starts_with("--") || arg.starts_with('-') {
+            flags.push(arg);
+        } else {
+            refined_args.push(arg);
+        }
+    }
+
+    (refined_args, flags)
+}
+
+/// The directory volt runs in from `started_from`.
+///
+/// `--cwd` runs volt as if started from another directory, so does `--prefix` for projects,
+/// global installs go to the prefix instead.
+fn project_dir(started_from: &Path, flags: &[String]) -> Result<PathBuf> {
+    let global = flags.iter().any(|flag| flag == "--global" || flag == "-g");
+    let directory =
+        value_of(flags, &["--cwd"]).or_else(|| value_of(flags, &["--prefix"]).filter(|_| !global));
+
+    let directory = match directory {
+        Some(directory) => started_from.join(directory),
+        None => return Ok(started_from.to_path_buf()),
+    };
+
+    let directory = directory
+        .canonicalize()
+        .with_context(|| format!("cannot run in {}", directory.display()))?;
+
+    if !directory.is_dir() {
+        return Err(anyhow!(
+            "cannot run in {}: not a directory",
+            directory.display()
+        ));
+    }
+
+    Ok(directory)
+}
+
+/// Get the value of one of `names` passed as `--flag=value`.
+fn value_of(flags: &[String], names: &[&str]) -> Option<String> {
+    flags.iter().find_map(|flag| {
+        let (name, value) = flag.split_once('=')?;
+
+        if names.contains(&name) {
+            Some(value.to_string())
+        } else {
+            None
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_values_of_directory_flags() {
+        let args = [
+            "add",
+            "--cwd",
+            "packages/web",
+            "react",
+            "--prefix=/opt/tools",
+            "-D",
+            "-w",
+        ];
+        let (args, flags) = parse_args(args.iter().map(|arg| arg.to_string()));
+
+        assert_eq!(args, vec!["add", "react"]);
+        assert!(flags.contains(&String::from("-w")));
+        assert_eq!(
+            value_of(&flags, &["--cwd"]).as_deref(),
+            Some("packages/web")
+        );
+        assert_eq!(
+            value_of(&flags, &["--prefix"]).as_deref(),
+            Some("/opt/tools")
+        );
+        assert_eq!(value_of(&flags, &["--registry"]), None);
+
+        let args = ["whoami", "--registry", "https://registry.example.com/"];
+        let (args, flags) = parse_args(args.iter().map(|arg| arg.to_string()));
+
+        assert_eq!(args, vec!["whoami"]);
+        assert_eq!(
+            value_of(&flags, &["--registry"]).as_deref(),
+            Some("https://registry.example.com/")
+        );
+    }
+
+    #[test]
+    fn reads_flags_before_the_command() {
+        let parse = |args: &[&str]| parse_args(args.iter().map(|arg| arg.to_string()));
+
+        let (args, flags) = parse(&["--cwd", "packages/web", "install", "--production"]);
+        assert_eq!(args, vec!["install"]);
+        assert_eq!(flags, vec!["--cwd=packages/web", "--production"]);
+
+        // The arguments of the program `volt x` runs are its own
+        let (args, flags) = parse(&[
+            "--cwd=web",
+            "x",
+            "-p",
+            "typescript",
+            "tsc",
+            "--version",
+            "-p",
+            "src",
+        ]);
+        assert_eq!(args, vec!["x", "tsc", "--version", "-p", "src"]);
+        assert_eq!(flags, vec!["--cwd=web", "-p=typescript"]);
+    }
+
+    #[test]
+    fn resolves_the_project_dir_without_changing_the_process_dir() {
+        let root = env::temp_dir().join(format!("volt-app-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("packages/web")).unwrap();
+        let root = root.canonicalize().unwrap();
+        let started_in = env::current_dir().unwrap();
+
+        let flags =
+            |flags: &[&str]| -> Vec<String> { flags.iter().map(|flag| flag.to_string()).collect() };
+
+        assert_eq!(project_dir(&root, &[]).unwrap(), root);
+        assert_eq!(
+            project_dir(&root, &flags(&["--cwd=packages/web"])).unwrap(),
+            root.join("packages/web")
+        );
+        assert_eq!(
+            project_dir(&root, &flags(&["--prefix=packages"])).unwrap(),
+            root.join("packages")
+        );
+        // Global installs go to the prefix, not the project
+        assert_eq!(
+            project_dir(&root, &flags(&["--prefix=packages", "-g"])).unwrap(),
+            root
+        );
+        assert!(project_dir(&root, &flags(&["--cwd=missing"])).is_err());
+        assert_eq!(env::current_dir().unwrap(), started_in);
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,158 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Which package provides each binary of an install.
+//!
+//! When installed packages declare the same binary name, the one package.json depends on
+//! directly wins, then the first by installed name, and the others are reported as conflicts
+//! instead of the last one written silently replacing the others. With `strict-bins=true` in
+//! `.voltrc`, conflicts fail the install instead.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::app::App;
+use crate::volt_api::VoltPackage;
+
+/// A binary name declared by several installed packages.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Conflict {
+    pub bin: String,
+    /// The package providing the binary, as [`describe`] shows it
+    pub chosen: String,
+    pub others: Vec<String>,
+}
+
+impl Conflict {
+    pub fn describe(&self) -> String {
+        format!(
+            "{} is provided by {}, not by {}",
+            self.bin,
+            self.chosen,
+            self.others.join(", ")
+        )
+    }
+}
+
+/// The binaries to link, by name, and the names several packages declare.
+#[derive(Debug, Default)]
+pub struct Plan {
+    /// The installed name of the package providing each binary
+    pub bins: BTreeMap<String, String>,
+    pub conflicts: Vec<Conflict>,
+}
+
+/// `name@version`, or `alias (npm:name@version)` for a package installed under another name.
+pub fn describe(installed: &str, package: &VoltPackage) -> String {
+    if installed == package.name {
+        format!("{}@{}", package.name, package.version)
+    } else {
+        format!("{} (npm:{}@{})", installed, package.name, package.version)
+    }
+}
+
+/// Choose the package providing each binary of `packages`, by installed name, preferring the
+/// `direct` dependencies of package.json.
+pub fn plan(packages: &HashMap<String, VoltPackage>, direct: &HashSet<String>) -> Plan {
+    let mut providers: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+
+    for (installed, package) in packages.iter() {
+        for bin in package.bin.iter().flatten().map(|(bin, _)| bin) {
+            providers.entry(bin).or_default().push(installed);
+        }
+    }
+
+    let mut plan = Plan::default();
+
+    for (bin, mut installed) in providers {
+        installed.sort_by_key(|name| (!direct.contains(*name), *name));
+
+        let (chosen, others) = installed.split_first().unwrap();
+
+        if !others.is_empty() {
+            plan.conflicts.push(Conflict {
+                bin: bin.to_string(),
+                chosen: describe(chosen, &packages[*chosen]),
+                others: others
+                    .iter()
+                    .map(|name| describe(name, &packages[*name]))
+                    .collect(),
+            });
+        }
+
+        plan.bins.insert(bin.to_string(), chosen.to_string());
+    }
+
+    plan
+}
+
+/// Whether conflicting binaries fail the install, with `strict-bins=true` in `.voltrc`.
+pub fn is_strict(app: &App) -> bool {
+    app.config.get_bool("strict-bins") == Some(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn package(name: &str, bins: &[&str]) -> VoltPackage {
+        VoltPackage {
+            name: name.to_string(),
+            version: String::from("1.0.0"),
+            tarball: String::new(),
+            sha1: String::new(),
+            integrity: None,
+            peer_dependencies: vec![],
+            dependencies: None,
+            optional_dependencies: vec![],
+            bin: Some(
+                bins.iter()
+                    .map(|bin| (bin.to_string(), String::from("cli.js")))
+                    .collect(),
+            ),
+            os: vec![],
+            cpu: vec![],
+            libc: vec![],
+            source: Default::default(),
+            deprecated: None,
+            engines: Default::default(),
+        }
+    }
+
+    #[test]
+    fn prefers_direct_dependencies() {
+        let mut packages = HashMap::new();
+        packages.insert(String::from("a-tool"), package("a-tool", &["tool"]));
+        packages.insert(String::from("tool"), package("tool", &["tool", "tool-x"]));
+        packages.insert(String::from("my-tool"), package("b-tool", &["tool"]));
+
+        let direct = [String::from("tool")].iter().cloned().collect();
+        let plan = plan(&packages, &direct);
+
+        assert_eq!(plan.bins["tool"], "tool");
+        assert_eq!(plan.bins["tool-x"], "tool");
+        assert_eq!(
+            plan.conflicts,
+            vec![Conflict {
+                bin: String::from("tool"),
+                chosen: String::from("tool@1.0.0"),
+                others: vec![
+                    String::from("a-tool@1.0.0"),
+                    String::from("my-tool (npm:b-tool@1.0.0)"),
+                ],
+            }]
+        );
+    }
+}
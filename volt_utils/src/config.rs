@@ -0,0 +1,270 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Volt settings read from `.voltrc` files.
+//!
+//! The files use the `.npmrc` syntax (`key=value` lines, `#` and `;` comments) and the same key
+//! names, so settings carry over from npm unchanged. The project `.voltrc` overrides the user one
+//! (see [paths](crate::paths) for where it is).
+//!
+//! The user config can hold named profiles, `profile.<name>.<key>=<value>` lines such as
+//! `profile.work.registry=https://npm.corp.example`. The profile chosen with `VOLT_PROFILE` or
+//! `volt config use <name>` (which writes `profile=<name>`) overrides the other user settings.
+
+use std::collections::BTreeMap;
+use std::fs::{read_to_string, write};
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+
+pub const CONFIG_FILE: &str = ".voltrc";
+
+/// Environment variable choosing the profile, over the `profile` setting.
+pub const PROFILE_ENV: &str = "VOLT_PROFILE";
+
+const PROFILE_PREFIX: &str = "profile.";
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Config {
+    values: BTreeMap<String, String>,
+    /// File each value was read from
+    sources: BTreeMap<String, PathBuf>,
+    /// Profile name → settings of the profile
+    profiles: BTreeMap<String, BTreeMap<String, String>>,
+    profile: Option<String>,
+}
+
+/// Split `profile.<name>.<key>` into the profile name and the key.
+fn profile_key(key: &str) -> Option<(&str, &str)> {
+    key.strip_prefix(PROFILE_PREFIX)?.split_once('.')
+}
+
+impl Config {
+    /// Load the user config from `user_file` and the project config from `project_dir`.
+    pub fn load(user_file: &Path, project_dir: &Path) -> Self {
+        let mut config = Config::default();
+
+        if let Ok(source) = read_to_string(user_file) {
+            for (key, value) in parse(&source) {
+                match profile_key(&key) {
+                    Some((profile, key)) => {
+                        config
+                            .profiles
+                            .entry(profile.to_string())
+                            .or_default()
+                            .insert(key.to_string(), value);
+                    }
+                    None => {
+                        config.sources.insert(key.clone(), user_file.to_path_buf());
+                        config.values.insert(key, value);
+                    }
+                }
+            }
+        }
+
+        config.profile = std::env::var(PROFILE_ENV)
+            .ok()
+            .filter(|profile| !profile.is_empty())
+            .or_else(|| config.values.get("profile").cloned());
+
+        if let Some(values) = config
+            .profile
+            .as_ref()
+            .and_then(|profile| config.profiles.get(profile))
+        {
+            for (key, value) in values.clone() {
+                config.sources.insert(key.clone(), user_file.to_path_buf());
+                config.values.insert(key, value);
+            }
+        }
+
+        let project_file = project_dir.join(CONFIG_FILE);
+
+        if let Ok(source) = read_to_string(&project_file) {
+            for (key, value) in parse(&source) {
+                config.sources.insert(key.clone(), project_file.clone());
+                config.values.insert(key, value);
+            }
+        }
+
+        config
+    }
+
+    /// The profile in use, whether the user config defines it or not.
+    pub fn profile(&self) -> Option<&str> {
+        self.profile.as_deref()
+    }
+
+    /// The profiles of the user config, with their settings.
+    pub fn profiles(&self) -> &BTreeMap<String, BTreeMap<String, String>> {
+        &self.profiles
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.values.get(key).map(String::as_str)
+    }
+
+    /// Get a `true`/`false` setting.
+    pub fn get_bool(&self, key: &str) -> Option<bool> {
+        match self.get(key)? {
+            "true" => Some(true),
+            "false" => Some(false),
+            _ => None,
+        }
+    }
+
+    /// Get a comma separated list setting (`hoist-pattern=*eslint*,*babel*`).
+    pub fn get_list(&self, key: &str) -> Option<Vec<String>> {
+        self.get(key).map(|value| {
+            value
+                .split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(String::from)
+                .collect()
+        })
+    }
+
+    /// Get the file `key` was read from, `None` for defaults and values set at runtime.
+    pub fn source(&self, key: &str) -> Option<&Path> {
+        self.sources.get(key).map(PathBuf::as_path)
+    }
+
+    pub fn set(&mut self, key: &str, value: &str) {
+        self.sources.remove(key);
+        self.values.insert(key.to_string(), value.to_string());
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &String)> {
+        self.values.iter()
+    }
+}
+
+/// Remove `key` from the config file at `path`, keeping the other lines and comments as they are.
+pub fn remove_from_file(path: &Path, key: &str) -> Result<()> {
+    let source = match read_to_string(path) {
+        Ok(source) => source,
+        Err(_) => return Ok(()),
+    };
+
+    let lines: Vec<&str> = source
+        .lines()
+        .filter(|line| match line.split_once('=') {
+            Some((existing, _)) => existing.trim() != key,
+            None => true,
+        })
+        .collect();
+
+    write(path, lines.join("\n") + "\n")?;
+
+    Ok(())
+}
+
+/// Parse `key=value` lines, ignoring comments and blank lines.
+pub fn parse(source: &str) -> BTreeMap<String, String> {
+    source
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#') && !line.starts_with(';'))
+        .filter_map(|line| {
+            let (key, value) = line.split_once('=')?;
+            let value = value.trim();
+
+            Some((key.trim().to_string(), value.trim_matches('"').to_string()))
+        })
+        .collect()
+}
+
+/// Set `key` in the config file at `path`, keeping the other lines and comments as they are.
+pub fn set_in_file(path: &Path, key: &str, value: &str) -> Result<()> {
+    let source = read_to_string(path).unwrap_or_default();
+    let line = format!("{}={}", key, value);
+    let mut found = false;
+
+    let mut lines: Vec<String> = source
+        .lines()
+        .map(|existing| match existing.split_once('=') {
+            Some((existing_key, _)) if existing_key.trim() == key => {
+                found = true;
+                line.clone()
+            }
+            _ => existing.to_string(),
+        })
+        .collect();
+
+    if !found {
+        lines.push(line);
+    }
+
+    write(path, lines.join("\n") + "\n")?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_npmrc_syntax() {
+        let values = parse(
+            "# comment\nregistry = https://registry.example.com/\n; other comment\n@acme:registry=\"https://npm.acme.dev\"\nstrict-peer-dependencies=true\n",
+        );
+
+        assert_eq!(values["registry"], "https://registry.example.com/");
+        assert_eq!(values["@acme:registry"], "https://npm.acme.dev");
+
+        let config = Config {
+            values,
+            ..Default::default()
+        };
+        assert_eq!(config.get_bool("strict-peer-dependencies"), Some(true));
+    }
+
+    #[test]
+    fn applies_the_chosen_profile() {
+        let dir = std::env::temp_dir().join(format!("volt-profiles-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let user_file = dir.join("voltrc");
+        write(
+            &user_file,
+            "registry=https://registry.npmjs.org/\nprofile=work\nprofile.work.registry=https://npm.corp.example/\nprofile.work.https-proxy=http://proxy.corp.example:8080\nprofile.oss.registry=https://registry.npmjs.org/\n",
+        )
+        .unwrap();
+
+        let config = Config::load(&user_file, &dir);
+
+        assert_eq!(config.profile(), Some("work"));
+        assert_eq!(config.get("registry"), Some("https://npm.corp.example/"));
+        assert_eq!(
+            config.get("https-proxy"),
+            Some("http://proxy.corp.example:8080")
+        );
+        assert_eq!(config.get("profile.work.registry"), None);
+        assert_eq!(
+            config.profiles().keys().collect::<Vec<_>>(),
+            vec!["oss", "work"]
+        );
+
+        remove_from_file(&user_file, "profile").unwrap();
+        let config = Config::load(&user_file, &dir);
+
+        assert_eq!(config.get("registry"), Some("https://registry.npmjs.org/"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,259 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Files shared by store entries are stored once.
+//!
+//! Licenses, READMEs and bundled builds are often byte for byte the same across packages. Once
+//! an entry is extracted and its files hashed for the [scrub](crate::scrub), each file is
+//! hardlinked with `<volt dir>/.files/<sha1>`: the first entry with some content puts it there,
+//! the next ones link to it instead of keeping their own copy. Executable files are kept apart
+//! from the others, a hardlink shares its mode. `store-dedup=false` in `.voltrc` turns it off,
+//! and files which can't be hardlinked keep their copy. `volt store status` shows the savings.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+
+use crate::app::App;
+use crate::scrub::{self, Integrity};
+
+pub const FILES_DIR: &str = ".files";
+
+/// Whether extracted entries are deduplicated, unless `store-dedup=false`.
+pub fn is_enabled(app: &App) -> bool {
+    app.config.get_bool("store-dedup") != Some(false)
+}
+
+#[cfg(unix)]
+fn is_executable(metadata: &fs::Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    metadata.permissions().mode() & 0o111 != 0
+}
+
+#[cfg(not(unix))]
+fn is_executable(_metadata: &fs::Metadata) -> bool {
+    false
+}
+
+/// What is stored for a file hashed `sha1`: its inode, shared by hardlinks.
+#[cfg(unix)]
+fn stored_as(metadata: &fs::Metadata, _sha1: &str) -> String {
+    use std::os::unix::fs::MetadataExt;
+
+    format!("{}:{}", metadata.dev(), metadata.ino())
+}
+
+/// What is stored for a file hashed `sha1`, assuming identical files are linked.
+#[cfg(not(unix))]
+fn stored_as(_metadata: &fs::Metadata, sha1: &str) -> String {
+    sha1.to_string()
+}
+
+/// Where the content of a file hashed `sha1` is shared.
+fn shared_path(volt_dir: &Path, sha1: &str, executable: bool) -> PathBuf {
+    let name = if executable {
+        format!("{}-x", sha1)
+    } else {
+        sha1.to_string()
+    };
+
+    volt_dir.join(FILES_DIR).join(&sha1[..2]).join(name)
+}
+
+/// Link the files of the entry in `dir`, hashed in `files`, with the shared files.
+///
+/// Returns the number of files which were already shared by another entry.
+pub fn dedup_entry(volt_dir: &Path, dir: &Path, files: &BTreeMap<String, String>) -> Result<usize> {
+    let mut linked = 0;
+
+    for (relative, sha1) in files.iter() {
+        let path = dir.join(relative);
+        let metadata = fs::symlink_metadata(&path)?;
+
+        if !metadata.is_file() || sha1.len() < 2 {
+            continue;
+        }
+
+        let shared = shared_path(volt_dir, sha1, is_executable(&metadata));
+
+        // A shared file corrupted in place is replaced by the fresh copy
+        if shared.exists() && scrub::hash_file(&shared).ok().as_ref() == Some(sha1) {
+            // Swapped through a temporary link, the file is never missing
+            let temporary = path.with_file_name(format!(".{}.dedup", sha1));
+
+            if fs::hard_link(&shared, &temporary).is_ok() {
+                fs::rename(&temporary, &path)?;
+                linked += 1;
+            }
+
+            continue;
+        }
+
+        if let Some(parent) = shared.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let _ = fs::remove_file(&shared);
+
+        // On another filesystem, the entry keeps its copy
+        let _ = fs::hard_link(&path, &shared);
+    }
+
+    Ok(linked)
+}
+
+/// Deduplicate the entry `name` of the store, just recorded as `integrity`.
+pub fn after_extraction(app: &App, name: &str, integrity: &Integrity) -> Result<()> {
+    if is_enabled(app) {
+        dedup_entry(&app.volt_dir, &app.volt_dir.join(name), &integrity.files)?;
+    }
+
+    Ok(())
+}
+
+/// Remove the shared files no entry links to anymore, once entries were evicted.
+///
+/// Returns the number of files removed.
+#[cfg(unix)]
+pub fn prune(volt_dir: &Path) -> Result<usize> {
+    use std::os::unix::fs::MetadataExt;
+
+    let mut removed = 0;
+
+    for entry in walkdir::WalkDir::new(volt_dir.join(FILES_DIR)) {
+        let entry = entry?;
+
+        if entry.file_type().is_file() && entry.metadata()?.nlink() == 1 {
+            fs::remove_file(entry.path())?;
+            removed += 1;
+        }
+    }
+
+    Ok(removed)
+}
+
+/// Link counts aren't available, shared files are kept.
+#[cfg(not(unix))]
+pub fn prune(_volt_dir: &Path) -> Result<usize> {
+    Ok(0)
+}
+
+/// How much deduplication saves across the recorded entries of the store.
+#[derive(Debug, Default, PartialEq)]
+pub struct DedupStatus {
+    pub entries: usize,
+    pub files: usize,
+    pub unique_files: usize,
+    /// The size of every file of every entry
+    pub logical_bytes: u64,
+    /// The size of the files actually stored, once for each set of hardlinks
+    pub stored_bytes: u64,
+}
+
+impl DedupStatus {
+    /// Bytes of the files for each byte stored, 1 when nothing is shared.
+    pub fn ratio(&self) -> f64 {
+        if self.stored_bytes == 0 {
+            1.0
+        } else {
+            self.logical_bytes as f64 / self.stored_bytes as f64
+        }
+    }
+}
+
+/// Compute the [`DedupStatus`] of the store in `volt_dir` from its integrity records.
+pub fn status(volt_dir: &Path) -> DedupStatus {
+    let mut status = DedupStatus::default();
+    let mut sizes: HashMap<String, u64> = HashMap::new();
+
+    for name in scrub::recorded_names(volt_dir) {
+        let integrity: Integrity = match fs::read_to_string(scrub::record_path(volt_dir, &name))
+            .ok()
+            .and_then(|record| serde_json::from_str(&record).ok())
+        {
+            Some(integrity) => integrity,
+            None => continue,
+        };
+
+        status.entries += 1;
+
+        for (relative, sha1) in integrity.files.iter() {
+            let metadata = match fs::metadata(volt_dir.join(&name).join(relative)) {
+                Ok(metadata) => metadata,
+                Err(_) => continue,
+            };
+
+            status.files += 1;
+            status.logical_bytes += metadata.len();
+            sizes.insert(stored_as(&metadata, sha1), metadata.len());
+        }
+    }
+
+    status.unique_files = sizes.len();
+    status.stored_bytes = sizes.values().sum();
+
+    status
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stores_identical_files_once() {
+        let volt_dir = std::env::temp_dir().join(format!("volt-dedup-{}", std::process::id()));
+
+        for (name, index) in [("a", "module.exports = 'a'"), ("b", "module.exports = 'b'")] {
+            let dir = volt_dir.join(name);
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.join("LICENSE"), "MIT License").unwrap();
+            fs::write(dir.join("index.js"), index).unwrap();
+
+            let files = scrub::hash_files(&dir).unwrap();
+            let linked = dedup_entry(&volt_dir, &dir, &files).unwrap();
+
+            assert_eq!(linked, if name == "a" { 0 } else { 1 });
+
+            let integrity = Integrity {
+                name: name.to_string(),
+                version: String::from("1.0.0"),
+                tarball: String::new(),
+                sha1: String::new(),
+                files,
+            };
+            let record = scrub::record_path(&volt_dir, name);
+            fs::create_dir_all(record.parent().unwrap()).unwrap();
+            fs::write(record, serde_json::to_string(&integrity).unwrap()).unwrap();
+        }
+
+        assert_eq!(
+            fs::read_to_string(volt_dir.join("b").join("LICENSE")).unwrap(),
+            "MIT License"
+        );
+
+        let status = status(&volt_dir);
+        assert_eq!(
+            (status.entries, status.files, status.unique_files),
+            (2, 4, 3)
+        );
+        assert_eq!(status.logical_bytes - status.stored_bytes, 11);
+
+        fs::remove_dir_all(&volt_dir).unwrap();
+    }
+}
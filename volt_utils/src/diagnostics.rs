@@ -0,0 +1,156 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Report errors as text for people or as JSON for tools (`--error-format=json`).
+//!
+//! In JSON mode every error is one object per line on stderr:
+//!
+//! ```json
+//! {"code":"volt::lint_manifest","severity":"error","message":"...","help":"...",
+//!  "filename":"package.json","labels":[{"label":"...","offset":42,"length":5,"line":3,"column":4}],
+//!  "causes":[]}
+//! ```
+//!
+//! `line` and `column` start at 1. Errors which are not miette diagnostics only have a message
+//! and their causes.
+
+use miette::{Diagnostic, Severity};
+use serde_json::{json, Value};
+
+use crate::app::App;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ErrorFormat {
+    Human,
+    Json,
+}
+
+impl ErrorFormat {
+    pub fn from_app(app: &App) -> Self {
+        match app.flag_value(&["--error-format"]).as_deref() {
+            Some("json") => ErrorFormat::Json,
+            _ => ErrorFormat::Human,
+        }
+    }
+}
+
+/// Serialize `diagnostic`, with its labels resolved to lines and columns.
+pub fn diagnostic_json(diagnostic: &dyn Diagnostic) -> Value {
+    let mut filename = None;
+    let mut labels = vec![];
+
+    for label in diagnostic.labels().into_iter().flatten() {
+        let position = diagnostic
+            .source_code()
+            .and_then(|source| source.read_span(label.inner(), 0, 0).ok());
+
+        if let Some(name) = position.as_ref().and_then(|contents| contents.name()) {
+            filename = Some(name.to_string());
+        }
+
+        labels.push(json!({
+            "label": label.label(),
+            "offset": label.offset(),
+            "length": label.len(),
+            "line": position.as_ref().map(|contents| contents.line() + 1),
+            "column": position.as_ref().map(|contents| contents.column() + 1),
+        }));
+    }
+
+    let severity = match diagnostic.severity() {
+        Some(Severity::Warning) => "warning",
+        Some(Severity::Advice) => "advice",
+        Some(Severity::Error) | None => "error",
+    };
+
+    json!({
+        "code": diagnostic.code().map(|code| code.to_string()),
+        "severity": severity,
+        "message": diagnostic.to_string(),
+        "help": diagnostic.help().map(|help| help.to_string()),
+        "url": diagnostic.url().map(|url| url.to_string()),
+        "filename": filename,
+        "labels": labels,
+        "causes": [],
+        "related": diagnostic
+            .related()
+            .into_iter()
+            .flatten()
+            .map(diagnostic_json)
+            .collect::<Vec<Value>>(),
+    })
+}
+
+/// Serialize an error which is not a diagnostic.
+pub fn error_json(error: &anyhow::Error) -> Value {
+    json!({
+        "code": null,
+        "severity": "error",
+        "message": error.to_string(),
+        "help": null,
+        "url": null,
+        "filename": null,
+        "labels": [],
+        "causes": error
+            .chain()
+            .skip(1)
+            .map(|cause| cause.to_string())
+            .collect::<Vec<String>>(),
+        "related": [],
+    })
+}
+
+/// Print `diagnostic` to stderr in `format`.
+pub fn emit(format: ErrorFormat, diagnostic: impl Diagnostic + Send + Sync + 'static) {
+    match format {
+        ErrorFormat::Human => eprintln!("{:?}", miette::Report::new(diagnostic)),
+        ErrorFormat::Json => eprintln!("{}", diagnostic_json(&diagnostic)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use miette::{NamedSource, SourceSpan};
+    use thiserror::Error;
+
+    #[derive(Debug, Error, Diagnostic)]
+    #[error("invalid version")]
+    #[diagnostic(code(volt::test), help("use semver"))]
+    struct Invalid {
+        #[source_code]
+        source_code: NamedSource,
+        #[label("here")]
+        span: SourceSpan,
+    }
+
+    #[test]
+    fn serializes_labels_with_positions() {
+        let diagnostic = Invalid {
+            source_code: NamedSource::new("package.json", "{\n  \"version\": \"one\"\n}"),
+            span: (15, 5).into(),
+        };
+
+        let value = diagnostic_json(&diagnostic);
+
+        assert_eq!(value["code"], "volt::test");
+        assert_eq!(value["help"], "use semver");
+        assert_eq!(value["filename"], "package.json");
+        assert_eq!(value["labels"][0]["label"], "here");
+        assert_eq!(value["labels"][0]["line"], 2);
+        assert_eq!(value["labels"][0]["column"], 14);
+    }
+}
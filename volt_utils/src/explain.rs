@@ -0,0 +1,133 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Why the [resolver](crate::resolver) chose the version of each package of a tree.
+//!
+//! A walk records every range asking for a package, what it resolved to and who asked for it.
+//! Trees are flattened by name, so when ranges resolve to different versions only one of them
+//! is kept, and the ranges it doesn't satisfy are conflicts.
+
+use std::collections::HashMap;
+
+use node_semver::{Range, Version};
+use serde::Serialize;
+
+/// A range asking for a package during a walk, and the version it resolved to.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Candidate {
+    pub range: String,
+    pub version: String,
+    /// Levels below the root of the walk, which is at 0
+    pub depth: usize,
+    /// `name@version` of the packages asking for the range, empty for the root
+    pub required_by: Vec<String>,
+}
+
+/// The version chosen for a package during a walk, among its candidates.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Decision {
+    pub name: String,
+    pub version: String,
+    /// Sorted by depth, then range
+    pub candidates: Vec<Candidate>,
+}
+
+/// The decisions of a walk, by package name.
+pub type Decisions = HashMap<String, Decision>;
+
+impl Decision {
+    /// The rule which picked the version, the one the resolver flattens trees with.
+    pub fn strategy(&self) -> String {
+        let depth = self
+            .candidates
+            .iter()
+            .filter(|candidate| candidate.version == self.version)
+            .map(|candidate| candidate.depth)
+            .min()
+            .unwrap_or(0);
+
+        let competing = self
+            .candidates
+            .iter()
+            .any(|candidate| candidate.version != self.version);
+
+        let tied = self
+            .candidates
+            .iter()
+            .any(|candidate| candidate.depth == depth && candidate.version != self.version);
+
+        if depth == 0 {
+            String::from("requested directly")
+        } else if !competing {
+            String::from("every range resolves to it")
+        } else if tied {
+            format!(
+                "the highest version at depth {}, the closest to the root",
+                depth
+            )
+        } else {
+            format!("the closest to the root, at depth {}", depth)
+        }
+    }
+
+    /// The candidates the chosen version doesn't satisfy.
+    pub fn conflicts(&self) -> Vec<&Candidate> {
+        let chosen = Version::parse(&self.version).ok();
+
+        self.candidates
+            .iter()
+            .filter(|candidate| candidate.version != self.version)
+            .filter(|candidate| {
+                match (Range::parse(&candidate.range), &chosen) {
+                    (Ok(range), Some(chosen)) => !range.satisfies(chosen),
+                    // Dist tags (`latest`) only match what they resolved to
+                    _ => true,
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(range: &str, version: &str, depth: usize) -> Candidate {
+        Candidate {
+            range: range.to_string(),
+            version: version.to_string(),
+            depth,
+            required_by: vec![],
+        }
+    }
+
+    #[test]
+    fn explains_the_closest_version() {
+        let decision = Decision {
+            name: String::from("c"),
+            version: String::from("1.5.0"),
+            candidates: vec![
+                candidate("^1.0.0", "1.5.0", 1),
+                candidate("^1.2.0", "1.5.0", 2),
+                candidate("^2.0.0", "2.1.0", 2),
+            ],
+        };
+
+        assert_eq!(decision.strategy(), "the closest to the root, at depth 1");
+        assert_eq!(decision.conflicts(), vec![&decision.candidates[2]]);
+    }
+}
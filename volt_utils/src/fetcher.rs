@@ -0,0 +1,255 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Fetch the tarballs of packages from where they are locked.
+//!
+//! Every source of tarballs is a [Fetcher], asked in turn whether it handles the tarball of a
+//! package. The first one which does fetches it, the resolver and the install never look at the
+//! tarball urls themselves. Volt comes with fetchers for:
+//!
+//! - git repositories (`git+https://...`), see [git](crate::git)
+//! - tarball directories (`tarball-dir:<file>`), see [tarball_dir](crate::tarball_dir)
+//! - local files (`file:<path>`, relative to the project)
+//! - registries and any other http url, through the [offline mirror](crate::mirror)
+//!
+//! Programs using volt as a library add their own sources, a store server or a bucket, with
+//! [register]. Registered fetchers are asked before the built-in ones, the last registered first.
+
+use std::fs::read;
+use std::sync::{Arc, RwLock};
+
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use lazy_static::lazy_static;
+
+use crate::app::App;
+use crate::git;
+use crate::mirror;
+use crate::tarball_dir::{self, TarballDir};
+use crate::volt_api::{PackageSource, VoltPackage};
+
+/// How tarballs on the disk are locked, followed by their path relative to the project.
+pub const FILE_PREFIX: &str = "file:";
+
+/// A source of tarballs.
+#[async_trait]
+pub trait Fetcher: Send + Sync {
+    /// What the fetcher is called, `registry` or `git`.
+    fn name(&self) -> &'static str;
+
+    /// Whether the tarball of `package` comes from this source.
+    fn handles(&self, app: &App, package: &VoltPackage) -> bool;
+
+    /// The tarball of `package`, which is checked against its checksum afterwards.
+    async fn fetch(&self, app: &App, package: &VoltPackage) -> Result<bytes::Bytes>;
+}
+
+pub struct GitFetcher;
+
+#[async_trait]
+impl Fetcher for GitFetcher {
+    fn name(&self) -> &'static str {
+        "git"
+    }
+
+    fn handles(&self, _app: &App, package: &VoltPackage) -> bool {
+        package.source == PackageSource::Git
+    }
+
+    async fn fetch(&self, app: &App, package: &VoltPackage) -> Result<bytes::Bytes> {
+        git::read_tarball(app, &package.tarball)
+    }
+}
+
+pub struct TarballDirFetcher;
+
+#[async_trait]
+impl Fetcher for TarballDirFetcher {
+    fn name(&self) -> &'static str {
+        "tarball-dir"
+    }
+
+    fn handles(&self, _app: &App, package: &VoltPackage) -> bool {
+        package.tarball.starts_with(tarball_dir::PREFIX)
+    }
+
+    async fn fetch(&self, app: &App, package: &VoltPackage) -> Result<bytes::Bytes> {
+        TarballDir::from_app(app)
+            .ok_or_else(|| {
+                anyhow!(
+                    "{}@{} is locked to a tarball directory, but tarball-dir is not set",
+                    package.name,
+                    package.version
+                )
+            })?
+            .read_tarball(&package.tarball)
+    }
+}
+
+pub struct FileFetcher;
+
+#[async_trait]
+impl Fetcher for FileFetcher {
+    fn name(&self) -> &'static str {
+        "file"
+    }
+
+    fn handles(&self, _app: &App, package: &VoltPackage) -> bool {
+        package.tarball.starts_with(FILE_PREFIX)
+    }
+
+    async fn fetch(&self, app: &App, package: &VoltPackage) -> Result<bytes::Bytes> {
+        let path = app
+            .current_dir
+            .join(package.tarball.trim_start_matches(FILE_PREFIX));
+
+        let data = read(&path).with_context(|| format!("failed to read {}", path.display()))?;
+
+        Ok(bytes::Bytes::from(data))
+    }
+}
+
+pub struct RegistryFetcher;
+
+#[async_trait]
+impl Fetcher for RegistryFetcher {
+    fn name(&self) -> &'static str {
+        "registry"
+    }
+
+    fn handles(&self, _app: &App, package: &VoltPackage) -> bool {
+        package.tarball.starts_with("http://") || package.tarball.starts_with("https://")
+    }
+
+    async fn fetch(&self, app: &App, package: &VoltPackage) -> Result<bytes::Bytes> {
+        mirror::fetch_tarball(app, package).await
+    }
+}
+
+lazy_static! {
+    static ref FETCHERS: RwLock<Vec<Arc<dyn Fetcher>>> = RwLock::new(vec![
+        Arc::new(GitFetcher),
+        Arc::new(TarballDirFetcher),
+        Arc::new(FileFetcher),
+        Arc::new(RegistryFetcher),
+    ]);
+}
+
+/// Add a source of tarballs, asked before the ones registered until now.
+pub fn register(fetcher: Arc<dyn Fetcher>) {
+    FETCHERS.write().unwrap().insert(0, fetcher);
+}
+
+/// The fetcher of `package`, if any handles it.
+pub fn find(app: &App, package: &VoltPackage) -> Option<Arc<dyn Fetcher>> {
+    FETCHERS
+        .read()
+        .unwrap()
+        .iter()
+        .find(|fetcher| fetcher.handles(app, package))
+        .cloned()
+}
+
+/// Get the tarball of `package` from the first fetcher handling it.
+pub async fn fetch(app: &App, package: &VoltPackage) -> Result<bytes::Bytes> {
+    let fetcher = find(app, package).ok_or_else(|| {
+        anyhow!(
+            "{}@{} is locked to {}, which no fetcher handles",
+            package.name,
+            package.version,
+            package.tarball
+        )
+    })?;
+
+    fetcher.fetch(app, package).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    struct MemoryFetcher;
+
+    #[async_trait]
+    impl Fetcher for MemoryFetcher {
+        fn name(&self) -> &'static str {
+            "memory"
+        }
+
+        fn handles(&self, _app: &App, package: &VoltPackage) -> bool {
+            package.tarball.starts_with("memory:")
+        }
+
+        async fn fetch(&self, _app: &App, package: &VoltPackage) -> Result<bytes::Bytes> {
+            Ok(bytes::Bytes::from(package.name.clone()))
+        }
+    }
+
+    #[tokio::test]
+    async fn fetches_from_the_fetcher_handling_the_tarball() {
+        let root = std::env::temp_dir().join(format!("volt-fetcher-{}", std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::write(root.join("left-pad.tgz"), "tarball").unwrap();
+
+        let app = App {
+            current_dir: root.clone(),
+            home_dir: root.join("home"),
+            node_modules_dir: root.join("node_modules"),
+            volt_dir: root.join("home/.volt"),
+            cache_dir: root.join("home/.volt/.cache"),
+            lock_file_path: root.join("volt.lock"),
+            config_file: root.join(".voltrc"),
+            args: vec![],
+            flags: vec![],
+            config: Config::default(),
+        };
+
+        let package = |tarball: &str| -> VoltPackage {
+            serde_json::from_value(serde_json::json!({
+                "name": "left-pad",
+                "version": "1.3.0",
+                "tarball": tarball,
+                "sha1": "",
+                "peerDependencies": [],
+                "dependencies": null,
+                "bin": null
+            }))
+            .unwrap()
+        };
+
+        assert_eq!(
+            fetch(&app, &package("file:left-pad.tgz")).await.unwrap(),
+            "tarball"
+        );
+        assert!(fetch(&app, &package("memory:left-pad")).await.is_err());
+
+        register(Arc::new(MemoryFetcher));
+
+        assert_eq!(
+            fetch(&app, &package("memory:left-pad")).await.unwrap(),
+            "left-pad"
+        );
+        assert_eq!(
+            find(&app, &package("https://registry.npmjs.org/left-pad.tgz"))
+                .unwrap()
+                .name(),
+            "registry"
+        );
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,168 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Filesystem helpers for large node_modules trees.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::thread::sleep;
+use std::time::Duration;
+
+use rayon::prelude::*;
+
+/// Attempts made on a file which is busy before giving up.
+const RETRIES: u32 = 5;
+
+/// Remove a directory tree, deleting the entries of every directory in parallel.
+///
+/// Symlinks are removed, never followed. Entries which are already gone are fine, and
+/// operations failing because a file is busy (virus scanners and editors on Windows) or read
+/// only are retried with a backoff.
+pub fn remove_dir_all(path: &Path) -> io::Result<()> {
+    let metadata = match fs::symlink_metadata(path) {
+        Ok(metadata) => metadata,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(error) => return Err(error),
+    };
+
+    if !metadata.is_dir() {
+        return retry(path, || remove_entry(path));
+    }
+
+    remove_tree(&long_path(path))
+}
+
+fn remove_tree(dir: &Path) -> io::Result<()> {
+    let entries: Vec<PathBuf> = fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<_>>()?;
+
+    entries
+        .par_iter()
+        .try_for_each(|entry| match fs::symlink_metadata(entry) {
+            Ok(metadata) if metadata.is_dir() => remove_tree(entry),
+            Ok(_) => retry(entry, || remove_entry(entry)),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(()),
+            Err(error) => Err(error),
+        })?;
+
+    retry(dir, || fs::remove_dir(dir))
+}
+
+/// Remove a file or a symlink, directory junctions on Windows are removed like directories.
+fn remove_entry(path: &Path) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Err(error) if cfg!(windows) && error.kind() != io::ErrorKind::NotFound => {
+            fs::remove_dir(path).map_err(|_| error)
+        }
+        result => result,
+    }
+}
+
+fn retry(path: &Path, mut operation: impl FnMut() -> io::Result<()>) -> io::Result<()> {
+    let mut attempt = 0;
+
+    loop {
+        match operation() {
+            Ok(()) => return Ok(()),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(error) if attempt < RETRIES && is_transient(&error) => {
+                if error.kind() == io::ErrorKind::PermissionDenied {
+                    clear_readonly(path);
+                }
+
+                sleep(Duration::from_millis(10 << attempt));
+                attempt += 1;
+            }
+            Err(error) => return Err(error),
+        }
+    }
+}
+
+fn is_transient(error: &io::Error) -> bool {
+    // ERROR_SHARING_VIOLATION, ERROR_LOCK_VIOLATION and ERROR_DIR_NOT_EMPTY while a file
+    // handle is still being closed
+    error.kind() == io::ErrorKind::PermissionDenied
+        || (cfg!(windows) && matches!(error.raw_os_error(), Some(32) | Some(33) | Some(145)))
+}
+
+fn clear_readonly(path: &Path) {
+    if let Ok(metadata) = fs::symlink_metadata(path) {
+        let mut permissions = metadata.permissions();
+
+        if permissions.readonly() {
+            #[allow(clippy::permissions_set_readonly_false)]
+            permissions.set_readonly(false);
+            let _ = fs::set_permissions(path, permissions);
+        }
+    }
+}
+
+/// Use the `\\?\` form of absolute paths on Windows, which is not limited to 260 characters.
+#[cfg(windows)]
+fn long_path(path: &Path) -> PathBuf {
+    if path.is_absolute() && !path.to_string_lossy().starts_with(r"\\") {
+        PathBuf::from(format!(r"\\?\{}", path.display()))
+    } else {
+        path.to_path_buf()
+    }
+}
+
+#[cfg(not(windows))]
+fn long_path(path: &Path) -> PathBuf {
+    path.to_path_buf()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn removes_nested_trees_without_following_symlinks() {
+        let root = std::env::temp_dir().join(format!("volt-remove-{}", std::process::id()));
+        let outside = root.with_extension("outside");
+
+        fs::create_dir_all(root.join("a").join("node_modules").join("b")).unwrap();
+        fs::create_dir_all(&outside).unwrap();
+        fs::write(root.join("a").join("index.js"), "").unwrap();
+        fs::write(outside.join("keep.js"), "").unwrap();
+
+        for i in 0..50 {
+            fs::write(
+                root.join("a")
+                    .join("node_modules")
+                    .join("b")
+                    .join(i.to_string()),
+                "",
+            )
+            .unwrap();
+        }
+
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(&outside, root.join("link")).unwrap();
+
+        remove_dir_all(&root).unwrap();
+
+        assert!(!root.exists());
+        assert!(outside.join("keep.js").exists());
+
+        // Removing it again is fine
+        remove_dir_all(&root).unwrap();
+
+        fs::remove_dir_all(&outside).unwrap();
+    }
+}
@@ -0,0 +1,327 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Install packages from git repositories.
+//!
+//! `foo@git+https://github.com/acme/tools.git#main` installs the package at the root of the
+//! repository, and `&path:packages/foo` after the committish the one in that directory, which is
+//! how the packages of a monorepo are used before they are published. `github:acme/tools` is
+//! short for the https url. The commit is fetched shallowly with the git cli, only the directory
+//! of the package is packed, like `volt publish` packs it, and its dependencies come from the
+//! registry.
+//!
+//! Packages are locked to the commit they were resolved to (`git+https://...#<sha>&path:...`).
+//! Packing is reproducible, so the tarball kept in `<cache dir>/git` can always be made again
+//! from the repository and checked against the lock file.
+
+use std::fmt;
+use std::fs::{create_dir_all, read, read_to_string, write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context, Result};
+use dashmap::DashMap;
+use serde_json::{json, Value};
+
+use crate::app::App;
+use crate::pack;
+use crate::resolver;
+use crate::volt_api::{PackageSource, VoltResponse};
+
+/// Marks the directory of the package in the fragment of a git url.
+pub const PATH_PREFIX: &str = "path:";
+
+/// A git dependency, `git+<url>#<committish>&path:<dir>`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GitSpec {
+    /// What git fetches from
+    pub url: String,
+    /// Branch, tag or commit, the default branch when there is none
+    pub committish: Option<String>,
+    /// Directory of the package in the repository
+    pub path: Option<String>,
+}
+
+impl GitSpec {
+    pub fn parse(spec: &str) -> Option<Self> {
+        let (url, fragment) = match spec.split_once('#') {
+            Some((url, fragment)) => (url, Some(fragment)),
+            None => (spec, None),
+        };
+
+        let url = if let Some(url) = url.strip_prefix("git+") {
+            url.to_string()
+        } else if url.starts_with("git://") {
+            url.to_string()
+        } else {
+            let repository = url.strip_prefix("github:")?;
+            format!(
+                "https://github.com/{}.git",
+                repository.trim_end_matches(".git")
+            )
+        };
+
+        let mut committish = None;
+        let mut path = None;
+
+        for part in fragment
+            .into_iter()
+            .flat_map(|fragment| fragment.split('&'))
+        {
+            match part.strip_prefix(PATH_PREFIX) {
+                Some(dir) => path = Some(dir.trim_matches('/').to_string()),
+                None if !part.is_empty() => committish = Some(part.to_string()),
+                None => {}
+            }
+        }
+
+        Some(GitSpec {
+            url,
+            committish,
+            path: path.filter(|path| !path.is_empty()),
+        })
+    }
+
+    /// Whether `range` is a git dependency rather than a range of the registry.
+    pub fn is_git(range: &str) -> bool {
+        GitSpec::parse(range).is_some()
+    }
+}
+
+impl fmt::Display for GitSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.url.starts_with("git://") {
+            write!(f, "{}", self.url)?;
+        } else {
+            write!(f, "git+{}", self.url)?;
+        }
+
+        let fragment: Vec<String> = self
+            .committish
+            .iter()
+            .cloned()
+            .chain(
+                self.path
+                    .iter()
+                    .map(|path| format!("{}{}", PATH_PREFIX, path)),
+            )
+            .collect();
+
+        if !fragment.is_empty() {
+            write!(f, "#{}", fragment.join("&"))?;
+        }
+
+        Ok(())
+    }
+}
+
+fn git(dir: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .context("failed to run git")?;
+
+    if !output.status.success() {
+        return Err(anyhow!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Where the tarball of the package locked as `locked` is kept.
+fn tarball_path(app: &App, locked: &GitSpec) -> Result<PathBuf> {
+    let hash = App::calc_hash(&bytes::Bytes::from(locked.to_string()))?;
+
+    Ok(app.cache_dir.join("git").join(format!("{}.tgz", hash)))
+}
+
+/// A packed package, with its manifest and the spec it is locked as.
+struct Packed {
+    manifest: Value,
+    locked: GitSpec,
+    tarball: pack::Tarball,
+}
+
+/// Fetch the commit of `spec` into the empty directory `checkout` and pack its package.
+fn pack_commit(checkout: &Path, spec: &GitSpec) -> Result<Packed> {
+    git(checkout, &["init", "-q"])?;
+    git(
+        checkout,
+        &[
+            "fetch",
+            "-q",
+            "--depth",
+            "1",
+            &spec.url,
+            spec.committish.as_deref().unwrap_or("HEAD"),
+        ],
+    )?;
+    git(checkout, &["checkout", "-q", "FETCH_HEAD"])?;
+
+    let commit = git(checkout, &["rev-parse", "HEAD"])?;
+    let dir = checkout.join(spec.path.as_deref().unwrap_or_default());
+
+    let manifest: Value = read_to_string(dir.join("package.json"))
+        .map_err(anyhow::Error::from)
+        .and_then(|text| Ok(serde_json::from_str(&text)?))
+        .with_context(|| format!("{} has no valid package.json", spec))?;
+
+    let tarball = pack::pack(&dir, &manifest)?;
+
+    Ok(Packed {
+        manifest,
+        locked: GitSpec {
+            committish: Some(commit),
+            ..spec.clone()
+        },
+        tarball,
+    })
+}
+
+/// Pack the package of `spec` and keep its tarball in the cache.
+fn fetch_and_pack(app: &App, spec: &GitSpec) -> Result<Packed> {
+    let checkout = app.cache_dir.join("git").join(format!(
+        "checkout-{}",
+        App::calc_hash(&bytes::Bytes::from(spec.to_string()))?
+    ));
+
+    crate::fs::remove_dir_all(&checkout)?;
+    create_dir_all(&checkout)?;
+
+    let result = pack_commit(&checkout, spec);
+
+    crate::fs::remove_dir_all(&checkout)?;
+
+    let packed = result.with_context(|| format!("failed to fetch {}", spec))?;
+
+    let path = tarball_path(app, &packed.locked)?;
+    write(&path, &packed.tarball.data)?;
+
+    Ok(packed)
+}
+
+/// Resolve `name` from the git dependency `spec`, with its dependencies from the registry.
+pub async fn resolve(app: &App, name: &str, spec: &GitSpec) -> Result<VoltResponse> {
+    let Packed {
+        mut manifest,
+        locked,
+        tarball,
+    } = fetch_and_pack(app, spec)?;
+
+    if manifest["name"] != name {
+        return Err(anyhow!(
+            "{} holds the package {}, not {}",
+            spec,
+            manifest["name"],
+            name
+        ));
+    }
+
+    let version = manifest["version"]
+        .as_str()
+        .ok_or_else(|| anyhow!("the package.json of {} has no version", spec))?
+        .to_string();
+
+    manifest["dist"] = json!({
+        "tarball": locked.to_string(),
+        "shasum": tarball.shasum,
+    });
+
+    let packument = Arc::new(json!({
+        "name": name,
+        "dist-tags": { "latest": version },
+        "versions": { version.clone(): manifest },
+    }));
+
+    let (mut response, _) = resolver::walk(
+        (name.to_string(), version),
+        resolver::DEFAULT_CONCURRENCY,
+        &DashMap::new(),
+        None,
+        |package| {
+            let packument = packument.clone();
+
+            async move {
+                if package == name {
+                    Ok(packument)
+                } else {
+                    resolver::packument(app, package).await
+                }
+            }
+        },
+    )
+    .await?;
+
+    for data in response.versions.values_mut() {
+        if let Some(package) = data.packages.get_mut(name) {
+            package.source = PackageSource::Git;
+        }
+    }
+
+    Ok(response)
+}
+
+/// The tarball of the package locked as `tarball`, packed again if it isn't kept anymore.
+pub fn read_tarball(app: &App, tarball: &str) -> Result<bytes::Bytes> {
+    let locked = GitSpec::parse(tarball).ok_or_else(|| anyhow!("{} is not a git url", tarball))?;
+
+    if let Ok(data) = read(tarball_path(app, &locked)?) {
+        return Ok(bytes::Bytes::from(data));
+    }
+
+    Ok(bytes::Bytes::from(
+        fetch_and_pack(app, &locked)?.tarball.data,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_subdirectories_and_committishes() {
+        let spec = GitSpec::parse("git+https://github.com/acme/tools.git#main&path:/packages/foo")
+            .unwrap();
+
+        assert_eq!(spec.url, "https://github.com/acme/tools.git");
+        assert_eq!(spec.committish.as_deref(), Some("main"));
+        assert_eq!(spec.path.as_deref(), Some("packages/foo"));
+        assert_eq!(
+            spec.to_string(),
+            "git+https://github.com/acme/tools.git#main&path:packages/foo"
+        );
+
+        let spec = GitSpec::parse("github:acme/tools#path:packages/bar").unwrap();
+        assert_eq!(spec.url, "https://github.com/acme/tools.git");
+        assert_eq!(spec.committish, None);
+        assert_eq!(spec.path.as_deref(), Some("packages/bar"));
+
+        assert_eq!(
+            GitSpec::parse("git://example.com/tools.git")
+                .unwrap()
+                .to_string(),
+            "git://example.com/tools.git"
+        );
+        assert!(!GitSpec::is_git("^1.2.0"));
+        assert!(!GitSpec::is_git("npm:foo@1"));
+    }
+}
@@ -0,0 +1,93 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Differences of the GitHub Packages registry (`npm.pkg.github.com`) from the npm one.
+//!
+//! Packages live under the scope of their owner and are routed there with
+//! `@owner:registry=https://npm.pkg.github.com` in `.voltrc`. Every request needs a token, even
+//! for public packages, so `GITHUB_TOKEN` is used when no `_authToken` is configured. The
+//! registry only serves full packuments, and those of packages published without a tag have no
+//! `dist-tags`, which [`normalize`] fills in so they resolve like any other.
+
+use serde_json::Value;
+
+pub const REGISTRY: &str = "https://npm.pkg.github.com";
+
+/// Environment variable holding the token when none is configured, as in GitHub Actions.
+pub const TOKEN_VARIABLE: &str = "GITHUB_TOKEN";
+
+/// Check if `registry` is GitHub Packages.
+pub fn is_github(registry: &str) -> bool {
+    let host = registry
+        .split_once("://")
+        .map_or(registry, |(_, host)| host)
+        .split('/')
+        .next()
+        .unwrap_or_default();
+
+    host.eq_ignore_ascii_case("npm.pkg.github.com")
+}
+
+/// Media type to ask packuments in, GitHub Packages has no abbreviated form.
+pub fn accept(registry: &str) -> &'static str {
+    if is_github(registry) {
+        "application/json"
+    } else {
+        "application/vnd.npm.install-v1+json"
+    }
+}
+
+/// Fill in what GitHub Packages leaves out of `packument`.
+pub fn normalize(packument: &mut Value) {
+    if packument["dist-tags"]["latest"].is_string() {
+        return;
+    }
+
+    let versions: Vec<String> = packument["versions"]
+        .as_object()
+        .map(|versions| versions.keys().cloned().collect())
+        .unwrap_or_default();
+
+    // Like npm, prereleases are only `latest` when there is nothing else
+    let latest = crate::max_satisfying(versions.iter(), "*").or_else(|| {
+        versions
+            .iter()
+            .filter_map(|version| node_semver::Version::parse(version).ok())
+            .max()
+            .map(|version| version.to_string())
+    });
+
+    if let Some(latest) = latest {
+        if !packument["dist-tags"].is_object() {
+            packument["dist-tags"] = Value::Object(Default::default());
+        }
+
+        packument["dist-tags"]["latest"] = Value::from(latest);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_the_registry() {
+        assert!(is_github("https://npm.pkg.github.com"));
+        assert!(is_github("https://npm.pkg.github.com/acme/"));
+        assert!(!is_github("https://registry.npmjs.org"));
+        assert!(!is_github("https://npm.pkg.github.com.evil.dev"));
+    }
+}
@@ -0,0 +1,339 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Global installs (`volt add -g`).
+//!
+//! Global packages are installed like the dependencies of a project living in a per-user
+//! directory, `<local data dir>/volt/global` unless `global-dir` is set in `.voltrc` or
+//! `--prefix` is passed, so no elevation is needed. Their binaries get shims in its `bin`
+//! directory, which is added to the user PATH: in `HKCU\Environment` on Windows and in the
+//! shell profile elsewhere.
+
+use std::fs::{self, create_dir_all};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+
+use crate::app::App;
+use crate::volt_api::VoltPackage;
+
+/// Directory of the global project.
+pub fn dir(app: &App) -> PathBuf {
+    match app.config.get("global-dir") {
+        Some(dir) => PathBuf::from(dir),
+        None => dirs::data_local_dir()
+            .unwrap_or_else(|| app.home_dir.join(".local").join("share"))
+            .join("volt")
+            .join("global"),
+    }
+}
+
+/// Directory of the shims of global binaries.
+pub fn bin_dir(app: &App) -> PathBuf {
+    dir(app).join("bin")
+}
+
+/// Create the global project if needed and get an app working in it.
+pub fn app(app: &App) -> Result<App> {
+    let dir = dir(app);
+    let manifest = dir.join("package.json");
+
+    create_dir_all(dir.join("bin"))
+        .with_context(|| format!("failed to create {}", dir.display()))?;
+
+    if !manifest.exists() {
+        fs::write(
+            &manifest,
+            "{\n  \"name\": \"volt-global\",\n  \"private\": true,\n  \"dependencies\": {}\n}\n",
+        )?;
+    }
+
+    Ok(App {
+        current_dir: dir.clone(),
+        home_dir: app.home_dir.clone(),
+        node_modules_dir: dir.join("node_modules"),
+        volt_dir: app.volt_dir.clone(),
+        cache_dir: app.cache_dir.clone(),
+        config_file: app.config_file.clone(),
+        lock_file_path: dir.join("volt.lock"),
+        args: app.args.clone(),
+        flags: app.flags.clone(),
+        config: app.config.clone(),
+    })
+}
+
+/// Write shims in `bin_dir` for the binaries of `package`, installed in `node_modules_dir`.
+///
+/// Returns the names of the binaries.
+pub fn write_shims(
+    bin_dir: &Path,
+    node_modules_dir: &Path,
+    package: &VoltPackage,
+) -> Result<Vec<String>> {
+    let mut names = vec![];
+
+    for (name, path) in package.bin.iter().flatten() {
+        let target = node_modules_dir.join(&package.name).join(path);
+
+        // POSIX shell, also used by Git Bash and WSL on Windows
+        let shell = bin_dir.join(name);
+        fs::write(
+            &shell,
+            format!(
+                "#!/bin/sh\nexec node \"{}\" \"$@\"\n",
+                target.to_string_lossy().replace('\\', "/")
+            ),
+        )?;
+        make_executable(&shell)?;
+
+        if cfg!(windows) {
+            fs::write(
+                bin_dir.join(format!("{}.cmd", name)),
+                format!("@ECHO off\r\nnode \"{}\" %*\r\n", target.display()),
+            )?;
+            fs::write(
+                bin_dir.join(format!("{}.ps1", name)),
+                format!(
+                    "& node \"{}\" $args\r\nexit $LASTEXITCODE\r\n",
+                    target.display()
+                ),
+            )?;
+        }
+
+        names.push(name.clone());
+    }
+
+    Ok(names)
+}
+
+#[cfg(unix)]
+fn make_executable(path: &Path) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    fs::set_permissions(path, fs::Permissions::from_mode(0o755))?;
+
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn make_executable(_path: &Path) -> Result<()> {
+    Ok(())
+}
+
+/// Check if `dir` is in PATH.
+pub fn is_on_path(dir: &Path) -> bool {
+    std::env::var_os("PATH")
+        .map(|path| std::env::split_paths(&path).any(|entry| entry == dir))
+        .unwrap_or(false)
+}
+
+/// Where `add_to_path` adds the directory, to tell the user before doing it.
+pub fn path_location(app: &App) -> String {
+    if cfg!(windows) {
+        String::from(r"the user PATH (HKCU\Environment)")
+    } else {
+        shell_profile(app).display().to_string()
+    }
+}
+
+/// The profile of the login shell.
+fn shell_profile(app: &App) -> PathBuf {
+    let shell = std::env::var("SHELL").unwrap_or_default();
+
+    if shell.ends_with("zsh") {
+        app.home_dir.join(".zshrc")
+    } else if shell.ends_with("bash") {
+        app.home_dir.join(".bashrc")
+    } else if shell.ends_with("fish") {
+        app.home_dir
+            .join(".config")
+            .join("fish")
+            .join("config.fish")
+    } else {
+        app.home_dir.join(".profile")
+    }
+}
+
+/// Add `dir` to the user PATH, for shells started afterwards.
+pub fn add_to_path(app: &App, dir: &Path) -> Result<()> {
+    if cfg!(windows) {
+        return add_to_user_path(dir);
+    }
+
+    let profile = shell_profile(app);
+
+    let line = if profile.ends_with("config.fish") {
+        format!("fish_add_path \"{}\"", dir.display())
+    } else {
+        format!("export PATH=\"{}:$PATH\"", dir.display())
+    };
+
+    let mut contents = fs::read_to_string(&profile).unwrap_or_default();
+
+    if contents.lines().any(|existing| existing == line) {
+        return Ok(());
+    }
+
+    if !contents.is_empty() && !contents.ends_with('\n') {
+        contents.push('\n');
+    }
+
+    contents.push_str(&format!("\n# volt global binaries\n{}\n", line));
+
+    if let Some(parent) = profile.parent() {
+        create_dir_all(parent)?;
+    }
+
+    fs::write(&profile, contents)
+        .with_context(|| format!("failed to update {}", profile.display()))?;
+
+    Ok(())
+}
+
+/// Append `dir` to `Path` in `HKCU\Environment`, keeping its `REG_EXPAND_SZ` type.
+fn add_to_user_path(dir: &Path) -> Result<()> {
+    use std::process::Command;
+
+    let output = Command::new("reg")
+        .args(["query", r"HKCU\Environment", "/v", "Path"])
+        .output()
+        .context("failed to run reg")?;
+
+    // `    Path    REG_EXPAND_SZ    C:\a;C:\b`, missing when the user has no PATH of their own
+    let current = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find_map(|line| {
+            let rest = line.trim_start().strip_prefix("Path")?;
+            let rest = rest.trim_start().strip_prefix("REG_")?;
+            // Skip the rest of the type name
+            let value = rest.trim_start_matches(|c: char| !c.is_whitespace());
+            Some(value.trim().to_string())
+        })
+        .unwrap_or_default();
+
+    let dir = dir.display().to_string();
+
+    if current
+        .split(';')
+        .any(|entry| entry.eq_ignore_ascii_case(&dir))
+    {
+        return Ok(());
+    }
+
+    let path = if current.is_empty() {
+        dir
+    } else {
+        format!("{};{}", current.trim_end_matches(';'), dir)
+    };
+
+    let status = Command::new("reg")
+        .args([
+            "add",
+            r"HKCU\Environment",
+            "/v",
+            "Path",
+            "/t",
+            "REG_EXPAND_SZ",
+            "/d",
+            &path,
+            "/f",
+        ])
+        .output()
+        .context("failed to run reg")?
+        .status;
+
+    if !status.success() {
+        anyhow::bail!(r"failed to update HKCU\Environment");
+    }
+
+    broadcast_environment_change();
+
+    Ok(())
+}
+
+/// Tell Explorer the environment changed, so new terminals get the PATH without logging out.
+#[cfg(windows)]
+fn broadcast_environment_change() {
+    use std::ffi::OsStr;
+    use std::os::windows::ffi::OsStrExt;
+    use winapi::um::winuser::{
+        SendMessageTimeoutW, HWND_BROADCAST, SMTO_ABORTIFHUNG, WM_SETTINGCHANGE,
+    };
+
+    let environment: Vec<u16> = OsStr::new("Environment")
+        .encode_wide()
+        .chain(Some(0))
+        .collect();
+
+    unsafe {
+        SendMessageTimeoutW(
+            HWND_BROADCAST,
+            WM_SETTINGCHANGE,
+            0,
+            environment.as_ptr() as isize,
+            SMTO_ABORTIFHUNG,
+            5000,
+            std::ptr::null_mut(),
+        );
+    }
+}
+
+#[cfg(not(windows))]
+fn broadcast_environment_change() {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_shims_for_each_binary() {
+        let root = std::env::temp_dir().join(format!("volt-global-{}", std::process::id()));
+        let bin_dir = root.join("bin");
+        create_dir_all(&bin_dir).unwrap();
+
+        let package = VoltPackage {
+            name: String::from("@scope/tool"),
+            version: String::from("1.0.0"),
+            tarball: String::new(),
+            sha1: String::new(),
+            integrity: None,
+            peer_dependencies: vec![],
+            dependencies: None,
+            optional_dependencies: vec![],
+            bin: Some(
+                vec![(String::from("tool"), String::from("./cli.js"))]
+                    .into_iter()
+                    .collect(),
+            ),
+            os: vec![],
+            cpu: vec![],
+            libc: vec![],
+            source: Default::default(),
+            deprecated: None,
+            engines: Default::default(),
+        };
+
+        let names = write_shims(&bin_dir, &root.join("node_modules"), &package).unwrap();
+
+        assert_eq!(names, vec![String::from("tool")]);
+
+        let shim = fs::read_to_string(bin_dir.join("tool")).unwrap();
+        assert!(shim.starts_with("#!/bin/sh\n"));
+        assert!(shim.contains("node_modules/@scope/tool/./cli.js"));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,217 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Record the responses of registries to disk and replay them later.
+//!
+//! `VOLT_HTTP_RECORD=<dir>` keeps every response to a request sent through
+//! [`network::send`](crate::network::send) (packuments, tarballs) in `<dir>`, one JSON file per
+//! request. `VOLT_HTTP_REPLAY=<dir>` answers those requests from `<dir>` without any network,
+//! and fails the ones which were never recorded. Integration tests of the resolver run against
+//! replays, and a recording attached to an issue reproduces a failing install anywhere.
+//!
+//! Requests are told apart by method, url and `Accept` header, registries answer the abbreviated
+//! and the full packument on the same url. Request headers aren't kept, so credentials never end
+//! up in a recording.
+
+use std::collections::BTreeMap;
+use std::fs::{create_dir_all, read_to_string, write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context, Result};
+use reqwest::{RequestBuilder, Response};
+use serde::{Deserialize, Serialize};
+
+use crate::app::App;
+
+pub const RECORD_ENV: &str = "VOLT_HTTP_RECORD";
+pub const REPLAY_ENV: &str = "VOLT_HTTP_REPLAY";
+
+/// Response headers which describe the transfer rather than the response.
+const TRANSFER_HEADERS: [&str; 4] = [
+    "content-encoding",
+    "content-length",
+    "set-cookie",
+    "transfer-encoding",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Mode {
+    Record(PathBuf),
+    Replay(PathBuf),
+}
+
+impl Mode {
+    /// The mode chosen in the environment, replaying over recording.
+    pub fn from_env() -> Option<Self> {
+        let dir = |name| std::env::var_os(name).filter(|dir| !dir.is_empty());
+
+        dir(REPLAY_ENV)
+            .map(|dir| Mode::Replay(PathBuf::from(dir)))
+            .or_else(|| dir(RECORD_ENV).map(|dir| Mode::Record(PathBuf::from(dir))))
+    }
+}
+
+/// What a recorded request is found by.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Key {
+    pub method: String,
+    pub url: String,
+    pub accept: Option<String>,
+}
+
+impl Key {
+    pub fn of(request: &RequestBuilder) -> Result<Self> {
+        let request = request
+            .try_clone()
+            .ok_or_else(|| anyhow!("can't record a request with a streamed body"))?
+            .build()?;
+
+        Ok(Key {
+            method: request.method().to_string(),
+            url: request.url().to_string(),
+            accept: request
+                .headers()
+                .get("Accept")
+                .and_then(|value| value.to_str().ok())
+                .map(String::from),
+        })
+    }
+
+    /// The file of the recording in `dir`.
+    fn path(&self, dir: &Path) -> PathBuf {
+        let key = format!(
+            "{} {} {}",
+            self.method,
+            self.url,
+            self.accept.as_deref().unwrap_or_default()
+        );
+        let hash = App::calc_hash(&bytes::Bytes::from(key)).unwrap_or_default();
+
+        dir.join(format!("{}.json", hash))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Recording {
+    #[serde(flatten)]
+    key: Key,
+    status: u16,
+    headers: BTreeMap<String, String>,
+    /// Base64, tarballs are binary
+    body: String,
+}
+
+fn to_response(recording: Recording) -> Result<Response> {
+    let mut response = http::Response::builder().status(recording.status);
+
+    for (name, value) in recording.headers.iter() {
+        response = response.header(name.as_str(), value.as_str());
+    }
+
+    let body = base64::decode(&recording.body).context("invalid body")?;
+
+    Ok(Response::from(response.body(body)?))
+}
+
+/// The recorded response to `key` from `dir`.
+pub fn replay(dir: &Path, key: &Key) -> Result<Response> {
+    let path = key.path(dir);
+
+    let text = read_to_string(&path).map_err(|_| {
+        anyhow!(
+            "{} {} was not recorded in {}, record it with {}={}",
+            key.method,
+            key.url,
+            dir.display(),
+            RECORD_ENV,
+            dir.display()
+        )
+    })?;
+
+    let recording = serde_json::from_str(&text)
+        .with_context(|| format!("invalid recording {}", path.display()))?;
+
+    to_response(recording).with_context(|| format!("invalid recording {}", path.display()))
+}
+
+/// Keep `response` to `key` in `dir`, returning it with the body read.
+pub async fn record(dir: &Path, key: Key, response: Response) -> Result<Response> {
+    let status = response.status().as_u16();
+    let headers = response
+        .headers()
+        .iter()
+        .filter(|(name, _)| !TRANSFER_HEADERS.contains(&name.as_str()))
+        .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+        .collect();
+    let body = response.bytes().await?;
+
+    let recording = Recording {
+        key,
+        status,
+        headers,
+        body: base64::encode(&body),
+    };
+
+    create_dir_all(dir)?;
+    write(
+        recording.key.path(dir),
+        serde_json::to_string_pretty(&recording)?,
+    )?;
+
+    to_response(recording)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn replays_what_was_recorded() {
+        let dir = std::env::temp_dir().join(format!("volt-http-recording-{}", std::process::id()));
+        let client = reqwest::Client::new();
+
+        let request = client
+            .get("https://registry.npmjs.org/leftish")
+            .header("Accept", "application/json");
+        let key = Key::of(&request).unwrap();
+
+        let response = http::Response::builder()
+            .status(404)
+            .header("content-type", "application/json")
+            .body(r#"{"error":"Not found"}"#)
+            .unwrap();
+
+        record(&dir, key.clone(), Response::from(response))
+            .await
+            .unwrap();
+
+        let replayed = replay(&dir, &key).unwrap();
+        assert_eq!(replayed.status().as_u16(), 404);
+        assert_eq!(replayed.headers()["content-type"], "application/json");
+        assert_eq!(replayed.text().await.unwrap(), r#"{"error":"Not found"}"#);
+
+        // The abbreviated packument is another request
+        let abbreviated = Key::of(
+            &client
+                .get("https://registry.npmjs.org/leftish")
+                .header("Accept", "application/vnd.npm.install-v1+json"),
+        )
+        .unwrap();
+        assert!(replay(&dir, &abbreviated).is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
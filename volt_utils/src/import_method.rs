@@ -0,0 +1,213 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! How files of the store are materialized in node_modules.
+//!
+//! Files are cloned (reflinks on btrfs, XFS and APFS) where the filesystem supports it,
+//! hardlinked where the store and node_modules share a filesystem, and copied otherwise. Which
+//! of these works is probed with a scratch file the first time a store is linked into a
+//! filesystem, and kept per pair of mount points in `<cache dir>/import-methods.json`.
+//!
+//! `package-import-method=clone|hardlink|copy` in `.voltrc` forces a method, failing instead of
+//! falling back, to debug installs on unusual filesystems. `auto` is the default.
+
+use std::collections::BTreeMap;
+use std::fs::{self, create_dir_all, read_to_string, write};
+use std::io;
+use std::path::Path;
+use std::sync::Mutex;
+
+use anyhow::{anyhow, Context, Result};
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+
+use crate::app::App;
+use crate::reporter;
+
+const METHODS_FILE: &str = "import-methods.json";
+
+/// Tried in order when probing.
+pub const CHAIN: [ImportMethod; 3] = [
+    ImportMethod::Clone,
+    ImportMethod::Hardlink,
+    ImportMethod::Copy,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportMethod {
+    Clone,
+    Hardlink,
+    Copy,
+}
+
+impl ImportMethod {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "clone" => Some(ImportMethod::Clone),
+            "hardlink" => Some(ImportMethod::Hardlink),
+            "copy" => Some(ImportMethod::Copy),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ImportMethod::Clone => "clone",
+            ImportMethod::Hardlink => "hardlink",
+            ImportMethod::Copy => "copy",
+        }
+    }
+
+    /// Materialize the file `src` at `dest`.
+    pub fn import(self, src: &Path, dest: &Path) -> io::Result<()> {
+        match self {
+            ImportMethod::Clone => reflink_copy::reflink(src, dest),
+            ImportMethod::Hardlink => fs::hard_link(src, dest),
+            ImportMethod::Copy => fs::copy(src, dest).map(|_| ()),
+        }
+    }
+}
+
+lazy_static! {
+    /// Mount points → method, read from the cache directory once per process.
+    static ref PROBED: Mutex<Option<BTreeMap<String, ImportMethod>>> = Mutex::new(None);
+}
+
+/// Something identifying the filesystem `path` is on.
+#[cfg(unix)]
+fn mount_point(path: &Path) -> String {
+    use std::os::unix::fs::MetadataExt;
+
+    fs::metadata(path)
+        .map(|metadata| metadata.dev().to_string())
+        .unwrap_or_default()
+}
+
+/// Something identifying the filesystem `path` is on.
+#[cfg(not(unix))]
+fn mount_point(path: &Path) -> String {
+    path.canonicalize()
+        .ok()
+        .and_then(|path| path.components().next())
+        .map(|prefix| prefix.as_os_str().to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+/// The first method of the chain which works from `store` to `root`.
+pub fn probe(store: &Path, root: &Path) -> Result<ImportMethod> {
+    let name = format!(".import-probe-{}", std::process::id());
+    let src = store.join(&name);
+    let dest = root.join(&name);
+
+    write(&src, "volt")?;
+
+    let method = CHAIN.iter().copied().find(|method| {
+        let _ = fs::remove_file(&dest);
+        let imported = method.import(&src, &dest).is_ok();
+        let _ = fs::remove_file(&dest);
+
+        imported
+    });
+
+    fs::remove_file(&src)?;
+
+    method.ok_or_else(|| anyhow!("failed to copy files from {}", store.display()))
+}
+
+/// The method to materialize the files of `store` under `root` with.
+pub fn resolve(app: &App, store: &Path, root: &Path) -> Result<ImportMethod> {
+    match app.config.get("package-import-method") {
+        None | Some("auto") => {}
+        Some(value) => {
+            return ImportMethod::parse(value)
+                .ok_or_else(|| anyhow!("invalid package-import-method: {}", value))
+        }
+    }
+
+    create_dir_all(store)?;
+    create_dir_all(root)?;
+
+    let key = format!("{}:{}", mount_point(store), mount_point(root));
+    let path = app.cache_dir.join(METHODS_FILE);
+
+    let mut probed = PROBED.lock().unwrap();
+    let probed = probed.get_or_insert_with(|| read_probed(&path));
+
+    if let Some(method) = probed.get(&key) {
+        return Ok(*method);
+    }
+
+    let method = probe(store, root)
+        .with_context(|| format!("failed to probe how to link into {}", root.display()))?;
+
+    if app.has_flag(&["--verbose"]) {
+        reporter::get().info(
+            "Importing",
+            &format!("files into {} with {}", root.display(), method.name()),
+        );
+    }
+
+    probed.insert(key, method);
+
+    // Probing again next time is fine if the result can't be kept
+    let _ = write_probed(&path, probed);
+
+    Ok(method)
+}
+
+fn read_probed(path: &Path) -> BTreeMap<String, ImportMethod> {
+    read_to_string(path)
+        .ok()
+        .and_then(|data| serde_json::from_str(&data).ok())
+        .unwrap_or_default()
+}
+
+fn write_probed(path: &Path, probed: &BTreeMap<String, ImportMethod>) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        create_dir_all(parent)?;
+    }
+
+    write(path, serde_json::to_string_pretty(probed)?)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn probes_a_working_method() {
+        let root = std::env::temp_dir().join(format!("volt-import-{}", std::process::id()));
+        let store = root.join("store");
+        let node_modules = root.join("node_modules");
+
+        create_dir_all(&store).unwrap();
+        create_dir_all(&node_modules).unwrap();
+
+        // Both are on the same filesystem, so cloning or hardlinking works
+        let method = probe(&store, &node_modules).unwrap();
+        assert_ne!(method, ImportMethod::Copy);
+        assert_eq!(fs::read_dir(&store).unwrap().count(), 0);
+        assert_eq!(fs::read_dir(&node_modules).unwrap().count(), 0);
+
+        assert_eq!(ImportMethod::parse("clone"), Some(ImportMethod::Clone));
+        assert_eq!(ImportMethod::parse("reflink"), None);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,257 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Install journal, to roll a failed install back.
+//!
+//! Before an install changes the project, package.json, the lock file and the linker state are
+//! copied into `.volt/journal`, and every package moved into node_modules is
+//! recorded there before the move happens. When the install fails or is interrupted with
+//! Ctrl-C, the journal is replayed backwards to put the project back as it was. A journal left
+//! behind by a killed process is replayed by the next install, which then resumes the work with
+//! the downloads and extractions already done (see [resume](crate::resume)).
+
+use std::fs::{self, create_dir_all, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::app::App;
+use crate::project_state::{self, INSTALL_STATE_FILE, JOURNAL_DIR};
+
+/// Operations, one JSON object per line.
+const OPERATIONS_FILE: &str = "operations";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "type")]
+pub enum Operation {
+    /// `path` did not exist before the install
+    Create { path: PathBuf },
+    /// `path` was copied to `backup` before being changed
+    Backup { path: PathBuf, backup: PathBuf },
+    /// `path` was moved to `backup` to make room for its replacement
+    Replace { path: PathBuf, backup: PathBuf },
+}
+
+impl Operation {
+    fn undo(&self) -> Result<()> {
+        match self {
+            Operation::Create { path } => remove(path),
+            Operation::Backup { path, backup } => {
+                fs::copy(backup, path)
+                    .with_context(|| format!("failed to restore {}", path.display()))?;
+                Ok(())
+            }
+            // Not moved yet if the backup doesn't exist, then `path` is still the original
+            Operation::Replace { path, backup } if backup.exists() => {
+                remove(path)?;
+                fs::rename(backup, path)
+                    .with_context(|| format!("failed to restore {}", path.display()))?;
+                Ok(())
+            }
+            Operation::Replace { .. } => Ok(()),
+        }
+    }
+}
+
+/// Check if an install of the project was killed before it could finish or roll back.
+pub fn is_pending(app: &App) -> bool {
+    project_state::path(app, JOURNAL_DIR).exists()
+}
+
+pub struct Journal {
+    dir: PathBuf,
+    file: File,
+    backups: usize,
+}
+
+impl Journal {
+    /// Start the journal of an install, rolling back the one an interrupted install left.
+    pub fn begin(app: &App) -> Result<Self> {
+        let dir = project_state::path(app, JOURNAL_DIR);
+
+        if dir.exists() {
+            rollback(&dir)?;
+        }
+
+        let created_node_modules = !app.node_modules_dir.exists();
+
+        project_state::create(app)?;
+        create_dir_all(&dir)?;
+
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(dir.join(OPERATIONS_FILE))?;
+
+        let mut journal = Journal {
+            dir,
+            file,
+            backups: 0,
+        };
+
+        if created_node_modules {
+            journal.write(&Operation::Create {
+                path: app.node_modules_dir.clone(),
+            })?;
+        }
+
+        journal.backup(&app.current_dir.join("package.json"))?;
+        journal.backup(&app.lock_file_path)?;
+        journal.backup(&project_state::path(app, INSTALL_STATE_FILE))?;
+
+        Ok(journal)
+    }
+
+    fn write(&mut self, operation: &Operation) -> Result<()> {
+        writeln!(self.file, "{}", serde_json::to_string(operation)?)?;
+        self.file.sync_data()?;
+
+        Ok(())
+    }
+
+    fn next_backup(&mut self) -> PathBuf {
+        self.backups += 1;
+        self.dir.join(self.backups.to_string())
+    }
+
+    /// Keep a copy of the file at `path` before it is changed.
+    pub fn backup(&mut self, path: &Path) -> Result<()> {
+        if !path.exists() {
+            return self.write(&Operation::Create {
+                path: path.to_path_buf(),
+            });
+        }
+
+        let backup = self.next_backup();
+        fs::copy(path, &backup)?;
+
+        self.write(&Operation::Backup {
+            path: path.to_path_buf(),
+            backup,
+        })
+    }
+
+    /// Record that `path` is about to be created or replaced, moving what is there aside.
+    pub fn replace(&mut self, path: &Path) -> Result<()> {
+        if fs::symlink_metadata(path).is_err() {
+            return self.write(&Operation::Create {
+                path: path.to_path_buf(),
+            });
+        }
+
+        let backup = self.next_backup();
+
+        self.write(&Operation::Replace {
+            path: path.to_path_buf(),
+            backup: backup.clone(),
+        })?;
+
+        fs::rename(path, &backup)
+            .with_context(|| format!("failed to move {} aside", path.display()))?;
+
+        Ok(())
+    }
+
+    /// The install succeeded, drop the backups.
+    pub fn commit(self) -> Result<()> {
+        drop(self.file);
+        crate::fs::remove_dir_all(&self.dir)?;
+
+        Ok(())
+    }
+
+    /// The install failed, put everything back.
+    pub fn rollback(self) -> Result<()> {
+        drop(self.file);
+        rollback(&self.dir)
+    }
+}
+
+fn remove(path: &Path) -> Result<()> {
+    match fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.is_dir() => crate::fs::remove_dir_all(path)?,
+        Ok(_) => fs::remove_file(path)?,
+        Err(_) => {}
+    }
+
+    Ok(())
+}
+
+/// Undo the operations in the journal at `dir`, latest first, and remove it.
+pub fn rollback(dir: &Path) -> Result<()> {
+    let operations: Vec<Operation> = match File::open(dir.join(OPERATIONS_FILE)) {
+        // The last line may be cut short if the process was killed while writing it
+        Ok(file) => BufReader::new(file)
+            .lines()
+            .map_while(|line| line.ok())
+            .filter_map(|line| serde_json::from_str(&line).ok())
+            .collect(),
+        Err(_) => vec![],
+    };
+
+    for operation in operations.iter().rev() {
+        operation.undo()?;
+    }
+
+    crate::fs::remove_dir_all(dir)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rolls_back_replacements_and_backups() {
+        let root = std::env::temp_dir().join(format!("volt-journal-{}", std::process::id()));
+        let dir = root.join(JOURNAL_DIR);
+        let package = root.join("a");
+
+        create_dir_all(&package).unwrap();
+        fs::write(package.join("index.js"), "old").unwrap();
+        fs::write(root.join("package.json"), "{}").unwrap();
+
+        let mut journal = Journal {
+            file: {
+                create_dir_all(&dir).unwrap();
+                File::create(dir.join(OPERATIONS_FILE)).unwrap()
+            },
+            dir,
+            backups: 0,
+        };
+
+        journal.backup(&root.join("package.json")).unwrap();
+        journal.replace(&package).unwrap();
+        journal.replace(&root.join("b")).unwrap();
+
+        fs::write(root.join("package.json"), r#"{"dependencies": {}}"#).unwrap();
+        create_dir_all(&package).unwrap();
+        fs::write(package.join("index.js"), "new").unwrap();
+        create_dir_all(root.join("b")).unwrap();
+
+        journal.rollback().unwrap();
+
+        assert_eq!(fs::read_to_string(root.join("package.json")).unwrap(), "{}");
+        assert_eq!(fs::read_to_string(package.join("index.js")).unwrap(), "old");
+        assert!(!root.join("b").exists());
+        assert!(!root.join(JOURNAL_DIR).exists());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
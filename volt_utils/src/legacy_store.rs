@@ -0,0 +1,176 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Move the entries of older stores to where they are kept now.
+//!
+//! Entries used to be named after their package alone (`<volt dir>/@types/node`), and are now
+//! named after their version and checksum too, see [store_name](crate::volt_api::store_name).
+//! Old entries with an [integrity record](crate::scrub) are renamed, the others can't be told
+//! apart from another build of their version and are removed, to be fetched again when a project
+//! needs them. The store is looked at once, `<volt dir>/.integrity/.layout` remembers it was.
+
+use std::fs::{self, read_dir, read_to_string};
+use std::path::Path;
+
+use anyhow::Result;
+use serde_json::Value;
+
+use crate::scrub::{self, Integrity};
+use crate::volt_api::store_name;
+
+/// Written once the store has no old entries anymore.
+const LAYOUT_FILE: &str = ".layout";
+
+/// The layout of the store, entries named after their version and checksum.
+pub const LAYOUT: &str = "2";
+
+/// The directories of `volt_dir` which are entries of the old layout: their package.json names
+/// them after their path, which entries of the current layout never are.
+fn legacy_entries(volt_dir: &Path) -> Vec<String> {
+    let dirs = |dir: &Path, prefix: &str| -> Vec<String> {
+        read_dir(dir)
+            .map(|entries| {
+                entries
+                    .filter_map(|entry| entry.ok())
+                    .filter(|entry| entry.path().is_dir())
+                    .map(|entry| format!("{}{}", prefix, entry.file_name().to_string_lossy()))
+                    .filter(|name| !name.starts_with('.'))
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+
+    dirs(volt_dir, "")
+        .into_iter()
+        .flat_map(|name| {
+            if name.starts_with('@') {
+                dirs(&volt_dir.join(&name), &format!("{}/", name))
+            } else {
+                vec![name]
+            }
+        })
+        .filter(|name| {
+            read_to_string(volt_dir.join(name).join("package.json"))
+                .ok()
+                .and_then(|text| serde_json::from_str::<Value>(&text).ok())
+                .is_some_and(|manifest| manifest["name"] == name.as_str())
+        })
+        .collect()
+}
+
+/// Rename the entries of the old layout in `volt_dir`, or remove those without a record.
+/// Returns how many entries were renamed and removed.
+pub fn migrate(volt_dir: &Path) -> Result<(usize, usize)> {
+    let layout = volt_dir.join(scrub::INTEGRITY_DIR).join(LAYOUT_FILE);
+
+    if read_to_string(&layout).is_ok_and(|text| text.trim() == LAYOUT) {
+        return Ok((0, 0));
+    }
+
+    let mut renamed = 0;
+    let mut removed = 0;
+
+    for name in legacy_entries(volt_dir) {
+        let dir = volt_dir.join(&name);
+        let record = scrub::record_path(volt_dir, &name);
+
+        let integrity: Option<Integrity> = read_to_string(&record)
+            .ok()
+            .and_then(|text| serde_json::from_str(&text).ok());
+
+        let target = integrity
+            .as_ref()
+            .map(|integrity| store_name(&name, &integrity.version, &integrity.sha1));
+
+        match (integrity, target) {
+            (Some(mut integrity), Some(target)) if !volt_dir.join(&target).exists() => {
+                fs::rename(&dir, volt_dir.join(&target))?;
+
+                integrity.name = name.clone();
+                fs::write(
+                    scrub::record_path(volt_dir, &target),
+                    serde_json::to_string(&integrity)?,
+                )?;
+                renamed += 1;
+            }
+            _ => {
+                crate::fs::remove_dir_all(&dir)?;
+                removed += 1;
+            }
+        }
+
+        let _ = fs::remove_file(&record);
+    }
+
+    if let Some(parent) = layout.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    fs::write(layout, LAYOUT)?;
+
+    Ok((renamed, removed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn renames_recorded_entries_and_removes_the_others() {
+        let volt_dir =
+            std::env::temp_dir().join(format!("volt-legacy-store-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&volt_dir);
+
+        let entry = |dir: &str, name: &str| {
+            fs::create_dir_all(volt_dir.join(dir)).unwrap();
+            fs::write(
+                volt_dir.join(dir).join("package.json"),
+                format!(r#"{{ "name": "{}", "version": "1.0.0" }}"#, name),
+            )
+            .unwrap();
+        };
+
+        entry("@types/node", "@types/node");
+        entry("left-pad", "left-pad");
+        entry("right-pad@1.0.0-0123456789ab", "right-pad");
+
+        let integrity = Integrity {
+            name: String::new(),
+            version: String::from("16.4.0"),
+            tarball: String::new(),
+            sha1: String::from("0123456789abcdef"),
+            files: BTreeMap::new(),
+        };
+        let record = scrub::record_path(&volt_dir, "@types/node");
+        fs::create_dir_all(record.parent().unwrap()).unwrap();
+        fs::write(&record, serde_json::to_string(&integrity).unwrap()).unwrap();
+
+        assert_eq!(migrate(&volt_dir).unwrap(), (1, 1));
+
+        assert!(volt_dir.join("@types/node@16.4.0-0123456789ab").exists());
+        assert!(scrub::record_path(&volt_dir, "@types/node@16.4.0-0123456789ab").exists());
+        assert!(!record.exists());
+        assert!(!volt_dir.join("left-pad").exists());
+        assert!(volt_dir.join("right-pad@1.0.0-0123456789ab").exists());
+
+        // Once migrated, the store isn't looked at again
+        entry("left-pad", "left-pad");
+        assert_eq!(migrate(&volt_dir).unwrap(), (0, 0));
+
+        fs::remove_dir_all(&volt_dir).unwrap();
+    }
+}
@@ -1,31 +1,72 @@
 pub mod app;
+pub mod bins;
+pub mod config;
+pub mod dedup;
+pub mod diagnostics;
+pub mod explain;
+pub mod fetcher;
+pub mod fs;
+pub mod git;
+pub mod github_packages;
+pub mod global;
+pub mod http_recording;
+pub mod import_method;
+pub mod journal;
+pub mod legacy_store;
+pub mod linker;
+pub mod metadata_db;
+pub mod metrics;
+pub mod minimize;
+pub mod mirror;
+pub mod network;
+pub mod pack;
 pub mod package;
+pub mod paths;
+pub mod peers;
+pub mod pipeline;
+pub mod platform;
+pub mod policy;
+pub mod project_state;
+pub mod provenance;
+pub mod quota;
+pub mod registry;
+pub mod registry_compat;
+pub mod registry_mirrors;
+pub mod report;
+pub mod reporter;
+pub mod resolver;
+pub mod resume;
+pub mod scrub;
+pub mod snapshot;
+pub mod sri;
+pub mod staging;
+pub mod store_compression;
+pub mod store_lock;
+pub mod tarball_dir;
+pub mod upstream;
+pub mod uring;
 pub mod volt_api;
+pub mod volt_version;
+pub mod warnings;
+pub mod workspace;
 use anyhow::Context;
-use chttp::{self, ResponseExt};
 use colored::Colorize;
 use flate2::read::GzDecoder;
-use futures_util::stream::FuturesUnordered;
-use futures_util::StreamExt;
-use indicatif::{ProgressBar, ProgressStyle};
 use std::borrow::Cow;
 use std::env::temp_dir;
-use std::fs::remove_dir_all;
 use std::io::Write;
 use std::path::{Path, PathBuf};
 use std::process;
 use std::sync::Arc;
 use tar::Archive;
 use tokio::fs::create_dir_all;
-use tokio::fs::hard_link;
-use walkdir::WalkDir;
 
 use anyhow::Error;
 use anyhow::Result;
 use app::App;
 use lazy_static::lazy_static;
 use package::Package;
-use volt_api::{VoltPackage, VoltResponse};
+use volt_api::VoltPackage;
 
 pub static PROGRESS_CHARS: &str = "=> ";
 
@@ -36,242 +77,78 @@ lazy_static! {
 pub async fn create_dependency_links(
     app: Arc<App>,
     packages: std::collections::HashMap<String, VoltPackage>,
+    journal: Option<&mut journal::Journal>,
 ) -> Result<()> {
-    let mut workers = FuturesUnordered::new();
-
-    for package in packages {
-        let package_instance = package.clone();
-        let app_instance = app.clone();
-        let volt_directory_location = format!("{}", app.volt_dir.display());
-
-        workers.push(async move {
-            // Hardlink Files
-            hardlink_files(
-                app_instance,
-                format!(r"{}\{}", volt_directory_location, package_instance.1.name),
-            )
-            .await;
-        });
+    let mut state = linker::InstallState::from_config(&app.config);
+
+    if let Some(previous) = linker::InstallState::load(&app) {
+        linker::relocate_virtual_store(&app, &previous, &state)?;
+        state.public_hoisted = previous.public_hoisted;
     }
 
-    while workers.next().await.is_some() {}
+    if state.node_linker == linker::NodeLinker::Isolated {
+        linker::link_isolated(&app, &mut state, &packages, journal)?;
+        return state.save(&app);
+    }
 
-    Ok(())
-}
+    // Packages are swapped in once they are all complete
+    let method = import_method::resolve(&app, &app.volt_dir, &app.node_modules_dir)?;
+    let mut staging = staging::Staging::new(&app.node_modules_dir, method)?;
 
-// Get response from volt CDN
-pub async fn get_volt_response(package_name: String) -> VoltResponse {
-    let response = chttp::get_async(format!("http://volt-api.b-cdn.net/{}.json", package_name))
-        .await
-        .unwrap_or_else(|_| {
-            println!("{}: package does not exist", "error".bright_red(),);
-            std::process::exit(1);
-        })
-        .text_async()
-        .await
-        .unwrap_or_else(|_| {
-            println!("{}: package does not exist", "error".bright_red());
-            std::process::exit(1);
-        });
-
-    // serde_json::from_str::<VoltResponse>(
-    //     r#"{"version": "2.0.116", "2.0.116": {"packages": {"swot-node": {"name": "swot-node", "version": "2.0.116", "tarball": "https://registry.npmjs.org/swot-node/-/swot-node-2.0.116.tgz", "sha1": "44cde2592f55b87c64d955fa0812e87998a18916", "dependencies": ["academic-tld", "tldjs"], "peerDependencies": []}, "academic-tld": {"name": "academic-tld", "version": "1.0.5", "tarball": "https://registry.npmjs.org/academic-tld/-/academic-tld-1.0.5.tgz", "sha1": "38bb955a41a7ee64f9d1fbed653fcb6110c4723d", "dependencies": [], "peerDependencies": []}, "tldjs": {"name": "tldjs", "version": "2.3.1", "tarball": "https://registry.npmjs.org/tldjs/-/tldjs-2.3.1.tgz", "sha1": "cf09c3eb5d7403a9e214b7d65f3cf9651c0ab039", "dependencies": ["punycode"], "peerDependencies": []}, "punycode": {"name": "punycode", "version": "1.4.1", "tarball": "https://registry.npmjs.org/punycode/-/punycode-1.4.1.tgz", "sha1": "c0d5a63b2718800ad8e1eb0fa5269c84dd41845e", "dependencies": [], "peerDependencies": []}}}}
-    // "#,
-    // ).unwrap()
-    serde_json::from_str::<VoltResponse>(&response).unwrap_or_else(|_| {
-        println!(
-            "{}: failed to parse response from server",
-            "error".bright_red()
-        );
-        std::process::exit(1);
-    })
-}
-#[cfg(windows)]
-pub async fn hardlink_files(app: Arc<App>, src: String) {
-    let mut src = src;
-    let volt_directory = format!("{}", app.volt_dir.display());
+    for package in packages.values() {
+        let dest = app.node_modules_dir.join(&package.name);
 
-    if !cfg!(target_os = "windows") {
-        src = src.replace(r"\", "/");
+        if !staging::is_installed(&dest, &package.version) {
+            staging.stage(&app.volt_dir.join(package.store_name()), &dest)?;
+        }
     }
 
-    for entry in WalkDir::new(src) {
-        let entry = entry.unwrap();
-
-        if !entry.path().is_dir() {
-            // index.js
-            let file_name = &entry.path().file_name().unwrap().to_str().unwrap();
-
-            // lib/index.js
-            let path = format!("{}", &entry.path().display())
-                .replace(r"\", "/")
-                .replace(&volt_directory, "");
-
-            // node_modules/lib
-            create_dir_all(format!(
-                "node_modules/{}",
-                &path
-                    .replace(
-                        format!("{}", &app.volt_dir.display())
-                            .replace(r"\", "/")
-                            .as_str(),
-                        ""
-                    )
-                    .trim_end_matches(file_name)
-            ))
-            .await
-            .unwrap();
+    staging.commit(journal)?;
 
-            // ~/.volt/package/lib/index.js -> node_modules/package/lib/index.js
-            if !Path::new(&format!(
-                "node_modules{}",
-                &path.replace(
-                    format!("{}", &app.volt_dir.display())
-                        .replace(r"\", "/")
-                        .as_str(),
-                    ""
-                )
-            ))
-            .exists()
-            {
-                hard_link(
-                    format!("{}", &path),
-                    format!(
-                        "node_modules{}",
-                        &path.replace(
-                            format!("{}", &app.volt_dir.display())
-                                .replace(r"\", "/")
-                                .as_str(),
-                            ""
-                        )
-                    ),
-                )
-                .await
-                .unwrap_or_else(|e| {
-                    panic!(
-                        "{:#?}",
-                        (
-                            format!("{}", &path),
-                            format!(
-                                "node_modules{}",
-                                &path.replace(
-                                    format!("{}", &app.volt_dir.display())
-                                        .replace(r"\", "/")
-                                        .as_str(),
-                                    ""
-                                )
-                            ),
-                        )
-                    )
-                });
-            }
-        }
+    state.save(&app)
+}
+
+/// downloads tarball file from package and clears the old copy out of node_modules
+pub async fn download_tarball(app: &App, package: &VoltPackage) -> Result<String> {
+    if !resume::is_extracted(app, &package.store_name()) {
+        clear_node_modules_entry(app, &package.name).await?;
     }
+
+    fetch_package(app, package).await
 }
 
-#[cfg(unix)]
-pub async fn hardlink_files(app: Arc<App>, src: String) {
-    let mut src = src;
-    let volt_directory = format!("{}", app.volt_dir.display());
+/// Remove the copy of a freshly fetched package from node_modules so it is linked again.
+pub async fn clear_node_modules_entry(app: &App, name: &str) -> Result<()> {
+    // Create node_modules
+    create_dir_all(&app.node_modules_dir).await?;
 
-    if !cfg!(target_os = "windows") {
-        src = src.replace(r"\", "/");
-    }
+    // Delete package from node_modules
+    let node_modules_dep_path = app.node_modules_dir.join(name);
 
-    for entry in WalkDir::new(src) {
-        let entry = entry.unwrap();
-
-        if !entry.path().is_dir() {
-            // index.js
-            let file_name = &entry.path().file_name().unwrap().to_str().unwrap();
-
-            // lib/index.js
-            let path = format!("{}", &entry.path().display())
-                .replace(r"\", "/")
-                .replace(&volt_directory, "");
-
-            // node_modules/lib
-            create_dir_all(format!(
-                "node_modules/{}",
-                &path
-                    .replace(
-                        format!("{}", &app.volt_dir.display())
-                            .replace(r"\", "/")
-                            .as_str(),
-                        ""
-                    )
-                    .trim_end_matches(file_name)
-            ))
-            .await
-            .unwrap();
+    if node_modules_dep_path.exists() {
+        fs::remove_dir_all(&node_modules_dep_path)?;
+    }
 
-            // ~/.volt/package/lib/index.js -> node_modules/package/lib/index.js
-            if !Path::new(&format!(
-                "node_modules{}",
-                &path.replace(
-                    format!("{}", &app.volt_dir.display())
-                        .replace(r"\", "/")
-                        .as_str(),
-                    ""
-                )
-            ))
-            .exists()
-            {
-                hard_link(
-                    format!("{}/.volt/{}", std::env::var("HOME").unwrap(), path),
-                    format!(
-                        "{}/node_modules{}",
-                        std::env::current_dir().unwrap().to_string_lossy(),
-                        &path.replace(
-                            format!("{}", &app.volt_dir.display())
-                                .replace(r"\", "/")
-                                .as_str(),
-                            ""
-                        )
-                    ),
-                )
-                .await
-                .unwrap_or_else(|e| {
-                    panic!(
-                        "{:#?}",
-                        (
-                            format!("{}", &path),
-                            format!(
-                                "node_modules{}",
-                                &path.replace(
-                                    format!("{}", &app.volt_dir.display())
-                                        .replace(r"\", "/")
-                                        .as_str(),
-                                    ""
-                                )
-                            ),
-                        )
-                    )
-                });
-            }
+    if let Some(parent) = node_modules_dep_path.parent() {
+        if !parent.exists() {
+            create_dir_all(&parent).await?;
         }
     }
+
+    Ok(())
 }
 
-/// downloads tarball file from package
-pub async fn download_tarball(app: &App, package: &VoltPackage) -> Result<String> {
+/// downloads tarball file from package and extracts it into the volt store
+pub async fn fetch_package(app: &App, package: &VoltPackage) -> Result<String> {
+    // eslint@8.0.0-1a2b3c4d5e6f
+    let entry = package.store_name();
+
     // @types/eslint
     if package.clone().name.starts_with('@') && package.clone().name.contains("/") {
-        let package_directory_location;
-
-        if cfg!(target_os = "windows") {
-            package_directory_location = format!(
-                r"{}\.volt\{}",
-                std::env::var("USERPROFILE").unwrap(),
-                &package.name.split("/").collect::<Vec<&str>>()[0]
-            );
-        } else {
-            package_directory_location = format!(
-                r"{}/.volt/{}",
-                std::env::var("HOME").unwrap(),
-                &package.name.split("/").collect::<Vec<&str>>()[0]
-            );
-        }
+        // <volt dir>/@types
+        let package_directory_location = app
+            .volt_dir
+            .join(package.name.split('/').collect::<Vec<&str>>()[0]);
 
         if !Path::new(&package_directory_location).exists() {
             create_dir_all(&package_directory_location).await.unwrap();
@@ -282,36 +159,65 @@ pub async fn download_tarball(app: &App, package: &VoltPackage) -> Result<String
     let loc;
 
     if cfg!(target_os = "windows") {
-        // C:\Users\username\.volt/@types/eslint
-        loc = format!(r"{}\{}", &app.volt_dir.to_str().unwrap(), &package.name);
+        // <volt dir>\@types/eslint@8.0.0-1a2b3c4d5e6f
+        loc = format!(r"{}\{}", &app.volt_dir.to_str().unwrap(), &entry);
     } else {
-        // ~/.volt/@types/eslint
-        loc = format!(r"{}/{}", &app.volt_dir.to_str().unwrap(), &package.name);
+        // <volt dir>/@types/eslint@8.0.0-1a2b3c4d5e6f
+        loc = format!(r"{}/{}", &app.volt_dir.to_str().unwrap(), &entry);
     }
 
-    // if package is not already installed
-    if !Path::new(&loc).exists() {
-        // Url to download tarball code files from
-        let url = package.tarball.replace("https", "http");
-
-        // Get Tarball File
-        let res = reqwest::get(url).await.unwrap();
-
-        // Recieve Bytes
-        let bytes: bytes::Bytes = res.bytes().await.unwrap();
+    // Another install sharing the store may be extracting the package, and its marker would
+    // look like an interrupted extraction
+    let _lock = if resume::is_extracted(app, &entry) {
+        metrics::record_store(true);
+        None
+    } else {
+        metrics::record_store(false);
+        Some(store_lock::StoreLock::acquire(app, &entry).await?)
+    };
 
-        // Verify If Bytes == Sha1
-        if package.sha1 == App::calc_hash(&bytes).unwrap() {
-            // Create node_modules
-            create_dir_all(&app.node_modules_dir).await?;
+    // Throw away what an install killed while extracting the package left
+    if resume::is_interrupted(app, &entry) {
+        fs::remove_dir_all(Path::new(&loc))?;
+    }
 
-            // Delete package from node_modules
-            let node_modules_dep_path = app.node_modules_dir.join(&package.name);
+    // if package is not already installed
+    if !resume::is_extracted(app, &entry) {
+        // Get Tarball File, from an interrupted install or the offline mirror if there is one
+        let bytes: bytes::Bytes = match resume::read_download(app, package) {
+            Some(bytes) => bytes,
+            None => {
+                let bytes = fetcher::fetch(app, package).await?;
+                resume::save_download(app, package, &bytes)?;
+                bytes
+            }
+        };
+
+        // Some private registries publish no checksum at all, those can't be verified
+        if package.sha1.is_empty() && package.integrity.is_none() {
+            reporter::get().warn(&format!(
+                "{}@{} has no checksum, installing it unverified",
+                package.name, package.version
+            ));
+        }
 
-            if node_modules_dep_path.exists() {
-                remove_dir_all(&node_modules_dep_path)?;
+        // Lockfiles of other package managers often only record a sha512
+        if let Some(integrity) = &package.integrity {
+            if sri::verify(integrity, &bytes) == Some(false) {
+                return Err(anyhow::anyhow!(
+                    "the tarball of {}@{} doesn't match {}",
+                    package.name,
+                    package.version,
+                    integrity
+                ));
             }
+        }
+
+        // The mirror which served the tarball could have served its checksum too
+        upstream::verify(app, package, &App::calc_hash(&bytes)?).await?;
 
+        // Verify If Bytes == Sha1
+        if package.sha1.is_empty() || package.sha1 == App::calc_hash(&bytes).unwrap() {
             // Directory to extract tarball to
             let mut extract_directory = PathBuf::from(&app.volt_dir);
 
@@ -322,32 +228,43 @@ pub async fn download_tarball(app: &App, package: &VoltPackage) -> Result<String
 
                     let split = name.split(r"\").collect::<Vec<&str>>();
 
-                    // C:\Users\xtrem\.volt\@types
+                    // <volt dir>\@types
                     extract_directory = extract_directory.join(split[0]);
                 } else {
                     let name = package.clone().name;
 
                     let split = name.split('/').collect::<Vec<&str>>();
 
-                    // ~/.volt/@types
+                    // <volt dir>/@types
                     extract_directory = extract_directory.join(split[0]);
                 }
             }
 
+            resume::begin_extraction(app, &entry)?;
+
             // Initialize tarfile decoder while directly passing in bytes
             let gz_decoder = GzDecoder::new(&*bytes);
 
             let mut archive = Archive::new(gz_decoder);
 
-            // Extract the data into extract_directory
-            archive
-                .unpack(&extract_directory)
-                .context("Unable to unpack dependency")?;
+            // Extract the data into extract_directory, without what isn't needed at runtime
+            match minimize::Minimize::from_app(app) {
+                Some(minimize) => {
+                    let skipped = minimize
+                        .unpack(&mut archive, &extract_directory)
+                        .context("Unable to unpack dependency")?;
 
-            let mut idx = 0;
-            let name = package.clone().name;
+                    if app.has_flag(&["--verbose"]) {
+                        reporter::get()
+                            .info("Skipped", &format!("{} files of {}", skipped, package.name));
+                    }
+                }
+                None => uring::unpack(&mut archive, &extract_directory)
+                    .context("Unable to unpack dependency")?,
+            }
 
-            let split = name.split('/').collect::<Vec<&str>>();
+            let mut idx = 0;
+            let split = entry.split('/').collect::<Vec<&str>>();
 
             if package.clone().name.contains('@') && package.clone().name.contains('/') {
                 idx = 1;
@@ -402,11 +319,24 @@ pub async fn download_tarball(app: &App, package: &VoltPackage) -> Result<String
                     }
                 }
             }
-            if let Some(parent) = node_modules_dep_path.parent() {
-                if !parent.exists() {
-                    create_dir_all(&parent).await?;
-                }
+
+            if let Some(level) = store_compression::level(app)? {
+                store_compression::compress_dir(Path::new(&loc), level)
+                    .with_context(|| format!("failed to compress {}", package.name))?;
             }
+
+            let integrity = scrub::record(app, package)
+                .with_context(|| format!("failed to record the files of {}", package.name))?;
+
+            dedup::after_extraction(app, &entry, &integrity)
+                .with_context(|| format!("failed to deduplicate the files of {}", package.name))?;
+
+            resume::finish_extraction(app, package)?;
+        }
+    } else if let Some(mirror) = mirror::OfflineMirror::from_app(app) {
+        // Already extracted, but the mirror needs the tarball too
+        if mirror.read_tarball(package).is_none() && !mirror::is_offline(app) {
+            fetcher::fetch(app, package).await?;
         }
     }
 
@@ -414,7 +344,7 @@ pub async fn download_tarball(app: &App, package: &VoltPackage) -> Result<String
 }
 
 pub async fn download_tarball_create(
-    _app: &App,
+    app: &App,
     package: &Package,
     name: &str,
 ) -> Result<String, Error> {
@@ -426,23 +356,10 @@ pub async fn download_tarball_create(
     }
 
     if name.starts_with('@') && name.contains("__") {
-        let package_dir_loc;
-
-        if cfg!(target_os = "windows") {
-            // Check if C:\Users\username\.volt exists
-            package_dir_loc = format!(
-                r"{}\.volt\{}",
-                std::env::var("USERPROFILE").unwrap(),
-                name.split("__").collect::<Vec<&str>>()[0]
-            );
-        } else {
-            // Check if ~/.volt\packagename exists
-            package_dir_loc = format!(
-                r"{}\.volt\{}",
-                std::env::var("HOME").unwrap(),
-                name.split("__").collect::<Vec<&str>>()[0]
-            );
-        }
+        // Check if <volt dir>/@scope exists
+        let package_dir_loc = app
+            .volt_dir
+            .join(name.split("__").collect::<Vec<&str>>()[0]);
 
         if !Path::new(&package_dir_loc).exists() {
             create_dir_all(&package_dir_loc).await.unwrap();
@@ -488,6 +405,35 @@ pub async fn download_tarball_create(
     Ok(path_str)
 }
 
+/// Split a package specifier (`react@17`, `@types/node@^16`) into its name and version range.
+pub fn parse_package_spec(spec: &str) -> (String, Option<String>) {
+    // Skip the leading `@` of scoped packages when looking for the version separator
+    let scope = usize::from(spec.starts_with('@'));
+
+    match spec[scope..].find('@') {
+        Some(idx) => (
+            spec[..scope + idx].to_string(),
+            Some(spec[scope + idx + 1..].to_string()),
+        ),
+        None => (spec.to_string(), None),
+    }
+}
+
+/// Get the highest version that satisfies the `range` (`2`, `^1.2.0`, `>=3 <4`).
+pub fn max_satisfying<'a, I>(versions: I, range: &str) -> Option<String>
+where
+    I: IntoIterator<Item = &'a String>,
+{
+    let range = node_semver::Range::parse(range).ok()?;
+
+    versions
+        .into_iter()
+        .filter_map(|version| node_semver::Version::parse(version).ok())
+        .filter(|version| range.satisfies(version))
+        .max()
+        .map(|version| version.to_string())
+}
+
 pub fn get_basename(path: &'_ str) -> Cow<'_, str> {
     let sep: char;
     if cfg!(target_os = "windows") {
@@ -583,21 +529,26 @@ pub fn create_symlink(original: String, link: String) -> Result<()> {
     Ok(())
 }
 
+/// Create a symlink to a directory
+#[cfg(unix)]
+pub fn create_symlink(original: String, link: String) -> Result<()> {
+    std::os::unix::fs::symlink(original, link)?;
+    Ok(())
+}
+
+/// Write the script running the binary `bin` of `package`.
 #[cfg(windows)]
-pub fn generate_script(app: &Arc<App>, package: &VoltPackage) {
+pub fn generate_script(app: &Arc<App>, package: &VoltPackage, bin: &str) {
     use std::fs::File;
 
     // Create node_modules/scripts if it doesn't exist
-    if !Path::new("node_modules/scripts").exists() {
-        std::fs::create_dir_all("node_modules/scripts").unwrap();
+    let scripts = app.node_modules_dir.join("scripts");
+    if !scripts.exists() {
+        std::fs::create_dir_all(&scripts).unwrap();
     }
 
-    // If the package has binary scripts, create them
-    if package.bin.is_some() {
-        let bin = package.bin.as_ref().unwrap();
-
-        let k = bin.keys().next().unwrap();
-        let v = bin.values().next().unwrap();
+    if let Some(v) = package.bin.as_ref().and_then(|bins| bins.get(bin)) {
+        let k = &package.store_name();
 
         let command = format!(
             r#"
@@ -612,40 +563,36 @@ pub fn generate_script(app: &Arc<App>, package: &VoltPackage) {
         )
         .replace(r"%~dp0\..", format!("{}", app.volt_dir.display()).as_str());
 
-        let mut f = File::create(format!(r"node_modules/scripts/{}.cmd", k)).unwrap();
+        let mut f = File::create(scripts.join(format!("{}.cmd", bin))).unwrap();
         f.write_all(command.as_bytes()).unwrap();
     }
 }
 
+/// Write the script running the binary `bin` of `package`.
 #[cfg(unix)]
-pub fn generate_script(app: &Arc<App>, package: &VoltPackage) {
+pub fn generate_script(app: &Arc<App>, package: &VoltPackage, bin: &str) {
     use std::fs::File;
 
     // Create node_modules/scripts if it doesn't exist
-    if !Path::new("node_modules/scripts").exists() {
-        std::fs::create_dir_all("node_modules/scripts").unwrap();
+    let scripts = app.node_modules_dir.join("scripts");
+    if !scripts.exists() {
+        std::fs::create_dir_all(&scripts).unwrap();
     }
 
-    // If the package has binary scripts, create them
-    if package.bin.is_some() {
-        let bin = package.bin.as_ref().unwrap();
-
-        let k = bin.keys().next().unwrap();
-        let v = bin.values().next().unwrap();
-
+    if let Some(v) = package.bin.as_ref().and_then(|bins| bins.get(bin)) {
         let command = format!(
             r#"
-node  "{}/.volt/{}/{}" %*
+node  "{}/{}/{}" %*
 "#,
             app.volt_dir.to_string_lossy(),
-            k,
+            package.store_name(),
             v,
         );
-        // .replace(r"%~dp0\..", format!("{}", app.volt_dir.display()).as_str());
-        let p = format!(r"node_modules/scripts/{}.sh", k);
-        let mut f = File::create(p.clone()).unwrap();
+        let p = scripts.join(format!("{}.sh", bin));
+        let mut f = File::create(&p).unwrap();
         std::process::Command::new("chmod")
-            .args(&["+x", &p])
+            .arg("+x")
+            .arg(&p)
             .spawn()
             .unwrap();
         f.write_all(command.as_bytes()).unwrap();
@@ -657,23 +604,38 @@ pub fn enable_ansi_support() -> Result<(), u32> {
     Ok(())
 }
 
-pub fn check_peer_dependency(_package_name: &str) -> bool {
-    false
-}
-
 pub async fn install_extract_package(app: &Arc<App>, package: &VoltPackage) -> Result<()> {
-    let pb = ProgressBar::new(0);
-    let text = format!("{}", "Installing Packages".bright_cyan());
-
-    pb.set_style(
-        ProgressStyle::default_spinner()
-            .template(("{spinner:.green}".to_string() + format!(" {}", text).as_str()).as_str())
-            .tick_strings(&["┤", "┘", "┴", "└", "├", "┌", "┬", "┐"]),
-    );
-
     download_tarball(&app, &package).await?;
 
-    generate_script(&app, package);
+    for bin in package.bin.iter().flatten().map(|(bin, _)| bin) {
+        generate_script(app, package, bin);
+    }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_package_specs() {
+        assert_eq!(
+            parse_package_spec("react@17"),
+            (String::from("react"), Some(String::from("17")))
+        );
+        assert_eq!(
+            parse_package_spec("@types/node@^16"),
+            (String::from("@types/node"), Some(String::from("^16")))
+        );
+        assert_eq!(
+            parse_package_spec("@types/node"),
+            (String::from("@types/node"), None)
+        );
+        assert_eq!(parse_package_spec(""), (String::new(), None));
+        assert_eq!(
+            parse_package_spec("é@1"),
+            (String::from("é"), Some(String::from("1")))
+        );
+    }
+}
@@ -0,0 +1,602 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Isolated node_modules layout.
+//!
+//! With `node-linker=isolated` every package lives in the virtual store
+//! (`<virtual-store-dir>/<name>@<version>/node_modules/<name>`) next to links to exactly its own
+//! dependencies, and only direct dependencies are linked into the root node_modules.
+//! The layout used by the last install is recorded in `.volt/install-state.json`.
+//!
+//! Packages matching `hoist-pattern` are additionally linked into `<virtual-store-dir>/node_modules`
+//! where every package in the store can find them, and packages matching `public-hoist-pattern`
+//! into the root node_modules, for tools which load plugins their users didn't depend on.
+
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, create_dir_all, read_to_string};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use node_semver::Version;
+use serde::{Deserialize, Serialize};
+use walkdir::WalkDir;
+
+use crate::import_method::{self, ImportMethod};
+use crate::journal::Journal;
+use crate::project_state;
+use crate::staging::{self, Staging};
+use crate::store_compression;
+use crate::uring;
+use crate::workspace::match_segment;
+use crate::{app::App, config::Config, create_symlink, volt_api::VoltPackage};
+
+/// Where node_modules recorded its layout before [project_state] existed.
+pub const LEGACY_STATE_FILE: &str = ".volt-state.json";
+
+/// Virtual store location used when `virtual-store-dir` is not set, relative to the project.
+pub const DEFAULT_VIRTUAL_STORE_DIR: &str = "node_modules/.volt";
+
+/// Packages hoisted to the root node_modules when `public-hoist-pattern` is not set.
+pub const DEFAULT_PUBLIC_HOIST_PATTERN: [&str; 2] = ["*eslint*", "*prettier*"];
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum NodeLinker {
+    /// Every package is copied to the top of node_modules
+    Hoisted,
+    /// Packages only see their own dependencies
+    Isolated,
+}
+
+/// Layout settings of an install, kept so that later runs and other tools use the same paths.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct InstallState {
+    pub node_linker: NodeLinker,
+    /// Relative to the project root
+    pub virtual_store_dir: PathBuf,
+    #[serde(default)]
+    pub hoist_pattern: Vec<String>,
+    #[serde(default)]
+    pub public_hoist_pattern: Vec<String>,
+    /// Packages linked into the root node_modules only because of `public_hoist_pattern`
+    #[serde(default)]
+    pub public_hoisted: Vec<String>,
+    /// What the last `volt install` installed, see [snapshot](crate::snapshot)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snapshot: Option<String>,
+}
+
+impl InstallState {
+    /// The layout requested by the `node-linker`, `virtual-store-dir` and hoisting settings.
+    pub fn from_config(config: &Config) -> Self {
+        let public_hoist_pattern = if config.get_bool("shamefully-hoist") == Some(true) {
+            vec![String::from("*")]
+        } else {
+            config.get_list("public-hoist-pattern").unwrap_or_else(|| {
+                DEFAULT_PUBLIC_HOIST_PATTERN
+                    .iter()
+                    .map(|pattern| pattern.to_string())
+                    .collect()
+            })
+        };
+
+        InstallState {
+            node_linker: match config.get("node-linker") {
+                Some("isolated") => NodeLinker::Isolated,
+                _ => NodeLinker::Hoisted,
+            },
+            virtual_store_dir: PathBuf::from(
+                config
+                    .get("virtual-store-dir")
+                    .unwrap_or(DEFAULT_VIRTUAL_STORE_DIR),
+            ),
+            hoist_pattern: config
+                .get_list("hoist-pattern")
+                .unwrap_or_else(|| vec![String::from("*")]),
+            public_hoist_pattern,
+            public_hoisted: vec![],
+            snapshot: None,
+        }
+    }
+
+    /// Read the state recorded by the last install.
+    pub fn load(app: &App) -> Option<Self> {
+        let text = read_to_string(project_state::path(app, project_state::INSTALL_STATE_FILE))
+            .or_else(|_| read_to_string(app.node_modules_dir.join(LEGACY_STATE_FILE)))
+            .ok()?;
+
+        serde_json::from_str(&text).ok()
+    }
+
+    pub fn save(&self, app: &App) -> Result<()> {
+        project_state::create(app)?;
+        fs::write(
+            project_state::path(app, project_state::INSTALL_STATE_FILE),
+            serde_json::to_string_pretty(self)? + "\n",
+        )?;
+
+        let legacy = app.node_modules_dir.join(LEGACY_STATE_FILE);
+
+        if legacy.exists() {
+            fs::remove_file(legacy)?;
+        }
+
+        Ok(())
+    }
+
+    /// Absolute location of the virtual store.
+    pub fn virtual_store_path(&self, project_dir: &Path) -> PathBuf {
+        project_dir.join(&self.virtual_store_dir)
+    }
+}
+
+/// Directory of `name@version` inside the virtual store.
+pub fn package_dir(virtual_store: &Path, name: &str, version: &str) -> PathBuf {
+    virtual_store
+        .join(format!("{}@{}", name.replace('/', "+"), version))
+        .join("node_modules")
+        .join(name)
+}
+
+/// Move the virtual store if `virtual-store-dir` changed since the last install, and point
+/// every link at the new location.
+pub fn relocate_virtual_store(
+    app: &App,
+    previous: &InstallState,
+    current: &InstallState,
+) -> Result<()> {
+    let from = previous.virtual_store_path(&app.current_dir);
+    let to = current.virtual_store_path(&app.current_dir);
+
+    if from == to || !from.exists() {
+        return Ok(());
+    }
+
+    if let Some(parent) = to.parent() {
+        create_dir_all(parent)?;
+    }
+
+    fs::rename(&from, &to).with_context(|| {
+        format!(
+            "failed to move the virtual store from {} to {}",
+            from.display(),
+            to.display()
+        )
+    })?;
+
+    // Links inside the store and the direct dependencies in node_modules
+    let links: Vec<PathBuf> = WalkDir::new(&to)
+        .min_depth(3)
+        .max_depth(4)
+        .into_iter()
+        .chain(
+            WalkDir::new(&app.node_modules_dir)
+                .min_depth(1)
+                .max_depth(2),
+        )
+        .filter_map(Result::ok)
+        .filter(|entry| entry.path_is_symlink())
+        .map(|entry| entry.into_path())
+        .collect();
+
+    for link in links {
+        let target = match fs::read_link(&link) {
+            Ok(target) => target,
+            Err(_) => continue,
+        };
+
+        if let Ok(relative) = target.strip_prefix(&from) {
+            remove_link(&link)?;
+            create_symlink(
+                to.join(relative).to_string_lossy().to_string(),
+                link.to_string_lossy().to_string(),
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Link `packages` in the isolated layout.
+///
+/// Packages which no other package depends on are linked into the root node_modules, then the
+/// hoisting patterns are applied to everything in the virtual store.
+pub fn link_isolated(
+    app: &App,
+    state: &mut InstallState,
+    packages: &HashMap<String, VoltPackage>,
+    journal: Option<&mut Journal>,
+) -> Result<()> {
+    let virtual_store = state.virtual_store_path(&app.current_dir);
+    let versions: HashMap<&str, &str> = packages
+        .values()
+        .map(|package| (package.name.as_str(), package.version.as_str()))
+        .collect();
+
+    let mut dependents: HashSet<&str> = HashSet::new();
+
+    create_dir_all(&virtual_store)?;
+    let method = import_method::resolve(app, &app.volt_dir, &virtual_store)?;
+    let mut staging = Staging::new(&virtual_store, method)?;
+
+    for package in packages.values() {
+        let dir = package_dir(&virtual_store, &package.name, &package.version);
+
+        if !staging::is_installed(&dir, &package.version) {
+            staging.stage(&app.volt_dir.join(package.store_name()), &dir)?;
+        }
+    }
+
+    staging.commit(journal)?;
+
+    for package in packages.values() {
+        for dependency in package.dependencies.iter().flatten() {
+            dependents.insert(dependency.as_str());
+
+            let version = match versions.get(dependency.as_str()) {
+                Some(version) => version,
+                None => continue,
+            };
+
+            // <store>/a@1.0.0/node_modules/b -> <store>/b@2.0.0/node_modules/b
+            let link = virtual_store
+                .join(format!(
+                    "{}@{}",
+                    package.name.replace('/', "+"),
+                    package.version
+                ))
+                .join("node_modules")
+                .join(dependency);
+
+            replace_link(&package_dir(&virtual_store, dependency, version), &link)?;
+        }
+    }
+
+    let mut direct = HashSet::new();
+
+    for package in packages.values() {
+        if !dependents.contains(package.name.as_str()) {
+            replace_link(
+                &package_dir(&virtual_store, &package.name, &package.version),
+                &app.node_modules_dir.join(&package.name),
+            )?;
+
+            direct.insert(package.name.clone());
+        }
+    }
+
+    hoist(app, state, &direct)
+}
+
+/// Check if `name` is selected by hoisting patterns like `*eslint*` or `!@types/*`.
+///
+/// Later patterns win, and a list of only negated patterns selects everything else. `*` matches
+/// any characters, the `/` of scoped names too.
+pub fn matches_pattern(patterns: &[String], name: &str) -> bool {
+    let mut matched =
+        !patterns.is_empty() && patterns.iter().all(|pattern| pattern.starts_with('!'));
+
+    for pattern in patterns {
+        match pattern.strip_prefix('!') {
+            Some(negated) if match_segment(negated.as_bytes(), name.as_bytes()) => matched = false,
+            None if match_segment(pattern.as_bytes(), name.as_bytes()) => matched = true,
+            _ => {}
+        }
+    }
+
+    matched
+}
+
+/// Get the newest version of every package in the virtual store.
+fn store_packages(virtual_store: &Path) -> Result<HashMap<String, String>> {
+    let mut packages: HashMap<String, String> = HashMap::new();
+
+    if !virtual_store.exists() {
+        return Ok(packages);
+    }
+
+    for entry in fs::read_dir(virtual_store)? {
+        let entry_name = entry?.file_name().to_string_lossy().to_string();
+
+        // `name@version`, the name may start with `@` for scoped packages
+        let (name, version) = match entry_name.get(1..).and_then(|rest| rest.rsplit_once('@')) {
+            Some((name, version)) => (
+                format!("{}{}", &entry_name[..1], name).replace('+', "/"),
+                version.to_string(),
+            ),
+            None => continue,
+        };
+
+        let newer = match (packages.get(&name), Version::parse(&version)) {
+            (Some(existing), Ok(version)) => {
+                Version::parse(existing).map_or(true, |existing| version > existing)
+            }
+            _ => true,
+        };
+
+        if newer {
+            packages.insert(name, version);
+        }
+    }
+
+    Ok(packages)
+}
+
+/// Apply `hoist_pattern` and `public_hoist_pattern` to the packages in the virtual store.
+fn hoist(app: &App, state: &mut InstallState, direct: &HashSet<String>) -> Result<()> {
+    let virtual_store = state.virtual_store_path(&app.current_dir);
+    let packages = store_packages(&virtual_store)?;
+
+    // The private hoisting directory only holds links, so it is rebuilt every time
+    let private = virtual_store.join("node_modules");
+
+    if private.exists() {
+        crate::fs::remove_dir_all(&private)?;
+    }
+
+    for (name, version) in packages.iter() {
+        if matches_pattern(&state.hoist_pattern, name) {
+            replace_link(
+                &package_dir(&virtual_store, name, version),
+                &private.join(name),
+            )?;
+        }
+    }
+
+    // Packages hoisted by an earlier install which are now direct dependencies or no longer match
+    let mut public_hoisted = vec![];
+
+    for name in state.public_hoisted.drain(..) {
+        let link = app.node_modules_dir.join(&name);
+
+        if direct.contains(&name) {
+            continue;
+        }
+
+        if packages.contains_key(&name) && matches_pattern(&state.public_hoist_pattern, &name) {
+            public_hoisted.push(name);
+        } else if fs::symlink_metadata(&link)
+            .is_ok_and(|metadata| metadata.file_type().is_symlink())
+        {
+            remove_link(&link)?;
+        }
+    }
+
+    for (name, version) in packages.iter() {
+        let link = app.node_modules_dir.join(name);
+
+        if matches_pattern(&state.public_hoist_pattern, name)
+            && !direct.contains(name)
+            && fs::symlink_metadata(&link).is_err()
+        {
+            replace_link(&package_dir(&virtual_store, name, version), &link)?;
+            public_hoisted.push(name.clone());
+        }
+    }
+
+    public_hoisted.sort();
+    state.public_hoisted = public_hoisted;
+
+    Ok(())
+}
+
+/// Recreate the directory tree of `src` at `dest`, importing files with `method`.
+///
+/// Compressed store entries are decompressed instead.
+pub(crate) fn import_dir(src: &Path, dest: &Path, method: ImportMethod) -> Result<()> {
+    if store_compression::is_compressed(src) {
+        return store_compression::decompress_dir(src, dest, method);
+    }
+
+    // Files are imported once the directories exist, hardlinks in batches
+    let mut files = vec![];
+
+    for entry in WalkDir::new(src) {
+        let entry = entry?;
+        let target = dest.join(entry.path().strip_prefix(src)?);
+
+        if entry.file_type().is_dir() {
+            create_dir_all(&target)?;
+        } else if !target.exists() {
+            files.push((entry.into_path(), target));
+        }
+    }
+
+    if method == ImportMethod::Hardlink {
+        return uring::link_files(&files);
+    }
+
+    for (file, target) in files.iter() {
+        method
+            .import(file, target)
+            .with_context(|| format!("failed to {} {}", method.name(), target.display()))?;
+    }
+
+    Ok(())
+}
+
+fn replace_link(target: &Path, link: &Path) -> Result<()> {
+    if fs::symlink_metadata(link).is_ok() {
+        remove_link(link)?;
+    }
+
+    if let Some(parent) = link.parent() {
+        create_dir_all(parent)?;
+    }
+
+    create_symlink(
+        target.to_string_lossy().to_string(),
+        link.to_string_lossy().to_string(),
+    )
+}
+
+/// Remove a symlink or junction, or a directory left behind by the hoisted layout.
+fn remove_link(link: &Path) -> Result<()> {
+    let metadata = fs::symlink_metadata(link)?;
+
+    // Junctions report as symlinks but are removed like directories
+    if metadata.file_type().is_symlink() {
+        fs::remove_file(link).or_else(|_| fs::remove_dir(link))?;
+    } else if metadata.is_dir() {
+        crate::fs::remove_dir_all(link)?;
+    } else {
+        fs::remove_file(link)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hoist_patterns() {
+        let patterns = |patterns: &[&str]| -> Vec<String> {
+            patterns.iter().map(|pattern| pattern.to_string()).collect()
+        };
+
+        assert!(matches_pattern(
+            &patterns(&["*eslint*"]),
+            "eslint-plugin-react"
+        ));
+        assert!(matches_pattern(
+            &patterns(&["*eslint*"]),
+            "@typescript-eslint/parser"
+        ));
+        assert!(!matches_pattern(&patterns(&["*eslint*"]), "react"));
+        assert!(!matches_pattern(
+            &patterns(&["*", "!@types/*"]),
+            "@types/node"
+        ));
+        assert!(matches_pattern(&patterns(&["!@types/*"]), "react"));
+        assert!(!matches_pattern(&[], "react"));
+    }
+
+    #[test]
+    fn virtual_store_defaults_to_node_modules() {
+        let mut config = Config::default();
+        let state = InstallState::from_config(&config);
+
+        assert_eq!(state.node_linker, NodeLinker::Hoisted);
+        assert_eq!(
+            package_dir(
+                &state.virtual_store_path(Path::new("/app")),
+                "@types/node",
+                "16.0.0"
+            ),
+            Path::new("/app/node_modules/.volt/@types+node@16.0.0/node_modules/@types/node")
+        );
+
+        config.set("virtual-store-dir", "../.store");
+        config.set("node-linker", "isolated");
+
+        let state = InstallState::from_config(&config);
+        assert_eq!(state.node_linker, NodeLinker::Isolated);
+        assert_eq!(
+            state.virtual_store_path(Path::new("/app")),
+            Path::new("/app/../.store")
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn links_isolated_layout_and_relocates_store() {
+        let root = std::env::temp_dir().join(format!("volt-linker-{}", std::process::id()));
+        let volt_dir = root.join("volt");
+        let project = root.join("project");
+
+        for name in ["a", "b"].iter() {
+            let entry = volt_dir.join(format!("{}@1.0.0", name));
+            create_dir_all(&entry).unwrap();
+            fs::write(entry.join("index.js"), name).unwrap();
+        }
+
+        let package = |name: &str, dependencies: Option<Vec<String>>| VoltPackage {
+            name: name.to_string(),
+            version: String::from("1.0.0"),
+            tarball: String::new(),
+            sha1: String::new(),
+            integrity: None,
+            peer_dependencies: vec![],
+            dependencies,
+            bin: None,
+            optional_dependencies: vec![],
+            os: vec![],
+            cpu: vec![],
+            libc: vec![],
+            source: Default::default(),
+            deprecated: None,
+            engines: Default::default(),
+        };
+
+        let mut packages = HashMap::new();
+        packages.insert("a".to_string(), package("a", Some(vec!["b".to_string()])));
+        packages.insert("b".to_string(), package("b", None));
+
+        let app = App {
+            current_dir: project.clone(),
+            home_dir: root.clone(),
+            node_modules_dir: project.join("node_modules"),
+            cache_dir: volt_dir.join(".cache"),
+            config_file: root.join(".voltrc"),
+            volt_dir,
+            lock_file_path: project.join("volt.lock"),
+            args: vec![],
+            flags: vec![],
+            config: Config::default(),
+        };
+
+        let mut config = Config::default();
+        config.set("node-linker", "isolated");
+        config.set("public-hoist-pattern", "b");
+        let mut previous = InstallState::from_config(&config);
+
+        link_isolated(&app, &mut previous, &packages, None).unwrap();
+
+        assert!(project.join("node_modules/a/index.js").exists());
+        assert!(project.join("node_modules/b/index.js").exists());
+        assert!(project
+            .join("node_modules/.volt/a@1.0.0/node_modules/b/index.js")
+            .exists());
+        assert!(project.join("node_modules/.volt/node_modules/b").exists());
+        assert_eq!(previous.public_hoisted, vec!["b"]);
+
+        config.set("virtual-store-dir", ".store");
+        let current = InstallState::from_config(&config);
+
+        relocate_virtual_store(&app, &previous, &current).unwrap();
+
+        let mut current = InstallState {
+            public_hoisted: previous.public_hoisted.clone(),
+            ..InstallState::from_config(&Config::default())
+        };
+        current.virtual_store_dir = PathBuf::from(".store");
+        hoist(&app, &mut current, &HashSet::new()).unwrap();
+
+        // `b` no longer matches the default public patterns
+        assert!(!project.join("node_modules/b").exists());
+
+        assert!(!project.join("node_modules/.volt").exists());
+        assert!(project.join("node_modules/a/index.js").exists());
+        assert!(project
+            .join(".store/a@1.0.0/node_modules/b/index.js")
+            .exists());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,209 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Embedded database of registry metadata, in `<cache dir>/metadata.redb`.
+//!
+//! It keeps the version every `(name, range)` pair resolved to, and a slim binary summary of
+//! each resolved `name@version`, so warm resolutions neither fetch nor parse packuments.
+//! Summaries never change once published. Resolutions of ranges can move when new versions are
+//! published, so they are trusted for `resolution-cache-max-age` seconds unless the range is an
+//! exact version.
+
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::Result;
+use redb::{Database, ReadableTable, TableDefinition};
+
+use crate::app::App;
+
+/// `name@range` → `version\tresolved at`
+const RESOLUTIONS: TableDefinition<&str, &str> = TableDefinition::new("resolutions");
+
+/// `name@version` → summary
+const SUMMARIES: TableDefinition<&str, &[u8]> = TableDefinition::new("summaries");
+
+/// Seconds a range resolution is trusted when `resolution-cache-max-age` is not set.
+pub const DEFAULT_MAX_AGE: u64 = 300;
+
+pub struct MetadataDb {
+    db: Database,
+    max_age: u64,
+}
+
+impl MetadataDb {
+    /// Open the database of the volt dir, `None` when another volt process has it open.
+    pub fn from_app(app: &App) -> Option<Self> {
+        let max_age = app
+            .config
+            .get("resolution-cache-max-age")
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(DEFAULT_MAX_AGE);
+
+        Self::open(&path(&app.cache_dir), max_age).ok()
+    }
+
+    pub fn open(path: &Path, max_age: u64) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        Ok(MetadataDb {
+            db: Database::create(path)?,
+            max_age,
+        })
+    }
+
+    /// Get the version `name@range` resolved to, if it is still fresh.
+    pub fn resolution(&self, name: &str, range: &str) -> Option<String> {
+        let transaction = self.db.begin_read().ok()?;
+        let table = transaction.open_table(RESOLUTIONS).ok()?;
+        let entry = table.get(key(name, range).as_str()).ok()??;
+
+        let (version, resolved_at) = entry.value().split_once('\t')?;
+        let age = now().saturating_sub(resolved_at.parse().ok()?);
+
+        if age <= self.max_age || version == range {
+            Some(version.to_string())
+        } else {
+            None
+        }
+    }
+
+    /// Names of the packages with a resolution starting with `prefix`, sorted.
+    pub fn names(&self, prefix: &str) -> Vec<String> {
+        let names = || -> Option<BTreeSet<String>> {
+            let transaction = self.db.begin_read().ok()?;
+            let table = transaction.open_table(RESOLUTIONS).ok()?;
+            let range = table.range(prefix..).ok()?;
+
+            // Keys are `name@range`, and sorted so the matches come first
+            let names = range
+                .filter_map(Result::ok)
+                .map(|(key, _)| key.value().to_string())
+                .take_while(|key| key.starts_with(prefix))
+                .filter_map(|key| key.rsplit_once('@').map(|(name, _)| name.to_string()))
+                .collect();
+
+            Some(names)
+        };
+
+        names().unwrap_or_default().into_iter().collect()
+    }
+
+    pub fn summary(&self, name: &str, version: &str) -> Option<Vec<u8>> {
+        let transaction = self.db.begin_read().ok()?;
+        let table = transaction.open_table(SUMMARIES).ok()?;
+        let entry = table.get(key(name, version).as_str()).ok()??;
+
+        Some(entry.value().to_vec())
+    }
+
+    /// Record resolutions `(name, range, version)` and summaries `(name, version, summary)` in
+    /// one transaction.
+    pub fn store(
+        &self,
+        resolutions: &[(String, String, String)],
+        summaries: &[(String, String, Vec<u8>)],
+    ) -> Result<()> {
+        let transaction = self.db.begin_write()?;
+
+        {
+            let mut table = transaction.open_table(RESOLUTIONS)?;
+            let resolved_at = now();
+
+            for (name, range, version) in resolutions {
+                table.insert(
+                    key(name, range).as_str(),
+                    format!("{}\t{}", version, resolved_at).as_str(),
+                )?;
+            }
+        }
+
+        {
+            let mut table = transaction.open_table(SUMMARIES)?;
+
+            for (name, version, summary) in summaries {
+                table.insert(key(name, version).as_str(), summary.as_slice())?;
+            }
+        }
+
+        transaction.commit()?;
+
+        Ok(())
+    }
+}
+
+pub fn path(cache_dir: &Path) -> PathBuf {
+    cache_dir.join("metadata.redb")
+}
+
+fn key(name: &str, suffix: &str) -> String {
+    format!("{}@{}", name, suffix)
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn range_resolutions_expire() {
+        let path = std::env::temp_dir().join(format!("volt-metadata-{}.redb", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let db = MetadataDb::open(&path, 0).unwrap();
+
+        db.store(
+            &[
+                (
+                    String::from("react"),
+                    String::from("^17.0.0"),
+                    String::from("17.0.2"),
+                ),
+                (
+                    String::from("react"),
+                    String::from("17.0.2"),
+                    String::from("17.0.2"),
+                ),
+            ],
+            &[(String::from("react"), String::from("17.0.2"), vec![1, 2, 3])],
+        )
+        .unwrap();
+
+        std::thread::sleep(std::time::Duration::from_millis(1100));
+
+        assert_eq!(db.resolution("react", "^17.0.0"), None);
+        assert_eq!(
+            db.resolution("react", "17.0.2"),
+            Some(String::from("17.0.2"))
+        );
+        assert_eq!(db.summary("react", "17.0.2"), Some(vec![1, 2, 3]));
+        assert_eq!(db.summary("react", "16.0.0"), None);
+        assert_eq!(db.names("rea"), vec![String::from("react")]);
+        assert!(db.names("vue").is_empty());
+
+        drop(db);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,433 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Export metrics of each command, to follow the health of installs across many repositories.
+//!
+//! Off unless `metrics` is set in `.voltrc` or `VOLT_METRICS` in the environment, which CI runs
+//! usually set:
+//!
+//! - `statsd://127.0.0.1:8125` sends statsd lines over UDP, with DogStatsD tags
+//! - `otlp+http://collector:4318` posts OpenTelemetry metrics to `<url>/v1/metrics` as OTLP JSON
+//!
+//! Metric names start with `metrics-prefix` (`volt`). Every metric is tagged with the command,
+//! whether it succeeded, the category of its failure and the `metrics-tags` list
+//! (`repo:web,team:platform`). Exporting never fails the command, a collector which is down only
+//! gets a warning.
+
+use std::net::UdpSocket;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, Result};
+use colored::Colorize;
+use serde_json::{json, Value};
+
+use crate::app::App;
+use crate::network;
+use crate::policy;
+use crate::resolver;
+
+/// Environment variable with the endpoint, over the `metrics` setting.
+pub const METRICS_ENV: &str = "VOLT_METRICS";
+
+const DEFAULT_PREFIX: &str = "volt";
+
+/// How long a collector has to take the metrics.
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+static STORE_HITS: AtomicUsize = AtomicUsize::new(0);
+static STORE_MISSES: AtomicUsize = AtomicUsize::new(0);
+
+/// Count a package found extracted in the store (`hit`) or downloaded to it.
+pub fn record_store(hit: bool) {
+    let counter = if hit { &STORE_HITS } else { &STORE_MISSES };
+    counter.fetch_add(1, Ordering::Relaxed);
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Endpoint {
+    /// `host:port`
+    Statsd(String),
+    /// The url of the collector, without a trailing slash
+    Otlp(String),
+}
+
+impl Endpoint {
+    pub fn parse(value: &str) -> Result<Self> {
+        if let Some(address) = value.strip_prefix("statsd://") {
+            return Ok(Endpoint::Statsd(address.trim_end_matches('/').to_string()));
+        }
+
+        if let Some(url) = value.strip_prefix("otlp+") {
+            if url.starts_with("http://") || url.starts_with("https://") {
+                return Ok(Endpoint::Otlp(url.trim_end_matches('/').to_string()));
+            }
+        }
+
+        Err(anyhow!(
+            "unknown metrics endpoint {}, expected statsd://<host>:<port> or otlp+http://<host>:<port>",
+            value
+        ))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kind {
+    Counter,
+    Gauge,
+    /// Milliseconds
+    Timing,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sample {
+    /// Without the prefix
+    pub name: &'static str,
+    pub kind: Kind,
+    pub value: f64,
+}
+
+impl Sample {
+    fn new(name: &'static str, kind: Kind, value: f64) -> Self {
+        Sample { name, kind, value }
+    }
+}
+
+/// What kind of problem `error` is, to group failures by.
+pub fn failure_category(error: &anyhow::Error) -> &'static str {
+    let chain = || error.chain();
+
+    if error.downcast_ref::<policy::Violations>().is_some() {
+        "policy"
+    } else if chain().any(|cause| cause.downcast_ref::<reqwest::Error>().is_some()) {
+        "network"
+    } else if chain().any(|cause| {
+        let message = cause.to_string();
+        message.contains("checksum") || message.contains("sha1")
+    }) {
+        "integrity"
+    } else if chain().any(|cause| cause.to_string().starts_with("no version of")) {
+        "resolution"
+    } else if chain().any(|cause| cause.downcast_ref::<std::io::Error>().is_some()) {
+        "io"
+    } else {
+        "other"
+    }
+}
+
+/// The ratio of `hits` in `total`, none without lookups.
+fn ratio(hits: usize, total: usize) -> Option<f64> {
+    (total > 0).then(|| hits as f64 / total as f64)
+}
+
+/// The metrics of the command which ran for `elapsed`.
+pub fn collect(elapsed: Duration) -> Vec<Sample> {
+    let mut samples = vec![
+        Sample::new("command.count", Kind::Counter, 1.0),
+        Sample::new("command.duration", Kind::Timing, elapsed.as_millis() as f64),
+    ];
+
+    let packuments = resolver::packument_stats();
+    samples.push(Sample::new(
+        "packuments.lookups",
+        Kind::Counter,
+        packuments.lookups as f64,
+    ));
+    samples.push(Sample::new(
+        "packuments.fetches",
+        Kind::Counter,
+        packuments.fetches as f64,
+    ));
+
+    if let Some(ratio) = ratio(packuments.hits(), packuments.lookups) {
+        samples.push(Sample::new("packuments.hit_ratio", Kind::Gauge, ratio));
+    }
+
+    let (hits, misses) = (
+        STORE_HITS.load(Ordering::Relaxed),
+        STORE_MISSES.load(Ordering::Relaxed),
+    );
+    samples.push(Sample::new("store.hits", Kind::Counter, hits as f64));
+    samples.push(Sample::new("store.misses", Kind::Counter, misses as f64));
+
+    if let Some(ratio) = ratio(hits, hits + misses) {
+        samples.push(Sample::new("store.hit_ratio", Kind::Gauge, ratio));
+    }
+
+    let registries = network::report();
+    let total = |field: fn(&network::RegistryStats) -> usize| -> f64 {
+        registries.values().map(field).sum::<usize>() as f64
+    };
+
+    samples.push(Sample::new(
+        "network.requests",
+        Kind::Counter,
+        total(|stats| stats.requests),
+    ));
+    samples.push(Sample::new(
+        "network.bytes",
+        Kind::Counter,
+        total(|stats| stats.bytes),
+    ));
+    samples.push(Sample::new(
+        "network.errors",
+        Kind::Counter,
+        total(|stats| stats.errors),
+    ));
+    samples.push(Sample::new(
+        "network.retries",
+        Kind::Counter,
+        total(|stats| stats.retries),
+    ));
+    samples.push(Sample::new(
+        "network.cache_hits",
+        Kind::Counter,
+        total(|stats| stats.cache_hits),
+    ));
+
+    samples
+}
+
+/// `samples` as statsd lines, with DogStatsD tags.
+pub fn statsd_lines(prefix: &str, samples: &[Sample], tags: &[(String, String)]) -> String {
+    let tags: Vec<String> = tags
+        .iter()
+        .map(|(key, value)| format!("{}:{}", key, value))
+        .collect();
+
+    samples
+        .iter()
+        .map(|sample| {
+            let kind = match sample.kind {
+                Kind::Counter => "c",
+                Kind::Gauge => "g",
+                Kind::Timing => "ms",
+            };
+
+            format!(
+                "{}.{}:{}|{}|#{}\n",
+                prefix,
+                sample.name,
+                sample.value,
+                kind,
+                tags.join(",")
+            )
+        })
+        .collect()
+}
+
+/// `samples` as an OTLP `ExportMetricsServiceRequest`, taken at `time` in nanoseconds.
+pub fn otlp_json(prefix: &str, samples: &[Sample], tags: &[(String, String)], time: u128) -> Value {
+    let attributes: Vec<Value> = tags
+        .iter()
+        .map(|(key, value)| json!({ "key": key, "value": { "stringValue": value } }))
+        .collect();
+
+    let metrics: Vec<Value> = samples
+        .iter()
+        .map(|sample| {
+            let point = json!({
+                "attributes": attributes,
+                "timeUnixNano": time.to_string(),
+                "asDouble": sample.value,
+            });
+
+            let name = format!("{}.{}", prefix, sample.name);
+
+            match sample.kind {
+                Kind::Counter => json!({
+                    "name": name,
+                    "sum": {
+                        "dataPoints": [point],
+                        // Each run reports its own counts
+                        "aggregationTemporality": 1,
+                        "isMonotonic": true,
+                    },
+                }),
+                Kind::Gauge => json!({ "name": name, "gauge": { "dataPoints": [point] } }),
+                Kind::Timing => json!({
+                    "name": name,
+                    "unit": "ms",
+                    "gauge": { "dataPoints": [point] },
+                }),
+            }
+        })
+        .collect();
+
+    json!({
+        "resourceMetrics": [{
+            "resource": {
+                "attributes": [{ "key": "service.name", "value": { "stringValue": "volt" } }],
+            },
+            "scopeMetrics": [{
+                "scope": { "name": "volt", "version": env!("CARGO_PKG_VERSION") },
+                "metrics": metrics,
+            }],
+        }],
+    })
+}
+
+/// Where and how the metrics of the command are exported.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Exporter {
+    pub endpoint: Endpoint,
+    pub prefix: String,
+    pub tags: Vec<(String, String)>,
+}
+
+impl Exporter {
+    /// The exporter configured for the command, if metrics are enabled.
+    pub fn from_app(app: &App) -> Option<Self> {
+        let value = std::env::var(METRICS_ENV)
+            .ok()
+            .or_else(|| app.config.get("metrics").map(String::from))
+            .filter(|value| !value.is_empty())?;
+
+        let endpoint = match Endpoint::parse(&value) {
+            Ok(endpoint) => endpoint,
+            Err(error) => {
+                eprintln!("{} {}", "warn".yellow(), error);
+                return None;
+            }
+        };
+
+        let tags = app
+            .config
+            .get_list("metrics-tags")
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|tag| {
+                let (key, value) = tag.split_once(':')?;
+                Some((key.trim().to_string(), value.trim().to_string()))
+            })
+            .collect();
+
+        Some(Exporter {
+            endpoint,
+            prefix: app
+                .config
+                .get("metrics-prefix")
+                .unwrap_or(DEFAULT_PREFIX)
+                .to_string(),
+            tags,
+        })
+    }
+
+    async fn send(&self, samples: &[Sample], tags: &[(String, String)]) -> Result<()> {
+        match &self.endpoint {
+            Endpoint::Statsd(address) => {
+                let socket = UdpSocket::bind("0.0.0.0:0")?;
+                socket.set_write_timeout(Some(TIMEOUT))?;
+
+                // One datagram per line keeps every packet under the usual MTU
+                for line in statsd_lines(&self.prefix, samples, tags).lines() {
+                    socket.send_to(line.as_bytes(), address)?;
+                }
+            }
+            Endpoint::Otlp(url) => {
+                let time = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_nanos();
+
+                let url = format!("{}/v1/metrics", url);
+                let request = reqwest::Client::new()
+                    .post(&url)
+                    .timeout(TIMEOUT)
+                    .header("Content-Type", "application/json")
+                    .body(serde_json::to_vec(&otlp_json(
+                        &self.prefix,
+                        samples,
+                        tags,
+                        time,
+                    ))?);
+
+                request.send().await?.error_for_status()?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Export the metrics of `command`, which ran for `elapsed` and failed with `error` if set.
+    pub async fn export(&self, command: &str, elapsed: Duration, error: Option<&anyhow::Error>) {
+        let mut tags = vec![
+            (String::from("command"), command.to_string()),
+            (
+                String::from("status"),
+                String::from(if error.is_some() {
+                    "failure"
+                } else {
+                    "success"
+                }),
+            ),
+        ];
+
+        if let Some(error) = error {
+            tags.push((String::from("failure"), failure_category(error).to_string()));
+        }
+
+        tags.extend(self.tags.iter().cloned());
+
+        if let Err(error) = self.send(&collect(elapsed), &tags).await {
+            eprintln!("{} failed to export metrics: {}", "warn".yellow(), error);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_statsd_lines_and_otlp_points() {
+        assert_eq!(
+            Endpoint::parse("statsd://localhost:8125").unwrap(),
+            Endpoint::Statsd(String::from("localhost:8125"))
+        );
+        assert_eq!(
+            Endpoint::parse("otlp+https://otel.example/").unwrap(),
+            Endpoint::Otlp(String::from("https://otel.example"))
+        );
+        assert!(Endpoint::parse("prometheus://localhost").is_err());
+
+        let samples = vec![
+            Sample::new("command.duration", Kind::Timing, 1250.0),
+            Sample::new("store.hit_ratio", Kind::Gauge, 0.5),
+        ];
+        let tags = vec![
+            (String::from("command"), String::from("install")),
+            (String::from("repo"), String::from("web")),
+        ];
+
+        assert_eq!(
+            statsd_lines("volt", &samples, &tags),
+            "volt.command.duration:1250|ms|#command:install,repo:web\n\
+             volt.store.hit_ratio:0.5|g|#command:install,repo:web\n"
+        );
+
+        let otlp = otlp_json("volt", &samples, &tags, 42);
+        let metrics = &otlp["resourceMetrics"][0]["scopeMetrics"][0]["metrics"];
+
+        assert_eq!(metrics[0]["name"], "volt.command.duration");
+        assert_eq!(metrics[0]["unit"], "ms");
+        assert_eq!(metrics[1]["gauge"]["dataPoints"][0]["asDouble"], 0.5);
+        assert_eq!(
+            metrics[1]["gauge"]["dataPoints"][0]["attributes"][1]["value"]["stringValue"],
+            "web"
+        );
+    }
+}
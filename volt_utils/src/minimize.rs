@@ -0,0 +1,164 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Leave files packages don't need at runtime out of the store.
+//!
+//! With `minimize-install=true` in `.voltrc` or `--minimize-install`, tests, examples, markdown,
+//! source maps and the like are not extracted from tarballs, which saves disk space and inodes
+//! in deploy images. `minimize-install-patterns=<glob>,<glob>` skips more paths and `!<glob>`
+//! keeps paths the defaults would skip. Globs match paths inside the package (`**/*.md`), and
+//! license files and `package.json` are always kept.
+//!
+//! Packages are extracted once into the store, so the store of a minimized install should not
+//! be shared with development machines.
+
+use std::io::Read;
+use std::path::{Component, Path};
+
+use anyhow::Result;
+use tar::Archive;
+
+use crate::app::App;
+use crate::workspace::glob_match;
+
+/// Paths skipped by default.
+pub const DEFAULT_PATTERNS: [&str; 14] = [
+    "**/test/**",
+    "**/tests/**",
+    "**/__tests__/**",
+    "**/__mocks__/**",
+    "**/example/**",
+    "**/examples/**",
+    "**/.github/**",
+    "**/*.md",
+    "**/*.markdown",
+    "**/*.map",
+    "**/*.tsbuildinfo",
+    "**/.eslintrc*",
+    "**/.travis.yml",
+    "**/.editorconfig",
+];
+
+pub struct Minimize {
+    patterns: Vec<String>,
+}
+
+impl Minimize {
+    /// The patterns to skip if minimized installs are enabled.
+    pub fn from_app(app: &App) -> Option<Self> {
+        if !app.has_flag(&["--minimize-install"])
+            && app.config.get_bool("minimize-install") != Some(true)
+        {
+            return None;
+        }
+
+        Some(Minimize::new(
+            app.config
+                .get_list("minimize-install-patterns")
+                .unwrap_or_default(),
+        ))
+    }
+
+    /// The default patterns followed by `patterns`.
+    pub fn new(patterns: Vec<String>) -> Self {
+        Minimize {
+            patterns: DEFAULT_PATTERNS
+                .iter()
+                .map(|pattern| pattern.to_string())
+                .chain(patterns)
+                .collect(),
+        }
+    }
+
+    /// Check if `path`, relative to the root of the package, is left out.
+    pub fn skips(&self, path: &str) -> bool {
+        let file_name = path.rsplit('/').next().unwrap_or(path).to_ascii_lowercase();
+
+        if file_name == "package.json"
+            || file_name.starts_with("license")
+            || file_name.starts_with("licence")
+        {
+            return false;
+        }
+
+        let mut skipped = false;
+
+        // Later patterns win, like hoisting patterns
+        for pattern in self.patterns.iter() {
+            match pattern.strip_prefix('!') {
+                Some(kept) if glob_match(kept, path) => skipped = false,
+                None if glob_match(pattern, path) => skipped = true,
+                _ => {}
+            }
+        }
+
+        skipped
+    }
+
+    /// Extract `archive` into `dir` without the skipped files, returning how many were skipped.
+    pub fn unpack<R: Read>(&self, archive: &mut Archive<R>, dir: &Path) -> Result<usize> {
+        let mut skipped = 0;
+
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            let path = entry.path()?.into_owned();
+
+            // Tarballs keep the package in a top directory, usually `package/`
+            let relative: Vec<String> = path
+                .components()
+                .skip(1)
+                .filter_map(|component| match component {
+                    Component::Normal(name) => Some(name.to_string_lossy().to_string()),
+                    _ => None,
+                })
+                .collect();
+
+            if !relative.is_empty() && self.skips(&relative.join("/")) {
+                skipped += 1;
+                continue;
+            }
+
+            entry.unpack_in(dir)?;
+        }
+
+        Ok(skipped)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skips_files_not_needed_at_runtime() {
+        let minimize = Minimize::new(vec![
+            String::from("**/*.flow"),
+            String::from("!docs/api.md"),
+        ]);
+
+        assert!(minimize.skips("README.md"));
+        assert!(minimize.skips("test/index.js"));
+        assert!(minimize.skips("lib/__tests__/parse.test.js"));
+        assert!(minimize.skips("dist/index.js.map"));
+        assert!(minimize.skips("lib/index.js.flow"));
+
+        assert!(!minimize.skips("docs/api.md"));
+        assert!(!minimize.skips("LICENSE.md"));
+        assert!(!minimize.skips("package.json"));
+        assert!(!minimize.skips("lib/index.js"));
+        assert!(!minimize.skips("lib/testing.js"));
+    }
+}
@@ -0,0 +1,241 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Offline mirror of package tarballs.
+//!
+//! With `offline-mirror=<dir>` in `.voltrc`, every tarball installed is copied into `<dir>`
+//! (`@types-node-16.4.0.tgz`) along with the resolution metadata in `<dir>/.metadata`. Installs
+//! with `--offline` or `offline=true` are then served from the mirror alone, which can be
+//! committed to the repository or restored from an artifact store in air-gapped environments.
+
+use std::fs::{create_dir_all, read, read_to_string, write};
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Result};
+
+use crate::app::App;
+use crate::git::{self, GitSpec};
+use crate::network;
+use crate::policy;
+use crate::registry;
+use crate::registry_compat;
+use crate::reporter;
+use crate::resolver;
+use crate::tarball_dir::TarballDir;
+use crate::volt_api::{self, VoltPackage, VoltResponse};
+
+const METADATA_DIR: &str = ".metadata";
+
+pub struct OfflineMirror {
+    pub dir: PathBuf,
+}
+
+impl OfflineMirror {
+    /// The mirror configured for the project, if any.
+    pub fn from_app(app: &App) -> Option<Self> {
+        app.config.get("offline-mirror").map(|dir| OfflineMirror {
+            dir: app.current_dir.join(dir),
+        })
+    }
+
+    fn tarball_path(&self, package: &VoltPackage) -> PathBuf {
+        self.dir.join(tarball_name(&package.name, &package.version))
+    }
+
+    fn metadata_path(&self, name: &str) -> PathBuf {
+        self.dir
+            .join(METADATA_DIR)
+            .join(format!("{}.json", name.replace('/', "-")))
+    }
+
+    /// Read the tarball of `package`, ignoring copies which don't match its checksum.
+    pub fn read_tarball(&self, package: &VoltPackage) -> Option<bytes::Bytes> {
+        let bytes = bytes::Bytes::from(read(self.tarball_path(package)).ok()?);
+
+        if App::calc_hash(&bytes).ok()? == package.sha1 {
+            Some(bytes)
+        } else {
+            None
+        }
+    }
+
+    pub fn write_tarball(&self, package: &VoltPackage, bytes: &[u8]) -> Result<()> {
+        create_dir_all(&self.dir)?;
+        write(self.tarball_path(package), bytes)?;
+
+        Ok(())
+    }
+
+    pub fn read_metadata(&self, name: &str) -> Option<VoltResponse> {
+        serde_json::from_str(&read_to_string(self.metadata_path(name)).ok()?).ok()
+    }
+
+    pub fn write_metadata(&self, name: &str, response: &VoltResponse) -> Result<()> {
+        let path = self.metadata_path(name);
+
+        if let Some(parent) = path.parent() {
+            create_dir_all(parent)?;
+        }
+
+        write(path, serde_json::to_string(response)?)?;
+
+        Ok(())
+    }
+}
+
+/// File name of a tarball in the mirror, the same as yarn uses.
+pub fn tarball_name(name: &str, version: &str) -> String {
+    format!("{}-{}.tgz", name.replace('/', "-"), version)
+}
+
+/// Check if the network must not be used.
+pub fn is_offline(app: &App) -> bool {
+    app.has_flag(&["--offline"]) || app.config.get_bool("offline") == Some(true)
+}
+
+/// Get the resolution of `name`, from the mirror when offline and recording it there otherwise.
+///
+/// Without `resolver=registry`, names the volt CDN can't resolve are resolved from the registry.
+/// With `tarball-dir=<dir>`, everything is resolved from the tarballs of `<dir>` instead.
+/// Git dependencies (`foo@git+https://...`) are resolved from their repository.
+/// The resolution is checked against the [policy](crate::policy) of the project.
+pub async fn get_volt_response(app: &App, name: &str) -> Result<VoltResponse> {
+    let response = resolve(app, name).await?;
+
+    policy::enforce(app, &response).await?;
+
+    Ok(response)
+}
+
+async fn resolve(app: &App, name: &str) -> Result<VoltResponse> {
+    if let (package, Some(range)) = crate::parse_package_spec(name) {
+        if let Some(spec) = GitSpec::parse(&range) {
+            return git::resolve(app, &package, &spec).await;
+        }
+    }
+
+    if let Some(tarball_dir) = TarballDir::from_app(app) {
+        return tarball_dir.resolve(name).await;
+    }
+
+    let mirror = OfflineMirror::from_app(app);
+
+    if is_offline(app) {
+        return mirror
+            .and_then(|mirror| mirror.read_metadata(name))
+            .ok_or_else(|| anyhow!("{} is not in the offline mirror", name));
+    }
+
+    let response = if resolver::is_enabled(app) {
+        resolver::resolve(app, name).await?
+    } else {
+        match volt_api::fetch(app, name).await {
+            Ok(response) => response,
+            Err(error) => {
+                if app.has_flag(&["--verbose"]) {
+                    reporter::get().info(
+                        "Resolving",
+                        &format!("{} from the registry: {:#}", name, error),
+                    );
+                }
+
+                resolver::resolve(app, name).await?
+            }
+        }
+    };
+
+    if let Some(mirror) = mirror {
+        mirror.write_metadata(name, &response)?;
+    }
+
+    Ok(response)
+}
+
+/// Download the tarball of `package` from its registry.
+async fn download(app: &App, package: &VoltPackage) -> Result<bytes::Bytes> {
+    let registry = registry::url_for(app, &package.name);
+
+    let bytes = match registry::credentials(app, &registry) {
+        // Private registries serve tarballs to the same credentials, which are only sent to them
+        Some(credentials) if package.tarball.starts_with(&registry) => {
+            let compat = registry_compat::get(app, &registry);
+
+            let request = registry_compat::authorize(
+                reqwest::Client::new().get(&package.tarball),
+                &credentials,
+                compat,
+            );
+            let response = network::send(&package.tarball, request).await?;
+
+            network::read(&package.tarball, response).await?
+        }
+        _ => {
+            // Url to download tarball code files from
+            let url = package.tarball.replace("https", "http");
+            let response = network::send(&url, reqwest::Client::new().get(&url)).await?;
+            network::read(&url, response).await?
+        }
+    };
+
+    Ok(bytes)
+}
+
+/// Get the tarball of `package` from its registry, from the mirror when it has it and copying it
+/// there otherwise. Tarballs from other sources are fetched by their [fetcher](crate::fetcher).
+pub async fn fetch_tarball(app: &App, package: &VoltPackage) -> Result<bytes::Bytes> {
+    let mirror = OfflineMirror::from_app(app);
+
+    if let Some(bytes) = mirror
+        .as_ref()
+        .and_then(|mirror| mirror.read_tarball(package))
+    {
+        network::record_cache_hit(&package.tarball);
+        return Ok(bytes);
+    }
+
+    if is_offline(app) {
+        return Err(anyhow!(
+            "{}@{} is not in the offline mirror",
+            package.name,
+            package.version
+        ));
+    }
+
+    // Freshly published tarballs can take a moment to reach every node of the registry
+    let bytes = network::retry_missing(&package.tarball, || download(app, package)).await?;
+
+    if let Some(mirror) = mirror {
+        if App::calc_hash(&bytes)? == package.sha1 {
+            mirror.write_tarball(package, &bytes)?;
+        }
+    }
+
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tarball_names() {
+        assert_eq!(tarball_name("react", "17.0.2"), "react-17.0.2.tgz");
+        assert_eq!(
+            tarball_name("@types/node", "16.4.0"),
+            "@types-node-16.4.0.tgz"
+        );
+    }
+}
@@ -0,0 +1,219 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Requests made to each registry during a command, shown with `--network-report`.
+//!
+//! Packument and tarball fetches count their requests, bytes received, status codes, failures
+//! and retries under the origin of the url (`https://registry.npmjs.org`). Lookups served from
+//! memory or the offline mirror count as cache hits. Enough to find which mirror slows an
+//! install down.
+
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use anyhow::Result;
+use lazy_static::lazy_static;
+use reqwest::{RequestBuilder, Response, Url};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::http_recording::{self, Key, Mode};
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RegistryStats {
+    pub requests: usize,
+    pub bytes: usize,
+    pub cache_hits: usize,
+    pub retries: usize,
+    /// Requests which got no response
+    pub errors: usize,
+    /// Responses by status code
+    pub statuses: BTreeMap<u16, usize>,
+}
+
+lazy_static! {
+    static ref STATS: Mutex<BTreeMap<String, RegistryStats>> = Mutex::new(BTreeMap::new());
+}
+
+/// `https://registry.npmjs.org` for any url of the registry, the url itself if it isn't one.
+pub fn origin(url: &str) -> String {
+    Url::parse(url)
+        .map(|url| url.origin().ascii_serialization())
+        .unwrap_or_else(|_| url.to_string())
+}
+
+fn update(url: &str, change: impl FnOnce(&mut RegistryStats)) {
+    let mut stats = STATS.lock().unwrap();
+    change(stats.entry(origin(url)).or_default());
+}
+
+/// Count a response to a request for `url`.
+pub fn record_response(url: &str, status: u16) {
+    update(url, |stats| {
+        stats.requests += 1;
+        *stats.statuses.entry(status).or_default() += 1;
+    });
+}
+
+/// Count `bytes` of a body received from `url`.
+pub fn record_bytes(url: &str, bytes: usize) {
+    update(url, |stats| stats.bytes += bytes);
+}
+
+/// Count a request for `url` which got no response.
+pub fn record_error(url: &str) {
+    update(url, |stats| {
+        stats.requests += 1;
+        stats.errors += 1;
+    });
+}
+
+/// Count a request for `url` which is made again differently.
+pub fn record_retry(url: &str) {
+    update(url, |stats| stats.retries += 1);
+}
+
+/// Count a lookup of `url` which needed no request.
+pub fn record_cache_hit(url: &str) {
+    update(url, |stats| stats.cache_hits += 1);
+}
+
+/// Send `request` for `url`, counting its response or failure.
+///
+/// Responses are recorded or replayed when the environment asks for it, see
+/// [http_recording](crate::http_recording).
+pub async fn send(url: &str, request: RequestBuilder) -> Result<Response> {
+    let mode = http_recording::Mode::from_env();
+
+    if let Some(Mode::Replay(dir)) = &mode {
+        let response = http_recording::replay(dir, &Key::of(&request)?)?;
+        record_response(url, response.status().as_u16());
+        return Ok(response);
+    }
+
+    let key = match &mode {
+        Some(Mode::Record(_)) => Some(Key::of(&request)?),
+        _ => None,
+    };
+
+    match request.send().await {
+        Ok(response) => {
+            record_response(url, response.status().as_u16());
+
+            match (mode, key) {
+                (Some(Mode::Record(dir)), Some(key)) => {
+                    http_recording::record(&dir, key, response).await
+                }
+                _ => Ok(response),
+            }
+        }
+        Err(error) => {
+            record_error(url);
+            Err(error.into())
+        }
+    }
+}
+
+/// Read the body of `response` to a request for `url`, failing on error statuses.
+pub async fn read(url: &str, response: Response) -> Result<bytes::Bytes> {
+    let bytes = response.error_for_status()?.bytes().await?;
+    record_bytes(url, bytes.len());
+
+    Ok(bytes)
+}
+
+/// Whether `error` comes from a 404 or 410 response, for something unpublished.
+pub fn is_missing(error: &anyhow::Error) -> bool {
+    error
+        .chain()
+        .filter_map(|cause| cause.downcast_ref::<reqwest::Error>())
+        .filter_map(reqwest::Error::status)
+        .any(|status| status.as_u16() == 404 || status.as_u16() == 410)
+}
+
+/// How long to wait before each new attempt at something a registry answered 404 for. A version
+/// which was just published can be tagged before every node of the registry has it.
+pub const REPLICATION_DELAYS: [Duration; 3] = [
+    Duration::from_millis(500),
+    Duration::from_secs(1),
+    Duration::from_secs(2),
+];
+
+/// Run `request` for `url` again after each of the [`REPLICATION_DELAYS`] while it fails with a
+/// 404 or 410, counting the retries.
+pub async fn retry_missing<F, Fut, T>(url: &str, mut request: F) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    for delay in REPLICATION_DELAYS.iter() {
+        match request().await {
+            Err(error) if is_missing(&error) => {
+                record_retry(url);
+                tokio::time::sleep(*delay).await;
+            }
+            result => return result,
+        }
+    }
+
+    request().await
+}
+
+/// The stats of every registry used so far, by origin.
+pub fn report() -> BTreeMap<String, RegistryStats> {
+    STATS.lock().unwrap().clone()
+}
+
+pub fn report_json() -> Value {
+    serde_json::to_value(report()).unwrap_or_default()
+}
+
+/// `1.2 MB`, `340 kB` or `12 B`.
+pub fn format_bytes(bytes: usize) -> String {
+    match bytes {
+        0..=999 => format!("{} B", bytes),
+        1_000..=999_999 => format!("{:.0} kB", bytes as f64 / 1e3),
+        _ => format!("{:.1} MB", bytes as f64 / 1e6),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_requests_per_origin() {
+        let registry = "https://network-test.example.com";
+
+        record_response(&format!("{}/react", registry), 200);
+        record_bytes(&format!("{}/react", registry), 1_500);
+        record_response(&format!("{}/react/-/react-18.2.0.tgz", registry), 404);
+        record_retry(&format!("{}/react", registry));
+        record_error(&format!("{}/react", registry));
+        record_cache_hit(&format!("{}/react", registry));
+
+        let stats = &report()[registry];
+
+        assert_eq!((stats.requests, stats.errors), (3, 1));
+        assert_eq!(stats.bytes, 1_500);
+        assert_eq!((stats.retries, stats.cache_hits), (1, 1));
+        assert_eq!(stats.statuses.get(&404), Some(&1));
+        assert_eq!(format_bytes(stats.bytes), "2 kB");
+    }
+}
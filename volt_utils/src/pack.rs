@@ -0,0 +1,224 @@
+/*
+    Copyright 2021 Volt Contributors
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+        http://www.apache.org/licenses/LICENSE-2.0
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Pack a package into the tarball published to the registry, or installed from git.
+//!
+//! With a `files` field only the matching files are packed, otherwise every file except the
+//! ones in `.npmignore` and the usual clutter. package.json, the readme and the license are
+//! always packed, at `package/<path>` like npm does.
+
+use std::fs::{self, read_to_string};
+use std::io::Write;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use flate2::{write::GzEncoder, Compression};
+use serde_json::Value;
+use sha1::Sha1;
+use sha2::{Digest, Sha512};
+use walkdir::WalkDir;
+
+use crate::workspace::glob_match;
+
+/// Never packed, whatever `files` says.
+const IGNORED: [&str; 7] = [
+    "node_modules",
+    ".git",
+    ".DS_Store",
+    "npm-debug.log",
+    "volt.lock",
+    ".voltrc",
+    ".volt",
+];
+
+/// Always packed, whatever their extension (matched without case).
+const ALWAYS_PACKED: [&str; 3] = ["readme", "license", "licence"];
+
+pub struct Tarball {
+    pub file_name: String,
+    pub data: Vec<u8>,
+    pub shasum: String,
+    /// `sha512-<base64>`
+    pub integrity: String,
+    /// Packed paths relative to the package
+    pub files: Vec<String>,
+}
+
+/// The paths packed from `dir`, sorted.
+pub fn files(dir: &Path, manifest: &Value) -> Vec<String> {
+    let patterns: Option<Vec<String>> = manifest["files"].as_array().map(|files| {
+        files
+            .iter()
+            .filter_map(|pattern| pattern.as_str())
+            .map(|pattern| pattern.trim_start_matches("./").to_string())
+            .collect()
+    });
+
+    let ignored: Vec<String> = read_to_string(dir.join(".npmignore"))
+        .unwrap_or_default()
+        .lines()
+        .map(|line| line.trim().trim_start_matches('/').trim_end_matches('/'))
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(String::from)
+        .collect();
+
+    let mut files: Vec<String> = WalkDir::new(dir)
+        .min_depth(1)
+        .into_iter()
+        .filter_entry(|entry| !IGNORED.iter().any(|name| entry.file_name() == *name))
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| {
+            let relative = entry.path().strip_prefix(dir).ok()?;
+            Some(relative.to_string_lossy().replace('\\', "/"))
+        })
+        .filter(|path| {
+            if is_always_packed(path) {
+                return true;
+            }
+
+            match &patterns {
+                Some(patterns) => patterns.iter().any(|pattern| matches(pattern, path)),
+                None => !ignored.iter().any(|pattern| matches(pattern, path)),
+            }
+        })
+        .collect();
+
+    files.sort();
+    files
+}
+
+fn is_always_packed(path: &str) -> bool {
+    let lowercase = path.to_lowercase();
+    let stem = lowercase.split('.').next().unwrap_or_default();
+
+    !path.contains('/') && (path == "package.json" || ALWAYS_PACKED.contains(&stem))
+}
+
+/// Check if `pattern` matches `path` or one of the directories it is in.
+fn matches(pattern: &str, path: &str) -> bool {
+    let segments: Vec<&str> = path.split('/').collect();
+
+    (1..=segments.len()).any(|length| {
+        let prefix = segments[..length].join("/");
+        glob_match(pattern, &prefix)
+            || (!pattern.contains('/') && glob_match(pattern, segments[length - 1]))
+    })
+}
+
+/// Pack the package in `dir`.
+pub fn pack(dir: &Path, manifest: &Value) -> Result<Tarball> {
+    let name = manifest["name"].as_str().unwrap_or_default();
+    let version = manifest["version"].as_str().unwrap_or_default();
+
+    let files = files(dir, manifest);
+
+    let mut builder = tar::Builder::new(GzEncoder::new(vec![], Compression::best()));
+
+    for file in files.iter() {
+        let path = dir.join(file);
+        let data = fs::read(&path).with_context(|| format!("failed to read {}", path.display()))?;
+
+        let mut header = tar::Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(if is_executable(&path) { 0o755 } else { 0o644 });
+        // Fixed times, so packing the same files gives the same tarball
+        header.set_mtime(499162500);
+        header.set_cksum();
+
+        builder.append_data(&mut header, format!("package/{}", file), data.as_slice())?;
+    }
+
+    let mut encoder = builder.into_inner()?;
+    encoder.flush()?;
+    let data = encoder.finish()?;
+
+    let shasum = format!("{:x}", Sha1::digest(&data));
+    let integrity = format!("sha512-{}", base64::encode(Sha512::digest(&data)));
+
+    Ok(Tarball {
+        // @scope/name → scope-name-1.0.0.tgz
+        file_name: format!(
+            "{}-{}.tgz",
+            name.trim_start_matches('@').replace('/', "-"),
+            version
+        ),
+        data,
+        shasum,
+        integrity,
+        files,
+    })
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    fs::metadata(path)
+        .map(|metadata| metadata.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+#[cfg(not(unix))]
+fn is_executable(_path: &Path) -> bool {
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn packs_files_field_and_always_packed_files() {
+        let dir = std::env::temp_dir().join(format!("volt-pack-{}", std::process::id()));
+
+        for file in [
+            "package.json",
+            "README.md",
+            "dist/index.js",
+            "dist/lib/a.js",
+            "src/index.ts",
+            "node_modules/a/index.js",
+        ] {
+            let path = dir.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "").unwrap();
+        }
+
+        assert_eq!(
+            files(&dir, &json!({ "files": ["dist"] })),
+            vec![
+                "README.md",
+                "dist/index.js",
+                "dist/lib/a.js",
+                "package.json"
+            ]
+        );
+
+        fs::write(dir.join(".npmignore"), "src\n").unwrap();
+
+        assert_eq!(
+            files(&dir, &json!({})),
+            vec![
+                ".npmignore",
+                "README.md",
+                "dist/index.js",
+                "dist/lib/a.js",
+                "package.json"
+            ]
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
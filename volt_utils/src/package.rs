@@ -14,16 +14,21 @@
     limitations under the License.
 */
 
-use anyhow::Context;
+use anyhow::{Context, Result};
 
 use colored::Colorize;
 use std::io::Write;
 use std::{
     collections::HashMap,
     fs::{read_to_string, File},
+    path::Path,
 };
 
 use serde::{Deserialize, Serialize};
+use serde_json::{ser::PrettyFormatter, Map, Value};
+
+use crate::app::App;
+use crate::workspace::Workspace;
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -90,15 +95,42 @@ pub struct Version {
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(default, rename_all = "camelCase")]
 pub struct Author {
+    #[serde(skip_serializing_if = "String::is_empty")]
     pub name: String,
+    /// `email` and `url`, written back untouched
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(default, rename_all = "camelCase")]
 pub struct Repository {
-    #[serde(rename = "type")]
+    #[serde(rename = "type", skip_serializing_if = "String::is_empty")]
     pub type_field: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
     pub url: String,
+    /// `directory` in monorepos, written back untouched
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+/// `"license": { "type": "MIT", "url": "..." }` in older packages
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct License {
+    #[serde(rename = "type", skip_serializing_if = "String::is_empty")]
+    pub type_field: String,
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+/// `"author": "Jane <jane@example.com>"` or `"author": { "name": "Jane" }`, npm accepts both for
+/// the author, repository and license
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum StringOr<T> {
+    String(String),
+    Object(T),
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -164,21 +196,101 @@ pub struct NpmOperationalInternal {
     pub tmp: String,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+/// Dependency maps whose keys are kept sorted if they were sorted before.
+const DEPENDENCY_FIELDS: [&str; 4] = [
+    "dependencies",
+    "devDependencies",
+    "optionalDependencies",
+    "peerDependencies",
+];
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
 pub struct PackageJson {
+    #[serde(default)]
     pub name: String,
+    #[serde(default)]
     pub version: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub private: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub main: Option<String>,
-    pub repository: Option<String>,
-    pub author: Option<String>,
-    pub license: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repository: Option<StringOr<Repository>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub author: Option<StringOr<Author>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub license: Option<StringOr<License>>,
+    #[serde(default)]
+    pub scripts: HashMap<String, String>,
     #[serde(default)]
     pub dependencies: HashMap<String, String>,
-    #[serde(rename = "devDependencies")]
     #[serde(default)]
     pub dev_dependencies: HashMap<String, String>,
     #[serde(default)]
-    pub scripts: HashMap<String, String>,
+    pub optional_dependencies: HashMap<String, String>,
+    #[serde(default)]
+    pub peer_dependencies: HashMap<String, String>,
+    #[serde(default)]
+    pub peer_dependencies_meta: HashMap<String, PeerDependencyMeta>,
+    #[serde(default)]
+    pub engines: HashMap<String, String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub workspaces: Option<Workspaces>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bin: Option<Bin>,
+    /// Conditional exports can nest arbitrarily, so they are kept as raw JSON
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exports: Option<Value>,
+    #[serde(default)]
+    pub os: Vec<String>,
+    #[serde(default)]
+    pub cpu: Vec<String>,
+    #[serde(default)]
+    pub libc: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub package_manager: Option<String>,
+    /// Every field without a typed counterpart, written back untouched
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+pub struct PeerDependencyMeta {
+    #[serde(default)]
+    pub optional: bool,
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+/// `"workspaces": [...]` or `"workspaces": { "packages": [...] }`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum Workspaces {
+    Packages(Vec<String>),
+    Config {
+        #[serde(default)]
+        packages: Vec<String>,
+        #[serde(flatten)]
+        other: Map<String, Value>,
+    },
+}
+
+impl Workspaces {
+    pub fn packages(&self) -> &[String] {
+        match self {
+            Workspaces::Packages(packages) => packages,
+            Workspaces::Config { packages, .. } => packages,
+        }
+    }
+}
+
+/// `"bin": "cli.js"` or `"bin": { "name": "cli.js" }`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum Bin {
+    Single(String),
+    Multiple(HashMap<String, String>),
 }
 
 impl PackageJson {
@@ -193,13 +305,72 @@ impl PackageJson {
         }
     }
 
-    pub fn save(&self) {
-        let mut file = File::create("package.json").unwrap();
-        file.write(serde_json::to_string_pretty(self).unwrap().as_bytes())
+    /// Get the binaries provided by this package, a single `bin` path is named after the package.
+    pub fn bin_entries(&self) -> HashMap<String, String> {
+        match &self.bin {
+            Some(Bin::Single(path)) => {
+                let name = self.name.rsplit('/').next().unwrap_or_default();
+                let mut entries = HashMap::new();
+                entries.insert(name.to_string(), path.clone());
+                entries
+            }
+            Some(Bin::Multiple(entries)) => entries.clone(),
+            None => HashMap::new(),
+        }
+    }
+
+    /// Write changes back to the package.json at `path`.
+    ///
+    /// The rest of the file keeps its key order, indentation and line endings.
+    pub fn save(&self, path: &Path) {
+        let source = read_to_string(path).unwrap_or_default();
+        let format = JsonFormat::detect(&source);
+
+        let mut data = serde_json::from_str(&source).unwrap_or_else(|_| Value::Object(Map::new()));
+
+        self.merge_into(&mut data);
+
+        let mut file = File::create(path).unwrap();
+        file.write_all(format.write(&data).as_bytes())
             .context("failed to write to package.json")
             .unwrap();
     }
 
+    fn merge_into(&self, data: &mut Value) {
+        let object = match data.as_object_mut() {
+            Some(object) => object,
+            None => {
+                *data = Value::Object(Map::new());
+                data.as_object_mut().unwrap()
+            }
+        };
+
+        let updated = match serde_json::to_value(self) {
+            Ok(Value::Object(updated)) => updated,
+            _ => return,
+        };
+
+        // Drop fields which were cleared on the struct, `Map::remove` would shuffle the key order
+        *object = std::mem::take(object)
+            .into_iter()
+            .filter(|(key, _)| updated.contains_key(key))
+            .collect();
+
+        for (key, value) in updated {
+            match value {
+                Value::Object(entries) => {
+                    let sort = DEPENDENCY_FIELDS.contains(&key.as_str());
+                    merge_map(object, &key, entries, sort);
+                }
+                // Don't add empty defaults for fields the file never had
+                value if is_empty(&value) && !object.contains_key(&key) => {}
+                value => {
+                    object.insert(key, value);
+                }
+            }
+        }
+    }
+
     // pub fn add_dependency(&mut self, name: String, version: String) {
     //     self.dependencies.unwrap().insert(name, version);
     // }
@@ -208,3 +379,378 @@ impl PackageJson {
     //     self.dependencies.unwrap().remove(&name);
     // }
 }
+
+/// Fields of a package.json which decide what gets installed.
+pub const INSTALL_FIELDS: [&str; 8] = [
+    "dependencies",
+    "devDependencies",
+    "optionalDependencies",
+    "peerDependencies",
+    "overrides",
+    "resolutions",
+    "catalog",
+    "catalogs",
+];
+
+/// The scripts npm runs when a package is installed, in order.
+pub const INSTALL_SCRIPTS: [&str; 3] = ["preinstall", "install", "postinstall"];
+
+/// The install scripts of the package in `dir` in the order they run, with the `node-gyp
+/// rebuild` npm runs for a `binding.gyp` when the package has no install script of its own.
+pub fn install_scripts(dir: &Path) -> Vec<(String, String)> {
+    let manifest: Value = read_to_string(dir.join("package.json"))
+        .ok()
+        .and_then(|data| serde_json::from_str(&data).ok())
+        .unwrap_or_default();
+
+    let mut scripts: Vec<(String, String)> = INSTALL_SCRIPTS
+        .iter()
+        .filter_map(|name| {
+            let command = manifest["scripts"][*name].as_str()?;
+            Some((name.to_string(), command.to_string()))
+        })
+        .collect();
+
+    let has_install = scripts
+        .iter()
+        .any(|(name, _)| name == "install" || name == "preinstall");
+
+    if !has_install && dir.join("binding.gyp").exists() {
+        scripts.insert(
+            0,
+            (String::from("install"), String::from("node-gyp rebuild")),
+        );
+    }
+
+    scripts
+}
+
+/// Hash the install fields of the project package.json and of its workspace members.
+///
+/// Formatting, key order and the other fields don't change the hash, so it only changes when
+/// the lock file needs to be updated.
+pub fn manifest_hash(project_dir: &Path) -> Result<String> {
+    let mut manifests = vec![project_dir.to_path_buf()];
+
+    if let Some(workspace) = Workspace::load(project_dir) {
+        manifests.extend(workspace.members());
+    }
+
+    let mut inputs = Map::new();
+
+    for dir in manifests {
+        let path = dir.join("package.json");
+        let data: Value = serde_json::from_str(
+            &read_to_string(&path).with_context(|| format!("failed to read {}", path.display()))?,
+        )?;
+
+        let fields: Map<String, Value> = INSTALL_FIELDS
+            .iter()
+            .filter_map(|field| Some((field.to_string(), canonical(data.get(*field)?))))
+            .collect();
+
+        let relative = dir.strip_prefix(project_dir).unwrap_or(&dir);
+
+        inputs.insert(
+            relative.to_string_lossy().replace('\\', "/"),
+            Value::Object(fields),
+        );
+    }
+
+    App::calc_hash(&bytes::Bytes::from(
+        serde_json::to_string(&canonical(&Value::Object(inputs)))?.into_bytes(),
+    ))
+}
+
+/// Sort the keys of every object in `value`.
+fn canonical(value: &Value) -> Value {
+    match value {
+        Value::Object(object) => {
+            let mut entries: Vec<(&String, &Value)> = object.iter().collect();
+            entries.sort_by(|a, b| a.0.cmp(b.0));
+
+            Value::Object(
+                entries
+                    .into_iter()
+                    .map(|(key, value)| (key.clone(), canonical(value)))
+                    .collect(),
+            )
+        }
+        Value::Array(values) => Value::Array(values.iter().map(canonical).collect()),
+        value => value.clone(),
+    }
+}
+
+fn is_empty(value: &Value) -> bool {
+    match value {
+        Value::String(value) => value.is_empty(),
+        Value::Array(values) => values.is_empty(),
+        Value::Object(entries) => entries.is_empty(),
+        _ => false,
+    }
+}
+
+/// Replace the object at `key` with `map`, keeping the order of existing entries.
+///
+/// New entries are appended, or sorted in if `sort` is set and the existing entries were sorted.
+fn merge_map(object: &mut Map<String, Value>, key: &str, mut map: Map<String, Value>, sort: bool) {
+    if map.is_empty() && !object.contains_key(key) {
+        return;
+    }
+
+    let existing = object
+        .get(key)
+        .and_then(Value::as_object)
+        .cloned()
+        .unwrap_or_default();
+
+    let existing_keys: Vec<&String> = existing.keys().collect();
+    let sorted = existing_keys.windows(2).all(|pair| pair[0] <= pair[1]);
+
+    let mut entries: Vec<(String, Value)> = existing
+        .keys()
+        .filter_map(|name| map.remove(name).map(|value| (name.clone(), value)))
+        .collect();
+
+    let mut added: Vec<(String, Value)> = map.into_iter().collect();
+
+    added.sort_by(|a, b| a.0.cmp(&b.0));
+    entries.extend(added);
+
+    if sort && sorted {
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+    }
+
+    object.insert(
+        key.to_string(),
+        Value::Object(entries.into_iter().collect()),
+    );
+}
+
+/// Formatting of a JSON file, detected so that it can be kept when the file is rewritten.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonFormat {
+    pub indent: String,
+    pub crlf: bool,
+    pub trailing_newline: bool,
+}
+
+impl Default for JsonFormat {
+    fn default() -> Self {
+        JsonFormat {
+            indent: String::from("  "),
+            crlf: false,
+            trailing_newline: true,
+        }
+    }
+}
+
+impl JsonFormat {
+    /// Detect the formatting of `source`, falling back to npm's defaults for empty files.
+    pub fn detect(source: &str) -> Self {
+        if source.trim().is_empty() {
+            return Self::default();
+        }
+
+        // The whitespace in front of the first indented line
+        let indent = source
+            .lines()
+            .skip(1)
+            .map(|line| {
+                let trimmed = line.trim_start_matches(&[' ', '\t'][..]);
+                &line[..line.len() - trimmed.len()]
+            })
+            .find(|indent| !indent.is_empty())
+            .unwrap_or("  ");
+
+        JsonFormat {
+            indent: indent.to_string(),
+            crlf: source.contains("\r\n"),
+            trailing_newline: source.ends_with('\n'),
+        }
+    }
+
+    /// Serialize `value` with this formatting.
+    pub fn write(&self, value: &Value) -> String {
+        let mut buffer = Vec::new();
+        let formatter = PrettyFormatter::with_indent(self.indent.as_bytes());
+        let mut serializer = serde_json::Serializer::with_formatter(&mut buffer, formatter);
+
+        value.serialize(&mut serializer).unwrap();
+
+        // Raw newlines can't appear inside JSON strings, so this only touches the layout
+        let mut output = String::from_utf8(buffer).unwrap();
+
+        if self.trailing_newline {
+            output.push('\n');
+        }
+
+        if self.crlf {
+            output = output.replace('\n', "\r\n");
+        }
+
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_tab_indentation_and_key_order() {
+        let source = "{\n\t\"version\": \"1.0.0\",\n\t\"name\": \"app\",\n\t\"private\": true\n}";
+        let format = JsonFormat::detect(source);
+
+        assert_eq!(format.indent, "\t");
+        assert!(!format.trailing_newline);
+
+        let value: Value = serde_json::from_str(source).unwrap();
+        assert_eq!(format.write(&value), source);
+    }
+
+    #[test]
+    fn keeps_crlf_line_endings() {
+        let source = "{\r\n    \"name\": \"app\"\r\n}\r\n";
+        let format = JsonFormat::detect(source);
+
+        let value: Value = serde_json::from_str(source).unwrap();
+        assert_eq!(format.write(&value), source);
+    }
+
+    #[test]
+    fn sorted_dependencies_stay_sorted() {
+        let mut object: Map<String, Value> =
+            serde_json::from_str(r#"{"dependencies": {"a": "1", "c": "1"}}"#).unwrap();
+
+        let dependencies: Map<String, Value> =
+            serde_json::from_str(r#"{"c": "1", "b": "2", "a": "1"}"#).unwrap();
+
+        merge_map(&mut object, "dependencies", dependencies, true);
+
+        let keys: Vec<&String> = object["dependencies"].as_object().unwrap().keys().collect();
+        assert_eq!(keys, vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn round_trips_unknown_fields() {
+        let source = r#"{
+  "name": "app",
+  "description": "An app",
+  "bin": "cli.js",
+  "exports": {
+    "import": "./index.mjs",
+    "require": "./index.cjs"
+  },
+  "peerDependenciesMeta": {
+    "react": {
+      "optional": true
+    }
+  },
+  "volta": {
+    "node": "16.0.0"
+  }
+}"#;
+
+        let package: PackageJson = serde_json::from_str(source).unwrap();
+        assert_eq!(package.bin_entries()["app"], "cli.js");
+        assert!(package.peer_dependencies_meta["react"].optional);
+
+        let mut data: Value = serde_json::from_str(source).unwrap();
+        package.merge_into(&mut data);
+
+        assert_eq!(JsonFormat::default().write(&data), format!("{}\n", source));
+    }
+
+    #[test]
+    fn parses_the_object_forms() {
+        let source = r#"{
+  "name": "app",
+  "author": {
+    "name": "Jane",
+    "email": "jane@example.com"
+  },
+  "repository": {
+    "type": "git",
+    "url": "https://github.com/jane/app.git",
+    "directory": "packages/app"
+  },
+  "license": {
+    "type": "MIT"
+  }
+}"#;
+
+        let package: PackageJson = serde_json::from_str(source).unwrap();
+
+        match &package.author {
+            Some(StringOr::Object(author)) => assert_eq!(author.name, "Jane"),
+            author => panic!("unexpected author {:?}", author),
+        }
+        match &package.repository {
+            Some(StringOr::Object(repository)) => {
+                assert_eq!(repository.url, "https://github.com/jane/app.git")
+            }
+            repository => panic!("unexpected repository {:?}", repository),
+        }
+
+        let mut data: Value = serde_json::from_str(source).unwrap();
+        package.merge_into(&mut data);
+        assert_eq!(JsonFormat::default().write(&data), format!("{}\n", source));
+
+        let package: PackageJson =
+            serde_json::from_str(r#"{ "author": "Jane", "license": "MIT" }"#).unwrap();
+        assert_eq!(package.author, Some(StringOr::String(String::from("Jane"))));
+        assert_eq!(package.license, Some(StringOr::String(String::from("MIT"))));
+    }
+
+    #[test]
+    fn finds_install_scripts_in_order() {
+        let dir = std::env::temp_dir().join(format!("volt-install-scripts-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        std::fs::write(
+            dir.join("package.json"),
+            r#"{ "scripts": { "postinstall": "node setup.js", "test": "jest" } }"#,
+        )
+        .unwrap();
+        std::fs::write(dir.join("binding.gyp"), "{}").unwrap();
+
+        assert_eq!(
+            install_scripts(&dir),
+            vec![
+                (String::from("install"), String::from("node-gyp rebuild")),
+                (String::from("postinstall"), String::from("node setup.js")),
+            ]
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(install_scripts(&dir).is_empty());
+    }
+
+    #[test]
+    fn manifest_hash_ignores_formatting() {
+        let dir = std::env::temp_dir().join(format!("volt-manifest-hash-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let path = dir.join("package.json");
+
+        std::fs::write(
+            &path,
+            r#"{"name": "app", "dependencies": {"a": "^1.0.0", "b": "^2.0.0"}}"#,
+        )
+        .unwrap();
+        let hash = manifest_hash(&dir).unwrap();
+
+        std::fs::write(
+            &path,
+            "{\n\t\"dependencies\": {\"b\": \"^2.0.0\", \"a\": \"^1.0.0\"},\n\t\"name\": \"renamed\"\n}",
+        )
+        .unwrap();
+        assert_eq!(manifest_hash(&dir).unwrap(), hash);
+
+        std::fs::write(&path, r#"{"dependencies": {"a": "^1.1.0", "b": "^2.0.0"}}"#).unwrap();
+        assert_ne!(manifest_hash(&dir).unwrap(), hash);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,135 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Where volt keeps its files on each platform.
+//!
+//! |        | Linux                          | macOS                                | Windows                   |
+//! |--------|--------------------------------|--------------------------------------|---------------------------|
+//! | store  | `$XDG_DATA_HOME/volt/store`    | `~/Library/Application Support/volt/store` | `%LOCALAPPDATA%\volt\store` |
+//! | cache  | `$XDG_CACHE_HOME/volt`         | `~/Library/Caches/volt`              | `%LOCALAPPDATA%\volt`     |
+//! | config | `$XDG_CONFIG_HOME/volt/voltrc` | `~/Library/Application Support/volt/voltrc` | `%APPDATA%\volt\voltrc` |
+//!
+//! Older versions kept everything in `~/.volt` and `~/.voltrc`, which are moved to the new
+//! locations on the first run. If a move fails, the old location keeps being used.
+
+use std::fs::{create_dir_all, rename};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Paths {
+    /// Extracted packages
+    pub store: PathBuf,
+    /// Metadata and downloads which can be thrown away
+    pub cache: PathBuf,
+    /// User `.voltrc`
+    pub config_file: PathBuf,
+}
+
+impl Paths {
+    /// The directories of the platform, under `home` where the platform has none.
+    pub fn platform(home: &Path) -> Self {
+        let data = dirs::data_local_dir().unwrap_or_else(|| home.join(".local").join("share"));
+        let cache = dirs::cache_dir().unwrap_or_else(|| home.join(".cache"));
+        let config = dirs::config_dir().unwrap_or_else(|| home.join(".config"));
+
+        Paths {
+            store: data.join("volt").join("store"),
+            cache: cache.join("volt"),
+            config_file: config.join("volt").join("voltrc"),
+        }
+    }
+
+    /// The locations used before platform directories.
+    pub fn legacy(home: &Path) -> Self {
+        Paths {
+            store: home.join(".volt"),
+            cache: home.join(".volt").join(".cache"),
+            config_file: home.join(".voltrc"),
+        }
+    }
+
+    /// Get the platform directories, moving the files of legacy locations there first.
+    pub fn resolve(home: &Path) -> Self {
+        Self::migrate(&Self::legacy(home), Self::platform(home))
+    }
+
+    fn migrate(legacy: &Paths, platform: Paths) -> Self {
+        let store = move_if_missing(&legacy.store, platform.store);
+
+        Paths {
+            // The legacy cache was inside the legacy store
+            cache: move_if_missing(&store.join(".cache"), platform.cache),
+            config_file: move_if_missing(&legacy.config_file, platform.config_file),
+            store,
+        }
+    }
+}
+
+/// Move `from` to `to` unless `to` already exists, and get the location to use.
+fn move_if_missing(from: &Path, to: PathBuf) -> PathBuf {
+    if !from.exists() || to.exists() {
+        return to;
+    }
+
+    let moved = to
+        .parent()
+        .map_or(Ok(()), create_dir_all)
+        .and_then(|_| rename(from, &to));
+
+    match moved {
+        Ok(()) => to,
+        Err(_) => from.to_path_buf(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn migrates_legacy_locations() {
+        let root = std::env::temp_dir().join(format!("volt-paths-{}", std::process::id()));
+        let home = root.join("home");
+
+        std::fs::create_dir_all(home.join(".volt").join(".cache")).unwrap();
+        std::fs::create_dir_all(home.join(".volt").join("react")).unwrap();
+        std::fs::write(home.join(".volt").join(".cache").join("metadata.redb"), "").unwrap();
+        std::fs::write(home.join(".voltrc"), "registry=https://npm.acme.dev\n").unwrap();
+
+        let platform = Paths {
+            store: root.join("data").join("volt").join("store"),
+            cache: root.join("cache").join("volt"),
+            config_file: root.join("config").join("volt").join("voltrc"),
+        };
+
+        let paths = Paths::migrate(&Paths::legacy(&home), platform.clone());
+
+        assert_eq!(paths, platform);
+        assert!(paths.store.join("react").exists());
+        assert!(!paths.store.join(".cache").exists());
+        assert!(paths.cache.join("metadata.redb").exists());
+        assert!(paths.config_file.exists());
+        assert!(!home.join(".volt").exists());
+
+        // Nothing left to move
+        assert_eq!(
+            Paths::migrate(&Paths::legacy(&home), platform.clone()),
+            platform
+        );
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,358 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Peer dependency checks.
+//!
+//! Unmet or conflicting peers are warnings by default. With `strict-peer-dependencies=true` in
+//! `.voltrc` or the `--strict-peer-deps` flag they fail the install instead, unless one of the
+//! `fixes` is chosen for each peer when installing from a terminal.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::fs::read_to_string;
+use std::path::Path;
+
+use colored::Colorize;
+use node_semver::{Range, Version};
+
+use crate::app::App;
+use crate::package::PackageJson;
+use crate::volt_api::VoltPackage;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PeerProblem {
+    /// `name@version` of the package declaring the peer
+    pub package: String,
+    pub peer: String,
+    pub range: Option<String>,
+    /// Version of the peer in the tree, `None` when it is missing
+    pub found: Option<String>,
+}
+
+impl PeerProblem {
+    pub fn describe(&self) -> String {
+        let wanted = match &self.range {
+            Some(range) => format!("{}@{}", self.peer, range),
+            None => self.peer.clone(),
+        };
+
+        match &self.found {
+            Some(found) => format!("peer {}, found {}", wanted, found),
+            None => format!("missing peer {}", wanted),
+        }
+    }
+}
+
+/// A change to the project which settles the problems of a peer.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PeerFix {
+    /// Add the peer as a direct dependency, in a range the packages want if there is one
+    Pin { peer: String, range: Option<String> },
+    /// Mark the missing peer optional in `peerDependenciesMeta`
+    Skip { peer: String },
+}
+
+impl PeerFix {
+    pub fn describe(&self) -> String {
+        match self {
+            PeerFix::Pin {
+                peer,
+                range: Some(range),
+            } => format!("pin {}@{} as a dependency", peer, range),
+            PeerFix::Pin { peer, range: None } => format!("add {} as a dependency", peer),
+            PeerFix::Skip { peer } => format!("skip {}, marking it optional", peer),
+        }
+    }
+
+    /// The package to install for the fix.
+    pub fn spec(&self) -> Option<String> {
+        match self {
+            PeerFix::Pin {
+                peer,
+                range: Some(range),
+            } => Some(format!("{}@{}", peer, range)),
+            PeerFix::Pin { peer, range: None } => Some(peer.clone()),
+            PeerFix::Skip { .. } => None,
+        }
+    }
+
+    /// Record the fix in the project manifest, pinned peers are added once they are resolved.
+    pub fn apply(&self, root: &mut PackageJson) {
+        if let PeerFix::Skip { peer } = self {
+            root.peer_dependencies_meta
+                .entry(peer.clone())
+                .or_default()
+                .optional = true;
+        }
+    }
+}
+
+/// The fixes of `problems` for each peer, by name.
+///
+/// Every range a package wants can be pinned. Only missing peers can be skipped, an optional
+/// peer is still checked when it is installed.
+pub fn fixes(problems: &[PeerProblem]) -> Vec<(String, Vec<PeerFix>)> {
+    let mut grouped: BTreeMap<&str, Vec<&PeerProblem>> = BTreeMap::new();
+
+    for problem in problems {
+        grouped.entry(&problem.peer).or_default().push(problem);
+    }
+
+    grouped
+        .into_iter()
+        .map(|(peer, problems)| {
+            let mut fixes: Vec<PeerFix> = vec![];
+
+            for problem in problems.iter() {
+                let pin = PeerFix::Pin {
+                    peer: peer.to_string(),
+                    range: problem.range.clone(),
+                };
+
+                if !fixes.contains(&pin) {
+                    fixes.push(pin);
+                }
+            }
+
+            if problems.iter().all(|problem| problem.found.is_none()) {
+                fixes.push(PeerFix::Skip {
+                    peer: peer.to_string(),
+                });
+            }
+
+            (peer.to_string(), fixes)
+        })
+        .collect()
+}
+
+/// Check if peer dependencies should fail the install.
+pub fn is_strict(app: &App) -> bool {
+    app.has_flag(&["--strict-peer-deps"])
+        || app.config.get_bool("strict-peer-dependencies") == Some(true)
+}
+
+/// Split a peer entry (`react` or `react@^17.0.0`) into its name and range.
+fn split_spec(spec: &str) -> (&str, Option<&str>) {
+    // Scoped names start with `@`, so the range separator is searched after it
+    match spec.get(1..).and_then(|rest| rest.find('@')) {
+        Some(index) => (&spec[..index + 1], Some(&spec[index + 2..])),
+        None => (spec, None),
+    }
+}
+
+/// Find the peers of `packages` which are missing from the tree or have the wrong version.
+///
+/// Peers the project depends on directly but which were not resolved with `packages` are looked
+/// up in `node_modules_dir`.
+pub fn check(
+    packages: &HashMap<String, VoltPackage>,
+    root: &PackageJson,
+    node_modules_dir: &Path,
+) -> Vec<PeerProblem> {
+    let mut problems = vec![];
+
+    for package in packages.values() {
+        for spec in package.peer_dependencies.iter() {
+            let (peer, range) = split_spec(spec);
+
+            let found = match packages.get(peer) {
+                Some(resolved) => Some(resolved.version.clone()),
+                None if root.dependencies.contains_key(peer)
+                    || root.dev_dependencies.contains_key(peer)
+                    || root.optional_dependencies.contains_key(peer) =>
+                {
+                    match installed_version(node_modules_dir, peer) {
+                        Some(version) => Some(version),
+                        // Not installed yet, it will be once the project's dependencies are
+                        None => continue,
+                    }
+                }
+                None if root
+                    .peer_dependencies_meta
+                    .get(peer)
+                    .is_some_and(|meta| meta.optional) =>
+                {
+                    continue
+                }
+                None => None,
+            };
+
+            let satisfied = match (&found, range) {
+                (None, _) => false,
+                (Some(_), None) => true,
+                (Some(found), Some(range)) => match (Range::parse(range), Version::parse(found)) {
+                    (Ok(range), Ok(version)) => range.satisfies(&version),
+                    // Tags and urls can't be checked
+                    _ => true,
+                },
+            };
+
+            if !satisfied {
+                problems.push(PeerProblem {
+                    package: format!("{}@{}", package.name, package.version),
+                    peer: peer.to_string(),
+                    range: range.map(String::from),
+                    found,
+                });
+            }
+        }
+    }
+
+    problems.sort_by(|a, b| (&a.package, &a.peer).cmp(&(&b.package, &b.peer)));
+    problems
+}
+
+fn installed_version(node_modules_dir: &Path, name: &str) -> Option<String> {
+    let source = read_to_string(node_modules_dir.join(name).join("package.json")).ok()?;
+    let manifest: serde_json::Value = serde_json::from_str(&source).ok()?;
+
+    manifest["version"].as_str().map(String::from)
+}
+
+/// Render `problems` as a tree grouped by the package declaring the peers.
+///
+/// ```text
+/// .
+/// ├─┬ react-dom@17.0.2
+/// │ └── peer react@^17.0.2, found 16.14.0
+/// └─┬ styled-components@5.3.0
+///   └── missing peer react-is@>= 16.8.0
+/// ```
+pub fn conflict_tree(problems: &[PeerProblem]) -> String {
+    let mut grouped: BTreeMap<&str, Vec<&PeerProblem>> = BTreeMap::new();
+
+    for problem in problems {
+        grouped.entry(&problem.package).or_default().push(problem);
+    }
+
+    let mut tree = String::from(".\n");
+
+    for (index, (package, problems)) in grouped.iter().enumerate() {
+        let last_package = index == grouped.len() - 1;
+        let (branch, indent) = if last_package {
+            ("└─┬", "  ")
+        } else {
+            ("├─┬", "│ ")
+        };
+
+        let _ = writeln!(tree, "{} {}", branch, package.bright_cyan());
+
+        for (index, problem) in problems.iter().enumerate() {
+            let branch = if index == problems.len() - 1 {
+                "└──"
+            } else {
+                "├──"
+            };
+
+            let _ = writeln!(
+                tree,
+                "{}{} {}",
+                indent,
+                branch,
+                problem.describe().bright_yellow()
+            );
+        }
+    }
+
+    tree
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn package(name: &str, version: &str, peers: &[&str]) -> (String, VoltPackage) {
+        (
+            name.to_string(),
+            VoltPackage {
+                name: name.to_string(),
+                version: version.to_string(),
+                tarball: String::new(),
+                sha1: String::new(),
+                integrity: None,
+                peer_dependencies: peers.iter().map(|peer| peer.to_string()).collect(),
+                dependencies: None,
+                bin: None,
+                optional_dependencies: vec![],
+                os: vec![],
+                cpu: vec![],
+                libc: vec![],
+                source: Default::default(),
+                deprecated: None,
+                engines: Default::default(),
+            },
+        )
+    }
+
+    #[test]
+    fn finds_unmet_and_conflicting_peers() {
+        let packages: HashMap<String, VoltPackage> = vec![
+            package("react-dom", "17.0.2", &["react@^17.0.2"]),
+            package("react", "16.14.0", &[]),
+            package("@emotion/react", "11.4.0", &["@babel/core", "react"]),
+        ]
+        .into_iter()
+        .collect();
+
+        let problems = check(
+            &packages,
+            &PackageJson::default(),
+            Path::new("node_modules"),
+        );
+
+        assert_eq!(
+            problems
+                .iter()
+                .map(PeerProblem::describe)
+                .collect::<Vec<_>>(),
+            vec![
+                "missing peer @babel/core",
+                "peer react@^17.0.2, found 16.14.0"
+            ]
+        );
+
+        assert_eq!(
+            fixes(&problems),
+            vec![
+                (
+                    String::from("@babel/core"),
+                    vec![
+                        PeerFix::Pin {
+                            peer: String::from("@babel/core"),
+                            range: None,
+                        },
+                        PeerFix::Skip {
+                            peer: String::from("@babel/core"),
+                        },
+                    ]
+                ),
+                (
+                    String::from("react"),
+                    vec![PeerFix::Pin {
+                        peer: String::from("react"),
+                        range: Some(String::from("^17.0.2")),
+                    }]
+                ),
+            ]
+        );
+
+        colored::control::set_override(false);
+        assert_eq!(
+            conflict_tree(&problems),
+            ".\n├─┬ @emotion/react@11.4.0\n│ └── missing peer @babel/core\n└─┬ react-dom@17.0.2\n  └── peer react@^17.0.2, found 16.14.0\n"
+        );
+    }
+}
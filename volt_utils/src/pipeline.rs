@@ -0,0 +1,307 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Resolve and fetch packages at the same time.
+//!
+//! Every requested package is resolved concurrently, and the downloads of a resolution start as
+//! soon as it arrives instead of once every package has been resolved, so the network is busy
+//! with tarballs while slower resolutions are still in flight. Packages shared between
+//! resolutions are downloaded once. Only the volt store is written, node_modules is left to the
+//! linking step, which links the resolutions together as one tree (`Pipeline::packages`).
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context, Result};
+use futures_util::stream::FuturesUnordered;
+use futures_util::StreamExt;
+
+use crate::app::App;
+use crate::mirror;
+use crate::network;
+use crate::platform::{self, Platform};
+use crate::policy;
+use crate::reporter::{self, Progress};
+use crate::resolver;
+use crate::volt_api::{VoltPackage, VoltResponse};
+
+pub struct Resolution {
+    /// Name of the requested package
+    pub name: String,
+    /// The package as it was requested (`react@^17`)
+    pub spec: String,
+    pub response: VoltResponse,
+    /// Packages of the resolved version which are installed on the target platform
+    pub packages: HashMap<String, VoltPackage>,
+    /// Optional packages left out for other platforms
+    pub skipped: Vec<String>,
+}
+
+pub struct Pipeline {
+    /// Resolutions in the order they were requested
+    pub resolutions: Vec<Resolution>,
+    /// Names of the packages downloaded into the store by this run
+    pub fetched: HashSet<String>,
+}
+
+impl Pipeline {
+    /// The packages of every resolution, as the single tree node_modules is linked from.
+    ///
+    /// When resolutions need different versions of a package, a requested package keeps the
+    /// version it was resolved to, otherwise the earliest requested resolution wins.
+    pub fn packages(&self) -> HashMap<String, VoltPackage> {
+        let mut packages = HashMap::new();
+
+        for resolution in self.resolutions.iter() {
+            if let Some(package) = resolution.packages.get(&resolution.name) {
+                packages.insert(resolution.name.clone(), package.clone());
+            }
+        }
+
+        for resolution in self.resolutions.iter() {
+            for (name, package) in resolution.packages.iter() {
+                packages
+                    .entry(name.clone())
+                    .or_insert_with(|| package.clone());
+            }
+        }
+
+        packages
+    }
+}
+
+/// Fetch `package` into the store, or the version before it when its tarball isn't on the
+/// registry yet and that version needs nothing but the packages of the resolution, `names`.
+///
+/// Returns the name of the package, and the version fetched instead if there was a fallback.
+async fn fetch_or_fall_back(
+    app: &App,
+    package: VoltPackage,
+    names: &HashSet<String>,
+) -> Result<(String, Option<VoltPackage>)> {
+    let error = match crate::fetch_package(app, &package).await {
+        Ok(_) => return Ok((package.name, None)),
+        Err(error) if network::is_missing(&error) => error,
+        Err(error) => return Err(error),
+    };
+
+    let fallback = match resolver::fallback(app, &package).await {
+        Ok(fallback)
+            if fallback
+                .dependencies
+                .iter()
+                .flatten()
+                .all(|name| names.contains(name)) =>
+        {
+            fallback
+        }
+        _ => return Err(error),
+    };
+
+    reporter::get().warn(&format!(
+        "the tarball of {}@{} is not on the registry yet, using {}",
+        package.name, package.version, fallback.version
+    ));
+
+    crate::fetch_package(app, &fallback).await?;
+
+    Ok((package.name, Some(fallback)))
+}
+
+/// Resolve `names` and download everything they need into the volt store.
+///
+/// `progress` grows as resolutions add downloads and advances as downloads complete.
+pub async fn resolve_and_fetch(
+    app: Arc<App>,
+    names: Vec<String>,
+    progress: &Progress,
+) -> Result<Pipeline> {
+    resolve_and_fetch_locked(app, names, HashMap::new(), progress).await
+}
+
+/// Like [`resolve_and_fetch`], with the resolutions of the names in `locked` already known,
+/// from the lock file. They are still checked against the [policy](crate::policy).
+pub async fn resolve_and_fetch_locked(
+    app: Arc<App>,
+    names: Vec<String>,
+    mut locked: HashMap<String, VoltResponse>,
+    progress: &Progress,
+) -> Result<Pipeline> {
+    let platform = Platform::from_app(&app);
+
+    let mut resolving = FuturesUnordered::new();
+
+    for name in names.iter().cloned() {
+        let app = app.clone();
+        let locked = locked.remove(&name);
+
+        resolving.push(async move {
+            let response = match locked {
+                Some(response) => policy::enforce(&app, &response).await.map(|_| response),
+                None => mirror::get_volt_response(&app, &name).await,
+            };
+            (name, response)
+        });
+    }
+
+    let mut fetching = FuturesUnordered::new();
+    let mut queued = HashSet::new();
+    let mut resolutions = vec![];
+    let mut fetched = HashSet::new();
+    let mut fallbacks = HashMap::new();
+
+    loop {
+        tokio::select! {
+            Some((name, response)) = resolving.next() => {
+                let response = response?;
+                let version = response.versions.get(&response.version).ok_or_else(|| {
+                    anyhow!("{}@{} is missing from its resolution", name, response.version)
+                })?;
+
+                let (packages, skipped) = platform::select(&version.packages, &platform);
+                let names: Arc<HashSet<String>> = Arc::new(packages.keys().cloned().collect());
+
+                for package in packages.values() {
+                    if crate::resume::is_extracted(&app, &package.store_name())
+                        || !queued.insert(package.name.clone())
+                    {
+                        continue;
+                    }
+
+                    let app = app.clone();
+                    let package = package.clone();
+                    let names = names.clone();
+
+                    progress.inc_length(1);
+
+                    fetching.push(async move {
+                        let context = format!("failed to fetch {}@{}", package.name, package.version);
+
+                        fetch_or_fall_back(&app, package, &names)
+                            .await
+                            .context(context)
+                    });
+                }
+
+                resolutions.push(Resolution {
+                    name: crate::parse_package_spec(&name).0,
+                    spec: name,
+                    response,
+                    packages,
+                    skipped,
+                });
+            }
+            Some(result) = fetching.next() => {
+                let (name, fallback) = result?;
+
+                if let Some(fallback) = fallback {
+                    fallbacks.insert(name.clone(), fallback);
+                }
+
+                fetched.insert(name);
+                progress.inc(1);
+            }
+            else => break,
+        }
+    }
+
+    for resolution in resolutions.iter_mut() {
+        for (name, fallback) in fallbacks.iter() {
+            if let Some(package) = resolution.packages.get_mut(name) {
+                *package = fallback.clone();
+
+                if resolution.name == *name {
+                    resolution.response.version = fallback.version.clone();
+                }
+            }
+        }
+    }
+
+    resolutions.sort_by_key(|resolution| names.iter().position(|name| *name == resolution.spec));
+
+    Ok(Pipeline {
+        resolutions,
+        fetched,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolution(name: &str, packages: &[(&str, &str)]) -> Resolution {
+        let packages: HashMap<String, VoltPackage> = packages
+            .iter()
+            .map(|(name, version)| {
+                let package = VoltPackage {
+                    name: name.to_string(),
+                    version: version.to_string(),
+                    tarball: String::new(),
+                    sha1: String::new(),
+                    integrity: None,
+                    peer_dependencies: vec![],
+                    dependencies: None,
+                    bin: None,
+                    optional_dependencies: vec![],
+                    os: vec![],
+                    cpu: vec![],
+                    libc: vec![],
+                    source: Default::default(),
+                    deprecated: None,
+                    engines: Default::default(),
+                };
+
+                (name.to_string(), package)
+            })
+            .collect();
+
+        Resolution {
+            name: name.to_string(),
+            spec: name.to_string(),
+            response: VoltResponse {
+                version: packages[name].version.clone(),
+                versions: HashMap::new(),
+            },
+            packages,
+            skipped: vec![],
+        }
+    }
+
+    #[test]
+    fn merges_resolutions_into_one_tree() {
+        let pipeline = Pipeline {
+            resolutions: vec![
+                resolution("express", &[("express", "4.17.1"), ("debug", "2.6.9")]),
+                resolution("nodemon", &[("nodemon", "2.0.12"), ("debug", "3.2.7")]),
+                resolution("debug", &[("debug", "4.3.2"), ("ms", "2.1.2")]),
+            ],
+            fetched: HashSet::new(),
+        };
+
+        let versions: HashMap<String, String> = pipeline
+            .packages()
+            .into_iter()
+            .map(|(name, package)| (name, package.version))
+            .collect();
+
+        assert_eq!(versions.len(), 4);
+        assert_eq!(versions["express"], "4.17.1");
+        assert_eq!(versions["nodemon"], "2.0.12");
+        // Asked for, so not the version express or nodemon depend on
+        assert_eq!(versions["debug"], "4.3.2");
+        assert_eq!(versions["ms"], "2.1.2");
+    }
+}
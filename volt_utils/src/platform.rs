@@ -0,0 +1,304 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! The platform packages are installed for.
+//!
+//! Packages declare supported platforms with the `os`, `cpu` and `libc` fields of their
+//! package.json, using node's names (`darwin`, `win32`, `x64`, `arm64`, `glibc`, `musl`).
+//! `--os`, `--cpu` and `--libc` install for another platform, like a linux-x64 Docker image built
+//! from a Mac. Like npm, projects whose own fields exclude the platform are not installed
+//! without `--force`.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::app::App;
+use crate::volt_api::VoltPackage;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Platform {
+    pub os: String,
+    pub cpu: String,
+    /// Only known on linux
+    pub libc: Option<String>,
+}
+
+impl Platform {
+    /// The platform volt is running on.
+    pub fn current() -> Self {
+        let os = match std::env::consts::OS {
+            "macos" => "darwin",
+            "windows" => "win32",
+            os => os,
+        };
+
+        let cpu = match std::env::consts::ARCH {
+            "x86_64" => "x64",
+            "x86" => "ia32",
+            "aarch64" => "arm64",
+            "powerpc64" => "ppc64",
+            cpu => cpu,
+        };
+
+        let libc = if os != "linux" {
+            None
+        } else if cfg!(target_env = "musl") {
+            Some(String::from("musl"))
+        } else {
+            Some(String::from("glibc"))
+        };
+
+        Platform {
+            os: os.to_string(),
+            cpu: cpu.to_string(),
+            libc,
+        }
+    }
+
+    /// The current platform with the `--os`, `--cpu` and `--libc` overrides applied.
+    pub fn from_app(app: &App) -> Self {
+        let mut platform = Platform::current();
+
+        if let Some(os) = app.flag_value(&["--os"]) {
+            // Target libc is unknown unless it is passed too
+            if os != platform.os {
+                platform.libc = None;
+            }
+
+            platform.os = os;
+        }
+
+        if let Some(cpu) = app.flag_value(&["--cpu"]) {
+            platform.cpu = cpu;
+        }
+
+        if let Some(libc) = app.flag_value(&["--libc"]) {
+            platform.libc = Some(libc);
+        }
+
+        platform
+    }
+
+    /// Check if `package` can be installed on this platform.
+    pub fn supports(&self, package: &VoltPackage) -> bool {
+        matches(&package.os, &self.os)
+            && matches(&package.cpu, &self.cpu)
+            && match &self.libc {
+                Some(libc) => matches(&package.libc, libc),
+                None => true,
+            }
+    }
+}
+
+/// Describe why the `os`, `cpu` and `libc` lists of a package.json exclude `platform`.
+///
+/// Empty when the platform is supported.
+pub fn mismatches(
+    platform: &Platform,
+    os: &[String],
+    cpu: &[String],
+    libc: &[String],
+) -> Vec<String> {
+    let mut mismatches = vec![];
+
+    if !matches(os, &platform.os) {
+        mismatches.push(format!("os {} (supports {})", platform.os, os.join(", ")));
+    }
+
+    if !matches(cpu, &platform.cpu) {
+        mismatches.push(format!(
+            "cpu {} (supports {})",
+            platform.cpu,
+            cpu.join(", ")
+        ));
+    }
+
+    if let Some(current) = &platform.libc {
+        if !matches(libc, current) {
+            mismatches.push(format!("libc {} (supports {})", current, libc.join(", ")));
+        }
+    }
+
+    mismatches
+}
+
+/// Check `value` against a package.json platform list like `["darwin", "linux"]` or `["!win32"]`.
+fn matches(list: &[String], value: &str) -> bool {
+    if list.is_empty() {
+        return true;
+    }
+
+    let blocked = list
+        .iter()
+        .any(|entry| entry.strip_prefix('!') == Some(value));
+    let allowed =
+        list.iter().any(|entry| entry == value) || list.iter().all(|entry| entry.starts_with('!'));
+
+    allowed && !blocked
+}
+
+/// Split `packages` into the ones to install on `platform` and the names of the optional ones
+/// which are skipped because they don't support it, along with everything only they depend on.
+///
+/// Required packages for another platform are kept, npm fails on them at runtime instead.
+pub fn select(
+    packages: &HashMap<String, VoltPackage>,
+    platform: &Platform,
+) -> (HashMap<String, VoltPackage>, Vec<String>) {
+    let optional: HashSet<&str> = packages
+        .values()
+        .flat_map(|package| package.optional_dependencies.iter().map(String::as_str))
+        .collect();
+
+    let mut skipped: HashSet<String> = packages
+        .values()
+        .filter(|package| optional.contains(package.name.as_str()) && !platform.supports(package))
+        .map(|package| package.name.clone())
+        .collect();
+
+    // Dependencies of skipped packages which nothing else needs
+    loop {
+        let needed: HashSet<&str> = packages
+            .values()
+            .filter(|package| !skipped.contains(&package.name))
+            .flat_map(|package| {
+                package
+                    .dependencies
+                    .iter()
+                    .flatten()
+                    .chain(package.optional_dependencies.iter())
+                    .map(String::as_str)
+            })
+            .collect();
+
+        let orphans: Vec<String> = packages
+            .values()
+            .filter(|package| {
+                !skipped.contains(&package.name)
+                    && !needed.contains(package.name.as_str())
+                    && packages.values().any(|parent| {
+                        skipped.contains(&parent.name)
+                            && parent
+                                .dependencies
+                                .iter()
+                                .flatten()
+                                .any(|dependency| dependency == &package.name)
+                    })
+            })
+            .map(|package| package.name.clone())
+            .collect();
+
+        if orphans.is_empty() {
+            break;
+        }
+
+        skipped.extend(orphans);
+    }
+
+    let selected = packages
+        .iter()
+        .filter(|(_, package)| !skipped.contains(&package.name))
+        .map(|(key, package)| (key.clone(), package.clone()))
+        .collect();
+
+    let mut skipped: Vec<String> = skipped.into_iter().collect();
+    skipped.sort();
+
+    (selected, skipped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn package(name: &str, os: &[&str], optional: &[&str]) -> (String, VoltPackage) {
+        (
+            name.to_string(),
+            VoltPackage {
+                name: name.to_string(),
+                version: String::from("1.0.0"),
+                tarball: String::new(),
+                sha1: String::new(),
+                integrity: None,
+                peer_dependencies: vec![],
+                dependencies: None,
+                optional_dependencies: optional.iter().map(|name| name.to_string()).collect(),
+                bin: None,
+                os: os.iter().map(|os| os.to_string()).collect(),
+                cpu: vec![],
+                libc: vec![],
+                source: Default::default(),
+                deprecated: None,
+                engines: Default::default(),
+            },
+        )
+    }
+
+    #[test]
+    fn skips_optional_packages_for_other_platforms() {
+        let packages: HashMap<String, VoltPackage> = vec![
+            package("esbuild", &[], &["esbuild-darwin-64", "esbuild-linux-64"]),
+            package("esbuild-darwin-64", &["darwin"], &[]),
+            package("esbuild-linux-64", &["linux"], &[]),
+        ]
+        .into_iter()
+        .collect();
+
+        let linux = Platform {
+            os: String::from("linux"),
+            cpu: String::from("x64"),
+            libc: Some(String::from("glibc")),
+        };
+
+        let (selected, skipped) = select(&packages, &linux);
+
+        assert!(selected.contains_key("esbuild-linux-64"));
+        assert_eq!(skipped, vec!["esbuild-darwin-64"]);
+    }
+
+    #[test]
+    fn platform_lists() {
+        let list = |entries: &[&str]| -> Vec<String> {
+            entries.iter().map(|entry| entry.to_string()).collect()
+        };
+
+        assert!(matches(&list(&[]), "linux"));
+        assert!(matches(&list(&["darwin", "linux"]), "linux"));
+        assert!(!matches(&list(&["darwin"]), "linux"));
+        assert!(matches(&list(&["!win32"]), "linux"));
+        assert!(!matches(&list(&["!win32"]), "win32"));
+
+        let linux = Platform {
+            os: String::from("linux"),
+            cpu: String::from("arm64"),
+            libc: Some(String::from("musl")),
+        };
+
+        assert!(mismatches(&linux, &list(&["linux"]), &[], &[]).is_empty());
+        assert_eq!(
+            mismatches(
+                &linux,
+                &list(&["darwin"]),
+                &list(&["x64"]),
+                &list(&["glibc"])
+            ),
+            vec![
+                "os linux (supports darwin)",
+                "cpu arm64 (supports x64)",
+                "libc musl (supports glibc)"
+            ]
+        );
+    }
+}
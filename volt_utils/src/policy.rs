@@ -0,0 +1,414 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Enforce the package rules of an organization, declared in `volt-policy.toml`.
+//!
+//! ```toml
+//! blocked = ["event-stream", "colors@>=1.4.1"]
+//! minimum-release-age-days = 3
+//! allowed-registries = ["https://registry.npmjs.org"]
+//! allowed-scripts = ["esbuild", "sharp"]
+//! ```
+//!
+//! The policy is read from the project, then from the root of its workspace, `policy-file` in
+//! `.voltrc` points elsewhere. Every resolved tree is checked against it before anything is
+//! installed, and the violations are reported together as one `volt::policy` diagnostic.
+//! Rules which are left out allow everything. Packages from a `tarball-dir` or git are only
+//! checked against `blocked`, there is no registry to ask for the rest.
+
+use std::collections::BTreeMap;
+use std::fs::read_to_string;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use lazy_static::lazy_static;
+use miette::Diagnostic;
+use serde::Deserialize;
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::app::App;
+use crate::network;
+use crate::package::INSTALL_SCRIPTS;
+use crate::registry;
+use crate::registry_compat;
+use crate::resolver;
+use crate::volt_api::{PackageSource, VoltPackage, VoltResponse};
+use crate::workspace::Workspace;
+
+pub const POLICY_FILE: &str = "volt-policy.toml";
+
+lazy_static! {
+    /// Package → the `time` of its full packument, abbreviated packuments leave it out.
+    static ref PUBLISH_TIMES: DashMap<String, Arc<Value>> = DashMap::new();
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Policy {
+    /// Packages (`event-stream`) and ranges of them (`colors@>=1.4.1`) which can't be installed
+    #[serde(default)]
+    pub blocked: Vec<String>,
+    /// Versions published more recently can't be installed
+    pub minimum_release_age_days: Option<i64>,
+    /// Registries tarballs can be downloaded from
+    pub allowed_registries: Option<Vec<String>>,
+    /// Packages which can have install scripts
+    pub allowed_scripts: Option<Vec<String>>,
+}
+
+/// A package which breaks a rule of the policy.
+#[derive(Debug, Clone, PartialEq, Error, Diagnostic)]
+#[error("{package} {reason} ({rule})")]
+#[diagnostic(code(volt::policy))]
+pub struct Violation {
+    /// `name@version`
+    pub package: String,
+    /// The key of the rule in the policy file
+    pub rule: &'static str,
+    pub reason: String,
+}
+
+/// Every violation of a resolved tree.
+#[derive(Debug, Error, Diagnostic)]
+#[error("{message}")]
+#[diagnostic(
+    code(volt::policy),
+    help("ask the owners of the policy for an exception, or pick other versions")
+)]
+pub struct Violations {
+    message: String,
+    #[related]
+    pub violations: Vec<Violation>,
+}
+
+impl Violations {
+    pub fn new(path: PathBuf, violations: Vec<Violation>) -> Self {
+        let mut message = format!(
+            "{} packages violate the policy of {}",
+            violations.len(),
+            path.display()
+        );
+
+        for violation in violations.iter() {
+            message.push_str(&format!("\n  - {}", violation));
+        }
+
+        Violations {
+            message,
+            violations,
+        }
+    }
+}
+
+/// Whether `manifest`, a version of a packument, runs scripts when it is installed.
+fn has_install_scripts(manifest: &Value) -> bool {
+    manifest["hasInstallScript"].as_bool() == Some(true)
+        || manifest["gypfile"].as_bool() == Some(true)
+        || INSTALL_SCRIPTS
+            .iter()
+            .any(|script| manifest["scripts"][*script].is_string())
+}
+
+impl Policy {
+    pub fn parse(text: &str) -> Result<Self> {
+        Ok(toml::from_str(text)?)
+    }
+
+    /// The policy file of the current directory, if there is one.
+    pub fn find(app: &App) -> Option<PathBuf> {
+        if let Some(path) = app.config.get("policy-file") {
+            return Some(app.current_dir.join(path));
+        }
+
+        let path = app.current_dir.join(POLICY_FILE);
+
+        if path.exists() {
+            return Some(path);
+        }
+
+        let path = Workspace::find(&app.current_dir)?.root.join(POLICY_FILE);
+
+        path.exists().then_some(path)
+    }
+
+    /// The policy of the current directory and where it was read from.
+    pub fn load(app: &App) -> Result<Option<(PathBuf, Self)>> {
+        let path = match Policy::find(app) {
+            Some(path) => path,
+            None => return Ok(None),
+        };
+
+        let policy = read_to_string(&path)
+            .map_err(anyhow::Error::from)
+            .and_then(|text| Policy::parse(&text))
+            .with_context(|| format!("failed to read the policy {}", path.display()))?;
+
+        Ok(Some((path, policy)))
+    }
+
+    /// The entry of `blocked` matching `name@version`.
+    pub fn blocked_by(&self, name: &str, version: &str) -> Option<&String> {
+        self.blocked.iter().find(|entry| {
+            let (blocked, range) = crate::parse_package_spec(entry);
+
+            blocked == name
+                && range.is_none_or(|range| {
+                    crate::max_satisfying([version.to_string()].iter(), &range).is_some()
+                })
+        })
+    }
+
+    /// Check `package` with its `manifest` from the packument and the time it was `published`.
+    pub fn check(
+        &self,
+        package: &VoltPackage,
+        manifest: Option<&Value>,
+        published: Option<&str>,
+        now: DateTime<Utc>,
+    ) -> Vec<Violation> {
+        let mut violations = vec![];
+        let mut violation = |rule: &'static str, reason: String| {
+            violations.push(Violation {
+                package: format!("{}@{}", package.name, package.version),
+                rule,
+                reason,
+            })
+        };
+
+        if let Some(entry) = self.blocked_by(&package.name, &package.version) {
+            violation("blocked", format!("is blocked by {}", entry));
+        }
+
+        if matches!(
+            package.source,
+            PackageSource::TarballDir | PackageSource::Git
+        ) {
+            return violations;
+        }
+
+        if let Some(days) = self.minimum_release_age_days {
+            match published.and_then(|time| DateTime::parse_from_rfc3339(time).ok()) {
+                Some(time) if (now - time.with_timezone(&Utc)).num_days() < days => violation(
+                    "minimum-release-age-days",
+                    format!(
+                        "was published on {}, less than {} days ago",
+                        time.format("%Y-%m-%d"),
+                        days
+                    ),
+                ),
+                Some(_) => {}
+                None => violation(
+                    "minimum-release-age-days",
+                    String::from("has no publish time to check its age against"),
+                ),
+            }
+        }
+
+        if let Some(registries) = self.allowed_registries.as_ref() {
+            let allowed = registries.iter().any(|registry| {
+                package
+                    .tarball
+                    .starts_with(&format!("{}/", registry.trim_end_matches('/')))
+            });
+
+            if !allowed {
+                violation(
+                    "allowed-registries",
+                    format!("is downloaded from {}", package.tarball),
+                );
+            }
+        }
+
+        if let Some(allowed) = self.allowed_scripts.as_ref() {
+            if manifest.is_some_and(has_install_scripts) && !allowed.contains(&package.name) {
+                violation("allowed-scripts", String::from("runs install scripts"));
+            }
+        }
+
+        violations
+    }
+}
+
+/// The `time` field of the full packument of `name`, fetched once per process.
+async fn publish_times(app: &App, name: &str) -> Result<Arc<Value>> {
+    if let Some(times) = PUBLISH_TIMES.get(name) {
+        return Ok(times.clone());
+    }
+
+    let packument = resolver::packument(app, name.to_string()).await?;
+
+    let times = if packument["time"].is_object() {
+        packument["time"].clone()
+    } else {
+        let registry = registry::url_for(app, name);
+        let url = format!("{}/{}", registry, name.replace('/', "%2f"));
+
+        let mut request = reqwest::Client::new()
+            .get(&url)
+            .header("Accept", "application/json");
+
+        if let Some(credentials) = registry::credentials(app, &registry) {
+            let compat = registry_compat::get(app, &registry);
+            request = registry_compat::authorize(request, &credentials, compat);
+        }
+
+        let response = network::send(&url, request)
+            .await?
+            .error_for_status()
+            .with_context(|| format!("failed to fetch {}", url))?;
+        let packument: Value = serde_json::from_slice(&network::read(&url, response).await?)?;
+
+        packument["time"].clone()
+    };
+
+    let times = Arc::new(times);
+    PUBLISH_TIMES.insert(name.to_string(), times.clone());
+
+    Ok(times)
+}
+
+/// Check every package of `response` against the policy of the current directory.
+pub async fn enforce(app: &App, response: &VoltResponse) -> Result<()> {
+    let (path, policy) = match Policy::load(app)? {
+        Some(loaded) => loaded,
+        None => return Ok(()),
+    };
+
+    let packages: BTreeMap<String, &VoltPackage> = response
+        .versions
+        .values()
+        .flat_map(|data| data.packages.values())
+        .map(|package| (format!("{}@{}", package.name, package.version), package))
+        .collect();
+
+    let now = Utc::now();
+    let mut violations = vec![];
+
+    for package in packages.values() {
+        let registry = !matches!(
+            package.source,
+            PackageSource::TarballDir | PackageSource::Git
+        );
+
+        let manifest = match policy.allowed_scripts {
+            Some(_) if registry => Some(
+                resolver::packument(app, package.name.clone())
+                    .await
+                    .with_context(|| format!("failed to check the policy of {}", package.name))?
+                    ["versions"][&package.version]
+                    .clone(),
+            ),
+            _ => None,
+        };
+
+        let times = match policy.minimum_release_age_days {
+            Some(_) if registry => Some(
+                publish_times(app, &package.name)
+                    .await
+                    .with_context(|| format!("failed to check the policy of {}", package.name))?,
+            ),
+            _ => None,
+        };
+
+        let published = times
+            .as_ref()
+            .and_then(|times| times[&package.version].as_str());
+
+        violations.extend(policy.check(package, manifest.as_ref(), published, now));
+    }
+
+    if violations.is_empty() {
+        return Ok(());
+    }
+
+    Err(Violations::new(path, violations).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn package(name: &str, version: &str, tarball: &str) -> VoltPackage {
+        serde_json::from_value(json!({
+            "name": name,
+            "version": version,
+            "tarball": tarball,
+            "sha1": "",
+            "peerDependencies": [],
+            "dependencies": null,
+            "bin": null,
+            "source": "registry",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn reports_every_broken_rule() {
+        let policy = Policy::parse(
+            r#"
+            blocked = ["event-stream", "colors@>=1.4.1"]
+            minimum-release-age-days = 7
+            allowed-registries = ["https://registry.npmjs.org/"]
+            allowed-scripts = ["esbuild"]
+            "#,
+        )
+        .unwrap();
+
+        let now = DateTime::parse_from_rfc3339("2022-01-10T00:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let old = Some("2021-06-01T00:00:00.000Z");
+        let npm = "https://registry.npmjs.org/colors/-/colors-1.4.0.tgz";
+
+        let rules = |package: &VoltPackage, manifest: Value, published| -> Vec<&'static str> {
+            policy
+                .check(package, Some(&manifest), published, now)
+                .into_iter()
+                .map(|violation| violation.rule)
+                .collect()
+        };
+
+        assert!(rules(&package("colors", "1.4.0", npm), json!({}), old).is_empty());
+        assert_eq!(
+            rules(&package("colors", "1.4.44", npm), json!({}), old),
+            vec!["blocked"]
+        );
+        assert_eq!(
+            rules(
+                &package("fresh", "1.0.0", "https://evil.example/fresh.tgz"),
+                json!({ "scripts": { "postinstall": "node x.js" } }),
+                Some("2022-01-08T00:00:00.000Z")
+            ),
+            vec![
+                "minimum-release-age-days",
+                "allowed-registries",
+                "allowed-scripts"
+            ]
+        );
+        assert!(rules(
+            &package("esbuild", "0.14.0", npm),
+            json!({ "hasInstallScript": true }),
+            old
+        )
+        .is_empty());
+        assert!(Policy::parse("blokked = []").is_err());
+    }
+}
@@ -0,0 +1,133 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! State volt keeps for a project, in the `.volt` directory next to its package.json.
+//!
+//! ```text
+//! .volt/
+//!   .gitignore            ignores the whole directory
+//!   install-state.json    the layout of node_modules, see [InstallState](crate::linker::InstallState)
+//!   journal/              the journal of an install in progress, see [journal](crate::journal)
+//!   logs/                 the logs of failed scripts
+//!   timings/              the traces of `--timing` runs
+//!   update-check.json     when the dependencies were last checked for new major versions
+//! ```
+//!
+//! The directory ignores itself, so it never shows up in `git status` and nothing has to be
+//! added to the `.gitignore` of the project. `volt clean --state` removes it, the next install
+//! then lays node_modules out again from scratch.
+
+use std::fs::{create_dir_all, write};
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Context, Result};
+
+use crate::app::App;
+
+pub const STATE_DIR: &str = ".volt";
+pub const INSTALL_STATE_FILE: &str = "install-state.json";
+pub const JOURNAL_DIR: &str = "journal";
+pub const LOGS_DIR: &str = "logs";
+pub const TIMINGS_DIR: &str = "timings";
+pub const UPDATE_CHECK_FILE: &str = "update-check.json";
+
+/// The `.volt` directory of the project, which may not exist yet.
+pub fn dir(app: &App) -> PathBuf {
+    app.current_dir.join(STATE_DIR)
+}
+
+/// `name` inside the `.volt` directory of the project.
+pub fn path(app: &App, name: &str) -> PathBuf {
+    dir(app).join(name)
+}
+
+/// Create the `.volt` directory of the project, ignored by git.
+pub fn create(app: &App) -> Result<PathBuf> {
+    let dir = dir(app);
+    let gitignore = dir.join(".gitignore");
+
+    if !gitignore.exists() {
+        create_dir_all(&dir)
+            .and_then(|_| write(&gitignore, "*\n"))
+            .with_context(|| format!("failed to create {}", dir.display()))?;
+    }
+
+    Ok(dir)
+}
+
+/// Remove the `.volt` directory of the project, returning whether there was one.
+pub fn clean(app: &App) -> Result<bool> {
+    let dir = dir(app);
+
+    // A project in the home directory would have the store there
+    if dir == app.volt_dir {
+        return Err(anyhow!(
+            "{} is the store of volt, not the state of a project",
+            dir.display()
+        ));
+    }
+
+    if !dir.exists() {
+        return Ok(false);
+    }
+
+    crate::fs::remove_dir_all(&dir)?;
+
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    #[test]
+    fn creates_an_ignored_directory_and_cleans_it() {
+        let root = std::env::temp_dir().join(format!("volt-project-state-{}", std::process::id()));
+        let app = App {
+            current_dir: root.clone(),
+            home_dir: root.join("home"),
+            node_modules_dir: root.join("node_modules"),
+            volt_dir: root.join("home/.volt"),
+            cache_dir: root.join("home/.volt/.cache"),
+            lock_file_path: root.join("volt.lock"),
+            config_file: root.join(".voltrc"),
+            args: vec![],
+            flags: vec![],
+            config: Config::default(),
+        };
+
+        assert!(!clean(&app).unwrap());
+
+        create(&app).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(root.join(".volt/.gitignore")).unwrap(),
+            "*\n"
+        );
+        assert_eq!(path(&app, LOGS_DIR), root.join(".volt/logs"));
+
+        assert!(clean(&app).unwrap());
+        assert!(!root.join(".volt").exists());
+
+        let home = App {
+            current_dir: root.join("home"),
+            ..app
+        };
+        assert!(clean(&home).is_err());
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
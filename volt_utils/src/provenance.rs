@@ -0,0 +1,403 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Registry signatures and provenance attestations of published packages.
+//!
+//! Registries sign `<name>@<version>:<integrity>` of every version with the keys listed at
+//! `/-/npm/v1/keys`, and packages published from CI with `--provenance` link an attestation
+//! (`dist.attestations.url`) saying which workflow of which repository built them.
+//!
+//! Registry signatures are verified (ECDSA P-256). Attestations are checked to be about the
+//! tarball in question, the `pkg:npm` subject and its sha512 digest, and their build details
+//! are read; the Sigstore certificate chain of the bundle is not verified.
+
+use anyhow::{anyhow, Context, Result};
+use ring::signature::{UnparsedPublicKey, ECDSA_P256_SHA256_ASN1};
+use serde_json::Value;
+
+use crate::app::App;
+
+/// Predicate types of SLSA provenance, the attestations describing builds.
+pub const PROVENANCE_TYPES: [&str; 2] = [
+    "https://slsa.dev/provenance/v1",
+    "https://slsa.dev/provenance/v0.2",
+];
+
+/// DER prefix of a P-256 `SubjectPublicKeyInfo`, followed by the 65 bytes of the point.
+const P256_SPKI_PREFIX: [u8; 26] = [
+    0x30, 0x59, 0x30, 0x13, 0x06, 0x07, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01, 0x06, 0x08, 0x2a,
+    0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07, 0x03, 0x42, 0x00,
+];
+
+/// Where and how a package was built, from its provenance attestation.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Provenance {
+    pub predicate_type: String,
+    /// The CI platform which ran the build
+    pub builder: Option<String>,
+    pub repository: Option<String>,
+    pub workflow: Option<String>,
+    pub git_ref: Option<String>,
+    pub commit: Option<String>,
+}
+
+/// How a registry signature checked out.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Signature {
+    Verified,
+    /// The version is not signed, or not with a key the registry lists
+    Missing,
+    Invalid,
+}
+
+/// A signing key of a registry.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RegistryKey {
+    pub keyid: String,
+    /// Base64 DER `SubjectPublicKeyInfo`
+    pub key: String,
+}
+
+/// The hex sha512 of a `sha512-<base64>` integrity, among the hashes it lists.
+fn sha512_hex(integrity: &str) -> Option<String> {
+    integrity
+        .split_whitespace()
+        .find_map(|hash| hash.strip_prefix("sha512-"))
+        .and_then(|hash| base64::decode(hash).ok())
+        .map(|bytes| bytes.iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+/// The `pkg:npm` url of a version, `@` of scopes encoded.
+fn purl(name: &str, version: &str) -> String {
+    format!("pkg:npm/{}@{}", name.replace('@', "%40"), version)
+}
+
+fn string(value: &Value) -> Option<String> {
+    value
+        .as_str()
+        .filter(|value| !value.is_empty())
+        .map(String::from)
+}
+
+/// Read the provenance of `name@version` from an attestations response.
+///
+/// Fails when the attestation is about another tarball than the one of `integrity`.
+pub fn parse(
+    name: &str,
+    version: &str,
+    integrity: &str,
+    attestations: &Value,
+) -> Result<Option<Provenance>> {
+    let attestation = attestations["attestations"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .find(|attestation| {
+            attestation["predicateType"]
+                .as_str()
+                .is_some_and(|kind| PROVENANCE_TYPES.contains(&kind))
+        });
+
+    let attestation = match attestation {
+        Some(attestation) => attestation,
+        None => return Ok(None),
+    };
+
+    let payload = attestation["bundle"]["dsseEnvelope"]["payload"]
+        .as_str()
+        .ok_or_else(|| anyhow!("the attestation has no payload"))?;
+    let statement: Value = serde_json::from_slice(&base64::decode(payload)?)
+        .context("the attestation payload is not a statement")?;
+
+    let expected = purl(name, version);
+    let digest = sha512_hex(integrity);
+
+    let about_tarball = statement["subject"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .any(|subject| {
+            subject["name"].as_str() == Some(expected.as_str())
+                && digest.is_some()
+                && subject["digest"]["sha512"].as_str() == digest.as_deref()
+        });
+
+    if !about_tarball {
+        return Err(anyhow!(
+            "the attestation is not about the tarball of {}@{}",
+            name,
+            version
+        ));
+    }
+
+    let predicate = &statement["predicate"];
+    let predicate_type = statement["predicateType"]
+        .as_str()
+        .unwrap_or_default()
+        .to_string();
+
+    let provenance = if predicate_type.ends_with("/v0.2") {
+        let source = &predicate["invocation"]["configSource"];
+        let uri = string(&source["uri"]);
+        let (repository, git_ref) = match uri.as_deref().and_then(|uri| uri.rsplit_once('@')) {
+            Some((repository, git_ref)) => {
+                (Some(repository.to_string()), Some(git_ref.to_string()))
+            }
+            None => (uri, None),
+        };
+
+        Provenance {
+            predicate_type,
+            builder: string(&predicate["builder"]["id"]),
+            repository: repository.map(|uri| uri.trim_start_matches("git+").to_string()),
+            workflow: string(&source["entryPoint"]),
+            git_ref,
+            commit: string(&source["digest"]["sha1"]),
+        }
+    } else {
+        let workflow = &predicate["buildDefinition"]["externalParameters"]["workflow"];
+
+        Provenance {
+            predicate_type,
+            builder: string(&predicate["runDetails"]["builder"]["id"]),
+            repository: string(&workflow["repository"]),
+            workflow: string(&workflow["path"]),
+            git_ref: string(&workflow["ref"]),
+            commit: string(
+                &predicate["buildDefinition"]["resolvedDependencies"][0]["digest"]["gitCommit"],
+            ),
+        }
+    };
+
+    Ok(Some(provenance))
+}
+
+/// Get the provenance of the version with `dist`, if it links an attestation.
+pub async fn fetch(
+    app: &App,
+    name: &str,
+    version: &str,
+    dist: &Value,
+) -> Result<Option<Provenance>> {
+    let url = match dist["attestations"]["url"].as_str() {
+        Some(url) => url,
+        None => return Ok(None),
+    };
+
+    let registry = crate::registry::url_for(app, name);
+    let mut request = reqwest::Client::new().get(url);
+
+    // Credentials of the registry stay with it, wherever the packument points
+    if url.starts_with(&registry) {
+        if let Some(token) = crate::registry::auth_token(app, &registry) {
+            request = request.bearer_auth(token);
+        }
+    }
+
+    let attestations: Value = serde_json::from_str(
+        &request
+            .send()
+            .await?
+            .error_for_status()
+            .with_context(|| format!("failed to fetch the attestations of {}@{}", name, version))?
+            .text()
+            .await?,
+    )?;
+
+    parse(
+        name,
+        version,
+        dist["integrity"].as_str().unwrap_or_default(),
+        &attestations,
+    )
+}
+
+/// Get the signing keys of `registry`, none for registries which don't sign.
+pub async fn keys(registry: &str) -> Result<Vec<RegistryKey>> {
+    let response = reqwest::get(format!("{}/-/npm/v1/keys", registry)).await?;
+
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(vec![]);
+    }
+
+    let keys: Value = serde_json::from_str(&response.error_for_status()?.text().await?)?;
+
+    Ok(keys["keys"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|key| {
+            Some(RegistryKey {
+                keyid: key["keyid"].as_str()?.to_string(),
+                key: key["key"].as_str()?.to_string(),
+            })
+        })
+        .collect())
+}
+
+/// Check the registry signatures of `name@version` in `dist` against `keys`.
+pub fn verify(keys: &[RegistryKey], name: &str, version: &str, dist: &Value) -> Signature {
+    let integrity = dist["integrity"].as_str().unwrap_or_default();
+    let message = format!("{}@{}:{}", name, version, integrity);
+
+    let mut checked = false;
+
+    for signature in dist["signatures"].as_array().into_iter().flatten() {
+        let key = keys
+            .iter()
+            .find(|key| signature["keyid"].as_str() == Some(key.keyid.as_str()));
+
+        let (key, signature) = match (key, signature["sig"].as_str()) {
+            (Some(key), Some(signature)) => (key, signature),
+            _ => continue,
+        };
+
+        checked = true;
+
+        let point = base64::decode(&key.key)
+            .ok()
+            .filter(|spki| spki.len() == 91 && spki.starts_with(&P256_SPKI_PREFIX))
+            .map(|spki| spki[P256_SPKI_PREFIX.len()..].to_vec());
+
+        let verified = match (point, base64::decode(signature)) {
+            (Some(point), Ok(signature)) => UnparsedPublicKey::new(&ECDSA_P256_SHA256_ASN1, point)
+                .verify(message.as_bytes(), &signature)
+                .is_ok(),
+            _ => false,
+        };
+
+        if verified {
+            return Signature::Verified;
+        }
+    }
+
+    if checked {
+        Signature::Invalid
+    } else {
+        Signature::Missing
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ring::rand::SystemRandom;
+    use ring::signature::{EcdsaKeyPair, KeyPair, ECDSA_P256_SHA256_ASN1_SIGNING};
+    use serde_json::json;
+
+    const INTEGRITY: &str = "sha512-AAECAwQFBgcICQoLDA0ODxAREhMUFRYXGBkaGxwdHh8gISIjJCUmJygpKissLS4vMDEyMzQ1Njc4OTo7PD0+Pw==";
+
+    fn attestations(subject: &str) -> Value {
+        let digest: String = (0..64u8).map(|byte| format!("{:02x}", byte)).collect();
+        let statement = json!({
+            "subject": [{ "name": subject, "digest": { "sha512": digest } }],
+            "predicateType": "https://slsa.dev/provenance/v1",
+            "predicate": {
+                "buildDefinition": {
+                    "externalParameters": { "workflow": {
+                        "repository": "https://github.com/acme/widgets",
+                        "path": ".github/workflows/publish.yml",
+                        "ref": "refs/tags/v2.0.0"
+                    } },
+                    "resolvedDependencies": [{ "digest": { "gitCommit": "0f3c9a1" } }]
+                },
+                "runDetails": { "builder": { "id": "https://github.com/actions/runner/github-hosted" } }
+            }
+        });
+
+        json!({ "attestations": [
+            { "predicateType": "https://github.com/npm/attestation/tree/main/specs/publish/v0.1" },
+            {
+                "predicateType": "https://slsa.dev/provenance/v1",
+                "bundle": { "dsseEnvelope": { "payload": base64::encode(statement.to_string()) } }
+            }
+        ] })
+    }
+
+    #[test]
+    fn reads_provenance_of_the_tarball() {
+        let provenance = parse(
+            "@acme/widgets",
+            "2.0.0",
+            INTEGRITY,
+            &attestations("pkg:npm/%40acme/widgets@2.0.0"),
+        )
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(
+            provenance.repository.as_deref(),
+            Some("https://github.com/acme/widgets")
+        );
+        assert_eq!(
+            provenance.workflow.as_deref(),
+            Some(".github/workflows/publish.yml")
+        );
+        assert_eq!(provenance.commit.as_deref(), Some("0f3c9a1"));
+
+        // Attestations of other versions don't vouch for this one
+        assert!(parse(
+            "@acme/widgets",
+            "2.0.1",
+            INTEGRITY,
+            &attestations("pkg:npm/%40acme/widgets@2.0.0"),
+        )
+        .is_err());
+        assert_eq!(
+            parse("left-pad", "1.3.0", INTEGRITY, &json!({})).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn verifies_registry_signatures() {
+        let random = SystemRandom::new();
+        let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, &random).unwrap();
+        let pair =
+            EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, pkcs8.as_ref()).unwrap();
+
+        let spki = [&P256_SPKI_PREFIX[..], pair.public_key().as_ref()].concat();
+        let keys = vec![RegistryKey {
+            keyid: String::from("SHA256:test"),
+            key: base64::encode(spki),
+        }];
+
+        let message = format!("left-pad@1.3.0:{}", INTEGRITY);
+        let signature = pair.sign(&random, message.as_bytes()).unwrap();
+        let dist = json!({
+            "integrity": INTEGRITY,
+            "signatures": [{ "keyid": "SHA256:test", "sig": base64::encode(signature.as_ref()) }]
+        });
+
+        assert_eq!(
+            verify(&keys, "left-pad", "1.3.0", &dist),
+            Signature::Verified
+        );
+        assert_eq!(
+            verify(&keys, "left-pad", "1.3.1", &dist),
+            Signature::Invalid
+        );
+        assert_eq!(
+            verify(
+                &keys,
+                "left-pad",
+                "1.3.0",
+                &json!({ "integrity": INTEGRITY })
+            ),
+            Signature::Missing
+        );
+    }
+}
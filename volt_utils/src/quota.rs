@@ -0,0 +1,320 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Keep the store under a size quota.
+//!
+//! Every install records the packages of the project lock file in `<store>/.usage.json`, with
+//! the time they were last installed. With `store-max-size=<size>` (`10GB`, `512MB` or bytes) in
+//! `.voltrc`, the store is then brought under the quota by evicting the entries installed the
+//! longest time ago. Entries still listed by a project which has a lock file are never evicted,
+//! so the store can stay over the quota when everything in it is in use.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{read_to_string, remove_file, write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, Context, Result};
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use walkdir::WalkDir;
+
+use crate::app::App;
+use crate::dedup;
+use crate::reporter;
+use crate::scrub;
+
+const USAGE_FILE: &str = ".usage.json";
+
+/// When store entries were used and by which projects.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Usage {
+    /// Store entry → seconds since the epoch it was last installed
+    pub last_used: BTreeMap<String, u64>,
+    /// Project directory → store entries its lock file lists
+    pub projects: BTreeMap<PathBuf, BTreeSet<String>>,
+}
+
+impl Usage {
+    pub fn load(volt_dir: &Path) -> Self {
+        read_to_string(volt_dir.join(USAGE_FILE))
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, volt_dir: &Path) -> Result<()> {
+        write(volt_dir.join(USAGE_FILE), serde_json::to_string(self)?)?;
+
+        Ok(())
+    }
+
+    /// Record that `project` installed `names` at `now`.
+    pub fn record(&mut self, project: &Path, names: BTreeSet<String>, now: u64) {
+        for name in names.iter() {
+            self.last_used.insert(name.clone(), now);
+        }
+
+        self.projects.insert(project.to_path_buf(), names);
+    }
+
+    /// Entries listed by projects which still have a lock file, forgetting the other projects.
+    pub fn referenced(&mut self) -> BTreeSet<String> {
+        self.projects
+            .retain(|project, _| project.join("volt.lock").exists());
+
+        self.projects.values().flatten().cloned().collect()
+    }
+}
+
+/// An entry of the store.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub name: String,
+    /// Bytes of its files
+    pub size: u64,
+    /// Seconds since the epoch it was last installed, if it was since usage is recorded
+    pub last_used: Option<u64>,
+}
+
+/// Bytes of the files below `dir`.
+pub fn dir_size(dir: &Path) -> u64 {
+    WalkDir::new(dir)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter_map(|entry| entry.metadata().ok())
+        .filter(|metadata| metadata.is_file())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+/// Bytes of the files of the package in `dir`, without the packages nested in its node_modules
+/// which are counted on their own.
+pub fn package_size(dir: &Path) -> u64 {
+    WalkDir::new(dir)
+        .into_iter()
+        .filter_entry(|entry| entry.depth() != 1 || entry.file_name() != "node_modules")
+        .filter_map(Result::ok)
+        .filter_map(|entry| entry.metadata().ok())
+        .filter(|metadata| metadata.is_file())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+/// The entries of the store, by name.
+pub fn entries(volt_dir: &Path, usage: &Usage) -> Vec<Entry> {
+    scrub::recorded_names(volt_dir)
+        .into_iter()
+        .map(|name| (volt_dir.join(&name), name))
+        .filter(|(dir, _)| dir.exists())
+        .map(|(dir, name)| Entry {
+            size: dir_size(&dir),
+            last_used: usage.last_used.get(&name).copied(),
+            name,
+        })
+        .collect()
+}
+
+/// A size such as `10GB`, `1.5 gb`, `512MB`, `64k` or plain bytes.
+pub fn parse_size(value: &str) -> Option<u64> {
+    let value = value.trim().to_ascii_lowercase();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+
+    let multiplier: u64 = match unit.trim().trim_end_matches('b') {
+        "" => 1,
+        "k" => 1000,
+        "m" => 1000 * 1000,
+        "g" => 1000 * 1000 * 1000,
+        "t" => 1000 * 1000 * 1000 * 1000,
+        _ => return None,
+    };
+
+    Some((number.parse::<f64>().ok()? * multiplier as f64) as u64)
+}
+
+/// A size in bytes, as `1.2 MB`.
+pub fn human_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["kB", "MB", "GB", "TB"];
+
+    if bytes < 1000 {
+        return format!("{} B", bytes);
+    }
+
+    let mut size = bytes as f64 / 1000.0;
+    let mut unit = 0;
+
+    while size >= 1000.0 && unit < UNITS.len() - 1 {
+        size /= 1000.0;
+        unit += 1;
+    }
+
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+/// The entries to evict to bring the store under `max` bytes, least recently used first.
+///
+/// Entries never recorded as used go first, they come from before usage was recorded.
+pub fn select_evictions<'a>(
+    entries: &'a [Entry],
+    referenced: &BTreeSet<String>,
+    max: u64,
+) -> Vec<&'a Entry> {
+    let mut total: u64 = entries.iter().map(|entry| entry.size).sum();
+
+    let mut candidates: Vec<&Entry> = entries
+        .iter()
+        .filter(|entry| !referenced.contains(&entry.name))
+        .collect();
+
+    candidates.sort_by_key(|entry| (entry.last_used.unwrap_or(0), entry.name.clone()));
+
+    let mut evicted = vec![];
+
+    for entry in candidates {
+        if total <= max {
+            break;
+        }
+
+        total -= entry.size;
+        evicted.push(entry);
+    }
+
+    evicted
+}
+
+/// Remove `name` from the store, with its integrity record.
+fn evict(volt_dir: &Path, name: &str) -> Result<()> {
+    crate::fs::remove_dir_all(&volt_dir.join(name))
+        .with_context(|| format!("failed to evict {}", name))?;
+
+    // Entries extracted before records were kept have none
+    let _ = remove_file(scrub::record_path(volt_dir, name));
+
+    Ok(())
+}
+
+/// Record the packages `names` of the project as used, and evict what goes over the quota.
+///
+/// Returns the evicted entries.
+pub fn after_install(app: &App, names: BTreeSet<String>) -> Result<Vec<Entry>> {
+    let project = app.lock_file_path.parent().unwrap_or(&app.current_dir);
+
+    let mut usage = Usage::load(&app.volt_dir);
+    usage.record(project, names, now());
+
+    let mut evicted = vec![];
+
+    if let Some(max) = app.config.get("store-max-size") {
+        let max = parse_size(max).ok_or_else(|| anyhow!("invalid store-max-size: {}", max))?;
+        let referenced = usage.referenced();
+        let entries = entries(&app.volt_dir, &usage);
+
+        for entry in select_evictions(&entries, &referenced, max) {
+            evict(&app.volt_dir, &entry.name)?;
+            usage.last_used.remove(&entry.name);
+
+            if app.has_flag(&["--verbose"]) {
+                reporter::get().info(
+                    "Evicted",
+                    &format!(
+                        "{} ({}) to stay under {}",
+                        entry.name.bright_cyan(),
+                        human_size(entry.size),
+                        human_size(max)
+                    ),
+                );
+            }
+
+            evicted.push(entry.clone());
+        }
+
+        if !evicted.is_empty() {
+            dedup::prune(&app.volt_dir)?;
+        }
+    }
+
+    usage.save(&app.volt_dir)?;
+
+    Ok(evicted)
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(name: &str, size: u64, last_used: Option<u64>) -> Entry {
+        Entry {
+            name: name.to_string(),
+            size,
+            last_used,
+        }
+    }
+
+    #[test]
+    fn evicts_least_recently_used_unreferenced_entries() {
+        let entries = vec![
+            entry("react", 300, Some(10)),
+            entry("lodash", 500, Some(20)),
+            entry("left-pad", 100, None),
+            entry("chalk", 200, Some(5)),
+        ];
+
+        let referenced: BTreeSet<String> = vec![String::from("react")].into_iter().collect();
+
+        let evicted: Vec<&str> = select_evictions(&entries, &referenced, 750)
+            .into_iter()
+            .map(|entry| entry.name.as_str())
+            .collect();
+
+        assert_eq!(evicted, vec!["left-pad", "chalk", "lodash"]);
+        assert!(select_evictions(&entries, &referenced, 1100).is_empty());
+
+        assert_eq!(parse_size("10GB"), Some(10_000_000_000));
+        assert_eq!(parse_size("1.5 gb"), Some(1_500_000_000));
+        assert_eq!(parse_size("512m"), Some(512_000_000));
+        assert_eq!(parse_size("4096"), Some(4096));
+        assert_eq!(parse_size("lots"), None);
+        assert_eq!(human_size(999), "999 B");
+        assert_eq!(human_size(1_540_000), "1.5 MB");
+    }
+
+    #[test]
+    fn measures_packages_without_their_node_modules() {
+        let dir = std::env::temp_dir().join(format!("volt-quota-{}", std::process::id()));
+        let nested = dir.join("node_modules").join("ms");
+        std::fs::create_dir_all(&nested).unwrap();
+        std::fs::create_dir_all(dir.join("lib")).unwrap();
+
+        write(dir.join("index.js"), "module.exports = 1").unwrap();
+        write(dir.join("lib").join("node_modules"), "1").unwrap();
+        write(nested.join("index.js"), "module.exports = 2").unwrap();
+
+        assert_eq!(dir_size(&dir), 37);
+        assert_eq!(package_size(&dir), 19);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
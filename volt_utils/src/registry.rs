@@ -0,0 +1,170 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! The npm registry configured in `.voltrc` and its credentials.
+//!
+//! Tokens use the `.npmrc` keys scoped to the registry url without its protocol
+//! (`//registry.npmjs.org/:_authToken=...`), so tokens written by `npm login` work as they are.
+//! Scoped packages are fetched from the registry of their scope (`@acme:registry=...`) when one
+//! is configured.
+
+use std::env;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use serde_json::Value;
+
+use crate::app::App;
+use crate::github_packages;
+
+pub const DEFAULT_REGISTRY: &str = "https://registry.npmjs.org/";
+
+/// The registry url, without a trailing slash.
+pub fn url(app: &App) -> String {
+    app.config
+        .get("registry")
+        .unwrap_or(DEFAULT_REGISTRY)
+        .trim_end_matches('/')
+        .to_string()
+}
+
+/// Key of a setting scoped to `registry` (`//registry.npmjs.org/:_authToken`).
+pub fn scoped_key(registry: &str, key: &str) -> String {
+    let host = registry
+        .split_once("://")
+        .map_or(registry, |(_, host)| host)
+        .trim_end_matches('/');
+
+    format!("//{}/:{}", host, key)
+}
+
+/// How to authenticate with a registry.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Credentials {
+    /// `_authToken`
+    Token(String),
+    /// `_auth`, base64 of `user:password`
+    Basic(String),
+}
+
+/// The registry configured for the scope of `name`, without a trailing slash.
+pub fn scope_url(app: &App, name: &str) -> Option<String> {
+    let (scope, _) = name
+        .split_once('/')
+        .filter(|(scope, _)| scope.starts_with('@'))?;
+
+    app.config
+        .get(&format!("{}:registry", scope))
+        .map(|registry| registry.trim_end_matches('/').to_string())
+}
+
+/// The registry `name` is published to, ignoring mirrors.
+pub fn url_for(app: &App, name: &str) -> String {
+    scope_url(app, name).unwrap_or_else(|| url(app))
+}
+
+/// The auth token configured for `registry`, `GITHUB_TOKEN` for GitHub Packages without one.
+pub fn auth_token(app: &App, registry: &str) -> Option<String> {
+    app.config
+        .get(&scoped_key(registry, "_authToken"))
+        .map(String::from)
+        .or_else(|| {
+            if github_packages::is_github(registry) {
+                env::var(github_packages::TOKEN_VARIABLE)
+                    .ok()
+                    .filter(|token| !token.is_empty())
+            } else {
+                None
+            }
+        })
+}
+
+/// The credentials configured for `registry`, a token before basic auth.
+pub fn credentials(app: &App, registry: &str) -> Option<Credentials> {
+    auth_token(app, registry)
+        .map(Credentials::Token)
+        .or_else(|| {
+            app.config
+                .get(&scoped_key(registry, "_auth"))
+                .map(|auth| Credentials::Basic(auth.to_string()))
+        })
+}
+
+/// Ask `registry` which user `token` belongs to.
+pub async fn whoami(registry: &str, token: &str) -> Result<String> {
+    let response = reqwest::Client::new()
+        .get(format!("{}/-/whoami", registry.trim_end_matches('/')))
+        .bearer_auth(token)
+        .send()
+        .await?;
+
+    if response.status() == reqwest::StatusCode::UNAUTHORIZED {
+        return Err(anyhow!("the auth token for {} is not valid", registry));
+    }
+
+    let body: Value = serde_json::from_str(
+        &response
+            .error_for_status()
+            .with_context(|| format!("failed to ask {} for the current user", registry))?
+            .text()
+            .await?,
+    )?;
+
+    body["username"]
+        .as_str()
+        .map(String::from)
+        .ok_or_else(|| anyhow!("{} did not return a username", registry))
+}
+
+/// Names of packages starting with `prefix`, from the search endpoint of `registry`.
+pub async fn suggest(registry: &str, prefix: &str, timeout: Duration) -> Result<Vec<String>> {
+    let response = reqwest::Client::builder()
+        .timeout(timeout)
+        .build()?
+        .get(format!("{}/-/v1/search", registry.trim_end_matches('/')))
+        .query(&[("text", prefix), ("size", "20")])
+        .send()
+        .await?
+        .error_for_status()?;
+
+    let body: Value = serde_json::from_str(&response.text().await?)?;
+
+    Ok(body["objects"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|object| object["package"]["name"].as_str())
+        .filter(|name| name.starts_with(prefix))
+        .map(String::from)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scoped_keys() {
+        assert_eq!(
+            scoped_key("https://registry.npmjs.org/", "_authToken"),
+            "//registry.npmjs.org/:_authToken"
+        );
+        assert_eq!(
+            scoped_key("https://npm.acme.dev/api/npm", "_authToken"),
+            "//npm.acme.dev/api/npm/:_authToken"
+        );
+    }
+}
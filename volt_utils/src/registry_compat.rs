@@ -0,0 +1,316 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Tolerate private registries (Verdaccio, Nexus, Artifactory) which differ from npm.
+//!
+//! What a registry needs is found out the first time a packument is fetched from it and kept
+//! in `<cache dir>/registry-compat.json`, so later installs go straight to what works:
+//!
+//! - registries which refuse the abbreviated media type, or answer it without versions, are
+//!   asked for full packuments
+//! - registries which challenge with `Basic` get the credentials as basic auth instead of a
+//!   bearer token, `_auth` settings always are
+//!
+//! Packuments are then normalized: relative tarball urls are made absolute and a missing
+//! `shasum` is taken from a `sha1-` integrity when there is one.
+
+use std::collections::BTreeMap;
+use std::fs::{create_dir_all, read_to_string, write};
+use std::path::Path;
+use std::sync::Mutex;
+
+use anyhow::{Context, Result};
+use lazy_static::lazy_static;
+use reqwest::{RequestBuilder, StatusCode, Url};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::app::App;
+use crate::github_packages;
+use crate::network;
+use crate::registry::{self, Credentials};
+use crate::reporter;
+
+const COMPAT_FILE: &str = "registry-compat.json";
+
+/// What a registry was found to need.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Compat {
+    /// Only serves full packuments
+    pub full_metadata: bool,
+    /// Challenges with `Basic` instead of `Bearer`
+    pub basic_auth: bool,
+}
+
+lazy_static! {
+    /// Registry → what it needs, read from the cache directory once per process.
+    static ref KNOWN: Mutex<Option<BTreeMap<String, Compat>>> = Mutex::new(None);
+}
+
+/// What `registry` was found to need, nothing for registries seen for the first time.
+pub fn get(app: &App, registry: &str) -> Compat {
+    let mut known = KNOWN.lock().unwrap();
+
+    known
+        .get_or_insert_with(|| read_known(&app.cache_dir.join(COMPAT_FILE)))
+        .get(registry)
+        .copied()
+        .unwrap_or_default()
+}
+
+fn remember(app: &App, registry: &str, compat: Compat) {
+    let path = app.cache_dir.join(COMPAT_FILE);
+    let mut known = KNOWN.lock().unwrap();
+    let known = known.get_or_insert_with(|| read_known(&path));
+
+    known.insert(registry.to_string(), compat);
+
+    // Finding out again next time is fine if it can't be kept
+    let _ = write_known(&path, known);
+}
+
+/// Add `credentials` to `request`, the way `compat` says the registry wants them.
+pub fn authorize(
+    request: RequestBuilder,
+    credentials: &Credentials,
+    compat: Compat,
+) -> RequestBuilder {
+    match credentials {
+        Credentials::Token(token) if compat.basic_auth => {
+            request.header("Authorization", format!("Basic {}", token))
+        }
+        Credentials::Token(token) => request.bearer_auth(token),
+        Credentials::Basic(auth) => request.header("Authorization", format!("Basic {}", auth)),
+    }
+}
+
+fn challenges_basic(challenge: Option<&str>) -> bool {
+    challenge.is_some_and(|challenge| {
+        challenge
+            .trim_start()
+            .to_ascii_lowercase()
+            .starts_with("basic")
+    })
+}
+
+/// Get the packument at `url` from `registry`, finding out what the registry needs on the way.
+pub async fn fetch_packument(app: &App, registry: &str, url: &str) -> Result<Value> {
+    let known = get(app, registry);
+    let credentials = registry::credentials(app, registry);
+    let client = reqwest::Client::new();
+    let mut compat = known;
+
+    // Every retry turns on something which was off, so this ends
+    loop {
+        let accept = if compat.full_metadata {
+            "application/json"
+        } else {
+            github_packages::accept(registry)
+        };
+
+        let mut request = client.get(url).header("Accept", accept);
+
+        if let Some(credentials) = credentials.as_ref() {
+            request = authorize(request, credentials, compat);
+        }
+
+        let response = network::send(url, request).await?;
+
+        let challenge = response
+            .headers()
+            .get("WWW-Authenticate")
+            .and_then(|value| value.to_str().ok());
+
+        match response.status() {
+            StatusCode::UNAUTHORIZED
+                if credentials.is_some() && !compat.basic_auth && challenges_basic(challenge) =>
+            {
+                compat.basic_auth = true;
+                network::record_retry(url);
+                continue;
+            }
+            StatusCode::BAD_REQUEST
+            | StatusCode::NOT_ACCEPTABLE
+            | StatusCode::UNSUPPORTED_MEDIA_TYPE
+                if !compat.full_metadata =>
+            {
+                compat.full_metadata = true;
+                network::record_retry(url);
+                continue;
+            }
+            _ => {}
+        }
+
+        let body = response
+            .error_for_status()
+            .with_context(|| format!("failed to fetch {}", url))?
+            .text()
+            .await?;
+
+        network::record_bytes(url, body.len());
+
+        let mut packument: Value = match serde_json::from_str(&body) {
+            Ok(packument) => packument,
+            Err(_) if !compat.full_metadata => {
+                compat.full_metadata = true;
+                network::record_retry(url);
+                continue;
+            }
+            Err(error) => return Err(error.into()),
+        };
+
+        if !compat.full_metadata && !packument["versions"].is_object() {
+            compat.full_metadata = true;
+            network::record_retry(url);
+            continue;
+        }
+
+        if compat != known {
+            if app.has_flag(&["--verbose"]) {
+                reporter::get().info(
+                    "compat",
+                    &format!("{} needs {}", registry, describe(compat)),
+                );
+            }
+
+            remember(app, registry, compat);
+        }
+
+        normalize(registry, &mut packument);
+
+        return Ok(packument);
+    }
+}
+
+fn describe(compat: Compat) -> String {
+    let mut needs = vec![];
+
+    if compat.full_metadata {
+        needs.push("full packuments");
+    }
+
+    if compat.basic_auth {
+        needs.push("basic auth");
+    }
+
+    needs.join(" and ")
+}
+
+/// Make the tarball urls of `packument` absolute and fill in missing checksums.
+pub fn normalize(registry: &str, packument: &mut Value) {
+    let base = Url::parse(&format!("{}/", registry.trim_end_matches('/'))).ok();
+
+    let versions = match packument["versions"].as_object_mut() {
+        Some(versions) => versions,
+        None => return,
+    };
+
+    for manifest in versions.values_mut() {
+        let dist = &mut manifest["dist"];
+
+        if let (Some(tarball), Some(base)) = (dist["tarball"].as_str(), base.as_ref()) {
+            if Url::parse(tarball).is_err() {
+                if let Ok(absolute) = base.join(tarball) {
+                    dist["tarball"] = Value::from(absolute.to_string());
+                }
+            }
+        }
+
+        if dist["shasum"].as_str().is_none_or(str::is_empty) {
+            if let Some(shasum) = dist["integrity"].as_str().and_then(sha1_from_integrity) {
+                dist["shasum"] = Value::from(shasum);
+            }
+        }
+    }
+}
+
+/// The hex sha1 of a `sha1-<base64>` integrity, among the hashes it lists.
+pub fn sha1_from_integrity(integrity: &str) -> Option<String> {
+    integrity
+        .split_whitespace()
+        .find_map(|hash| hash.strip_prefix("sha1-"))
+        .and_then(|hash| base64::decode(hash).ok())
+        .filter(|bytes| bytes.len() == 20)
+        .map(|bytes| bytes.iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+fn read_known(path: &Path) -> BTreeMap<String, Compat> {
+    read_to_string(path)
+        .ok()
+        .and_then(|source| serde_json::from_str(&source).ok())
+        .unwrap_or_default()
+}
+
+fn write_known(path: &Path, known: &BTreeMap<String, Compat>) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        create_dir_all(parent)?;
+    }
+
+    write(path, serde_json::to_string_pretty(known)?)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_private_registry_packuments() {
+        let mut packument = serde_json::json!({
+            "versions": {
+                "1.0.0": { "dist": {
+                    "tarball": "/repository/npm/left-pad/-/left-pad-1.0.0.tgz",
+                    "integrity": "sha512-abc sha1-MH1QpD9alkbahWKy4kM81IDsKPM="
+                } },
+                "1.1.0": { "dist": {
+                    "tarball": "left-pad/-/left-pad-1.1.0.tgz",
+                    "shasum": "f0c3b9ae1a5d2c1d2c8e4f0e8a7a1b3c5d7e9f01"
+                } },
+                "1.2.0": { "dist": {
+                    "tarball": "https://cdn.acme.dev/left-pad-1.2.0.tgz"
+                } }
+            }
+        });
+
+        normalize("https://nexus.acme.dev/repository/npm", &mut packument);
+
+        let dist = |version: &str| packument["versions"][version]["dist"].clone();
+
+        assert_eq!(
+            dist("1.0.0")["tarball"],
+            "https://nexus.acme.dev/repository/npm/left-pad/-/left-pad-1.0.0.tgz"
+        );
+        assert_eq!(
+            dist("1.0.0")["shasum"],
+            "307d50a43f5a9646da8562b2e2433cd480ec28f3"
+        );
+        assert_eq!(
+            dist("1.1.0")["tarball"],
+            "https://nexus.acme.dev/repository/npm/left-pad/-/left-pad-1.1.0.tgz"
+        );
+        assert_eq!(
+            dist("1.2.0")["tarball"],
+            "https://cdn.acme.dev/left-pad-1.2.0.tgz"
+        );
+        assert!(dist("1.2.0")["shasum"].is_null());
+
+        assert!(challenges_basic(Some("Basic realm=\"Artifactory Realm\"")));
+        assert!(!challenges_basic(Some("Bearer realm=\"npm\"")));
+        assert!(!challenges_basic(None));
+    }
+}
@@ -0,0 +1,270 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Pick the fastest of several registry mirrors.
+//!
+//! With `registry-mirrors=<url>,<url>` in `.voltrc`, the `/-/ping` endpoint of every mirror is
+//! timed the first time the registry is needed, and the fastest one is used instead of
+//! `registry`. The choice is kept in `<cache dir>/registry-mirror.json` and the mirrors are
+//! probed again once it is older than `mirror-probe-interval` seconds, so machines which move
+//! between regions end up on their closest mirror. `--verbose` prints the decision.
+
+use std::collections::BTreeMap;
+use std::fs::{create_dir_all, read_to_string, write};
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use futures_util::future::join_all;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::app::App;
+use crate::reporter;
+
+/// Seconds a choice is kept when `mirror-probe-interval` is not set.
+pub const DEFAULT_PROBE_INTERVAL: u64 = 60 * 60;
+
+/// Mirrors slower than this to answer a ping are not considered.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(3);
+
+const SELECTION_FILE: &str = "registry-mirror.json";
+
+/// The outcome of a probe, as kept in the cache directory.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Selection {
+    /// Mirrors which were probed, in the order they are configured
+    pub mirrors: Vec<String>,
+    pub selected: String,
+    /// Seconds since the epoch
+    pub probed_at: u64,
+    /// Mirror → milliseconds to answer, for the mirrors which answered
+    pub latencies: BTreeMap<String, u64>,
+}
+
+impl Selection {
+    /// Check if the selection can still be used for `mirrors` at `now`.
+    pub fn is_fresh(&self, mirrors: &[String], now: u64, interval: u64) -> bool {
+        self.mirrors == mirrors && now.saturating_sub(self.probed_at) < interval
+    }
+}
+
+lazy_static! {
+    /// The selection of this process, so that concurrent resolutions probe once.
+    static ref SELECTED: Mutex<Option<String>> = Mutex::new(None);
+}
+
+/// The configured mirrors, without trailing slashes.
+pub fn mirrors(app: &App) -> Vec<String> {
+    app.config
+        .get_list("registry-mirrors")
+        .unwrap_or_default()
+        .iter()
+        .map(|mirror| mirror.trim_end_matches('/').to_string())
+        .collect()
+}
+
+/// The registry url to fetch metadata from: the fastest mirror if mirrors are configured.
+pub async fn url(app: &App) -> String {
+    let mirrors = mirrors(app);
+
+    if mirrors.is_empty() {
+        return crate::registry::url(app);
+    }
+
+    let mut selected = SELECTED.lock().await;
+
+    if let Some(url) = selected.as_ref() {
+        return url.clone();
+    }
+
+    let url = select(app, &mirrors)
+        .await
+        .unwrap_or_else(|| crate::registry::url(app));
+
+    *selected = Some(url.clone());
+
+    url
+}
+
+/// Reuse the last selection if it is fresh, probe the mirrors again otherwise.
+async fn select(app: &App, mirrors: &[String]) -> Option<String> {
+    let path = app.cache_dir.join(SELECTION_FILE);
+    let verbose = app.has_flag(&["--verbose"]);
+    let now = now();
+
+    let interval = app
+        .config
+        .get("mirror-probe-interval")
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(DEFAULT_PROBE_INTERVAL);
+
+    if let Some(selection) = read_selection(&path) {
+        if selection.is_fresh(mirrors, now, interval) {
+            if verbose {
+                reporter::get().info(
+                    "mirror",
+                    &format!(
+                        "using {}, selected {}s ago",
+                        selection.selected,
+                        now - selection.probed_at
+                    ),
+                );
+            }
+
+            return Some(selection.selected);
+        }
+    }
+
+    let latencies: BTreeMap<String, u64> = join_all(mirrors.iter().map(|mirror| probe(mirror)))
+        .await
+        .into_iter()
+        .zip(mirrors)
+        .filter_map(|(latency, mirror)| Some((mirror.clone(), latency?)))
+        .collect();
+
+    let selected = match fastest(mirrors, &latencies) {
+        Some(selected) => selected,
+        None => {
+            if verbose {
+                reporter::get().info(
+                    "mirror",
+                    &format!(
+                        "none of the {} mirrors answered, using the registry",
+                        mirrors.len()
+                    ),
+                );
+            }
+
+            return None;
+        }
+    };
+
+    if verbose {
+        let timings: Vec<String> = mirrors
+            .iter()
+            .map(|mirror| match latencies.get(mirror) {
+                Some(latency) => format!("{} {}ms", mirror, latency),
+                None => format!("{} unreachable", mirror),
+            })
+            .collect();
+
+        reporter::get().info(
+            "mirror",
+            &format!("using {} ({})", selected, timings.join(", ")),
+        );
+    }
+
+    let selection = Selection {
+        mirrors: mirrors.to_vec(),
+        selected: selected.clone(),
+        probed_at: now,
+        latencies,
+    };
+
+    // Probing again next time is fine if the choice can't be kept
+    let _ = write_selection(&path, &selection);
+
+    Some(selected)
+}
+
+/// Milliseconds `mirror` takes to answer a ping, if it answers in time.
+async fn probe(mirror: &str) -> Option<u64> {
+    let client = reqwest::Client::builder()
+        .timeout(PROBE_TIMEOUT)
+        .build()
+        .ok()?;
+
+    let start = Instant::now();
+
+    client
+        .get(format!("{}/-/ping", mirror))
+        .send()
+        .await
+        .ok()?
+        .error_for_status()
+        .ok()?;
+
+    Some(start.elapsed().as_millis() as u64)
+}
+
+/// The mirror which answered the fastest, the first configured one among equals.
+pub fn fastest(mirrors: &[String], latencies: &BTreeMap<String, u64>) -> Option<String> {
+    mirrors
+        .iter()
+        .filter_map(|mirror| Some((latencies.get(mirror)?, mirror)))
+        .min_by_key(|(latency, _)| **latency)
+        .map(|(_, mirror)| mirror.clone())
+}
+
+fn read_selection(path: &Path) -> Option<Selection> {
+    serde_json::from_str(&read_to_string(path).ok()?).ok()
+}
+
+fn write_selection(path: &Path, selection: &Selection) -> anyhow::Result<()> {
+    if let Some(parent) = path.parent() {
+        create_dir_all(parent)?;
+    }
+
+    write(path, serde_json::to_string(selection)?)?;
+
+    Ok(())
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prefers_the_fastest_mirror_while_fresh() {
+        let mirrors = vec![
+            String::from("https://eu.mirror.dev"),
+            String::from("https://us.mirror.dev"),
+            String::from("https://asia.mirror.dev"),
+        ];
+
+        let latencies: BTreeMap<String, u64> = vec![
+            (String::from("https://eu.mirror.dev"), 120),
+            (String::from("https://us.mirror.dev"), 40),
+        ]
+        .into_iter()
+        .collect();
+
+        assert_eq!(
+            fastest(&mirrors, &latencies).as_deref(),
+            Some("https://us.mirror.dev")
+        );
+        assert_eq!(fastest(&mirrors, &BTreeMap::new()), None);
+
+        let selection = Selection {
+            mirrors: mirrors.clone(),
+            selected: String::from("https://us.mirror.dev"),
+            probed_at: 1000,
+            latencies,
+        };
+
+        assert!(selection.is_fresh(&mirrors, 1000 + 59, 60));
+        assert!(!selection.is_fresh(&mirrors, 1000 + 60, 60));
+        assert!(!selection.is_fresh(&mirrors[..2], 1000, 60));
+    }
+}
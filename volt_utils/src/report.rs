@@ -0,0 +1,102 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Output of report commands (`outdated`, `audit`), for the terminal or as markdown.
+//!
+//! `--format markdown` prints GitHub flavored markdown tables, to paste into pull requests or
+//! post from CI bots.
+
+use anyhow::{anyhow, Result};
+
+use crate::app::App;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Table,
+    Markdown,
+}
+
+impl Format {
+    /// The format selected with `--format`, a table for the terminal by default.
+    pub fn from_app(app: &App) -> Result<Self> {
+        match app.flag_value(&["--format"]).as_deref() {
+            None | Some("table") => Ok(Format::Table),
+            Some("markdown") | Some("md") => Ok(Format::Markdown),
+            Some(other) => Err(anyhow!(
+                "unknown format {}, expected table or markdown",
+                other
+            )),
+        }
+    }
+}
+
+/// The page of `name` on npmjs.com.
+pub fn package_url(name: &str) -> String {
+    format!("https://www.npmjs.com/package/{}", name)
+}
+
+/// Escape `text` for a markdown table cell.
+pub fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('|', "\\|")
+        .replace('\r', "")
+        .replace('\n', "<br>")
+}
+
+/// A markdown link, with `text` escaped for a table cell.
+pub fn link(text: &str, url: &str) -> String {
+    format!(
+        "[{}]({})",
+        escape(text).replace('[', "\\[").replace(']', "\\]"),
+        url.replace(' ', "%20").replace(')', "%29")
+    )
+}
+
+/// A markdown table, cells are expected to be escaped already.
+pub fn markdown_table(headers: &[&str], rows: &[Vec<String>]) -> String {
+    let mut table = format!("| {} |\n", headers.join(" | "));
+
+    table.push_str(&format!(
+        "|{}\n",
+        headers.iter().map(|_| " --- |").collect::<String>()
+    ));
+
+    for row in rows {
+        table.push_str(&format!("| {} |\n", row.join(" | ")));
+    }
+
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_markdown_tables() {
+        let rows = vec![vec![
+            link("@types/node", &package_url("@types/node")),
+            escape("^16 || ^18"),
+        ]];
+
+        assert_eq!(
+            markdown_table(&["Package", "Range"], &rows),
+            "| Package | Range |\n\
+             | --- | --- |\n\
+             | [@types/node](https://www.npmjs.com/package/@types/node) | ^16 \\|\\| ^18 |\n"
+        );
+    }
+}
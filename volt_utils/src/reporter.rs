@@ -0,0 +1,542 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! How commands show what they are doing.
+//!
+//! Progress, `info` lines, warnings, the results of commands and the end of a command go through
+//! a [Reporter] rather than straight to the terminal. Volt comes with three:
+//!
+//! - `human`, colored lines and progress bars for the terminal, the default
+//! - `json`, one JSON event per line for other programs, the documents of commands among them,
+//!   used with `--json`
+//! - `quiet`, nothing but errors and the documents asked for, a completion script
+//!
+//! `--reporter=<name>` or `reporter=<name>` in `.voltrc` picks one. Programs using volt as a
+//! library, and the dashboard of `volt ui`, render the activity their own way with [set].
+
+use std::collections::HashMap;
+use std::io::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use colored::Colorize;
+use console::strip_ansi_codes;
+use indicatif::{ProgressBar, ProgressStyle};
+use lazy_static::lazy_static;
+use serde_json::{json, Value};
+
+use crate::app::App;
+use crate::warnings::Warnings;
+use crate::PROGRESS_CHARS;
+
+/// How far a task of a command is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProgressEvent<'a> {
+    /// `total` steps of `title` are to be done, more can be added as it goes
+    Started { title: &'a str, total: u64 },
+    /// `done` of the `total` steps of `title` are done
+    Advanced {
+        title: &'a str,
+        done: u64,
+        total: u64,
+    },
+    /// What `title` is working on, the files it reads
+    Message { title: &'a str, message: &'a str },
+    /// `title` is done
+    Finished { title: &'a str },
+}
+
+/// Where the activity of commands is shown.
+pub trait Reporter: Send + Sync {
+    /// What the reporter is called, `human` or `json`.
+    fn name(&self) -> &'static str;
+
+    /// A task of the command started, advanced or finished.
+    fn progress(&self, event: ProgressEvent);
+
+    /// What volt is doing, `info <title> <message>` in the terminal.
+    fn info(&self, title: &str, message: &str);
+
+    /// The result of a command for people to read, a line of `volt why` or `volt list`.
+    fn output(&self, text: &str);
+
+    /// A document other programs read, the JSON of `--json` or a completion script.
+    fn document(&self, text: &str);
+
+    /// Something which went wrong without failing the command.
+    fn warn(&self, message: &str);
+
+    /// The warnings gathered by an install, once it is done.
+    fn warnings(&self, warnings: &Warnings);
+
+    /// The end of a command which succeeded.
+    fn finished(&self, elapsed: Duration);
+}
+
+/// Colored lines and progress bars for the terminal.
+pub struct HumanReporter {
+    /// Progress bars are drawn, unless `--no-progress` is set
+    progress: bool,
+    /// The bars of the running tasks, by title
+    bars: Mutex<HashMap<String, ProgressBar>>,
+}
+
+impl HumanReporter {
+    pub fn new(progress: bool) -> Self {
+        HumanReporter {
+            progress,
+            bars: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl Reporter for HumanReporter {
+    fn name(&self) -> &'static str {
+        "human"
+    }
+
+    fn progress(&self, event: ProgressEvent) {
+        if !self.progress {
+            return;
+        }
+
+        let mut bars = self.bars.lock().unwrap();
+
+        match event {
+            ProgressEvent::Started { title, total } => {
+                let progress_bar = ProgressBar::new(total);
+
+                progress_bar.set_style(
+                    ProgressStyle::default_bar()
+                        .progress_chars(PROGRESS_CHARS)
+                        .template(&format!(
+                            "{} [{{bar:40.magenta/blue}}] {{msg:.blue}} {{pos}} / {{len}}",
+                            title.bright_blue()
+                        )),
+                );
+
+                bars.insert(title.to_string(), progress_bar);
+            }
+            ProgressEvent::Advanced { title, done, total } => {
+                if let Some(progress_bar) = bars.get(title) {
+                    progress_bar.set_length(total);
+                    progress_bar.set_position(done);
+                }
+            }
+            ProgressEvent::Message { title, message } => {
+                if let Some(progress_bar) = bars.get(title) {
+                    progress_bar.set_message(message.to_string());
+                }
+            }
+            ProgressEvent::Finished { title } => {
+                if let Some(progress_bar) = bars.remove(title) {
+                    progress_bar.finish_and_clear();
+                }
+            }
+        }
+    }
+
+    fn info(&self, title: &str, message: &str) {
+        if message.is_empty() {
+            println!("info {}", title.yellow());
+        } else {
+            println!("info {} {}", title.yellow(), message);
+        }
+    }
+
+    fn output(&self, text: &str) {
+        println!("{}", text);
+    }
+
+    fn document(&self, text: &str) {
+        println!("{}", text);
+    }
+
+    fn warn(&self, message: &str) {
+        println!("{} {}", "warn".yellow(), message);
+    }
+
+    fn warnings(&self, warnings: &Warnings) {
+        warnings.print(false);
+    }
+
+    fn finished(&self, elapsed: Duration) {
+        println!("Finished in {:.2}s", elapsed.as_secs_f32());
+    }
+}
+
+/// One JSON event per line on stdout (NDJSON) for other programs to follow the activity with,
+/// `{"type": "progress", ...}`, `info`, `output`, `document`, `warning`, `warnings` and `finished`.
+pub struct JsonReporter {
+    out: Mutex<Box<dyn Write + Send>>,
+}
+
+impl JsonReporter {
+    fn with_writer(out: Box<dyn Write + Send>) -> Self {
+        JsonReporter {
+            out: Mutex::new(out),
+        }
+    }
+
+    fn emit(&self, event: Value) {
+        let mut out = self.out.lock().unwrap();
+        let _ = writeln!(out, "{}", event);
+        let _ = out.flush();
+    }
+}
+
+impl Default for JsonReporter {
+    fn default() -> Self {
+        JsonReporter::with_writer(Box::new(std::io::stdout()))
+    }
+}
+
+impl Reporter for JsonReporter {
+    fn name(&self) -> &'static str {
+        "json"
+    }
+
+    fn progress(&self, event: ProgressEvent) {
+        self.emit(match event {
+            ProgressEvent::Started { title, total } => {
+                json!({ "type": "progress", "event": "started", "title": title, "total": total })
+            }
+            ProgressEvent::Advanced { title, done, total } => json!({
+                "type": "progress",
+                "event": "advanced",
+                "title": title,
+                "done": done,
+                "total": total
+            }),
+            ProgressEvent::Message { title, message } => json!({
+                "type": "progress",
+                "event": "message",
+                "title": title,
+                "message": strip_ansi_codes(message)
+            }),
+            ProgressEvent::Finished { title } => {
+                json!({ "type": "progress", "event": "finished", "title": title })
+            }
+        });
+    }
+
+    fn info(&self, title: &str, message: &str) {
+        self.emit(json!({
+            "type": "info",
+            "title": strip_ansi_codes(title),
+            "message": strip_ansi_codes(message)
+        }));
+    }
+
+    fn output(&self, text: &str) {
+        self.emit(json!({ "type": "output", "text": strip_ansi_codes(text) }));
+    }
+
+    fn document(&self, text: &str) {
+        // The JSON of `--json` is embedded, anything else (a completion script) as text
+        self.emit(match serde_json::from_str::<Value>(text) {
+            Ok(document) => json!({ "type": "document", "document": document }),
+            Err(_) => json!({ "type": "document", "text": text }),
+        });
+    }
+
+    fn warn(&self, message: &str) {
+        self.emit(json!({ "type": "warning", "message": strip_ansi_codes(message) }));
+    }
+
+    fn warnings(&self, warnings: &Warnings) {
+        self.emit(json!({ "type": "warnings", "warnings": warnings.to_json()["warnings"] }));
+    }
+
+    fn finished(&self, elapsed: Duration) {
+        self.emit(json!({ "type": "finished", "elapsed": elapsed.as_secs_f64() }));
+    }
+}
+
+/// Nothing at all but the documents asked for, errors are still printed when the command fails.
+pub struct QuietReporter;
+
+impl Reporter for QuietReporter {
+    fn name(&self) -> &'static str {
+        "quiet"
+    }
+
+    fn progress(&self, _event: ProgressEvent) {}
+
+    fn info(&self, _title: &str, _message: &str) {}
+
+    fn output(&self, _text: &str) {}
+
+    fn document(&self, text: &str) {
+        println!("{}", text);
+    }
+
+    fn warn(&self, _message: &str) {}
+
+    fn warnings(&self, _warnings: &Warnings) {}
+
+    fn finished(&self, _elapsed: Duration) {}
+}
+
+/// A task reporting its progress, `total` steps which can grow as it goes.
+pub struct Progress {
+    title: String,
+    done: AtomicU64,
+    total: AtomicU64,
+    reporter: Arc<dyn Reporter>,
+}
+
+impl Progress {
+    /// Start `title`, shown by the current reporter.
+    pub fn start(title: &str, total: u64) -> Self {
+        Progress::with_reporter(get(), title, total)
+    }
+
+    /// A task which is not shown.
+    pub fn hidden() -> Self {
+        Progress::with_reporter(Arc::new(QuietReporter), "", 0)
+    }
+
+    fn with_reporter(reporter: Arc<dyn Reporter>, title: &str, total: u64) -> Self {
+        reporter.progress(ProgressEvent::Started { title, total });
+
+        Progress {
+            title: title.to_string(),
+            done: AtomicU64::new(0),
+            total: AtomicU64::new(total),
+            reporter,
+        }
+    }
+
+    /// `steps` more steps are done.
+    pub fn inc(&self, steps: u64) {
+        let done = self.done.fetch_add(steps, Ordering::SeqCst) + steps;
+        self.advanced(done, self.total.load(Ordering::SeqCst));
+    }
+
+    /// `steps` more steps are to be done.
+    pub fn inc_length(&self, steps: u64) {
+        let total = self.total.fetch_add(steps, Ordering::SeqCst) + steps;
+        self.advanced(self.done.load(Ordering::SeqCst), total);
+    }
+
+    fn advanced(&self, done: u64, total: u64) {
+        self.reporter.progress(ProgressEvent::Advanced {
+            title: &self.title,
+            done,
+            total,
+        });
+    }
+
+    /// Show what the task is working on.
+    pub fn message(&self, message: &str) {
+        self.reporter.progress(ProgressEvent::Message {
+            title: &self.title,
+            message,
+        });
+    }
+
+    /// The task is done.
+    pub fn finish(&self) {
+        self.reporter
+            .progress(ProgressEvent::Finished { title: &self.title });
+    }
+}
+
+lazy_static! {
+    static ref REPORTER: RwLock<Option<Arc<dyn Reporter>>> = RwLock::new(None);
+}
+
+/// The reporter selected for `app`, `json` with `--json`.
+pub fn from_app(app: &App) -> Result<Arc<dyn Reporter>> {
+    let name = if app.has_flag(&["--json"]) {
+        Some(String::from("json"))
+    } else {
+        app.flag_value(&["--reporter"])
+            .or_else(|| app.config.get("reporter").map(String::from))
+    };
+
+    match name.as_deref() {
+        None | Some("human") => Ok(Arc::new(HumanReporter::new(
+            !app.has_flag(&["--no-progress", "-np"]),
+        ))),
+        Some("json") => Ok(Arc::new(JsonReporter::default())),
+        Some("quiet") => Ok(Arc::new(QuietReporter)),
+        Some(other) => Err(anyhow!(
+            "unknown reporter {}, expected human, json or quiet",
+            other
+        )),
+    }
+}
+
+/// Show the activity of every command with `reporter` from now on.
+pub fn set(reporter: Arc<dyn Reporter>) {
+    *REPORTER.write().unwrap() = Some(reporter);
+}
+
+/// The reporter commands show their activity with, the `human` one until another is set.
+pub fn get() -> Arc<dyn Reporter> {
+    REPORTER
+        .read()
+        .unwrap()
+        .clone()
+        .unwrap_or_else(|| Arc::new(HumanReporter::new(true)))
+}
+
+/// Whether commands print their result as JSON, with `--json` or the `json` reporter.
+pub fn json() -> bool {
+    get().name() == "json"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    #[test]
+    fn selects_the_reporter_of_the_flags_and_config() {
+        let root = std::env::temp_dir().join(format!("volt-reporter-{}", std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::write(root.join(".voltrc"), "reporter=quiet\n").unwrap();
+
+        let app = |flags: &[&str]| App {
+            current_dir: root.clone(),
+            home_dir: root.join("home"),
+            node_modules_dir: root.join("node_modules"),
+            volt_dir: root.join("home/.volt"),
+            cache_dir: root.join("home/.volt/.cache"),
+            lock_file_path: root.join("volt.lock"),
+            config_file: root.join(".voltrc"),
+            args: vec![],
+            flags: flags.iter().map(|flag| flag.to_string()).collect(),
+            config: Config::load(&root.join("home/.voltrc"), &root),
+        };
+
+        let name = |flags: &[&str]| from_app(&app(flags)).map(|reporter| reporter.name());
+
+        assert_eq!(name(&[]).unwrap(), "quiet");
+        assert_eq!(name(&["--reporter=human"]).unwrap(), "human");
+        assert_eq!(name(&["--reporter=human", "--json"]).unwrap(), "json");
+        assert!(name(&["--reporter=fancy"]).is_err());
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[derive(Default)]
+    struct RecordingReporter {
+        events: Mutex<Vec<String>>,
+    }
+
+    impl Reporter for RecordingReporter {
+        fn name(&self) -> &'static str {
+            "recording"
+        }
+
+        fn progress(&self, event: ProgressEvent) {
+            self.events.lock().unwrap().push(format!("{:?}", event));
+        }
+
+        fn info(&self, _title: &str, _message: &str) {}
+
+        fn output(&self, _text: &str) {}
+
+        fn document(&self, _text: &str) {}
+
+        fn warn(&self, _message: &str) {}
+
+        fn warnings(&self, _warnings: &Warnings) {}
+
+        fn finished(&self, _elapsed: Duration) {}
+    }
+
+    #[test]
+    fn reports_progress_as_events() {
+        let reporter = Arc::new(RecordingReporter::default());
+        let progress = Progress::with_reporter(reporter.clone(), "Fetching", 1);
+
+        progress.inc_length(1);
+        progress.inc(2);
+        progress.finish();
+
+        assert_eq!(
+            *reporter.events.lock().unwrap(),
+            vec![
+                r#"Started { title: "Fetching", total: 1 }"#,
+                r#"Advanced { title: "Fetching", done: 0, total: 2 }"#,
+                r#"Advanced { title: "Fetching", done: 2, total: 2 }"#,
+                r#"Finished { title: "Fetching" }"#,
+            ]
+        );
+    }
+
+    /// A writer the test can read back.
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, data: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(data);
+            Ok(data.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn emits_json_events_line_by_line() {
+        let buffer = Buffer::default();
+        let reporter = Arc::new(JsonReporter::with_writer(Box::new(buffer.clone())));
+
+        let progress = Progress::with_reporter(reporter.clone(), "Fetching", 2);
+        progress.inc(1);
+        progress.finish();
+        reporter.info("Pruned", "left-pad");
+        reporter.output("left-pad@1.3.0");
+        reporter.warn(&format!("{} is deprecated", "request".yellow()));
+        reporter.document("{\n  \"direct\": 1\n}");
+        reporter.finished(Duration::from_millis(1500));
+
+        let text = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        let events: Vec<Value> = text
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+
+        assert_eq!(
+            events,
+            vec![
+                json!({ "type": "progress", "event": "started", "title": "Fetching", "total": 2 }),
+                json!({
+                    "type": "progress",
+                    "event": "advanced",
+                    "title": "Fetching",
+                    "done": 1,
+                    "total": 2
+                }),
+                json!({ "type": "progress", "event": "finished", "title": "Fetching" }),
+                json!({ "type": "info", "title": "Pruned", "message": "left-pad" }),
+                json!({ "type": "output", "text": "left-pad@1.3.0" }),
+                json!({ "type": "warning", "message": "request is deprecated" }),
+                json!({ "type": "document", "document": { "direct": 1 } }),
+                json!({ "type": "finished", "elapsed": 1.5 }),
+            ]
+        );
+    }
+}
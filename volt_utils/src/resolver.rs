@@ -0,0 +1,827 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Resolve dependency trees from the npm registry instead of the volt CDN.
+//!
+//! Enabled with `resolver=registry` in `.voltrc`, and used for packages the CDN can't resolve.
+//! The tree is walked breadth first from a work queue: every `(name, range)` pair is resolved
+//! once, with at most `network-concurrency` requests in flight, and the results are memoized in
+//! maps shared by every resolution in the process, so packages requested together only resolve
+//! their common dependencies once. Packuments are fetched and parsed once per process too, later
+//! requests wait for the one in flight (see `packument_stats` for `--timing`).
+//! Across processes, resolutions are kept in the [metadata database](crate::metadata_db).
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context, Result};
+use dashmap::DashMap;
+use futures_util::future::{self, Either};
+use futures_util::stream::FuturesUnordered;
+use futures_util::StreamExt;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::OnceCell;
+
+use crate::app::App;
+use crate::explain::{Candidate, Decision, Decisions};
+use crate::github_packages;
+use crate::metadata_db::MetadataDb;
+use crate::network;
+use crate::registry;
+use crate::registry_compat;
+use crate::reporter;
+use crate::volt_api::{PackageSource, VersionData, VoltPackage, VoltResponse};
+
+/// Requests in flight when `network-concurrency` is not set.
+pub const DEFAULT_CONCURRENCY: usize = 16;
+
+/// A `(name, range)` pair as it appears in a dependency list.
+type Request = (String, String);
+
+/// A resolved package and the dependencies it asks for, the summary kept in the database.
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct Resolved {
+    package: VoltPackage,
+    dependencies: Vec<Request>,
+}
+
+lazy_static! {
+    static ref PACKUMENTS: DashMap<String, Arc<OnceCell<Arc<Value>>>> = DashMap::new();
+    static ref RESOLVED: DashMap<Request, Resolved> = DashMap::new();
+}
+
+static PACKUMENT_LOOKUPS: AtomicUsize = AtomicUsize::new(0);
+static PACKUMENT_FETCHES: AtomicUsize = AtomicUsize::new(0);
+
+/// How often packuments were asked for in this process, and how often they had to be fetched.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PackumentStats {
+    pub lookups: usize,
+    pub fetches: usize,
+}
+
+impl PackumentStats {
+    /// Lookups answered from memory, including those which waited for a fetch in flight.
+    pub fn hits(&self) -> usize {
+        self.lookups.saturating_sub(self.fetches)
+    }
+}
+
+pub fn packument_stats() -> PackumentStats {
+    PackumentStats {
+        lookups: PACKUMENT_LOOKUPS.load(Ordering::Relaxed),
+        fetches: PACKUMENT_FETCHES.load(Ordering::Relaxed),
+    }
+}
+
+/// Check if the registry resolver is enabled.
+pub fn is_enabled(app: &App) -> bool {
+    app.config.get("resolver") == Some("registry")
+}
+
+/// Resolve `spec` (`react` or `react@^17.0.0`) and its whole dependency tree.
+pub async fn resolve(app: &App, spec: &str) -> Result<VoltResponse> {
+    resolve_explained(app, spec)
+        .await
+        .map(|(response, _)| response)
+}
+
+/// Resolve `spec` like [`resolve`], with why each version of the tree was chosen.
+pub async fn resolve_explained(app: &App, spec: &str) -> Result<(VoltResponse, Decisions)> {
+    let concurrency = app
+        .config
+        .get("network-concurrency")
+        .and_then(|value| value.parse().ok())
+        .filter(|concurrency| *concurrency > 0)
+        .unwrap_or(DEFAULT_CONCURRENCY);
+
+    let (name, range) = crate::parse_package_spec(spec);
+    let root = (name, range.unwrap_or_else(|| String::from("latest")));
+
+    let db = MetadataDb::from_app(app);
+
+    walk(root, concurrency, &RESOLVED, db.as_ref(), |name| {
+        packument(app, name)
+    })
+    .await
+}
+
+/// Forget the packuments and resolutions of this process, for long running processes to see
+/// new versions.
+pub fn forget() {
+    PACKUMENTS.clear();
+    RESOLVED.clear();
+}
+
+/// Get the packument of `name` from the registry of its scope, or the fastest mirror.
+pub async fn packument(app: &App, name: String) -> Result<Arc<Value>> {
+    let registry = match registry::scope_url(app, &name) {
+        Some(registry) => registry,
+        None => crate::registry_mirrors::url(app).await,
+    };
+
+    fetch_packument(app, registry, name).await
+}
+
+/// Get the packument of `name` from `registry`, once per process.
+pub async fn fetch_packument(app: &App, registry: String, name: String) -> Result<Arc<Value>> {
+    let fetched = AtomicBool::new(false);
+
+    let packument = memoized(name.clone(), || async {
+        fetched.store(true, Ordering::Relaxed);
+
+        let url = format!("{}/{}", registry, name.replace('/', "%2f"));
+        let fetch = || async {
+            let mut packument = registry_compat::fetch_packument(app, &registry, &url)
+                .await
+                .with_context(|| format!("failed to fetch {}", name))?;
+
+            if github_packages::is_github(&registry) {
+                github_packages::normalize(&mut packument);
+            }
+
+            Ok::<_, anyhow::Error>(packument)
+        };
+
+        let mut packument = fetch().await?;
+
+        // The node which answered may have the new tag but not the version yet
+        for delay in network::REPLICATION_DELAYS.iter() {
+            if unreplicated_tags(&packument).is_empty() {
+                break;
+            }
+
+            network::record_retry(&url);
+            tokio::time::sleep(*delay).await;
+            packument = fetch().await?;
+        }
+
+        Ok(packument)
+    })
+    .await?;
+
+    if !fetched.load(Ordering::Relaxed) {
+        network::record_cache_hit(&registry);
+    }
+
+    Ok(packument)
+}
+
+/// Get the packument of `name` from memory, or with `fetch` if no lookup has fetched it yet.
+///
+/// Concurrent lookups of a package wait for the same fetch, a failed fetch is tried again by
+/// the next lookup.
+async fn memoized<F, Fut>(name: String, fetch: F) -> Result<Arc<Value>>
+where
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = Result<Value>>,
+{
+    PACKUMENT_LOOKUPS.fetch_add(1, Ordering::Relaxed);
+
+    // The map is only locked to get the cell, never across the fetch
+    let cell = PACKUMENTS.entry(name).or_default().clone();
+
+    let packument = cell
+        .get_or_try_init(|| async {
+            PACKUMENT_FETCHES.fetch_add(1, Ordering::Relaxed);
+            fetch().await.map(Arc::new)
+        })
+        .await?;
+
+    Ok(packument.clone())
+}
+
+/// Walk the tree of `root` breadth first, fetching packuments with `fetch`.
+///
+/// Packages are flattened by name, keeping the version closest to the root and the highest one
+/// among those at the same depth. Every range asking for a package is kept in the decisions.
+pub(crate) async fn walk<F, Fut>(
+    root: Request,
+    concurrency: usize,
+    memo: &DashMap<Request, Resolved>,
+    db: Option<&MetadataDb>,
+    fetch: F,
+) -> Result<(VoltResponse, Decisions)>
+where
+    F: Fn(String) -> Fut,
+    Fut: Future<Output = Result<Arc<Value>>>,
+{
+    let mut queue: VecDeque<(Request, usize)> = VecDeque::new();
+    let mut seen: HashSet<Request> = HashSet::new();
+    let mut in_flight = FuturesUnordered::new();
+    let mut packages: HashMap<String, (usize, VoltPackage)> = HashMap::new();
+    let mut candidates: HashMap<Request, Candidate> = HashMap::new();
+    let mut root_version = None;
+    let mut new_resolutions = vec![];
+    let mut new_summaries = vec![];
+
+    queue.push_back((root.clone(), 0));
+    seen.insert(root.clone());
+    candidates.insert(root.clone(), candidate(&root, 0));
+
+    loop {
+        while in_flight.len() < concurrency {
+            let (request, depth) = match queue.pop_front() {
+                Some(next) => next,
+                None => break,
+            };
+
+            let cached = memo
+                .get(&request)
+                .map(|resolved| resolved.clone())
+                .or_else(|| db.and_then(|db| from_db(db, &request)));
+
+            let lookup = match cached {
+                Some(resolved) => Either::Left(future::ok::<_, anyhow::Error>((resolved, false))),
+                None => {
+                    let packument = fetch(request.0.clone());
+                    let request = request.clone();
+
+                    Either::Right(async move {
+                        Ok((select_version(&request, &*packument.await?)?, true))
+                    })
+                }
+            };
+
+            in_flight.push(async move {
+                let (resolved, fetched) = lookup.await?;
+                Ok::<_, anyhow::Error>((request, depth, resolved, fetched))
+            });
+        }
+
+        let (request, depth, resolved, fetched) = match in_flight.next().await {
+            Some(result) => result?,
+            None => break,
+        };
+
+        if fetched && db.is_some() {
+            let (name, range) = request.clone();
+            let version = resolved.package.version.clone();
+
+            new_summaries.push((
+                name.clone(),
+                version.clone(),
+                bincode::serialize(&resolved)?,
+            ));
+            new_resolutions.push((name, range, version));
+        }
+
+        memo.insert(request.clone(), resolved.clone());
+
+        if request == root {
+            root_version = Some(resolved.package.version.clone());
+        }
+
+        if let Some(candidate) = candidates.get_mut(&request) {
+            candidate.version = resolved.package.version.clone();
+        }
+
+        let requirer = format!("{}@{}", resolved.package.name, resolved.package.version);
+
+        for dependency in resolved.dependencies.iter() {
+            if seen.insert(dependency.clone()) {
+                queue.push_back((dependency.clone(), depth + 1));
+            }
+
+            let required_by = &mut candidates
+                .entry(dependency.clone())
+                .or_insert_with(|| candidate(dependency, depth + 1))
+                .required_by;
+
+            if !required_by.contains(&requirer) {
+                required_by.push(requirer.clone());
+            }
+        }
+
+        let closer = match packages.get(&resolved.package.name) {
+            Some((existing_depth, existing)) => {
+                depth < *existing_depth
+                    || (depth == *existing_depth && newer(&resolved.package, existing))
+            }
+            None => true,
+        };
+
+        if closer {
+            packages.insert(resolved.package.name.clone(), (depth, resolved.package));
+        }
+    }
+
+    if let Some(db) = db {
+        // The database is only a cache, the resolution stands without it
+        let _ = db.store(&new_resolutions, &new_summaries);
+    }
+
+    let version = root_version.ok_or_else(|| anyhow!("{} could not be resolved", root.0))?;
+
+    let mut decisions: Decisions = HashMap::new();
+
+    for ((name, _), candidate) in candidates {
+        if let Some((_, package)) = packages.get(&name) {
+            decisions
+                .entry(name.clone())
+                .or_insert_with(|| Decision {
+                    name,
+                    version: package.version.clone(),
+                    candidates: vec![],
+                })
+                .candidates
+                .push(candidate);
+        }
+    }
+
+    for decision in decisions.values_mut() {
+        decision
+            .candidates
+            .sort_by(|a, b| (a.depth, &a.range).cmp(&(b.depth, &b.range)));
+    }
+
+    let mut versions = HashMap::new();
+    versions.insert(
+        version.clone(),
+        VersionData {
+            packages: packages
+                .into_iter()
+                .map(|(name, (_, package))| (name, package))
+                .collect(),
+        },
+    );
+
+    Ok((VoltResponse { version, versions }, decisions))
+}
+
+fn candidate((_, range): &Request, depth: usize) -> Candidate {
+    Candidate {
+        range: range.clone(),
+        version: String::new(),
+        depth,
+        required_by: vec![],
+    }
+}
+
+fn from_db(db: &MetadataDb, (name, range): &Request) -> Option<Resolved> {
+    let version = db.resolution(name, range)?;
+
+    bincode::deserialize(&db.summary(name, &version)?).ok()
+}
+
+fn newer(a: &VoltPackage, b: &VoltPackage) -> bool {
+    match (
+        node_semver::Version::parse(&a.version),
+        node_semver::Version::parse(&b.version),
+    ) {
+        (Ok(a), Ok(b)) => a > b,
+        _ => false,
+    }
+}
+
+/// The deprecation message of `version`, or of the whole package when every version is
+/// deprecated at once.
+pub fn deprecation(packument: &Value, version: &str) -> Option<String> {
+    [
+        &packument["versions"][version]["deprecated"],
+        &packument["deprecated"],
+    ]
+    .iter()
+    .filter_map(|message| message.as_str())
+    .find(|message| !message.is_empty())
+    .map(String::from)
+}
+
+/// The version of `versions` closest to `locked` which matches `range`, other than `locked`: the
+/// next higher one, or the highest lower one when none is higher.
+pub fn nearest_version<'a, I>(versions: I, locked: &str, range: &str) -> Option<String>
+where
+    I: IntoIterator<Item = &'a String>,
+{
+    let locked = node_semver::Version::parse(locked).ok()?;
+    let range = node_semver::Range::parse(range).ok()?;
+
+    let (higher, lower): (Vec<_>, Vec<_>) = versions
+        .into_iter()
+        .filter_map(|version| node_semver::Version::parse(version).ok())
+        .filter(|version| *version != locked && range.satisfies(version))
+        .partition(|version| *version > locked);
+
+    higher
+        .into_iter()
+        .min()
+        .or_else(|| lower.into_iter().max())
+        .map(|version| version.to_string())
+}
+
+/// The dist tags of `packument` pointing to a version it doesn't have.
+pub fn unreplicated_tags(packument: &Value) -> Vec<String> {
+    packument["dist-tags"]
+        .as_object()
+        .into_iter()
+        .flatten()
+        .filter(|(_, version)| {
+            version
+                .as_str()
+                .is_some_and(|version| packument["versions"][version].is_null())
+        })
+        .map(|(tag, _)| tag.clone())
+        .collect()
+}
+
+/// The highest version of `versions` below `version` matching every range of `ranges`, dist
+/// tags match any version which is not a prerelease.
+pub fn previous_version<'a, I>(versions: I, version: &str, ranges: &[String]) -> Option<String>
+where
+    I: IntoIterator<Item = &'a String>,
+{
+    let version = node_semver::Version::parse(version).ok()?;
+
+    let ranges: Vec<Option<node_semver::Range>> = ranges
+        .iter()
+        .map(|range| node_semver::Range::parse(range).ok())
+        .collect();
+
+    versions
+        .into_iter()
+        .filter_map(|candidate| node_semver::Version::parse(candidate).ok())
+        .filter(|candidate| *candidate < version)
+        .filter(|candidate| {
+            ranges.iter().all(|range| match range {
+                Some(range) => range.satisfies(candidate),
+                None => candidate.pre_release.is_empty(),
+            })
+        })
+        .max()
+        .map(|version| version.to_string())
+}
+
+/// The version before `package`, whose tarball isn't on the registry yet, which every range
+/// resolved to it in this process accepts.
+pub async fn fallback(app: &App, package: &VoltPackage) -> Result<VoltPackage> {
+    let ranges: Vec<String> = RESOLVED
+        .iter()
+        .filter(|entry| {
+            entry.key().0 == package.name && entry.value().package.version == package.version
+        })
+        .map(|entry| entry.key().1.clone())
+        .collect();
+
+    if ranges.is_empty() {
+        return Err(anyhow!(
+            "{}@{} was not resolved from the registry",
+            package.name,
+            package.version
+        ));
+    }
+
+    let packument = packument(app, package.name.clone()).await?;
+
+    let versions = packument["versions"]
+        .as_object()
+        .ok_or_else(|| anyhow!("invalid packument for {}", package.name))?;
+
+    let version = previous_version(versions.keys(), &package.version, &ranges)
+        .ok_or_else(|| anyhow!("no earlier version of {} matches", package.name))?;
+
+    Ok(select_version(&(package.name.clone(), version), &packument)?.package)
+}
+
+/// Replace the locked `name@locked`, whose tarball is gone from the registry, with the nearest
+/// version matching `range`.
+pub async fn substitute(app: &App, name: &str, locked: &str, range: &str) -> Result<VoltPackage> {
+    let packument = packument(app, name.to_string()).await?;
+
+    let versions = packument["versions"]
+        .as_object()
+        .ok_or_else(|| anyhow!("invalid packument for {}", name))?;
+
+    let version = nearest_version(versions.keys(), locked, range)
+        .ok_or_else(|| anyhow!("no other version of {} matches {}", name, range))?;
+
+    Ok(select_version(&(name.to_string(), version), &packument)?.package)
+}
+
+/// The package of the version of `name` matching `range`, a dist tag or a semver range.
+pub async fn select(app: &App, name: &str, range: &str) -> Result<VoltPackage> {
+    let packument = packument(app, name.to_string()).await?;
+
+    Ok(select_version(&(name.to_string(), range.to_string()), &packument)?.package)
+}
+
+/// Pick the version of `packument` matching the range of `request`.
+fn select_version(request: &Request, packument: &Value) -> Result<Resolved> {
+    let (name, range) = request;
+
+    let versions = packument["versions"]
+        .as_object()
+        .ok_or_else(|| anyhow!("invalid packument for {}", name))?;
+
+    // Dist tags (`latest`, `next`) and then semver ranges, `*` for empty ones
+    let version = match packument["dist-tags"][range.as_str()].as_str() {
+        // Still not replicated after the retries, the version before it is the safe bet
+        Some(tagged) if !versions.contains_key(tagged) => {
+            let previous = previous_version(versions.keys(), tagged, std::slice::from_ref(range));
+
+            if let Some(previous) = previous.as_ref() {
+                reporter::get().warn(&format!(
+                    "{}@{} is tagged {} but not published yet, using {}",
+                    name, tagged, range, previous
+                ));
+            }
+
+            previous
+        }
+        Some(version) => Some(version.to_string()),
+        None if range.is_empty() => crate::max_satisfying(versions.keys(), "*"),
+        None => crate::max_satisfying(versions.keys(), range),
+    }
+    .ok_or_else(|| anyhow!("no version of {} matches {}", name, range))?;
+
+    let manifest = &versions[&version];
+
+    let dependency_list = |field: &str| -> Vec<Request> {
+        manifest[field]
+            .as_object()
+            .map(|dependencies| {
+                dependencies
+                    .iter()
+                    .map(|(name, range)| (name.clone(), range.as_str().unwrap_or("").to_string()))
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+
+    let string_list = |field: &str| -> Vec<String> {
+        manifest[field]
+            .as_array()
+            .map(|items| {
+                items
+                    .iter()
+                    .filter_map(|item| item.as_str().map(String::from))
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+
+    let string_map = |field: &str| -> HashMap<String, String> {
+        manifest[field]
+            .as_object()
+            .map(|entries| {
+                entries
+                    .iter()
+                    .filter_map(|(key, value)| Some((key.clone(), value.as_str()?.to_string())))
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+
+    let dependencies = dependency_list("dependencies");
+    let optional_dependencies = dependency_list("optionalDependencies");
+
+    let bin = match &manifest["bin"] {
+        Value::String(path) => {
+            let mut bin = HashMap::new();
+            bin.insert(
+                name.rsplit('/').next().unwrap_or(name).to_string(),
+                path.clone(),
+            );
+            Some(bin)
+        }
+        Value::Object(entries) => Some(
+            entries
+                .iter()
+                .filter_map(|(name, path)| Some((name.clone(), path.as_str()?.to_string())))
+                .collect(),
+        ),
+        _ => None,
+    };
+
+    let package = VoltPackage {
+        name: name.clone(),
+        version: version.clone(),
+        tarball: manifest["dist"]["tarball"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string(),
+        sha1: manifest["dist"]["shasum"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string(),
+        integrity: None,
+        peer_dependencies: dependency_list("peerDependencies")
+            .into_iter()
+            .map(|(name, range)| format!("{}@{}", name, range))
+            .collect(),
+        dependencies: Some(dependencies.iter().map(|(name, _)| name.clone()).collect()),
+        optional_dependencies: optional_dependencies
+            .iter()
+            .map(|(name, _)| name.clone())
+            .collect(),
+        bin,
+        os: string_list("os"),
+        cpu: string_list("cpu"),
+        libc: string_list("libc"),
+        source: PackageSource::Registry,
+        deprecated: deprecation(packument, &version),
+        engines: string_map("engines"),
+    };
+
+    Ok(Resolved {
+        package,
+        dependencies: dependencies
+            .into_iter()
+            .chain(optional_dependencies)
+            .collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn packument(versions: &[(&str, &[(&str, &str)])]) -> Value {
+        let mut packument = serde_json::json!({ "dist-tags": {}, "versions": {} });
+
+        for (version, dependencies) in versions {
+            packument["versions"][*version] = serde_json::json!({
+                "dependencies": dependencies
+                    .iter()
+                    .map(|(name, range)| (name.to_string(), Value::from(*range)))
+                    .collect::<serde_json::Map<String, Value>>(),
+                "dist": { "tarball": "", "shasum": "" },
+            });
+        }
+
+        let latest = versions.last().unwrap().0;
+        packument["dist-tags"]["latest"] = Value::from(latest);
+        packument
+    }
+
+    #[tokio::test]
+    async fn walks_the_tree_once_per_range() {
+        let mut registry = HashMap::new();
+        registry.insert(
+            "app",
+            packument(&[("1.0.0", &[("a", "^1.0.0"), ("b", "^1.0.0")])]),
+        );
+        registry.insert(
+            "a",
+            packument(&[("1.0.0", &[("c", "^1.0.0")]), ("1.2.0", &[("c", "^1.0.0")])]),
+        );
+        registry.insert("b", packument(&[("1.0.0", &[("c", "^2.0.0")])]));
+        registry.insert("c", packument(&[("1.0.0", &[]), ("2.0.0", &[])]));
+
+        let registry = Arc::new(
+            registry
+                .into_iter()
+                .map(|(name, packument)| (name.to_string(), Arc::new(packument)))
+                .collect::<HashMap<_, _>>(),
+        );
+        let fetches = Arc::new(std::sync::Mutex::new(vec![]));
+        let memo = DashMap::new();
+
+        let (response, decisions) = walk(
+            (String::from("app"), String::from("latest")),
+            2,
+            &memo,
+            None,
+            |name| {
+                fetches.lock().unwrap().push(name.clone());
+                let registry = registry.clone();
+                async move { Ok(registry[&name].clone()) }
+            },
+        )
+        .await
+        .unwrap();
+
+        let packages = &response.versions["1.0.0"].packages;
+        assert_eq!(packages["a"].version, "1.2.0");
+        // Both versions of `c` are at the same depth, the highest is kept
+        assert_eq!(packages["c"].version, "2.0.0");
+        assert_eq!(packages["c"].source, PackageSource::Registry);
+        assert_eq!(memo.len(), 5);
+
+        let mut fetches = fetches.lock().unwrap().clone();
+        fetches.sort();
+        assert_eq!(fetches, vec!["a", "app", "b", "c", "c"]);
+
+        let c = &decisions["c"];
+        assert_eq!(
+            c.strategy(),
+            "the highest version at depth 2, the closest to the root"
+        );
+        assert_eq!(c.conflicts()[0].range, "^1.0.0");
+        assert_eq!(c.conflicts()[0].required_by, vec!["a@1.2.0"]);
+    }
+
+    #[test]
+    fn deprecates_versions_and_packages() {
+        let mut packument = packument(&[("1.0.0", &[]), ("2.0.0", &[])]);
+        packument["versions"]["1.0.0"]["deprecated"] = Value::from("use 2.0.0");
+
+        assert_eq!(
+            deprecation(&packument, "1.0.0"),
+            Some(String::from("use 2.0.0"))
+        );
+        assert_eq!(deprecation(&packument, "2.0.0"), None);
+
+        packument["deprecated"] = Value::from("no longer maintained");
+
+        assert_eq!(
+            deprecation(&packument, "2.0.0"),
+            Some(String::from("no longer maintained"))
+        );
+    }
+
+    #[tokio::test]
+    async fn fetches_each_packument_once() {
+        let fetches = Arc::new(AtomicUsize::new(0));
+
+        let lookups = (0..8).map(|_| {
+            let fetches = fetches.clone();
+
+            memoized(String::from("memoized-test-package"), || async move {
+                fetches.fetch_add(1, Ordering::SeqCst);
+                tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+                Ok(packument(&[("1.0.0", &[])]))
+            })
+        });
+
+        let packuments = future::join_all(lookups).await;
+
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+        assert!(packuments.iter().all(|packument| packument.is_ok()));
+
+        let stats = packument_stats();
+        assert!(stats.lookups >= 8 && stats.hits() >= 7);
+    }
+
+    #[test]
+    fn finds_the_nearest_version() {
+        let versions: Vec<String> = ["1.2.0", "1.2.3", "1.2.5", "1.3.0", "2.0.0"]
+            .iter()
+            .map(|version| version.to_string())
+            .collect();
+
+        assert_eq!(
+            nearest_version(&versions, "1.2.4", "^1.2.0"),
+            Some(String::from("1.2.5"))
+        );
+        assert_eq!(
+            nearest_version(&versions, "1.3.0", "^1.2.0"),
+            Some(String::from("1.2.5"))
+        );
+        assert_eq!(nearest_version(&versions, "2.0.0", "^2.0.0"), None);
+    }
+
+    #[test]
+    fn falls_back_from_unreplicated_versions() {
+        let mut packument = packument(&[("1.0.0", &[]), ("1.1.0", &[]), ("2.0.0-rc.1", &[])]);
+        packument["dist-tags"] = serde_json::json!({ "latest": "1.2.0", "next": "2.0.0-rc.1" });
+
+        assert_eq!(unreplicated_tags(&packument), vec!["latest"]);
+
+        let request = (String::from("left"), String::from("latest"));
+        assert_eq!(
+            select_version(&request, &packument)
+                .unwrap()
+                .package
+                .version,
+            "1.1.0"
+        );
+
+        let versions: Vec<String> = ["1.0.0", "1.1.0", "1.1.5", "2.0.0-rc.1"]
+            .iter()
+            .map(|version| version.to_string())
+            .collect();
+        let ranges = |ranges: &[&str]| -> Vec<String> {
+            ranges.iter().map(|range| range.to_string()).collect()
+        };
+
+        assert_eq!(
+            previous_version(&versions, "1.2.0", &ranges(&["^1.0.0", "~1.1.0"])),
+            Some(String::from("1.1.5"))
+        );
+        assert_eq!(
+            previous_version(&versions, "2.0.0", &ranges(&["latest"])),
+            Some(String::from("1.1.5"))
+        );
+        assert_eq!(
+            previous_version(&versions, "1.2.0", &ranges(&["^1.2.0"])),
+            None
+        );
+    }
+}
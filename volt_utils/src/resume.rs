@@ -0,0 +1,96 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Pick up the work of installs which were killed.
+//!
+//! Tarballs are kept in `<cache dir>/downloads` from the moment they are downloaded until they
+//! are extracted, and a marker in `<cache dir>/extracting` exists while a package is
+//! being extracted into the store. The next install reuses the downloads, and extracts entries
+//! whose marker is still there again instead of trusting them.
+
+use std::fs::{self, create_dir_all, read, write};
+use std::path::PathBuf;
+
+use anyhow::Result;
+
+use crate::app::App;
+use crate::mirror::tarball_name;
+use crate::volt_api::VoltPackage;
+
+fn cache_dir(app: &App, kind: &str) -> PathBuf {
+    app.cache_dir.join(kind)
+}
+
+fn download_path(app: &App, package: &VoltPackage) -> PathBuf {
+    cache_dir(app, "downloads").join(tarball_name(&package.name, &package.version))
+}
+
+fn marker_path(app: &App, name: &str) -> PathBuf {
+    cache_dir(app, "extracting").join(name.replace('/', "+"))
+}
+
+/// Get the tarball of `package` downloaded by an install which didn't get to extract it.
+pub fn read_download(app: &App, package: &VoltPackage) -> Option<bytes::Bytes> {
+    let bytes = bytes::Bytes::from(read(download_path(app, package)).ok()?);
+
+    if App::calc_hash(&bytes).ok()? == package.sha1 {
+        Some(bytes)
+    } else {
+        None
+    }
+}
+
+pub fn save_download(app: &App, package: &VoltPackage, bytes: &[u8]) -> Result<()> {
+    let path = download_path(app, package);
+
+    if let Some(parent) = path.parent() {
+        create_dir_all(parent)?;
+    }
+
+    write(path, bytes)?;
+
+    Ok(())
+}
+
+/// Check if the store entry `name` is complete, rather than missing or half extracted.
+pub fn is_extracted(app: &App, name: &str) -> bool {
+    app.volt_dir.join(name).exists() && !marker_path(app, name).exists()
+}
+
+/// Check if the extraction of `name` was cut short.
+pub fn is_interrupted(app: &App, name: &str) -> bool {
+    marker_path(app, name).exists()
+}
+
+pub fn begin_extraction(app: &App, name: &str) -> Result<()> {
+    let path = marker_path(app, name);
+
+    if let Some(parent) = path.parent() {
+        create_dir_all(parent)?;
+    }
+
+    write(path, "")?;
+
+    Ok(())
+}
+
+/// The package is in the store, its download and marker are not needed anymore.
+pub fn finish_extraction(app: &App, package: &VoltPackage) -> Result<()> {
+    let _ = fs::remove_file(download_path(app, package));
+    fs::remove_file(marker_path(app, &package.store_name()))?;
+
+    Ok(())
+}
@@ -0,0 +1,416 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Find and repair silent corruption in the volt store.
+//!
+//! When a package is extracted into the store, the sha1 of each of its files is recorded in
+//! `<volt dir>/.integrity/<name>.json` with the tarball it came from. A scrub re-hashes entries
+//! against their records, a batch at a time starting where the previous pass stopped, fetches
+//! corrupted entries again and evicts the ones which can't be fetched. With
+//! `scrub-after-install=true` a batch is scrubbed by a low priority background process after
+//! every install.
+
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha1::{Digest, Sha1};
+use walkdir::WalkDir;
+
+use crate::app::App;
+use crate::volt_api::VoltPackage;
+
+pub const INTEGRITY_DIR: &str = ".integrity";
+
+/// Last entry scrubbed by an incremental pass.
+const CURSOR_FILE: &str = ".cursor";
+
+/// Entries scrubbed after an install when `scrub-batch-size` is not set.
+pub const DEFAULT_BATCH_SIZE: usize = 50;
+
+/// What a store entry looked like when it was extracted.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Integrity {
+    /// The name of the package, older records only have the name of the entry
+    #[serde(default)]
+    pub name: String,
+    pub version: String,
+    pub tarball: String,
+    pub sha1: String,
+    /// Relative path → sha1 of the file
+    pub files: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Verdict {
+    Intact,
+    /// Files which changed or went missing
+    Corrupt(Vec<String>),
+}
+
+#[derive(Debug, Default)]
+pub struct ScrubReport {
+    pub checked: usize,
+    pub repaired: Vec<String>,
+    pub evicted: Vec<String>,
+    /// Every entry has been scrubbed, the next pass starts from the beginning
+    pub finished: bool,
+}
+
+pub(crate) fn record_path(volt_dir: &Path, name: &str) -> PathBuf {
+    volt_dir.join(INTEGRITY_DIR).join(format!("{}.json", name))
+}
+
+/// The sha1 of the file at `path`.
+pub fn hash_file(path: &Path) -> Result<String> {
+    let mut hasher = Sha1::new();
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Hash every file below `dir`, by path relative to it.
+pub fn hash_files(dir: &Path) -> Result<BTreeMap<String, String>> {
+    let mut files = BTreeMap::new();
+
+    for entry in WalkDir::new(dir) {
+        let entry = entry?;
+
+        if !entry.file_type().is_file() {
+            continue;
+        }
+
+        let relative = entry.path().strip_prefix(dir)?.to_string_lossy();
+
+        files.insert(relative.replace('\\', "/"), hash_file(entry.path())?);
+    }
+
+    Ok(files)
+}
+
+/// Record the files of `package`, freshly extracted into the store.
+pub fn record(app: &App, package: &VoltPackage) -> Result<Integrity> {
+    let integrity = Integrity {
+        name: package.name.clone(),
+        version: package.version.clone(),
+        tarball: package.tarball.clone(),
+        sha1: package.sha1.clone(),
+        files: hash_files(&app.volt_dir.join(package.store_name()))?,
+    };
+
+    let path = record_path(&app.volt_dir, &package.store_name());
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    fs::write(path, serde_json::to_string(&integrity)?)?;
+
+    Ok(integrity)
+}
+
+/// Compare the files in `dir` with `integrity`.
+///
+/// Files added after extraction, by install scripts for instance, are not corruption.
+pub fn verify(dir: &Path, integrity: &Integrity) -> Result<Verdict> {
+    let files = hash_files(dir)?;
+
+    let corrupt: Vec<String> = integrity
+        .files
+        .iter()
+        .filter(|(path, hash)| files.get(*path) != Some(*hash))
+        .map(|(path, _)| path.clone())
+        .collect();
+
+    if corrupt.is_empty() {
+        Ok(Verdict::Intact)
+    } else {
+        Ok(Verdict::Corrupt(corrupt))
+    }
+}
+
+/// Names of the store entries which have a record, sorted.
+pub fn recorded_names(volt_dir: &Path) -> Vec<String> {
+    let dir = volt_dir.join(INTEGRITY_DIR);
+
+    let mut names: Vec<String> = WalkDir::new(&dir)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| {
+            let relative = entry.path().strip_prefix(&dir).ok()?.to_string_lossy();
+            relative
+                .strip_suffix(".json")
+                .map(|name| name.replace('\\', "/"))
+        })
+        .collect();
+
+    names.sort();
+    names
+}
+
+/// The name of the package in the entry `name`, `@types/node` for `@types/node@16.4.0-1a2b3c4d5e6f`.
+fn package_name(name: &str, integrity: &Integrity) -> String {
+    if !integrity.name.is_empty() {
+        return integrity.name.clone();
+    }
+
+    match name.rfind('@') {
+        Some(index) if index > 0 => name[..index].to_string(),
+        _ => name.to_string(),
+    }
+}
+
+/// Scrub up to `limit` entries after the last one scrubbed, or every entry without a limit.
+pub async fn scrub(app: &App, limit: Option<usize>) -> Result<ScrubReport> {
+    crate::legacy_store::migrate(&app.volt_dir)?;
+
+    let cursor_path = app.volt_dir.join(INTEGRITY_DIR).join(CURSOR_FILE);
+    let names = recorded_names(&app.volt_dir);
+
+    let start = match (limit, fs::read_to_string(&cursor_path)) {
+        (Some(_), Ok(cursor)) => names
+            .iter()
+            .position(|name| name.as_str() > cursor.trim())
+            .unwrap_or(names.len()),
+        _ => 0,
+    };
+
+    let end = limit.map_or(names.len(), |limit| (start + limit).min(names.len()));
+
+    let mut report = ScrubReport::default();
+
+    for name in names[start..end].iter() {
+        let record = record_path(&app.volt_dir, name);
+        let dir = app.volt_dir.join(name);
+
+        let integrity: Integrity = match fs::read_to_string(&record)
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+        {
+            Some(integrity) if dir.exists() => integrity,
+            // The entry was removed, or the record is unreadable
+            _ => {
+                fs::remove_file(&record)?;
+                continue;
+            }
+        };
+
+        report.checked += 1;
+
+        if verify(&dir, &integrity)? == Verdict::Intact {
+            continue;
+        }
+
+        crate::fs::remove_dir_all(&dir).with_context(|| format!("failed to remove {}", name))?;
+
+        let package = VoltPackage {
+            name: package_name(name, &integrity),
+            version: integrity.version,
+            tarball: integrity.tarball,
+            sha1: integrity.sha1,
+            integrity: None,
+            peer_dependencies: vec![],
+            dependencies: None,
+            optional_dependencies: vec![],
+            bin: None,
+            os: vec![],
+            cpu: vec![],
+            libc: vec![],
+            source: Default::default(),
+            deprecated: None,
+            engines: Default::default(),
+        };
+
+        if crate::fetch_package(app, &package).await.is_ok() && dir.exists() {
+            report.repaired.push(name.clone());
+        } else {
+            fs::remove_file(&record)?;
+            report.evicted.push(name.clone());
+        }
+    }
+
+    report.finished = end == names.len();
+
+    if report.finished {
+        let _ = fs::remove_file(&cursor_path);
+    } else if let Some(last) = names[..end].last() {
+        fs::write(&cursor_path, last)?;
+    }
+
+    Ok(report)
+}
+
+/// Start a background scrub of one batch if `scrub-after-install` is enabled.
+pub fn spawn_after_install(app: &App) {
+    if app.config.get_bool("scrub-after-install") != Some(true) {
+        return;
+    }
+
+    let batch_size = app
+        .config
+        .get("scrub-batch-size")
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(DEFAULT_BATCH_SIZE);
+
+    let executable = match std::env::current_exe() {
+        Ok(executable) => executable,
+        Err(_) => return,
+    };
+
+    let args = [
+        String::from("cache"),
+        String::from("scrub"),
+        format!("--limit={}", batch_size),
+    ];
+
+    // Lowest CPU priority where `nice` is available
+    let mut command = if cfg!(unix) {
+        let mut command = Command::new("nice");
+        command.args(["-n", "19"]).arg(&executable);
+        command
+    } else {
+        Command::new(&executable)
+    };
+
+    let spawned = command
+        .args(&args)
+        .current_dir(&app.current_dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn();
+
+    if spawned.is_err() && cfg!(unix) {
+        let _ = Command::new(&executable)
+            .args(&args)
+            .current_dir(&app.current_dir)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_changed_and_missing_files() {
+        let dir = std::env::temp_dir().join(format!("volt-scrub-{}", std::process::id()));
+        fs::create_dir_all(dir.join("lib")).unwrap();
+        fs::write(dir.join("index.js"), "module.exports = 1").unwrap();
+        fs::write(dir.join("lib").join("a.js"), "a").unwrap();
+
+        let integrity = Integrity {
+            name: String::from("a"),
+            version: String::from("1.0.0"),
+            tarball: String::new(),
+            sha1: String::new(),
+            files: hash_files(&dir).unwrap(),
+        };
+
+        assert!(integrity.files.contains_key("lib/a.js"));
+
+        // Files created by install scripts are fine
+        fs::write(dir.join("build.node"), "binary").unwrap();
+        assert_eq!(verify(&dir, &integrity).unwrap(), Verdict::Intact);
+
+        fs::write(dir.join("index.js"), "module.exports = 2").unwrap();
+        fs::remove_file(dir.join("lib").join("a.js")).unwrap();
+
+        assert_eq!(
+            verify(&dir, &integrity).unwrap(),
+            Verdict::Corrupt(vec![String::from("index.js"), String::from("lib/a.js")])
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn repairs_a_corrupted_entry() {
+        use flate2::{write::GzEncoder, Compression};
+
+        let root = std::env::temp_dir().join(format!("volt-scrub-repair-{}", std::process::id()));
+        fs::create_dir_all(&root).unwrap();
+
+        let mut builder = tar::Builder::new(GzEncoder::new(vec![], Compression::fast()));
+
+        for (path, contents) in [
+            (
+                "package/package.json",
+                r#"{ "name": "@acme/pad", "version": "1.3.0" }"#,
+            ),
+            ("package/index.js", "module.exports = 1"),
+        ] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(contents.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, path, contents.as_bytes())
+                .unwrap();
+        }
+
+        let tarball = builder.into_inner().unwrap().finish().unwrap();
+        fs::write(root.join("pad.tgz"), &tarball).unwrap();
+
+        let app = App {
+            current_dir: root.clone(),
+            home_dir: root.join("home"),
+            node_modules_dir: root.join("node_modules"),
+            volt_dir: root.join("home/.volt"),
+            cache_dir: root.join("home/.volt/.cache"),
+            lock_file_path: root.join("volt.lock"),
+            config_file: root.join(".voltrc"),
+            args: vec![],
+            flags: vec![],
+            config: Default::default(),
+        };
+
+        let package: VoltPackage = serde_json::from_value(serde_json::json!({
+            "name": "@acme/pad",
+            "version": "1.3.0",
+            "tarball": "file:pad.tgz",
+            "sha1": App::calc_hash(&bytes::Bytes::from(tarball)).unwrap(),
+            "peerDependencies": [],
+            "dependencies": null,
+            "bin": null
+        }))
+        .unwrap();
+
+        crate::fetch_package(&app, &package).await.unwrap();
+
+        let entry = app.volt_dir.join(package.store_name());
+        fs::write(entry.join("index.js"), "tampered").unwrap();
+
+        let report = scrub(&app, None).await.unwrap();
+
+        assert_eq!(report.repaired, vec![package.store_name()]);
+        assert!(report.evicted.is_empty());
+        assert_eq!(
+            fs::read_to_string(entry.join("index.js")).unwrap(),
+            "module.exports = 1"
+        );
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
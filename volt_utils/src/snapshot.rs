@@ -0,0 +1,119 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Skip installs which would change nothing.
+//!
+//! A successful `volt install` records a hash of the lock file, the package.json files, the
+//! platform, the layout settings and `--production` in the
+//! [install state](crate::linker::InstallState) of the project. The next install computes the
+//! same hash, which only reads a few small files, and returns right away when it matches,
+//! without walking node_modules. Any other command rewriting the install state drops the
+//! snapshot.
+
+use std::fs::read;
+
+use anyhow::{Context, Result};
+use serde_json::json;
+
+use crate::app::App;
+use crate::legacy_store;
+use crate::linker::InstallState;
+use crate::minimize;
+use crate::package;
+use crate::platform::Platform;
+
+/// Hash everything deciding what `volt install` puts in node_modules.
+pub fn compute(app: &App) -> Result<String> {
+    let lock_file = read(&app.lock_file_path)
+        .with_context(|| format!("failed to read {}", app.lock_file_path.display()))?;
+    let platform = Platform::from_app(app);
+
+    let inputs = json!({
+        "lockFile": App::calc_hash(&bytes::Bytes::from(lock_file))?,
+        "manifests": package::manifest_hash(&app.current_dir)?,
+        "platform": [platform.os, platform.cpu, platform.libc],
+        "layout": InstallState::from_config(&app.config),
+        // Projects linked to the entries of an older store are installed again
+        "store": legacy_store::LAYOUT,
+        "minimize": [
+            minimize::Minimize::from_app(app).is_some(),
+            app.config.get("minimize-install-patterns"),
+        ],
+        "production": app.has_flag(&["--production"]),
+    });
+
+    App::calc_hash(&bytes::Bytes::from(serde_json::to_vec(&inputs)?))
+}
+
+/// Whether node_modules holds what `volt install` would install now.
+pub fn is_current(app: &App) -> bool {
+    // The state outlives a node_modules deleted by hand
+    if !app.node_modules_dir.exists() {
+        return false;
+    }
+
+    let recorded = match InstallState::load(app).and_then(|state| state.snapshot) {
+        Some(recorded) => recorded,
+        None => return false,
+    };
+
+    compute(app).ok().as_ref() == Some(&recorded)
+}
+
+/// Record the snapshot of the install which just completed.
+pub fn record(app: &App) -> Result<()> {
+    let mut state =
+        InstallState::load(app).unwrap_or_else(|| InstallState::from_config(&app.config));
+
+    state.snapshot = Some(compute(app)?);
+    state.save(app)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn changes_with_the_lock_file() {
+        let project = std::env::temp_dir().join(format!("volt-snapshot-{}", std::process::id()));
+        std::fs::create_dir_all(project.join("node_modules")).unwrap();
+        std::fs::write(project.join("package.json"), r#"{ "name": "app" }"#).unwrap();
+        std::fs::write(project.join("volt.lock"), "{}").unwrap();
+
+        let app = App {
+            current_dir: project.clone(),
+            home_dir: project.clone(),
+            node_modules_dir: project.join("node_modules"),
+            volt_dir: project.join(".volt"),
+            cache_dir: project.join(".cache"),
+            config_file: project.join(".voltrc"),
+            lock_file_path: project.join("volt.lock"),
+            args: vec![],
+            flags: vec![],
+            config: Default::default(),
+        };
+
+        assert!(!is_current(&app));
+
+        record(&app).unwrap();
+        assert!(is_current(&app));
+
+        std::fs::write(project.join("volt.lock"), r#"{ "a@1.0.0": {} }"#).unwrap();
+        assert!(!is_current(&app));
+
+        let _ = std::fs::remove_dir_all(&project);
+    }
+}
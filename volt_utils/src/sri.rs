@@ -0,0 +1,82 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Subresource integrity strings (`sha512-<base64>`), the checksums npm, yarn and pnpm lockfiles
+//! record for tarballs.
+//!
+//! A string can list several hashes separated by spaces, the strongest one volt knows is used.
+
+use sha1::Sha1;
+use sha2::{Digest, Sha256, Sha512};
+
+/// The algorithms volt can check, strongest first.
+const ALGORITHMS: [&str; 3] = ["sha512", "sha256", "sha1"];
+
+/// The base64 digest of `algorithm` in `integrity`.
+fn digest<'a>(integrity: &'a str, algorithm: &str) -> Option<&'a str> {
+    integrity.split_whitespace().find_map(|hash| {
+        let (name, digest) = hash.split_once('-')?;
+        // Options (`sha512-<digest>?foo`) are allowed after the digest
+        (name == algorithm).then(|| digest.split('?').next().unwrap_or(digest))
+    })
+}
+
+/// The hex sha1 in `integrity`, which volt uses to name and check store entries.
+pub fn sha1_hex(integrity: &str) -> Option<String> {
+    let bytes = base64::decode(digest(integrity, "sha1")?).ok()?;
+
+    Some(bytes.iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+/// Whether `data` matches `integrity`, none if it has no hash volt can check.
+pub fn verify(integrity: &str, data: &[u8]) -> Option<bool> {
+    let (algorithm, expected) = ALGORITHMS
+        .iter()
+        .find_map(|algorithm| Some((*algorithm, digest(integrity, algorithm)?)))?;
+
+    let actual = match algorithm {
+        "sha512" => base64::encode(Sha512::digest(data)),
+        "sha256" => base64::encode(Sha256::digest(data)),
+        _ => base64::encode(Sha1::digest(data)),
+    };
+
+    Some(actual == expected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verifies_the_strongest_hash() {
+        let data = b"left-pad";
+        let sha512 = format!("sha512-{}", base64::encode(Sha512::digest(data)));
+        let sha1 = format!("sha1-{}", base64::encode(Sha1::digest(data)));
+
+        assert_eq!(verify(&sha512, data), Some(true));
+        assert_eq!(verify(&sha512, b"right-pad"), Some(false));
+        // A wrong sha1 next to the right sha512 doesn't matter
+        assert_eq!(verify(&format!("sha1-AAAA {}", sha512), data), Some(true));
+        assert_eq!(verify(&sha1, data), Some(true));
+        assert_eq!(verify("md5-AAAA", data), None);
+
+        assert_eq!(
+            sha1_hex(&format!("{} {}", sha512, sha1)).as_deref(),
+            Some("16c385a6cbd7c6ad06cd6a7195aafae4932fcf3d")
+        );
+        assert_eq!(sha1_hex(&sha512), None);
+    }
+}
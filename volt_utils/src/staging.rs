@@ -0,0 +1,150 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Atomic placement of packages.
+//!
+//! Packages are materialized in a `.staging` directory next to their destination, and only
+//! renamed into place once every package of the install is complete. An interrupted install
+//! leaves the staging directory behind, which the next install throws away, instead of half
+//! linked packages which fail when they are required.
+
+use std::fs::{self, create_dir_all, read_to_string};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde_json::Value;
+
+use crate::import_method::ImportMethod;
+use crate::journal::Journal;
+use crate::linker::import_dir;
+
+pub const STAGING_DIR: &str = ".staging";
+
+/// Directory the replaced packages are moved to before the staging directory is removed.
+const REPLACED_DIR: &str = ".replaced";
+
+pub struct Staging {
+    dir: PathBuf,
+    method: ImportMethod,
+    /// Staged directory → destination
+    moves: Vec<(PathBuf, PathBuf)>,
+}
+
+impl Staging {
+    /// Stage packages in `root/.staging`, `root` must be on the same filesystem as the
+    /// destinations. Files are imported from the store with `method`.
+    pub fn new(root: &Path, method: ImportMethod) -> Result<Self> {
+        let dir = root.join(STAGING_DIR);
+
+        // Left behind by an interrupted install
+        crate::fs::remove_dir_all(&dir)?;
+        create_dir_all(&dir)?;
+
+        Ok(Staging {
+            dir,
+            method,
+            moves: vec![],
+        })
+    }
+
+    /// Stage a copy of `src`, to be placed at `dest`.
+    pub fn stage(&mut self, src: &Path, dest: &Path) -> Result<()> {
+        let staged = self.dir.join(self.moves.len().to_string());
+
+        import_dir(src, &staged, self.method)?;
+        self.moves.push((staged, dest.to_path_buf()));
+
+        Ok(())
+    }
+
+    /// Move every staged package into place, replacing what was there.
+    ///
+    /// With a `journal`, the replaced packages are kept in it until the install is over.
+    pub fn commit(self, mut journal: Option<&mut Journal>) -> Result<()> {
+        let replaced = self.dir.join(REPLACED_DIR);
+
+        for (index, (staged, dest)) in self.moves.iter().enumerate() {
+            if let Some(parent) = dest.parent() {
+                create_dir_all(parent)?;
+            }
+
+            if let Some(journal) = journal.as_deref_mut() {
+                journal.replace(dest)?;
+            } else if fs::symlink_metadata(dest).is_ok() {
+                create_dir_all(&replaced)?;
+                fs::rename(dest, replaced.join(index.to_string()))
+                    .with_context(|| format!("failed to replace {}", dest.display()))?;
+            }
+
+            fs::rename(staged, dest)
+                .with_context(|| format!("failed to move {} into place", dest.display()))?;
+        }
+
+        crate::fs::remove_dir_all(&self.dir)?;
+
+        Ok(())
+    }
+}
+
+/// Check if the package in `dir` is at `version`, so it doesn't need to be placed again.
+pub fn is_installed(dir: &Path, version: &str) -> bool {
+    read_to_string(dir.join("package.json"))
+        .ok()
+        .and_then(|data| serde_json::from_str::<Value>(&data).ok())
+        .is_some_and(|data| data["version"] == version)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replaces_packages_on_commit() {
+        let root = std::env::temp_dir().join(format!("volt-staging-{}", std::process::id()));
+        let store = root.join("store").join("a");
+        let node_modules = root.join("node_modules");
+
+        fs::create_dir_all(store.join("lib")).unwrap();
+        fs::write(store.join("package.json"), r#"{"version": "2.0.0"}"#).unwrap();
+        fs::write(store.join("lib").join("index.js"), "").unwrap();
+
+        fs::create_dir_all(node_modules.join("a")).unwrap();
+        fs::write(
+            node_modules.join("a").join("package.json"),
+            r#"{"version": "1.0.0"}"#,
+        )
+        .unwrap();
+        fs::write(node_modules.join("a").join("old.js"), "").unwrap();
+
+        // Leftovers of an interrupted install
+        fs::create_dir_all(node_modules.join(STAGING_DIR).join("0")).unwrap();
+
+        let mut staging = Staging::new(&node_modules, ImportMethod::Hardlink).unwrap();
+        staging.stage(&store, &node_modules.join("a")).unwrap();
+
+        // Nothing changes until the commit
+        assert!(is_installed(&node_modules.join("a"), "1.0.0"));
+
+        staging.commit(None).unwrap();
+
+        assert!(is_installed(&node_modules.join("a"), "2.0.0"));
+        assert!(node_modules.join("a").join("lib").join("index.js").exists());
+        assert!(!node_modules.join("a").join("old.js").exists());
+        assert!(!node_modules.join(STAGING_DIR).exists());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
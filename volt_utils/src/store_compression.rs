@@ -0,0 +1,190 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Optional zstd compression of the store.
+//!
+//! With `store-compression=zstd` in `.voltrc`, the files of packages extracted into the store are
+//! kept compressed (`index.js.zst`) at `store-compression-level` (1 to 19, 3 by default) and a
+//! `.volt-zstd` file marks the entry. Marked entries are decompressed when they are linked into
+//! node_modules, which copies them instead of hardlinking: every install costs more CPU and disk
+//! in the project for a store a fraction of its size, which suits CI runners with small disks.
+//! Entries are marked one by one, so turning compression on or off leaves the store usable.
+
+use std::fs::{self, create_dir_all, File};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context, Result};
+use walkdir::WalkDir;
+
+use crate::app::App;
+use crate::import_method::ImportMethod;
+
+/// Marks an entry of the store as compressed.
+pub const MARKER: &str = ".volt-zstd";
+
+pub const EXTENSION: &str = "zst";
+
+pub const DEFAULT_LEVEL: i32 = 3;
+
+/// The compression level to extract packages at, if the store is compressed.
+pub fn level(app: &App) -> Result<Option<i32>> {
+    match app.config.get("store-compression") {
+        None | Some("none") => return Ok(None),
+        Some("zstd") => {}
+        Some(other) => return Err(anyhow!("invalid store-compression: {}", other)),
+    }
+
+    match app.config.get("store-compression-level") {
+        Some(level) => match level.parse() {
+            Ok(level) if (1..=19).contains(&level) => Ok(Some(level)),
+            _ => Err(anyhow!("invalid store-compression-level: {}", level)),
+        },
+        None => Ok(Some(DEFAULT_LEVEL)),
+    }
+}
+
+pub fn is_compressed(dir: &Path) -> bool {
+    dir.join(MARKER).exists()
+}
+
+fn compressed_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".");
+    name.push(EXTENSION);
+
+    PathBuf::from(name)
+}
+
+/// Compress every file of the store entry `dir` in place and mark it.
+pub fn compress_dir(dir: &Path, level: i32) -> Result<()> {
+    if is_compressed(dir) {
+        return Ok(());
+    }
+
+    // Listed first, the walk would see the compressed files otherwise
+    let files: Vec<PathBuf> = WalkDir::new(dir)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_file())
+        .map(|entry| entry.into_path())
+        .collect();
+
+    for path in files.iter() {
+        let path = path.as_path();
+
+        zstd::stream::copy_encode(
+            File::open(path)?,
+            File::create(compressed_path(path))?,
+            level,
+        )
+        .with_context(|| format!("failed to compress {}", path.display()))?;
+
+        fs::remove_file(path)?;
+    }
+
+    fs::write(dir.join(MARKER), level.to_string())?;
+
+    Ok(())
+}
+
+/// Recreate the compressed store entry `src` at `dest` with decompressed files.
+///
+/// Files which are not compressed, symlinks for instance, are imported with `method`.
+pub fn decompress_dir(src: &Path, dest: &Path, method: ImportMethod) -> Result<()> {
+    for entry in WalkDir::new(src) {
+        let entry = entry?;
+        let relative = entry.path().strip_prefix(src)?;
+
+        if relative == Path::new(MARKER) {
+            continue;
+        }
+
+        if entry.file_type().is_dir() {
+            create_dir_all(dest.join(relative))?;
+            continue;
+        }
+
+        let compressed = relative
+            .extension()
+            .is_some_and(|extension| extension == EXTENSION);
+
+        if !compressed {
+            let target = dest.join(relative);
+
+            if !target.exists() {
+                method
+                    .import(entry.path(), &target)
+                    .with_context(|| format!("failed to {} {}", method.name(), target.display()))?;
+            }
+
+            continue;
+        }
+
+        let target = dest.join(relative.with_extension(""));
+
+        if target.exists() {
+            continue;
+        }
+
+        let mut decoder = zstd::stream::Decoder::new(File::open(entry.path())?)?;
+
+        io::copy(&mut decoder, &mut File::create(&target)?)
+            .with_context(|| format!("failed to decompress {}", target.display()))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decompresses_entries_when_linking() {
+        let root = std::env::temp_dir().join(format!("volt-zstd-{}", std::process::id()));
+        let entry = root.join("store").join("left-pad");
+        let dest = root.join("node_modules").join("left-pad");
+
+        create_dir_all(entry.join("lib")).unwrap();
+        fs::write(entry.join("package.json"), r#"{"version":"1.3.0"}"#).unwrap();
+        fs::write(
+            entry.join("lib").join("index.js"),
+            "module.exports = pad;".repeat(100),
+        )
+        .unwrap();
+
+        compress_dir(&entry, DEFAULT_LEVEL).unwrap();
+
+        assert!(is_compressed(&entry));
+        assert!(!entry.join("package.json").exists());
+        assert!(entry.join("lib").join("index.js.zst").exists());
+
+        decompress_dir(&entry, &dest, ImportMethod::Hardlink).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(dest.join("lib").join("index.js")).unwrap(),
+            "module.exports = pad;".repeat(100)
+        );
+        assert_eq!(
+            fs::read_to_string(dest.join("package.json")).unwrap(),
+            r#"{"version":"1.3.0"}"#
+        );
+        assert!(!dest.join(MARKER).exists());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,128 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Keep installs sharing the store from extracting the same package at once.
+//!
+//! The install which extracts a package holds `<cache dir>/locks/<name>` until the package is
+//! complete in the store, others wait for it and then find the package extracted. A lock older
+//! than [`STALE`] was left by an install which was killed, and is taken over.
+
+use std::fs::{self, create_dir_all, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use anyhow::{Context, Result};
+
+use crate::app::App;
+
+/// How long a lock is held at most, longer means its install is gone.
+const STALE: Duration = Duration::from_secs(10 * 60);
+
+/// How often a held lock is checked again.
+const POLL: Duration = Duration::from_millis(50);
+
+/// The lock of a package in the store, released when dropped.
+#[derive(Debug)]
+pub struct StoreLock {
+    path: PathBuf,
+}
+
+fn lock_path(app: &App, name: &str) -> PathBuf {
+    app.cache_dir.join("locks").join(name.replace('/', "+"))
+}
+
+fn is_stale(path: &Path) -> bool {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+        .is_some_and(|age| age > STALE)
+}
+
+impl StoreLock {
+    /// Take the lock of the package `name`, waiting while another install holds it.
+    pub async fn acquire(app: &App, name: &str) -> Result<Self> {
+        let path = lock_path(app, name);
+
+        if let Some(parent) = path.parent() {
+            create_dir_all(parent)?;
+        }
+
+        loop {
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(mut file) => {
+                    // Only there to tell who holds it
+                    let _ = write!(file, "{}", std::process::id());
+                    return Ok(StoreLock { path });
+                }
+                Err(error) if error.kind() == ErrorKind::AlreadyExists => {
+                    if is_stale(&path) {
+                        let _ = fs::remove_file(&path);
+                        continue;
+                    }
+
+                    tokio::time::sleep(POLL).await;
+                }
+                Err(error) => {
+                    return Err(error)
+                        .with_context(|| format!("failed to lock {}", path.display()));
+                }
+            }
+        }
+    }
+}
+
+impl Drop for StoreLock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn waits_for_the_holder() {
+        let dir = std::env::temp_dir().join(format!("volt-store-lock-{}", std::process::id()));
+        let app = App {
+            current_dir: dir.clone(),
+            home_dir: dir.clone(),
+            node_modules_dir: dir.join("node_modules"),
+            volt_dir: dir.join("store"),
+            cache_dir: dir.join("cache"),
+            config_file: dir.join(".voltrc"),
+            lock_file_path: dir.join("volt.lock"),
+            args: vec![],
+            flags: vec![],
+            config: Default::default(),
+        };
+
+        let held = StoreLock::acquire(&app, "@scope/pkg").await.unwrap();
+
+        let waiting = tokio::time::timeout(
+            Duration::from_millis(200),
+            StoreLock::acquire(&app, "@scope/pkg"),
+        );
+        assert!(waiting.await.is_err());
+
+        drop(held);
+        StoreLock::acquire(&app, "@scope/pkg").await.unwrap();
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,272 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Install from a directory of packed tarballs.
+//!
+//! With `tarball-dir=<dir>` in `.voltrc`, packages are resolved and installed from the `.tgz`
+//! files of `<dir>` alone (`left-pad-1.3.0.tgz`, `@types-node-16.4.0.tgz`, named like the
+//! [offline mirror](crate::mirror) names them), never from the network. The directory is
+//! authoritative: a package or range it has no tarball for fails the install. Tarballs are
+//! locked as `tarball-dir:<file>`, so the lock file holds wherever the directory is checked out.
+
+use std::collections::HashMap;
+use std::fs::{read, read_dir};
+use std::io::Read;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use anyhow::{anyhow, Context, Result};
+use flate2::read::GzDecoder;
+use node_semver::Version;
+use serde_json::{json, Value};
+use tar::Archive;
+
+use crate::app::App;
+use crate::resolver;
+use crate::volt_api::{PackageSource, VoltResponse};
+
+/// How tarballs of the directory are locked, followed by their file name.
+pub const PREFIX: &str = "tarball-dir:";
+
+pub struct TarballDir {
+    pub dir: PathBuf,
+}
+
+impl TarballDir {
+    /// The directory configured for the project, if any.
+    pub fn from_app(app: &App) -> Option<Self> {
+        app.config.get("tarball-dir").map(|dir| TarballDir {
+            dir: app.current_dir.join(dir),
+        })
+    }
+
+    /// The file names of the tarballs of `name`, by version.
+    fn tarballs(&self, name: &str) -> Result<Vec<(String, String)>> {
+        let entries = read_dir(&self.dir)
+            .with_context(|| format!("failed to read {}", self.dir.display()))?;
+
+        Ok(entries
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let file = entry.file_name().to_string_lossy().to_string();
+                let (package, version) = decode_file_name(file.strip_suffix(".tgz")?)?;
+
+                if package == name {
+                    Some((version, file))
+                } else {
+                    None
+                }
+            })
+            .collect())
+    }
+
+    /// A packument of `name` made of the manifests of its tarballs, as registries serve it.
+    pub fn packument(&self, name: &str) -> Result<Value> {
+        let mut versions = serde_json::Map::new();
+
+        for (version, file) in self.tarballs(name)? {
+            let bytes = bytes::Bytes::from(read(self.dir.join(&file))?);
+
+            let mut manifest =
+                read_manifest(&bytes).with_context(|| format!("invalid tarball {}", file))?;
+
+            if manifest["name"] != name || manifest["version"] != version.as_str() {
+                return Err(anyhow!(
+                    "{} holds {}@{}, not {}@{}",
+                    file,
+                    manifest["name"],
+                    manifest["version"],
+                    name,
+                    version
+                ));
+            }
+
+            manifest["dist"] = json!({
+                "tarball": format!("{}{}", PREFIX, file),
+                "shasum": App::calc_hash(&bytes)?,
+            });
+
+            versions.insert(version, manifest);
+        }
+
+        let latest = versions
+            .keys()
+            .filter_map(|version| Version::parse(version).ok())
+            .max()
+            .ok_or_else(|| anyhow!("{} has no tarball in {}", name, self.dir.display()))?;
+
+        Ok(json!({
+            "name": name,
+            "dist-tags": { "latest": latest.to_string() },
+            "versions": versions,
+        }))
+    }
+
+    /// Resolve `spec` and its whole tree from the directory.
+    pub async fn resolve(&self, spec: &str) -> Result<VoltResponse> {
+        let (name, range) = crate::parse_package_spec(spec);
+        let root = (name, range.unwrap_or_else(|| String::from("latest")));
+
+        // Each package is read once, however many ranges ask for it
+        let packuments: Mutex<HashMap<String, Arc<Value>>> = Mutex::new(HashMap::new());
+
+        let (mut response, _) = resolver::walk(
+            root,
+            resolver::DEFAULT_CONCURRENCY,
+            &Default::default(),
+            None,
+            |name| {
+                let packuments = &packuments;
+
+                async move {
+                    if let Some(packument) = packuments.lock().unwrap().get(&name) {
+                        return Ok(packument.clone());
+                    }
+
+                    let packument = Arc::new(self.packument(&name)?);
+                    packuments.lock().unwrap().insert(name, packument.clone());
+
+                    Ok(packument)
+                }
+            },
+        )
+        .await?;
+
+        for data in response.versions.values_mut() {
+            for package in data.packages.values_mut() {
+                package.source = PackageSource::TarballDir;
+            }
+        }
+
+        Ok(response)
+    }
+
+    /// Read the tarball locked as `tarball-dir:<file>`.
+    pub fn read_tarball(&self, tarball: &str) -> Result<bytes::Bytes> {
+        let file = tarball.strip_prefix(PREFIX).unwrap_or(tarball);
+        let path = self.dir.join(file);
+
+        read(&path)
+            .map(bytes::Bytes::from)
+            .with_context(|| format!("{} is missing", path.display()))
+    }
+}
+
+/// Split a file name without its extension (`@types-node-16.4.0`) into the package name and
+/// version, at the first dash followed by a valid version.
+pub fn decode_file_name(stem: &str) -> Option<(String, String)> {
+    let (index, _) = stem
+        .match_indices('-')
+        .find(|(index, _)| Version::parse(&stem[index + 1..]).is_ok())?;
+
+    let name = &stem[..index];
+
+    let name = if name.starts_with('@') {
+        name.replacen('-', "/", 1)
+    } else {
+        name.to_string()
+    };
+
+    Some((name, stem[index + 1..].to_string()))
+}
+
+/// The package.json at the top of a tarball, in `package/` or whatever the top directory is.
+fn read_manifest(bytes: &[u8]) -> Result<Value> {
+    let mut archive = Archive::new(GzDecoder::new(bytes));
+
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.to_path_buf();
+
+        if path.components().count() == 2 && path.ends_with("package.json") {
+            let mut source = String::new();
+            entry.read_to_string(&mut source)?;
+
+            return Ok(serde_json::from_str(&source)?);
+        }
+    }
+
+    Err(anyhow!("no package.json"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use flate2::{write::GzEncoder, Compression};
+
+    #[test]
+    fn decodes_file_names() {
+        assert_eq!(
+            decode_file_name("left-pad-1.3.0"),
+            Some((String::from("left-pad"), String::from("1.3.0")))
+        );
+        assert_eq!(
+            decode_file_name("@types-node-16.4.0-beta.1"),
+            Some((String::from("@types/node"), String::from("16.4.0-beta.1")))
+        );
+        assert_eq!(decode_file_name("readme"), None);
+    }
+
+    #[tokio::test]
+    async fn resolves_from_the_directory() {
+        let dir = std::env::temp_dir().join(format!("volt-tarball-dir-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let pack = |name: &str, version: &str, dependencies: Value| {
+            let manifest =
+                json!({ "name": name, "version": version, "dependencies": dependencies });
+            let manifest = serde_json::to_vec(&manifest).unwrap();
+
+            let mut header = tar::Header::new_gnu();
+            header.set_size(manifest.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+
+            let mut builder = tar::Builder::new(GzEncoder::new(vec![], Compression::fast()));
+            builder
+                .append_data(&mut header, "package/package.json", &manifest[..])
+                .unwrap();
+
+            let file = format!("{}-{}.tgz", name, version);
+            std::fs::write(
+                dir.join(file),
+                builder.into_inner().unwrap().finish().unwrap(),
+            )
+            .unwrap();
+        };
+
+        pack("app", "1.0.0", json!({ "dep": "^1.0.0" }));
+        pack("dep", "1.1.0", json!({}));
+        pack("dep", "2.0.0", json!({}));
+
+        let tarball_dir = TarballDir { dir: dir.clone() };
+        let response = tarball_dir.resolve("app").await.unwrap();
+        let dep = &response.versions["1.0.0"].packages["dep"];
+
+        assert_eq!(dep.version, "1.1.0");
+        assert_eq!(dep.tarball, "tarball-dir:dep-1.1.0.tgz");
+        assert_eq!(dep.source, PackageSource::TarballDir);
+        assert_eq!(
+            App::calc_hash(&tarball_dir.read_tarball(&dep.tarball).unwrap()).unwrap(),
+            dep.sha1
+        );
+
+        assert!(tarball_dir.resolve("missing").await.is_err());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
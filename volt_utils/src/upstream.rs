@@ -0,0 +1,188 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Verify tarballs installed through a mirror against the upstream registry.
+//!
+//! A mirror serves both the tarballs and the checksums they are verified with, so a tampered
+//! mirror can serve tampered tarballs which pass. When `registry-mirrors` are configured, the
+//! sha1 of every tarball from a mirror is also compared with the `dist.shasum` the upstream
+//! registry (`upstream-registry`, the npm registry by default) publishes for that version, and a
+//! mismatch fails the install as a security error. `verify-upstream=false` turns the check off,
+//! `verify-upstream=true` turns it on for a `registry` other than upstream too.
+//!
+//! Published versions never change, so upstream checksums are kept in
+//! `<cache dir>/upstream-checksums.json` and each package is only looked up once. Packages of
+//! scoped registries and packages upstream doesn't know aren't checked.
+
+use std::collections::BTreeMap;
+use std::fs::{create_dir_all, read_to_string, write};
+use std::path::Path;
+use std::sync::Mutex;
+
+use anyhow::{anyhow, Result};
+use lazy_static::lazy_static;
+use serde_json::Value;
+
+use crate::app::App;
+use crate::network;
+use crate::registry::{self, DEFAULT_REGISTRY};
+use crate::registry_compat;
+use crate::registry_mirrors;
+use crate::reporter;
+use crate::volt_api::{PackageSource, VoltPackage};
+
+const DATABASE_FILE: &str = "upstream-checksums.json";
+
+lazy_static! {
+    /// `name@version` → sha1, loaded from the cache directory on first use.
+    static ref CHECKSUMS: Mutex<Option<BTreeMap<String, String>>> = Mutex::new(None);
+}
+
+/// The registry mirrors are checked against, without a trailing slash.
+pub fn upstream(app: &App) -> String {
+    app.config
+        .get("upstream-registry")
+        .unwrap_or(DEFAULT_REGISTRY)
+        .trim_end_matches('/')
+        .to_string()
+}
+
+/// Whether tarballs are checked against upstream, by default when mirrors are configured.
+pub fn is_enabled(app: &App) -> bool {
+    app.config
+        .get_bool("verify-upstream")
+        .unwrap_or_else(|| !registry_mirrors::mirrors(app).is_empty())
+}
+
+/// The sha1 of every version in an upstream packument, by `name@version`.
+pub fn checksums(name: &str, packument: &Value) -> BTreeMap<String, String> {
+    packument["versions"]
+        .as_object()
+        .into_iter()
+        .flatten()
+        .filter_map(|(version, manifest)| {
+            Some((
+                format!("{}@{}", name, version),
+                manifest["dist"]["shasum"].as_str()?.to_string(),
+            ))
+        })
+        .collect()
+}
+
+fn load(path: &Path) -> BTreeMap<String, String> {
+    read_to_string(path)
+        .ok()
+        .and_then(|data| serde_json::from_str(&data).ok())
+        .unwrap_or_default()
+}
+
+/// The upstream sha1 of `name@version`, from the database or the upstream registry.
+async fn upstream_sha1(app: &App, name: &str, version: &str) -> Result<Option<String>> {
+    let key = format!("{}@{}", name, version);
+    let path = app.cache_dir.join(DATABASE_FILE);
+
+    if let Some(sha1) = CHECKSUMS
+        .lock()
+        .unwrap()
+        .get_or_insert_with(|| load(&path))
+        .get(&key)
+    {
+        return Ok(Some(sha1.clone()));
+    }
+
+    let upstream = upstream(app);
+    let url = format!("{}/{}", upstream, name.replace('/', "%2f"));
+
+    let packument = match registry_compat::fetch_packument(app, &upstream, &url).await {
+        Ok(packument) => packument,
+        Err(error) if network::is_missing(&error) => return Ok(None),
+        Err(error) => return Err(error),
+    };
+
+    let mut database = CHECKSUMS.lock().unwrap();
+    let database = database.get_or_insert_with(|| load(&path));
+
+    database.extend(checksums(name, &packument));
+
+    if let Some(parent) = path.parent() {
+        create_dir_all(parent)?;
+    }
+
+    write(&path, serde_json::to_string_pretty(&*database)?)?;
+
+    Ok(database.get(&key).cloned())
+}
+
+/// Check the tarball of `package`, hashed `sha1`, against the checksum upstream publishes.
+pub async fn verify(app: &App, package: &VoltPackage, sha1: &str) -> Result<()> {
+    if package.source != PackageSource::Registry
+        || !is_enabled(app)
+        || registry::scope_url(app, &package.name).is_some()
+        || registry_mirrors::url(app).await.trim_end_matches('/') == upstream(app)
+    {
+        return Ok(());
+    }
+
+    let expected = match upstream_sha1(app, &package.name, &package.version).await {
+        Ok(Some(expected)) => expected,
+        Ok(None) => return Ok(()),
+        Err(error) => {
+            reporter::get().warn(&format!(
+                "could not check {}@{} against {}: {}",
+                package.name,
+                package.version,
+                upstream(app),
+                error
+            ));
+            return Ok(());
+        }
+    };
+
+    if expected != sha1 {
+        return Err(anyhow!(
+            "security: the tarball of {}@{} from the mirror has sha1 {}, but {} publishes {}. \
+             The mirror may be serving a tampered package",
+            package.name,
+            package.version,
+            sha1,
+            upstream(app),
+            expected
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_checksums_of_every_version() {
+        let packument = serde_json::json!({
+            "versions": {
+                "1.0.0": { "dist": { "shasum": "aaa" } },
+                "1.1.0": { "dist": { "shasum": "bbb" } },
+                "2.0.0": { "dist": {} }
+            }
+        });
+
+        let checksums = checksums("@scope/pkg", &packument);
+
+        assert_eq!(checksums.len(), 2);
+        assert_eq!(checksums["@scope/pkg@1.1.0"], "bbb");
+    }
+}
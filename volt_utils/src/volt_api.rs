@@ -14,9 +14,23 @@
     limitations under the License.
 */
 
+//! Flattened dependency trees served by the volt CDN.
+//!
+//! Packages the CDN doesn't have, or every package while it is down, are resolved from the npm
+//! registry into the same model instead, and each package records where it came from.
+//!
+//! `volt-api=<url>` in `.voltrc` points installs at a `volt_api_server` serving the trees of a
+//! private registry instead of the public CDN.
+
+use anyhow::{anyhow, Context, Result};
+use chttp::{self, ResponseExt};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+use crate::app::App;
+
+pub const DEFAULT_URL: &str = "http://volt-api.b-cdn.net";
+
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct VoltResponse {
     pub version: String,
@@ -29,14 +43,128 @@ pub struct VersionData {
     pub packages: HashMap<String, VoltPackage>,
 }
 
+/// Where the metadata of a package was resolved from.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PackageSource {
+    /// The volt CDN
+    #[default]
+    Volt,
+    /// An npm registry
+    Registry,
+    /// A directory of packed tarballs
+    #[serde(rename = "tarball-dir")]
+    TarballDir,
+    /// A directory of a git repository, packed by volt
+    Git,
+}
+
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct VoltPackage {
     pub name: String,
     pub version: String,
     pub tarball: String,
     pub sha1: String,
+    /// The subresource integrity of the tarball, when a lockfile of another package manager
+    /// recorded one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub integrity: Option<String>,
     #[serde(rename = "peerDependencies")]
     pub peer_dependencies: Vec<String>,
     pub dependencies: Option<Vec<String>>,
+    #[serde(rename = "optionalDependencies", default)]
+    pub optional_dependencies: Vec<String>,
     pub bin: Option<HashMap<String, String>>,
+    #[serde(default)]
+    pub os: Vec<String>,
+    #[serde(default)]
+    pub cpu: Vec<String>,
+    #[serde(default)]
+    pub libc: Vec<String>,
+    #[serde(default)]
+    pub source: PackageSource,
+    /// The deprecation message of the version, if it has one
+    #[serde(default)]
+    pub deprecated: Option<String>,
+    #[serde(default)]
+    pub engines: HashMap<String, String>,
+}
+
+/// Length of the start of the checksum which tells builds of a version apart in the store.
+const STORE_HASH_LENGTH: usize = 12;
+
+/// The entry of a package in the store, `<name>@<version>-<start of its sha1>`.
+///
+/// Projects share the store, so each version has an entry of its own, and so does each build of
+/// a version, from a git commit or a tarball directory, whose checksum differs. Files are shared
+/// between entries by their content anyway, see [dedup](crate::dedup).
+///
+/// The sha1 names entries rather than the subresource integrity because every lock file entry has
+/// one, packages resolved through the volt API have no other, and the integrity is only kept from
+/// the lockfiles of other package managers. Tarballs are checked against the full checksum when
+/// they are extracted, the name only has to tell builds of one version apart, which the start of
+/// the sha1 does while keeping the paths of the virtual store short enough for Windows.
+pub fn store_name(name: &str, version: &str, sha1: &str) -> String {
+    let hash = &sha1[..sha1.len().min(STORE_HASH_LENGTH)];
+
+    if hash.is_empty() {
+        format!("{}@{}", name, version)
+    } else {
+        format!("{}@{}-{}", name, version, hash)
+    }
+}
+
+impl VoltPackage {
+    /// The entry of the package in the store, see [`store_name`].
+    pub fn store_name(&self) -> String {
+        store_name(&self.name, &self.version, &self.sha1)
+    }
+}
+
+/// The url of the volt API, without a trailing slash.
+pub fn url(app: &App) -> String {
+    app.config
+        .get("volt-api")
+        .unwrap_or(DEFAULT_URL)
+        .trim_end_matches('/')
+        .to_string()
+}
+
+/// Get the tree of `name` from the volt API.
+pub async fn fetch(app: &App, name: &str) -> Result<VoltResponse> {
+    let url = url(app);
+
+    let mut response = chttp::get_async(format!("{}/{}.json", url, name))
+        .await
+        .inspect_err(|_| crate::network::record_error(&url))
+        .with_context(|| format!("failed to reach {}", url))?;
+
+    crate::network::record_response(&url, response.status().as_u16());
+
+    if !response.status().is_success() {
+        return Err(anyhow!("{} is not on {}", name, url));
+    }
+
+    let text = response.text_async().await?;
+    crate::network::record_bytes(&url, text.len());
+
+    serde_json::from_str(&text).with_context(|| format!("invalid response for {}", name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_store_entries_by_version_and_checksum() {
+        assert_eq!(
+            store_name(
+                "@types/node",
+                "16.0.0",
+                "a2c8e4f1b0d9c7e6f5a4b3c2d1e0f9a8b7c6d5e4"
+            ),
+            "@types/node@16.0.0-a2c8e4f1b0d9"
+        );
+        assert_eq!(store_name("left-pad", "1.3.0", ""), "left-pad@1.3.0");
+    }
 }
@@ -0,0 +1,198 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Run the version of volt a project pins.
+//!
+//! A project pins volt with `"packageManager": "volt@1.2.0"` in its package.json (or the one of
+//! its workspace root), or with `volt-version=1.2.0` in `.voltrc`. When another version is
+//! running, the pinned one is downloaded once to `<cache dir>/versions/<version>` and the
+//! command runs there instead, so everyone working on the project gets the same behavior.
+//!
+//! Binaries come from the GitHub releases of volt, `volt-download-url` points elsewhere with
+//! `{version}`, `{os}`, `{arch}` and `{ext}` placeholders. `switch-volt-version=false` keeps the
+//! running volt.
+
+use std::fs::{create_dir_all, read_to_string, rename, write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use anyhow::{anyhow, Context, Result};
+use colored::Colorize;
+use node_semver::Version;
+use serde_json::Value;
+
+use crate::app::App;
+use crate::network;
+use crate::workspace::Workspace;
+
+/// Set for the pinned volt, which runs the command itself.
+pub const SWITCHED_ENV: &str = "VOLT_VERSION_SWITCHED";
+
+const DOWNLOAD_URL: &str =
+    "https://github.com/voltpkg/volt/releases/download/v{version}/volt-{os}-{arch}{ext}";
+
+/// The version of `"packageManager": "volt@1.2.0+sha256.abc"`, none for other package managers.
+pub fn from_package_manager(field: &str) -> Option<String> {
+    let version = field.strip_prefix("volt@")?;
+
+    Some(version.split('+').next().unwrap_or(version).to_string())
+}
+
+fn package_manager(dir: &Path) -> Option<String> {
+    let data: Value = serde_json::from_str(&read_to_string(dir.join("package.json")).ok()?).ok()?;
+
+    from_package_manager(data["packageManager"].as_str()?)
+}
+
+/// The version of volt pinned for the current directory, and where it is pinned.
+pub fn pinned(app: &App) -> Option<(String, String)> {
+    if let Some(version) = app.config.get("volt-version") {
+        return Some((version.to_string(), String::from(".voltrc")));
+    }
+
+    if let Some(version) = package_manager(&app.current_dir) {
+        return Some((version, String::from("package.json")));
+    }
+
+    let workspace = Workspace::find(&app.current_dir)?;
+    let version = package_manager(&workspace.root)?;
+
+    Some((
+        version,
+        workspace.root.join("package.json").display().to_string(),
+    ))
+}
+
+/// Where the binary of `version` is kept.
+fn binary_path(app: &App, version: &str) -> PathBuf {
+    app.cache_dir
+        .join("versions")
+        .join(version)
+        .join(format!("volt{}", std::env::consts::EXE_SUFFIX))
+}
+
+fn download_url(app: &App, version: &str) -> String {
+    app.config
+        .get("volt-download-url")
+        .unwrap_or(DOWNLOAD_URL)
+        .replace("{version}", version)
+        .replace("{os}", std::env::consts::OS)
+        .replace("{arch}", std::env::consts::ARCH)
+        .replace("{ext}", std::env::consts::EXE_SUFFIX)
+}
+
+/// The binary of `version`, downloaded unless it is already there.
+async fn binary(app: &App, version: &str) -> Result<PathBuf> {
+    let path = binary_path(app, version);
+
+    if path.exists() {
+        return Ok(path);
+    }
+
+    let url = download_url(app, version);
+
+    // On stderr, the output of the command is still to come
+    eprintln!(
+        "{} Downloading volt {} pinned by the project",
+        "info".yellow(),
+        version
+    );
+
+    let bytes = async {
+        let response = network::send(&url, reqwest::Client::new().get(&url)).await?;
+        network::read(&url, response).await
+    }
+    .await
+    .with_context(|| format!("failed to download volt {} from {}", version, url))?;
+
+    // Written next to its place first, so a killed download is never run
+    let partial = path.with_extension("partial");
+
+    if let Some(parent) = path.parent() {
+        create_dir_all(parent)?;
+    }
+
+    write(&partial, &bytes)?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&partial, std::fs::Permissions::from_mode(0o755))?;
+    }
+
+    rename(&partial, &path)?;
+
+    Ok(path)
+}
+
+/// Run the command with the version of volt the project pins, if it isn't `current`.
+///
+/// Returns the exit code of the pinned volt, none when the running volt should go on.
+pub async fn switch(app: &App, current: &str) -> Result<Option<i32>> {
+    if std::env::var_os(SWITCHED_ENV).is_some()
+        || app.config.get_bool("switch-volt-version") == Some(false)
+    {
+        return Ok(None);
+    }
+
+    let (version, source) = match pinned(app) {
+        Some(pinned) => pinned,
+        None => return Ok(None),
+    };
+
+    // The version ends up in a path, so it has to be one
+    if Version::parse(&version).is_err() {
+        return Err(anyhow!(
+            "{} pins volt {}, which is not an exact version",
+            source,
+            version
+        ));
+    }
+
+    if version == current {
+        return Ok(None);
+    }
+
+    let binary = binary(app, &version).await.with_context(|| {
+        format!(
+            "{} pins volt {}, set switch-volt-version=false in .voltrc to use volt {}",
+            source, version, current
+        )
+    })?;
+
+    let status = Command::new(&binary)
+        .args(std::env::args_os().skip(1))
+        .env(SWITCHED_ENV, "1")
+        .status()
+        .with_context(|| format!("failed to run {}", binary.display()))?;
+
+    Ok(Some(status.code().unwrap_or(1)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_volt_from_package_manager() {
+        assert_eq!(
+            from_package_manager("volt@1.2.0+sha256.abcdef").as_deref(),
+            Some("1.2.0")
+        );
+        assert_eq!(from_package_manager("volt@1.3.0").as_deref(), Some("1.3.0"));
+        assert_eq!(from_package_manager("pnpm@8.6.0"), None);
+    }
+}
@@ -0,0 +1,197 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Warnings gathered while installing, shown together once the install is done.
+//!
+//! Deprecated packages, peer dependency problems, engine mismatches and conflicting binaries are
+//! collected as they are found and printed as one summary grouped by kind (or as JSON with
+//! `--json`), instead of between progress bars where they scroll away. With `strict-deprecations=true` in `.voltrc`,
+//! deprecated packages fail the install instead.
+
+use std::collections::BTreeMap;
+
+use colored::Colorize;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Kind {
+    Deprecated,
+    Peer,
+    Engine,
+    Platform,
+    Bin,
+    Other,
+}
+
+impl Kind {
+    /// The heading of the warnings of this kind in the summary.
+    pub fn title(&self) -> &'static str {
+        match self {
+            Kind::Deprecated => "deprecated packages",
+            Kind::Peer => "peer dependencies",
+            Kind::Engine => "engines",
+            Kind::Platform => "platform",
+            Kind::Bin => "binaries",
+            Kind::Other => "other",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Warning {
+    pub kind: Kind,
+    /// `name@version`, or the name alone, of the package the warning is about
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub package: Option<String>,
+    pub message: String,
+}
+
+/// The warnings of an install, in the order they were found.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Warnings {
+    warnings: Vec<Warning>,
+}
+
+impl Warnings {
+    pub fn new() -> Self {
+        Warnings::default()
+    }
+
+    /// Add a warning, unless the same one was already added.
+    pub fn add(&mut self, kind: Kind, package: Option<String>, message: String) {
+        let warning = Warning {
+            kind,
+            package,
+            message,
+        };
+
+        if !self.warnings.contains(&warning) {
+            self.warnings.push(warning);
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.warnings.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.warnings.len()
+    }
+
+    /// The warnings of each kind, kinds in a fixed order.
+    pub fn grouped(&self) -> BTreeMap<Kind, Vec<&Warning>> {
+        let mut groups: BTreeMap<Kind, Vec<&Warning>> = BTreeMap::new();
+
+        for warning in self.warnings.iter() {
+            groups.entry(warning.kind).or_default().push(warning);
+        }
+
+        groups
+    }
+
+    /// The warnings under a heading per kind.
+    pub fn summary(&self) -> String {
+        let mut summary = format!(
+            "{} {}",
+            "warn".yellow(),
+            if self.len() == 1 {
+                String::from("1 warning")
+            } else {
+                format!("{} warnings", self.len())
+            }
+        );
+
+        for (kind, warnings) in self.grouped() {
+            summary.push_str(&format!("\n  {}", kind.title().bright_yellow()));
+
+            for warning in warnings {
+                match &warning.package {
+                    Some(package) => summary.push_str(&format!(
+                        "\n    {} {}",
+                        package.bright_cyan(),
+                        warning.message
+                    )),
+                    None => summary.push_str(&format!("\n    {}", warning.message)),
+                }
+            }
+        }
+
+        summary
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({ "warnings": self.warnings })
+    }
+
+    /// Print the summary if there are warnings, or the JSON if `json` is set.
+    pub fn print(&self, json: bool) {
+        if json {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&self.to_json()).unwrap_or_default()
+            );
+        } else if !self.is_empty() {
+            println!("{}", self.summary());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn groups_warnings_by_kind() {
+        colored::control::set_override(false);
+
+        let mut warnings = Warnings::new();
+        warnings.add(
+            Kind::Peer,
+            Some(String::from("react-dom")),
+            String::from("has react@^18 missing"),
+        );
+        warnings.add(
+            Kind::Deprecated,
+            Some(String::from("request@2.88.2")),
+            String::from("request has been deprecated"),
+        );
+        warnings.add(
+            Kind::Deprecated,
+            Some(String::from("request@2.88.2")),
+            String::from("request has been deprecated"),
+        );
+
+        assert_eq!(
+            warnings.summary(),
+            "warn 2 warnings
+  deprecated packages
+    request@2.88.2 request has been deprecated
+  peer dependencies
+    react-dom has react@^18 missing"
+        );
+
+        assert_eq!(
+            warnings.to_json()["warnings"][0],
+            json!({
+                "kind": "peer",
+                "package": "react-dom",
+                "message": "has react@^18 missing",
+            })
+        );
+    }
+}
@@ -0,0 +1,297 @@
+/*
+    Copyright 2021 Volt Contributors
+
+    Licensed under the Apache License, Version 2.0 (the "License");
+    you may not use this file except in compliance with the License.
+    You may obtain a copy of the License at
+
+        http://www.apache.org/licenses/LICENSE-2.0
+
+    Unless required by applicable law or agreed to in writing, software
+    distributed under the License is distributed on an "AS IS" BASIS,
+    WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+    See the License for the specific language governing permissions and
+    limitations under the License.
+*/
+
+//! Monorepo workspaces declared through the `workspaces` field of the root package.json.
+//!
+//! The root package.json can also hold a `catalog` of dependency ranges, and named ones in
+//! `catalogs`. Members depend on `"react": "catalog:"` (or `"catalog:<name>"`) to install the
+//! range of the catalog, so the whole monorepo upgrades a dependency in one place.
+
+use std::collections::BTreeMap;
+use std::fs::{read_to_string, write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Result};
+use serde_json::Value;
+use walkdir::WalkDir;
+
+use crate::package::JsonFormat;
+
+#[derive(Debug, Clone)]
+pub struct Workspace {
+    /// Directory containing the root package.json
+    pub root: PathBuf,
+    /// Member globs relative to the root (`packages/*`)
+    pub patterns: Vec<String>,
+    /// Catalog name → dependency → range, `catalog` is the one named `default`
+    pub catalogs: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+/// Prefix of the ranges taken from a catalog of the workspace (`catalog:`, `catalog:react17`).
+pub const CATALOG_PREFIX: &str = "catalog:";
+
+const DEFAULT_CATALOG: &str = "default";
+
+/// The ranges of a `catalog` field, by dependency.
+fn catalog(value: &Value) -> Option<BTreeMap<String, String>> {
+    Some(
+        value
+            .as_object()?
+            .iter()
+            .filter_map(|(name, range)| Some((name.clone(), range.as_str()?.to_string())))
+            .collect(),
+    )
+}
+
+impl Workspace {
+    /// Load the workspace declared in `root/package.json`, if it declares one.
+    pub fn load(root: &Path) -> Option<Self> {
+        let data: Value =
+            serde_json::from_str(&read_to_string(root.join("package.json")).ok()?).ok()?;
+
+        // "workspaces": [...] or "workspaces": { "packages": [...] }
+        let patterns = match &data["workspaces"] {
+            Value::Array(patterns) => patterns,
+            Value::Object(config) => config.get("packages")?.as_array()?,
+            _ => return None,
+        };
+
+        let mut catalogs: BTreeMap<String, BTreeMap<String, String>> = data["catalogs"]
+            .as_object()
+            .into_iter()
+            .flatten()
+            .filter_map(|(name, ranges)| Some((name.clone(), catalog(ranges)?)))
+            .collect();
+
+        if let Some(ranges) = catalog(&data["catalog"]) {
+            catalogs.insert(String::from(DEFAULT_CATALOG), ranges);
+        }
+
+        Some(Workspace {
+            root: root.to_path_buf(),
+            patterns: patterns
+                .iter()
+                .filter_map(|pattern| pattern.as_str().map(String::from))
+                .collect(),
+            catalogs,
+        })
+    }
+
+    /// The range `reference` (`catalog:` or `catalog:<name>`) stands for, for the dependency
+    /// `name`.
+    pub fn catalog_range(&self, name: &str, reference: &str) -> Result<String> {
+        let catalog = match reference.trim_start_matches(CATALOG_PREFIX) {
+            "" => DEFAULT_CATALOG,
+            catalog => catalog,
+        };
+
+        let ranges = self.catalogs.get(catalog).ok_or_else(|| {
+            anyhow!(
+                "{} has no catalog named {}",
+                self.root.join("package.json").display(),
+                catalog
+            )
+        })?;
+
+        ranges.get(name).cloned().ok_or_else(|| {
+            anyhow!(
+                "{} is missing from the {} catalog of {}",
+                name,
+                catalog,
+                self.root.join("package.json").display()
+            )
+        })
+    }
+
+    /// Find the closest workspace root at or above `dir`.
+    pub fn find(dir: &Path) -> Option<Self> {
+        dir.ancestors().find_map(Self::load)
+    }
+
+    /// Check if `dir` is matched by one of the member globs.
+    pub fn includes(&self, dir: &Path) -> bool {
+        match relative_path(&self.root, dir) {
+            Some(relative) => self
+                .patterns
+                .iter()
+                .any(|pattern| glob_match(pattern, &relative)),
+            None => false,
+        }
+    }
+
+    /// Get the directories of all members which contain a package.json.
+    pub fn members(&self) -> Vec<PathBuf> {
+        let mut members: Vec<PathBuf> = WalkDir::new(&self.root)
+            .min_depth(1)
+            .into_iter()
+            .filter_entry(|entry| entry.file_name() != "node_modules")
+            .filter_map(Result::ok)
+            .filter(|entry| entry.file_type().is_dir())
+            .map(|entry| entry.into_path())
+            .filter(|dir| dir.join("package.json").exists() && self.includes(dir))
+            .collect();
+
+        members.sort();
+        members
+    }
+
+    /// Register `dir` as a member by adding its relative path to the `workspaces` field.
+    pub fn add_member(&mut self, dir: &Path) -> Result<()> {
+        let relative = relative_path(&self.root, dir)
+            .ok_or_else(|| anyhow!("{} is not inside the workspace", dir.display()))?;
+
+        let path = self.root.join("package.json");
+        let source = read_to_string(&path)?;
+        let mut data: Value = serde_json::from_str(&source)?;
+
+        let patterns = match &mut data["workspaces"] {
+            Value::Object(config) => config.get_mut("packages"),
+            patterns => Some(patterns),
+        }
+        .and_then(Value::as_array_mut)
+        .ok_or_else(|| anyhow!("invalid workspaces field in {}", path.display()))?;
+
+        patterns.push(Value::String(relative.clone()));
+        self.patterns.push(relative);
+
+        // The rest of the file keeps its formatting
+        write(&path, JsonFormat::detect(&source).write(&data))?;
+
+        Ok(())
+    }
+}
+
+/// The range to install for the dependency `name` of the project in `dir`: `range` itself, or
+/// the range of the workspace catalog it references.
+pub fn resolve_range(dir: &Path, name: &str, range: &str) -> Result<String> {
+    if !range.starts_with(CATALOG_PREFIX) {
+        return Ok(range.to_string());
+    }
+
+    Workspace::find(dir)
+        .ok_or_else(|| anyhow!("{} uses {}, but there is no workspace", name, range))?
+        .catalog_range(name, range)
+}
+
+/// Get `dir` relative to `root` with `/` separators.
+pub fn relative_path(root: &Path, dir: &Path) -> Option<String> {
+    let relative = dir.strip_prefix(root).ok()?;
+
+    Some(
+        relative
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/"),
+    )
+}
+
+/// Match a relative path against a workspace glob (`packages/*`, `apps/**`, `tools/cli-*`).
+pub fn glob_match(pattern: &str, path: &str) -> bool {
+    let pattern: Vec<&str> = pattern
+        .trim_start_matches("./")
+        .trim_end_matches('/')
+        .split('/')
+        .collect();
+    let path: Vec<&str> = path.split('/').collect();
+
+    match_segments(&pattern, &path)
+}
+
+fn match_segments(pattern: &[&str], path: &[&str]) -> bool {
+    match pattern {
+        [] => path.is_empty(),
+        ["**", rest @ ..] => (0..=path.len()).any(|i| match_segments(rest, &path[i..])),
+        [segment, rest @ ..] => match path {
+            [name, path_rest @ ..] => {
+                match_segment(segment.as_bytes(), name.as_bytes())
+                    && match_segments(rest, path_rest)
+            }
+            [] => false,
+        },
+    }
+}
+
+/// Match `name` against `pattern`, where `*` stands for any characters.
+pub(crate) fn match_segment(pattern: &[u8], name: &[u8]) -> bool {
+    match pattern {
+        [] => name.is_empty(),
+        [b'*', rest @ ..] => (0..=name.len()).any(|i| match_segment(rest, &name[i..])),
+        [c, rest @ ..] => match name {
+            [n, name_rest @ ..] if n == c => match_segment(rest, name_rest),
+            _ => false,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_catalog_ranges() {
+        let root = std::env::temp_dir().join(format!("volt-catalog-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("packages").join("app")).unwrap();
+        write(
+            root.join("package.json"),
+            r#"{
+                "workspaces": ["packages/*"],
+                "catalog": { "react": "^18.2.0" },
+                "catalogs": { "legacy": { "react": "^17.0.2" } }
+            }"#,
+        )
+        .unwrap();
+
+        let member = root.join("packages").join("app");
+
+        assert_eq!(
+            resolve_range(&member, "react", "catalog:").unwrap(),
+            "^18.2.0"
+        );
+        assert_eq!(
+            resolve_range(&member, "react", "catalog:legacy").unwrap(),
+            "^17.0.2"
+        );
+        assert_eq!(resolve_range(&member, "react", "^16").unwrap(), "^16");
+        assert!(resolve_range(&member, "vue", "catalog:").is_err());
+        assert!(resolve_range(&member, "react", "catalog:next").is_err());
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn adds_members_keeping_the_formatting() {
+        let root = std::env::temp_dir().join(format!("volt-add-member-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("tools").join("cli")).unwrap();
+
+        let source =
+            "{\r\n\t\"name\": \"root\",\r\n\t\"workspaces\": [\r\n\t\t\"packages/*\"\r\n\t]\r\n}";
+        write(root.join("package.json"), source).unwrap();
+
+        let mut workspace = Workspace::find(&root).unwrap();
+        workspace
+            .add_member(&root.join("tools").join("cli"))
+            .unwrap();
+
+        assert_eq!(
+            read_to_string(root.join("package.json")).unwrap(),
+            "{\r\n\t\"name\": \"root\",\r\n\t\"workspaces\": [\r\n\t\t\"packages/*\",\r\n\t\t\"tools/cli\"\r\n\t]\r\n}"
+        );
+        assert!(workspace.includes(&root.join("tools").join("cli")));
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,96 @@
+//! GitHub Packages support, against packuments recorded from `npm.pkg.github.com`.
+
+use std::env;
+use std::fs::read_to_string;
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+use volt_utils::{app::App, config::Config, github_packages, registry};
+
+fn fixture(name: &str) -> Value {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/github_packages")
+        .join(format!("{}.json", name));
+
+    serde_json::from_str(&read_to_string(path).unwrap()).unwrap()
+}
+
+fn app(settings: &[(&str, &str)]) -> App {
+    let mut config = Config::default();
+
+    for (key, value) in settings {
+        config.set(key, value);
+    }
+
+    App {
+        current_dir: PathBuf::new(),
+        home_dir: PathBuf::new(),
+        node_modules_dir: PathBuf::new(),
+        volt_dir: PathBuf::new(),
+        cache_dir: PathBuf::new(),
+        config_file: PathBuf::new(),
+        lock_file_path: PathBuf::new(),
+        args: vec![],
+        flags: vec![],
+        config,
+    }
+}
+
+#[test]
+fn tags_packuments_published_without_one() {
+    let mut widgets = fixture("widgets");
+    assert!(widgets["dist-tags"].is_null());
+
+    github_packages::normalize(&mut widgets);
+    assert_eq!(widgets["dist-tags"]["latest"], "1.2.0");
+
+    let mut tokens = fixture("tokens");
+    github_packages::normalize(&mut tokens);
+    assert_eq!(tokens, fixture("tokens"));
+}
+
+#[test]
+fn routes_scopes_to_their_registry() {
+    let app = app(&[("@acme:registry", "https://npm.pkg.github.com/")]);
+
+    assert_eq!(
+        registry::url_for(&app, "@acme/widgets"),
+        "https://npm.pkg.github.com"
+    );
+    assert_eq!(
+        registry::url_for(&app, "@other/widgets"),
+        "https://registry.npmjs.org"
+    );
+    assert_eq!(
+        registry::url_for(&app, "react"),
+        "https://registry.npmjs.org"
+    );
+    assert_eq!(
+        github_packages::accept(&registry::url_for(&app, "@acme/widgets")),
+        "application/json"
+    );
+}
+
+#[test]
+fn falls_back_to_github_token() {
+    env::set_var(github_packages::TOKEN_VARIABLE, "ghp_from_env");
+
+    let github = "https://npm.pkg.github.com";
+
+    assert_eq!(
+        registry::auth_token(&app(&[]), github).as_deref(),
+        Some("ghp_from_env")
+    );
+    assert_eq!(
+        registry::auth_token(
+            &app(&[("//npm.pkg.github.com/:_authToken", "ghp_configured")]),
+            github
+        )
+        .as_deref(),
+        Some("ghp_configured")
+    );
+    assert_eq!(
+        registry::auth_token(&app(&[]), "https://registry.npmjs.org"),
+        None
+    );
+}
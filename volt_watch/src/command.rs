@@ -21,11 +21,10 @@ use std::sync::Arc;
 
 use anyhow::Result;
 use async_trait::async_trait;
-use colored::Colorize;
-use indicatif::ProgressBar;
-use indicatif::ProgressStyle;
-use volt_core::command::Command;
+use volt_core::{command::Command, help::Help};
 use volt_utils::app::App;
+use volt_utils::reporter::Progress;
+use volt_utils::reporter;
 use walkdir::WalkDir;
 
 use syntect::easy::HighlightLines;
@@ -33,17 +32,10 @@ use syntect::parsing::SyntaxSet;
 use syntect::highlighting::{ThemeSet, Style};
 use syntect::util::{as_24_bit_terminal_escaped, LinesWithEndings};
 
-const PROGRESS_CHARS: &str = "=> ";
 pub struct Watch {}
 
-fn src_folder_exists() -> bool {
-    for f in read_dir(".").unwrap() {
-        let f = f.unwrap();
-        if f.path().is_dir() && format!("{}", f.path().display()).as_str() == r".\src" {
-            return true;
-        }
-    }
-    false
+fn src_folder_exists(dir: &Path) -> bool {
+    dir.join("src").is_dir()
 }
 
 fn get_top_elements(elements: &[String]) -> Vec<String> {
@@ -61,8 +53,11 @@ fn get_top_elements(elements: &[String]) -> Vec<String> {
 
 #[async_trait]
 impl Command for Watch {
-    fn help() -> String {
-        todo!()
+    fn help() -> Help {
+        Help::new(
+            "watch",
+            "Scan the JavaScript and TypeScript files of src for the packages they import.",
+        )
     }
 
     /// Execute the `volt watch` command
@@ -78,16 +73,16 @@ impl Command for Watch {
     /// ```
     /// ## Returns
     /// * `Result<()>`
-    async fn exec(_app: Arc<App>) -> Result<()> {
+    async fn exec(app: Arc<App>) -> Result<()> {
         // Set current dir
-        let mut current_dir = std::env::current_dir()?;
+        let mut current_dir = app.current_dir.clone();
 
         // Set list for all JS files
         let mut files: Vec<String> = vec![];
 
         // Scan for all JS files
         // Code must be in src folder
-        if !current_dir.ends_with("src") && src_folder_exists() {
+        if !current_dir.ends_with("src") && src_folder_exists(&current_dir) {
             current_dir = current_dir.join("src");
         }
 
@@ -105,16 +100,7 @@ impl Command for Watch {
         }
 
         if files.len() > 0 {
-            let progress_bar = ProgressBar::new(files.len() as u64);
-
-            progress_bar.set_style(
-                ProgressStyle::default_bar()
-                    .progress_chars(PROGRESS_CHARS)
-                    .template(&format!(
-                        "{} [{{bar:20.magenta/blue}}] {{pos}} / {{len}} {{msg:.yellow}}",
-                        "Scanning Code".bright_cyan()
-                    )),
-            );
+            let progress = Progress::start("Scanning Code", files.len() as u64);
 
             let mut files_message_vec = files.clone();
             let mut modules: Vec<String> = vec![];
@@ -127,7 +113,7 @@ impl Command for Watch {
                 file_names = get_top_elements(&files_message_vec.as_slice());
 
                 let message = file_names.join(", ");
-                progress_bar.set_message(message);
+                progress.message(&message);
 
                 let mut syntax = Syntax::default();
 
@@ -141,7 +127,7 @@ impl Command for Watch {
                 let errors = res.errors();
 
                 if errors != [] {
-                    progress_bar.abandon();
+                    progress.finish();
                     for err in errors {
                         // let file_name = Path::new(&f).file_name().unwrap().to_str().unwrap();
                         // let code = &err.code.as_ref().unwrap();
@@ -157,7 +143,7 @@ impl Command for Watch {
                         for line in LinesWithEndings::from(s) { // LinesWithEndings enables use of newlines mode
                             let ranges: Vec<(Style, &str)> = h.highlight(line, &ps);
                             let escaped = as_24_bit_terminal_escaped(&ranges[..], false);
-                            println!("{}", escaped);
+                            reporter::get().output(&escaped.to_string());
                         }
                         std::process::exit(0);
                     }
@@ -187,11 +173,11 @@ impl Command for Watch {
                 }
 
                 files_message_vec.remove(0);
-                progress_bar.inc(1);
+                progress.inc(1);
             }
-            progress_bar.finish_with_message("");
+            progress.finish();
 
-            let node_modules = read_dir("node_modules").unwrap();
+            let node_modules = read_dir(&app.node_modules_dir).unwrap();
             let mut availiable_modules: Vec<String> = vec![];
 
             for entry in node_modules {
@@ -0,0 +1,77 @@
+/*
+Copyright 2021 Volt Contributors
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+    http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+//! Show the username of the registry auth token.
+
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use colored::Colorize;
+use volt_core::{command::Command, help::Help};
+use volt_utils::{app::App, registry, reporter};
+
+/// Struct implementation for the `whoami` command.
+pub struct Whoami;
+
+#[async_trait]
+impl Command for Whoami {
+    /// Display a help menu for the `volt whoami` command.
+    fn help() -> Help {
+        Help::new(
+            "whoami",
+            "Show the username the registry auth token belongs to.",
+        )
+        .usage("[flags]")
+        .flag(
+            &["--registry=<url>"],
+            "Ask this registry instead of the configured one.",
+        )
+        .verbose()
+    }
+
+    /// Execute the `volt whoami` command
+    ///
+    /// Prints the username of the auth token configured for the registry.
+    /// ## Arguments
+    /// * `app` - Instance of the command (`Arc<App>`)
+    /// ## Examples
+    /// ```ignore
+    /// // .exec() is an async call so you need to await it
+    /// Whoami.exec(app).await;
+    /// ```
+    /// ## Returns
+    /// * `Result<()>`
+    async fn exec(app: Arc<App>) -> Result<()> {
+        let registry = app
+            .flag_value(&["--registry"])
+            .map(|registry| registry.trim_end_matches('/').to_string())
+            .unwrap_or_else(|| registry::url(&app));
+
+        let token = registry::auth_token(&app, &registry).ok_or_else(|| {
+            anyhow!(
+                "not logged in to {}, add {} to .voltrc",
+                registry,
+                registry::scoped_key(&registry, "_authToken")
+            )
+        })?;
+
+        if app.has_flag(&["-v", "--verbose"]) {
+            reporter::get().info("Registry", &registry.bright_cyan().to_string());
+        }
+
+        reporter::get().output(&registry::whoami(&registry, &token).await?.to_string());
+
+        Ok(())
+    }
+}
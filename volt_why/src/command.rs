@@ -0,0 +1,272 @@
+/*
+Copyright 2021 Volt Contributors
+Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+    http://www.apache.org/licenses/LICENSE-2.0
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+*/
+
+//! Explain why a package is installed.
+
+use std::process::exit;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use colored::Colorize;
+use serde::Serialize;
+use volt_core::{
+    command::Command,
+    help::Help,
+    model::{
+        lock_file::LockFile,
+        lock_graph::{Dependent, LockGraph, Node},
+    },
+};
+use volt_utils::{app::App, package::PackageJson, reporter};
+
+/// Chains listed before the rest are summarized.
+const PATH_LIMIT: usize = 50;
+
+/// A dependency of package.json, where chains start.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Root {
+    field: &'static str,
+    name: String,
+    range: String,
+}
+
+#[derive(Serialize)]
+struct Chains {
+    name: String,
+    versions: Vec<String>,
+    /// Each chain starts with a dependency of package.json and ends with the package
+    paths: Vec<Vec<Node>>,
+}
+
+#[derive(Serialize)]
+struct Reverse {
+    name: String,
+    versions: Vec<String>,
+    /// The fields of package.json which ask for the package itself
+    direct: Vec<Root>,
+    dependents: Vec<Dependent>,
+}
+
+fn roots(package_json: &PackageJson) -> Vec<Root> {
+    let fields = [
+        ("dependencies", &package_json.dependencies),
+        ("devDependencies", &package_json.dev_dependencies),
+        ("optionalDependencies", &package_json.optional_dependencies),
+    ];
+
+    let mut roots: Vec<Root> = fields
+        .iter()
+        .flat_map(|(field, dependencies)| {
+            dependencies.iter().map(move |(name, range)| Root {
+                field,
+                name: name.clone(),
+                range: range.clone(),
+            })
+        })
+        .collect();
+
+    roots.sort_by(|a, b| a.name.cmp(&b.name));
+    roots
+}
+
+fn node_label(node: &Node) -> String {
+    format!("{}@{}", node.name, node.version)
+}
+
+fn print_paths(why: &Chains, roots: &[Root]) {
+    reporter::get().output(&format!(
+        "{} {}",
+        why.name.bright_cyan().bold(),
+        why.versions.join(", ").dimmed()
+    ));
+
+    for path in why.paths.iter() {
+        let root = path
+            .first()
+            .and_then(|first| roots.iter().find(|root| root.name == first.name));
+
+        let chain: Vec<String> = path.iter().map(node_label).collect();
+
+        match root {
+            Some(root) if path.len() == 1 => reporter::get().output(&format!(
+                "  {} ({} {})",
+                "direct dependency".green(),
+                root.field,
+                root.range
+            )),
+            Some(root) => reporter::get().output(&format!(
+                "  {} ({} {})",
+                chain.join(&format!(" {} ", "→".dimmed())),
+                root.field,
+                root.range
+            )),
+            None => reporter::get().output(&format!("  {}", chain.join(" → "))),
+        }
+    }
+
+    if why.paths.len() >= PATH_LIMIT {
+        reporter::get().output(&format!(
+            "  … only the first {} chains are listed",
+            PATH_LIMIT
+        ));
+    }
+}
+
+fn print_reverse(reverse: &Reverse) {
+    reporter::get().output(&format!(
+        "{} {} is required by:",
+        reverse.name.bright_cyan().bold(),
+        reverse.versions.join(", ").dimmed()
+    ));
+
+    for root in reverse.direct.iter() {
+        reporter::get().output(&format!(
+            "  {} ({} {})",
+            "package.json".green(),
+            root.field,
+            root.range
+        ));
+    }
+
+    for dependent in reverse.dependents.iter() {
+        let range = if dependent.range.is_empty() {
+            String::from("unknown range")
+        } else {
+            dependent.range.clone()
+        };
+
+        reporter::get().output(&format!(
+            "  {}{}@{} requires {} ({})",
+            "  ".repeat(dependent.depth - 1),
+            dependent.name.bright_cyan(),
+            dependent.version,
+            node_label(&dependent.requires),
+            range.yellow()
+        ));
+    }
+}
+
+/// Struct implementation for the `why` command.
+pub struct Why;
+
+#[async_trait]
+impl Command for Why {
+    /// Display a help menu for the `volt why` command.
+    fn help() -> Help {
+        Help::new(
+            "why",
+            "Show why a package is installed, from the dependency graph of the lock file.",
+        )
+        .usage("[package] [flags]")
+        .arg("[package]", "The installed package.")
+        .flag(
+            &["--reverse", "-r"],
+            "List everything which depends on the package, directly or transitively.",
+        )
+        .flag(&["--json"], "Print the result as JSON.")
+        .verbose()
+        .example("volt why react-is", "")
+    }
+
+    /// Execute the `volt why` command
+    ///
+    /// Lists the chains of dependencies leading to a package, or its dependents with `--reverse`.
+    /// ## Arguments
+    /// * `app` - Instance of the command (`Arc<App>`)
+    /// ## Examples
+    /// ```ignore
+    /// // .exec() is an async call so you need to await it
+    /// Why.exec(app).await;
+    /// ```
+    /// ## Returns
+    /// * `Result<()>`
+    async fn exec(app: Arc<App>) -> Result<()> {
+        let name = match app.args.get(1) {
+            Some(name) => volt_utils::parse_package_spec(name).0,
+            None => {
+                reporter::get().output(&Self::help().to_string());
+                exit(1);
+            }
+        };
+
+        let lock_file = LockFile::load(app.lock_file_path.clone()).map_err(|_| {
+            anyhow!(
+                "failed to read {}, run volt install first",
+                app.lock_file_path.display()
+            )
+        })?;
+
+        let graph = LockGraph::new(&lock_file);
+
+        let versions: Vec<String> = graph
+            .find(&name)
+            .iter()
+            .map(|node| graph.node(*node).version.clone())
+            .collect();
+
+        if versions.is_empty() {
+            return Err(anyhow!("{} is not in the lock file", name));
+        }
+
+        let package_json =
+            PackageJson::from(&app.current_dir.join("package.json").to_string_lossy());
+        let roots = roots(&package_json);
+        let json = reporter::json();
+
+        if app.has_flag(&["--reverse", "-r"]) {
+            let reverse = Reverse {
+                direct: roots
+                    .iter()
+                    .filter(|root| root.name == name)
+                    .cloned()
+                    .collect(),
+                dependents: graph.dependents_of(&name),
+                name,
+                versions,
+            };
+
+            if json {
+                reporter::get().document(&serde_json::to_string_pretty(&reverse)?);
+            } else {
+                print_reverse(&reverse);
+            }
+
+            return Ok(());
+        }
+
+        let root_nodes: Vec<usize> = roots
+            .iter()
+            .filter_map(|root| graph.resolve(&root.name, &root.range))
+            .collect();
+
+        let why = Chains {
+            paths: graph
+                .paths_to(&root_nodes, &name, PATH_LIMIT)
+                .into_iter()
+                .map(|path| path.iter().map(|node| graph.node(*node).clone()).collect())
+                .collect(),
+            name,
+            versions,
+        };
+
+        if json {
+            reporter::get().document(&serde_json::to_string_pretty(&why)?);
+        } else {
+            print_paths(&why, &roots);
+        }
+
+        Ok(())
+    }
+}